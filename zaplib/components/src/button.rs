@@ -113,6 +113,11 @@ impl Button {
         })
     }
 
+    /// The [`Area`] of the button's background, e.g. for attaching a [`crate::Tooltip`].
+    pub fn area(&self) -> Area {
+        self.bg_area
+    }
+
     pub fn draw(&mut self, cx: &mut Cx, label: &str) {
         cx.begin_shader_group(&[&SHADER, &TEXT_INS_SHADER]);
 
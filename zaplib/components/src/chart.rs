@@ -1,9 +1,11 @@
 use std::{
     collections::HashMap,
-    f32::{EPSILON, INFINITY, NEG_INFINITY},
+    f32::{consts::PI, EPSILON, INFINITY, NEG_INFINITY},
     sync::{Arc, RwLock},
 };
 
+use crate::chartshapes::*;
+use crate::charttime::*;
 use crate::*;
 use zaplib::*;
 
@@ -72,7 +74,10 @@ impl ChartTooltip {
             let text_props = TextInsProps { text_style: TEXT_STYLE_MONO, color: text_color, ..TextInsProps::DEFAULT };
 
             let path = {
-                if (self.axis as usize) < config.labels.len() {
+                let x_scale = config.scales.get("x");
+                if let Some(x_scale) = x_scale.filter(|scale| scale.scale_type == ChartScaleType::Time) {
+                    format_time(x_scale.time_origin + self.axis as f64)
+                } else if (self.axis as usize) < config.labels.len() {
                     config.labels[self.axis as usize].to_string()
                 } else {
                     format!("{}", self.axis)
@@ -85,9 +90,30 @@ impl ChartTooltip {
     }
 }
 
-/// TODO(hernan): Implement other chart types like bar, pie, etc...
+/// The kind of chart to render, see [`ChartConfig::chart_type`]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ChartType {
+    /// Points connected by lines.
     Line,
+    /// Vertical bars, side by side for each x value, or on top of each other if
+    /// [`ChartConfig::stacked`] is set.
+    Bar,
+    /// Points without lines.
+    Scatter,
+    /// Lines with the area between them and zero filled in with [`ChartDataset::background_color`].
+    /// Datasets are drawn on top of each other if [`ChartConfig::stacked`] is set.
+    Area,
+    /// A circle with a slice for each value. Multiple datasets are drawn as concentric rings,
+    /// with the first one on the outside.
+    Pie,
+    /// Same as [`ChartType::Pie`], but with a hole in the center.
+    Doughnut,
+}
+
+impl Default for ChartType {
+    fn default() -> Self {
+        ChartType::Line
+    }
 }
 
 /// Contains the data is going to be used to render the chart
@@ -99,6 +125,8 @@ pub enum ChartData<'a> {
     Empty,
     Values(&'a [f32]),
     Pairs(&'a [Vec2]),
+    /// Large datasets that are decimated before drawing, see [`ChartStream`].
+    Stream(&'a ChartStream),
 }
 
 impl<'a> ChartData<'a> {
@@ -110,10 +138,15 @@ impl<'a> ChartData<'a> {
         ChartData::Pairs(data)
     }
 
+    pub fn from_stream(data: &'a ChartStream) -> ChartData<'a> {
+        ChartData::Stream(data)
+    }
+
     pub fn len(&self) -> usize {
         match self {
             ChartData::Values(data) => data.len(),
             ChartData::Pairs(data) => data.len(),
+            ChartData::Stream(data) => data.len(),
             ChartData::Empty => 0,
         }
     }
@@ -122,6 +155,7 @@ impl<'a> ChartData<'a> {
         match self {
             ChartData::Values(data) => data.is_empty(),
             ChartData::Pairs(data) => data.is_empty(),
+            ChartData::Stream(data) => data.is_empty(),
             ChartData::Empty => true,
         }
     }
@@ -148,6 +182,10 @@ impl<'a> ChartData<'a> {
                 }
                 (min, max)
             }
+            ChartData::Stream(data) => match data.min_max() {
+                Some((min, max)) => (lo.min(&min), hi.max(&max)),
+                None => (lo, hi),
+            },
             ChartData::Empty => (lo, hi),
         }
     }
@@ -156,6 +194,7 @@ impl<'a> ChartData<'a> {
         match self {
             ChartData::Values(data) => vec2(i as f32, data[i]),
             ChartData::Pairs(data) => data[i],
+            ChartData::Stream(data) => data.points()[i],
             ChartData::Empty => vec2(INFINITY, INFINITY),
         }
     }
@@ -165,6 +204,7 @@ impl<'a> ChartData<'a> {
         match self {
             ChartData::Values(data) => data.iter().enumerate().map(|(x, y)| vec2(x as f32, *y)).collect(),
             ChartData::Pairs(data) => data.to_vec(),
+            ChartData::Stream(data) => data.points().to_vec(),
             ChartData::Empty => vec![],
         }
     }
@@ -180,6 +220,26 @@ pub struct ChartDataset<'a> {
     pub border_color: Vec4,
     pub border_width: f32,
     pub show_line: bool,
+    /// Fill color for bars, areas and slices
+    pub background_color: Vec4,
+    /// Fill colors for each value, e.g. to give each slice of a pie chart its own color. Falls back to
+    /// [`ChartDataset::background_color`] when empty, and repeats when shorter than the data.
+    pub background_colors: Vec<Vec4>,
+    /// The key in [`ChartConfig::scales`] of the y axis that this dataset is drawn against.
+    pub y_axis_id: String,
+    /// Leave the dataset out, as if it had no data. Datasets can also be hidden by plugins, like
+    /// [`ChartLegend`].
+    pub hidden: bool,
+}
+
+impl<'a> ChartDataset<'a> {
+    fn background_color_at(&self, datum_index: usize) -> Vec4 {
+        if self.background_colors.is_empty() {
+            self.background_color
+        } else {
+            self.background_colors[datum_index % self.background_colors.len()]
+        }
+    }
 }
 
 impl<'a> Default for ChartDataset<'a> {
@@ -193,13 +253,70 @@ impl<'a> Default for ChartDataset<'a> {
             border_color: COLOR_WHITE,
             border_width: 2.,
             show_line: true,
+            background_color: vec4(1., 1., 1., 0.5),
+            background_colors: vec![],
+            y_axis_id: "y".to_string(),
+            hidden: false,
         }
     }
 }
 
+/// How values are mapped onto an axis, see [`ChartScale::scale_type`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartScaleType {
+    Linear,
+    /// Values are spaced by their order of magnitude. Zero and negative values are not shown.
+    Logarithmic,
+    /// Values are milliseconds since [`ChartScale::time_origin`], and ticks are placed on calendar
+    /// units, like every 15 minutes, or at the start of every month.
+    Time,
+}
+
+impl Default for ChartScaleType {
+    fn default() -> Self {
+        ChartScaleType::Linear
+    }
+}
+
+/// Which side of the chart a y axis is drawn on, see [`ChartScale::position`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartAxisPosition {
+    Left,
+    Right,
+}
+
+impl Default for ChartAxisPosition {
+    fn default() -> Self {
+        ChartAxisPosition::Left
+    }
+}
+
+/// Options for an axis, see [`ChartConfig::scales`]
+#[derive(Debug, Clone)]
 pub struct ChartScale {
+    /// The axis always includes this value, even if the data doesn't go this low.
     pub min: f32,
+    /// The axis always includes this value, even if the data doesn't go this high.
     pub max: f32,
+    pub scale_type: ChartScaleType,
+    /// Only used for y axes; the x axis is always at the bottom.
+    pub position: ChartAxisPosition,
+    /// For [`ChartScaleType::Time`], the moment that values are relative to, in milliseconds since the
+    /// Unix epoch. Values are `f32`s, which can't represent the current time with millisecond precision,
+    /// so set this to somewhere close to the data. Dates and times are shown in UTC.
+    pub time_origin: f64,
+}
+
+impl Default for ChartScale {
+    fn default() -> Self {
+        Self {
+            min: INFINITY,
+            max: NEG_INFINITY,
+            scale_type: ChartScaleType::Linear,
+            position: ChartAxisPosition::Left,
+            time_origin: 0.,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -234,13 +351,30 @@ pub trait ChartTooltipRenderer {
 /// An extension machanism for charts
 ///
 /// Implement this trait to render custom elements on top of the chart, but
-/// behind tooltips, if any.
+/// behind tooltips, if any. See [`ChartLegend`], [`ChartAnnotations`] and
+/// [`ChartCrosshair`] for the plugins that are provided.
 ///
 /// Note that any new element added by this function will not be considered for
 /// selection and will not affect selection of existing elements drawn by the
 /// chart.
 pub trait ChartPlugin {
-    fn draw(&mut self, cx: &mut Cx, config: &ChartConfig, chart_bounds: &Rect);
+    /// Called for the events of the chart before they're returned from [`Chart::handle`].
+    ///
+    /// Return `true` to stop the chart from handling the event, e.g. when the pointer
+    /// is on something drawn by the plugin. [`Chart::handle`] then returns [`ChartEvent::None`],
+    /// but the other plugins still get the event.
+    fn handle(&mut self, _cx: &mut Cx, _event: &ChartEvent, _chart: &Chart) -> bool {
+        false
+    }
+
+    /// Use `chart` to get the chart boundaries or to convert between data and screen
+    /// coordinates, e.g. using [`Chart::normalize_data_point`].
+    fn draw(&mut self, cx: &mut Cx, config: &ChartConfig, chart: &Chart);
+
+    /// Hide datasets, see [`ChartDataset::hidden`].
+    fn is_dataset_hidden(&self, _dataset_index: usize) -> bool {
+        false
+    }
 }
 
 /// Tooltip configuration
@@ -268,11 +402,17 @@ pub struct ChartConfig<'a> {
     /// it has to contain the same amount of elements as the dataset with the most values.
     pub labels: Vec<String>,
     pub datasets: Vec<ChartDataset<'a>>,
+    /// Options for the axes: "x" for the x axis, and any other key for a y axis that datasets can refer
+    /// to using [`ChartDataset::y_axis_id`]. The "y" axis, or otherwise the first one by key, is the one
+    /// that grid lines are drawn for.
     pub scales: HashMap<String, ChartScale>,
     pub style: ChartStyle,
     pub tooltip: ChartTooltipConfig,
     pub zoom_enabled: bool,
     pub pan_enabled: bool,
+    /// Draw datasets on top of each other instead of next to each other, for [`ChartType::Bar`]
+    /// and [`ChartType::Area`]. Positive and negative values are stacked separately.
+    pub stacked: bool,
 }
 
 impl<'a> Default for ChartConfig<'a> {
@@ -286,6 +426,7 @@ impl<'a> Default for ChartConfig<'a> {
             tooltip: ChartTooltipConfig::default(),
             pan_enabled: false,
             zoom_enabled: false,
+            stacked: false,
         }
     }
 }
@@ -302,6 +443,12 @@ pub struct ChartCurrentElement {
 pub enum ChartEvent {
     None,
     PointerOut,
+    PointerDown {
+        /// Current mouse position relative to the chart boundaries.
+        cursor: Vec2,
+        /// The value at the current mouse position
+        cursor_value: Vec2,
+    },
     PointerHover {
         /// Current mouse position relative to the chart boundaries.
         /// Useful for positioning tooltips.
@@ -316,6 +463,175 @@ pub enum ChartEvent {
     },
 }
 
+/// A bar of a [`ChartType::Bar`] chart, in screen coordinates
+#[derive(Debug, Clone)]
+struct ChartBar {
+    /// The bar, clipped to the chart boundaries.
+    rect: Rect,
+    /// Where the tooltip points to: the middle of the edge at the bar's value.
+    anchor: Vec2,
+    dataset_index: usize,
+    datum_index: usize,
+    data_point: Vec2,
+}
+
+/// A slice of a [`ChartType::Pie`] or [`ChartType::Doughnut`] chart, in screen coordinates
+#[derive(Debug, Clone)]
+struct ChartSlice {
+    center: Vec2,
+    /// Inner and outer radius.
+    radii: Vec2,
+    /// Start and end angle in radians, clockwise from the top.
+    angles: Vec2,
+    dataset_index: usize,
+    datum_index: usize,
+    data_point: Vec2,
+}
+
+impl ChartSlice {
+    /// The middle of the slice, where the tooltip points to.
+    fn anchor(&self) -> Vec2 {
+        let angle = 0.5 * (self.angles.x + self.angles.y);
+        let radius = 0.5 * (self.radii.x + self.radii.y);
+        self.center + vec2(angle.sin(), -angle.cos()) * radius
+    }
+
+    fn contains(&self, pos: Vec2) -> bool {
+        let d = pos - self.center;
+        let radius = d.length();
+        let angle = Chart::angle_of(d);
+        self.radii.x <= radius && radius <= self.radii.y && self.angles.x <= angle && angle <= self.angles.y
+    }
+}
+
+/// An axis with its range computed from the data
+#[derive(Debug, Clone, Default)]
+struct ChartAxis {
+    id: String,
+    scale_type: ChartScaleType,
+    position: ChartAxisPosition,
+    time_origin: f64,
+    /// Range of the axis, in scaled units, see [`ChartAxis::scale`].
+    min: f32,
+    max: f32,
+}
+
+/// Used when no axes have been computed yet.
+static DEFAULT_AXIS: ChartAxis = ChartAxis {
+    id: String::new(),
+    scale_type: ChartScaleType::Linear,
+    position: ChartAxisPosition::Left,
+    time_origin: 0.,
+    min: 0.,
+    max: 1.,
+};
+
+impl ChartAxis {
+    fn new(id: &str, scale: Option<&ChartScale>) -> Self {
+        let scale = scale.cloned().unwrap_or_default();
+        Self {
+            id: id.to_string(),
+            scale_type: scale.scale_type,
+            position: scale.position,
+            time_origin: scale.time_origin,
+            min: scale.min,
+            max: scale.max,
+        }
+    }
+
+    /// Extends the range to include `value`, before calling [`ChartAxis::fit`].
+    fn include(&mut self, value: f32) {
+        if self.scale_type != ChartScaleType::Logarithmic || value > 0. {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+    }
+
+    /// Converts the range to scaled units, and rounds it outwards: to powers of 10 for logarithmic axes,
+    /// and for linear axes if `round` is set, in which case it also includes zero.
+    fn fit(&mut self, round: bool) {
+        match self.scale_type {
+            ChartScaleType::Logarithmic => {
+                if !(self.min > 0. && self.min <= self.max && self.max.is_finite()) {
+                    // No positive values to show, so fall back to a single power of 10.
+                    self.min = 1.;
+                    self.max = 10.;
+                }
+                self.min = self.min.log10().floor();
+                self.max = self.max.log10().ceil().max(self.min + 1.);
+            }
+            ChartScaleType::Linear if round => {
+                // Force either bound to be zero (but not both)
+                if self.max < 0. {
+                    self.max = 0.;
+                } else if self.min > 0. {
+                    self.min = 0.;
+                }
+                self.min = Chart::round_down(vec2(0., self.min)).y;
+                self.max = Chart::round_up(vec2(0., self.max)).y;
+            }
+            _ => {}
+        }
+    }
+
+    /// Maps a value to the units that are linear on screen: the exponent for logarithmic axes.
+    fn scale(&self, value: f32) -> f32 {
+        match self.scale_type {
+            ChartScaleType::Logarithmic => value.log10(),
+            _ => value,
+        }
+    }
+
+    fn unscale(&self, value: f32) -> f32 {
+        match self.scale_type {
+            ChartScaleType::Logarithmic => 10_f32.powf(value),
+            _ => value,
+        }
+    }
+
+    /// Ticks for the part of the axis between `min` and `max` (in scaled units), which is `length` pixels
+    /// long on screen, as scaled values with their labels.
+    fn ticks(&self, min: f32, max: f32, length: f32) -> Vec<(f32, String)> {
+        let (min, max) = (min.min(max), min.max(max));
+        let min_spacing = if self.scale_type == ChartScaleType::Time { 80. } else { 50. };
+        let min_step = min_spacing / length * (max - min);
+        match self.scale_type {
+            ChartScaleType::Linear => {
+                let step = Chart::nice_step(min_step);
+                let decimals = (-step.log10().floor()).max(0.) as usize;
+                Chart::steps(min, max, step).into_iter().map(|value| (value, format!("{:.*}", decimals, value))).collect()
+            }
+            ChartScaleType::Logarithmic => Chart::steps(min, max, Chart::nice_step(min_step.max(1.)))
+                .into_iter()
+                .map(|exponent| {
+                    let label = if (0. ..=6.).contains(&exponent) {
+                        format!("{}", 10_i64.pow(exponent as u32))
+                    } else if (-3. ..0.).contains(&exponent) {
+                        format!("{:.*}", -exponent as usize, 10_f64.powi(exponent as i32))
+                    } else {
+                        format!("1e{}", exponent)
+                    };
+                    (exponent, label)
+                })
+                .collect(),
+            ChartScaleType::Time => {
+                let (unit, ticks) = time_ticks(self.time_origin + min as f64, self.time_origin + max as f64, min_step as f64);
+                ticks.into_iter().map(|tick| ((tick - self.time_origin) as f32, format_time_tick(tick, unit))).collect()
+            }
+        }
+    }
+}
+
+/// Width of the labels of a y axis, for fitting multiple axes on the same side.
+const AXIS_LABELS_WIDTH: f32 = 50.;
+
+/// Percentage of the space between two x values that is used by bars, as in ChartJS.
+const CATEGORY_PERCENTAGE: f32 = 0.8;
+/// Percentage of the space available to each bar that is actually filled, as in ChartJS.
+const BAR_PERCENTAGE: f32 = 0.9;
+/// Size of the hole of a [`ChartType::Doughnut`] chart relative to its radius.
+const DOUGHNUT_CUTOUT: f32 = 0.5;
+
 #[derive(Default)]
 pub struct Chart {
     bounds: Rect,
@@ -325,10 +641,17 @@ pub struct Chart {
     texture_area: Area,
     pass: Pass,
     color_texture: Texture,
-    min: Vec2,
-    max: Vec2,
+    x_axis: ChartAxis,
+    /// The first one is the primary axis, which grid lines are drawn for.
+    y_axes: Vec<ChartAxis>,
     background: Background,
     areas: Vec<Area>,
+    /// For every dataset, the datum index of every point in [`Chart::areas`], since decimated streams and points
+    /// outside of the chart boundaries don't get drawn.
+    point_datum_indices: Vec<Vec<usize>>,
+    chart_type: ChartType,
+    bars: Vec<ChartBar>,
+    slices: Vec<ChartSlice>,
     tooltip: ChartTooltip,
     tooltip_visible: bool,
     // Keep an Arc<RwLock> to plugins since most of them are maybe owned
@@ -353,10 +676,17 @@ impl Chart {
     pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> ChartEvent {
         match event.hits_pointer(cx, self.component_id, self.texture_area.get_rect_for_first_instance(cx)) {
             Event::PointerDown(pd) => {
+                let chart_event = ChartEvent::PointerDown { cursor: pd.rel, cursor_value: self.denormalize_data_point(pd.rel) };
+                if self.handle_plugins(cx, &chart_event) {
+                    return ChartEvent::None;
+                }
+
                 if self.pan_enabled {
                     self.last_pointer_pos = pd.rel;
                     self.panning = true;
                 }
+
+                return chart_event;
             }
             Event::PointerMove(pm) => {
                 if self.pan_enabled {
@@ -405,23 +735,29 @@ impl Chart {
             Event::PointerHover(pe) => {
                 if self.panning || pe.hover_state == HoverState::Out {
                     self.tooltip_visible = false;
+                    self.handle_plugins(cx, &ChartEvent::PointerOut);
                     return ChartEvent::PointerOut;
                 }
 
                 let mouse_pos_rel = pe.rel;
                 let cursor = mouse_pos_rel.clamp(&self.bounds.pos, &(self.bounds.pos + self.bounds.size));
                 let cursor_value = self.denormalize_data_point(cursor);
-                let current_element = self.get_element_at(cx, cursor_value);
-                if let Some(current_element) = &current_element {
+                let current_element = self.get_element_at(cx, cursor, cursor_value);
+                cx.request_draw();
+
+                let chart_event = ChartEvent::PointerHover { cursor, cursor_value, current_element };
+                if self.handle_plugins(cx, &chart_event) {
+                    self.tooltip_visible = false;
+                    return ChartEvent::None;
+                }
+
+                if let ChartEvent::PointerHover { current_element: Some(current_element), .. } = &chart_event {
                     self.tooltip.update(current_element);
                     self.tooltip_visible = true;
                 } else {
                     self.tooltip_visible = false;
                 }
-
-                cx.request_draw();
-
-                return ChartEvent::PointerHover { cursor, cursor_value, current_element };
+                return chart_event;
             }
             _ => (),
         }
@@ -429,6 +765,15 @@ impl Chart {
         ChartEvent::None
     }
 
+    /// Passes an event to all plugins, and returns whether any of them handled it.
+    fn handle_plugins(&self, cx: &mut Cx, event: &ChartEvent) -> bool {
+        let mut handled = false;
+        for plugin in &self.plugins {
+            handled |= plugin.write().unwrap().handle(cx, event, self);
+        }
+        handled
+    }
+
     /// Gets the nearest element for a given value
     /// `stride` is used to jump between elements, in case they are represented by
     /// more than one value.
@@ -468,22 +813,61 @@ impl Chart {
         Some(partition_index)
     }
 
-    fn get_element_at(&self, cx: &mut Cx, cursor: Vec2) -> Option<ChartCurrentElement> {
+    /// Gets the element to show a tooltip for, given the cursor position in screen
+    /// coordinates and its value in data coordinates.
+    fn get_element_at(&self, cx: &mut Cx, cursor: Vec2, cursor_value: Vec2) -> Option<ChartCurrentElement> {
+        match self.chart_type {
+            ChartType::Line | ChartType::Area => self.get_nearest_point_by_x(cx, cursor, cursor_value),
+            ChartType::Scatter => self.get_nearest_point(cx, cursor),
+            ChartType::Bar => self.get_bar_at(cursor),
+            ChartType::Pie | ChartType::Doughnut => self.get_slice_at(cursor),
+        }
+    }
+
+    /// For each dataset, finds the point that is closest to the cursor horizontally, and
+    /// then returns the one that is closest vertically.
+    fn get_nearest_point_by_x(&self, cx: &mut Cx, cursor: Vec2, cursor_value: Vec2) -> Option<ChartCurrentElement> {
         let mut ret: Option<ChartCurrentElement> = None;
         let mut min_distance = INFINITY;
 
         for dataset_index in 0..self.areas.len() {
             let area = &self.areas[dataset_index];
             let points = area.get_slice::<DrawPoints3dInstance>(cx);
-            if let Some(datum_index) = Self::get_nearest_element_index(points, cursor.x) {
-                let data_point = points[datum_index].user_info;
-                let distance = (data_point.y - cursor.y).abs();
+            if let Some(index) = Self::get_nearest_element_index(points, cursor_value.x) {
+                // Compare screen positions, since stacked points are not drawn at their value.
+                let position = vec2(points[index].position.x, points[index].position.y);
+                let distance = (position.y - cursor.y).abs();
                 if distance < min_distance {
                     ret = Some(ChartCurrentElement {
                         dataset_index,
-                        datum_index,
-                        data_point,
-                        normalized_data_point: self.normalize_data_point(data_point),
+                        datum_index: self.point_datum_indices[dataset_index][index],
+                        data_point: points[index].user_info,
+                        normalized_data_point: position,
+                    });
+                    min_distance = distance;
+                }
+            }
+        }
+
+        ret
+    }
+
+    /// Finds the point that is closest to the cursor. Points don't have to be sorted.
+    fn get_nearest_point(&self, cx: &mut Cx, cursor: Vec2) -> Option<ChartCurrentElement> {
+        let mut ret: Option<ChartCurrentElement> = None;
+        let mut min_distance = INFINITY;
+
+        for dataset_index in 0..self.areas.len() {
+            let points = self.areas[dataset_index].get_slice::<DrawPoints3dInstance>(cx);
+            for (index, point) in points.iter().enumerate() {
+                let position = vec2(point.position.x, point.position.y);
+                let distance = position.distance(&cursor);
+                if distance < min_distance {
+                    ret = Some(ChartCurrentElement {
+                        dataset_index,
+                        datum_index: self.point_datum_indices[dataset_index][index],
+                        data_point: point.user_info,
+                        normalized_data_point: position,
                     });
                     min_distance = distance;
                 }
@@ -493,6 +877,49 @@ impl Chart {
         ret
     }
 
+    /// Finds the bar that is closest to the cursor horizontally, preferring the one whose
+    /// value is closest to the cursor when several of them are, like for stacked bars.
+    fn get_bar_at(&self, cursor: Vec2) -> Option<ChartCurrentElement> {
+        let mut ret: Option<&ChartBar> = None;
+        let mut min_distance = vec2(INFINITY, INFINITY);
+
+        for bar in &self.bars {
+            let left = bar.rect.pos.x;
+            let right = left + bar.rect.size.x;
+            let distance = vec2((left - cursor.x).max(cursor.x - right).max(0.), (bar.anchor.y - cursor.y).abs());
+            if distance.x < min_distance.x || (distance.x == min_distance.x && distance.y < min_distance.y) {
+                ret = Some(bar);
+                min_distance = distance;
+            }
+        }
+
+        ret.map(|bar| ChartCurrentElement {
+            dataset_index: bar.dataset_index,
+            datum_index: bar.datum_index,
+            data_point: bar.data_point,
+            normalized_data_point: bar.anchor,
+        })
+    }
+
+    fn get_slice_at(&self, cursor: Vec2) -> Option<ChartCurrentElement> {
+        self.slices.iter().find(|slice| slice.contains(cursor)).map(|slice| ChartCurrentElement {
+            dataset_index: slice.dataset_index,
+            datum_index: slice.datum_index,
+            data_point: slice.data_point,
+            normalized_data_point: slice.anchor(),
+        })
+    }
+
+    /// Angle of a vector in radians, clockwise from the top (negative y), in `[0, 2 * PI)`.
+    fn angle_of(v: Vec2) -> f32 {
+        let angle = v.x.atan2(-v.y);
+        if angle < 0. {
+            angle + 2. * PI
+        } else {
+            angle
+        }
+    }
+
     fn remap(value: f32, lo0: f32, hi0: f32, lo1: f32, hi1: f32) -> f32 {
         lo1 + (value - lo0) / (hi0 - lo0) * (hi1 - lo1)
     }
@@ -506,7 +933,11 @@ impl Chart {
         // Minimum size of a cell, in pixels.
         let min_cell_size = 50.;
 
-        let data_min = self.min;
+        let x_axis = &self.x_axis;
+        let y_axis = self.y_axis(0);
+
+        // When both axes are linear, the step size of the grid is the distance between consecutive x values.
+        let data_min = vec2(x_axis.unscale(x_axis.min), y_axis.unscale(y_axis.min));
         let data_max = self.denormalize_data_point(vec2(max_x, max_y));
 
         let first = self.normalize_data_point(data_min);
@@ -514,10 +945,18 @@ impl Chart {
 
         let max_lines = (data_max.x - data_min.x).abs();
         let step_size = (last.x - first.x).abs() / max_lines;
+        let linear_grid = x_axis.scale_type == ChartScaleType::Linear && y_axis.scale_type == ChartScaleType::Linear;
+
+        // Visible ranges in scaled units.
+        let visible_min = self.screen_to_scaled(vec2(min_x, max_y), y_axis);
+        let visible_max = self.screen_to_scaled(vec2(max_x, min_y), y_axis);
+
+        let label_props =
+            |position_anchoring| TextInsProps { position_anchoring, color: config.style.label_color, ..TextInsProps::DEFAULT };
 
         let mut lines = vec![];
 
-        let mut draw_vertical_line = |x, round_op: &dyn Fn(f32) -> f32| {
+        let mut draw_vertical_line = |cx: &mut Cx, x, label: &str| {
             lines.push(DrawLines3dInstance::from_segment(
                 vec3(x, min_y, 0.),
                 vec3(x, max_y + 10., 0.),
@@ -525,106 +964,167 @@ impl Chart {
                 1.,
             ));
 
+            if !label.is_empty() {
+                TextIns::draw_str(cx, label, Vec2 { x, y: max_y + 10. }, &label_props(TEXT_ANCHOR_CENTER_H));
+            }
+        };
+
+        if x_axis.scale_type == ChartScaleType::Linear {
             // TODO(hernan): Render text labels if provided in config
-            let label = {
+            let label = |x, round_op: &dyn Fn(f32) -> f32| {
                 let col_value = round_op(self.denormalize_data_point(vec2(x, min_y)).x);
                 format!("{:.0}", col_value)
             };
 
-            TextIns::draw_str(
-                cx,
-                &label,
-                Vec2 { x, y: max_y + 10. },
-                &TextInsProps {
-                    position_anchoring: TEXT_ANCHOR_CENTER_H,
-                    color: config.style.label_color,
-                    ..TextInsProps::DEFAULT
-                },
-            );
-        };
+            // Lines are rendered in reversed order first
+            // This prevents jumping when panning and zooming
+            let mut x = first.x;
+            let mut last_x = x + min_cell_size;
+            while x > min_x {
+                // Skip some lines in order to ensure there is enough
+                // space between them
+                if x < max_x && (last_x - x >= min_cell_size) {
+                    draw_vertical_line(cx, x, &label(x, &|x| x));
+                    last_x = x;
+                }
+                x -= step_size;
+            }
 
-        // Lines are rendered in reversed order first
-        // This prevents jumping when panning and zooming
-        let mut x = first.x;
-        let mut last_x = x + min_cell_size;
-        while x > min_x {
-            // Skip some lines in order to ensure there is enough
-            // space between them
-            if x < max_x && (last_x - x >= min_cell_size) {
-                draw_vertical_line(x, &|x| x);
-                last_x = x;
+            let mut x = first.x;
+            let mut last_x = x - min_cell_size;
+            while x < max_x {
+                if min_x < x && (x - last_x >= min_cell_size) {
+                    draw_vertical_line(cx, x, &label(x, &|x| x));
+                    last_x = x;
+                }
+                x += step_size;
             }
-            x -= step_size;
-        }
 
-        let mut x = first.x;
-        let mut last_x = x - min_cell_size;
-        while x < max_x {
-            if min_x < x && (x - last_x >= min_cell_size) {
-                draw_vertical_line(x, &|x| x);
-                last_x = x;
+            draw_vertical_line(cx, min_x, &label(min_x, &|x| x.floor()));
+            draw_vertical_line(cx, max_x, &label(max_x, &|x| x.ceil()));
+        } else {
+            for (value, label) in x_axis.ticks(visible_min.x, visible_max.x, self.bounds.size.x) {
+                let x = self.scaled_to_screen(vec2(value, 0.), y_axis).x;
+                if min_x < x && x < max_x {
+                    draw_vertical_line(cx, x, &label);
+                }
             }
-            x += step_size;
+            draw_vertical_line(cx, min_x, "");
+            draw_vertical_line(cx, max_x, "");
         }
 
-        draw_vertical_line(min_x, &|x| x.floor());
-        draw_vertical_line(max_x, &|x| x.ceil());
-
-        let mut draw_horizontal_line = |y, round_op: &dyn Fn(f32) -> f32| {
+        let (label_x, label_anchor) = self.axis_labels_position(0);
+        let tick_x = if y_axis.position == ChartAxisPosition::Left { min_x - 10. } else { max_x + 10. };
+        let mut draw_horizontal_line = |cx: &mut Cx, y, label: &str| {
             lines.push(DrawLines3dInstance::from_segment(
-                vec3(min_x - 10., y, 0.),
-                vec3(max_x, y, 0.),
+                vec3(tick_x.min(min_x), y, 0.),
+                vec3(tick_x.max(max_x), y, 0.),
                 config.style.grid_color,
                 1.,
             ));
 
-            // Flip min_y/max_y since y coordinate is inverted
-            let row_value = round_op(self.denormalize_data_point(vec2(min_x, y)).y);
-
-            TextIns::draw_str(
-                cx,
-                &format!("{:.0}", row_value),
-                Vec2 { x: min_x - 15., y },
-                &TextInsProps {
-                    position_anchoring: TEXT_ANCHOR_RIGHT + TEXT_ANCHOR_CENTER_V,
-                    color: config.style.label_color,
-                    ..TextInsProps::DEFAULT
-                },
-            );
+            if !label.is_empty() {
+                TextIns::draw_str(cx, label, Vec2 { x: label_x, y }, &label_props(label_anchor));
+            }
         };
 
-        // See comments above for rendering vertical lines
-        let mut y = first.y;
-        let mut last_y = y + min_cell_size;
-        while y > min_y {
-            if y < max_y && (last_y - y >= min_cell_size) {
-                draw_horizontal_line(y, &|y| y);
-                last_y = y;
+        if linear_grid {
+            // Flip min_y/max_y since y coordinate is inverted
+            let label = |y, round_op: &dyn Fn(f32) -> f32| {
+                let row_value = round_op(self.denormalize_data_point(vec2(min_x, y)).y);
+                format!("{:.0}", row_value)
+            };
+
+            // See comments above for rendering vertical lines
+            let mut y = first.y;
+            let mut last_y = y + min_cell_size;
+            while y > min_y {
+                if y < max_y && (last_y - y >= min_cell_size) {
+                    draw_horizontal_line(cx, y, &label(y, &|y| y));
+                    last_y = y;
+                }
+                y -= step_size;
             }
-            y -= step_size;
-        }
 
-        let mut y = first.y;
-        let mut last_y = y - min_cell_size;
-        while y < max_y {
-            if min_y < y && (y - last_y >= min_cell_size) {
-                draw_horizontal_line(y, &|y| y);
-                last_y = y;
+            let mut y = first.y;
+            let mut last_y = y - min_cell_size;
+            while y < max_y {
+                if min_y < y && (y - last_y >= min_cell_size) {
+                    draw_horizontal_line(cx, y, &label(y, &|y| y));
+                    last_y = y;
+                }
+                y += step_size;
             }
-            y += step_size;
+
+            draw_horizontal_line(cx, min_y, &label(min_y, &|y| y.floor()));
+            draw_horizontal_line(cx, max_y, &label(max_y, &|y| y.ceil()));
+        } else {
+            for (value, label) in y_axis.ticks(visible_min.y, visible_max.y, self.bounds.size.y) {
+                let y = self.scaled_to_screen(vec2(0., value), y_axis).y;
+                if min_y < y && y < max_y {
+                    draw_horizontal_line(cx, y, &label);
+                }
+            }
+            draw_horizontal_line(cx, min_y, "");
+            draw_horizontal_line(cx, max_y, "");
         }
 
-        draw_horizontal_line(min_y, &|y| y.floor());
-        draw_horizontal_line(max_y, &|y| y.ceil());
+        // Other y axes only get labels, since their grid lines would not line up with the primary axis.
+        for (index, axis) in self.y_axes.iter().enumerate().skip(1) {
+            let (label_x, label_anchor) = self.axis_labels_position(index);
+            let visible_min = self.screen_to_scaled(vec2(min_x, max_y), axis).y;
+            let visible_max = self.screen_to_scaled(vec2(min_x, min_y), axis).y;
+            for (value, label) in axis.ticks(visible_min, visible_max, self.bounds.size.y) {
+                let y = self.scaled_to_screen(vec2(0., value), axis).y;
+                if min_y <= y && y <= max_y {
+                    TextIns::draw_str(cx, &label, vec2(label_x, y), &label_props(label_anchor));
+                }
+            }
+        }
 
         // Draw axes a bit brighter than columns/rows
         let axis_color = vec4(0.5, 0.5, 0.5, 1.);
         lines.push(DrawLines3dInstance::from_segment(vec3(min_x, max_y, 0.), vec3(max_x, max_y, 0.), axis_color, 1.));
         lines.push(DrawLines3dInstance::from_segment(vec3(min_x, min_y, 0.), vec3(min_x, max_y, 0.), axis_color, 1.));
+        if self.y_axes.iter().any(|axis| axis.position == ChartAxisPosition::Right) {
+            lines.push(DrawLines3dInstance::from_segment(vec3(max_x, min_y, 0.), vec3(max_x, max_y, 0.), axis_color, 1.));
+        }
 
         DrawLines3d::draw(cx, &lines, Default::default());
     }
 
+    /// Where to draw the labels of the y axis with the given index, and how to anchor them. Axes on
+    /// the same side are drawn next to each other, with the first one closest to the chart.
+    fn axis_labels_position(&self, index: usize) -> (f32, Vec2) {
+        let axis = self.y_axis(index);
+        let column = self.y_axes.iter().take(index).filter(|other| other.position == axis.position).count() as f32;
+        match axis.position {
+            ChartAxisPosition::Left => {
+                (self.bounds.pos.x - 15. - column * AXIS_LABELS_WIDTH, TEXT_ANCHOR_RIGHT + TEXT_ANCHOR_CENTER_V)
+            }
+            ChartAxisPosition::Right => (
+                self.bounds.pos.x + self.bounds.size.x + 15. + column * AXIS_LABELS_WIDTH,
+                TEXT_ANCHOR_LEFT + TEXT_ANCHOR_CENTER_V,
+            ),
+        }
+    }
+
+    /// Smallest multiple of 1, 2 or 5 times a power of 10 that is at least `min_step`.
+    fn nice_step(min_step: f32) -> f32 {
+        let magnitude = 10_f32.powf(min_step.log10().floor());
+        [1., 2., 5., 10.].iter().map(|multiplier| multiplier * magnitude).find(|step| *step >= min_step).unwrap_or(min_step)
+    }
+
+    /// Multiples of `step` between `min` and `max`.
+    fn steps(min: f32, max: f32, step: f32) -> Vec<f32> {
+        if !(min.is_finite() && max.is_finite() && step.is_finite() && step > 0.) {
+            return vec![];
+        }
+        let first = (min / step).ceil() as i64;
+        let last = (max / step).floor() as i64;
+        (first..=last).map(|i| i as f32 * step).collect()
+    }
+
     /// Use Liang-Barsky algorithm to clip line its points are both inside
     /// the chart boundaries (see: <https://en.wikipedia.org/wiki/Liang%E2%80%93Barsky_algorithm>)
     fn draw_lines(&mut self, cx: &mut Cx, data: &[Vec2], color: Vec4, scale: f32) {
@@ -692,11 +1192,11 @@ impl Chart {
         &mut self,
         cx: &mut Cx,
         normalized_data: &[Vec2],
-        original_data: &[Vec2],
+        original_data: &[(usize, Vec2)],
         color: Vec4,
         scale: f32,
         point_style: DrawPoints3dStyle,
-    ) -> Area {
+    ) -> (Area, Vec<usize>) {
         let min_x = self.bounds.pos.x;
         let max_x = min_x + self.bounds.size.x;
         let min_y = self.bounds.pos.y;
@@ -705,20 +1205,24 @@ impl Chart {
         let color = color.to_vec3();
         let size = scale;
         let mut points = Vec::<DrawPoints3dInstance>::with_capacity(normalized_data.len());
+        let mut datum_indices = Vec::with_capacity(normalized_data.len());
         for i in 0..normalized_data.len() {
             let p = normalized_data[i];
 
             // Check if point is inside the chart boundaries before drawing
             if min_x <= p.x && p.x <= max_x && min_y <= p.y && p.y <= max_y {
-                points.push(DrawPoints3dInstance { position: p.to_vec3(), color, size, user_info: original_data[i] });
+                let (datum_index, data_point) = original_data[i];
+                points.push(DrawPoints3dInstance { position: p.to_vec3(), color, size, user_info: data_point });
+                datum_indices.push(datum_index);
             }
         }
 
-        DrawPoints3d::draw(
+        let area = DrawPoints3d::draw(
             cx,
             &points,
             DrawPoints3dOptions { use_screen_space: true, point_style, ..DrawPoints3dOptions::default() },
-        )
+        );
+        (area, datum_indices)
     }
 
     /// Compute offset and scaling based on zoom/pan values
@@ -732,46 +1236,262 @@ impl Chart {
         }
     }
 
-    /// Transform a data point from data coordinates to normalized screen coordinates
-    fn normalize_data_point(&self, data_point: Vec2) -> Vec2 {
+    fn y_axis(&self, index: usize) -> &ChartAxis {
+        self.y_axes.get(index).unwrap_or(&DEFAULT_AXIS)
+    }
+
+    fn y_axis_index(&self, dataset: &ChartDataset) -> usize {
+        self.y_axes.iter().position(|axis| axis.id == dataset.y_axis_id).unwrap_or(0)
+    }
+
+    /// Transform a point in scaled units (see [`ChartAxis::scale`]) to normalized screen coordinates
+    fn scaled_to_screen(&self, scaled_point: Vec2, y_axis: &ChartAxis) -> Vec2 {
         let (offset, scale) = self.get_offset_scale();
         offset
             + scale
                 * vec2(
                     // For x axis, we want values to be in the range [bounds.pos.x, bounds.pos.x + bounds.size.x],
                     // using (p.x - min.x) / (max.x - min.x) for interpolation.
-                    self.bounds.pos.x + (data_point.x - self.min.x) / (self.max.x - self.min.x) * self.bounds.size.x,
+                    self.bounds.pos.x
+                        + (scaled_point.x - self.x_axis.min) / (self.x_axis.max - self.x_axis.min) * self.bounds.size.x,
                     // For y axis, it's a similar process except that we want charts to start at the bottom instead.
                     // Then, we add bounds.size.y and subtract the interpolated value.
                     (self.bounds.pos.y + self.bounds.size.y)
-                        - (data_point.y - self.min.y) / (self.max.y - self.min.y) * self.bounds.size.y,
+                        - (scaled_point.y - y_axis.min) / (y_axis.max - y_axis.min) * self.bounds.size.y,
                 )
     }
 
-    /// Transform a normalized data point from screen coordinates to data coordinates
-    fn denormalize_data_point(&self, normalized_data_point: Vec2) -> Vec2 {
+    /// Transform a point from normalized screen coordinates to scaled units
+    fn screen_to_scaled(&self, normalized_point: Vec2, y_axis: &ChartAxis) -> Vec2 {
         let (offset, scale) = self.get_offset_scale();
-        let normalized_data_point = (normalized_data_point - offset) / scale;
+        let normalized_point = (normalized_point - offset) / scale;
         vec2(
             Self::remap(
-                normalized_data_point.x,
+                normalized_point.x,
                 self.bounds.pos.x,
                 self.bounds.pos.x + self.bounds.size.x,
-                self.min.x,
-                self.max.x,
-            ),
-            Self::remap(
-                normalized_data_point.y,
-                self.bounds.pos.y,
-                self.bounds.pos.y + self.bounds.size.y,
-                self.max.y,
-                self.min.y,
+                self.x_axis.min,
+                self.x_axis.max,
             ),
+            Self::remap(normalized_point.y, self.bounds.pos.y, self.bounds.pos.y + self.bounds.size.y, y_axis.max, y_axis.min),
         )
     }
 
-    fn normalize(&self, data: &[Vec2]) -> Vec<Vec2> {
-        data.iter().map(|p| self.normalize_data_point(*p)).collect()
+    /// Transform a data point from data coordinates to normalized screen coordinates, using the y axis with
+    /// the given index
+    fn normalize_data_point_on(&self, data_point: Vec2, y_axis: usize) -> Vec2 {
+        let y_axis = self.y_axis(y_axis);
+        self.scaled_to_screen(vec2(self.x_axis.scale(data_point.x), y_axis.scale(data_point.y)), y_axis)
+    }
+
+    /// Transform a data point from data coordinates to normalized screen coordinates, using the primary y axis
+    pub fn normalize_data_point(&self, data_point: Vec2) -> Vec2 {
+        self.normalize_data_point_on(data_point, 0)
+    }
+
+    /// Like [`Chart::normalize_data_point`], but for the y axis with the given key in [`ChartConfig::scales`]
+    pub fn normalize_data_point_on_axis(&self, data_point: Vec2, y_axis_id: &str) -> Vec2 {
+        self.normalize_data_point_on(data_point, self.y_axes.iter().position(|axis| axis.id == y_axis_id).unwrap_or(0))
+    }
+
+    /// Transform a normalized data point from screen coordinates to data coordinates, using the primary y axis
+    pub fn denormalize_data_point(&self, normalized_data_point: Vec2) -> Vec2 {
+        let y_axis = self.y_axis(0);
+        let scaled_point = self.screen_to_scaled(normalized_data_point, y_axis);
+        vec2(self.x_axis.unscale(scaled_point.x), y_axis.unscale(scaled_point.y))
+    }
+
+    /// Intersection of two rects, if they overlap. Empty rects are kept if they are inside
+    /// the bounds, so e.g. bars for zero values can still be hovered.
+    pub(crate) fn clip_rect(rect: Rect, bounds: Rect) -> Option<Rect> {
+        let pos = rect.pos.max(&bounds.pos);
+        let size = (rect.pos + rect.size).min(&(bounds.pos + bounds.size)) - pos;
+        if size.x < 0. || size.y < 0. {
+            return None;
+        }
+        Some(Rect { pos, size })
+    }
+
+    /// Computes the bottom and top of each value when stacking datasets on top of each
+    /// other. Values are stacked with those of previous datasets that have the same x value
+    /// and y axis, and positive and negative values are stacked separately.
+    fn stack(datasets: &[ChartDataset]) -> Vec<Vec<Vec2>> {
+        // Keyed by the y axis and the bits of the x value; the tuples hold the positive and negative totals.
+        let mut totals: HashMap<(&str, u32), (f32, f32)> = HashMap::new();
+        datasets
+            .iter()
+            .map(|dataset| {
+                (0..dataset.data.len())
+                    .map(|i| {
+                        let value = dataset.data.value_at(i);
+                        let total = totals.entry((dataset.y_axis_id.as_str(), value.x.to_bits())).or_insert((0., 0.));
+                        let base = if value.y < 0. { &mut total.1 } else { &mut total.0 };
+                        let ret = vec2(*base, *base + value.y);
+                        *base += value.y;
+                        ret
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The smallest distance between two different x values, which is the space that is
+    /// available for the bars of each x value.
+    fn category_width(config: &ChartConfig) -> f32 {
+        let mut xs: Vec<f32> = config
+            .datasets
+            .iter()
+            .flat_map(|dataset| (0..dataset.data.len()).map(move |i| dataset.data.value_at(i).x))
+            .filter(|x| x.is_finite())
+            .collect();
+        xs.sort_by(f32::total_cmp);
+        let width = xs.windows(2).map(|w| w[1] - w[0]).filter(|d| *d > 0.).fold(INFINITY, f32::min);
+        if width.is_finite() {
+            width
+        } else {
+            1.
+        }
+    }
+
+    fn layout_bars(&self, config: &ChartConfig) -> Vec<ChartBar> {
+        let stacked = if config.stacked { Some(Self::stack(&config.datasets)) } else { None };
+        let category_width = Self::category_width(config) * CATEGORY_PERCENTAGE;
+        // Hidden datasets don't take up a slot.
+        let slot_count = config.datasets.iter().filter(|dataset| !dataset.hidden).count();
+        let slot_width = if config.stacked { category_width } else { category_width / slot_count.max(1) as f32 };
+        let min_y = self.bounds.pos.y;
+        let max_y = min_y + self.bounds.size.y;
+
+        let mut bars = vec![];
+        for (dataset_index, dataset) in config.datasets.iter().enumerate() {
+            let y_axis = self.y_axis_index(dataset);
+            // Logarithmic axes don't go down to zero, so start bars at the bottom of the axis instead.
+            let axis = self.y_axis(y_axis);
+            let baseline = if axis.scale_type == ChartScaleType::Logarithmic { axis.unscale(axis.min) } else { 0. };
+            let slot = if config.stacked { 0 } else { config.datasets[..dataset_index].iter().filter(|d| !d.hidden).count() };
+            for datum_index in 0..dataset.data.len() {
+                let data_point = dataset.data.value_at(datum_index);
+                let (base, top) = match &stacked {
+                    Some(stacked) => (stacked[dataset_index][datum_index].x, stacked[dataset_index][datum_index].y),
+                    None => (baseline, data_point.y),
+                };
+                let left = data_point.x - 0.5 * category_width + (slot as f32 + 0.5 * (1. - BAR_PERCENTAGE)) * slot_width;
+                let right = left + BAR_PERCENTAGE * slot_width;
+                let a = self.normalize_data_point_on(vec2(left, top), y_axis);
+                let b = self.normalize_data_point_on(vec2(right, base), y_axis);
+                let rect = Rect { pos: a.min(&b), size: a.max(&b) - a.min(&b) };
+                if let Some(rect) = Self::clip_rect(rect, self.bounds) {
+                    bars.push(ChartBar {
+                        rect,
+                        anchor: vec2(rect.pos.x + 0.5 * rect.size.x, a.y.clamp(min_y, max_y)),
+                        dataset_index,
+                        datum_index,
+                        data_point,
+                    });
+                }
+            }
+        }
+        bars
+    }
+
+    fn layout_slices(&self, config: &ChartConfig) -> Vec<ChartSlice> {
+        let center = self.bounds.pos + self.bounds.size * 0.5;
+        let outer_radius = (0.5 * self.bounds.size.x.min(self.bounds.size.y)).max(0.);
+        let inner_radius = if config.chart_type == ChartType::Doughnut { DOUGHNUT_CUTOUT * outer_radius } else { 0. };
+        let visible_datasets = config.datasets.iter().enumerate().filter(|(_, dataset)| !dataset.hidden);
+        let ring_width = (outer_radius - inner_radius) / visible_datasets.clone().count().max(1) as f32;
+
+        let mut slices = vec![];
+        for (ring, (dataset_index, dataset)) in visible_datasets.enumerate() {
+            let radii = vec2(outer_radius - (ring + 1) as f32 * ring_width, outer_radius - ring as f32 * ring_width);
+            let total: f32 = (0..dataset.data.len()).map(|i| dataset.data.value_at(i).y.abs()).sum();
+            if total <= 0. {
+                continue;
+            }
+
+            let mut start = 0.;
+            for datum_index in 0..dataset.data.len() {
+                let data_point = dataset.data.value_at(datum_index);
+                let end = start + data_point.y.abs() / total * 2. * PI;
+                slices.push(ChartSlice { center, radii, angles: vec2(start, end), dataset_index, datum_index, data_point });
+                start = end;
+            }
+        }
+        slices
+    }
+
+    fn draw_bars(&mut self, cx: &mut Cx, config: &ChartConfig) {
+        self.bars = self.layout_bars(config);
+        let rects: Vec<ChartRectIns> = self
+            .bars
+            .iter()
+            .map(|bar| ChartRectIns {
+                base: QuadIns::from_rect(bar.rect),
+                color: config.datasets[bar.dataset_index].background_color_at(bar.datum_index),
+            })
+            .collect();
+        ChartRectIns::draw(cx, &rects);
+    }
+
+    /// Fills the area between `tops` and `bottoms`, which have to be in screen coordinates
+    /// and have the same x values.
+    fn draw_area(&mut self, cx: &mut Cx, tops: &[Vec2], bottoms: &[Vec2], color: Vec4) {
+        let mut areas = vec![];
+        for i in 1..tops.len() {
+            let (a, b) = (tops[i - 1], tops[i]);
+            let (c, d) = (bottoms[i - 1], bottoms[i]);
+            let min = a.min(&b).min(&c).min(&d);
+            let max = a.max(&b).max(&c).max(&d);
+            // Only the quad gets clipped, the shader still interpolates between the original points.
+            if let Some(rect) = Self::clip_rect(Rect { pos: min, size: max - min }, self.bounds) {
+                areas.push(ChartAreaIns {
+                    base: QuadIns::from_rect(rect),
+                    x_range: vec2(a.x, b.x),
+                    top: vec2(a.y, b.y),
+                    bottom: vec2(c.y, d.y),
+                    color,
+                });
+            }
+        }
+        ChartAreaIns::draw(cx, &areas);
+    }
+
+    fn draw_slices(&mut self, cx: &mut Cx, config: &ChartConfig) {
+        self.slices = self.layout_slices(config);
+        let current_dpi = cx.current_dpi_factor;
+        let slices: Vec<ChartSliceIns> = self
+            .slices
+            .iter()
+            .map(|slice| {
+                let dataset = &config.datasets[slice.dataset_index];
+                ChartSliceIns {
+                    base: QuadIns::from_rect(Rect {
+                        pos: slice.center - vec2(slice.radii.y, slice.radii.y),
+                        size: vec2(2. * slice.radii.y, 2. * slice.radii.y),
+                    }),
+                    center: slice.center,
+                    radii: slice.radii,
+                    angles: slice.angles,
+                    color: dataset.background_color_at(slice.datum_index),
+                    border_color: dataset.border_color,
+                    border_width: 0.5 * dataset.border_width * current_dpi,
+                }
+            })
+            .collect();
+        ChartSliceIns::draw(cx, &slices);
+    }
+
+    /// The points of `stream` that are visible with the current zoom/pan, decimated to a few points per pixel column,
+    /// along with their datum indices.
+    fn decimate(&self, stream: &ChartStream, dpi_factor: f32) -> Vec<(usize, Vec2)> {
+        let min_x = self.denormalize_data_point(self.bounds.pos).x;
+        let max_x = self.denormalize_data_point(self.bounds.pos + self.bounds.size).x;
+        let columns = (self.bounds.size.x * dpi_factor).ceil().max(1.) as usize;
+        stream.decimate(min_x, max_x, columns)
+    }
+
+    fn normalize(&self, data: &[Vec2], y_axis: usize) -> Vec<Vec2> {
+        data.iter().map(|p| self.normalize_data_point_on(*p, y_axis)).collect()
     }
 
     /// Rounds a number to the closest power of 10, rounded up
@@ -794,29 +1514,70 @@ impl Chart {
         vec2(value.x, if value.y < 0. { -Self::round_up_to_10s(value.y.abs()) } else { Self::round_down_to_10s(value.y) })
     }
 
-    fn get_min_max(config: &ChartConfig) -> (Vec2, Vec2) {
-        let mut min = vec2(INFINITY, INFINITY);
-        let mut max = vec2(NEG_INFINITY, NEG_INFINITY);
-
-        if let Some(x_scale) = config.scales.get("x") {
-            min.x = x_scale.min;
-            max.x = x_scale.max;
+    /// Computes the range of the x axis and of each y axis from the data and from [`ChartConfig::scales`]
+    fn get_axes(config: &ChartConfig) -> (ChartAxis, Vec<ChartAxis>) {
+        let mut x_axis = ChartAxis::new("x", config.scales.get("x"));
+
+        let mut ids: Vec<&str> = config
+            .datasets
+            .iter()
+            .map(|dataset| dataset.y_axis_id.as_str())
+            .chain(config.scales.keys().map(|id| id.as_str()).filter(|id| *id != "x"))
+            .collect();
+        if ids.is_empty() {
+            ids.push("y");
+        }
+        // Put "y" first, so it's the primary axis.
+        ids.sort_by_key(|id| (*id != "y", *id));
+        ids.dedup();
+        let mut y_axes: Vec<ChartAxis> = ids.iter().map(|id| ChartAxis::new(id, config.scales.get(*id))).collect();
+
+        let stacked = config.stacked && matches!(config.chart_type, ChartType::Bar | ChartType::Area);
+        let stacks = if stacked { Some(Self::stack(&config.datasets)) } else { None };
+        for (dataset_index, dataset) in config.datasets.iter().enumerate() {
+            let y_axis = y_axes.iter_mut().find(|axis| axis.id == dataset.y_axis_id).unwrap();
+            if let Some(stacks) = &stacks {
+                for (datum_index, base_top) in stacks[dataset_index].iter().enumerate() {
+                    x_axis.include(dataset.data.value_at(datum_index).x);
+                    y_axis.include(base_top.x);
+                    y_axis.include(base_top.y);
+                }
+            } else if !dataset.data.is_empty() {
+                let (lo, hi) = dataset.data.min_max(vec2(INFINITY, INFINITY), vec2(NEG_INFINITY, NEG_INFINITY));
+                let is_log = |axis: &ChartAxis| axis.scale_type == ChartScaleType::Logarithmic;
+                if (is_log(&x_axis) && lo.x <= 0.) || (is_log(y_axis) && lo.y <= 0.) {
+                    // Logarithmic axes skip values that are not positive, so we need the smallest positive value.
+                    for i in 0..dataset.data.len() {
+                        let value = dataset.data.value_at(i);
+                        x_axis.include(value.x);
+                        y_axis.include(value.y);
+                    }
+                } else {
+                    x_axis.include(lo.x);
+                    x_axis.include(hi.x);
+                    y_axis.include(lo.y);
+                    y_axis.include(hi.y);
+                }
+            }
         }
 
-        for dataset in &config.datasets {
-            let (lo, hi) = dataset.data.min_max(min, max);
-            min = lo;
-            max = hi;
+        if config.chart_type == ChartType::Bar && x_axis.scale_type == ChartScaleType::Linear {
+            // Leave room for the bars of the first and last values.
+            let half_category_width = 0.5 * Self::category_width(config);
+            x_axis.min -= half_category_width;
+            x_axis.max += half_category_width;
         }
 
-        // Force either bound to be zero (but not both)
-        if max.y < 0. {
-            max.y = 0.;
-        } else if min.y > 0. {
-            min.y = 0.;
+        x_axis.fit(false);
+        for y_axis in &mut y_axes {
+            y_axis.fit(true);
         }
+        (x_axis, y_axes)
+    }
 
-        (Self::round_down(min), Self::round_up(max))
+    /// The rect that data is drawn in, in the same coordinates as the positions in [`ChartEvent`].
+    pub fn bounds(&self) -> Rect {
+        self.bounds
     }
 
     pub fn reset_zoom_pan(&mut self) {
@@ -835,49 +1596,126 @@ impl Chart {
         self.zoom_enabled = config.zoom_enabled;
         self.pan_enabled = config.pan_enabled;
 
-        // Compute the rect where the chart will be rendered. The offsets below
-        // add some marging so we can also render labels for each axis.
-        // TODO(Hernan): should this be customizable?
-        self.bounds = Rect { pos: rect.pos + vec2(60., 5.), size: rect.size - vec2(80., 40.) };
+        self.chart_type = config.chart_type;
+        self.areas = vec![];
+        self.point_datum_indices = vec![];
+        self.bars = vec![];
+        self.slices = vec![];
+
+        if matches!(config.chart_type, ChartType::Pie | ChartType::Doughnut) {
+            // No axes, so only leave a small margin.
+            self.bounds = Rect { pos: rect.pos + vec2(5., 5.), size: rect.size - vec2(10., 10.) };
+            self.draw_slices(cx, config);
+            self.draw_overlays(cx, config);
+            self.chart_view.end_view(cx);
+            return;
+        }
 
-        if self.zoom_pan.is_none() {
+        let (x_axis, y_axes) = Self::get_axes(config);
+        if self.zoom_pan.is_none() || y_axes.len() != self.y_axes.len() {
             // Compute min/max for all datasets before rendering
             // Only update min/max values if we're not panning/zooming
-            let (data_min, data_max) = Self::get_min_max(config);
-            self.min = data_min;
-            self.max = data_max;
+            self.x_axis = x_axis;
+            self.y_axes = y_axes;
         }
 
-        self.areas = vec![];
+        // Compute the rect where the chart will be rendered. The offsets below
+        // add some marging so we can also render labels for each axis.
+        // TODO(Hernan): should this be customizable?
+        let axes_on = |position| self.y_axes.iter().filter(|axis| axis.position == position).count() as f32;
+        let left_margin = 60. + (axes_on(ChartAxisPosition::Left) - 1.).max(0.) * AXIS_LABELS_WIDTH;
+        let right_margin = 20. + axes_on(ChartAxisPosition::Right) * AXIS_LABELS_WIDTH;
+        self.bounds = Rect { pos: rect.pos + vec2(left_margin, 5.), size: rect.size - vec2(left_margin + right_margin, 40.) };
 
         self.draw_grid(cx, config);
 
-        for dataset in &config.datasets {
-            let points = dataset.data.points();
-            let normalized_data = self.normalize(&points);
+        if config.chart_type == ChartType::Bar {
+            self.draw_bars(cx, config);
+            self.draw_overlays(cx, config);
+            self.chart_view.end_view(cx);
+            return;
+        }
+
+        // Screen positions of the points and of the bottom of the filled areas.
+        let stacked =
+            if config.stacked && config.chart_type == ChartType::Area { Some(Self::stack(&config.datasets)) } else { None };
+        let mut lines = vec![];
+        for (dataset_index, dataset) in config.datasets.iter().enumerate() {
+            let y_axis = self.y_axis_index(dataset);
+            let points: Vec<(usize, Vec2)> = match (&dataset.data, &stacked) {
+                // Stacking needs all values, and min/max decimation would drop points of a scatter plot, so only
+                // decimate lines and areas that aren't stacked.
+                (ChartData::Stream(stream), None) if config.chart_type != ChartType::Scatter => {
+                    self.decimate(stream, current_dpi)
+                }
+                _ => dataset.data.points().into_iter().enumerate().collect(),
+            };
+            let (tops, bottoms): (Vec<Vec2>, Vec<Vec2>) = match &stacked {
+                Some(stacked) => points
+                    .iter()
+                    .zip(&stacked[dataset_index])
+                    .map(|((_, p), base_top)| (vec2(p.x, base_top.y), vec2(p.x, base_top.x)))
+                    .unzip(),
+                None => {
+                    // Logarithmic axes don't go down to zero, so fill to the bottom of the axis instead.
+                    let axis = self.y_axis(y_axis);
+                    let baseline = if axis.scale_type == ChartScaleType::Logarithmic { axis.unscale(axis.min) } else { 0. };
+                    points.iter().map(|(_, p)| (*p, vec2(p.x, baseline))).unzip()
+                }
+            };
+            let (tops, bottoms) = (self.normalize(&tops, y_axis), self.normalize(&bottoms, y_axis));
+            // Drop values that can't be shown, like zero on a logarithmic axis. The remaining points keep their datum
+            // index, so the tooltip still shows the right datum.
+            let (points, (tops, bottoms)): (Vec<_>, (Vec<_>, Vec<_>)) = points
+                .into_iter()
+                .zip(tops.into_iter().zip(bottoms))
+                .filter(|(_, (top, _))| top.x.is_finite() && top.y.is_finite())
+                .unzip();
+            lines.push((points, tops, bottoms));
+        }
+
+        if config.chart_type == ChartType::Area {
+            // Draw all areas first, so they don't cover the lines and points of other datasets.
+            for (dataset, (_, tops, bottoms)) in config.datasets.iter().zip(&lines) {
+                self.draw_area(cx, tops, bottoms, dataset.background_color);
+            }
+        }
+
+        for (dataset, (points, normalized_data, _)) in config.datasets.iter().zip(&lines) {
             if !normalized_data.is_empty() {
-                self.draw_lines(cx, &normalized_data, dataset.border_color, dataset.border_width * current_dpi);
-                let area = self.draw_points(
+                if config.chart_type != ChartType::Scatter {
+                    self.draw_lines(cx, normalized_data, dataset.border_color, dataset.border_width * current_dpi);
+                }
+                let (area, datum_indices) = self.draw_points(
                     cx,
-                    &normalized_data,
-                    &points,
+                    normalized_data,
+                    points,
                     dataset.point_background_color,
                     dataset.point_radius * current_dpi,
                     dataset.point_style,
                 );
                 self.areas.push(area);
+                self.point_datum_indices.push(datum_indices);
+            } else {
+                // Keep the indices of the areas the same as the dataset indices.
+                self.areas.push(Area::Empty);
+                self.point_datum_indices.push(vec![]);
             }
         }
 
-        for plugin in &mut self.plugins {
-            plugin.write().unwrap().draw(cx, config, &self.bounds)
+        self.draw_overlays(cx, config);
+        self.chart_view.end_view(cx);
+    }
+
+    /// Draws plugins and the tooltip on top of the chart.
+    fn draw_overlays(&mut self, cx: &mut Cx, config: &ChartConfig) {
+        for plugin in &self.plugins {
+            plugin.write().unwrap().draw(cx, config, self)
         }
 
         if self.tooltip_visible {
             self.tooltip.draw(cx, config);
         }
-
-        self.chart_view.end_view(cx);
     }
 
     fn draw_view(&mut self, cx: &mut Cx) {
@@ -897,7 +1735,38 @@ impl Chart {
         self.pass.set_size(cx, rect.size);
         self.pass.add_color_texture(cx, color_texture_handle, ClearColor::default());
 
-        self.draw_chart(cx, config);
+        let hidden: Vec<bool> = (0..config.datasets.len())
+            .map(|index| {
+                config.datasets[index].hidden || self.plugins.iter().any(|plugin| plugin.read().unwrap().is_dataset_hidden(index))
+            })
+            .collect();
+        if hidden.iter().any(|hidden| *hidden) {
+            // Draw hidden datasets as if they have no data, so they keep their index.
+            let datasets =
+                config
+                    .datasets
+                    .iter()
+                    .zip(hidden)
+                    .map(|(dataset, hidden)| {
+                        if hidden {
+                            ChartDataset { data: ChartData::Empty, hidden, ..dataset.clone() }
+                        } else {
+                            dataset.clone()
+                        }
+                    })
+                    .collect();
+            let config = ChartConfig {
+                labels: config.labels.clone(),
+                datasets,
+                scales: config.scales.clone(),
+                style: config.style.clone(),
+                tooltip: config.tooltip.clone(),
+                ..*config
+            };
+            self.draw_chart(cx, &config);
+        } else {
+            self.draw_chart(cx, config);
+        }
 
         self.pass.end_pass(cx);
     }
@@ -905,9 +1774,12 @@ impl Chart {
 
 #[cfg(test)]
 mod tests {
-    use zaplib::vec2;
+    use std::{collections::HashMap, f32::consts::PI};
+
+    use zaplib::*;
 
-    use crate::Chart;
+    use super::ChartAxis;
+    use crate::*;
 
     #[test]
     fn it_rounds_up() {
@@ -944,4 +1816,334 @@ mod tests {
         assert_eq!(Chart::round_down(vec2(10., 1001.)), vec2(10., 1000.));
         assert_eq!(Chart::round_down(vec2(10., -1001.)), vec2(10., -10000.));
     }
+
+    #[test]
+    fn it_stacks_positive_and_negative_values_separately() {
+        let first = [10., -20., 30.];
+        let second = [vec2(0., 5.), vec2(1., -5.), vec2(3., 7.)];
+        let datasets = vec![
+            ChartDataset { data: ChartData::from_values(&first), ..ChartDataset::default() },
+            ChartDataset { data: ChartData::from_pairs(&second), ..ChartDataset::default() },
+        ];
+        assert_eq!(
+            Chart::stack(&datasets),
+            vec![vec![vec2(0., 10.), vec2(0., -20.), vec2(0., 30.)], vec![vec2(10., 15.), vec2(-20., -25.), vec2(0., 7.)],]
+        );
+    }
+
+    #[test]
+    fn it_lays_out_grouped_bars() {
+        let first = [10., 20.];
+        let second = [-10., 5.];
+        let config = ChartConfig {
+            chart_type: ChartType::Bar,
+            datasets: vec![
+                ChartDataset { data: ChartData::from_values(&first), ..ChartDataset::default() },
+                ChartDataset { data: ChartData::from_values(&second), ..ChartDataset::default() },
+            ],
+            ..ChartConfig::default()
+        };
+        let (x_axis, y_axes) = Chart::get_axes(&config);
+        assert_eq!((x_axis.min, x_axis.max), (-0.5, 1.5));
+        assert_eq!((y_axes[0].min, y_axes[0].max), (-10., 100.));
+        let mut chart = Chart { bounds: Rect { pos: vec2(0., 0.), size: vec2(200., 100.) }, x_axis, y_axes, ..Chart::default() };
+
+        chart.bars = chart.layout_bars(&config);
+        let bars = &chart.bars;
+        assert_eq!(bars.len(), 4);
+        // Each x value gets 100px, of which 80px are split between the two datasets.
+        assert_eq!(bars[0].rect.pos.x, 10. + 2.);
+        assert_eq!(bars[0].rect.size.x, 36.);
+        assert_eq!(bars[2].rect.pos.x, 50. + 2.);
+        // Negative values go down from zero, and the tooltip points to their bottom.
+        assert_eq!(bars[2].rect.pos.y, chart.normalize_data_point(vec2(0., 0.)).y);
+        assert_eq!(bars[2].anchor.y, 100.);
+        let bar_at = |pos| chart.get_bar_at(pos).map(|element| (element.dataset_index, element.datum_index));
+        assert_eq!(bar_at(vec2(60., 0.)), Some((1, 0)));
+        assert_eq!(bar_at(vec2(140., 0.)), Some((0, 1)));
+    }
+
+    #[test]
+    fn it_computes_axes() {
+        let first = [10., -20., 30.];
+        let second = [0., 5., 2000.];
+        let mut scales = HashMap::new();
+        scales.insert(
+            "y1".to_string(),
+            ChartScale { scale_type: ChartScaleType::Logarithmic, position: ChartAxisPosition::Right, ..ChartScale::default() },
+        );
+        let config = ChartConfig {
+            datasets: vec![
+                ChartDataset { data: ChartData::from_values(&second), y_axis_id: "y1".to_string(), ..ChartDataset::default() },
+                ChartDataset { data: ChartData::from_values(&first), ..ChartDataset::default() },
+            ],
+            scales,
+            ..ChartConfig::default()
+        };
+
+        let (x_axis, y_axes) = Chart::get_axes(&config);
+        assert_eq!((x_axis.min, x_axis.max), (0., 2.));
+        assert_eq!(y_axes.iter().map(|axis| axis.id.as_str()).collect::<Vec<_>>(), vec!["y", "y1"]);
+        assert_eq!((y_axes[0].min, y_axes[0].max), (-100., 100.));
+        // Zero is skipped on logarithmic axes, and the range is in powers of 10.
+        assert_eq!((y_axes[1].min, y_axes[1].max), (0., 4.));
+        assert_eq!(y_axes[1].position, ChartAxisPosition::Right);
+
+        let labels =
+            |axis: &ChartAxis| axis.ticks(axis.min, axis.max, 100.).into_iter().map(|(_, label)| label).collect::<Vec<_>>();
+        assert_eq!(labels(&y_axes[0]), vec!["-100", "0", "100"]);
+        assert_eq!(labels(&y_axes[1]), vec!["1", "100", "10000"]);
+
+        let chart = Chart { bounds: Rect { pos: vec2(0., 0.), size: vec2(200., 100.) }, x_axis, y_axes, ..Chart::default() };
+        assert_eq!(chart.normalize_data_point_on(vec2(1., 100.), 1), vec2(100., 50.));
+        assert_eq!(chart.normalize_data_point_on(vec2(1., 0.), 0), vec2(100., 50.));
+
+        // Logarithmic axes without any positive values.
+        let mut axis =
+            ChartAxis::new("y", Some(&ChartScale { scale_type: ChartScaleType::Logarithmic, ..ChartScale::default() }));
+        axis.include(0.);
+        axis.include(-5.);
+        axis.fit(true);
+        assert_eq!((axis.min, axis.max), (0., 1.));
+    }
+
+    #[test]
+    fn it_finds_slices() {
+        let data = [1., 1., 2.];
+        let config = ChartConfig {
+            chart_type: ChartType::Doughnut,
+            datasets: vec![ChartDataset { data: ChartData::from_values(&data), ..ChartDataset::default() }],
+            ..ChartConfig::default()
+        };
+        let chart = Chart { bounds: Rect { pos: vec2(0., 0.), size: vec2(200., 100.) }, ..Chart::default() };
+        let slices = chart.layout_slices(&config);
+        assert_eq!(
+            slices.iter().map(|slice| slice.angles).collect::<Vec<_>>(),
+            vec![vec2(0., 0.5 * PI), vec2(0.5 * PI, PI), vec2(PI, 2. * PI)]
+        );
+        assert_eq!(slices[0].radii, vec2(25., 50.));
+
+        let center = vec2(100., 50.);
+        let find = |pos: Vec2| slices.iter().position(|slice| slice.contains(pos));
+        assert_eq!(find(center + vec2(40., -1.)), Some(0));
+        assert_eq!(find(center + vec2(40., 1.)), Some(1));
+        assert_eq!(find(center + vec2(-40., 0.)), Some(2));
+        // Inside the hole, and outside of the doughnut.
+        assert_eq!(find(center + vec2(20., 1.)), None);
+        assert_eq!(find(center + vec2(0., 60.)), None);
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod app_tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, RwLock},
+    };
+
+    use zaplib::*;
+
+    use crate::*;
+
+    struct ChartTestApp {
+        chart: Chart,
+        data: Vec<f32>,
+        chart_type: ChartType,
+        /// Adds a second dataset and stacks it on top of the first one.
+        stacked: bool,
+        /// Draws this instead of `data`.
+        stream: Option<ChartStream>,
+        logarithmic: bool,
+    }
+
+    impl MainApp for ChartTestApp {
+        fn new(_cx: &mut Cx) -> Self {
+            Self {
+                chart: Chart::default(),
+                data: vec![10., 30., -20., 40., 0.],
+                chart_type: ChartType::Line,
+                stacked: false,
+                stream: None,
+                logarithmic: false,
+            }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            self.chart.handle(cx, event);
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            let mut datasets = vec![ChartDataset {
+                label: "Values".to_string(),
+                data: self.stream.as_ref().map_or(ChartData::from_values(&self.data), ChartData::from_stream),
+                border_color: COLOR_RED,
+                point_background_color: COLOR_RED,
+                background_color: COLOR_RED,
+                ..ChartDataset::default()
+            }];
+            if matches!(self.chart_type, ChartType::Pie | ChartType::Doughnut) {
+                datasets[0].border_color = COLOR_WHITE;
+                datasets[0].background_colors = vec![COLOR_RED, COLOR_GREEN, COLOR_BLUE, COLOR_ORANGE, COLOR_PURPLE];
+            }
+            if self.stacked {
+                datasets.push(ChartDataset {
+                    label: "More values".to_string(),
+                    data: ChartData::from_values(&self.data[1..]),
+                    border_color: COLOR_BLUE,
+                    point_background_color: COLOR_BLUE,
+                    background_color: COLOR_BLUE,
+                    ..ChartDataset::default()
+                });
+            }
+            let mut scales = HashMap::new();
+            if self.logarithmic {
+                scales.insert("y".to_string(), ChartScale { scale_type: ChartScaleType::Logarithmic, ..ChartScale::default() });
+            }
+            let config = ChartConfig {
+                chart_type: self.chart_type,
+                labels: ["a", "b", "c", "d", "e"].iter().map(|label| label.to_string()).collect(),
+                datasets,
+                scales,
+                style: CHART_STYLE_LIGHT,
+                stacked: self.stacked,
+                ..ChartConfig::default()
+            };
+            self.chart.draw(cx, &config);
+        }
+    }
+
+    #[test]
+    fn test_chart_tooltip() {
+        let mut test = AppTest::<ChartTestApp>::new_in_window(vec2(200., 120.));
+        test.assert_golden_draw_tree(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/chart_initial.txt"));
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/chart_initial.png"));
+
+        test.pointer_move(vec2(100., 60.));
+        test.advance_time(1.0);
+        assert!(test.app.chart.tooltip_visible);
+        test.assert_golden_draw_tree(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/chart_tooltip.txt"));
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/chart_tooltip.png"));
+    }
+
+    #[test]
+    fn test_chart_types() {
+        let mut test = AppTest::<ChartTestApp>::new_in_window(vec2(200., 120.));
+
+        test.app.chart_type = ChartType::Area;
+        test.app.stacked = true;
+        test.cx.request_draw();
+        test.next_frame();
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/chart_area_stacked.png"));
+
+        test.app.chart_type = ChartType::Bar;
+        test.cx.request_draw();
+        test.next_frame();
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/chart_bar_stacked.png"));
+        assert_eq!(test.app.chart.bars.len(), 9);
+
+        // Hovering over the top of "a" selects the bar of the second dataset, which is stacked on the first one.
+        let bar = test.app.chart.bars.iter().find(|bar| bar.dataset_index == 1 && bar.datum_index == 0).unwrap().clone();
+        test.pointer_move(bar.anchor + vec2(0., 2.));
+        test.advance_time(1.0);
+        assert!(test.app.chart.tooltip_visible);
+        assert_eq!(test.app.chart.tooltip.dataset, 1);
+        assert_eq!(test.app.chart.tooltip.value, 30.);
+
+        test.app.chart_type = ChartType::Doughnut;
+        test.app.stacked = false;
+        test.cx.request_draw();
+        test.next_frame();
+
+        let slice = test.app.chart.slices[1].clone();
+        test.pointer_move(slice.anchor());
+        test.advance_time(1.0);
+        assert!(test.app.chart.tooltip_visible);
+        assert_eq!(test.app.chart.tooltip.value, 30.);
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/chart_doughnut_tooltip.png"));
+    }
+
+    #[test]
+    fn test_chart_plugins() {
+        let mut test = AppTest::<ChartTestApp>::new_in_window(vec2(240., 160.));
+        let legend = Arc::new(RwLock::new(ChartLegend::default()));
+        let annotations = Arc::new(RwLock::new(ChartAnnotations {
+            annotations: vec![
+                ChartAnnotation {
+                    kind: ChartAnnotationKind::HorizontalLine { y: 25. },
+                    label: "limit".to_string(),
+                    ..ChartAnnotation::default()
+                },
+                ChartAnnotation {
+                    kind: ChartAnnotationKind::VerticalRange { min_x: 1., max_x: 2. },
+                    color: vec4(0., 0., 1., 0.2),
+                    ..ChartAnnotation::default()
+                },
+            ],
+        }));
+        let crosshair = Arc::new(RwLock::new(ChartCrosshair::default()));
+        test.app.chart.plugins = vec![annotations, legend.clone(), crosshair.clone()];
+        test.app.stacked = true;
+        test.cx.request_draw();
+        test.next_frame();
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/chart_plugins.png"));
+
+        // Clicking on the first dataset in the legend hides it.
+        let rect = legend.read().unwrap().items[0].1;
+        test.click(rect.pos + rect.size * 0.5);
+        test.next_frame();
+        assert!(legend.read().unwrap().is_hidden(0));
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/chart_plugins_hidden.png"));
+
+        // Hovering only finds the visible dataset, and moves the crosshair.
+        let pos = test.app.chart.normalize_data_point(vec2(1., 30.));
+        test.pointer_move(pos);
+        test.advance_time(1.0);
+        assert!(test.app.chart.tooltip_visible);
+        assert_eq!(test.app.chart.tooltip.dataset, 1);
+        assert!((crosshair.read().unwrap().x().unwrap() - 1.).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_chart_stream() {
+        let mut test = AppTest::<ChartTestApp>::new_in_window(vec2(200., 120.));
+        let mut stream = ChartStream::new();
+        for i in 0..10_000 {
+            stream.push_value(((i * 7919) % 1013) as f32);
+        }
+        test.app.stream = Some(stream);
+
+        for chart_type in [ChartType::Line, ChartType::Scatter] {
+            test.app.chart_type = chart_type;
+            test.cx.request_draw();
+            test.next_frame();
+            // Lines get decimated, but scatter plots show every point.
+            let point_count = test.app.chart.point_datum_indices[0].len();
+            assert_eq!(point_count == 10_000, chart_type == ChartType::Scatter);
+
+            // Hovering finds the index of the point in the stream, not in the decimated points.
+            let points = test.app.stream.as_ref().unwrap().points();
+            for x in [30., 100., 170.] {
+                let cursor = vec2(x, 50.);
+                let cursor_value = test.app.chart.denormalize_data_point(cursor);
+                let element = test.app.chart.get_element_at(&mut test.cx, cursor, cursor_value).unwrap();
+                assert_eq!(points[element.datum_index], element.data_point);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chart_logarithmic_tooltip() {
+        let mut test = AppTest::<ChartTestApp>::new_in_window(vec2(200., 120.));
+        test.app.data = vec![0., 10., 100., 1000.];
+        test.app.logarithmic = true;
+        test.cx.request_draw();
+        test.next_frame();
+
+        // Zero can't be shown on a logarithmic axis, but the points after it keep their datum index.
+        assert_eq!(test.app.chart.point_datum_indices[0], vec![1, 2, 3]);
+        let cursor = test.app.chart.normalize_data_point(vec2(2., 100.));
+        let cursor_value = test.app.chart.denormalize_data_point(cursor);
+        let element = test.app.chart.get_element_at(&mut test.cx, cursor, cursor_value).unwrap();
+        assert_eq!((element.datum_index, element.data_point), (2, vec2(2., 100.)));
+    }
 }
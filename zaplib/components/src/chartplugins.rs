@@ -0,0 +1,316 @@
+//! Plugins for [`Chart`], see [`ChartPlugin`].
+
+use std::collections::HashSet;
+
+use crate::chartshapes::*;
+use crate::*;
+use zaplib::*;
+
+/// Where a [`ChartLegend`] is drawn inside of the chart boundaries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartLegendPosition {
+    TopLeft,
+    TopRight,
+}
+
+impl Default for ChartLegendPosition {
+    fn default() -> Self {
+        ChartLegendPosition::TopRight
+    }
+}
+
+/// Lists the datasets with their colors. Clicking on a dataset hides it, and clicking on it again shows it.
+#[derive(Default)]
+pub struct ChartLegend {
+    pub position: ChartLegendPosition,
+    hidden: HashSet<usize>,
+    /// Dataset indices with the rects that can be clicked to toggle them, as of the last draw.
+    pub(crate) items: Vec<(usize, Rect)>,
+}
+
+impl ChartLegend {
+    pub fn is_hidden(&self, dataset_index: usize) -> bool {
+        self.hidden.contains(&dataset_index)
+    }
+
+    pub fn set_hidden(&mut self, dataset_index: usize, hidden: bool) {
+        if hidden {
+            self.hidden.insert(dataset_index);
+        } else {
+            self.hidden.remove(&dataset_index);
+        }
+    }
+
+    fn color(config: &ChartConfig, dataset: &ChartDataset) -> Vec4 {
+        match config.chart_type {
+            ChartType::Line => dataset.border_color,
+            ChartType::Scatter => dataset.point_background_color,
+            ChartType::Bar | ChartType::Area | ChartType::Pie | ChartType::Doughnut => dataset.background_color,
+        }
+    }
+}
+
+impl ChartPlugin for ChartLegend {
+    fn handle(&mut self, cx: &mut Cx, event: &ChartEvent, _chart: &Chart) -> bool {
+        match event {
+            ChartEvent::PointerDown { cursor, .. } => {
+                if let Some((dataset_index, _)) = self.items.iter().find(|(_, rect)| rect.contains(*cursor)) {
+                    let dataset_index = *dataset_index;
+                    self.set_hidden(dataset_index, !self.is_hidden(dataset_index));
+                    cx.request_draw();
+                    return true;
+                }
+                false
+            }
+            // Don't show tooltips for data behind the legend.
+            ChartEvent::PointerHover { cursor, .. } => self.items.iter().any(|(_, rect)| rect.contains(*cursor)),
+            _ => false,
+        }
+    }
+
+    fn draw(&mut self, cx: &mut Cx, config: &ChartConfig, chart: &Chart) {
+        let bounds = chart.bounds();
+        let char_width = TextIns::get_monospace_base(cx, &TEXT_STYLE_MONO).x * TEXT_STYLE_MONO.font_size;
+        let padding = 5.;
+        let swatch_size = 10.;
+        let item_height = 14.;
+        let item_spacing = 10.;
+
+        // Lay out the items in rows that fit in the chart.
+        let mut items = vec![];
+        let mut pos = vec2(0., 0.);
+        let mut width: f32 = 0.;
+        for (dataset_index, dataset) in config.datasets.iter().enumerate() {
+            let item_width = swatch_size + 4. + dataset.label.chars().count() as f32 * char_width;
+            if pos.x > 0. && pos.x + item_width > bounds.size.x - 2. * padding {
+                pos = vec2(0., pos.y + item_height);
+            }
+            items.push((dataset_index, Rect { pos, size: vec2(item_width, item_height) }));
+            width = width.max(pos.x + item_width);
+            pos.x += item_width + item_spacing;
+        }
+        if items.is_empty() {
+            self.items = vec![];
+            return;
+        }
+
+        let size = vec2(width, pos.y + item_height) + vec2(2. * padding, 2. * padding);
+        let origin = match self.position {
+            ChartLegendPosition::TopLeft => bounds.pos,
+            ChartLegendPosition::TopRight => vec2(bounds.pos.x + bounds.size.x - size.x, bounds.pos.y),
+        };
+        self.items = items.into_iter().map(|(index, rect)| (index, rect.translate(origin + vec2(padding, padding)))).collect();
+
+        let mut background_color = config.style.background_color;
+        background_color.w = 0.8;
+        let mut rects = vec![ChartRectIns { base: QuadIns::from_rect(Rect { pos: origin, size }), color: background_color }];
+        let mut labels = vec![];
+        let mut strikethroughs = vec![];
+        for (dataset_index, rect) in &self.items {
+            let dataset = &config.datasets[*dataset_index];
+            let alpha = if dataset.hidden { 0.4 } else { 1. };
+
+            let mut color = Self::color(config, dataset);
+            color.w *= alpha;
+            let swatch_pos = rect.pos + vec2(0., 0.5 * (item_height - swatch_size));
+            rects.push(ChartRectIns {
+                base: QuadIns::from_rect(Rect { pos: swatch_pos, size: vec2(swatch_size, swatch_size) }),
+                color,
+            });
+
+            let mut label_color = config.style.label_color;
+            label_color.w *= alpha;
+            let label_pos = rect.pos + vec2(swatch_size + 4., 0.5 * item_height);
+            labels.push((&dataset.label, label_pos, label_color));
+
+            if dataset.hidden {
+                strikethroughs.push(DrawLines3dInstance::from_segment(
+                    vec3(label_pos.x, label_pos.y, 0.),
+                    vec3(rect.pos.x + rect.size.x, label_pos.y, 0.),
+                    label_color,
+                    1.,
+                ));
+            }
+        }
+        // Draw the labels after the rects, so the background doesn't cover them.
+        ChartRectIns::draw(cx, &rects);
+        for (label, pos, color) in labels {
+            let props = TextInsProps {
+                text_style: TEXT_STYLE_MONO,
+                color,
+                position_anchoring: TEXT_ANCHOR_CENTER_V,
+                ..TextInsProps::DEFAULT
+            };
+            TextIns::draw_str(cx, label, pos, &props);
+        }
+        DrawLines3d::draw(cx, &strikethroughs, Default::default());
+    }
+
+    fn is_dataset_hidden(&self, dataset_index: usize) -> bool {
+        self.is_hidden(dataset_index)
+    }
+}
+
+/// What a [`ChartAnnotation`] marks, in data coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartAnnotationKind {
+    /// A line across the whole height of the chart.
+    VerticalLine { x: f32 },
+    /// A line across the whole width of the chart.
+    HorizontalLine { y: f32 },
+    /// A shaded range of x values, across the whole height of the chart.
+    VerticalRange { min_x: f32, max_x: f32 },
+    /// A shaded range of y values, across the whole width of the chart.
+    HorizontalRange { min_y: f32, max_y: f32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct ChartAnnotation {
+    pub kind: ChartAnnotationKind,
+    /// Drawn at the top left of the annotation. Can be empty.
+    pub label: String,
+    /// Color of lines and their labels. Ranges are filled with this color, so give it some transparency.
+    pub color: Vec4,
+    /// The key in [`ChartConfig::scales`] of the y axis that y values are on.
+    pub y_axis_id: String,
+}
+
+impl Default for ChartAnnotation {
+    fn default() -> Self {
+        Self {
+            kind: ChartAnnotationKind::VerticalLine { x: 0. },
+            label: String::new(),
+            color: COLOR_RED,
+            y_axis_id: "y".to_string(),
+        }
+    }
+}
+
+/// Draws lines and shaded ranges with labels on top of a chart, e.g. to mark thresholds or events.
+#[derive(Default)]
+pub struct ChartAnnotations {
+    pub annotations: Vec<ChartAnnotation>,
+}
+
+impl ChartPlugin for ChartAnnotations {
+    fn draw(&mut self, cx: &mut Cx, config: &ChartConfig, chart: &Chart) {
+        if matches!(config.chart_type, ChartType::Pie | ChartType::Doughnut) {
+            return;
+        }
+
+        let bounds = chart.bounds();
+        let mut rects = vec![];
+        let mut lines = vec![];
+        let mut labels = vec![];
+        for annotation in &self.annotations {
+            let to_screen = |x: f32, y: f32| chart.normalize_data_point_on_axis(vec2(x, y), &annotation.y_axis_id);
+            let (a, b) = match annotation.kind {
+                ChartAnnotationKind::VerticalLine { x } => {
+                    let x = to_screen(x, 0.).x;
+                    (vec2(x, bounds.pos.y), vec2(x, bounds.pos.y + bounds.size.y))
+                }
+                ChartAnnotationKind::HorizontalLine { y } => {
+                    let y = to_screen(0., y).y;
+                    (vec2(bounds.pos.x, y), vec2(bounds.pos.x + bounds.size.x, y))
+                }
+                ChartAnnotationKind::VerticalRange { min_x, max_x } => {
+                    (vec2(to_screen(min_x, 0.).x, bounds.pos.y), vec2(to_screen(max_x, 0.).x, bounds.pos.y + bounds.size.y))
+                }
+                ChartAnnotationKind::HorizontalRange { min_y, max_y } => {
+                    (vec2(bounds.pos.x, to_screen(0., min_y).y), vec2(bounds.pos.x + bounds.size.x, to_screen(0., max_y).y))
+                }
+            };
+            if !(a.x.is_finite() && a.y.is_finite() && b.x.is_finite() && b.y.is_finite()) {
+                continue;
+            }
+            let rect = match Chart::clip_rect(Rect { pos: a.min(&b), size: a.max(&b) - a.min(&b) }, bounds) {
+                Some(rect) => rect,
+                None => continue,
+            };
+
+            let is_line =
+                matches!(annotation.kind, ChartAnnotationKind::VerticalLine { .. } | ChartAnnotationKind::HorizontalLine { .. });
+            let mut label_color = annotation.color;
+            if is_line {
+                let end = rect.pos + rect.size;
+                lines.push(DrawLines3dInstance::from_segment(
+                    vec3(rect.pos.x, rect.pos.y, 0.),
+                    vec3(end.x, end.y, 0.),
+                    annotation.color,
+                    1.,
+                ));
+            } else {
+                rects.push(ChartRectIns { base: QuadIns::from_rect(rect), color: annotation.color });
+                label_color.w = 1.;
+            }
+
+            if !annotation.label.is_empty() {
+                labels.push((rect.pos + vec2(4., 4.), &annotation.label, label_color));
+            }
+        }
+
+        ChartRectIns::draw(cx, &rects);
+        DrawLines3d::draw(cx, &lines, Default::default());
+        for (pos, label, color) in labels {
+            TextIns::draw_str(cx, label, pos, &TextInsProps { text_style: TEXT_STYLE_MONO, color, ..TextInsProps::DEFAULT });
+        }
+    }
+}
+
+/// Draws a vertical line at the x value that is being hovered.
+///
+/// To synchronize several charts, add the same `Arc` to the [`Chart::plugins`] of each of them. Hovering one of
+/// them then draws the line at the same x value in all of them, so their x axes should use the same units.
+pub struct ChartCrosshair {
+    pub color: Vec4,
+    x: Option<f32>,
+}
+
+impl Default for ChartCrosshair {
+    fn default() -> Self {
+        Self { color: COLOR_GRAY, x: None }
+    }
+}
+
+impl ChartCrosshair {
+    /// The x value that is being hovered in any of the charts, if any.
+    pub fn x(&self) -> Option<f32> {
+        self.x
+    }
+
+    /// Show the crosshair at a specific x value, e.g. to follow playback of recorded data.
+    pub fn set_x(&mut self, x: Option<f32>) {
+        self.x = x;
+    }
+}
+
+impl ChartPlugin for ChartCrosshair {
+    fn handle(&mut self, _cx: &mut Cx, event: &ChartEvent, _chart: &Chart) -> bool {
+        match event {
+            ChartEvent::PointerHover { cursor_value, .. } => self.x = Some(cursor_value.x),
+            ChartEvent::PointerOut => self.x = None,
+            _ => (),
+        }
+        false
+    }
+
+    fn draw(&mut self, cx: &mut Cx, config: &ChartConfig, chart: &Chart) {
+        if matches!(config.chart_type, ChartType::Pie | ChartType::Doughnut) {
+            return;
+        }
+
+        if let Some(x) = self.x {
+            let bounds = chart.bounds();
+            let x = chart.normalize_data_point(vec2(x, 0.)).x;
+            if x >= bounds.pos.x && x <= bounds.pos.x + bounds.size.x {
+                let line = DrawLines3dInstance::from_segment(
+                    vec3(x, bounds.pos.y, 0.),
+                    vec3(x, bounds.pos.y + bounds.size.y, 0.),
+                    self.color,
+                    1.,
+                );
+                DrawLines3d::draw(cx, &[line], Default::default());
+            }
+        }
+    }
+}
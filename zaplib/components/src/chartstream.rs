@@ -0,0 +1,219 @@
+use zaplib::*;
+
+/// Number of blocks of one level of the min/max index that are summarized by a single block of the next level.
+const BRANCHING: usize = 16;
+
+/// An owned, append-only dataset for [`crate::Chart`], for series that are too large to draw every point of,
+/// e.g. live telemetry with millions of samples.
+///
+/// Points have to be appended in increasing x order. Use [`crate::ChartData::from_stream`] to draw it; only
+/// the points that are visible in the current zoom/pan rect get drawn, and those are decimated to the first,
+/// minimum, maximum, and last point of every pixel column, which draws the same line as drawing every point.
+///
+/// To make that fast regardless of how many points are visible, we keep an index of the minimum and maximum
+/// for blocks of [`BRANCHING`] points, blocks of [`BRANCHING`] of those blocks, and so on. Appending a point
+/// only updates one block on every level.
+#[derive(Debug, Clone, Default)]
+pub struct ChartStream {
+    points: Vec<Vec2>,
+    /// Level `l` contains the indices of the points with the minimum and maximum y value for each block of
+    /// `BRANCHING^(l + 1)` points. A level only exists once there are more points than fit in one of its blocks.
+    levels: Vec<Vec<(usize, usize)>>,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl ChartStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+
+    /// The lower and upper bounds of all points, if there are any.
+    pub fn min_max(&self) -> Option<(Vec2, Vec2)> {
+        if self.points.is_empty() {
+            None
+        } else {
+            Some((self.min, self.max))
+        }
+    }
+
+    /// Append a point. Panics if `point.x` is smaller than the x of the last point.
+    pub fn push(&mut self, point: Vec2) {
+        if let Some(last) = self.points.last() {
+            assert!(point.x >= last.x, "ChartStream points must be appended in increasing x order");
+            self.min = self.min.min(&point);
+            self.max = self.max.max(&point);
+        } else {
+            self.min = point;
+            self.max = point;
+        }
+
+        let index = self.points.len();
+        self.points.push(point);
+
+        for level in 0..self.levels.len() {
+            let block = index / Self::block_size(level);
+            if block < self.levels[level].len() {
+                let min_max = self.levels[level][block];
+                self.levels[level][block] = self.combine(min_max, (index, index));
+            } else {
+                self.levels[level].push((index, index));
+            }
+        }
+
+        // Add a level once a second block would be needed for it, computing its blocks from the levels below.
+        let level = self.levels.len();
+        if self.points.len() > Self::block_size(level) {
+            let size = Self::block_size(level);
+            let blocks = (0..self.points.len())
+                .step_by(size)
+                .map(|start| self.range_min_max(start, self.points.len().min(start + size)))
+                .collect();
+            self.levels.push(blocks);
+        }
+    }
+
+    /// Append a value, using the number of points so far as its x.
+    pub fn push_value(&mut self, value: f32) {
+        self.push(vec2(self.points.len() as f32, value));
+    }
+
+    pub fn extend_from_slice(&mut self, points: &[Vec2]) {
+        for point in points {
+            self.push(*point);
+        }
+    }
+
+    fn block_size(level: usize) -> usize {
+        BRANCHING.pow(level as u32 + 1)
+    }
+
+    fn combine(&self, a: (usize, usize), b: (usize, usize)) -> (usize, usize) {
+        let min = if self.points[b.0].y < self.points[a.0].y { b.0 } else { a.0 };
+        let max = if self.points[b.1].y > self.points[a.1].y { b.1 } else { a.1 };
+        (min, max)
+    }
+
+    /// Indices of the points with the minimum and maximum y value in `start..end`, which must not be empty.
+    fn range_min_max(&self, start: usize, end: usize) -> (usize, usize) {
+        let mut ret = (start, start);
+        let mut index = start;
+        while index < end {
+            // Use the biggest block that starts here and fits in the range, or else a single point.
+            let block = (0..self.levels.len())
+                .rev()
+                .map(|level| (level, Self::block_size(level)))
+                .find(|(_, size)| index % size == 0 && index + size <= end);
+            match block {
+                Some((level, size)) => {
+                    ret = self.combine(ret, self.levels[level][index / size]);
+                    index += size;
+                }
+                None => {
+                    ret = self.combine(ret, (index, index));
+                    index += 1;
+                }
+            }
+        }
+        ret
+    }
+
+    /// The points to draw for x values between `min_x` and `max_x` on a chart that is `columns` pixels wide.
+    ///
+    /// This includes the points right outside of the range, so lines still extend to the edges of the chart.
+    /// If there are more than a few points per column, only the first, minimum, maximum, and last point of
+    /// every column are returned. Every point comes with its index in [`ChartStream::points`].
+    pub fn decimate(&self, min_x: f32, max_x: f32, columns: usize) -> Vec<(usize, Vec2)> {
+        let start = self.points.partition_point(|p| p.x < min_x).saturating_sub(1);
+        let end = (self.points.partition_point(|p| p.x <= max_x) + 1).min(self.points.len());
+        if end <= start || end - start <= 4 * columns {
+            return (start.min(end)..end).map(|index| (index, self.points[index])).collect();
+        }
+
+        let column_width = (max_x - min_x) / columns as f32;
+        let mut ret = Vec::with_capacity(4 * columns + 2);
+        let mut column_start = start;
+        for column in 1..=columns {
+            let column_end = if column == columns {
+                end
+            } else {
+                let column_max_x = min_x + column as f32 * column_width;
+                column_start + self.points[column_start..end].partition_point(|p| p.x < column_max_x)
+            };
+            if column_end > column_start {
+                let (min, max) = self.range_min_max(column_start, column_end);
+                let mut indices = [column_start, min, max, column_end - 1];
+                indices.sort_unstable();
+                let mut last = None;
+                for index in indices {
+                    if last != Some(index) {
+                        ret.push((index, self.points[index]));
+                        last = Some(index);
+                    }
+                }
+            }
+            column_start = column_end;
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zaplib::*;
+
+    use crate::*;
+
+    fn test_stream(len: usize) -> ChartStream {
+        let mut stream = ChartStream::new();
+        for i in 0..len {
+            // Deterministic but irregular values.
+            stream.push_value(((i * 7919) % 1013) as f32 - 500.);
+        }
+        stream
+    }
+
+    #[test]
+    fn test_range_min_max() {
+        let stream = test_stream(5000);
+        assert_eq!(stream.levels.len(), 3);
+        for (start, end) in [(0, 5000), (3, 17), (15, 4097), (250, 251), (4095, 5000), (1234, 4321)] {
+            let (min, max) = stream.range_min_max(start, end);
+            let points = &stream.points()[start..end];
+            assert_eq!(stream.points()[min].y, points.iter().map(|p| p.y).fold(f32::INFINITY, f32::min));
+            assert_eq!(stream.points()[max].y, points.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max));
+        }
+        assert_eq!(stream.min_max(), Some((vec2(0., -500.), vec2(4999., 512.))));
+    }
+
+    #[test]
+    fn test_decimate() {
+        let stream = test_stream(100_000);
+
+        // Few points are returned as is, including the ones right outside of the range.
+        assert_eq!(stream.decimate(10., 20., 100), (9..22).map(|index| (index, stream.points()[index])).collect::<Vec<_>>());
+
+        let decimated = stream.decimate(1000.5, 90000.5, 100);
+        assert!(decimated.len() <= 400);
+        assert!(decimated.iter().all(|(index, p)| stream.points()[*index] == *p));
+        let points: Vec<Vec2> = decimated.into_iter().map(|(_, p)| p).collect();
+        assert_eq!(points.first(), Some(&vec2(1000., stream.points()[1000].y)));
+        assert_eq!(points.last(), Some(&vec2(90001., stream.points()[90001].y)));
+        assert!(points.windows(2).all(|w| w[0].x < w[1].x));
+        // The extremes of the range are kept.
+        assert!(points.iter().any(|p| p.y == -500.));
+        assert!(points.iter().any(|p| p.y == 512.));
+    }
+}
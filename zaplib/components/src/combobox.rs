@@ -0,0 +1,266 @@
+//! A [`TextInput`] with a list of suggestions that is filtered as you type.
+
+use crate::*;
+use zaplib::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComboBoxEvent {
+    None,
+    /// The text was edited; see [`ComboBox::value`].
+    Changed,
+    /// An option was picked from the list, as an index into [`ComboBox::options`]. Its label is now the value.
+    Selected {
+        index: usize,
+    },
+}
+
+const COLOR_FRAME: Vec4 = vec4(0.2, 0.2, 0.2, 1.);
+const COLOR_FRAME_FOCUS: Vec4 = vec4(0.22, 0.27, 0.34, 1.);
+
+/// A [`TextInput`] that shows the [`ComboBox::options`] containing the typed text (ignoring case) in a [`Popover`].
+///
+/// The key focus stays on the [`TextInput`] the whole time, also when clicking on an option, so you can keep
+/// typing. While the list is open, the up and down arrow keys (and Page Up and Page Down) move through it, Return
+/// picks the highlighted option, and Escape closes it. When the list is closed the up and down arrow keys open it.
+///
+/// Like [`Popover::handle`] says, call [`ComboBox::handle`] before the event handlers of whatever
+/// is drawn underneath the list, so that clicks on the list don't leak through.
+pub struct ComboBox {
+    component_id: ComponentId,
+    pub options: Vec<String>,
+    pub width: f32,
+    text_input: TextInput,
+    frame: Background,
+    /// Indices into [`ComboBox::options`] of the options that match the current text.
+    filtered: Vec<usize>,
+    is_open: bool,
+    list: OptionList,
+}
+
+impl Default for ComboBox {
+    fn default() -> Self {
+        Self {
+            component_id: Default::default(),
+            options: vec![],
+            width: 160.,
+            text_input: TextInput::new(TextInputOptions::default()),
+            frame: Background::default().with_radius(2.5),
+            filtered: vec![],
+            is_open: false,
+            list: OptionList::default(),
+        }
+    }
+}
+
+impl ComboBox {
+    #[must_use]
+    pub fn with_options(self, options: Vec<String>) -> Self {
+        Self { options, ..self }
+    }
+
+    /// Text to show when nothing has been typed, see [`TextInput::empty_message`].
+    #[must_use]
+    pub fn with_placeholder(mut self, placeholder: &str) -> Self {
+        self.text_input.empty_message = placeholder.to_string();
+        self
+    }
+
+    #[must_use]
+    pub fn with_width(self, width: f32) -> Self {
+        Self { width, ..self }
+    }
+
+    pub fn value(&self) -> String {
+        self.text_input.get_value()
+    }
+
+    /// Replace the text, without opening the list.
+    pub fn set_value(&mut self, cx: &mut Cx, value: &str) {
+        self.text_input.set_value(cx, value);
+        let end = self.text_input.text_buffer.calc_char_count();
+        self.text_input.text_editor.cursors.clear_and_set_last_cursor_head_and_tail(end, end, &self.text_input.text_buffer);
+    }
+
+    /// Indices into [`ComboBox::options`] of the options that are shown in the list.
+    pub fn filtered_options(&self) -> &[usize] {
+        &self.filtered
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    pub fn set_key_focus(&mut self, cx: &mut Cx) {
+        self.text_input.text_editor.set_key_focus(cx);
+    }
+
+    /// Open the list with the options that match the current text, if there are any.
+    pub fn open(&mut self, cx: &mut Cx) {
+        let value = self.value().to_lowercase();
+        self.filtered = (0..self.options.len()).filter(|&index| self.options[index].to_lowercase().contains(&value)).collect();
+        self.is_open = !self.filtered.is_empty();
+        self.list.reset(None, self.filtered.len());
+        cx.request_draw();
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        if self.is_open {
+            self.is_open = false;
+            cx.request_draw();
+        }
+    }
+
+    fn select(&mut self, cx: &mut Cx, index: usize) -> ComboBoxEvent {
+        let value = self.options[index].clone();
+        self.set_value(cx, &value);
+        self.close(cx);
+        ComboBoxEvent::Selected { index }
+    }
+
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> ComboBoxEvent {
+        if self.is_open {
+            if let OptionListEvent::Clicked(index) = self.list.handle(cx, event, self.filtered.len()) {
+                self.set_key_focus(cx);
+                return self.select(cx, self.filtered[index]);
+            }
+            if let Event::PointerDown(pe) = event {
+                let on_input = self.frame.area().get_rect_for_first_instance(cx).map_or(false, |rect| rect.contains(pe.abs));
+                if !on_input && !self.list.contains(cx, pe.abs) {
+                    self.close(cx);
+                }
+            }
+        }
+
+        // Keys for the list take precedence over the ones for moving the cursor in the text.
+        if let Event::KeyDown(ke) = event {
+            if self.text_input.text_editor.has_key_focus(cx) {
+                match ke.key_code {
+                    KeyCode::ArrowDown | KeyCode::ArrowUp if !self.is_open => {
+                        self.open(cx);
+                        return ComboBoxEvent::None;
+                    }
+                    KeyCode::Return if self.is_open && self.list.highlighted.is_some() => {
+                        let index = self.filtered[self.list.highlighted.unwrap()];
+                        return self.select(cx, index);
+                    }
+                    KeyCode::Escape if self.is_open => {
+                        self.close(cx);
+                        return ComboBoxEvent::None;
+                    }
+                    KeyCode::ArrowDown | KeyCode::ArrowUp | KeyCode::PageDown | KeyCode::PageUp if self.is_open => {
+                        self.list.handle_navigation_key(cx, ke.key_code, self.filtered.len());
+                        return ComboBoxEvent::None;
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        let text_input_event = self.text_input.handle(cx, event);
+
+        // The text input is only as wide as its text, so make clicks anywhere in the frame focus it.
+        if let Event::PointerDown(_) =
+            event.hits_pointer(cx, self.component_id, self.frame.area().get_rect_for_first_instance(cx))
+        {
+            self.set_key_focus(cx);
+        }
+
+        match text_input_event {
+            TextEditorEvent::Change => {
+                self.open(cx);
+                // Highlight the best match, so that Return picks it.
+                self.list.highlight(0, self.filtered.len());
+                ComboBoxEvent::Changed
+            }
+            TextEditorEvent::KeyFocus | TextEditorEvent::KeyFocusLost => {
+                self.close(cx);
+                cx.request_draw();
+                ComboBoxEvent::None
+            }
+            _ => ComboBoxEvent::None,
+        }
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx) {
+        let color = if self.text_input.text_editor.has_key_focus(cx) { COLOR_FRAME_FOCUS } else { COLOR_FRAME };
+        self.frame.begin_draw(cx, Width::Fix(self.width), Height::Compute, color);
+        self.text_input.draw(cx);
+        self.frame.end_draw(cx);
+
+        if self.is_open {
+            if let Some(anchor) = self.frame.area().get_rect_for_first_instance(cx) {
+                let labels: Vec<&str> = self.filtered.iter().map(|&index| self.options[index].as_str()).collect();
+                self.list.draw(cx, anchor, &labels, None);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use zaplib::*;
+
+    use crate::*;
+
+    struct ComboBoxTestApp {
+        combo_box: ComboBox,
+        events: Vec<ComboBoxEvent>,
+    }
+
+    impl MainApp for ComboBoxTestApp {
+        fn new(_cx: &mut Cx) -> Self {
+            let options = ["Apple", "Apricot", "Banana", "Blueberry", "Cherry"].iter().map(|s| s.to_string()).collect();
+            Self { combo_box: ComboBox::default().with_options(options).with_placeholder("Fruit"), events: vec![] }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            let event = self.combo_box.handle(cx, event);
+            if event != ComboBoxEvent::None {
+                self.events.push(event);
+            }
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            cx.begin_padding_box(Padding::all(10.));
+            self.combo_box.draw(cx);
+            cx.end_padding_box();
+        }
+    }
+
+    #[test]
+    fn test_combo_box() {
+        let mut test = AppTest::<ComboBoxTestApp>::new_in_window(vec2(200., 200.));
+
+        // Typing filters the options, and highlights the first match.
+        test.click(vec2(50., 20.));
+        test.text_input("b");
+        assert!(test.app.combo_box.is_open());
+        assert_eq!(test.app.combo_box.filtered_options(), &[2, 3]);
+        test.text_input("l");
+        assert_eq!(test.app.combo_box.filtered_options(), &[3]);
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/combo_box_filtered.png"));
+        test.key_press(KeyCode::Return);
+        assert!(!test.app.combo_box.is_open());
+        assert_eq!(test.app.combo_box.value(), "Blueberry");
+        assert_eq!(test.app.events.last(), Some(&ComboBoxEvent::Selected { index: 3 }));
+
+        // The arrow keys open the list and move through it.
+        test.app.combo_box.set_value(&mut test.cx, "AP");
+        test.key_press(KeyCode::ArrowDown);
+        assert_eq!(test.app.combo_box.filtered_options(), &[0, 1]);
+        test.key_press(KeyCode::ArrowDown);
+        test.key_press(KeyCode::ArrowDown);
+        test.key_press(KeyCode::Return);
+        assert_eq!(test.app.combo_box.value(), "Apricot");
+
+        // Clicking an option keeps the key focus in the text input.
+        test.key_press(KeyCode::ArrowDown);
+        test.click(vec2(50., 50.));
+        assert_eq!(test.app.events.last(), Some(&ComboBoxEvent::Selected { index: 1 }));
+        test.text_input("s");
+        assert_eq!(test.app.combo_box.value(), "Apricots");
+        assert!(!test.app.combo_box.is_open());
+        test.key_press(KeyCode::Escape);
+        assert!(!test.app.combo_box.is_open());
+    }
+}
@@ -0,0 +1,567 @@
+//! Drawing [`Menu`]s inside of the window, for platforms that don't have native menus (see [`Cx::update_menu`]).
+//! Used by [`ContextMenu`] and [`crate::MenuBar`].
+
+use crate::*;
+use zaplib::*;
+
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+struct ArrowIns {
+    base: QuadIns,
+    color: Vec4,
+}
+
+static ARROW_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            instance color: vec4;
+
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * 10.);
+                df.triangle(vec2(3., 2.), vec2(7., 5.), vec2(3., 8.));
+                df.fill(color);
+                return df.result;
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+const ITEM_HEIGHT: f32 = 22.;
+const LINE_HEIGHT: f32 = 9.;
+const MENU_PADDING: f32 = 4.;
+const ITEM_PADDING_X: f32 = 12.;
+const SHORTCUT_GAP: f32 = 24.;
+const ARROW_SIZE: f32 = 10.;
+const MIN_MENU_WIDTH: f32 = 120.;
+
+const COLOR_MENU: Vec4 = vec4(0.17, 0.17, 0.17, 1.);
+const COLOR_HIGHLIGHT: Vec4 = vec4(0.07, 0.27, 0.43, 1.);
+const COLOR_SEPARATOR: Vec4 = vec4(0.3, 0.3, 0.3, 1.);
+const COLOR_TEXT: Vec4 = vec4(0.85, 0.85, 0.85, 1.);
+const COLOR_TEXT_DISABLED: Vec4 = vec4(0.45, 0.45, 0.45, 1.);
+const COLOR_SHORTCUT: Vec4 = vec4(0.6, 0.6, 0.6, 1.);
+
+/// The items of a [`Menu::Main`] or [`Menu::Sub`].
+pub(crate) fn menu_items(menu: &Menu) -> &[Menu] {
+    match menu {
+        Menu::Main { items } | Menu::Sub { items, .. } => items,
+        _ => &[],
+    }
+}
+
+/// The items of the [`Menu::Sub`] found by following `path` (indices of [`Menu::Sub`]s) from `root`.
+fn items_at<'a>(root: &'a [Menu], path: &[usize]) -> &'a [Menu] {
+    path.iter().fold(root, |items, &index| items.get(index).map_or(&[], menu_items))
+}
+
+fn is_selectable(cx: &Cx, item: &Menu) -> bool {
+    match item {
+        Menu::Item { command, .. } => command.is_enabled(cx),
+        Menu::Sub { .. } => true,
+        _ => false,
+    }
+}
+
+/// The next item after `from` (or the first item if `None`) that can be highlighted, wrapping around.
+fn next_selectable(cx: &Cx, items: &[Menu], from: Option<usize>, forward: bool) -> Option<usize> {
+    let len = items.len();
+    (1..=len)
+        .map(|offset| match (from, forward) {
+            (Some(from), true) => (from + offset) % len,
+            (Some(from), false) => (from + len - offset) % len,
+            (None, true) => offset - 1,
+            (None, false) => len - offset,
+        })
+        .find(|&index| is_selectable(cx, &items[index]))
+}
+
+/// Label for the keyboard shortcut of a command, e.g. "Ctrl+Shift+Z". See [`CommandId::set_key`].
+pub(crate) fn shortcut_label(cx: &Cx, command: CommandId) -> Option<String> {
+    let (key_code, shift) = command.get_key(cx)?;
+    let key = match key_code {
+        KeyCode::Backtick => "`".to_string(),
+        KeyCode::Minus => "-".to_string(),
+        KeyCode::Equals => "=".to_string(),
+        KeyCode::LBracket => "[".to_string(),
+        KeyCode::RBracket => "]".to_string(),
+        KeyCode::Semicolon => ";".to_string(),
+        KeyCode::Quote => "'".to_string(),
+        KeyCode::Backslash => "\\".to_string(),
+        KeyCode::Comma => ",".to_string(),
+        KeyCode::Period => ".".to_string(),
+        KeyCode::Slash => "/".to_string(),
+        _ => format!("{:?}", key_code).trim_start_matches("Key").to_string(),
+    };
+    let modifier = if matches!(cx.platform_type, PlatformType::OSX) { "Cmd" } else { "Ctrl" };
+    Some(format!("{}{}+{}", modifier, if shift { "+Shift" } else { "" }, key))
+}
+
+pub(crate) enum MenuPopupsEvent {
+    None,
+    /// All menus were closed, e.g. because an item was picked (which sends its [`Event::Command`]) or because
+    /// Escape was pressed.
+    Closed,
+    /// The left arrow key was pressed in the top-level menu.
+    PrevMenu,
+    /// The right arrow key was pressed on an item that doesn't have a submenu.
+    NextMenu,
+}
+
+/// A single open menu: the top-level one or one of its submenus.
+#[derive(Default)]
+struct MenuLevel {
+    component_id: ComponentId,
+    popover: Popover,
+    /// Indices of the [`Menu::Sub`]s that lead to this menu, starting from the top-level menu.
+    path: Vec<usize>,
+    highlighted: Option<usize>,
+    /// The [`Rect`] of every item, in window coordinates, as of the last draw.
+    item_rects: Vec<Rect>,
+}
+
+impl MenuLevel {
+    fn item_at(&self, pos: Vec2) -> Option<usize> {
+        self.item_rects.iter().position(|rect| rect.contains(pos))
+    }
+
+    fn draw(&mut self, cx: &mut Cx, anchor: Rect, items: &[Menu]) {
+        let props = TextInsProps { color: COLOR_TEXT, position_anchoring: TEXT_ANCHOR_CENTER_V, ..TextInsProps::DEFAULT };
+        let shortcuts: Vec<Option<String>> = items
+            .iter()
+            .map(|item| match item {
+                Menu::Item { command, .. } => shortcut_label(cx, *command),
+                _ => None,
+            })
+            .collect();
+        let label_width = items
+            .iter()
+            .map(|item| match item {
+                Menu::Item { name, .. } | Menu::Sub { name, .. } => TextIns::measure_str_width(cx, name, &props),
+                _ => 0.,
+            })
+            .fold(0., f32::max);
+        let shortcut_width =
+            shortcuts.iter().flatten().map(|shortcut| TextIns::measure_str_width(cx, shortcut, &props)).fold(0., f32::max);
+        let width = (2. * ITEM_PADDING_X + label_width + SHORTCUT_GAP + shortcut_width + ARROW_SIZE).max(MIN_MENU_WIDTH);
+        let height = 2. * MENU_PADDING
+            + items.iter().map(|item| if let Menu::Line = item { LINE_HEIGHT } else { ITEM_HEIGHT }).sum::<f32>();
+
+        let rect = self.popover.begin_draw_anchored(cx, anchor, vec2(width, height), COLOR_MENU);
+
+        self.item_rects.clear();
+        let mut y = rect.pos.y + MENU_PADDING;
+        for item in items {
+            let item_height = if let Menu::Line = item { LINE_HEIGHT } else { ITEM_HEIGHT };
+            self.item_rects.push(Rect { pos: vec2(rect.pos.x, y), size: vec2(width, item_height) });
+            y += item_height;
+        }
+
+        // These get batched into the same draw call as the popover background, so give them a higher draw depth
+        // to keep them from fighting over which one is on top.
+        if let Some(highlighted) = self.highlighted {
+            Background::default().with_draw_depth(0.1).draw(cx, self.item_rects[highlighted], COLOR_HIGHLIGHT);
+        }
+        for (item, item_rect) in items.iter().zip(&self.item_rects) {
+            if let Menu::Line = item {
+                let line_rect = Rect {
+                    pos: item_rect.pos + vec2(ITEM_PADDING_X / 2., (LINE_HEIGHT - 1.) / 2.),
+                    size: vec2(width - ITEM_PADDING_X, 1.),
+                };
+                Background::default().with_draw_depth(0.1).draw(cx, line_rect, COLOR_SEPARATOR);
+            }
+        }
+
+        let arrows: Vec<ArrowIns> = items
+            .iter()
+            .zip(&self.item_rects)
+            .filter(|(item, _)| matches!(item, Menu::Sub { .. }))
+            .map(|(_, item_rect)| {
+                let pos = item_rect.pos + vec2(width - ARROW_SIZE - MENU_PADDING, (ITEM_HEIGHT - ARROW_SIZE) / 2.);
+                let quad = QuadIns::from_rect(Rect { pos, size: vec2(ARROW_SIZE, ARROW_SIZE) }).with_draw_depth(0.4);
+                ArrowIns { base: quad, color: COLOR_SHORTCUT }
+            })
+            .collect();
+        if !arrows.is_empty() {
+            cx.add_instances(&ARROW_SHADER, &arrows);
+        }
+
+        for ((item, item_rect), shortcut) in items.iter().zip(&self.item_rects).zip(&shortcuts) {
+            let (name, enabled) = match item {
+                Menu::Item { name, command } => (name, command.is_enabled(cx)),
+                Menu::Sub { name, .. } => (name, true),
+                _ => continue,
+            };
+            let color = if enabled { COLOR_TEXT } else { COLOR_TEXT_DISABLED };
+            let center_y = item_rect.pos.y + ITEM_HEIGHT / 2.;
+            TextIns::draw_str(
+                cx,
+                name,
+                vec2(item_rect.pos.x + ITEM_PADDING_X, center_y),
+                &TextInsProps { color, draw_depth: 0.4, ..props },
+            );
+            if let Some(shortcut) = shortcut {
+                let shortcut_props = TextInsProps {
+                    color: if enabled { COLOR_SHORTCUT } else { COLOR_TEXT_DISABLED },
+                    position_anchoring: vec2(1., 0.5),
+                    draw_depth: 0.4,
+                    ..props
+                };
+                TextIns::draw_str(cx, shortcut, vec2(item_rect.pos.x + width - ITEM_PADDING_X, center_y), &shortcut_props);
+            }
+        }
+
+        self.popover.end_draw_anchored(cx);
+    }
+}
+
+/// A top-level menu with the submenus that are opened from it, each drawn in its own [`Popover`].
+///
+/// Doesn't hold on to the [`Menu`] itself; the items of the top-level menu are passed in to every call instead.
+#[derive(Default)]
+pub(crate) struct MenuPopups {
+    /// Grows as deeper submenus get opened, but never shrinks, so that the [`Popover`]s can be reused.
+    levels: Vec<MenuLevel>,
+    /// How many of [`MenuPopups::levels`] are currently open.
+    depth: usize,
+    /// The top-level menu goes below this [`Rect`] (or above it if there's no room), in window coordinates.
+    pub(crate) anchor: Rect,
+}
+
+impl MenuPopups {
+    pub(crate) fn is_open(&self) -> bool {
+        self.depth > 0
+    }
+
+    /// Open the top-level menu. `highlight_first` is typically used when opening using the keyboard.
+    pub(crate) fn open(&mut self, cx: &mut Cx, anchor: Rect, root: &[Menu], highlight_first: bool) {
+        self.anchor = anchor;
+        self.depth = 0;
+        self.open_level(cx, vec![], root, highlight_first);
+    }
+
+    fn open_level(&mut self, cx: &mut Cx, path: Vec<usize>, root: &[Menu], highlight_first: bool) {
+        if self.levels.len() <= self.depth {
+            self.levels.push(MenuLevel::default());
+        }
+        let highlighted = if highlight_first { next_selectable(cx, items_at(root, &path), None, true) } else { None };
+        let level = &mut self.levels[self.depth];
+        level.path = path;
+        level.highlighted = highlighted;
+        level.item_rects.clear();
+        self.depth += 1;
+        cx.request_draw();
+    }
+
+    pub(crate) fn close(&mut self, cx: &mut Cx) {
+        if self.depth > 0 {
+            self.depth = 0;
+            cx.request_draw();
+        }
+    }
+
+    /// Whether `pos` (in window coordinates) is on any of the open menus.
+    pub(crate) fn contains(&self, cx: &Cx, pos: Vec2) -> bool {
+        self.levels[..self.depth]
+            .iter()
+            .any(|level| level.popover.area().get_rect_for_first_instance(cx).map_or(false, |rect| rect.contains(pos)))
+    }
+
+    /// Pick the item at `index` in the menu at `level_index`: send its command, or open its submenu.
+    fn activate(&mut self, cx: &mut Cx, root: &[Menu], level_index: usize, index: usize, from_keyboard: bool) -> MenuPopupsEvent {
+        let items = items_at(root, &self.levels[level_index].path);
+        match &items[index] {
+            Menu::Item { command, .. } if command.is_enabled(cx) => {
+                cx.send_command(*command);
+                self.close(cx);
+                MenuPopupsEvent::Closed
+            }
+            Menu::Sub { .. } => {
+                let mut path = self.levels[level_index].path.clone();
+                path.push(index);
+                self.levels[level_index].highlighted = Some(index);
+                self.depth = level_index + 1;
+                self.open_level(cx, path, root, from_keyboard);
+                MenuPopupsEvent::None
+            }
+            _ => MenuPopupsEvent::None,
+        }
+    }
+
+    pub(crate) fn handle(&mut self, cx: &mut Cx, event: &mut Event, root: &[Menu]) -> MenuPopupsEvent {
+        for level_index in 0..self.depth {
+            if level_index >= self.depth {
+                break;
+            }
+            let level = &self.levels[level_index];
+            let rect = level.popover.area().get_rect_for_first_instance(cx);
+            match event.hits_pointer(cx, level.component_id, rect) {
+                Event::PointerHover(pe) => {
+                    let items = items_at(root, &level.path);
+                    let index = if pe.hover_state == HoverState::Out { None } else { level.item_at(pe.abs) };
+                    match index.filter(|&index| is_selectable(cx, &items[index])) {
+                        Some(index) => {
+                            let submenu_is_open = self.depth > level_index + 1 && level.highlighted == Some(index);
+                            if let Menu::Sub { .. } = items[index] {
+                                if !submenu_is_open {
+                                    self.activate(cx, root, level_index, index, false);
+                                }
+                            } else if level.highlighted != Some(index) || self.depth > level_index + 1 {
+                                self.levels[level_index].highlighted = Some(index);
+                                self.depth = level_index + 1;
+                                cx.request_draw();
+                            }
+                        }
+                        // Keep the item of an open submenu highlighted, so you can see where it came from.
+                        None if self.depth == level_index + 1 && level.highlighted.is_some() => {
+                            self.levels[level_index].highlighted = None;
+                            cx.request_draw();
+                        }
+                        None => (),
+                    }
+                }
+                Event::PointerDown(pe) => {
+                    cx.keep_key_focus();
+                    if let Some(index) = level.item_at(pe.abs) {
+                        return self.activate(cx, root, level_index, index, false);
+                    }
+                }
+                _ => (),
+            }
+        }
+        MenuPopupsEvent::None
+    }
+
+    /// Handle a key press for the deepest open menu.
+    pub(crate) fn handle_key(&mut self, cx: &mut Cx, key_code: KeyCode, root: &[Menu]) -> MenuPopupsEvent {
+        if self.depth == 0 {
+            return MenuPopupsEvent::None;
+        }
+        let level_index = self.depth - 1;
+        let items = items_at(root, &self.levels[level_index].path);
+        let highlighted = self.levels[level_index].highlighted;
+        let new_highlighted = match key_code {
+            KeyCode::ArrowDown => next_selectable(cx, items, highlighted, true),
+            KeyCode::ArrowUp => next_selectable(cx, items, highlighted, false),
+            KeyCode::Home => next_selectable(cx, items, None, true),
+            KeyCode::End => next_selectable(cx, items, None, false),
+            KeyCode::Return | KeyCode::Space | KeyCode::ArrowRight => {
+                return match highlighted {
+                    Some(index) if key_code != KeyCode::ArrowRight || matches!(items[index], Menu::Sub { .. }) => {
+                        self.activate(cx, root, level_index, index, true)
+                    }
+                    _ if key_code == KeyCode::ArrowRight => MenuPopupsEvent::NextMenu,
+                    _ => MenuPopupsEvent::None,
+                };
+            }
+            KeyCode::ArrowLeft | KeyCode::Escape if self.depth > 1 => {
+                self.depth -= 1;
+                cx.request_draw();
+                return MenuPopupsEvent::None;
+            }
+            KeyCode::ArrowLeft => return MenuPopupsEvent::PrevMenu,
+            KeyCode::Escape => {
+                self.close(cx);
+                return MenuPopupsEvent::Closed;
+            }
+            _ => return MenuPopupsEvent::None,
+        };
+        self.levels[level_index].highlighted = new_highlighted;
+        cx.request_draw();
+        MenuPopupsEvent::None
+    }
+
+    pub(crate) fn draw(&mut self, cx: &mut Cx, root: &[Menu]) {
+        for level_index in 0..self.depth {
+            let anchor = if level_index == 0 {
+                self.anchor
+            } else {
+                // Submenus go to the right of the item that they belong to.
+                let index = *self.levels[level_index].path.last().unwrap();
+                match self.levels[level_index - 1].item_rects.get(index) {
+                    Some(rect) => Rect { pos: vec2(rect.pos.x + rect.size.x, rect.pos.y - MENU_PADDING), size: Vec2::default() },
+                    None => break,
+                }
+            };
+            let items = items_at(root, &self.levels[level_index].path);
+            self.levels[level_index].draw(cx, anchor, items);
+        }
+    }
+}
+
+/// A [`Menu`] that pops up at some position, typically when right-clicking on something. Picking one of its items
+/// fires an [`Event::Command`] (see [`Cx::send_command`]), just like a native menu.
+///
+/// Call [`ContextMenu::open`] when you want to show it, e.g. when your component gets an [`Event::PointerDown`] with
+/// [`MouseButton::Right`]. Items are greyed out unless their command is enabled using [`CommandId::set_enabled`], and
+/// the shortcuts set using [`CommandId::set_key`] are shown next to the items.
+///
+/// While it's open it has the key focus, so it can be used with the arrow keys, Return, and Escape. When it closes
+/// the key focus goes back to where it was before.
+///
+/// Call [`ContextMenu::handle`] before the event handlers of whatever is drawn underneath, so that clicks on the
+/// menu don't leak through.
+pub struct ContextMenu {
+    component_id: ComponentId,
+    /// The items to show, taken from a [`Menu::Main`] or [`Menu::Sub`].
+    pub menu: Menu,
+    popups: MenuPopups,
+}
+
+impl Default for ContextMenu {
+    fn default() -> Self {
+        Self { component_id: Default::default(), menu: Menu::main(vec![]), popups: MenuPopups::default() }
+    }
+}
+
+impl ContextMenu {
+    #[must_use]
+    pub fn with_menu(self, menu: Menu) -> Self {
+        Self { menu, ..self }
+    }
+
+    /// Show the menu at `pos`, in window coordinates (e.g. [`PointerDownEvent::abs`]).
+    pub fn open(&mut self, cx: &mut Cx, pos: Vec2) {
+        self.popups.open(cx, Rect { pos, size: Vec2::default() }, menu_items(&self.menu), false);
+        cx.set_key_focus(Some(self.component_id));
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popups.is_open()
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        if self.popups.is_open() {
+            self.popups.close(cx);
+            cx.revert_key_focus();
+        }
+    }
+
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if !self.popups.is_open() {
+            return;
+        }
+        if let MenuPopupsEvent::Closed = self.popups.handle(cx, event, menu_items(&self.menu)) {
+            cx.revert_key_focus();
+            return;
+        }
+        if let Event::PointerDown(pe) = event {
+            if !self.popups.contains(cx, pe.abs) {
+                self.close(cx);
+                return;
+            }
+        }
+        match event.hits_keyboard(cx, self.component_id) {
+            Event::KeyFocusLost(_) => self.popups.close(cx),
+            Event::KeyDown(ke) => {
+                if let MenuPopupsEvent::Closed = self.popups.handle_key(cx, ke.key_code, menu_items(&self.menu)) {
+                    cx.revert_key_focus();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx) {
+        self.popups.draw(cx, menu_items(&self.menu));
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use zaplib::*;
+
+    use crate::*;
+
+    struct ContextMenuTestApp {
+        context_menu: ContextMenu,
+        commands: Vec<CommandId>,
+    }
+
+    impl MainApp for ContextMenuTestApp {
+        fn new(cx: &mut Cx) -> Self {
+            cx.command_default_keymap();
+            for command in [Cx::COMMAND_UNDO, Cx::COMMAND_ZOOM_IN, Cx::COMMAND_ZOOM_OUT, Cx::COMMAND_SELECT_ALL] {
+                command.set_enabled(cx, true);
+            }
+            let menu = Menu::main(vec![
+                Menu::item("Undo", Cx::COMMAND_UNDO),
+                Menu::item("Redo", Cx::COMMAND_REDO),
+                Menu::line(),
+                Menu::sub("Zoom", vec![Menu::item("Zoom In", Cx::COMMAND_ZOOM_IN), Menu::item("Zoom Out", Cx::COMMAND_ZOOM_OUT)]),
+                Menu::item("Select All", Cx::COMMAND_SELECT_ALL),
+            ]);
+            Self { context_menu: ContextMenu::default().with_menu(menu), commands: vec![] }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            self.context_menu.handle(cx, event);
+            match event {
+                Event::PointerDown(pe) if pe.button == MouseButton::Right => self.context_menu.open(cx, pe.abs),
+                Event::Command(command) => self.commands.push(*command),
+                _ => (),
+            }
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            self.context_menu.draw(cx);
+        }
+    }
+
+    fn right_click(test: &mut AppTest<ContextMenuTestApp>, pos: Vec2) {
+        let mut pe = PointerDownEvent::default();
+        pe.abs = pos;
+        pe.rel = pos;
+        pe.button = MouseButton::Right;
+        test.send_event(Event::PointerDown(pe));
+    }
+
+    fn item_center(test: &AppTest<ContextMenuTestApp>, level: usize, index: usize) -> Vec2 {
+        let rect = test.app.context_menu.popups.levels[level].item_rects[index];
+        rect.pos + rect.size * 0.5
+    }
+
+    #[test]
+    fn test_context_menu() {
+        let mut test = AppTest::<ContextMenuTestApp>::new_in_window(vec2(400., 200.));
+
+        right_click(&mut test, vec2(20., 20.));
+        assert!(test.app.context_menu.is_open());
+
+        // Disabled items can't be picked.
+        test.click(item_center(&test, 0, 1));
+        assert!(test.app.context_menu.is_open());
+        assert!(test.app.commands.is_empty());
+
+        // Hovering over a submenu opens it.
+        test.pointer_move(item_center(&test, 0, 3));
+        test.pointer_move(item_center(&test, 1, 0));
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/context_menu_submenu.png"));
+        test.click(item_center(&test, 1, 0));
+        assert!(!test.app.context_menu.is_open());
+        assert_eq!(test.app.commands, vec![Cx::COMMAND_ZOOM_IN]);
+
+        // The arrow keys skip over disabled items and separators.
+        right_click(&mut test, vec2(20., 20.));
+        test.key_press(KeyCode::ArrowDown);
+        test.key_press(KeyCode::ArrowDown);
+        test.key_press(KeyCode::ArrowRight);
+        test.key_press(KeyCode::ArrowDown);
+        test.key_press(KeyCode::Return);
+        assert!(!test.app.context_menu.is_open());
+        assert_eq!(test.app.commands, vec![Cx::COMMAND_ZOOM_IN, Cx::COMMAND_ZOOM_OUT]);
+
+        // Escape and clicking outside of the menu close it without picking anything.
+        right_click(&mut test, vec2(20., 20.));
+        test.key_press(KeyCode::Escape);
+        assert!(!test.app.context_menu.is_open());
+        right_click(&mut test, vec2(20., 20.));
+        test.click(vec2(350., 180.));
+        assert!(!test.app.context_menu.is_open());
+        assert_eq!(test.app.commands.len(), 2);
+    }
+}
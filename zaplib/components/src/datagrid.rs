@@ -0,0 +1,729 @@
+//! A table with virtualized rows and columns, see [`DataGrid`].
+
+use std::ops::Range;
+
+use crate::*;
+use zaplib::*;
+
+/// Provides the contents of a [`DataGrid`]. Only the cells that are visible get requested, so this can be backed
+/// by millions of rows.
+pub trait DataGridSource {
+    fn row_count(&self) -> usize;
+    /// The text of a cell, where `column` is an index into [`DataGrid::columns`].
+    fn cell_text(&self, row: usize, column: usize) -> String;
+}
+
+#[derive(Debug, Clone)]
+pub struct DataGridColumn {
+    pub label: String,
+    /// Width in pixels. Updated when the user resizes the column.
+    pub width: f32,
+    /// Whether clicking on the header emits [`DataGridEvent::Sort`].
+    pub sortable: bool,
+}
+
+impl Default for DataGridColumn {
+    fn default() -> Self {
+        Self { label: String::new(), width: 100., sortable: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataGridSortDirection {
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataGridEvent {
+    None,
+    /// A sortable header was clicked. The grid doesn't own the rows, so sort the [`DataGridSource`] accordingly.
+    Sort {
+        column: usize,
+        direction: DataGridSortDirection,
+    },
+    /// The selected cells changed, see [`DataGrid::selected_rows`] and [`DataGrid::selected_columns`].
+    SelectionChanged,
+}
+
+/// A cell, as its row and the position of its column in display order.
+type DataGridCell = (usize, usize);
+
+enum DataGridDrag {
+    /// Selecting cells, from the anchor of the selection to the cell under the pointer.
+    Select,
+    Resize {
+        column: usize,
+        start_width: f32,
+    },
+    /// Pressed on a header, which becomes a sort on release, or a reorder once the pointer moves.
+    Header {
+        position: usize,
+        reordering: bool,
+    },
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct DataGridRectIns {
+    base: QuadIns,
+    color: Vec4,
+}
+
+static RECT_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            instance color: vec4;
+
+            fn pixel() -> vec4 {
+                return vec4(color.rgb * color.a, color.a);
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+/// Rects and text that are drawn with the same scroll stickiness.
+#[derive(Default)]
+struct DataGridLayer {
+    rects: Vec<DataGridRectIns>,
+    glyphs: Vec<TextIns>,
+}
+
+impl DataGridLayer {
+    fn add_rect(&mut self, rect: Rect, color: Vec4) {
+        self.rects.push(DataGridRectIns { base: QuadIns::from_rect(rect), color });
+    }
+
+    /// Adds text that is vertically centered in `rect`, dropping the glyphs that don't fit.
+    fn add_text(&mut self, cx: &Cx, text: &str, rect: Rect, color: Vec4) {
+        let text_height = TEXT_STYLE_NORMAL.font_size * TEXT_STYLE_NORMAL.line_spacing;
+        let pos = vec2(rect.pos.x + CELL_PADDING, rect.pos.y + 0.5 * (rect.size.y - text_height));
+        let max_x = rect.pos.x + rect.size.x - CELL_PADDING;
+        let glyphs = TextIns::generate_2d_glyphs(
+            &TEXT_STYLE_NORMAL,
+            &cx.fonts_data,
+            cx.current_dpi_factor,
+            1.,
+            0.,
+            color,
+            pos,
+            0,
+            text.chars(),
+            |_, _, _, _| 0.,
+        );
+        // Glyphs get a depth that increases with their x coordinate, which for columns far to the right would put
+        // them in front of the layers that are drawn later, like the frozen columns and the header.
+        self.glyphs.extend(
+            glyphs
+                .into_iter()
+                .filter(|glyph| glyph.rect_pos.x + glyph.rect_size.x <= max_x)
+                .map(|glyph| TextIns { char_depth: 0., ..glyph }),
+        );
+    }
+
+    fn draw(&self, cx: &mut Cx, horizontal: bool, vertical: bool) {
+        cx.add_instances_with_scroll_sticky(&RECT_SHADER, &self.rects, horizontal, vertical);
+        if !self.glyphs.is_empty() {
+            TextIns::draw_glyphs_with_scroll_sticky(cx, &self.glyphs, &TEXT_STYLE_NORMAL, horizontal, vertical);
+        }
+    }
+}
+
+const CELL_PADDING: f32 = 4.;
+const MIN_COLUMN_WIDTH: f32 = 20.;
+/// How close to the right edge of a header the pointer has to be to resize the column.
+const RESIZE_MARGIN: f32 = 4.;
+
+const COLOR_GRID_LINES: Vec4 = vec4(0.25, 0.25, 0.25, 1.);
+const COLOR_ROW_EVEN: Vec4 = vec4(0.13, 0.13, 0.13, 1.);
+const COLOR_ROW_ODD: Vec4 = vec4(0.16, 0.16, 0.16, 1.);
+const COLOR_HEADER: Vec4 = vec4(0.2, 0.2, 0.2, 1.);
+const COLOR_SELECTED: Vec4 = vec4(0.18, 0.3, 0.48, 1.);
+const COLOR_CURSOR: Vec4 = vec4(0.25, 0.4, 0.65, 1.);
+const COLOR_DROP_MARKER: Vec4 = vec4(0.4, 0.6, 1., 1.);
+const COLOR_TEXT: Vec4 = vec4(0.85, 0.85, 0.85, 1.);
+
+/// A table that only draws the rows and columns that are visible, so it can show millions of rows.
+///
+/// Columns can be resized and reordered by dragging their headers, and clicking on a header emits
+/// [`DataGridEvent::Sort`]. The header row always stays visible, as do the first [`DataGrid::frozen_columns`]
+/// columns. Cells can be selected with the pointer and the keyboard, and copied as tab-separated values.
+pub struct DataGrid {
+    pub columns: Vec<DataGridColumn>,
+    /// Number of columns, in display order, that don't scroll horizontally.
+    pub frozen_columns: usize,
+    pub row_height: f32,
+    pub header_height: f32,
+    component_id: ComponentId,
+    view: ScrollView,
+    /// Indices into `columns`, in display order.
+    column_order: Vec<usize>,
+    sort: Option<(usize, DataGridSortDirection)>,
+    /// The cell where the selection started, and the cell that it extends to.
+    selection: Option<(DataGridCell, DataGridCell)>,
+    drag: Option<DataGridDrag>,
+    /// Where a header that is being dragged would be inserted, as a position in display order.
+    drop_position: Option<usize>,
+}
+
+impl Default for DataGrid {
+    fn default() -> Self {
+        Self {
+            columns: vec![],
+            frozen_columns: 0,
+            row_height: 20.,
+            header_height: 24.,
+            component_id: Default::default(),
+            view: ScrollView::new_standard_vh(),
+            column_order: vec![],
+            sort: None,
+            selection: None,
+            drag: None,
+            drop_position: None,
+        }
+    }
+}
+
+impl DataGrid {
+    #[must_use]
+    pub fn with_columns(self, columns: Vec<DataGridColumn>) -> Self {
+        Self { column_order: (0..columns.len()).collect(), columns, ..self }
+    }
+
+    #[must_use]
+    pub fn with_frozen_columns(self, frozen_columns: usize) -> Self {
+        Self { frozen_columns, ..self }
+    }
+
+    /// Indices into [`DataGrid::columns`], in the order in which they are displayed.
+    pub fn column_order(&self) -> &[usize] {
+        &self.column_order
+    }
+
+    /// The column that was last sorted on by clicking its header, and in which direction.
+    pub fn sort(&self) -> Option<(usize, DataGridSortDirection)> {
+        self.sort
+    }
+
+    pub fn selected_rows(&self) -> Range<usize> {
+        match self.selection {
+            Some((anchor, cursor)) => anchor.0.min(cursor.0)..anchor.0.max(cursor.0) + 1,
+            None => 0..0,
+        }
+    }
+
+    /// Indices into [`DataGrid::columns`] of the selected columns, in display order.
+    pub fn selected_columns(&self) -> Vec<usize> {
+        match self.selection {
+            Some((anchor, cursor)) => {
+                (anchor.1.min(cursor.1)..=anchor.1.max(cursor.1)).map(|position| self.column_order[position]).collect()
+            }
+            None => vec![],
+        }
+    }
+
+    pub fn clear_selection(&mut self, cx: &mut Cx) {
+        self.selection = None;
+        cx.request_draw();
+    }
+
+    /// The selected cells as tab-separated values, with a line per row. This is what gets copied to the clipboard.
+    pub fn selection_to_tsv(&self, source: &dyn DataGridSource) -> String {
+        let columns = self.selected_columns();
+        let rows = self.selected_rows();
+        let row_count = source.row_count();
+        let mut tsv = String::new();
+        for row in rows.start.min(row_count)..rows.end.min(row_count) {
+            let cells: Vec<String> =
+                columns.iter().map(|&column| source.cell_text(row, column).replace(&['\t', '\n', '\r'][..], " ")).collect();
+            tsv.push_str(&cells.join("\t"));
+            tsv.push('\n');
+        }
+        tsv
+    }
+
+    fn update_column_order(&mut self) {
+        if self.column_order.len() != self.columns.len() {
+            self.column_order = (0..self.columns.len()).collect();
+        }
+    }
+
+    fn frozen_count(&self) -> usize {
+        self.frozen_columns.min(self.columns.len())
+    }
+
+    /// The left of the column at a display position, relative to the left of the grid.
+    fn column_x(&self, position: usize) -> f32 {
+        self.column_order[..position].iter().map(|&column| self.columns[column].width).sum()
+    }
+
+    fn frozen_width(&self) -> f32 {
+        self.column_x(self.frozen_count())
+    }
+
+    fn column_width(&self, position: usize) -> f32 {
+        self.columns[self.column_order[position]].width
+    }
+
+    /// Converts an x coordinate relative to the view into one relative to the grid. Frozen columns are at the same
+    /// place in both, since they don't scroll.
+    fn grid_x(&self, x: f32, scroll: Vec2) -> f32 {
+        if x < self.frozen_width() {
+            x
+        } else {
+            x + scroll.x
+        }
+    }
+
+    fn position_at(&self, grid_x: f32) -> Option<usize> {
+        let mut x = 0.;
+        for position in 0..self.column_order.len() {
+            x += self.column_width(position);
+            if grid_x < x {
+                return Some(position);
+            }
+        }
+        None
+    }
+
+    /// The position of the column of which the right edge is near `grid_x`.
+    fn resize_edge_at(&self, grid_x: f32) -> Option<usize> {
+        let mut x = 0.;
+        for position in 0..self.column_order.len() {
+            x += self.column_width(position);
+            if (grid_x - x).abs() <= RESIZE_MARGIN {
+                return Some(position);
+            }
+        }
+        None
+    }
+
+    /// Where a dragged header would be inserted: before the first column whose center is right of `grid_x`.
+    fn drop_position_at(&self, grid_x: f32) -> usize {
+        (0..self.column_order.len())
+            .find(|&position| self.column_x(position) + 0.5 * self.column_width(position) > grid_x)
+            .unwrap_or(self.column_order.len())
+    }
+
+    /// The cell at a position relative to the view, clamped to the cells that exist.
+    fn cell_at(&self, rel: Vec2, scroll: Vec2, row_count: usize) -> Option<DataGridCell> {
+        if row_count == 0 || self.columns.is_empty() {
+            return None;
+        }
+        let row = ((rel.y - self.header_height + scroll.y) / self.row_height).floor().max(0.) as usize;
+        let grid_x = self.grid_x(rel.x, scroll).max(0.);
+        let position = self.position_at(grid_x).unwrap_or(self.columns.len() - 1);
+        Some((row.min(row_count - 1), position))
+    }
+
+    fn scroll_cell_into_view(&mut self, cx: &mut Cx, (row, position): DataGridCell) {
+        let rect = self.view.get_rect(cx);
+        let mut scroll = self.view.get_scroll_pos(cx);
+        let bar_size = ScrollBarConfig::default().bar_size;
+
+        let data_height = rect.size.y - self.header_height - bar_size;
+        let top = row as f32 * self.row_height;
+        if top < scroll.y {
+            scroll.y = top;
+        } else if top + self.row_height > scroll.y + data_height {
+            scroll.y = top + self.row_height - data_height;
+        }
+
+        if position >= self.frozen_count() {
+            let frozen_width = self.frozen_width();
+            let left = self.column_x(position);
+            let right = left + self.column_width(position);
+            if left - frozen_width < scroll.x {
+                scroll.x = left - frozen_width;
+            } else if right > scroll.x + rect.size.x - bar_size {
+                scroll.x = right - (rect.size.x - bar_size);
+            }
+        }
+        self.view.set_scroll_pos(cx, scroll);
+    }
+
+    fn select(&mut self, cx: &mut Cx, cell: DataGridCell, extend: bool) {
+        let anchor = match self.selection {
+            Some((anchor, _)) if extend => anchor,
+            _ => cell,
+        };
+        self.selection = Some((anchor, cell));
+        self.scroll_cell_into_view(cx, cell);
+        cx.request_draw();
+    }
+
+    fn handle_key_down(&mut self, cx: &mut Cx, ke: &KeyEvent, row_count: usize) -> bool {
+        if row_count == 0 || self.columns.is_empty() {
+            return false;
+        }
+        let last_row = row_count - 1;
+        let last_position = self.columns.len() - 1;
+        let (row, position) = self.selection.map_or((0, 0), |(_, cursor)| cursor);
+        let page = ((self.view.get_rect(cx).size.y - self.header_height) / self.row_height).floor().max(1.) as usize;
+        let ctrl = ke.modifiers.control || ke.modifiers.logo;
+
+        let cell = match ke.key_code {
+            KeyCode::ArrowUp => (row.saturating_sub(1), position),
+            KeyCode::ArrowDown => ((row + 1).min(last_row), position),
+            KeyCode::ArrowLeft => (row, position.saturating_sub(1)),
+            KeyCode::ArrowRight => (row, (position + 1).min(last_position)),
+            KeyCode::PageUp => (row.saturating_sub(page), position),
+            KeyCode::PageDown => ((row + page).min(last_row), position),
+            KeyCode::Home if ctrl => (0, position),
+            KeyCode::Home => (row, 0),
+            KeyCode::End if ctrl => (last_row, position),
+            KeyCode::End => (row, last_position),
+            KeyCode::KeyA if ctrl => {
+                self.selection = Some(((0, 0), (last_row, last_position)));
+                cx.request_draw();
+                return true;
+            }
+            _ => return false,
+        };
+        self.select(cx, cell, ke.modifiers.shift);
+        true
+    }
+
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event, source: &dyn DataGridSource) -> DataGridEvent {
+        self.update_column_order();
+        self.view.handle(cx, event);
+
+        let row_count = source.row_count();
+        let rect = self.view.get_rect(cx);
+        let scroll = self.view.get_scroll_pos(cx);
+        match event.hits_pointer(cx, self.component_id, Some(rect)) {
+            Event::PointerDown(pe) => {
+                cx.set_key_focus(Some(self.component_id));
+                if pe.rel.y < self.header_height {
+                    let grid_x = self.grid_x(pe.rel.x, scroll);
+                    if let Some(position) = self.resize_edge_at(grid_x) {
+                        let column = self.column_order[position];
+                        self.drag = Some(DataGridDrag::Resize { column, start_width: self.columns[column].width });
+                        cx.set_down_mouse_cursor(MouseCursor::ColResize);
+                    } else if let Some(position) = self.position_at(grid_x) {
+                        self.drag = Some(DataGridDrag::Header { position, reordering: false });
+                    }
+                } else if let Some(cell) = self.cell_at(pe.rel, scroll, row_count) {
+                    self.drag = Some(DataGridDrag::Select);
+                    self.select(cx, cell, pe.modifiers.shift);
+                    return DataGridEvent::SelectionChanged;
+                }
+            }
+            Event::PointerMove(pe) => match &mut self.drag {
+                Some(DataGridDrag::Resize { column, start_width }) => {
+                    self.columns[*column].width = (*start_width + pe.rel.x - pe.rel_start.x).max(MIN_COLUMN_WIDTH);
+                    cx.request_draw();
+                }
+                Some(DataGridDrag::Header { reordering, .. }) => {
+                    if *reordering || (pe.rel.x - pe.rel_start.x).abs() > RESIZE_MARGIN {
+                        *reordering = true;
+                        self.drop_position = Some(self.drop_position_at(self.grid_x(pe.rel.x, scroll)));
+                        cx.request_draw();
+                    }
+                }
+                Some(DataGridDrag::Select) => {
+                    if let Some(cell) = self.cell_at(pe.rel, scroll, row_count) {
+                        if self.selection.map(|(_, cursor)| cursor) != Some(cell) {
+                            self.select(cx, cell, true);
+                            return DataGridEvent::SelectionChanged;
+                        }
+                    }
+                }
+                None => (),
+            },
+            Event::PointerUp(_) => match self.drag.take() {
+                Some(DataGridDrag::Header { position, reordering: false }) => {
+                    let column = self.column_order[position];
+                    if self.columns[column].sortable {
+                        let direction = match self.sort {
+                            Some((sort_column, DataGridSortDirection::Ascending)) if sort_column == column => {
+                                DataGridSortDirection::Descending
+                            }
+                            _ => DataGridSortDirection::Ascending,
+                        };
+                        self.sort = Some((column, direction));
+                        cx.request_draw();
+                        return DataGridEvent::Sort { column, direction };
+                    }
+                }
+                Some(DataGridDrag::Header { position, reordering: true }) => {
+                    if let Some(drop_position) = self.drop_position.take() {
+                        let column = self.column_order.remove(position);
+                        let drop_position = if drop_position > position { drop_position - 1 } else { drop_position };
+                        self.column_order.insert(drop_position, column);
+                        // The selection is by display position, so it would now cover different columns.
+                        self.selection = None;
+                        cx.request_draw();
+                    }
+                }
+                _ => (),
+            },
+            Event::PointerHover(pe) => {
+                if pe.rel.y < self.header_height && self.resize_edge_at(self.grid_x(pe.rel.x, scroll)).is_some() {
+                    cx.set_hover_mouse_cursor(MouseCursor::ColResize);
+                }
+            }
+            _ => (),
+        }
+
+        match event.hits_keyboard(cx, self.component_id) {
+            Event::KeyDown(ke) => {
+                if self.handle_key_down(cx, &ke, row_count) {
+                    return DataGridEvent::SelectionChanged;
+                }
+            }
+            Event::TextCopy => {
+                if self.selection.is_some() {
+                    cx.copy_text_to_clipboard(&self.selection_to_tsv(source));
+                }
+            }
+            _ => (),
+        }
+        DataGridEvent::None
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx, source: &dyn DataGridSource) {
+        self.update_column_order();
+        let row_count = source.row_count();
+        if let Some((anchor, cursor)) = &mut self.selection {
+            if row_count == 0 || self.columns.is_empty() {
+                self.selection = None;
+            } else {
+                for cell in [anchor, cursor] {
+                    *cell = (cell.0.min(row_count - 1), cell.1.min(self.columns.len() - 1));
+                }
+            }
+        }
+
+        self.view.begin_view(cx, LayoutSize::FILL);
+        let rect = cx.get_box_rect();
+        let scroll = self.view.get_scroll_pos(cx);
+        let origin = rect.pos;
+
+        // Reserve the size of the whole grid, so that the scroll bars can scroll through all of it. Add the size of the
+        // scroll bars, since they are drawn on top of the last row and column otherwise.
+        let total_width = self.column_x(self.columns.len());
+        let bar_size = ScrollBarConfig::default().bar_size;
+        cx.add_box(LayoutSize::new(
+            Width::Fix(total_width + bar_size),
+            Height::Fix(self.header_height + row_count as f32 * self.row_height + bar_size),
+        ));
+
+        let first_row = ((scroll.y / self.row_height).floor().max(0.) as usize).min(row_count);
+        let visible_rows = ((rect.size.y - self.header_height).max(0.) / self.row_height).ceil() as usize + 1;
+        let rows = first_row..(first_row + visible_rows).min(row_count);
+
+        let frozen_count = self.frozen_count();
+        let frozen_width = self.frozen_width();
+        let scrolling_positions: Vec<usize> = (frozen_count..self.columns.len())
+            .filter(|&position| {
+                let x = self.column_x(position);
+                x + self.column_width(position) > scroll.x + frozen_width && x < scroll.x + rect.size.x
+            })
+            .collect();
+        let frozen_positions: Vec<usize> = (0..frozen_count).collect();
+
+        let selected_rows = self.selected_rows();
+        let selected_positions = match self.selection {
+            Some((anchor, cursor)) => anchor.1.min(cursor.1)..anchor.1.max(cursor.1) + 1,
+            None => 0..0,
+        };
+        let cursor = self.selection.map(|(_, cursor)| cursor);
+
+        let cells = |cx: &Cx, positions: &[usize]| {
+            let mut layer = DataGridLayer::default();
+            for &position in positions {
+                let column = self.column_order[position];
+                let x = origin.x + self.column_x(position);
+                let width = self.column_width(position);
+                for row in rows.clone() {
+                    let y = origin.y + self.header_height + row as f32 * self.row_height;
+                    let color = if cursor == Some((row, position)) {
+                        COLOR_CURSOR
+                    } else if selected_rows.contains(&row) && selected_positions.contains(&position) {
+                        COLOR_SELECTED
+                    } else if row % 2 == 0 {
+                        COLOR_ROW_EVEN
+                    } else {
+                        COLOR_ROW_ODD
+                    };
+                    // Leave a pixel on the right for the grid lines.
+                    let cell_rect = Rect { pos: vec2(x, y), size: vec2(width - 1., self.row_height) };
+                    layer.add_rect(cell_rect, color);
+                    layer.add_text(cx, &source.cell_text(row, column), cell_rect, COLOR_TEXT);
+                }
+            }
+            layer
+        };
+        let headers = |cx: &Cx, positions: &[usize], frozen: bool| {
+            let mut layer = DataGridLayer::default();
+            for &position in positions {
+                let column = self.column_order[position];
+                let cell_rect = Rect {
+                    pos: vec2(origin.x + self.column_x(position), origin.y),
+                    size: vec2(self.column_width(position) - 1., self.header_height - 1.),
+                };
+                layer.add_rect(cell_rect, COLOR_HEADER);
+                let label = match self.sort {
+                    Some((sort_column, DataGridSortDirection::Ascending)) if sort_column == column => {
+                        format!("{} ↑", self.columns[column].label)
+                    }
+                    Some((sort_column, DataGridSortDirection::Descending)) if sort_column == column => {
+                        format!("{} ↓", self.columns[column].label)
+                    }
+                    _ => self.columns[column].label.clone(),
+                };
+                layer.add_text(cx, &label, cell_rect, COLOR_TEXT);
+            }
+            if let Some(drop_position) = self.drop_position {
+                // Draw the marker on the boundary with the frozen columns on top of them.
+                if frozen == (frozen_count > 0 && drop_position <= frozen_count) {
+                    let x = origin.x + self.column_x(drop_position) - 1.;
+                    layer.add_rect(Rect { pos: vec2(x, origin.y), size: vec2(2., self.header_height) }, COLOR_DROP_MARKER);
+                }
+            }
+            layer
+        };
+
+        // The grid lines show through between cells. This doesn't scroll, so it covers the whole view unless the
+        // grid is smaller than that.
+        let height = rect.size.y.min(self.header_height + row_count as f32 * self.row_height);
+        let mut background = DataGridLayer::default();
+        background.add_rect(Rect { pos: origin, size: vec2(rect.size.x.min(total_width), height) }, COLOR_GRID_LINES);
+        background.draw(cx, true, true);
+
+        // Draw from back to front: the scrolling cells, the frozen cells, the header, and the frozen headers.
+        cells(cx, &scrolling_positions).draw(cx, false, false);
+        if frozen_count > 0 {
+            let mut layer = DataGridLayer::default();
+            layer.add_rect(Rect { pos: origin, size: vec2(frozen_width, height) }, COLOR_GRID_LINES);
+            layer.draw(cx, true, true);
+            cells(cx, &frozen_positions).draw(cx, true, false);
+        }
+        headers(cx, &scrolling_positions, false).draw(cx, false, true);
+        if frozen_count > 0 {
+            headers(cx, &frozen_positions, true).draw(cx, true, true);
+        }
+
+        self.view.end_view(cx);
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::cell::Cell;
+
+    use zaplib::*;
+
+    use crate::*;
+
+    /// A million rows, of which the cells are computed and counted when requested.
+    #[derive(Default)]
+    struct TestSource {
+        requested_cells: Cell<usize>,
+    }
+
+    impl DataGridSource for TestSource {
+        fn row_count(&self) -> usize {
+            1_000_000
+        }
+
+        fn cell_text(&self, row: usize, column: usize) -> String {
+            self.requested_cells.set(self.requested_cells.get() + 1);
+            format!("{}:{}", row, column)
+        }
+    }
+
+    struct DataGridTestApp {
+        grid: DataGrid,
+        source: TestSource,
+        events: Vec<DataGridEvent>,
+    }
+
+    impl MainApp for DataGridTestApp {
+        fn new(_cx: &mut Cx) -> Self {
+            let columns = (0..20)
+                .map(|i| DataGridColumn { label: format!("Column {}", i), width: 60., ..DataGridColumn::default() })
+                .collect();
+            Self {
+                grid: DataGrid::default().with_columns(columns).with_frozen_columns(1),
+                source: TestSource::default(),
+                events: vec![],
+            }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            let event = self.grid.handle(cx, event, &self.source);
+            if event != DataGridEvent::None {
+                self.events.push(event);
+            }
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            self.grid.draw(cx, &self.source);
+        }
+    }
+
+    #[test]
+    fn test_data_grid() {
+        let mut test = AppTest::<DataGridTestApp>::new_in_window(vec2(240., 100.));
+        // Only the visible cells are requested.
+        assert!(test.app.source.requested_cells.get() < 100);
+
+        // Select a range with the pointer and the keyboard.
+        test.click(vec2(90., 50.));
+        assert_eq!(test.app.events, vec![DataGridEvent::SelectionChanged]);
+        test.set_modifiers(KeyModifiers { shift: true, ..KeyModifiers::default() });
+        test.key_press(KeyCode::ArrowDown);
+        test.key_press(KeyCode::ArrowRight);
+        test.set_modifiers(KeyModifiers::default());
+        test.next_frame();
+        assert_eq!(test.app.grid.selected_rows(), 1..3);
+        assert_eq!(test.app.grid.selected_columns(), vec![1, 2]);
+        assert_eq!(test.app.grid.selection_to_tsv(&test.app.source), "1:1\t1:2\n2:1\t2:2\n");
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/data_grid_selection.png"));
+
+        // Jumping to the last row scrolls it into view, while the header and the frozen column stay in place.
+        test.key_press(KeyCode::End);
+        test.set_modifiers(KeyModifiers { control: true, ..KeyModifiers::default() });
+        test.key_press(KeyCode::End);
+        test.set_modifiers(KeyModifiers::default());
+        test.next_frame();
+        assert_eq!(test.app.grid.selected_rows(), 999_999..1_000_000);
+        assert_eq!(test.app.grid.selected_columns(), vec![19]);
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/data_grid_scrolled.png"));
+    }
+
+    #[test]
+    fn test_data_grid_headers() {
+        let mut test = AppTest::<DataGridTestApp>::new_in_window(vec2(240., 100.));
+
+        // Clicking on a header sorts on it, and clicking again reverses the direction.
+        test.click(vec2(90., 10.));
+        test.click(vec2(90., 10.));
+        assert_eq!(
+            test.app.events,
+            vec![
+                DataGridEvent::Sort { column: 1, direction: DataGridSortDirection::Ascending },
+                DataGridEvent::Sort { column: 1, direction: DataGridSortDirection::Descending },
+            ]
+        );
+
+        // Dragging the right edge of a header resizes the column.
+        test.pointer_down(vec2(120., 10.));
+        test.pointer_move(vec2(150., 10.));
+        test.pointer_up();
+        assert_eq!(test.app.grid.columns[1].width, 90.);
+
+        // Dragging a header elsewhere moves the column.
+        test.pointer_down(vec2(170., 10.));
+        test.pointer_move(vec2(70., 10.));
+        test.pointer_up();
+        assert_eq!(&test.app.grid.column_order()[..4], &[0, 2, 1, 3]);
+    }
+}
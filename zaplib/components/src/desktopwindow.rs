@@ -1,6 +1,7 @@
 use crate::background::*;
 use crate::desktopbutton::*;
 use crate::ButtonEvent;
+use crate::MenuBar;
 use zaplib::*;
 
 pub struct DesktopWindow {
@@ -23,6 +24,8 @@ pub struct DesktopWindow {
     pub caption: String,
 
     pub default_menu: Menu,
+    /// Draws the menu passed to [`DesktopWindow::begin_draw`] on platforms without native menus.
+    menu_bar: MenuBar,
 
     pub start_pos: Option<Vec2>,
 
@@ -56,6 +59,7 @@ impl Default for DesktopWindow {
             fullscreen_btn: DesktopButton::default(),
 
             default_menu: Menu::main(vec![Menu::sub("App", vec![Menu::item("Quit App", Cx::COMMAND_QUIT)])]),
+            menu_bar: MenuBar::default(),
             //caption_bg_color: Color_bg_selected_over::id(cx),
             caption_bg: Background::default(),
             caption_size: Vec2::default(),
@@ -78,6 +82,8 @@ impl DesktopWindow {
     pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> DesktopWindowEvent {
         //self.main_view.handle_scroll_bars(cx, event);
         //self.inner_view.handle_scroll_bars(cx, event);
+        self.menu_bar.handle(cx, event);
+
         if let ButtonEvent::Clicked = self.xr_btn.handle(cx, event) {
             if self.window.xr_is_presenting(cx) {
                 self.window.xr_stop_presenting(cx);
@@ -122,7 +128,13 @@ impl DesktopWindow {
                     true
                 }
                 Event::WindowDragQuery(dq) => {
-                    if dq.window_id == window_id && dq.abs.x < self.caption_size.x && dq.abs.y < self.caption_size.y {
+                    let on_menu_bar =
+                        self.menu_bar.area().get_rect_for_first_instance(cx).map_or(false, |rect| rect.contains(dq.abs));
+                    if dq.window_id == window_id
+                        && !on_menu_bar
+                        && dq.abs.x < self.caption_size.x
+                        && dq.abs.y < self.caption_size.y
+                    {
                         if dq.abs.x < 50. {
                             dq.response = WindowDragQueryResponse::SysMenu;
                         } else {
@@ -168,10 +180,8 @@ impl DesktopWindow {
                 PlatformType::Windows | PlatformType::Unknown | PlatformType::Linux { .. } => {
                     self.caption_bg.begin_draw(cx, Width::Fill, Height::Compute, color);
 
-                    // we need to draw the window menu here.
-                    if let Some(_menu) = menu {
-                        // lets draw the thing, check with the clone if it changed
-                        // then draw it
+                    if let Some(menu) = menu {
+                        self.draw_menu_bar(cx, menu);
                     }
                     cx.begin_right_box();
                     self.min_btn.draw(cx, DesktopButtonType::WindowsMin);
@@ -207,13 +217,22 @@ impl DesktopWindow {
                     self.caption_bg.end_draw(cx);
                 }
                 PlatformType::Web { .. } => {
-                    if self.window.is_fullscreen(cx) {
+                    if let Some(menu) = menu {
+                        self.caption_bg.begin_draw(cx, Width::Fill, Height::Compute, color);
+                        self.draw_menu_bar(cx, menu);
+                        self.caption_bg.end_draw(cx);
+                    } else if self.window.is_fullscreen(cx) {
                         // put a bar at the top
                         let rect = cx.add_box(LayoutSize::new(Width::Fill, Height::Fix(22.)));
                         self.caption_bg.draw(cx, rect, color);
                     }
                 }
             }
+        } else if let Some(menu) = menu {
+            // The native window chrome on Linux doesn't come with menus either.
+            self.caption_bg.begin_draw(cx, Width::Fill, Height::Compute, vec4(0.24, 0.24, 0.24, 1.0));
+            self.draw_menu_bar(cx, menu);
+            self.caption_bg.end_draw(cx);
         }
         self.caption_view.end_view(cx);
 
@@ -224,6 +243,14 @@ impl DesktopWindow {
         cx.begin_row(Width::Fill, Height::Fill);
     }
 
+    fn draw_menu_bar(&mut self, cx: &mut Cx, menu: &Menu) {
+        if self.menu_bar.menu != *menu {
+            self.menu_bar.close(cx);
+            self.menu_bar.menu = menu.clone();
+        }
+        self.menu_bar.draw(cx);
+    }
+
     pub fn end_draw(&mut self, cx: &mut Cx) {
         cx.end_row();
         self.inner_view.end_view(cx);
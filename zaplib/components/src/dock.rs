@@ -916,3 +916,81 @@ where
         &mut redraw.item
     }
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use crate::*;
+    use zaplib::*;
+
+    #[derive(Clone)]
+    enum Panel {
+        Red,
+        Green,
+        Blue,
+    }
+
+    struct DockTestApp {
+        dock: Dock<Panel>,
+        dock_items: DockItem<Panel>,
+        panel_bg: Background,
+    }
+
+    impl MainApp for DockTestApp {
+        fn new(_cx: &mut Cx) -> Self {
+            let tab = |title: &str, item: Panel| DockTab { closeable: false, title: title.to_string(), item };
+            Self {
+                dock: Dock::default(),
+                dock_items: DockItem::Splitter {
+                    axis: Axis::Vertical,
+                    align: SplitterAlign::First,
+                    pos: 120.,
+                    first: Box::new(DockItem::TabControl {
+                        current: 0,
+                        previous: 0,
+                        tabs: vec![tab("Red", Panel::Red), tab("Green", Panel::Green)],
+                    }),
+                    last: Box::new(DockItem::TabControl { current: 0, previous: 0, tabs: vec![tab("Blue", Panel::Blue)] }),
+                },
+                panel_bg: Background::default(),
+            }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            let mut dock_walker = self.dock.walker(&mut self.dock_items);
+            while dock_walker.walk_handle_dock(cx, event).is_some() {}
+            self.dock.handle(cx, event, &mut self.dock_items);
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            self.dock.draw(cx);
+            let mut dock_walker = self.dock.walker(&mut self.dock_items);
+            while let Some(item) = dock_walker.walk_draw_dock(cx, |cx, tab_control, tab, selected| {
+                tab_control.draw_tab(cx, &tab.title, selected, tab.closeable)
+            }) {
+                let color = match item {
+                    Panel::Red => vec4(0.8, 0.2, 0.2, 1.),
+                    Panel::Green => vec4(0.2, 0.8, 0.2, 1.),
+                    Panel::Blue => vec4(0.2, 0.2, 0.8, 1.),
+                };
+                self.panel_bg.begin_draw(cx, Width::Fill, Height::Fill, color);
+                self.panel_bg.end_draw(cx);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dock_select_tab() {
+        let mut test = AppTest::<DockTestApp>::new_in_window(vec2(160., 80.));
+        test.assert_golden_draw_tree(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/dock_initial.txt"));
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/dock_initial.png"));
+
+        test.click(vec2(80., 20.));
+        test.advance_time(1.0);
+        match &test.app.dock_items {
+            DockItem::Splitter { first, .. } => assert!(matches!(first.as_ref(), DockItem::TabControl { current: 1, .. })),
+            DockItem::TabControl { .. } => panic!(),
+        }
+        test.assert_golden_draw_tree(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/dock_selected.txt"));
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/dock_selected.png"));
+    }
+}
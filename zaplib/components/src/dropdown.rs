@@ -0,0 +1,571 @@
+//! Select boxes: a [`Dropdown`] for picking one or more fixed options, and the list of options in a [`Popover`]
+//! that it shares with [`crate::ComboBox`].
+
+use crate::*;
+use zaplib::*;
+
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+struct ArrowIns {
+    base: QuadIns,
+    color: Vec4,
+}
+
+static ARROW_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            instance color: vec4;
+
+            fn pixel() -> vec4 {
+                let df = Df::viewport(pos * 10.);
+                df.triangle(vec2(2., 3.), vec2(8., 3.), vec2(5., 7.));
+                df.fill(color);
+                return df.result;
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+const OPTION_HEIGHT: f32 = 22.;
+const LIST_PADDING: f32 = 4.;
+const CHECKBOX_SIZE: f32 = 10.;
+const SCROLL_THUMB_WIDTH: f32 = 4.;
+const BUTTON_HEIGHT: f32 = 26.;
+const ARROW_SIZE: f32 = 10.;
+
+const COLOR_BUTTON: Vec4 = vec4(0.2, 0.2, 0.2, 1.);
+const COLOR_BUTTON_HOVER: Vec4 = vec4(0.25, 0.25, 0.25, 1.);
+const COLOR_BUTTON_FOCUS: Vec4 = vec4(0.22, 0.27, 0.34, 1.);
+const COLOR_LIST: Vec4 = vec4(0.14, 0.14, 0.14, 1.);
+const COLOR_HIGHLIGHT: Vec4 = vec4(0.07, 0.27, 0.43, 1.);
+const COLOR_CHECKBOX: Vec4 = vec4(0.5, 0.5, 0.5, 1.);
+const COLOR_CHECKED: Vec4 = vec4(0.4, 0.6, 1., 1.);
+const COLOR_SCROLL_THUMB: Vec4 = vec4(0.35, 0.35, 0.35, 1.);
+const COLOR_ARROW: Vec4 = vec4(0.6, 0.6, 0.6, 1.);
+const COLOR_TEXT: Vec4 = vec4(0.85, 0.85, 0.85, 1.);
+const COLOR_PLACEHOLDER: Vec4 = vec4(0.5, 0.5, 0.5, 1.);
+
+pub(crate) enum OptionListEvent {
+    None,
+    /// An option was clicked, as an index into the shown options.
+    Clicked(usize),
+}
+
+/// The list of options that a [`Dropdown`] or [`crate::ComboBox`] shows in a [`Popover`] when it is open.
+///
+/// Shows at most [`OptionList::max_visible`] options at once (fewer if they don't fit in the window), and scrolls to
+/// show the rest.
+pub(crate) struct OptionList {
+    component_id: ComponentId,
+    popover: Popover,
+    pub(crate) max_visible: usize,
+    /// The option that is hovered or moved to with the keyboard, as an index into the shown options.
+    pub(crate) highlighted: Option<usize>,
+    first_visible: usize,
+    /// How many options fit in the list, as of the last draw.
+    visible: usize,
+    /// Scroll distance that didn't add up to a whole option yet.
+    scroll_remainder: f32,
+}
+
+impl Default for OptionList {
+    fn default() -> Self {
+        Self {
+            component_id: Default::default(),
+            popover: Popover::default(),
+            max_visible: 8,
+            highlighted: None,
+            first_visible: 0,
+            visible: 8,
+            scroll_remainder: 0.,
+        }
+    }
+}
+
+impl OptionList {
+    fn rect(&self, cx: &Cx) -> Option<Rect> {
+        self.popover.area().get_rect_for_first_instance(cx)
+    }
+
+    /// Whether `pos` (in window coordinates) is on the list. Only meaningful while it is being drawn.
+    pub(crate) fn contains(&self, cx: &Cx, pos: Vec2) -> bool {
+        self.rect(cx).map_or(false, |rect| rect.contains(pos))
+    }
+
+    /// Reset the highlight and scroll position, e.g. when opening the list.
+    pub(crate) fn reset(&mut self, highlighted: Option<usize>, len: usize) {
+        self.first_visible = 0;
+        self.scroll_remainder = 0.;
+        self.highlighted = None;
+        if let Some(index) = highlighted {
+            self.highlight(index, len);
+        }
+    }
+
+    /// Highlight the option at `index` (clamped to `len`), and scroll it into view.
+    pub(crate) fn highlight(&mut self, index: usize, len: usize) {
+        if len == 0 {
+            self.highlighted = None;
+            return;
+        }
+        let index = index.min(len - 1);
+        self.highlighted = Some(index);
+        self.scroll_into_view(index);
+    }
+
+    fn scroll_into_view(&mut self, index: usize) {
+        if index < self.first_visible {
+            self.first_visible = index;
+        } else if index >= self.first_visible + self.visible {
+            self.first_visible = index + 1 - self.visible;
+        }
+    }
+
+    /// Handle keys that move the highlight around, returning whether the key was handled.
+    pub(crate) fn handle_navigation_key(&mut self, cx: &mut Cx, key_code: KeyCode, len: usize) -> bool {
+        let last = len.max(1) - 1;
+        let index = match (key_code, self.highlighted) {
+            (KeyCode::ArrowDown, Some(index)) => index + 1,
+            (KeyCode::ArrowUp, Some(index)) => index.max(1) - 1,
+            (KeyCode::ArrowDown | KeyCode::Home, None) | (KeyCode::Home, _) => 0,
+            (KeyCode::ArrowUp | KeyCode::End, None) | (KeyCode::End, _) => last,
+            (KeyCode::PageDown, highlighted) => highlighted.unwrap_or(0) + self.visible,
+            (KeyCode::PageUp, highlighted) => highlighted.unwrap_or(0).max(self.visible) - self.visible,
+            _ => return false,
+        };
+        self.highlight(index, len);
+        cx.request_draw();
+        true
+    }
+
+    pub(crate) fn handle(&mut self, cx: &mut Cx, event: &mut Event, len: usize) -> OptionListEvent {
+        let index_at = |first_visible: usize, rel: Vec2| {
+            let index = first_visible + ((rel.y - LIST_PADDING) / OPTION_HEIGHT).max(0.) as usize;
+            if index < len {
+                Some(index)
+            } else {
+                None
+            }
+        };
+        match event.hits_pointer(cx, self.component_id, self.rect(cx)) {
+            Event::PointerHover(pe) => {
+                let highlighted =
+                    if pe.hover_state == HoverState::Out { self.highlighted } else { index_at(self.first_visible, pe.rel) };
+                if highlighted != self.highlighted {
+                    self.highlighted = highlighted;
+                    cx.request_draw();
+                }
+            }
+            Event::PointerDown(pe) => {
+                if let Some(index) = index_at(self.first_visible, pe.rel) {
+                    return OptionListEvent::Clicked(index);
+                }
+            }
+            Event::PointerScroll(pe) => {
+                let max_first_visible = len.max(self.visible) - self.visible;
+                self.scroll_remainder += pe.scroll.y;
+                let rows = (self.scroll_remainder / OPTION_HEIGHT).trunc();
+                self.scroll_remainder -= rows * OPTION_HEIGHT;
+                let first_visible = (self.first_visible as f32 + rows).max(0.) as usize;
+                if first_visible.min(max_first_visible) != self.first_visible {
+                    self.first_visible = first_visible.min(max_first_visible);
+                    cx.request_draw();
+                }
+            }
+            _ => (),
+        }
+        OptionListEvent::None
+    }
+
+    /// Draw the list below (or above) `anchor`, which is in window coordinates, with the same width as `anchor`.
+    ///
+    /// `checked` is `None` to draw the options without checkboxes, or a function that returns whether the option
+    /// at some index of `labels` is checked.
+    pub(crate) fn draw(&mut self, cx: &mut Cx, anchor: Rect, labels: &[&str], checked: Option<&dyn Fn(usize) -> bool>) {
+        // Use whichever side of the anchor has the most room, which is where the popover will go if neither fits.
+        let room = anchor.pos.y.max(cx.get_pass_size().y - anchor.pos.y - anchor.size.y) - 2. * LIST_PADDING;
+        let visible = labels.len().min(self.max_visible).min(((room / OPTION_HEIGHT) as usize).max(1));
+        self.visible = visible;
+        self.first_visible = self.first_visible.min(labels.len() - visible);
+        if let Some(highlighted) = self.highlighted {
+            self.scroll_into_view(highlighted);
+        }
+        let size = vec2(anchor.size.x, visible as f32 * OPTION_HEIGHT + 2. * LIST_PADDING);
+        let rect = self.popover.begin_draw_anchored(cx, anchor, size, COLOR_LIST);
+
+        let rows = self.first_visible..self.first_visible + visible;
+        let row_rect = |index: usize| Rect {
+            pos: rect.pos + vec2(0., LIST_PADDING + (index - self.first_visible) as f32 * OPTION_HEIGHT),
+            size: vec2(rect.size.x, OPTION_HEIGHT),
+        };
+        let text_x = if checked.is_some() { 2. * CHECKBOX_SIZE } else { 8. };
+
+        if let Some(highlighted) = self.highlighted.filter(|index| rows.contains(index)) {
+            // These all get batched into the same draw call as the popover background, so give them increasing draw
+            // depths to keep them from fighting over which one is on top.
+            Background::default().with_draw_depth(0.1).draw(cx, row_rect(highlighted), COLOR_HIGHLIGHT);
+        }
+        if let Some(checked) = checked {
+            for index in rows.clone() {
+                let pos = row_rect(index).pos + vec2(CHECKBOX_SIZE / 2., (OPTION_HEIGHT - CHECKBOX_SIZE) / 2.);
+                Background::default().with_draw_depth(0.2).draw(
+                    cx,
+                    Rect { pos, size: vec2(CHECKBOX_SIZE, CHECKBOX_SIZE) },
+                    COLOR_CHECKBOX,
+                );
+                let inner = if checked(index) { COLOR_CHECKED } else { COLOR_LIST };
+                let inner_rect = Rect { pos: pos + vec2(1.5, 1.5), size: vec2(CHECKBOX_SIZE - 3., CHECKBOX_SIZE - 3.) };
+                Background::default().with_draw_depth(0.3).draw(cx, inner_rect, inner);
+            }
+        }
+        if labels.len() > visible {
+            let thumb_height = size.y * visible as f32 / labels.len() as f32;
+            let thumb_y = size.y * self.first_visible as f32 / labels.len() as f32;
+            let thumb_rect = Rect {
+                pos: rect.pos + vec2(rect.size.x - SCROLL_THUMB_WIDTH - 1., thumb_y),
+                size: vec2(SCROLL_THUMB_WIDTH, thumb_height),
+            };
+            Background::default().with_radius(2.).with_draw_depth(0.1).draw(cx, thumb_rect, COLOR_SCROLL_THUMB);
+        }
+
+        let props = TextInsProps {
+            color: COLOR_TEXT,
+            position_anchoring: TEXT_ANCHOR_CENTER_V,
+            wrapping: Wrapping::Ellipsis(rect.size.x - text_x - SCROLL_THUMB_WIDTH - 4.),
+            draw_depth: 0.4,
+            ..TextInsProps::DEFAULT
+        };
+        for index in rows.clone() {
+            let pos = row_rect(index).pos + vec2(text_x, OPTION_HEIGHT / 2.);
+            TextIns::draw_str(cx, labels[index], pos, &props);
+        }
+
+        self.popover.end_draw_anchored(cx);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DropdownEvent {
+    None,
+    /// The selection changed; see [`Dropdown::selected`].
+    Changed,
+}
+
+/// A select box, which shows a list of options in a [`Popover`] when clicked.
+///
+/// In single select mode picking an option replaces the selection and closes the list. With
+/// [`Dropdown::with_multi_select`] the options get checkboxes, and picking one toggles it while keeping the list
+/// open.
+///
+/// Keyboard: Return, Space, and the arrow keys open the list; the arrow keys, Home, End, Page Up, and Page Down
+/// move through it; Return and Space pick the highlighted option; and Escape or Tab close it.
+///
+/// Like [`Popover::handle`] says, call [`Dropdown::handle`] before the event handlers of whatever
+/// is drawn underneath the list, so that clicks on the list don't leak through.
+pub struct Dropdown {
+    component_id: ComponentId,
+    pub options: Vec<String>,
+    pub multi_select: bool,
+    /// Shown when nothing is selected.
+    pub placeholder: String,
+    pub width: f32,
+    /// Always sorted.
+    selected: Vec<usize>,
+    is_open: bool,
+    is_hovered: bool,
+    button: Background,
+    list: OptionList,
+}
+
+impl Default for Dropdown {
+    fn default() -> Self {
+        Self {
+            component_id: Default::default(),
+            options: vec![],
+            multi_select: false,
+            placeholder: String::new(),
+            width: 160.,
+            selected: vec![],
+            is_open: false,
+            is_hovered: false,
+            button: Background::default().with_radius(2.5),
+            list: OptionList::default(),
+        }
+    }
+}
+
+impl Dropdown {
+    #[must_use]
+    pub fn with_options(self, options: Vec<String>) -> Self {
+        Self { options, ..self }
+    }
+
+    #[must_use]
+    pub fn with_multi_select(self, multi_select: bool) -> Self {
+        Self { multi_select, ..self }
+    }
+
+    #[must_use]
+    pub fn with_placeholder(self, placeholder: &str) -> Self {
+        Self { placeholder: placeholder.to_string(), ..self }
+    }
+
+    #[must_use]
+    pub fn with_width(self, width: f32) -> Self {
+        Self { width, ..self }
+    }
+
+    /// The indices of the selected options, in ascending order.
+    pub fn selected(&self) -> &[usize] {
+        &self.selected
+    }
+
+    /// Replace the selection. In single select mode only the first index is used.
+    pub fn set_selected(&mut self, cx: &mut Cx, mut selected: Vec<usize>) {
+        selected.retain(|&index| index < self.options.len());
+        selected.sort_unstable();
+        selected.dedup();
+        if !self.multi_select {
+            selected.truncate(1);
+        }
+        self.selected = selected;
+        cx.request_draw();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    pub fn open(&mut self, cx: &mut Cx) {
+        if !self.is_open && !self.options.is_empty() {
+            self.is_open = true;
+            self.list.reset(Some(self.selected.first().copied().unwrap_or(0)), self.options.len());
+            cx.request_draw();
+        }
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        if self.is_open {
+            self.is_open = false;
+            cx.request_draw();
+        }
+    }
+
+    fn pick(&mut self, cx: &mut Cx, index: usize) -> DropdownEvent {
+        cx.request_draw();
+        if self.multi_select {
+            match self.selected.binary_search(&index) {
+                Ok(position) => {
+                    self.selected.remove(position);
+                }
+                Err(position) => self.selected.insert(position, index),
+            }
+            DropdownEvent::Changed
+        } else {
+            self.close(cx);
+            if self.selected == [index] {
+                return DropdownEvent::None;
+            }
+            self.selected = vec![index];
+            DropdownEvent::Changed
+        }
+    }
+
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> DropdownEvent {
+        if self.is_open {
+            if let OptionListEvent::Clicked(index) = self.list.handle(cx, event, self.options.len()) {
+                cx.set_key_focus(Some(self.component_id));
+                return self.pick(cx, index);
+            }
+            if let Event::PointerDown(pe) = event {
+                let on_button = self.button.area().get_rect_for_first_instance(cx).map_or(false, |rect| rect.contains(pe.abs));
+                if !on_button && !self.list.contains(cx, pe.abs) {
+                    self.close(cx);
+                }
+            }
+        }
+
+        match event.hits_pointer(cx, self.component_id, self.button.area().get_rect_for_first_instance(cx)) {
+            Event::PointerHover(pe) => {
+                cx.set_hover_mouse_cursor(MouseCursor::Hand);
+                let is_hovered = pe.hover_state != HoverState::Out;
+                if is_hovered != self.is_hovered {
+                    self.is_hovered = is_hovered;
+                    cx.request_draw();
+                }
+            }
+            Event::PointerDown(_) => {
+                cx.set_key_focus(Some(self.component_id));
+                if self.is_open {
+                    self.close(cx);
+                } else {
+                    self.open(cx);
+                }
+            }
+            _ => (),
+        }
+
+        match event.hits_keyboard(cx, self.component_id) {
+            Event::KeyFocus(_) => cx.request_draw(),
+            Event::KeyFocusLost(_) => {
+                self.close(cx);
+                cx.request_draw();
+            }
+            Event::KeyDown(ke) => match ke.key_code {
+                KeyCode::Return | KeyCode::Space if self.is_open => {
+                    if let Some(index) = self.list.highlighted {
+                        return self.pick(cx, index);
+                    }
+                }
+                KeyCode::Return | KeyCode::Space | KeyCode::ArrowDown | KeyCode::ArrowUp if !self.is_open => self.open(cx),
+                KeyCode::Escape | KeyCode::Tab => self.close(cx),
+                key_code if self.is_open => {
+                    self.list.handle_navigation_key(cx, key_code, self.options.len());
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+        DropdownEvent::None
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx) {
+        let rect = cx.add_box(LayoutSize::new(Width::Fix(self.width), Height::Fix(BUTTON_HEIGHT)));
+        let color = if cx.has_key_focus(Some(self.component_id)) {
+            COLOR_BUTTON_FOCUS
+        } else if self.is_hovered {
+            COLOR_BUTTON_HOVER
+        } else {
+            COLOR_BUTTON
+        };
+        self.button.draw(cx, rect, color);
+
+        let arrow_pos = rect.pos + vec2(rect.size.x - ARROW_SIZE - 8., (rect.size.y - ARROW_SIZE) / 2.);
+        let arrow = ArrowIns {
+            base: QuadIns::from_rect(Rect { pos: arrow_pos, size: vec2(ARROW_SIZE, ARROW_SIZE) }),
+            color: COLOR_ARROW,
+        };
+        cx.add_instances(&ARROW_SHADER, &[arrow]);
+
+        let label = self.selected.iter().map(|&index| self.options[index].as_str()).collect::<Vec<_>>().join(", ");
+        let (label, color) =
+            if label.is_empty() { (self.placeholder.as_str(), COLOR_PLACEHOLDER) } else { (label.as_str(), COLOR_TEXT) };
+        let props = TextInsProps {
+            color,
+            position_anchoring: TEXT_ANCHOR_CENTER_V,
+            wrapping: Wrapping::Ellipsis(rect.size.x - ARROW_SIZE - 24.),
+            ..TextInsProps::DEFAULT
+        };
+        TextIns::draw_str(cx, label, rect.pos + vec2(8., rect.size.y / 2.), &props);
+
+        if self.is_open {
+            let anchor = self.button.area().get_rect_for_first_instance(cx).unwrap_or(rect);
+            let labels: Vec<&str> = self.options.iter().map(|option| option.as_str()).collect();
+            if self.multi_select {
+                let selected = &self.selected;
+                self.list.draw(cx, anchor, &labels, Some(&|index| selected.binary_search(&index).is_ok()));
+            } else {
+                self.list.draw(cx, anchor, &labels, None);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use zaplib::*;
+
+    use crate::*;
+
+    struct DropdownTestApp {
+        top: Dropdown,
+        bottom: Dropdown,
+        events: Vec<(&'static str, DropdownEvent)>,
+    }
+
+    impl MainApp for DropdownTestApp {
+        fn new(_cx: &mut Cx) -> Self {
+            let options = |count: usize| (1..=count).map(|i| format!("Option {}", i)).collect();
+            Self {
+                top: Dropdown::default().with_options(options(12)).with_placeholder("Pick one"),
+                bottom: Dropdown::default().with_options(options(4)).with_multi_select(true).with_placeholder("Pick some"),
+                events: vec![],
+            }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            let event_top = self.top.handle(cx, event);
+            let event_bottom = self.bottom.handle(cx, event);
+            for (name, event) in [("top", event_top), ("bottom", event_bottom)] {
+                if event != DropdownEvent::None {
+                    self.events.push((name, event));
+                }
+            }
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            for (dropdown, y) in [(&mut self.bottom, 150.), (&mut self.top, 10.)] {
+                cx.begin_absolute_box();
+                cx.begin_padding_box(Padding { l: 10., t: y, r: 0., b: 0. });
+                dropdown.draw(cx);
+                cx.end_padding_box();
+                cx.end_absolute_box();
+            }
+        }
+    }
+
+    #[test]
+    fn test_dropdown() {
+        let mut test = AppTest::<DropdownTestApp>::new_in_window(vec2(200., 200.));
+
+        // Open with a click, and pick an option with the keyboard.
+        test.click(vec2(50., 23.));
+        assert!(test.app.top.is_open());
+        test.key_press(KeyCode::ArrowDown);
+        test.key_press(KeyCode::ArrowDown);
+        test.key_press(KeyCode::Return);
+        assert!(!test.app.top.is_open());
+        assert_eq!(test.app.top.selected(), &[2]);
+        assert_eq!(test.app.events, vec![("top", DropdownEvent::Changed)]);
+
+        // The list scrolls to keep the highlighted option visible.
+        test.key_press(KeyCode::Space);
+        test.key_press(KeyCode::End);
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/dropdown_open.png"));
+        test.key_press(KeyCode::Escape);
+        assert!(!test.app.top.is_open());
+        assert_eq!(test.app.top.selected(), &[2]);
+
+        // Clicking outside of the list closes it.
+        test.click(vec2(50., 23.));
+        assert!(test.app.top.is_open());
+        test.click(vec2(190., 100.));
+        assert!(!test.app.top.is_open());
+    }
+
+    #[test]
+    fn test_dropdown_multi_select_flipped() {
+        let mut test = AppTest::<DropdownTestApp>::new_in_window(vec2(200., 200.));
+
+        // There is no room below the bottom dropdown, so the list goes above it. Picking options keeps it open.
+        test.click(vec2(50., 163.));
+        test.click(vec2(50., 91.));
+        test.click(vec2(50., 135.));
+        assert!(test.app.bottom.is_open());
+        assert_eq!(test.app.bottom.selected(), &[1, 3]);
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/dropdown_flipped.png"));
+
+        // Clicking an option moved the key focus back to the dropdown, so the keyboard still works.
+        test.key_press(KeyCode::ArrowUp);
+        test.key_press(KeyCode::Return);
+        assert_eq!(test.app.bottom.selected(), &[1, 2, 3]);
+        test.key_press(KeyCode::Tab);
+        assert!(!test.app.bottom.is_open());
+        assert_eq!(test.app.events, vec![("bottom", DropdownEvent::Changed); 3]);
+    }
+}
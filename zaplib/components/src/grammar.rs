@@ -0,0 +1,464 @@
+//! Data-driven [`Tokenizer`]s, so that highlighting a new language doesn't require writing a tokenizer by hand.
+//!
+//! A [`Grammar`] is a small state machine. Every [`GrammarState`] has a list of [`GrammarRule`]s that get tried in
+//! order at the current position, and rules can enter and leave states, to tokenize e.g. the inside of strings and
+//! multi-line comments differently. See [`crate::RUST_GRAMMAR`] and the other built-in grammars for examples.
+
+use crate::tokentype::*;
+
+/// What a [`GrammarRule`] matches at the current position. Patterns never match across lines.
+#[derive(Clone, Copy, Debug)]
+pub enum GrammarPattern {
+    /// This exact text, like `"/*"` or `"::"`.
+    Literal(&'static str),
+    /// This text and everything after it up to the end of the line, like line comments.
+    ToEndOfLine(&'static str),
+    /// One of these words, when not part of a longer identifier, like keywords.
+    Words(&'static [&'static str]),
+    /// A letter or `_`, followed by any number of letters, digits, and `_`.
+    Identifier,
+    /// An identifier that starts with an uppercase letter, like type names in many languages.
+    CapitalizedIdentifier,
+    /// An identifier that is directly followed by this char, which itself is not part of the match. Like `(` for
+    /// function calls, or `!` for Rust macros.
+    IdentifierBefore(char),
+    /// This char followed by an identifier, like Rust lifetimes.
+    PrefixedIdentifier(char),
+    /// A number like `12`, `0xff`, or `1.5e-3`, including any suffix like `u32` or `f`.
+    Number,
+    /// A single quoted char like `'a'` or `'\n'`.
+    CharLiteral,
+    /// A backslash and the char after it.
+    Escape,
+    /// Spaces and tabs.
+    Whitespace,
+    /// Any single one of these chars.
+    AnyOf(&'static str),
+}
+
+/// What to do after a [`GrammarRule`] matched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GrammarAction {
+    /// Stay in the current state.
+    None,
+    /// Enter the state with this index in [`Grammar::states`].
+    Push(usize),
+    /// Go back to the state that was current before the last [`GrammarAction::Push`].
+    Pop,
+}
+
+/// A [`GrammarPattern`] that produces a token of a [`TokenType`].
+#[derive(Clone, Copy, Debug)]
+pub struct GrammarRule {
+    pub pattern: GrammarPattern,
+    pub token_type: TokenType,
+    pub action: GrammarAction,
+}
+
+impl GrammarRule {
+    pub const fn new(pattern: GrammarPattern, token_type: TokenType) -> Self {
+        Self { pattern, token_type, action: GrammarAction::None }
+    }
+
+    /// A rule that enters the state with index `state` in [`Grammar::states`] after matching.
+    pub const fn push(pattern: GrammarPattern, token_type: TokenType, state: usize) -> Self {
+        Self { pattern, token_type, action: GrammarAction::Push(state) }
+    }
+
+    /// A rule that goes back to the previous state after matching.
+    pub const fn pop(pattern: GrammarPattern, token_type: TokenType) -> Self {
+        Self { pattern, token_type, action: GrammarAction::Pop }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GrammarState {
+    /// Tried in order; the first one that matches produces the next token.
+    pub rules: &'static [GrammarRule],
+    /// Type of text that no rule matches, like the contents of strings. Consecutive chars that don't match any rule
+    /// become a single token.
+    pub default_token_type: TokenType,
+}
+
+/// Describes how to tokenize a language; use it with a [`GrammarTokenizer`].
+#[derive(Debug)]
+pub struct Grammar {
+    pub name: &'static str,
+    /// The first state is the initial one.
+    pub states: &'static [GrammarState],
+}
+
+fn matched(condition: bool, len: usize) -> Option<usize> {
+    if condition {
+        Some(len)
+    } else {
+        None
+    }
+}
+
+/// The text at the current position: `next`, followed by `rest` up to the end of the line.
+struct Lookahead<'a> {
+    next: char,
+    rest: &'a [char],
+}
+
+impl<'a> Lookahead<'a> {
+    fn get(&self, index: usize) -> Option<char> {
+        if index == 0 {
+            Some(self.next)
+        } else {
+            self.rest.get(index - 1).copied()
+        }
+    }
+
+    fn is(&self, index: usize, f: impl Fn(char) -> bool) -> bool {
+        self.get(index).map_or(false, f)
+    }
+
+    fn count_while(&self, start: usize, f: impl Fn(char) -> bool) -> usize {
+        let mut index = start;
+        while self.is(index, &f) {
+            index += 1;
+        }
+        index
+    }
+
+    fn identifier_len(&self, start: usize) -> Option<usize> {
+        if !self.is(start, |c| c.is_alphabetic() || c == '_') {
+            return None;
+        }
+        Some(self.count_while(start + 1, |c| c.is_alphanumeric() || c == '_') - start)
+    }
+
+    fn number_len(&self) -> Option<usize> {
+        if !self.is(0, |c| c.is_ascii_digit()) {
+            return None;
+        }
+        let mut len = if self.next == '0' && self.is(1, |c| "xXbBoO".contains(c)) {
+            self.count_while(2, |c| c.is_ascii_hexdigit() || c == '_')
+        } else {
+            let mut len = self.count_while(1, |c| c.is_ascii_digit() || c == '_');
+            if self.get(len) == Some('.') && self.is(len + 1, |c| c.is_ascii_digit()) {
+                len = self.count_while(len + 1, |c| c.is_ascii_digit() || c == '_');
+            }
+            if self.is(len, |c| c == 'e' || c == 'E') {
+                let digits_start = if self.is(len + 1, |c| c == '+' || c == '-') { len + 2 } else { len + 1 };
+                if self.is(digits_start, |c| c.is_ascii_digit()) {
+                    len = self.count_while(digits_start, |c| c.is_ascii_digit());
+                }
+            }
+            len
+        };
+        // Suffix
+        len = self.count_while(len, |c| c.is_alphanumeric() || c == '_');
+        Some(len)
+    }
+
+    /// Returns the number of chars that `pattern` matches, if any.
+    fn match_len(&self, pattern: &GrammarPattern) -> Option<usize> {
+        match *pattern {
+            GrammarPattern::Literal(literal) => {
+                let mut len = 0;
+                for c in literal.chars() {
+                    if self.get(len) != Some(c) {
+                        return None;
+                    }
+                    len += 1;
+                }
+                Some(len)
+            }
+            GrammarPattern::ToEndOfLine(start) => {
+                self.match_len(&GrammarPattern::Literal(start))?;
+                Some(self.rest.len() + 1)
+            }
+            GrammarPattern::Words(words) => {
+                let len = self.identifier_len(0)?;
+                matched(words.iter().any(|word| word.chars().eq((0..len).map(|index| self.get(index).unwrap()))), len)
+            }
+            GrammarPattern::Identifier => self.identifier_len(0),
+            GrammarPattern::CapitalizedIdentifier => {
+                if self.next.is_uppercase() {
+                    self.identifier_len(0)
+                } else {
+                    None
+                }
+            }
+            GrammarPattern::IdentifierBefore(next) => {
+                let len = self.identifier_len(0)?;
+                matched(self.get(len) == Some(next), len)
+            }
+            GrammarPattern::PrefixedIdentifier(prefix) => {
+                if self.next == prefix {
+                    Some(self.identifier_len(1)? + 1)
+                } else {
+                    None
+                }
+            }
+            GrammarPattern::Number => self.number_len(),
+            GrammarPattern::CharLiteral => {
+                if self.next != '\'' {
+                    None
+                } else if self.get(1) == Some('\\') {
+                    // Escapes like '\n' or '\u{1F600}'.
+                    let len = self.count_while(3, |c| c != '\'');
+                    matched(self.get(len) == Some('\''), len + 1)
+                } else {
+                    matched(self.get(1).is_some() && self.get(2) == Some('\''), 3)
+                }
+            }
+            GrammarPattern::Escape => {
+                if self.next == '\\' {
+                    Some(if self.rest.is_empty() { 1 } else { 2 })
+                } else {
+                    None
+                }
+            }
+            GrammarPattern::Whitespace => {
+                let len = self.count_while(0, |c| c == ' ' || c == '\t');
+                matched(len > 0, len)
+            }
+            GrammarPattern::AnyOf(chars) => matched(chars.contains(self.next), 1),
+        }
+    }
+}
+
+/// A [`Tokenizer`] that follows a [`Grammar`]. Newlines always become separate [`TokenType::Newline`] tokens, while
+/// the current state carries over to the next line.
+///
+/// ```ignore
+/// text_buffer.update_token_chunks(&GrammarTokenizer::new(&RUST_GRAMMAR));
+/// ```
+#[derive(Clone, Debug)]
+pub struct GrammarTokenizer {
+    grammar: &'static Grammar,
+    /// Indices into [`Grammar::states`] of the states that were entered, with the current state last. Empty when in
+    /// the initial state.
+    state_stack: Vec<usize>,
+}
+
+impl GrammarTokenizer {
+    pub fn new(grammar: &'static Grammar) -> Self {
+        Self { grammar, state_stack: Vec::new() }
+    }
+
+    fn current_state(&self) -> &'static GrammarState {
+        &self.grammar.states[self.state_stack.last().copied().unwrap_or(0)]
+    }
+}
+
+impl PartialEq for GrammarTokenizer {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.grammar, other.grammar) && self.state_stack == other.state_stack
+    }
+}
+
+impl Tokenizer for GrammarTokenizer {
+    fn next_token<'a>(
+        &mut self,
+        state: &mut TokenizerState<'a>,
+        chunk: &mut Vec<char>,
+        _token_chunks: &[TokenChunk],
+    ) -> TokenType {
+        if state.eof {
+            state.advance();
+            chunk.push(' ');
+            return TokenType::Eof;
+        }
+        if state.next == '\n' {
+            chunk.push(state.next);
+            state.advance();
+            return TokenType::Newline;
+        }
+
+        let grammar_state = self.current_state();
+        let start = chunk.len();
+        loop {
+            let lookahead = Lookahead { next: state.next, rest: state.peek_line() };
+            let matched = grammar_state.rules.iter().find_map(|rule| Some((rule, lookahead.match_len(&rule.pattern)?)));
+            if let Some((rule, len)) = matched {
+                if chunk.len() > start {
+                    return grammar_state.default_token_type;
+                }
+                if len == 0 {
+                    // A rule that matches nothing (like `Literal("")`) would never advance, so skip a char instead.
+                    chunk.push(state.next);
+                    state.advance();
+                    return TokenType::Error;
+                }
+                for _ in 0..len {
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                match rule.action {
+                    GrammarAction::None => {}
+                    GrammarAction::Push(index) => self.state_stack.push(index),
+                    GrammarAction::Pop => {
+                        self.state_stack.pop();
+                    }
+                }
+                return rule.token_type;
+            }
+
+            chunk.push(state.next);
+            state.advance();
+            if state.next == '\n' || state.eof {
+                return grammar_state.default_token_type;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn tokens(grammar: &'static Grammar, text: &str) -> Vec<(TokenType, String)> {
+        let mut text_buffer = TextBuffer::from_utf8(text);
+        text_buffer.update_token_chunks(&GrammarTokenizer::new(grammar));
+        text_buffer
+            .iter_token_chunks()
+            .filter(|token| !matches!(token.token_chunk.token_type, TokenType::Whitespace | TokenType::Eof))
+            .map(|token| (token.token_chunk.token_type, token.chars.iter().collect()))
+            .collect()
+    }
+
+    fn token(token_type: TokenType, text: &str) -> (TokenType, String) {
+        (token_type, text.to_string())
+    }
+
+    #[test]
+    fn test_rust_grammar() {
+        assert_eq!(
+            tokens(&RUST_GRAMMAR, "fn main<'a>() { println!(\"{}\\n\", x.len() + 0x1fu8); } // done"),
+            vec![
+                token(TokenType::Fn, "fn"),
+                token(TokenType::Identifier, "main"),
+                token(TokenType::Operator, "<"),
+                token(TokenType::TypeName, "'a"),
+                token(TokenType::Operator, ">"),
+                token(TokenType::ParenOpen, "("),
+                token(TokenType::ParenClose, ")"),
+                token(TokenType::ParenOpen, "{"),
+                token(TokenType::Macro, "println"),
+                token(TokenType::Operator, "!"),
+                token(TokenType::ParenOpen, "("),
+                token(TokenType::String, "\""),
+                token(TokenType::String, "{}"),
+                token(TokenType::String, "\\n"),
+                token(TokenType::String, "\""),
+                token(TokenType::Delimiter, ","),
+                token(TokenType::Identifier, "x"),
+                token(TokenType::Operator, "."),
+                token(TokenType::Call, "len"),
+                token(TokenType::ParenOpen, "("),
+                token(TokenType::ParenClose, ")"),
+                token(TokenType::Operator, "+"),
+                token(TokenType::Number, "0x1fu8"),
+                token(TokenType::ParenClose, ")"),
+                token(TokenType::Delimiter, ";"),
+                token(TokenType::ParenClose, "}"),
+                token(TokenType::CommentLine, "// done"),
+            ]
+        );
+        // Nested block comments across lines.
+        assert_eq!(
+            tokens(&RUST_GRAMMAR, "/* a /* b */\nc */ d"),
+            vec![
+                token(TokenType::CommentMultiBegin, "/*"),
+                token(TokenType::CommentChunk, " a "),
+                token(TokenType::CommentMultiBegin, "/*"),
+                token(TokenType::CommentChunk, " b "),
+                token(TokenType::CommentMultiEnd, "*/"),
+                token(TokenType::Newline, "\n"),
+                token(TokenType::CommentChunk, "c "),
+                token(TokenType::CommentMultiEnd, "*/"),
+                token(TokenType::Identifier, "d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_other_grammars() {
+        assert_eq!(
+            tokens(&JSON_GRAMMAR, "{\"a\": [1.5e3, true, null]}"),
+            vec![
+                token(TokenType::ParenOpen, "{"),
+                token(TokenType::String, "\""),
+                token(TokenType::String, "a"),
+                token(TokenType::String, "\""),
+                token(TokenType::Colon, ":"),
+                token(TokenType::ParenOpen, "["),
+                token(TokenType::Number, "1.5e3"),
+                token(TokenType::Delimiter, ","),
+                token(TokenType::Bool, "true"),
+                token(TokenType::Delimiter, ","),
+                token(TokenType::Keyword, "null"),
+                token(TokenType::ParenClose, "]"),
+                token(TokenType::ParenClose, "}"),
+            ]
+        );
+        assert_eq!(
+            tokens(&TOML_GRAMMAR, "[package] # header\nname = 'zap'"),
+            vec![
+                token(TokenType::ParenOpen, "["),
+                token(TokenType::Identifier, "package"),
+                token(TokenType::ParenClose, "]"),
+                token(TokenType::CommentLine, "# header"),
+                token(TokenType::Newline, "\n"),
+                token(TokenType::Identifier, "name"),
+                token(TokenType::Operator, "="),
+                token(TokenType::String, "'"),
+                token(TokenType::String, "zap"),
+                token(TokenType::String, "'"),
+            ]
+        );
+        assert_eq!(
+            tokens(&JS_GRAMMAR, "const x = new Map(); // y"),
+            vec![
+                token(TokenType::Keyword, "const"),
+                token(TokenType::Identifier, "x"),
+                token(TokenType::Operator, "="),
+                token(TokenType::Keyword, "new"),
+                token(TokenType::Call, "Map"),
+                token(TokenType::ParenOpen, "("),
+                token(TokenType::ParenClose, ")"),
+                token(TokenType::Delimiter, ";"),
+                token(TokenType::CommentLine, "// y"),
+            ]
+        );
+        assert_eq!(
+            tokens(&GLSL_GRAMMAR, "#version 300 es\nuniform vec4 color;"),
+            vec![
+                token(TokenType::Hash, "#version 300 es"),
+                token(TokenType::Newline, "\n"),
+                token(TokenType::Keyword, "uniform"),
+                token(TokenType::BuiltinType, "vec4"),
+                token(TokenType::Identifier, "color"),
+                token(TokenType::Delimiter, ";"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zero_length_match() {
+        static EMPTY_GRAMMAR: Grammar = Grammar {
+            name: "Empty",
+            states: &[GrammarState {
+                rules: &[
+                    GrammarRule::new(GrammarPattern::Literal("ab"), TokenType::Keyword),
+                    GrammarRule::push(GrammarPattern::Literal(""), TokenType::Operator, 0),
+                ],
+                default_token_type: TokenType::Identifier,
+            }],
+        };
+        assert_eq!(
+            tokens(&EMPTY_GRAMMAR, "xab\nc"),
+            vec![
+                token(TokenType::Error, "x"),
+                token(TokenType::Keyword, "ab"),
+                token(TokenType::Newline, "\n"),
+                token(TokenType::Error, "c"),
+            ]
+        );
+    }
+}
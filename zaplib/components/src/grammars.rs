@@ -0,0 +1,321 @@
+//! Built-in [`Grammar`]s for use with [`GrammarTokenizer`].
+//!
+//! These cover the common constructs of each language, not every corner of it. For example, JavaScript regular
+//! expression literals are tokenized as operators and identifiers, and template literal substitutions as strings.
+
+use crate::grammar::GrammarPattern::*;
+use crate::grammar::*;
+use crate::tokentype::*;
+
+/// Comments that start with `/*` and end at the first `*/`.
+const BLOCK_COMMENT_STATE: GrammarState = GrammarState {
+    rules: &[GrammarRule::pop(Literal("*/"), TokenType::CommentMultiEnd)],
+    default_token_type: TokenType::CommentChunk,
+};
+
+/// Strings that end with `"`, with backslash escapes.
+const DOUBLE_QUOTED_STRING_STATE: GrammarState = GrammarState {
+    rules: &[GrammarRule::new(Escape, TokenType::String), GrammarRule::pop(Literal("\""), TokenType::String)],
+    default_token_type: TokenType::String,
+};
+
+/// Strings that end with `'`, with backslash escapes.
+const SINGLE_QUOTED_STRING_STATE: GrammarState = GrammarState {
+    rules: &[GrammarRule::new(Escape, TokenType::String), GrammarRule::pop(Literal("'"), TokenType::String)],
+    default_token_type: TokenType::String,
+};
+
+/// Brackets and punctuation shared by the C-like languages.
+const PARENS: [GrammarRule; 4] = [
+    GrammarRule::new(AnyOf("([{"), TokenType::ParenOpen),
+    GrammarRule::new(AnyOf(")]}"), TokenType::ParenClose),
+    GrammarRule::new(AnyOf(",;"), TokenType::Delimiter),
+    GrammarRule::new(AnyOf(":"), TokenType::Colon),
+];
+
+pub static JSON_GRAMMAR: Grammar = Grammar {
+    name: "JSON",
+    states: &[
+        GrammarState {
+            rules: &[
+                GrammarRule::new(Whitespace, TokenType::Whitespace),
+                GrammarRule::push(Literal("\""), TokenType::String, 1),
+                GrammarRule::new(Number, TokenType::Number),
+                GrammarRule::new(Words(&["true", "false"]), TokenType::Bool),
+                GrammarRule::new(Words(&["null"]), TokenType::Keyword),
+                GrammarRule::new(AnyOf("[{"), TokenType::ParenOpen),
+                GrammarRule::new(AnyOf("]}"), TokenType::ParenClose),
+                GrammarRule::new(AnyOf(","), TokenType::Delimiter),
+                GrammarRule::new(AnyOf(":"), TokenType::Colon),
+                GrammarRule::new(AnyOf("-"), TokenType::Operator),
+            ],
+            default_token_type: TokenType::Unexpected,
+        },
+        DOUBLE_QUOTED_STRING_STATE,
+    ],
+};
+
+pub static TOML_GRAMMAR: Grammar = Grammar {
+    name: "TOML",
+    states: &[
+        GrammarState {
+            rules: &[
+                GrammarRule::new(Whitespace, TokenType::Whitespace),
+                GrammarRule::new(ToEndOfLine("#"), TokenType::CommentLine),
+                GrammarRule::push(Literal("\"\"\""), TokenType::String, 3),
+                GrammarRule::push(Literal("'''"), TokenType::String, 4),
+                GrammarRule::push(Literal("\""), TokenType::String, 1),
+                GrammarRule::push(Literal("'"), TokenType::String, 2),
+                GrammarRule::new(Words(&["true", "false"]), TokenType::Bool),
+                GrammarRule::new(Number, TokenType::Number),
+                GrammarRule::new(Identifier, TokenType::Identifier),
+                GrammarRule::new(AnyOf("[{"), TokenType::ParenOpen),
+                GrammarRule::new(AnyOf("]}"), TokenType::ParenClose),
+                GrammarRule::new(AnyOf(","), TokenType::Delimiter),
+                GrammarRule::new(AnyOf("=.+-:"), TokenType::Operator),
+            ],
+            default_token_type: TokenType::Unexpected,
+        },
+        DOUBLE_QUOTED_STRING_STATE,
+        // Literal strings don't have escapes.
+        GrammarState { rules: &[GrammarRule::pop(Literal("'"), TokenType::String)], default_token_type: TokenType::String },
+        GrammarState {
+            rules: &[GrammarRule::new(Escape, TokenType::String), GrammarRule::pop(Literal("\"\"\""), TokenType::String)],
+            default_token_type: TokenType::String,
+        },
+        GrammarState { rules: &[GrammarRule::pop(Literal("'''"), TokenType::String)], default_token_type: TokenType::String },
+    ],
+};
+
+pub static RUST_GRAMMAR: Grammar = Grammar {
+    name: "Rust",
+    states: &[
+        GrammarState {
+            rules: &[
+                GrammarRule::new(Whitespace, TokenType::Whitespace),
+                GrammarRule::new(ToEndOfLine("//"), TokenType::CommentLine),
+                GrammarRule::push(Literal("/*"), TokenType::CommentMultiBegin, 1),
+                GrammarRule::push(Literal("r#\""), TokenType::String, 3),
+                GrammarRule::push(Literal("r\""), TokenType::String, 4),
+                GrammarRule::push(Literal("b\""), TokenType::String, 2),
+                GrammarRule::push(Literal("\""), TokenType::String, 2),
+                GrammarRule::new(CharLiteral, TokenType::String),
+                // Lifetimes
+                GrammarRule::new(PrefixedIdentifier('\''), TokenType::TypeName),
+                GrammarRule::new(Number, TokenType::Number),
+                GrammarRule::new(Words(&["if", "else", "match", "return", "break", "continue"]), TokenType::Flow),
+                GrammarRule::new(Words(&["for", "while", "loop"]), TokenType::Looping),
+                GrammarRule::new(Words(&["struct", "enum", "trait", "union", "type"]), TokenType::TypeDef),
+                GrammarRule::new(Words(&["fn"]), TokenType::Fn),
+                GrammarRule::new(Words(&["impl"]), TokenType::Impl),
+                GrammarRule::new(Words(&["true", "false"]), TokenType::Bool),
+                GrammarRule::new(
+                    Words(&[
+                        "as", "async", "await", "const", "crate", "dyn", "extern", "in", "let", "mod", "move", "mut", "pub",
+                        "ref", "self", "Self", "static", "super", "unsafe", "use", "where",
+                    ]),
+                    TokenType::Keyword,
+                ),
+                GrammarRule::new(
+                    Words(&[
+                        "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128",
+                        "isize", "f32", "f64",
+                    ]),
+                    TokenType::BuiltinType,
+                ),
+                GrammarRule::new(IdentifierBefore('!'), TokenType::Macro),
+                GrammarRule::new(IdentifierBefore('('), TokenType::Call),
+                GrammarRule::new(CapitalizedIdentifier, TokenType::TypeName),
+                GrammarRule::new(Identifier, TokenType::Identifier),
+                GrammarRule::new(Literal("::"), TokenType::Namespace),
+                GrammarRule::new(Literal(".."), TokenType::Splat),
+                GrammarRule::new(AnyOf("#"), TokenType::Hash),
+                PARENS[0],
+                PARENS[1],
+                PARENS[2],
+                PARENS[3],
+                GrammarRule::new(AnyOf("+-*/%=<>!&|^?@.~$"), TokenType::Operator),
+            ],
+            default_token_type: TokenType::Unexpected,
+        },
+        // Block comments nest in Rust.
+        GrammarState {
+            rules: &[
+                GrammarRule::push(Literal("/*"), TokenType::CommentMultiBegin, 1),
+                GrammarRule::pop(Literal("*/"), TokenType::CommentMultiEnd),
+            ],
+            default_token_type: TokenType::CommentChunk,
+        },
+        DOUBLE_QUOTED_STRING_STATE,
+        GrammarState { rules: &[GrammarRule::pop(Literal("\"#"), TokenType::String)], default_token_type: TokenType::String },
+        GrammarState { rules: &[GrammarRule::pop(Literal("\""), TokenType::String)], default_token_type: TokenType::String },
+    ],
+};
+
+pub static JS_GRAMMAR: Grammar = Grammar {
+    name: "JavaScript",
+    states: &[
+        GrammarState {
+            rules: &[
+                GrammarRule::new(Whitespace, TokenType::Whitespace),
+                GrammarRule::new(ToEndOfLine("//"), TokenType::CommentLine),
+                GrammarRule::push(Literal("/*"), TokenType::CommentMultiBegin, 1),
+                GrammarRule::push(Literal("\""), TokenType::String, 2),
+                GrammarRule::push(Literal("'"), TokenType::String, 3),
+                GrammarRule::push(Literal("`"), TokenType::String, 4),
+                GrammarRule::new(Number, TokenType::Number),
+                GrammarRule::new(
+                    Words(&[
+                        "if", "else", "switch", "case", "default", "return", "break", "continue", "throw", "try", "catch",
+                        "finally",
+                    ]),
+                    TokenType::Flow,
+                ),
+                GrammarRule::new(Words(&["for", "while", "do"]), TokenType::Looping),
+                GrammarRule::new(Words(&["class"]), TokenType::TypeDef),
+                GrammarRule::new(Words(&["function"]), TokenType::Fn),
+                GrammarRule::new(Words(&["true", "false"]), TokenType::Bool),
+                GrammarRule::new(
+                    Words(&[
+                        "var",
+                        "let",
+                        "const",
+                        "new",
+                        "delete",
+                        "typeof",
+                        "instanceof",
+                        "in",
+                        "of",
+                        "this",
+                        "super",
+                        "import",
+                        "export",
+                        "from",
+                        "extends",
+                        "static",
+                        "async",
+                        "await",
+                        "yield",
+                        "void",
+                        "null",
+                        "undefined",
+                    ]),
+                    TokenType::Keyword,
+                ),
+                GrammarRule::new(IdentifierBefore('('), TokenType::Call),
+                GrammarRule::new(CapitalizedIdentifier, TokenType::TypeName),
+                GrammarRule::new(Identifier, TokenType::Identifier),
+                GrammarRule::new(Literal("..."), TokenType::Splat),
+                PARENS[0],
+                PARENS[1],
+                PARENS[2],
+                PARENS[3],
+                GrammarRule::new(AnyOf("+-*/%=<>!&|^?.~$#@"), TokenType::Operator),
+            ],
+            default_token_type: TokenType::Unexpected,
+        },
+        BLOCK_COMMENT_STATE,
+        DOUBLE_QUOTED_STRING_STATE,
+        SINGLE_QUOTED_STRING_STATE,
+        GrammarState {
+            rules: &[GrammarRule::new(Escape, TokenType::String), GrammarRule::pop(Literal("`"), TokenType::String)],
+            default_token_type: TokenType::String,
+        },
+    ],
+};
+
+pub static GLSL_GRAMMAR: Grammar = Grammar {
+    name: "GLSL",
+    states: &[
+        GrammarState {
+            rules: &[
+                GrammarRule::new(Whitespace, TokenType::Whitespace),
+                GrammarRule::new(ToEndOfLine("//"), TokenType::CommentLine),
+                GrammarRule::push(Literal("/*"), TokenType::CommentMultiBegin, 1),
+                // Preprocessor directives
+                GrammarRule::new(ToEndOfLine("#"), TokenType::Hash),
+                GrammarRule::new(Number, TokenType::Number),
+                GrammarRule::new(
+                    Words(&["if", "else", "switch", "case", "default", "return", "break", "continue", "discard"]),
+                    TokenType::Flow,
+                ),
+                GrammarRule::new(Words(&["for", "while", "do"]), TokenType::Looping),
+                GrammarRule::new(Words(&["struct"]), TokenType::TypeDef),
+                GrammarRule::new(Words(&["true", "false"]), TokenType::Bool),
+                GrammarRule::new(
+                    Words(&[
+                        "in",
+                        "out",
+                        "inout",
+                        "uniform",
+                        "attribute",
+                        "varying",
+                        "const",
+                        "layout",
+                        "precision",
+                        "highp",
+                        "mediump",
+                        "lowp",
+                        "flat",
+                        "smooth",
+                        "centroid",
+                        "invariant",
+                    ]),
+                    TokenType::Keyword,
+                ),
+                GrammarRule::new(
+                    Words(&[
+                        "void",
+                        "bool",
+                        "int",
+                        "uint",
+                        "float",
+                        "double",
+                        "vec2",
+                        "vec3",
+                        "vec4",
+                        "ivec2",
+                        "ivec3",
+                        "ivec4",
+                        "uvec2",
+                        "uvec3",
+                        "uvec4",
+                        "bvec2",
+                        "bvec3",
+                        "bvec4",
+                        "dvec2",
+                        "dvec3",
+                        "dvec4",
+                        "mat2",
+                        "mat3",
+                        "mat4",
+                        "mat2x2",
+                        "mat2x3",
+                        "mat2x4",
+                        "mat3x2",
+                        "mat3x3",
+                        "mat3x4",
+                        "mat4x2",
+                        "mat4x3",
+                        "mat4x4",
+                        "sampler2D",
+                        "sampler3D",
+                        "samplerCube",
+                        "sampler2DArray",
+                        "sampler2DShadow",
+                    ]),
+                    TokenType::BuiltinType,
+                ),
+                GrammarRule::new(IdentifierBefore('('), TokenType::Call),
+                GrammarRule::new(Identifier, TokenType::Identifier),
+                PARENS[0],
+                PARENS[1],
+                PARENS[2],
+                PARENS[3],
+                GrammarRule::new(AnyOf("+-*/%=<>!&|^?.~"), TokenType::Operator),
+            ],
+            default_token_type: TokenType::Unexpected,
+        },
+        BLOCK_COMMENT_STATE,
+    ],
+};
@@ -0,0 +1,139 @@
+//! Filled shapes for [`crate::Chart`]: bars, areas under lines, and pie slices.
+
+use zaplib::*;
+
+/// A filled rectangle, for bar charts.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub(crate) struct ChartRectIns {
+    pub(crate) base: QuadIns,
+    pub(crate) color: Vec4,
+}
+
+static RECT_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            instance color: vec4;
+
+            fn pixel() -> vec4 {
+                return vec4(color.rgb * color.a, color.a);
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+impl ChartRectIns {
+    pub(crate) fn draw(cx: &mut Cx, data: &[ChartRectIns]) -> Area {
+        cx.add_instances(&RECT_SHADER, data)
+    }
+}
+
+/// The area between two line segments that span the same horizontal range, for area charts. The quad should cover
+/// the shape; only the part of it between the two segments gets filled.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub(crate) struct ChartAreaIns {
+    pub(crate) base: QuadIns,
+    /// Absolute x coordinates of the start and end of the segments.
+    pub(crate) x_range: Vec2,
+    /// Absolute y coordinates of the start and end of the first segment.
+    pub(crate) top: Vec2,
+    /// Absolute y coordinates of the start and end of the second segment.
+    pub(crate) bottom: Vec2,
+    pub(crate) color: Vec4,
+}
+
+static AREA_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            instance x_range: vec2;
+            instance top: vec2;
+            instance bottom: vec2;
+            instance color: vec4;
+
+            fn pixel() -> vec4 {
+                let p = rect_pos + pos * rect_size;
+                let t = clamp((p.x - x_range.x) / max(x_range.y - x_range.x, 0.0001), 0., 1.);
+                let y0 = mix(top.x, top.y, t);
+                let y1 = mix(bottom.x, bottom.y, t);
+                if p.y < min(y0, y1) || p.y > max(y0, y1) {
+                    return vec4(0.);
+                }
+                return vec4(color.rgb * color.a, color.a);
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+impl ChartAreaIns {
+    pub(crate) fn draw(cx: &mut Cx, data: &[ChartAreaIns]) -> Area {
+        cx.add_instances(&AREA_SHADER, data)
+    }
+}
+
+/// A slice of a pie or doughnut, with a border along its edges. The quad should cover the whole circle.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub(crate) struct ChartSliceIns {
+    pub(crate) base: QuadIns,
+    /// Absolute position of the center of the circle.
+    pub(crate) center: Vec2,
+    /// Inner and outer radius.
+    pub(crate) radii: Vec2,
+    /// Start and end angle in radians, clockwise from the top, with the start in `[0, 2 * PI]`.
+    pub(crate) angles: Vec2,
+    pub(crate) color: Vec4,
+    pub(crate) border_color: Vec4,
+    pub(crate) border_width: f32,
+}
+
+static SLICE_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            instance center: vec2;
+            instance radii: vec2;
+            instance angles: vec2;
+            instance color: vec4;
+            instance border_color: vec4;
+            instance border_width: float;
+
+            fn pixel() -> vec4 {
+                let d = rect_pos + pos * rect_size - center;
+                let r = length(d);
+                let angle = mod(atan(d.x, -d.y) + 2. * PI, 2. * PI);
+                if r < radii.x || r > radii.y + 1. || angle < angles.x || angle > angles.y {
+                    return vec4(0.);
+                }
+                // Distance to the nearest edge of the slice, in pixels.
+                let edge = min(min(r - radii.x, radii.y - r), r * min(angle - angles.x, angles.y - angle));
+                let c = color;
+                if edge < border_width {
+                    c = border_color;
+                }
+                let alpha = c.a * clamp(radii.y - r + 0.5, 0., 1.);
+                return vec4(c.rgb * alpha, alpha);
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+impl ChartSliceIns {
+    pub(crate) fn draw(cx: &mut Cx, data: &[ChartSliceIns]) -> Area {
+        cx.add_instances(&SLICE_SHADER, data)
+    }
+}
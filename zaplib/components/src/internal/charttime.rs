@@ -0,0 +1,248 @@
+//! Calendar-aware ticks and labels for time axes of [`crate::Chart`]. All times are milliseconds since the Unix epoch,
+//! and dates are in UTC.
+
+const MS_PER_SECOND: f64 = 1000.;
+const MS_PER_MINUTE: f64 = 60. * MS_PER_SECOND;
+const MS_PER_HOUR: f64 = 60. * MS_PER_MINUTE;
+const MS_PER_DAY: f64 = 24. * MS_PER_HOUR;
+
+const MONTH_NAMES: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// The unit of the ticks on a time axis, which determines how their labels are formatted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TimeUnit {
+    Millisecond,
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Month,
+    Year,
+}
+
+impl TimeUnit {
+    /// Length of the unit, averaged over leap years for months and years.
+    fn approx_ms(self) -> f64 {
+        match self {
+            TimeUnit::Millisecond => 1.,
+            TimeUnit::Second => MS_PER_SECOND,
+            TimeUnit::Minute => MS_PER_MINUTE,
+            TimeUnit::Hour => MS_PER_HOUR,
+            TimeUnit::Day => MS_PER_DAY,
+            TimeUnit::Month => 30.436875 * MS_PER_DAY,
+            TimeUnit::Year => 365.2425 * MS_PER_DAY,
+        }
+    }
+}
+
+/// Steps between ticks, from small to large. Larger steps are multiples of years, see [`time_ticks`].
+const STEPS: &[(TimeUnit, u32)] = &[
+    (TimeUnit::Millisecond, 1),
+    (TimeUnit::Millisecond, 2),
+    (TimeUnit::Millisecond, 5),
+    (TimeUnit::Millisecond, 10),
+    (TimeUnit::Millisecond, 20),
+    (TimeUnit::Millisecond, 50),
+    (TimeUnit::Millisecond, 100),
+    (TimeUnit::Millisecond, 200),
+    (TimeUnit::Millisecond, 500),
+    (TimeUnit::Second, 1),
+    (TimeUnit::Second, 2),
+    (TimeUnit::Second, 5),
+    (TimeUnit::Second, 10),
+    (TimeUnit::Second, 15),
+    (TimeUnit::Second, 30),
+    (TimeUnit::Minute, 1),
+    (TimeUnit::Minute, 2),
+    (TimeUnit::Minute, 5),
+    (TimeUnit::Minute, 10),
+    (TimeUnit::Minute, 15),
+    (TimeUnit::Minute, 30),
+    (TimeUnit::Hour, 1),
+    (TimeUnit::Hour, 2),
+    (TimeUnit::Hour, 3),
+    (TimeUnit::Hour, 6),
+    (TimeUnit::Hour, 12),
+    (TimeUnit::Day, 1),
+    (TimeUnit::Day, 2),
+    (TimeUnit::Day, 7),
+    (TimeUnit::Month, 1),
+    (TimeUnit::Month, 2),
+    (TimeUnit::Month, 3),
+    (TimeUnit::Month, 6),
+];
+
+/// Converts days since the Unix epoch to a (year, month, day) date, with months and days starting at 1.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Months start in March, so the leap day is at the end of the year.
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month as u32, day as u32)
+}
+
+/// Inverse of [`civil_from_days`].
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let day_of_year = (153 * shifted_month + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Start of the month with the given number of months since the start of year 0.
+fn month_start_ms(month_index: i64) -> f64 {
+    let year = month_index.div_euclid(12);
+    let month = month_index.rem_euclid(12) as u32 + 1;
+    days_from_civil(year, month, 1) as f64 * MS_PER_DAY
+}
+
+/// Generates ticks between `min` and `max` (inclusive) that are at least `min_step` apart, aligned to calendar units:
+/// e.g. every 15 minutes, every day at midnight, or on the first of every 3rd month.
+pub(crate) fn time_ticks(min: f64, max: f64, min_step: f64) -> (TimeUnit, Vec<f64>) {
+    if !(min.is_finite() && max.is_finite() && min <= max) {
+        return (TimeUnit::Millisecond, vec![]);
+    }
+
+    let (unit, count) =
+        STEPS.iter().copied().find(|(unit, count)| unit.approx_ms() * *count as f64 >= min_step).unwrap_or_else(|| {
+            // 1, 2, 5, 10, 20, 50, ... years.
+            let count = (0..9)
+                .flat_map(|exp| [1, 2, 5].map(|multiplier| multiplier * 10_u32.pow(exp)))
+                .find(|count| TimeUnit::Year.approx_ms() * *count as f64 >= min_step)
+                .unwrap_or(1_000_000_000);
+            (TimeUnit::Year, count)
+        });
+
+    let mut ticks = vec![];
+    match unit {
+        TimeUnit::Month | TimeUnit::Year => {
+            let (year, month, day) = civil_from_days((min / MS_PER_DAY).floor() as i64);
+            // Months since the start of year 0, rounded up to the next multiple of the step.
+            let step = if unit == TimeUnit::Year { 12 * count as i64 } else { count as i64 };
+            let mut month_index = year * 12 + month as i64 - 1;
+            if day > 1 || month_start_ms(month_index) < min {
+                month_index += 1;
+            }
+            month_index = (month_index + step - 1).div_euclid(step) * step;
+            loop {
+                let tick = month_start_ms(month_index);
+                if tick > max {
+                    break;
+                }
+                ticks.push(tick);
+                month_index += step;
+            }
+        }
+        _ => {
+            let step = unit.approx_ms() * count as f64;
+            let mut tick = (min / step).ceil() * step;
+            while tick <= max {
+                ticks.push(tick);
+                tick += step;
+            }
+        }
+    }
+    (unit, ticks)
+}
+
+struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    millisecond: u32,
+}
+
+impl DateTime {
+    fn from_ms(ms: f64) -> Self {
+        let ms = ms.round();
+        let days = (ms / MS_PER_DAY).floor();
+        let (year, month, day) = civil_from_days(days as i64);
+        let ms_of_day = (ms - days * MS_PER_DAY) as u32;
+        Self {
+            year,
+            month,
+            day,
+            hour: ms_of_day / MS_PER_HOUR as u32,
+            minute: ms_of_day / MS_PER_MINUTE as u32 % 60,
+            second: ms_of_day / MS_PER_SECOND as u32 % 60,
+            millisecond: ms_of_day % 1000,
+        }
+    }
+}
+
+/// Formats a tick label, leaving out the parts of the date that are implied by the unit of the ticks.
+pub(crate) fn format_time_tick(ms: f64, unit: TimeUnit) -> String {
+    let t = DateTime::from_ms(ms);
+    match unit {
+        TimeUnit::Millisecond => format!("{:02}:{:02}:{:02}.{:03}", t.hour, t.minute, t.second, t.millisecond),
+        TimeUnit::Second => format!("{:02}:{:02}:{:02}", t.hour, t.minute, t.second),
+        TimeUnit::Minute | TimeUnit::Hour => format!("{:02}:{:02}", t.hour, t.minute),
+        TimeUnit::Day => format!("{} {}", MONTH_NAMES[t.month as usize - 1], t.day),
+        TimeUnit::Month => format!("{} {}", MONTH_NAMES[t.month as usize - 1], t.year),
+        TimeUnit::Year => format!("{}", t.year),
+    }
+}
+
+/// Formats a full date and time, e.g. for tooltips. Milliseconds are only shown if there are any.
+pub(crate) fn format_time(ms: f64) -> String {
+    let t = DateTime::from_ms(ms);
+    let date = format!("{}-{:02}-{:02} {:02}:{:02}:{:02}", t.year, t.month, t.day, t.hour, t.minute, t.second);
+    if t.millisecond == 0 {
+        date
+    } else {
+        format!("{}.{:03}", date, t.millisecond)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_civil_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+        for days in (-800_000..800_000).step_by(997) {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn test_time_ticks() {
+        // 2024-01-01 00:00:00 UTC
+        let start = 1_704_067_200_000.;
+
+        let (unit, ticks) = time_ticks(start + 1., start + 10. * MS_PER_MINUTE, 2.5 * MS_PER_MINUTE);
+        assert_eq!(unit, TimeUnit::Minute);
+        assert_eq!(ticks.iter().map(|t| format_time_tick(*t, unit)).collect::<Vec<_>>(), vec!["00:05", "00:10"]);
+
+        let (unit, ticks) = time_ticks(start - 40. * MS_PER_DAY, start + 100. * MS_PER_DAY, 50. * MS_PER_DAY);
+        assert_eq!(unit, TimeUnit::Month);
+        assert_eq!(ticks.iter().map(|t| format_time_tick(*t, unit)).collect::<Vec<_>>(), vec!["Jan 2024", "Mar 2024"]);
+
+        let (unit, ticks) = time_ticks(start - 3000. * MS_PER_DAY, start + 3000. * MS_PER_DAY, 1000. * MS_PER_DAY);
+        assert_eq!(unit, TimeUnit::Year);
+        assert_eq!(ticks.iter().map(|t| format_time_tick(*t, unit)).collect::<Vec<_>>(), vec!["2020", "2025", "2030"]);
+
+        assert_eq!(format_time(start + 13. * MS_PER_HOUR + 5. * MS_PER_SECOND + 250.), "2024-01-01 13:00:05.250");
+    }
+}
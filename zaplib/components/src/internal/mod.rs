@@ -1,4 +1,6 @@
 pub(crate) mod buttonlogic;
+pub(crate) mod chartshapes;
+pub(crate) mod charttime;
 pub(crate) mod desktopbutton;
 pub(crate) mod scrollbar;
 pub(crate) mod tabclose;
@@ -29,6 +29,8 @@ mod list;
 pub use crate::list::*;
 mod textbuffer;
 pub use crate::textbuffer::*;
+mod textrope;
+pub use crate::textrope::*;
 mod texteditor;
 pub use crate::texteditor::*;
 mod textcursor;
@@ -39,6 +41,12 @@ mod scrollshadow;
 pub use crate::scrollshadow::*;
 mod tokentype;
 pub use crate::tokentype::*;
+mod tokenchunks;
+pub use crate::tokenchunks::*;
+mod grammar;
+pub use crate::grammar::*;
+mod grammars;
+pub use crate::grammars::*;
 mod foldcaption;
 pub use crate::foldcaption::*;
 mod floatslider;
@@ -58,6 +66,28 @@ pub use crate::geometry3d::*;
 
 mod chart;
 pub use crate::chart::*;
+mod chartstream;
+pub use crate::chartstream::*;
+mod chartplugins;
+pub use crate::chartplugins::*;
+mod datagrid;
+pub use crate::datagrid::*;
+mod treeview;
+pub use crate::treeview::*;
+mod dropdown;
+pub use crate::dropdown::*;
+mod combobox;
+pub use crate::combobox::*;
+mod contextmenu;
+pub use crate::contextmenu::*;
+mod menubar;
+pub use crate::menubar::*;
+mod modal;
+pub use crate::modal::*;
+mod toasts;
+pub use crate::toasts::*;
+mod tooltip;
+pub use crate::tooltip::*;
 mod drawlines3d;
 pub use crate::drawlines3d::*;
 mod drawpoints3d;
@@ -0,0 +1,323 @@
+//! A menu bar drawn inside of the window, for platforms that don't have native menus (see [`Cx::update_menu`]).
+
+use crate::contextmenu::*;
+use crate::*;
+use zaplib::*;
+
+const BAR_HEIGHT: f32 = 24.;
+const TITLE_PADDING_X: f32 = 8.;
+
+const COLOR_TITLE_HOVER: Vec4 = vec4(0.3, 0.3, 0.3, 1.);
+const COLOR_TITLE_OPEN: Vec4 = vec4(0.07, 0.27, 0.43, 1.);
+const COLOR_TEXT: Vec4 = vec4(0.85, 0.85, 0.85, 1.);
+
+/// Draws the [`Menu::Sub`]s of a [`Menu::Main`] as a row of titles, which open their menus when clicked. Picking an
+/// item fires an [`Event::Command`] (see [`Cx::send_command`]), just like a native menu.
+///
+/// Like native menus, this also fires the [`Event::Command`] of an item when its shortcut (see [`CommandId::set_key`])
+/// is pressed together with the platform's command key, and it shows items greyed out unless their command is enabled
+/// using [`CommandId::set_enabled`].
+///
+/// Once a menu is open, hovering over the other titles opens their menus instead, and the menus can be used with the
+/// arrow keys, Return, and Escape.
+///
+/// [`crate::DesktopWindow`] uses this when there are no native menus.
+pub struct MenuBar {
+    component_id: ComponentId,
+    pub menu: Menu,
+    /// Transparent, but used for hit testing.
+    bar: Background,
+    /// Horizontal range of every title relative to the bar, as of the last draw.
+    title_ranges: Vec<(f32, f32)>,
+    hovered: Option<usize>,
+    /// Index of the [`Menu::Sub`] in [`MenuBar::menu`] that is open.
+    open_index: Option<usize>,
+    popups: MenuPopups,
+}
+
+impl Default for MenuBar {
+    fn default() -> Self {
+        Self {
+            component_id: Default::default(),
+            menu: Menu::main(vec![]),
+            bar: Background::default(),
+            title_ranges: vec![],
+            hovered: None,
+            open_index: None,
+            popups: MenuPopups::default(),
+        }
+    }
+}
+
+/// The enabled [`CommandId`] in `items` (recursively) whose shortcut matches a key press.
+fn find_shortcut(cx: &Cx, items: &[Menu], ke: &KeyEvent) -> Option<CommandId> {
+    items.iter().find_map(|item| match item {
+        Menu::Item { command, .. } => Some(*command)
+            .filter(|command| command.get_key(cx) == Some((ke.key_code, ke.modifiers.shift)) && command.is_enabled(cx)),
+        Menu::Sub { items, .. } => find_shortcut(cx, items, ke),
+        _ => None,
+    })
+}
+
+impl MenuBar {
+    #[must_use]
+    pub fn with_menu(self, menu: Menu) -> Self {
+        Self { menu, ..self }
+    }
+
+    /// The [`Area`] of the whole bar, e.g. to exclude it from dragging the window around.
+    pub fn area(&self) -> Area {
+        self.bar.area()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open_index.is_some()
+    }
+
+    fn title_rect(&self, cx: &Cx, index: usize) -> Option<Rect> {
+        let bar_rect = self.bar.area().get_rect_for_first_instance(cx)?;
+        let (x, width) = *self.title_ranges.get(index)?;
+        Some(Rect { pos: bar_rect.pos + vec2(x, 0.), size: vec2(width, bar_rect.size.y) })
+    }
+
+    fn title_at(&self, x: f32) -> Option<usize> {
+        self.title_ranges.iter().position(|&(start, width)| x >= start && x < start + width)
+    }
+
+    /// Open the menu of the title at `index`, or send the command if it's a [`Menu::Item`].
+    fn open_menu(&mut self, cx: &mut Cx, index: usize, from_keyboard: bool) {
+        let anchor = self.title_rect(cx, index).unwrap_or_default();
+        match &menu_items(&self.menu)[index] {
+            Menu::Sub { items, .. } => {
+                if self.open_index.is_none() {
+                    cx.set_key_focus(Some(self.component_id));
+                }
+                self.open_index = Some(index);
+                self.popups.open(cx, anchor, items, from_keyboard);
+            }
+            Menu::Item { command, .. } if command.is_enabled(cx) => {
+                let command = *command;
+                self.close(cx);
+                cx.send_command(command);
+            }
+            _ => (),
+        }
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        if self.open_index.is_some() {
+            self.open_index = None;
+            self.popups.close(cx);
+            cx.revert_key_focus();
+        }
+    }
+
+    /// The next title after `index` in `direction` that has a menu, wrapping around.
+    fn next_sub(&self, index: usize, forward: bool) -> Option<usize> {
+        let titles = menu_items(&self.menu);
+        let len = titles.len();
+        (1..len)
+            .map(|offset| if forward { (index + offset) % len } else { (index + len - offset) % len })
+            .find(|&index| matches!(titles[index], Menu::Sub { .. }))
+    }
+
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if let Event::KeyDown(ke) = event {
+            let command_key =
+                if matches!(cx.platform_type, PlatformType::OSX) { ke.modifiers.logo } else { ke.modifiers.control };
+            if command_key {
+                if let Some(command) = find_shortcut(cx, menu_items(&self.menu), ke) {
+                    self.close(cx);
+                    cx.send_command(command);
+                    return;
+                }
+            }
+        }
+
+        let bar_rect = self.bar.area().get_rect_for_first_instance(cx);
+        if let Some(open_index) = self.open_index {
+            if let MenuPopupsEvent::Closed = self.popups.handle(cx, event, menu_items(&menu_items(&self.menu)[open_index])) {
+                self.open_index = None;
+                cx.revert_key_focus();
+                return;
+            }
+            if let Event::PointerDown(pe) = event {
+                let on_bar = bar_rect.map_or(false, |rect| rect.contains(pe.abs));
+                if !on_bar && !self.popups.contains(cx, pe.abs) {
+                    self.close(cx);
+                    return;
+                }
+            }
+        }
+
+        match event.hits_pointer(cx, self.component_id, bar_rect) {
+            Event::PointerHover(pe) => {
+                let hovered = if pe.hover_state == HoverState::Out { None } else { self.title_at(pe.rel.x) };
+                if hovered != self.hovered {
+                    self.hovered = hovered;
+                    cx.request_draw();
+                }
+                if let (Some(hovered), Some(open_index)) = (hovered, self.open_index) {
+                    if hovered != open_index && matches!(menu_items(&self.menu)[hovered], Menu::Sub { .. }) {
+                        self.open_menu(cx, hovered, false);
+                    }
+                }
+            }
+            Event::PointerDown(pe) => match self.title_at(pe.rel.x) {
+                Some(index) if self.open_index == Some(index) => self.close(cx),
+                Some(index) => self.open_menu(cx, index, false),
+                None => cx.keep_key_focus(),
+            },
+            _ => (),
+        }
+
+        match event.hits_keyboard(cx, self.component_id) {
+            Event::KeyFocusLost(_) => {
+                self.open_index = None;
+                self.popups.close(cx);
+            }
+            Event::KeyDown(ke) => {
+                if let Some(open_index) = self.open_index {
+                    let items = menu_items(&menu_items(&self.menu)[open_index]);
+                    match self.popups.handle_key(cx, ke.key_code, items) {
+                        MenuPopupsEvent::Closed => {
+                            self.open_index = None;
+                            cx.revert_key_focus();
+                        }
+                        MenuPopupsEvent::PrevMenu => {
+                            if let Some(index) = self.next_sub(open_index, false) {
+                                self.open_menu(cx, index, true);
+                            }
+                        }
+                        MenuPopupsEvent::NextMenu => {
+                            if let Some(index) = self.next_sub(open_index, true) {
+                                self.open_menu(cx, index, true);
+                            }
+                        }
+                        MenuPopupsEvent::None => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx) {
+        let props = TextInsProps { color: COLOR_TEXT, position_anchoring: TEXT_ANCHOR_CENTER_V, ..TextInsProps::DEFAULT };
+        let titles = menu_items(&self.menu);
+        self.title_ranges.clear();
+        let mut x = 0.;
+        for title in titles {
+            let width = match title {
+                Menu::Sub { name, .. } | Menu::Item { name, .. } => {
+                    TextIns::measure_str_width(cx, name, &props) + 2. * TITLE_PADDING_X
+                }
+                _ => 0.,
+            };
+            self.title_ranges.push((x, width));
+            x += width;
+        }
+
+        let rect = cx.add_box(LayoutSize::new(Width::Fix(x), Height::Fix(BAR_HEIGHT)));
+        self.bar.draw(cx, rect, Vec4::default());
+
+        let highlighted =
+            self.open_index.map(|index| (index, COLOR_TITLE_OPEN)).or(self.hovered.map(|index| (index, COLOR_TITLE_HOVER)));
+        if let Some((index, color)) = highlighted {
+            let (x, width) = self.title_ranges[index];
+            let title_rect = Rect { pos: rect.pos + vec2(x, 0.), size: vec2(width, rect.size.y) };
+            Background::default().with_draw_depth(0.1).draw(cx, title_rect, color);
+        }
+        // Draw the titles on top of the highlight.
+        let props = TextInsProps { draw_depth: 0.2, ..props };
+        for (title, &(x, _)) in titles.iter().zip(&self.title_ranges) {
+            if let Menu::Sub { name, .. } | Menu::Item { name, .. } = title {
+                TextIns::draw_str(cx, name, rect.pos + vec2(x + TITLE_PADDING_X, rect.size.y / 2.), &props);
+            }
+        }
+
+        if let Some(open_index) = self.open_index {
+            // Follow the title around, e.g. when the window gets resized.
+            if let Some(anchor) = self.title_rect(cx, open_index) {
+                self.popups.anchor = anchor;
+            }
+            self.popups.draw(cx, menu_items(&menu_items(&self.menu)[open_index]));
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use zaplib::*;
+
+    use crate::*;
+
+    const COMMAND_NEW: CommandId = location_hash!();
+
+    struct MenuBarTestApp {
+        menu_bar: MenuBar,
+        commands: Vec<CommandId>,
+    }
+
+    impl MainApp for MenuBarTestApp {
+        fn new(cx: &mut Cx) -> Self {
+            cx.command_default_keymap();
+            COMMAND_NEW.set_key(cx, KeyCode::KeyN);
+            for command in [COMMAND_NEW, Cx::COMMAND_QUIT, Cx::COMMAND_UNDO] {
+                command.set_enabled(cx, true);
+            }
+            let menu = Menu::main(vec![
+                Menu::sub("File", vec![Menu::item("New", COMMAND_NEW), Menu::line(), Menu::item("Quit", Cx::COMMAND_QUIT)]),
+                Menu::sub("Edit", vec![Menu::item("Undo", Cx::COMMAND_UNDO), Menu::item("Redo", Cx::COMMAND_REDO)]),
+            ]);
+            Self { menu_bar: MenuBar::default().with_menu(menu), commands: vec![] }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            self.menu_bar.handle(cx, event);
+            if let Event::Command(command) = event {
+                self.commands.push(*command);
+            }
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            self.menu_bar.draw(cx);
+        }
+    }
+
+    fn title_center(test: &AppTest<MenuBarTestApp>, index: usize) -> Vec2 {
+        let (x, width) = test.app.menu_bar.title_ranges[index];
+        vec2(x + width / 2., 12.)
+    }
+
+    #[test]
+    fn test_menu_bar() {
+        let mut test = AppTest::<MenuBarTestApp>::new_in_window(vec2(300., 150.));
+
+        // Clicking a title opens its menu, and hovering over another title switches to its menu.
+        test.click(title_center(&test, 0));
+        assert_eq!(test.app.menu_bar.open_index, Some(0));
+        test.pointer_move(title_center(&test, 1));
+        assert_eq!(test.app.menu_bar.open_index, Some(1));
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/menu_bar_open.png"));
+
+        // The left and right arrow keys go to the adjacent menus.
+        test.key_press(KeyCode::ArrowRight);
+        assert_eq!(test.app.menu_bar.open_index, Some(0));
+        test.key_press(KeyCode::Return);
+        assert!(!test.app.menu_bar.is_open());
+        assert_eq!(test.app.commands, vec![COMMAND_NEW]);
+
+        // Clicking the title of the open menu closes it.
+        test.click(title_center(&test, 1));
+        test.click(title_center(&test, 1));
+        assert!(!test.app.menu_bar.is_open());
+
+        // Shortcuts work without opening the menu, but not for disabled commands.
+        test.set_modifiers(KeyModifiers { control: true, ..KeyModifiers::default() });
+        test.key_press(KeyCode::KeyZ);
+        test.set_modifiers(KeyModifiers { control: true, shift: true, ..KeyModifiers::default() });
+        test.key_press(KeyCode::KeyZ);
+        assert_eq!(test.app.commands, vec![COMMAND_NEW, Cx::COMMAND_UNDO]);
+    }
+}
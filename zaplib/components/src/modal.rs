@@ -0,0 +1,247 @@
+//! Modal dialogs, which block the rest of the application while they are open.
+
+use crate::*;
+use zaplib::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModalEvent {
+    None,
+    /// The dialog was closed by pressing Escape, or by clicking outside of it if [`Modal::close_on_backdrop_click`]
+    /// is set.
+    Closed,
+}
+
+const DIALOG_PADDING: f32 = 16.;
+
+const COLOR_BACKDROP: Vec4 = vec4(0., 0., 0., 0.5);
+const COLOR_DIALOG: Vec4 = vec4(0.17, 0.17, 0.17, 1.);
+
+/// A dialog that is drawn in the middle of the window, on top of everything else (see [`View::with_is_overlay`]).
+/// The rest of the window is dimmed, and doesn't get any pointer events while the dialog is open.
+///
+/// [`Modal::open`] takes the key focus away from whatever had it, so that it doesn't get any keyboard events
+/// either, and [`Modal::close`] gives it back. To focus something inside of the dialog, call its `set_key_focus`
+/// right after [`Modal::open`]. Since clicks outside of the dialog are blocked, the key focus can't leave the
+/// dialog using the pointer.
+///
+/// Call [`Modal::handle`] after the event handlers of whatever is drawn inside of the dialog, but before the event
+/// handlers of everything else. Similarly, draw the dialog after everything else, by calling [`Modal::begin_draw`],
+/// drawing its contents, and calling [`Modal::end_draw`], but only if [`Modal::is_open`].
+pub struct Modal {
+    component_id: ComponentId,
+    backdrop_view: View,
+    backdrop: Background,
+    /// A separate overlay [`View`] on top of [`Modal::backdrop_view`], since the [`Background`]s of the dialog and
+    /// the backdrop would otherwise get batched into the same [`DrawCall`].
+    dialog_view: View,
+    dialog: Background,
+    is_open: bool,
+    /// Whether clicking outside of the dialog closes it, just like pressing Escape does.
+    pub close_on_backdrop_click: bool,
+    /// What had the key focus when the dialog was opened, so we can give it back when it closes.
+    focus_before_open: Option<ComponentId>,
+}
+
+impl Default for Modal {
+    fn default() -> Self {
+        Self {
+            component_id: Default::default(),
+            backdrop_view: View::default().with_is_overlay(true),
+            backdrop: Background::default(),
+            dialog_view: View::default().with_is_overlay(true),
+            dialog: Background::default().with_radius(4.),
+            is_open: false,
+            close_on_backdrop_click: false,
+            focus_before_open: None,
+        }
+    }
+}
+
+impl Modal {
+    #[must_use]
+    pub fn with_close_on_backdrop_click(self, close_on_backdrop_click: bool) -> Self {
+        Self { close_on_backdrop_click, ..self }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    pub fn open(&mut self, cx: &mut Cx) {
+        if !self.is_open {
+            self.is_open = true;
+            self.focus_before_open = cx.get_key_focus();
+            cx.set_key_focus(Some(self.component_id));
+            cx.request_draw();
+        }
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        if self.is_open {
+            self.is_open = false;
+            cx.set_key_focus(self.focus_before_open);
+            cx.request_draw();
+        }
+    }
+
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> ModalEvent {
+        if !self.is_open {
+            return ModalEvent::None;
+        }
+
+        match event {
+            Event::KeyDown(ke) if ke.key_code == KeyCode::Escape => {
+                self.close(cx);
+                return ModalEvent::Closed;
+            }
+            Event::PointerScroll(pe) => {
+                pe.handled_x = true;
+                pe.handled_y = true;
+            }
+            _ => (),
+        }
+
+        // The backdrop covers the whole window, including the dialog, so this marks all pointer events as handled
+        // that weren't already handled by the contents of the dialog.
+        if let Event::PointerDown(pe) =
+            event.hits_pointer(cx, self.component_id, self.backdrop.area().get_rect_for_first_instance(cx))
+        {
+            let on_dialog = self.dialog.area().get_rect_for_first_instance(cx).map_or(false, |rect| rect.contains(pe.abs));
+            if !on_dialog && self.close_on_backdrop_click {
+                self.close(cx);
+                return ModalEvent::Closed;
+            }
+            cx.keep_key_focus();
+        }
+        ModalEvent::None
+    }
+
+    /// Start drawing the dialog, which gets sized to `width` and `height` (plus some padding).
+    pub fn begin_draw(&mut self, cx: &mut Cx, width: Width, height: Height) {
+        cx.begin_absolute_box();
+        self.backdrop_view.begin_view(cx, LayoutSize::FILL);
+        let rect = Rect { pos: cx.get_draw_pos(), size: vec2(cx.get_width_left(), cx.get_height_left()) };
+        self.backdrop.draw(cx, rect, COLOR_BACKDROP);
+        self.backdrop_view.end_view(cx);
+        cx.end_absolute_box();
+
+        cx.begin_absolute_box();
+        self.dialog_view.begin_view(cx, LayoutSize::FILL);
+        cx.begin_center_x_and_y_align();
+        self.dialog.begin_draw(cx, width, height, COLOR_DIALOG);
+        cx.begin_padding_box(Padding::all(DIALOG_PADDING));
+    }
+
+    /// Ends the corresponding [`Modal::begin_draw`].
+    pub fn end_draw(&mut self, cx: &mut Cx) {
+        cx.end_padding_box();
+        self.dialog.end_draw(cx);
+        cx.end_center_x_and_y_align();
+        self.dialog_view.end_view(cx);
+        cx.end_absolute_box();
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use zaplib::*;
+
+    use crate::*;
+
+    struct ModalTestApp {
+        text_input: TextInput,
+        delete_button: Button,
+        modal: Modal,
+        ok_button: Button,
+        events: Vec<ModalEvent>,
+        deleted: bool,
+    }
+
+    impl MainApp for ModalTestApp {
+        fn new(_cx: &mut Cx) -> Self {
+            Self {
+                text_input: TextInput::new(TextInputOptions { empty_message: "Name".to_string(), ..TextInputOptions::default() }),
+                delete_button: Button::default(),
+                modal: Modal::default(),
+                ok_button: Button::default(),
+                events: vec![],
+                deleted: false,
+            }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            if self.modal.is_open() {
+                if let ButtonEvent::Clicked = self.ok_button.handle(cx, event) {
+                    self.deleted = true;
+                    self.modal.close(cx);
+                }
+            }
+            let modal_event = self.modal.handle(cx, event);
+            if modal_event != ModalEvent::None {
+                self.events.push(modal_event);
+            }
+
+            self.text_input.handle(cx, event);
+            if let ButtonEvent::Clicked = self.delete_button.handle(cx, event) {
+                self.modal.open(cx);
+            }
+            if let Event::KeyDown(ke) = event {
+                if ke.key_code == KeyCode::Return && self.text_input.text_editor.has_key_focus(cx) {
+                    self.modal.open(cx);
+                }
+            }
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            cx.begin_padding_box(Padding::all(10.));
+            self.text_input.draw(cx);
+            self.delete_button.draw(cx, "Delete");
+            cx.end_padding_box();
+
+            if self.modal.is_open() {
+                self.modal.begin_draw(cx, Width::Compute, Height::Compute);
+                TextIns::draw_walk(cx, "Really delete?", &TextInsProps::DEFAULT);
+                self.ok_button.draw(cx, "OK");
+                self.modal.end_draw(cx);
+            }
+        }
+    }
+
+    fn center(test: &AppTest<ModalTestApp>, area: Area) -> Vec2 {
+        let rect = area.get_rect_for_first_instance(&test.cx).unwrap();
+        rect.pos + rect.size * 0.5
+    }
+
+    #[test]
+    fn test_modal() {
+        let mut test = AppTest::<ModalTestApp>::new_in_window(vec2(200., 120.));
+
+        // Nothing underneath the dialog gets pointer events.
+        let delete_pos = center(&test, test.app.delete_button.area());
+        test.click(delete_pos);
+        assert!(test.app.modal.is_open());
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/modal.png"));
+        test.click(vec2(25., 28.));
+        test.text_input("a");
+        assert_eq!(test.app.text_input.get_value(), "");
+        test.click(delete_pos);
+        assert!(test.app.modal.is_open());
+
+        // Buttons inside of the dialog do work.
+        test.click(center(&test, test.app.ok_button.area()));
+        assert!(!test.app.modal.is_open());
+        assert!(test.app.deleted);
+
+        // Escape closes the dialog, and gives the key focus back.
+        test.click(vec2(25., 28.));
+        test.text_input("a");
+        test.key_press(KeyCode::Return);
+        assert!(test.app.modal.is_open());
+        test.text_input("b");
+        test.key_press(KeyCode::Escape);
+        assert!(!test.app.modal.is_open());
+        assert_eq!(test.app.events, vec![ModalEvent::Closed]);
+        test.text_input("c");
+        assert_eq!(test.app.text_input.get_value(), "ac");
+    }
+}
@@ -6,11 +6,11 @@ use zaplib::*;
 /// For more general information about popovers, see
 /// <https://uxdesign.cc/pop-up-popover-or-popper-a-quick-look-into-ui-terms-cb4114fca2a>.
 ///
-/// TODO(JP): This currently only supports showing a popover _above_ the current
-/// box position. To make this more useful we should add different ways
-/// of positioning this.
+/// [`Popover::begin_draw`] shows a popover _above_ the current box position, and
+/// [`Popover::begin_draw_anchored`] shows it below some [`Rect`] (or above it if
+/// there is no room below it in the window).
 ///
-/// TODO(JP): This currently assumes you draw this on top of everything. That is
+/// TODO(JP): [`Popover::begin_draw`] currently assumes you draw this on top of everything. That is
 /// not always practical (e.g. if you want to show a tooltip in a deeply nested
 /// widget), and it also doesn't break out of [`View`]s currently, so we might
 /// need to use some combination of [`View::is_overlay`] and z-depth. To show
@@ -19,38 +19,35 @@ use zaplib::*;
 ///
 /// TODO(JP): Aligning the popover doesn't actually work for nested [`View`]s yet,
 /// like [`crate::TextInput`] or [`crate::TextEditor`]. See [`Cx::do_align_x`] and [`Cx::do_align_y`].
-#[derive(Default)]
 pub struct Popover {
+    component_id: ComponentId,
     background: Background,
+    view: View,
+}
+
+impl Default for Popover {
+    fn default() -> Self {
+        Self { component_id: Default::default(), background: Background::default(), view: View::default().with_is_overlay(true) }
+    }
 }
 
 impl Popover {
     /// Handle events for the [`Popover`] widget.
     ///
-    /// Always marks events like [`PointerDownEvent`] and [`PointerHoverEvent`] as "handled".
-    /// This way, we don't leak those events to whatever is sitting in the background.
-    /// This does require this function to get called before any other event handlers!
+    /// Marks events like [`PointerDownEvent`] and [`PointerHoverEvent`] on top of the popover as "handled".
+    /// This way, we don't leak those events to whatever is sitting in the background. Call this after the
+    /// event handlers of whatever is drawn inside of the popover, but before any other event handlers.
     ///
-    /// TODO(JP): This might actually be a place where our eventing system breaks
-    /// down a bit. If you instantiate a [`Popover`] deep inside your application
-    /// inside some widget, then any events (e.g. [`PointerMoveEvent`]) might already
-    /// get handled earlier in the application. There are a few ways we can fix
-    /// this:
-    /// - We could do all of this in user space, by having some notion of a
-    ///   "popover manager" that adds a [`View`] at the top level of the application
-    ///   and can listen to "signal" events or so if you want to instantiate
-    ///   a popover. Otherwise it might be tricky to communicate from inside
-    ///   a widget to this "popover manager", or you'd have to pass a reference
-    ///   to every widget! If we want to make this better, we can also consider
-    ///   adding a function to get access to the application state through
-    ///   [`Cx`], since we already pass that everywhere.
-    /// - We could also make such a "popover manager" or "popover layer" part of
-    ///   the framework itself. Rik Arends told me that that has been on his
-    ///   TODO list, so there indeed isn't proper support for this yet. I think
-    ///   it might be better to start in user space though, since that's more
-    ///   flexible, and then we can "graduate" it when we're happy with it.
-    pub fn handle(&mut self, _cx: &mut Cx, _event: &mut Event) {
-        // event.hits_pointer(cx, self.component_id, self.background.area().get_rect_for_first_instance(cx));
+    /// Since the popover is often instantiated deep inside of some other component, the other event handlers
+    /// might already have been called. For things that should always be on top, like modal dialogs, toasts,
+    /// and tooltips, see [`crate::Modal`], [`crate::Toasts`], and [`crate::Tooltip`], which are typically
+    /// handled and drawn at the top level of the application.
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if let Event::PointerDown(_) =
+            event.hits_pointer(cx, self.component_id, self.background.area().get_rect_for_first_instance(cx))
+        {
+            cx.keep_key_focus();
+        }
     }
 
     /// Draw the popover.
@@ -72,4 +69,39 @@ impl Popover {
         cx.end_column();
         cx.end_absolute_box();
     }
+
+    /// Draw the popover at a fixed `size` right below `anchor`, which is in window coordinates (e.g. from
+    /// [`Area::get_rect_for_first_instance`]). If it doesn't fit below `anchor` in the window, but does fit above
+    /// it, then it's flipped to go above `anchor` instead. It's also moved to the left if it would otherwise
+    /// stick out of the right side of the window.
+    ///
+    /// Unlike [`Popover::begin_draw`], this uses an overlay [`View`] (see [`View::with_is_overlay`]), so it's
+    /// drawn on top of everything else, regardless of where in the draw tree it is called.
+    ///
+    /// Returns the [`Rect`] that the popover is drawn in.
+    pub fn begin_draw_anchored(&mut self, cx: &mut Cx, anchor: Rect, size: Vec2, color: Vec4) -> Rect {
+        let pass_size = cx.get_pass_size();
+        let below_y = anchor.pos.y + anchor.size.y;
+        let y = if below_y + size.y > pass_size.y && anchor.pos.y - size.y >= 0. { anchor.pos.y - size.y } else { below_y };
+        let x = anchor.pos.x.min(pass_size.x - size.x).max(0.);
+
+        cx.begin_absolute_box();
+        self.view.begin_view(cx, LayoutSize::FILL);
+        cx.begin_padding_box(Padding { l: x, t: y, r: 0., b: 0. });
+        self.background.begin_draw(cx, Width::Fix(size.x), Height::Fix(size.y), color);
+        Rect { pos: vec2(x, y), size }
+    }
+
+    /// Ends the corresponding [`Popover::begin_draw_anchored`].
+    pub fn end_draw_anchored(&mut self, cx: &mut Cx) {
+        self.background.end_draw(cx);
+        cx.end_padding_box();
+        self.view.end_view(cx);
+        cx.end_absolute_box();
+    }
+
+    /// The [`Area`] of the popover's background, e.g. for hit testing.
+    pub fn area(&self) -> Area {
+        self.background.area()
+    }
 }
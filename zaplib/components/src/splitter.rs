@@ -290,3 +290,55 @@ impl Splitter {
         };
     }
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use crate::*;
+    use zaplib::*;
+
+    struct SplitterTestApp {
+        splitter: Splitter,
+        left: Background,
+        right: Background,
+    }
+
+    impl MainApp for SplitterTestApp {
+        fn new(_cx: &mut Cx) -> Self {
+            let mut splitter = Splitter::default();
+            splitter.set_splitter_state(SplitterAlign::First, 40., Axis::Vertical);
+            Self { splitter, left: Background::default(), right: Background::default() }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            self.splitter.handle(cx, event);
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            cx.begin_row(Width::Fill, Height::Fill);
+            self.splitter.begin_draw(cx);
+            self.left.begin_draw(cx, Width::Fill, Height::Fill, vec4(0.8, 0.2, 0.2, 1.));
+            self.left.end_draw(cx);
+            self.splitter.mid_draw(cx);
+            self.right.begin_draw(cx, Width::Fill, Height::Fill, vec4(0.2, 0.2, 0.8, 1.));
+            self.right.end_draw(cx);
+            self.splitter.end_draw(cx);
+            cx.end_row();
+        }
+    }
+
+    #[test]
+    fn test_splitter_drag() {
+        let mut test = AppTest::<SplitterTestApp>::new_in_window(vec2(120., 60.));
+        test.assert_golden_draw_tree(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/splitter_initial.txt"));
+
+        test.pointer_down(vec2(41., 30.));
+        test.pointer_move(vec2(60., 30.));
+        test.pointer_move(vec2(95., 30.));
+        test.pointer_up();
+        test.advance_time(1.0);
+        assert!(test.app.splitter.align == SplitterAlign::Last);
+        assert_eq!(test.app.splitter.pos, 26.);
+        test.assert_golden_draw_tree(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/splitter_dragged.txt"));
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/splitter_dragged.png"));
+    }
+}
@@ -1,16 +1,14 @@
 use zaplib::*;
 
 use crate::textcursor::*;
+use crate::textrope::*;
+use crate::tokenchunks::*;
 use crate::tokentype::*;
 
 #[derive(Clone, Default)]
 pub struct TextBuffer {
-    // Vec<Vec<char>> was chosen because, for all practical use (code) most lines are short
-    // Concatenating the total into a utf8 string is trivial, and O(1) for windowing into the lines is handy.
-    // Also inserting lines is pretty cheap even approaching 100k lines.
-    // If you want to load a 100 meg single line file or something with >100k lines
-    // other options are better. But these are not usecases for this editor.
-    pub lines: Vec<Vec<char>>,
+    /// The text, split into lines without their newlines. See [`TextRope`] for how this is stored.
+    pub lines: TextRope,
     pub undo_stack: Vec<TextUndo>,
     pub redo_stack: Vec<TextUndo>,
 
@@ -19,11 +17,10 @@ pub struct TextBuffer {
     pub mutation_id: u32,
     pub is_crlf: bool,
     pub markers: TextBufferMarkers,
-    pub flat_text: Vec<char>,
-    pub token_chunks: Vec<TokenChunk>,
+    /// The tokens of [`TextBuffer::lines`]. Their chars aren't stored separately, use
+    /// [`TextBuffer::iter_token_chunks`] to get them.
+    pub token_chunks: TokenChunks,
     pub was_invalid_pair: bool,
-    pub old_flat_text: Vec<char>,
-    pub old_token_chunks: Vec<TokenChunk>,
     pub token_chunks_id: u32,
     pub keyboard: TextBufferKeyboard,
 }
@@ -65,7 +62,7 @@ pub struct TextBufferMessage {
     pub body: String,
 }
 
-#[derive(Clone, Copy, PartialEq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct TextPos {
     pub row: usize,
     pub col: usize,
@@ -153,38 +150,80 @@ impl TextBuffer {
         tb
     }
 
+    /// Returns whether the text changed since the last tokenization, in which case [`TextBuffer::token_chunks`] gets
+    /// cleared, and needs to be filled by tokenizing the whole text again with [`TokenChunks::push`].
+    ///
+    /// Prefer [`TextBuffer::update_token_chunks`], which only tokenizes what changed.
     pub fn needs_token_chunks(&mut self) -> bool {
         if self.token_chunks_id != self.mutation_id {
             self.token_chunks_id = self.mutation_id;
-            if !self.was_invalid_pair {
-                std::mem::swap(&mut self.token_chunks, &mut self.old_token_chunks);
-                std::mem::swap(&mut self.flat_text, &mut self.old_flat_text);
-            }
             self.was_invalid_pair = false;
-            self.token_chunks.truncate(0);
-            self.flat_text.truncate(0);
+            self.token_chunks.clear();
             return true;
         }
         false
     }
 
+    /// Bring [`TextBuffer::token_chunks`] up to date with the text, using `tokenizer` (in its initial state). Returns
+    /// whether anything changed.
+    ///
+    /// After an edit, this restarts tokenizing at the start of the [`TokenChunks`] segment before the edit, and stops
+    /// as soon as the tokenizer gets back into the same state as before the edit, so typing in a large file only
+    /// tokenizes a few lines.
+    ///
+    /// Don't mix tokenizer types or initial states on the same [`TextBuffer`], since the checkpoints would not match.
+    pub fn update_token_chunks<T: Tokenizer>(&mut self, tokenizer: &T) -> bool {
+        if self.token_chunks_id == self.mutation_id {
+            return false;
+        }
+        self.token_chunks_id = self.mutation_id;
+        self.token_chunks.update(&self.lines, tokenizer);
+        self.was_invalid_pair = self.token_chunks.has_invalid_pair();
+        true
+    }
+
+    /// Iterate over [`TextBuffer::token_chunks`] along with their chars.
+    pub fn iter_token_chunks(&self) -> TokenChunkTextIter<'_> {
+        self.iter_token_chunks_from(0)
+    }
+
+    /// Iterate over [`TextBuffer::token_chunks`] along with their chars, starting at the token with index `index`.
+    pub fn iter_token_chunks_from(&self, index: usize) -> TokenChunkTextIter<'_> {
+        let token_chunks = self.token_chunks.iter_from(index);
+        let pos = self.token_chunks.get(index).map_or(TextPos::zero(), |token_chunk| self.offset_to_text_pos(token_chunk.offset));
+        let mut lines = self.lines.iter_from(pos.row);
+        let line = lines.next().unwrap_or("");
+        let line_len = line.chars().count();
+        let line_offset = self.text_pos_to_offset(TextPos { row: pos.row, col: 0 });
+        TokenChunkTextIter {
+            text_buffer: self,
+            token_chunks,
+            index,
+            lines,
+            line,
+            line_len,
+            row: pos.row,
+            line_offset,
+            col: 0,
+            rest: line,
+        }
+    }
+
     pub fn scan_token_chunks_prev_line(&self, token: usize, lines: usize) -> (usize, isize) {
-        let mut nls = 0;
-        for i in (0..token).rev() {
-            if let TokenType::Newline = self.token_chunks[i].token_type {
-                nls += 1;
-                if nls == lines {
-                    return (i + 1, -1);
-                }
-            }
+        // Find the start of the line instead of walking back through the tokens, assuming a newline token per line.
+        let row =
+            self.token_chunks.get(token).map_or(self.lines.len(), |token_chunk| self.offset_to_text_pos(token_chunk.offset).row);
+        if lines == 0 || row < lines {
+            return (0, 0);
         }
-        (0, 0)
+        let offset = self.text_pos_to_offset(TextPos { row: row + 1 - lines, col: 0 });
+        (self.token_chunks.find_offset(offset).unwrap_or(token), -1)
     }
 
     pub fn scan_token_chunks_next_line(&self, token: usize, lines: usize) -> usize {
         let mut nls = 0;
-        for i in token..self.token_chunks.len() {
-            if let TokenType::Newline = self.token_chunks[i].token_type {
+        for (i, token_chunk) in (token..).zip(self.token_chunks.iter_from(token)) {
+            if let TokenType::Newline = token_chunk.token_type {
                 nls += 1;
                 if nls == lines {
                     return i + 1;
@@ -195,55 +234,62 @@ impl TextBuffer {
     }
 
     pub fn offset_to_text_pos(&self, char_offset: usize) -> TextPos {
-        let mut char_count = 0;
-        for (row, line) in self.lines.iter().enumerate() {
-            let next_char_count = char_count + line.len() + 1;
-            if next_char_count > char_offset {
-                return TextPos { row, col: char_offset - char_count };
-            }
-            char_count = next_char_count;
-        }
-        TextPos { row: self.lines.len().max(1) - 1, col: 0 }
+        self.lines.offset_to_text_pos(char_offset)
     }
 
     pub fn offset_to_text_pos_next(&self, query_off: usize, old_pos: TextPos, old_off: usize) -> TextPos {
-        let mut row = old_pos.row;
         let mut iter_off = old_off - old_pos.col;
-        while row < self.lines.len() {
-            let line = &self.lines[row];
-            let next_off = iter_off + line.len() + 1;
+        for (row, line) in (old_pos.row..).zip(self.lines.iter_from(old_pos.row)) {
+            let next_off = iter_off + line.chars().count() + 1;
             if next_off > query_off {
                 return TextPos { row, col: query_off - iter_off };
             }
             iter_off = next_off;
-            row += 1;
         }
         TextPos { row: self.lines.len().max(1) - 1, col: 0 }
     }
 
     pub fn text_pos_to_offset(&self, pos: TextPos) -> usize {
-        let mut char_count = 0;
-        if pos.row >= self.lines.len() {
-            return self.calc_char_count();
+        self.lines.text_pos_to_offset(pos)
+    }
+
+    /// The offset of the next grapheme cluster boundary after `offset`, so that e.g. a character and its combining
+    /// accents are treated as one. Line endings count as a single character.
+    pub fn next_grapheme_offset(&self, offset: usize) -> usize {
+        let pos = self.offset_to_text_pos(offset);
+        match self.lines.get(pos.row).map(|line| line.chars().collect::<Vec<_>>()) {
+            Some(line) if pos.col < line.len() => offset - pos.col + grapheme::next_boundary(&line, pos.col),
+            _ => offset + 1,
         }
-        for (ln_row, line) in self.lines.iter().enumerate() {
-            if ln_row == pos.row {
-                return char_count + (line.len()).min(pos.col);
-            }
-            char_count += line.len() + 1;
+    }
+
+    /// The offset of the previous grapheme cluster boundary before `offset`; see [`TextBuffer::next_grapheme_offset`].
+    pub fn prev_grapheme_offset(&self, offset: usize) -> usize {
+        let pos = self.offset_to_text_pos(offset);
+        match self.lines.get(pos.row).map(|line| line.chars().collect::<Vec<_>>()) {
+            Some(line) if pos.col > 0 => offset - pos.col + grapheme::prev_boundary(&line, pos.col),
+            _ => offset.saturating_sub(1),
+        }
+    }
+
+    /// Moves `offset` back to the start of the grapheme cluster that it is in, if it is in the middle of one.
+    pub fn snap_to_grapheme_offset(&self, offset: usize) -> usize {
+        let pos = self.offset_to_text_pos(offset);
+        match self.lines.get(pos.row).map(|line| line.chars().collect::<Vec<_>>()) {
+            Some(line) if !grapheme::is_boundary(&line, pos.col) => offset - pos.col + grapheme::prev_boundary(&line, pos.col),
+            _ => offset,
         }
-        0
     }
 
     pub fn get_nearest_line_range(&self, offset: usize) -> (usize, usize) {
         let pos = self.offset_to_text_pos(offset);
-        let line = &self.lines[pos.row];
-        (offset - pos.col, line.len() + if pos.row < (line.len().max(1) - 1) { 1 } else { 0 })
+        let line_len = self.lines.line_len(pos.row);
+        (offset - pos.col, line_len + if pos.row < (line_len.max(1) - 1) { 1 } else { 0 })
     }
 
     pub fn calc_next_line_indent_depth(&self, offset: usize, tabsize: usize) -> (usize, usize) {
         let pos = self.offset_to_text_pos(offset);
-        let line = &self.lines[pos.row];
+        let line = self.lines.line_chars(pos.row);
         let mut prev_index = pos.col;
         if prev_index == 0 || prev_index > line.len() {
             return (offset - pos.col, 0);
@@ -270,7 +316,7 @@ impl TextBuffer {
     }
 
     pub fn calc_line_indent_depth(&self, row: usize) -> usize {
-        let line = &self.lines[row];
+        let line = self.lines.line_chars(row);
         for (i, ch) in line.iter().enumerate() {
             if *ch != ' ' {
                 return i;
@@ -281,7 +327,7 @@ impl TextBuffer {
 
     pub fn calc_backspace_line_indent_depth_and_pair(&self, offset: usize) -> (usize, usize) {
         let pos = self.offset_to_text_pos(offset);
-        let line = &self.lines[pos.row];
+        let line = self.lines.line_chars(pos.row);
         // check pair removal
         if pos.col >= 1 && pos.col < line.len() {
             let pch = line[pos.col - 1];
@@ -290,7 +336,8 @@ impl TextBuffer {
                 return (offset - 1, 2);
             }
         }
-        (offset - 1, 1)
+        let start = self.prev_grapheme_offset(offset);
+        (start, offset - start)
     }
 
     pub fn calc_deletion_whitespace(&self, offset: usize) -> Option<(usize, usize, usize, usize)> {
@@ -298,9 +345,9 @@ impl TextBuffer {
         if self.lines.is_empty() || pos.row >= self.lines.len() - 1 {
             return None;
         }
-        let line1 = &self.lines[pos.row];
+        let line1 = self.lines.line_chars(pos.row);
         let mut line1_ws = 0;
-        for ch in line1 {
+        for ch in &line1 {
             if *ch != ' ' {
                 break;
             }
@@ -309,8 +356,8 @@ impl TextBuffer {
 
         let line2 = &self.lines[pos.row + 1];
         let mut line2_ws = 0;
-        for ch in line2 {
-            if *ch != ' ' {
+        for ch in line2.chars() {
+            if ch != ' ' {
                 break;
             }
             line2_ws += 1;
@@ -324,9 +371,9 @@ impl TextBuffer {
         if self.lines.is_empty() || pos.row >= self.lines.len() {
             return None;
         }
-        let line1 = &self.lines[pos.row];
+        let line1 = self.lines.line_chars(pos.row);
         let mut line1_ws = 0;
-        for ch in line1 {
+        for ch in &line1 {
             if *ch != ' ' {
                 break;
             }
@@ -337,7 +384,7 @@ impl TextBuffer {
     }
 
     pub fn calc_char_count(&self) -> usize {
-        calc_char_count(&self.lines)
+        self.lines.char_count()
     }
 
     pub fn get_line_count(&self) -> usize {
@@ -345,46 +392,40 @@ impl TextBuffer {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.lines.len() == 0 || self.lines.len() == 1 && self.lines[0].len() == 0
+        self.lines.is_empty() || self.lines.len() == 1 && self.lines[0].is_empty()
     }
 
     pub fn get_range_as_string(&self, start: usize, len: usize, ret: &mut String) {
-        let mut pos = self.offset_to_text_pos(start);
-        for _ in 0..len {
-            let line = &self.lines[pos.row];
-            if pos.col >= line.len() {
-                ret.push('\n');
-                pos.col = 0;
-                pos.row += 1;
-                if pos.row >= self.lines.len() {
-                    return;
-                }
-            } else {
-                ret.push(line[pos.col]);
-                pos.col += 1;
+        let pos = self.offset_to_text_pos(start);
+        let mut col = pos.col;
+        let mut remaining = len;
+        for line in self.lines.iter_from(pos.row) {
+            for ch in line.chars().skip(col).take(remaining) {
+                ret.push(ch);
+                remaining -= 1;
+            }
+            if remaining == 0 {
+                return;
             }
+            ret.push('\n');
+            remaining -= 1;
+            col = 0;
         }
     }
 
     pub fn get_char(&self, start: usize) -> char {
         let pos = self.offset_to_text_pos(start);
-        let line = &self.lines[pos.row];
-        if pos.row == self.lines.len() - 1 && pos.col >= line.len() {
-            return '\0';
+        match self.lines[pos.row].chars().nth(pos.col) {
+            Some(ch) => ch,
+            None if pos.row == self.lines.len() - 1 => '\0',
+            None => '\n',
         }
-        if pos.col >= line.len() {
-            return '\n';
-        }
-        line[pos.col]
     }
 
     pub fn get_as_string(&self) -> String {
         let mut ret = String::new();
-        for i in 0..self.lines.len() {
-            let line = &self.lines[i];
-            for ch in line {
-                ret.push(*ch);
-            }
+        for (i, line) in self.lines.iter().enumerate() {
+            ret.push_str(line);
             if i != self.lines.len() - 1 {
                 if self.is_crlf {
                     ret.push('\r');
@@ -399,29 +440,27 @@ impl TextBuffer {
 
     pub fn load_from_utf8(&mut self, utf8: &str) {
         self.is_crlf = utf8.contains("\r\n");
-        self.lines = TextBuffer::split_string_to_lines(utf8);
+        self.lines = TextRope::from_lines(utf8.split(if self.is_crlf { "\r\n" } else { "\n" }));
         self.mutation_id += 1;
+        self.token_chunks.reset_checkpoints();
     }
 
     pub fn replace_line(&mut self, row: usize, start_col: usize, len: usize, rep_line: Vec<char>) -> Vec<char> {
         self.mutation_id += 1;
-        self.lines[row].splice(start_col..(start_col + len), rep_line).collect()
+        let start = self.text_pos_to_offset(TextPos { row, col: start_col });
+        let rep_line_len = rep_line.len();
+        let line: Vec<char> = self.lines.modify_line(row, |line| line.splice(start_col..(start_col + len), rep_line).collect());
+        self.token_chunks.record_edit(start, line.len(), rep_line_len);
+        line
     }
 
     pub fn copy_line(&self, row: usize, start_col: usize, len: usize) -> Vec<char> {
-        let line = &self.lines[row];
-        if start_col >= line.len() {
-            return vec![];
-        }
-        if start_col + len > line.len() {
-            self.lines[row][start_col..line.len()].to_vec()
-        } else {
-            self.lines[row][start_col..(start_col + len)].to_vec()
-        }
+        self.lines[row].chars().skip(start_col).take(len).collect()
     }
 
     pub fn mark_clean(&mut self) {
         self.token_chunks_id = self.mutation_id;
+        self.token_chunks.reset_checkpoints();
     }
 
     pub fn replace_range(&mut self, start: usize, len: usize, mut rep_lines: Vec<Vec<char>>) -> Vec<Vec<char>> {
@@ -429,78 +468,33 @@ impl TextBuffer {
         let start_pos = self.offset_to_text_pos(start);
         let end_pos = self.offset_to_text_pos_next(start + len, start_pos, start);
 
+        if start_pos.row > end_pos.row || start_pos.row == end_pos.row && start_pos.col > end_pos.col {
+            return vec![];
+        }
+
         if start_pos.row == end_pos.row && rep_lines.len() == 1 {
             // replace in one line
             let rep_line_zero = rep_lines.drain(0..1).next().unwrap();
-
-            if start_pos.col > end_pos.col {
-                return vec![];
-            }
-            let line = self.lines[start_pos.row].splice(start_pos.col..end_pos.col, rep_line_zero).collect();
+            let rep_line_len = rep_line_zero.len();
+            let line: Vec<char> =
+                self.lines.modify_line(start_pos.row, |line| line.splice(start_pos.col..end_pos.col, rep_line_zero).collect());
+            self.token_chunks.record_edit(start, line.len(), rep_line_len);
             return vec![line];
-        } else if rep_lines.len() == 1 {
-            // we are replacing multiple lines with one line
-            // drain first line
-            let rep_line_zero = rep_lines.drain(0..1).next().unwrap();
-
-            // replace it in the first
-            let first = self.lines[start_pos.row].splice(start_pos.col.., rep_line_zero).collect();
-
-            // collect the middle ones
-            let mut middle: Vec<Vec<char>> = self.lines.drain((start_pos.row + 1)..(end_pos.row)).collect();
-
-            // cut out the last bit
-            let last: Vec<char> = self.lines[start_pos.row + 1].drain(0..end_pos.col).collect();
-
-            // last line bit
-            let mut last_line = self.lines.drain((start_pos.row + 1)..(start_pos.row + 2)).next().unwrap();
-
-            // merge start_row+1 into start_row
-            self.lines[start_pos.row].append(&mut last_line);
-
-            // concat it all together
-            middle.insert(0, first);
-            middle.push(last);
-
-            middle
-        } else if start_pos.row == end_pos.row {
-            // replacing single line with multiple lines
-
-            let mut last_bit: Vec<char> = self.lines[start_pos.row].drain(end_pos.col..).collect();
-            // but we have co drain end_col..
-
-            // replaced first line
-            let rep_lines_len = rep_lines.len();
-            let rep_line_first: Vec<char> = rep_lines.drain(0..1).next().unwrap();
-            let line = self.lines[start_pos.row].splice(start_pos.col.., rep_line_first).collect();
-
-            // splice in middle rest
-            let rep_line_mid = rep_lines.drain(0..(rep_lines.len()));
-            self.lines.splice((start_pos.row + 1)..(start_pos.row + 1), rep_line_mid);
-
-            // append last bit
-            self.lines[start_pos.row + rep_lines_len - 1].append(&mut last_bit);
-
-            return vec![line];
-        } else {
-            // replaceing multiple lines with multiple lines
-            // drain and replace last line
-            let rep_line_last = rep_lines.drain((rep_lines.len() - 1)..(rep_lines.len())).next().unwrap();
-            let last = self.lines[end_pos.row].splice(..end_pos.col, rep_line_last).collect();
-
-            // swap out middle lines and drain them
-            let rep_line_mid = rep_lines.drain(1..(rep_lines.len()));
-            let mut middle: Vec<Vec<char>> = self.lines.splice((start_pos.row + 1)..end_pos.row, rep_line_mid).collect();
+        }
 
-            // drain and replace first line
-            let rep_line_zero = rep_lines.drain(0..1).next().unwrap();
-            let first = self.lines[start_pos.row].splice(start_pos.col.., rep_line_zero).collect();
+        // replace all lines in the range, with the bits before and after the range put back in
+        let rep_lines_chars = calc_char_count(&rep_lines);
+        let mut first_bit: Vec<char> = self.lines[start_pos.row].chars().take(start_pos.col).collect();
+        first_bit.append(&mut rep_lines[0]);
+        rep_lines[0] = first_bit;
+        let last_bit = self.lines[end_pos.row].chars().skip(end_pos.col);
+        rep_lines.last_mut().unwrap().extend(last_bit);
 
-            // concat it all together
-            middle.insert(0, first);
-            middle.push(last);
-            middle
-        }
+        let mut lines = self.lines.splice(start_pos.row..(end_pos.row + 1), rep_lines);
+        lines.last_mut().unwrap().truncate(end_pos.col);
+        lines[0].drain(..start_pos.col);
+        self.token_chunks.record_edit(start, calc_char_count(&lines), rep_lines_chars);
+        lines
     }
 
     pub fn replace_lines(&mut self, start_row: usize, end_row: usize, rep_lines: Vec<Vec<char>>) -> TextOp {
@@ -558,12 +552,11 @@ impl TextBuffer {
                 last_clamp_range: None,
             },
         });
-        // check if we can hotpatch tokenchunks
+        // check if we can hotpatch tokenchunks; their chars come from the lines, so only their lengths have to match
         if !was_dirty && value.len() == end - start {
-            for (index, c) in value.chars().enumerate() {
-                self.flat_text[start + index] = c;
-            }
             self.token_chunks_id = self.mutation_id;
+            // The tokenizer states in the checkpoints might depend on the old text.
+            self.token_chunks.reset_checkpoints();
             return true;
         }
         false
@@ -631,6 +624,75 @@ impl TextBuffer {
     }
 }
 
+/// A token of [`TextBuffer::token_chunks`] along with its chars; see [`TextBuffer::iter_token_chunks`].
+pub struct TokenChunkText<'a> {
+    pub index: usize,
+    pub token_chunk: TokenChunk,
+    /// The chars of the token. Past the end of the text, where tokenizers put the [`TokenType::Eof`] token, these
+    /// are spaces.
+    pub chars: Vec<char>,
+    /// The rest of the line that the token starts on, from the start of the token and without the newline.
+    pub line: &'a str,
+}
+
+/// Iterator over the tokens of a [`TextBuffer`] along with their chars, which get read from [`TextBuffer::lines`].
+pub struct TokenChunkTextIter<'a> {
+    text_buffer: &'a TextBuffer,
+    token_chunks: TokenChunksIter<'a>,
+    index: usize,
+    lines: TextRopeIter<'a>,
+    /// The line that the last token started on, its number of chars, and where it starts.
+    line: &'a str,
+    line_len: usize,
+    row: usize,
+    line_offset: usize,
+    /// The rest of `line` from column `col`, so that walking through the tokens of a line doesn't have to skip over
+    /// all the chars before each token.
+    col: usize,
+    rest: &'a str,
+}
+
+impl<'a> Iterator for TokenChunkTextIter<'a> {
+    type Item = TokenChunkText<'a>;
+
+    fn next(&mut self) -> Option<TokenChunkText<'a>> {
+        let token_chunk = self.token_chunks.next()?;
+        while token_chunk.offset > self.line_offset + self.line_len && self.row + 1 < self.text_buffer.lines.len() {
+            self.line_offset += self.line_len + 1;
+            self.line = self.lines.next().unwrap();
+            self.line_len = self.line.chars().count();
+            self.row += 1;
+            self.col = 0;
+            self.rest = self.line;
+        }
+        let col = (token_chunk.offset - self.line_offset).min(self.line_len);
+        if col < self.col {
+            self.col = 0;
+            self.rest = self.line;
+        }
+        let skip_bytes = self.rest.char_indices().nth(col - self.col).map_or(self.rest.len(), |(index, _)| index);
+        self.rest = &self.rest[skip_bytes..];
+        self.col = col;
+
+        let is_last_line = self.row + 1 >= self.text_buffer.lines.len();
+        let chars = if col + token_chunk.len <= self.line_len {
+            self.rest.chars().take(token_chunk.len).collect()
+        } else if col == self.line_len && token_chunk.len == 1 && !is_last_line {
+            vec!['\n']
+        } else {
+            // Spans multiple lines, or goes past the end of the text.
+            let in_text = token_chunk.len.min(self.text_buffer.lines.char_count().saturating_sub(token_chunk.offset));
+            let mut string = String::new();
+            self.text_buffer.get_range_as_string(token_chunk.offset, in_text, &mut string);
+            let mut chars: Vec<char> = string.chars().collect();
+            chars.resize(token_chunk.len, ' ');
+            chars
+        };
+        self.index += 1;
+        Some(TokenChunkText { index: self.index - 1, token_chunk, chars, line: self.rest })
+    }
+}
+
 pub struct LineTokenizer<'a> {
     pub prev: char,
     pub cur: char,
@@ -696,3 +758,166 @@ impl<'a> LineTokenizer<'a> {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// Tokenizes `/* */` comments that span multiple lines, to have some tokenizer state.
+    #[derive(Clone, Default, PartialEq)]
+    struct CommentTokenizer {
+        in_comment: bool,
+    }
+
+    impl Tokenizer for CommentTokenizer {
+        fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, _: &[TokenChunk]) -> TokenType {
+            if state.next == '\0' {
+                state.advance();
+                chunk.push(' ');
+                return TokenType::Eof;
+            }
+            if state.next == '\n' {
+                chunk.push(state.next);
+                state.advance();
+                return TokenType::Newline;
+            }
+            if self.in_comment || state.next == '/' {
+                while state.next != '\n' && state.next != '\0' {
+                    let prev = chunk.last().copied();
+                    chunk.push(state.next);
+                    state.advance();
+                    if self.in_comment && prev == Some('*') && chunk.last() == Some(&'/') {
+                        self.in_comment = false;
+                        return TokenType::CommentChunk;
+                    }
+                    if !self.in_comment && chunk.last() == Some(&'*') {
+                        self.in_comment = true;
+                    }
+                }
+                return TokenType::CommentChunk;
+            }
+            let token_type = match state.next {
+                '(' => TokenType::ParenOpen,
+                ')' => TokenType::ParenClose,
+                ' ' => TokenType::Whitespace,
+                _ => TokenType::Identifier,
+            };
+            chunk.push(state.next);
+            state.advance();
+            while token_type == TokenType::Identifier && !" ()/\n\0".contains(state.next) {
+                chunk.push(state.next);
+                state.advance();
+            }
+            token_type
+        }
+    }
+
+    fn assert_tokens_up_to_date(text_buffer: &mut TextBuffer) {
+        text_buffer.update_token_chunks(&CommentTokenizer::default());
+        let mut expected = TextBuffer::from_utf8(&text_buffer.get_as_string());
+        expected.update_token_chunks(&CommentTokenizer::default());
+
+        let collect_tokens = |text_buffer: &TextBuffer| -> Vec<(TokenType, usize, usize, usize, Vec<char>)> {
+            text_buffer
+                .iter_token_chunks()
+                .map(|token| {
+                    let chunk = token.token_chunk;
+                    (chunk.token_type, chunk.offset, chunk.len, chunk.pair_token, token.chars)
+                })
+                .collect()
+        };
+        let tokens = collect_tokens(text_buffer);
+        assert_eq!(tokens, collect_tokens(&expected));
+        assert_eq!(tokens.iter().flat_map(|token| token.4.iter()).collect::<String>(), text_buffer.get_as_string() + " ");
+
+        // Pair up parentheses across the whole text, like if it was one big segment.
+        let mut pair_stack = Vec::new();
+        let mut invalid_pair = false;
+        for (index, token) in tokens.iter().enumerate() {
+            match token.0 {
+                TokenType::ParenOpen => pair_stack.push(index),
+                TokenType::ParenClose => match pair_stack.pop() {
+                    Some(other) => {
+                        assert_eq!(token.3, other);
+                        assert_eq!(tokens[other].3, index);
+                    }
+                    None => {
+                        assert_eq!(token.3, index);
+                        invalid_pair = true;
+                    }
+                },
+                _ => assert_eq!(token.3, index),
+            }
+        }
+        for index in &pair_stack {
+            assert_eq!(tokens[*index].3, *index);
+        }
+        assert_eq!(text_buffer.was_invalid_pair, invalid_pair || !pair_stack.is_empty());
+    }
+
+    fn replace(text_buffer: &mut TextBuffer, start: usize, len: usize, string: &str) {
+        let op = text_buffer.replace_lines_with_string(start, len, string);
+        text_buffer.undo_stack.push(TextUndo {
+            ops: vec![op],
+            grouping: TextUndoGrouping::Other,
+            cursors: TextCursorSet::default(),
+        });
+    }
+
+    #[test]
+    fn test_incremental_tokenizing() {
+        let text: Vec<String> = (0..1000).map(|row| format!("line {} (with (parens))", row)).collect();
+        let mut text_buffer = TextBuffer::from_utf8(&text.join("\n"));
+        assert_tokens_up_to_date(&mut text_buffer);
+
+        let line_offset = |text_buffer: &TextBuffer, row| text_buffer.text_pos_to_offset(TextPos { row, col: 0 });
+
+        // Typing in a line.
+        let offset = line_offset(&text_buffer, 500) + 2;
+        replace(&mut text_buffer, offset, 0, "abc");
+        assert_tokens_up_to_date(&mut text_buffer);
+        let edited_len = text_buffer.token_chunks.len();
+
+        // Parentheses that span many lines.
+        let offset = line_offset(&text_buffer, 10);
+        replace(&mut text_buffer, offset, 0, "(");
+        let offset = line_offset(&text_buffer, 250);
+        replace(&mut text_buffer, offset, 0, ")");
+        assert_tokens_up_to_date(&mut text_buffer);
+        assert!(!text_buffer.was_invalid_pair);
+
+        // Opening a comment changes everything after it, and closing it changes it back.
+        let offset = line_offset(&text_buffer, 300);
+        replace(&mut text_buffer, offset, 0, "/*");
+        assert_tokens_up_to_date(&mut text_buffer);
+        assert!(text_buffer.token_chunks.len() < edited_len / 2);
+        let offset = line_offset(&text_buffer, 700);
+        replace(&mut text_buffer, offset, 0, "*/");
+        assert_tokens_up_to_date(&mut text_buffer);
+
+        // Multiple edits between tokenizing, inserting and removing lines and parentheses.
+        let offset = line_offset(&text_buffer, 100);
+        replace(&mut text_buffer, offset, 10, "new\nlines )\n");
+        let offset = line_offset(&text_buffer, 900);
+        let len = line_offset(&text_buffer, 950) - offset;
+        replace(&mut text_buffer, offset, len, "");
+        assert_tokens_up_to_date(&mut text_buffer);
+        assert!(text_buffer.was_invalid_pair);
+
+        // Undoing all of it.
+        let mut cursors = TextCursorSet::default();
+        while !text_buffer.undo_stack.is_empty() {
+            text_buffer.undo(false, &mut cursors);
+        }
+        assert_eq!(text_buffer.get_as_string(), text.join("\n"));
+        assert_tokens_up_to_date(&mut text_buffer);
+
+        // Typing into an empty buffer, where every edit is at the start of the first segment.
+        let mut text_buffer = TextBuffer::from_utf8("");
+        assert_tokens_up_to_date(&mut text_buffer);
+        for (offset, c) in "a (b)".chars().enumerate() {
+            replace(&mut text_buffer, offset, 0, &c.to_string());
+            assert_tokens_up_to_date(&mut text_buffer);
+        }
+    }
+}
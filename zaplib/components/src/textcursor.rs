@@ -64,8 +64,8 @@ impl TextCursor {
         let pos = text_buffer.offset_to_text_pos(self.head);
 
         // alright lets walk the line from the left till its no longer 9 or 32
-        for (index, ch) in text_buffer.lines[pos.row].iter().enumerate() {
-            if *ch != '\t' && *ch != ' ' {
+        for (index, ch) in text_buffer.lines[pos.row].chars().enumerate() {
+            if ch != '\t' && ch != ' ' {
                 self.head = text_buffer.text_pos_to_offset(TextPos { row: pos.row, col: index });
                 return;
             }
@@ -75,23 +75,28 @@ impl TextCursor {
     pub fn move_end(&mut self, text_buffer: &TextBuffer) {
         let pos = text_buffer.offset_to_text_pos(self.head);
         // alright lets walk the line from the left till its no longer 9 or 32
-        self.head = text_buffer.text_pos_to_offset(TextPos { row: pos.row, col: text_buffer.lines[pos.row].len() });
+        self.head = text_buffer.text_pos_to_offset(TextPos { row: pos.row, col: text_buffer.lines.line_len(pos.row) });
     }
 
-    pub fn move_left(&mut self, char_count: usize, _text_buffer: &TextBuffer) {
-        if self.head >= char_count {
-            self.head -= char_count;
-        } else {
-            self.head = 0;
+    /// Move the head left by `grapheme_count` grapheme clusters.
+    pub fn move_left(&mut self, grapheme_count: usize, text_buffer: &TextBuffer) {
+        for _ in 0..grapheme_count {
+            if self.head == 0 {
+                break;
+            }
+            self.head = text_buffer.prev_grapheme_offset(self.head);
         }
     }
 
-    pub fn move_right(&mut self, char_count: usize, total_char_count: usize, _text_buffer: &TextBuffer) {
-        if self.head + char_count < total_char_count {
-            self.head += char_count;
-        } else {
-            self.head = total_char_count;
+    /// Move the head right by `grapheme_count` grapheme clusters.
+    pub fn move_right(&mut self, grapheme_count: usize, total_char_count: usize, text_buffer: &TextBuffer) {
+        for _ in 0..grapheme_count {
+            if self.head >= total_char_count {
+                break;
+            }
+            self.head = text_buffer.next_grapheme_offset(self.head);
         }
+        self.head = self.head.min(total_char_count);
     }
 
     pub fn move_up(&mut self, line_count: usize, text_buffer: &TextBuffer) {
@@ -146,7 +151,7 @@ impl TextCursorSet {
             let (start, end) = cursor.order();
             text_buffer.get_range_as_string(start, end - start, &mut ret);
             let mut exact = false;
-            for tok in &text_buffer.token_chunks {
+            for tok in text_buffer.token_chunks.iter() {
                 if start == tok.offset && end == tok.offset + tok.len {
                     exact = true;
                     break;
@@ -158,7 +163,7 @@ impl TextCursorSet {
             return ret;
         }
 
-        for tok in &text_buffer.token_chunks {
+        for tok in text_buffer.token_chunks.iter() {
             if cursor.head >= tok.offset && cursor.head <= tok.offset + tok.len {
                 match &tok.token_type {
                     TokenType::Identifier | TokenType::Call | TokenType::TypeName => {
@@ -359,23 +364,23 @@ impl TextCursorSet {
         // lets start the cursor gen
         let mut offset = text_buffer.text_pos_to_offset(TextPos { row: top, col: 0 });
         for row in top..(bottom + 1) {
-            let line = &text_buffer.lines[row];
-            if left < line.len() {
+            let line_len = text_buffer.lines.line_len(row);
+            if left < line_len {
                 if start_pos.col < end_pos.col {
                     self.set.push(TextCursor {
                         tail: offset + left,
-                        head: offset + line.len().min(right),
-                        max: line.len().min(right),
+                        head: offset + line_len.min(right),
+                        max: line_len.min(right),
                     });
                 } else {
                     self.set.push(TextCursor {
                         head: offset + left,
-                        tail: offset + line.len().min(right),
-                        max: line.len().min(right),
+                        tail: offset + line_len.min(right),
+                        max: line_len.min(right),
                     });
                 }
             }
-            offset += line.len() + 1;
+            offset += line_len + 1;
         }
         // depending on the direction the last cursor remains
         if self.set.is_empty() {
@@ -403,12 +408,12 @@ impl TextCursorSet {
         for cursor in &mut self.set {
             let (start, end) = cursor.delta(delta);
             // lets find where we are as a cursor in the textbuffer
-            if start == end && start > 0 && start < text_buffer.flat_text.len() {
+            if start == end && start > 0 && start <= text_buffer.calc_char_count() {
                 // insert spaces till indent level
                 let (pre_base, pre_spaces) = text_buffer.calc_next_line_indent_depth(start, 4);
 
-                let pch = text_buffer.flat_text[start - 1];
-                let nch = text_buffer.flat_text[start];
+                let pch = text_buffer.get_char(start - 1);
+                let nch = text_buffer.get_char(start);
                 // we have to insert more newlines and spaces because we were between () {} or []
                 if pch == '{' && nch == '}' || pch == '(' && nch == ')' || pch == '[' && nch == ']' {
                     let mut text = String::new();
@@ -623,12 +628,14 @@ impl TextCursorSet {
                         delta -= l2ws as isize + 1;
                         text_buffer.replace_lines_with_string(start, l2ws + 1, "")
                     } else {
-                        delta += cursor.collapse(start, start + 1, 0);
-                        text_buffer.replace_lines_with_string(start, 1, "")
+                        let len = text_buffer.next_grapheme_offset(start) - start;
+                        delta += cursor.collapse(start, start + len, 0);
+                        text_buffer.replace_lines_with_string(start, len, "")
                     }
                 } else {
-                    delta += cursor.collapse(start, start + 1, 0);
-                    text_buffer.replace_lines_with_string(start, 1, "")
+                    let len = text_buffer.next_grapheme_offset(start) - start;
+                    delta += cursor.collapse(start, start + len, 0);
+                    text_buffer.replace_lines_with_string(start, len, "")
                 };
                 if !op.lines.is_empty() {
                     ops.push(op);
@@ -715,7 +722,7 @@ impl TextCursorSet {
             for row in start_pos.row..(end_pos.row + last_line) {
                 // ok so how do we compute the actual op offset of this line
                 let op = text_buffer.replace_line_with_string(off, row, 0, 0, tab_str);
-                off += text_buffer.lines[row].len() + 1;
+                off += text_buffer.lines.line_len(row) + 1;
                 ops.push(op);
             }
             // figure out which way the cursor is
@@ -737,7 +744,8 @@ impl TextCursorSet {
 
     pub fn replace_lines_formatted(&mut self, mut out_lines: Vec<Vec<char>>, text_buffer: &mut TextBuffer) {
         let mut top_row = 0;
-        while top_row < text_buffer.lines.len() && top_row < out_lines.len() && text_buffer.lines[top_row] == out_lines[top_row] {
+        while top_row < text_buffer.lines.len() && top_row < out_lines.len() && text_buffer.lines[top_row].chars().eq(out_lines[top_row].iter().copied())
+        {
             top_row += 1;
         }
 
@@ -745,7 +753,7 @@ impl TextCursorSet {
         let mut bottom_row_new = out_lines.len();
         while bottom_row_old > top_row
             && bottom_row_new > top_row
-            && text_buffer.lines[bottom_row_old - 1] == out_lines[bottom_row_new - 1]
+            && text_buffer.lines[bottom_row_old - 1].chars().eq(out_lines[bottom_row_new - 1].iter().copied())
         {
             bottom_row_old -= 1;
             bottom_row_new -= 1;
@@ -829,7 +837,7 @@ impl TextCursorSet {
                     }
                     ops.push(op);
                 }
-                off += text_buffer.lines[row].len() + 1;
+                off += text_buffer.lines.line_len(row) + 1;
             }
             cursor.head -= delta;
             cursor.tail -= delta;
@@ -909,14 +917,14 @@ impl TextCursorSet {
         self.fuse_adjacent(text_buffer)
     }
 
-    pub fn move_left(&mut self, char_count: usize, only_head: bool, text_buffer: &TextBuffer) {
+    pub fn move_left(&mut self, grapheme_count: usize, only_head: bool, text_buffer: &TextBuffer) {
         self.insert_undo_group += 1;
         let mut old_max = (TextPos { row: 0, col: 0 }, 0);
         for cursor in &mut self.set {
             if cursor.head != cursor.tail && !only_head {
                 cursor.head = cursor.head.min(cursor.tail)
             } else {
-                cursor.move_left(char_count, text_buffer);
+                cursor.move_left(grapheme_count, text_buffer);
             }
             if !only_head {
                 cursor.tail = cursor.head
@@ -926,14 +934,14 @@ impl TextCursorSet {
         self.fuse_adjacent(text_buffer)
     }
 
-    pub fn move_right(&mut self, char_count: usize, only_head: bool, text_buffer: &TextBuffer) {
+    pub fn move_right(&mut self, grapheme_count: usize, only_head: bool, text_buffer: &TextBuffer) {
         let mut old_max = (TextPos { row: 0, col: 0 }, 0);
         let total_char_count = text_buffer.calc_char_count();
         for cursor in &mut self.set {
             if cursor.head != cursor.tail && !only_head {
                 cursor.head = cursor.head.max(cursor.tail)
             } else {
-                cursor.move_right(char_count, total_char_count, text_buffer);
+                cursor.move_right(grapheme_count, total_char_count, text_buffer);
             }
             if !only_head {
                 cursor.tail = cursor.head
@@ -945,94 +953,93 @@ impl TextCursorSet {
 
     pub fn get_nearest_token_chunk_boundary(left: bool, offset: usize, text_buffer: &TextBuffer) -> usize {
         let token_chunks = &text_buffer.token_chunks;
-        for i in 0..token_chunks.len() {
-            // if we are in the chunk, decide what to do
-            if offset >= token_chunks[i].offset && offset < token_chunks[i].offset + token_chunks[i].len {
-                if left {
-                    // we want to to the beginning of the prev token
-                    if offset > token_chunks[i].offset {
-                        return token_chunks[i].offset;
-                    }
-                    if i == 0 {
-                        return 0;
-                    }
-                    if offset == token_chunks[i].offset {
-                        if token_chunks[i - 1].token_type == TokenType::Whitespace && i > 1 {
-                            return token_chunks[i - 2].offset; // + chunks[i-2].len
-                        }
-                        return token_chunks[i - 1].offset;
-                    }
-                    return token_chunks[i - 1].offset + token_chunks[i - 1].len;
-                } else {
-                    // jump right
-
-                    if i < token_chunks.len() - 1 && token_chunks[i].token_type == TokenType::Whitespace {
-                        return token_chunks[i + 1].offset + token_chunks[i + 1].len;
-                    }
-                    return token_chunks[i].offset + token_chunks[i].len;
-                }
+        // find the chunk we are in, and decide what to do
+        let i = match token_chunks.find_offset(offset) {
+            Some(i) => i,
+            None => return 0,
+        };
+        let token_chunk = token_chunks.get(i).unwrap();
+        if left {
+            // we want to to the beginning of the prev token
+            if offset > token_chunk.offset {
+                return token_chunk.offset;
+            }
+            if i == 0 {
+                return 0;
+            }
+            let prev = token_chunks.get(i - 1).unwrap();
+            if prev.token_type == TokenType::Whitespace && i > 1 {
+                return token_chunks.get(i - 2).unwrap().offset; // + chunks[i-2].len
+            }
+            prev.offset
+        } else {
+            // jump right
+            if i < token_chunks.len() - 1 && token_chunk.token_type == TokenType::Whitespace {
+                let next = token_chunks.get(i + 1).unwrap();
+                return next.offset + next.len;
             }
+            token_chunk.offset + token_chunk.len
         }
-        0
     }
 
     pub fn get_nearest_token_chunk(offset: usize, text_buffer: &TextBuffer) -> Option<(usize, usize)> {
         let token_chunks = &text_buffer.token_chunks;
-        for i in 0..token_chunks.len() {
-            if token_chunks[i].token_type == TokenType::Whitespace {
-                if offset == token_chunks[i].offset && i > 0 {
-                    // at the start of whitespace
-                    return Some((token_chunks[i - 1].offset, token_chunks[i - 1].len));
-                } else if offset == token_chunks[i].offset + token_chunks[i].len && i < token_chunks.len() - 1 {
-                    return Some((token_chunks[i + 1].offset, token_chunks[i + 1].len));
-                }
-            };
+        let i = token_chunks.find_offset(offset)?;
+        let token_chunk = token_chunks.get(i).unwrap();
+        if i > 0 && offset == token_chunk.offset {
+            let prev = token_chunks.get(i - 1).unwrap();
+            if prev.token_type == TokenType::Whitespace {
+                // at the end of whitespace
+                return Some((token_chunk.offset, token_chunk.len));
+            } else if token_chunk.token_type == TokenType::Whitespace {
+                // at the start of whitespace
+                return Some((prev.offset, prev.len));
+            }
+        }
 
-            if offset >= token_chunks[i].offset && offset < token_chunks[i].offset + token_chunks[i].len {
-                let i = if token_chunks[i].token_type == TokenType::Newline && i > 0 { i - 1 } else { i };
-                let pair_token = token_chunks[i].pair_token;
-                if pair_token > i {
-                    return Some((
-                        token_chunks[i].offset,
-                        token_chunks[pair_token].len + (token_chunks[pair_token].offset - token_chunks[i].offset),
-                    ));
+        let (i, token_chunk) = if token_chunk.token_type == TokenType::Newline && i > 0 {
+            (i - 1, token_chunks.get(i - 1).unwrap())
+        } else {
+            (i, token_chunk)
+        };
+        let pair_token = token_chunk.pair_token;
+        if pair_token > i {
+            let pair = token_chunks.get(pair_token).unwrap();
+            return Some((token_chunk.offset, pair.len + (pair.offset - token_chunk.offset)));
+        }
+        if token_chunk.token_type == TokenType::String || token_chunk.token_type == TokenType::CommentChunk {
+            if token_chunk.len <= 2 {
+                return Some((token_chunk.offset, token_chunk.len));
+            } else {
+                // scan for the nearest left and right space in the string
+                let mut scan_left = offset;
+                let boundary_tokens = "' :(){}[]+-|/<,.>;\"'!%^&*=";
+                while scan_left > 0 && scan_left > token_chunk.offset {
+                    if boundary_tokens.find(text_buffer.get_char(scan_left)).is_some() {
+                        scan_left += 1;
+                        break;
+                    }
+                    scan_left -= 1;
                 }
-                if token_chunks[i].token_type == TokenType::String || token_chunks[i].token_type == TokenType::CommentChunk {
-                    if token_chunks[i].len <= 2 {
-                        return Some((token_chunks[i].offset, token_chunks[i].len));
-                    } else {
-                        // scan for the nearest left and right space in the string
-                        let mut scan_left = offset;
-                        let boundary_tokens = "' :(){}[]+-|/<,.>;\"'!%^&*=";
-                        while scan_left > 0 && scan_left > token_chunks[i].offset {
-                            if boundary_tokens.find(text_buffer.flat_text[scan_left]).is_some() {
-                                scan_left += 1;
-                                break;
-                            }
-                            scan_left -= 1;
-                        }
-                        if boundary_tokens.find(text_buffer.flat_text[scan_left]).is_some() {
-                            scan_left += 1;
-                        }
-                        let mut scan_right = offset;
-                        while scan_right < token_chunks[i].offset + token_chunks[i].len {
-                            if boundary_tokens.find(text_buffer.flat_text[scan_right]).is_some() {
-                                //scan_left += 1;
-                                break;
-                            }
-                            scan_right += 1;
-                        }
-                        if scan_right <= scan_left {
-                            return Some((token_chunks[i].offset, token_chunks[i].len));
-                        } else {
-                            return Some((scan_left, scan_right - scan_left));
-                        }
+                if boundary_tokens.find(text_buffer.get_char(scan_left)).is_some() {
+                    scan_left += 1;
+                }
+                let mut scan_right = offset;
+                while scan_right < token_chunk.offset + token_chunk.len {
+                    if boundary_tokens.find(text_buffer.get_char(scan_right)).is_some() {
+                        //scan_left += 1;
+                        break;
                     }
+                    scan_right += 1;
+                }
+                if scan_right <= scan_left {
+                    return Some((token_chunk.offset, token_chunk.len));
+                } else {
+                    return Some((scan_left, scan_right - scan_left));
                 }
-                return Some((token_chunks[i].offset, token_chunks[i].len));
             }
         }
-        None
+        Some((token_chunk.offset, token_chunk.len))
     }
 
     pub fn move_left_nearest_token(&mut self, only_head: bool, text_buffer: &TextBuffer) {
@@ -1258,12 +1265,17 @@ impl DrawCursors {
             self.left_top.x = x;
             self.left_top.y = y;
             self.empty = true;
+            self.right_bottom.x = x;
         } else {
             self.empty = false;
+            // Characters aren't drawn left-to-right in lines with right-to-left text.
+            self.left_top.x = self.left_top.x.min(x);
         }
         // current right/bottom
-        self.last_w = w;
-        self.right_bottom.x = x;
+        if x >= self.right_bottom.x {
+            self.last_w = w;
+            self.right_bottom.x = x;
+        }
         if y + h > self.right_bottom.y {
             self.right_bottom.y = y + h;
         }
@@ -1344,3 +1356,30 @@ impl DrawCursors {
         mark_spaces
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_grapheme_movement_and_deletion() {
+        // "e" with a combining accent, a family emoji, and a newline.
+        let mut text_buffer = TextBuffer::from_utf8("ae\u{0301}\u{1F468}\u{200D}\u{1F469}\n!");
+        let mut cursors = TextCursorSet::default();
+
+        cursors.move_right(2, false, &text_buffer);
+        assert_eq!(cursors.set[0].head, 3);
+        cursors.move_right(1, false, &text_buffer);
+        assert_eq!(cursors.set[0].head, 6);
+        cursors.move_right(1, false, &text_buffer);
+        assert_eq!(cursors.set[0].head, 7);
+        cursors.move_left(2, false, &text_buffer);
+        assert_eq!(cursors.set[0].head, 3);
+
+        cursors.delete(&mut text_buffer);
+        assert_eq!(text_buffer.get_as_string(), "ae\u{0301}\n!");
+        cursors.backspace(&mut text_buffer, 0);
+        assert_eq!(text_buffer.get_as_string(), "a\n!");
+        assert_eq!(cursors.set[0].head, 1);
+    }
+}
@@ -338,6 +338,8 @@ pub struct TextEditor {
     pub _monospace_base: Vec2,
 
     pub _tokens_on_line: usize,
+    /// Set while drawing a line that contains right-to-left text.
+    pub _bidi_line: Option<BidiLine>,
     pub _line_was_folded: bool,
     pub _final_fill_height: f32,
     pub _draw_cursors: DrawCursors,
@@ -489,6 +491,7 @@ impl Default for TextEditor {
             _monospace_base: Vec2::default(),
             _last_pointer_move: None,
             _tokens_on_line: 0,
+            _bidi_line: None,
             _line_was_folded: false,
             _scroll_pos: Vec2::default(),
             _visible_lines: 0,
@@ -588,7 +591,7 @@ impl TextEditor {
             self.cursors.set_last_clamp_range(range);
             self._is_row_select = true;
         } else {
-            offset = if let Some(o) = TextIns::closest_offset(cx, &self.text_area, pe.abs, TEXT_STYLE_MONO.line_spacing) {
+            offset = if let Some(o) = self.closest_grapheme_offset(cx, pe.abs, text_buffer) {
                 o
             } else {
                 return;
@@ -662,7 +665,7 @@ impl TextEditor {
         } else if self._is_row_select {
             let offset = self.compute_offset_from_ypos(cx, pe.abs.y, text_buffer, true);
             self.cursors.set_last_cursor_head(offset, text_buffer)
-        } else if let Some(offset) = TextIns::closest_offset(cx, &self.text_area, pe.abs, TEXT_STYLE_MONO.line_spacing) {
+        } else if let Some(offset) = self.closest_grapheme_offset(cx, pe.abs, text_buffer) {
             self.cursors.set_last_cursor_head(offset, text_buffer)
         } else {
             false
@@ -682,7 +685,10 @@ impl TextEditor {
         }
     }
 
-    fn handle_pointer_up(&mut self, cx: &mut Cx, _pe: &PointerUpEvent, _text_buffer: &mut TextBuffer) {
+    fn handle_pointer_up(&mut self, cx: &mut Cx, _pe: &PointerUpEvent, text_buffer: &mut TextBuffer) {
+        if self.cursors.set.iter().any(|cursor| cursor.has_selection()) {
+            cx.set_primary_selection(&self.cursors.get_all_as_string(text_buffer));
+        }
         self.cursors.clear_last_clamp_range();
         self._select_scroll = None;
         self._last_pointer_move = None;
@@ -980,9 +986,7 @@ impl TextEditor {
                 if let Some(grid_select_corner) = self._grid_select_corner {
                     let pos = self.compute_grid_text_pos_from_abs(cx, last_pointer_move);
                     self.cursors.grid_select(grid_select_corner, pos, text_buffer);
-                } else if let Some(offset) =
-                    TextIns::closest_offset(cx, &self.text_area, last_pointer_move, TEXT_STYLE_MONO.line_spacing)
-                {
+                } else if let Some(offset) = self.closest_grapheme_offset(cx, last_pointer_move, text_buffer) {
                     self.cursors.set_last_cursor_head(offset, text_buffer);
                 }
             }
@@ -1150,6 +1154,7 @@ impl TextEditor {
         self._draw_messages = DrawCursors::default();
         self._draw_search = DrawCursors::default();
         self._tokens_on_line = 0;
+        self._bidi_line = None;
         self._visible_lines = 0;
         self._newline_tabs = 0;
         self._last_tabs = 0;
@@ -1368,20 +1373,61 @@ impl TextEditor {
         }
     }
 
-    pub fn draw_chunk(
-        &mut self,
-        cx: &mut Cx,
-        token_chunks_index: usize,
-        flat_text: &[char],
-        token_chunk: &TokenChunk,
-        markers: &TextBufferMarkers,
-    ) {
+    /// The color to draw a token with.
+    fn token_color(&self, token_type: TokenType) -> Vec4 {
+        match token_type {
+            TokenType::Whitespace | TokenType::Newline => self.colors.whitespace,
+            TokenType::BuiltinType => self.colors.keyword,
+            TokenType::Keyword => self.colors.keyword,
+            TokenType::Bool => self.colors.keyword,
+            TokenType::Error => self.colors.error,
+            TokenType::Warning => self.colors.warning,
+            TokenType::Defocus => self.colors.defocus,
+            TokenType::Flow => self.colors.flow,
+            TokenType::Looping => self.colors.looping,
+            TokenType::TypeDef => self.colors.keyword,
+            TokenType::Impl => self.colors.keyword,
+            TokenType::Fn => self.colors.keyword,
+            TokenType::Identifier => self.colors.identifier,
+            TokenType::Macro | TokenType::Call => self.colors.call,
+            TokenType::TypeName => self.colors.type_name,
+            TokenType::ThemeName => self.colors.theme_name,
+            TokenType::Color => self.colors.string,
+            TokenType::Regex => self.colors.string,
+            TokenType::String => self.colors.string,
+            TokenType::Number => self.colors.number,
+
+            TokenType::StringMultiBegin => self.colors.string,
+            TokenType::StringChunk => self.colors.string,
+            TokenType::StringMultiEnd => self.colors.string,
+
+            TokenType::CommentMultiBegin => self.colors.comment,
+            TokenType::CommentMultiEnd => self.colors.comment,
+            TokenType::CommentLine => self.colors.comment,
+            TokenType::CommentChunk => self.colors.comment,
+            TokenType::Operator => self.colors.operator,
+            TokenType::Namespace => self.colors.operator,
+            TokenType::Hash => self.colors.operator,
+            TokenType::Delimiter => self.colors.delimiter,
+            TokenType::Colon => self.colors.delimiter,
+            TokenType::Splat => self.colors.operator,
+            TokenType::Eof => self.colors.unexpected,
+            TokenType::Unexpected => self.colors.unexpected,
+            TokenType::ParenOpen | TokenType::ParenClose => match self._paren_stack.len() % 2 {
+                0 => self.colors.paren_d1,
+                _ => self.colors.paren_d2,
+            },
+        }
+    }
+
+    pub fn draw_chunk(&mut self, cx: &mut Cx, token: &TokenChunkText, markers: &TextBufferMarkers) {
+        let (token_chunks_index, token_chunk) = (token.index, &token.token_chunk);
         if token_chunk.len == 0 {
             return;
         }
 
         let token_type = token_chunk.token_type;
-        let chunk = &flat_text[token_chunk.offset..(token_chunk.offset + token_chunk.len)]; //chunk;
+        let chunk = &token.chars[..];
         let offset = token_chunk.offset; // end_offset - chunk.len() - 1;
         let next_char = token_chunk.next;
 
@@ -1459,6 +1505,15 @@ impl TextEditor {
                 _ => (),
             }
         }
+        if self._tokens_on_line == 0 {
+            let line: Vec<char> = token.line.chars().collect();
+            self._bidi_line = if TextIns::has_rtl(&line) {
+                let chars = line.into_iter().chain(Some('\n')).collect();
+                Some(BidiLine { pos: cx.get_draw_pos(), start: offset, chars, ..BidiLine::default() })
+            } else {
+                None
+            };
+        }
         // lets check if the geom is visible
         if let Some(geom) = self.move_cursor_right_no_wrap(
             cx,
@@ -1491,41 +1546,9 @@ impl TextEditor {
                     }
                     self.colors.whitespace
                 }
-                TokenType::BuiltinType => self.colors.keyword,
-                TokenType::Keyword => self.colors.keyword,
-                TokenType::Bool => self.colors.keyword,
-                TokenType::Error => self.colors.error,
-                TokenType::Warning => self.colors.warning,
-                TokenType::Defocus => self.colors.defocus,
-                TokenType::Flow => self.colors.flow,
-                TokenType::Looping => self.colors.looping,
-                TokenType::TypeDef => self.colors.keyword,
-                TokenType::Impl => self.colors.keyword,
-                TokenType::Fn => self.colors.keyword,
-                TokenType::Identifier => self.colors.identifier,
-                TokenType::Macro | TokenType::Call => self.colors.call,
-                TokenType::TypeName => self.colors.type_name,
-                TokenType::ThemeName => self.colors.theme_name,
-                TokenType::Color => self.colors.string,
-                TokenType::Regex => self.colors.string,
-                TokenType::String => self.colors.string,
-                TokenType::Number => self.colors.number,
-
-                TokenType::StringMultiBegin => self.colors.string,
-                TokenType::StringChunk => self.colors.string,
-                TokenType::StringMultiEnd => self.colors.string,
-
-                TokenType::CommentMultiBegin => self.colors.comment,
-                TokenType::CommentMultiEnd => self.colors.comment,
-                TokenType::CommentLine => self.colors.comment,
-                TokenType::CommentChunk => self.colors.comment,
                 TokenType::ParenOpen => {
-                    let depth = self._paren_stack.len();
                     self._paren_stack.last_mut().unwrap().geom_open = Some(geom);
-                    match depth % 2 {
-                        0 => self.colors.paren_d1,
-                        _ => self.colors.paren_d2,
-                    }
+                    self.token_color(token_type)
                 }
                 TokenType::ParenClose => {
                     if let Some(paren) = self._paren_stack.last_mut() {
@@ -1534,20 +1557,9 @@ impl TextEditor {
                         self.paren_pair.color = self.colors.paren_pair_fail;
                         self.paren_pair.draw_quad_abs(cx, geom);
                     }
-                    let depth = self._paren_stack.len();
-                    match depth % 2 {
-                        0 => self.colors.paren_d1,
-                        _ => self.colors.paren_d2,
-                    }
+                    self.token_color(token_type)
                 }
-                TokenType::Operator => self.colors.operator,
-                TokenType::Namespace => self.colors.operator,
-                TokenType::Hash => self.colors.operator,
-                TokenType::Delimiter => self.colors.delimiter,
-                TokenType::Colon => self.colors.delimiter,
-                TokenType::Splat => self.colors.operator,
-                TokenType::Eof => self.colors.unexpected,
-                TokenType::Unexpected => self.colors.unexpected,
+                _ => self.token_color(token_type),
             };
 
             if self._tokens_on_line == 0 {
@@ -1567,7 +1579,10 @@ impl TextEditor {
             let z = 2.0; // + self._paren_stack.len() as f32;
                          //self.text.z = z;
                          //let line_chunk = &mut self._line_chunk;
-            if !search_cursors.is_empty() {
+            if let Some(bidi_line) = &mut self._bidi_line {
+                bidi_line.tokens.push(BidiLineToken { end: offset + chunk.len(), color, mark_spaces });
+                bidi_line.visible = true;
+            } else if !search_cursors.is_empty() {
                 // slow loop
                 let char_callback = |ch, offset, x, w| {
                     //line_chunk.push((x, ch));
@@ -1606,6 +1621,12 @@ impl TextEditor {
                     char_callback,
                 ));
             };
+        } else {
+            // Reordering can move invisible tokens into view.
+            let color = self.token_color(token_type);
+            if let Some(bidi_line) = &mut self._bidi_line {
+                bidi_line.tokens.push(BidiLineToken { end: offset + chunk.len(), color, mark_spaces: 0.0 });
+            }
         }
         self._tokens_on_line += 1;
 
@@ -1613,10 +1634,76 @@ impl TextEditor {
         if token_chunk.token_type == TokenType::ParenClose {
             self.draw_paren_close(cx, token_chunks_index, offset, next_char, chunk);
         } else if token_type == TokenType::Newline {
+            self.draw_bidi_line(cx, markers);
             self.draw_new_line(cx);
         }
     }
 
+    /// Draw the current line if it's a [`BidiLine`], so that it gets reordered as a whole.
+    fn draw_bidi_line(&mut self, cx: &mut Cx, markers: &TextBufferMarkers) {
+        let bidi_line = match self._bidi_line.take() {
+            Some(bidi_line) if bidi_line.visible => bidi_line,
+            _ => return,
+        };
+        let mut chars = Vec::with_capacity(bidi_line.chars.len());
+        let mut glyphs = TextIns::generate_2d_glyphs(
+            &TEXT_STYLE_MONO,
+            &cx.fonts_data,
+            cx.current_dpi_factor,
+            self.current_font_scale,
+            0.,
+            COLOR_WHITE,
+            bidi_line.pos,
+            bidi_line.start,
+            &bidi_line.chars,
+            |ch, offset, x, w| {
+                chars.push((ch, offset, x, w));
+                0.0
+            },
+        );
+
+        // Cursors and selections are tracked in logical order.
+        chars.sort_by_key(|&(_, offset, _, _)| offset);
+        let token_at = |offset: usize| {
+            let index = bidi_line.tokens.partition_point(|token| token.end <= offset);
+            &bidi_line.tokens[index.min(bidi_line.tokens.len() - 1)]
+        };
+        let (y, height, z) = (bidi_line.pos.y, self._monospace_size.y, 2.0);
+        let search_cursors =
+            if !self.search_markers_bypass.is_empty() { &self.search_markers_bypass } else { &markers.search_cursors };
+        let mut glyph_markers = vec![0.0; bidi_line.chars.len()];
+        for (ch, offset, x, w) in chars {
+            if !search_cursors.is_empty() {
+                self._draw_search.mark_text_select_only(search_cursors, offset, x, y, w, height);
+            }
+            self._draw_messages.mark_text_select_only(&markers.message_cursors, offset, x, y, w, height);
+            glyph_markers[offset - bidi_line.start] = self._draw_cursors.mark_text_with_cursor(
+                &self.cursors.set,
+                ch,
+                offset,
+                x,
+                y,
+                w,
+                height,
+                z,
+                self.cursors.last_cursor,
+                token_at(offset).mark_spaces,
+            );
+        }
+        for glyph in &mut glyphs {
+            let offset = glyph.char_offset as usize;
+            glyph.color = token_at(offset).color;
+            glyph.marker = glyph_markers[offset - bidi_line.start];
+        }
+        self.text_glyphs.extend(glyphs);
+    }
+
+    /// Like [`TextIns::closest_offset`], but never in the middle of a grapheme cluster.
+    fn closest_grapheme_offset(&self, cx: &Cx, pos: Vec2, text_buffer: &TextBuffer) -> Option<usize> {
+        TextIns::closest_offset(cx, &self.text_area, pos, TEXT_STYLE_MONO.line_spacing)
+            .map(|offset| text_buffer.snap_to_grapheme_offset(offset))
+    }
+
     fn move_cursor_right_no_wrap(&self, cx: &mut Cx, w: f32, h: f32, scroll: Vec2) -> Option<Rect> {
         // Save position before updating it
         let pos = cx.get_draw_pos();
@@ -1717,6 +1804,7 @@ impl TextEditor {
     }
 
     pub fn end_text_editor(&mut self, cx: &mut Cx, text_buffer: &TextBuffer) {
+        self.draw_bidi_line(cx, &text_buffer.markers);
         if self.multiline {
             // lets insert an empty newline at the bottom so its nicer to scroll
             self.draw_new_line(cx);
@@ -1883,9 +1971,7 @@ impl TextEditor {
                 // self.cursors.grid_select(offset, text_buffer);
                 let pos = self.compute_grid_text_pos_from_abs(cx, select_scroll.abs);
                 self.cursors.grid_select(grid_select_corner, pos, text_buffer);
-            } else if let Some(offset) =
-                TextIns::closest_offset(cx, &self.text_area, select_scroll.abs, TEXT_STYLE_MONO.line_spacing)
-            {
+            } else if let Some(offset) = self.closest_grapheme_offset(cx, select_scroll.abs, text_buffer) {
                 self.cursors.set_last_cursor_head(offset, text_buffer);
             }
             if select_scroll.at_end {
@@ -2220,6 +2306,25 @@ pub struct SelectScroll {
     pub at_end: bool,
 }
 
+/// A line with right-to-left text, which has to be reordered as a whole instead of per token. So we collect its
+/// tokens, and draw it when the line ends.
+#[derive(Clone, Default)]
+pub struct BidiLine {
+    pos: Vec2,
+    start: usize,
+    /// Including the newline, if any.
+    chars: Vec<char>,
+    tokens: Vec<BidiLineToken>,
+    visible: bool,
+}
+
+#[derive(Clone)]
+struct BidiLineToken {
+    end: usize,
+    color: Vec4,
+    mark_spaces: f32,
+}
+
 #[derive(Clone)]
 pub struct ParenItem {
     pair_start: usize,
@@ -2288,3 +2393,45 @@ impl AnimFoldingState {
         }
     }
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use crate::*;
+    use zaplib::*;
+
+    struct TextEditorTestApp {
+        text_input: TextInput,
+    }
+
+    impl MainApp for TextEditorTestApp {
+        fn new(_cx: &mut Cx) -> Self {
+            Self { text_input: TextInput::new(TextInputOptions { empty_message: "Empty".to_string(), ..Default::default() }) }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            self.text_input.handle(cx, event);
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            self.text_input.draw(cx);
+        }
+    }
+
+    #[test]
+    fn test_text_editor_typing() {
+        let mut test = AppTest::<TextEditorTestApp>::new_in_window(vec2(160., 40.));
+        test.assert_golden_draw_tree(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/text_editor_empty.txt"));
+
+        test.click(vec2(20., 20.));
+        test.text_input("Hello");
+        test.key_press(KeyCode::ArrowLeft);
+        test.key_press(KeyCode::ArrowLeft);
+        test.text_input("!");
+        test.key_press(KeyCode::Backspace);
+        test.text_input("p");
+        test.advance_time(1.0);
+        assert_eq!(test.app.text_input.get_value(), "Helplo");
+        test.assert_golden_draw_tree(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/text_editor_typed.txt"));
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/text_editor_typed.png"));
+    }
+}
@@ -67,25 +67,8 @@ impl TextInput {
 
     pub fn draw(&mut self, cx: &mut Cx) {
         let text_buffer = &mut self.text_buffer;
-        if text_buffer.needs_token_chunks() && !text_buffer.lines.is_empty() {
-            let mut state = TokenizerState::new(&text_buffer.lines);
-            let mut tokenizer = TextInputTokenizer::default();
-            let mut pair_stack = Vec::new();
-            loop {
-                let offset = text_buffer.flat_text.len();
-                let token_type = tokenizer.next_token(&mut state, &mut text_buffer.flat_text, &text_buffer.token_chunks);
-                TokenChunk::push_with_pairing(
-                    &mut text_buffer.token_chunks,
-                    &mut pair_stack,
-                    state.next,
-                    offset,
-                    text_buffer.flat_text.len(),
-                    token_type,
-                );
-                if token_type == TokenType::Eof {
-                    break;
-                }
-            }
+        if !text_buffer.lines.is_empty() {
+            text_buffer.update_token_chunks(&TextInputTokenizer::default());
         }
         cx.begin_padding_box(Padding { t: 11., b: 7., r: 7., l: 7. }); // all (7.0) + top (4.0)
 
@@ -105,8 +88,8 @@ impl TextInput {
             cx.set_draw_pos(pos);
         }
 
-        for (index, token_chunk) in text_buffer.token_chunks.iter_mut().enumerate() {
-            self.text_editor.draw_chunk(cx, index, &text_buffer.flat_text, token_chunk, &text_buffer.markers);
+        for token in text_buffer.iter_token_chunks() {
+            self.text_editor.draw_chunk(cx, &token, &text_buffer.markers);
         }
 
         self.text_editor.end_text_editor(cx, text_buffer);
@@ -114,11 +97,11 @@ impl TextInput {
     }
 }
 
-#[derive(Default)]
+#[derive(Clone, Default, PartialEq)]
 pub struct TextInputTokenizer {}
 
-impl TextInputTokenizer {
-    pub fn next_token<'a>(
+impl Tokenizer for TextInputTokenizer {
+    fn next_token<'a>(
         &mut self,
         state: &mut TokenizerState<'a>,
         chunk: &mut Vec<char>,
@@ -0,0 +1,424 @@
+use std::ops::{Index, Range};
+
+use crate::textbuffer::TextPos;
+
+/// Number of lines that we put in a chunk when building a [`TextRope`] from scratch.
+const CHUNK_LINES: usize = 512;
+/// Chunks that grow beyond this many lines get split up.
+const MAX_CHUNK_LINES: usize = 2 * CHUNK_LINES;
+/// Chunks that shrink below this many lines get merged with their neighbour.
+const MIN_CHUNK_LINES: usize = CHUNK_LINES / 4;
+
+/// A few hundred lines, stored as UTF-8 with a newline after every line.
+#[derive(Clone)]
+struct LineChunk {
+    text: String,
+    /// Byte offset in `text` where each line starts, followed by `text.len()`.
+    byte_starts: Vec<usize>,
+    /// Char offset in `text` where each line starts, followed by the number of chars in `text`.
+    char_starts: Vec<usize>,
+}
+
+impl Default for LineChunk {
+    fn default() -> Self {
+        Self { text: String::new(), byte_starts: vec![0], char_starts: vec![0] }
+    }
+}
+
+impl LineChunk {
+    fn line_count(&self) -> usize {
+        self.byte_starts.len() - 1
+    }
+
+    /// Number of chars in this chunk including a newline after every line.
+    fn len_with_newlines(&self) -> usize {
+        self.char_starts[self.line_count()]
+    }
+
+    fn line(&self, row: usize) -> &str {
+        &self.text[self.byte_starts[row]..self.byte_starts[row + 1] - 1]
+    }
+
+    /// Number of chars in the line, not counting the newline.
+    fn line_len(&self, row: usize) -> usize {
+        self.char_starts[row + 1] - self.char_starts[row] - 1
+    }
+
+    fn push_line(&mut self, chars: impl Iterator<Item = char>) {
+        let mut char_count = self.len_with_newlines();
+        for ch in chars {
+            self.text.push(ch);
+            char_count += 1;
+        }
+        self.text.push('\n');
+        self.byte_starts.push(self.text.len());
+        self.char_starts.push(char_count + 1);
+    }
+
+    /// Copy the lines in `rows` into a new chunk.
+    fn slice(&self, rows: Range<usize>) -> Self {
+        let (byte_start, char_start) = (self.byte_starts[rows.start], self.char_starts[rows.start]);
+        Self {
+            text: self.text[byte_start..self.byte_starts[rows.end]].to_string(),
+            byte_starts: self.byte_starts[rows.start..=rows.end].iter().map(|start| start - byte_start).collect(),
+            char_starts: self.char_starts[rows.start..=rows.end].iter().map(|start| start - char_start).collect(),
+        }
+    }
+
+    fn append(&mut self, other: &LineChunk) {
+        let (byte_start, char_start) = (self.text.len(), self.len_with_newlines());
+        self.text.push_str(&other.text);
+        self.byte_starts.extend(other.byte_starts[1..].iter().map(|start| start + byte_start));
+        self.char_starts.extend(other.char_starts[1..].iter().map(|start| start + char_start));
+    }
+
+    /// Replace the lines in `rows` with `replace_with`, returning the removed lines.
+    fn splice(&mut self, rows: Range<usize>, replace_with: &[Vec<char>]) -> Vec<Vec<char>> {
+        let removed = rows.clone().map(|row| self.line(row).chars().collect()).collect();
+
+        let mut replacement = LineChunk::default();
+        for line in replace_with {
+            replacement.push_line(line.iter().copied());
+        }
+        let (byte_start, byte_end) = (self.byte_starts[rows.start], self.byte_starts[rows.end]);
+        let (char_start, char_end) = (self.char_starts[rows.start], self.char_starts[rows.end]);
+        self.text.replace_range(byte_start..byte_end, &replacement.text);
+
+        // Put in the starts of the new lines, and shift the starts of the lines after them.
+        let next_row = rows.start + replace_with.len();
+        self.byte_starts
+            .splice(rows.clone(), replacement.byte_starts[..replace_with.len()].iter().map(|start| start + byte_start));
+        self.char_starts.splice(rows, replacement.char_starts[..replace_with.len()].iter().map(|start| start + char_start));
+        for start in &mut self.byte_starts[next_row..] {
+            *start = *start + replacement.text.len() - (byte_end - byte_start);
+        }
+        for start in &mut self.char_starts[next_row..] {
+            *start = *start + replacement.len_with_newlines() - (char_end - char_start);
+        }
+        removed
+    }
+}
+
+/// The lines of a [`crate::TextBuffer`].
+///
+/// This is a two-level rope: lines are stored as UTF-8 in chunks of a few hundred lines, along with where each line
+/// starts. The rope keeps track of the first line and char offset of every chunk, so finding a line or converting
+/// between offsets and [`TextPos`] is a binary search over the chunks and then over the lines within a chunk.
+/// Inserting or removing lines only moves the text within a chunk. This keeps editing responsive for files with
+/// millions of lines, without storing 4 bytes for every char.
+///
+/// Lines are accessed as `&str`, e.g. `lines[row]`, `lines.len()`, and `lines.iter()`, but can only be changed through
+/// [`TextRope::splice`] and [`TextRope::modify_line`], which keep the chunks up to date.
+#[derive(Clone)]
+pub struct TextRope {
+    chunks: Vec<LineChunk>,
+    /// The first row of each chunk, followed by the number of lines.
+    chunk_rows: Vec<usize>,
+    /// The char offset where each chunk starts, followed by the number of chars including a newline after every line.
+    chunk_offsets: Vec<usize>,
+}
+
+impl Default for TextRope {
+    fn default() -> Self {
+        Self { chunks: vec![], chunk_rows: vec![0], chunk_offsets: vec![0] }
+    }
+}
+
+impl TextRope {
+    /// Build a rope from lines without newlines, e.g. `TextRope::from_lines(text.split('\n'))`.
+    pub fn from_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut rope = Self::default();
+        let mut chunk = LineChunk::default();
+        for line in lines {
+            if chunk.line_count() == CHUNK_LINES {
+                rope.chunks.push(std::mem::take(&mut chunk));
+            }
+            chunk.push_line(line.chars());
+        }
+        if chunk.line_count() > 0 {
+            rope.chunks.push(chunk);
+        }
+        rope.update_chunk_starts(0);
+        rope
+    }
+
+    /// Number of lines.
+    pub fn len(&self) -> usize {
+        self.chunk_rows[self.chunks.len()]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of chars in all lines, including the newlines between them.
+    pub fn char_count(&self) -> usize {
+        self.chunk_offsets[self.chunks.len()].max(1) - 1
+    }
+
+    pub fn get(&self, row: usize) -> Option<&str> {
+        if row >= self.len() {
+            return None;
+        }
+        let (chunk_index, chunk_row) = self.locate_row(row);
+        Some(self.chunks[chunk_index].line(chunk_row))
+    }
+
+    /// Number of chars in the line at `row`, which has to be smaller than `self.len()`.
+    pub fn line_len(&self, row: usize) -> usize {
+        assert!(row < self.len(), "Row out of bounds");
+        let (chunk_index, chunk_row) = self.locate_row(row);
+        self.chunks[chunk_index].line_len(chunk_row)
+    }
+
+    /// The chars of the line at `row`, which has to be smaller than `self.len()`.
+    pub fn line_chars(&self, row: usize) -> Vec<char> {
+        self[row].chars().collect()
+    }
+
+    pub fn iter(&self) -> TextRopeIter<'_> {
+        self.iter_from(0)
+    }
+
+    /// Iterate over the lines starting at `row`, without walking all the lines before it.
+    pub fn iter_from(&self, row: usize) -> TextRopeIter<'_> {
+        if row >= self.len() {
+            return TextRopeIter { chunks: [].iter(), chunk: None, row: 0 };
+        }
+        let (chunk_index, chunk_row) = self.locate_row(row);
+        TextRopeIter { chunks: self.chunks[chunk_index + 1..].iter(), chunk: Some(&self.chunks[chunk_index]), row: chunk_row }
+    }
+
+    /// Returns the chunk index and row within that chunk, for a `row` that is smaller than `self.len()`.
+    fn locate_row(&self, row: usize) -> (usize, usize) {
+        let chunk_index = self.chunk_rows.partition_point(|&chunk_row| chunk_row <= row) - 1;
+        (chunk_index, row - self.chunk_rows[chunk_index])
+    }
+
+    /// Convert a char offset into a [`TextPos`]. Offsets beyond the end of the text return the start of the last line.
+    pub fn offset_to_text_pos(&self, offset: usize) -> TextPos {
+        if offset >= self.chunk_offsets[self.chunks.len()] {
+            return TextPos { row: self.len().max(1) - 1, col: 0 };
+        }
+        let chunk_index = self.chunk_offsets.partition_point(|&chunk_offset| chunk_offset <= offset) - 1;
+        let chunk = &self.chunks[chunk_index];
+        let chunk_offset = offset - self.chunk_offsets[chunk_index];
+        let chunk_row = chunk.char_starts.partition_point(|&char_start| char_start <= chunk_offset) - 1;
+        TextPos { row: self.chunk_rows[chunk_index] + chunk_row, col: chunk_offset - chunk.char_starts[chunk_row] }
+    }
+
+    /// Convert a [`TextPos`] into a char offset, clamping the column to the length of the line. Rows beyond the end
+    /// of the text return the total char count.
+    pub fn text_pos_to_offset(&self, pos: TextPos) -> usize {
+        if pos.row >= self.len() {
+            return self.char_count();
+        }
+        let (chunk_index, chunk_row) = self.locate_row(pos.row);
+        let chunk = &self.chunks[chunk_index];
+        self.chunk_offsets[chunk_index] + chunk.char_starts[chunk_row] + chunk.line_len(chunk_row).min(pos.col)
+    }
+
+    /// Change a single line in place.
+    pub fn modify_line<R>(&mut self, row: usize, f: impl FnOnce(&mut Vec<char>) -> R) -> R {
+        let (chunk_index, chunk_row) = self.locate_row(row);
+        let chunk = &mut self.chunks[chunk_index];
+        let mut line: Vec<char> = chunk.line(chunk_row).chars().collect();
+        let ret = f(&mut line);
+        chunk.splice(chunk_row..chunk_row + 1, &[line]);
+        self.update_chunk_starts(chunk_index);
+        ret
+    }
+
+    /// Replace the lines in `rows` with `replace_with`, returning the removed lines, like [`Vec::splice`].
+    pub fn splice(&mut self, rows: Range<usize>, replace_with: Vec<Vec<char>>) -> Vec<Vec<char>> {
+        assert!(rows.start <= rows.end && rows.end <= self.len(), "Rows out of bounds");
+
+        if self.chunks.is_empty() {
+            self.chunks.push(LineChunk::default());
+            self.update_chunk_starts(0);
+        }
+        let (chunk_index, chunk_row) = if rows.start == self.len() {
+            (self.chunks.len() - 1, self.chunks.last().unwrap().line_count())
+        } else {
+            self.locate_row(rows.start)
+        };
+
+        // Remove lines, possibly spanning multiple chunks.
+        let mut removed = Vec::with_capacity(rows.len());
+        let mut remaining = rows.len();
+        let mut remove_chunk_index = chunk_index;
+        let mut remove_row = chunk_row;
+        while remaining > 0 {
+            let chunk = &mut self.chunks[remove_chunk_index];
+            let count = remaining.min(chunk.line_count() - remove_row);
+            removed.extend(chunk.splice(remove_row..remove_row + count, &[]));
+            remaining -= count;
+            remove_chunk_index += 1;
+            remove_row = 0;
+        }
+
+        // Insert the new lines into the first chunk.
+        self.chunks[chunk_index].splice(chunk_row..chunk_row, &replace_with);
+
+        self.rebalance(chunk_index, remove_chunk_index.max(chunk_index + 1));
+        self.update_chunk_starts(chunk_index.saturating_sub(1));
+        removed
+    }
+
+    /// Restore the chunk size invariants for the chunks in `start..end`.
+    fn rebalance(&mut self, start: usize, end: usize) {
+        let mut index = start;
+        let mut end = end.min(self.chunks.len());
+        while index < end {
+            let chunk_lines = self.chunks[index].line_count();
+            if chunk_lines > MAX_CHUNK_LINES {
+                // Split into chunks of `CHUNK_LINES`, adding any small remainder to the last one.
+                let mut piece_starts: Vec<usize> = (0..chunk_lines).step_by(CHUNK_LINES).collect();
+                if chunk_lines - piece_starts.last().unwrap() < MIN_CHUNK_LINES {
+                    piece_starts.pop();
+                }
+                piece_starts.push(chunk_lines);
+                let chunk = &self.chunks[index];
+                let pieces: Vec<LineChunk> = piece_starts.windows(2).map(|rows| chunk.slice(rows[0]..rows[1])).collect();
+                let piece_count = pieces.len();
+                self.chunks.splice(index..index + 1, pieces);
+                end += piece_count - 1;
+                index += piece_count;
+            } else if chunk_lines < MIN_CHUNK_LINES && self.chunks.len() > 1 {
+                // Merge into the next chunk, or the previous one if this is the last chunk, and check that chunk again.
+                let mut chunk = self.chunks.remove(index);
+                if index < self.chunks.len() {
+                    chunk.append(&self.chunks[index]);
+                    self.chunks[index] = chunk;
+                    end = end.max(index + 2) - 1;
+                } else {
+                    index -= 1;
+                    self.chunks[index].append(&chunk);
+                    end = index + 1;
+                }
+            } else {
+                index += 1;
+            }
+        }
+        if self.chunks.iter().all(|chunk| chunk.line_count() == 0) {
+            self.chunks.clear();
+        }
+    }
+
+    /// Recompute `chunk_rows` and `chunk_offsets` for the chunks after `chunk_index`.
+    fn update_chunk_starts(&mut self, chunk_index: usize) {
+        let chunk_index = chunk_index.min(self.chunks.len());
+        self.chunk_rows.truncate(chunk_index + 1);
+        self.chunk_offsets.truncate(chunk_index + 1);
+        for chunk in &self.chunks[chunk_index..] {
+            self.chunk_rows.push(self.chunk_rows.last().unwrap() + chunk.line_count());
+            self.chunk_offsets.push(self.chunk_offsets.last().unwrap() + chunk.len_with_newlines());
+        }
+    }
+}
+
+/// Iterator over the lines of a [`TextRope`].
+pub struct TextRopeIter<'a> {
+    chunks: std::slice::Iter<'a, LineChunk>,
+    chunk: Option<&'a LineChunk>,
+    /// The next row within `chunk`.
+    row: usize,
+}
+
+impl<'a> Iterator for TextRopeIter<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        loop {
+            if let Some(chunk) = self.chunk.filter(|chunk| self.row < chunk.line_count()) {
+                self.row += 1;
+                return Some(chunk.line(self.row - 1));
+            }
+            self.chunk = Some(self.chunks.next()?);
+            self.row = 0;
+        }
+    }
+}
+
+impl Index<usize> for TextRope {
+    type Output = str;
+
+    fn index(&self, row: usize) -> &str {
+        self.get(row).expect("Row out of bounds")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(row: usize) -> Vec<char> {
+        format!("line {} é", row).chars().collect()
+    }
+
+    fn assert_lines_eq(rope: &TextRope, lines: &[Vec<char>]) {
+        assert_eq!(rope.len(), lines.len());
+        assert!(rope.iter().map(|line| line.chars().collect::<Vec<_>>()).eq(lines.iter().cloned()));
+        let char_count = lines.iter().map(|line| line.len() + 1).sum::<usize>().max(1) - 1;
+        assert_eq!(rope.char_count(), char_count);
+        assert_eq!(rope.chunk_rows.len(), rope.chunks.len() + 1);
+        for (chunk_index, chunk) in rope.chunks.iter().enumerate() {
+            assert_eq!(chunk.byte_starts[chunk.line_count()], chunk.text.len());
+            assert_eq!(chunk.len_with_newlines(), chunk.text.chars().count());
+            assert_eq!(rope.chunk_rows[chunk_index + 1] - rope.chunk_rows[chunk_index], chunk.line_count());
+            assert!(chunk.line_count() <= MAX_CHUNK_LINES);
+        }
+    }
+
+    #[test]
+    fn test_splice() {
+        let mut lines: Vec<Vec<char>> = (0..5000).map(line).collect();
+        let strings: Vec<String> = lines.iter().map(|line| line.iter().collect()).collect();
+        let mut rope = TextRope::from_lines(strings.iter().map(String::as_str));
+        assert_lines_eq(&rope, &lines);
+
+        // Insert a lot of lines, remove across chunks, and replace a few lines.
+        let inserted: Vec<Vec<char>> = (0..3000).map(|row| line(row * 7)).collect();
+        assert_eq!(rope.splice(100..100, inserted.clone()), lines.splice(100..100, inserted).collect::<Vec<_>>());
+        assert_lines_eq(&rope, &lines);
+        assert_eq!(rope.splice(300..4000, vec![]), lines.splice(300..4000, vec![]).collect::<Vec<_>>());
+        assert_lines_eq(&rope, &lines);
+        let replacement = vec![vec!['a'], vec![], vec!['ü', 'c']];
+        assert_eq!(rope.splice(10..20, replacement.clone()), lines.splice(10..20, replacement).collect::<Vec<_>>());
+        assert_lines_eq(&rope, &lines);
+
+        // Append at the end, and remove everything.
+        let end = lines.len();
+        rope.splice(end..end, vec![vec!['z']]);
+        lines.push(vec!['z']);
+        assert_lines_eq(&rope, &lines);
+        rope.splice(0..lines.len(), vec![]);
+        assert_lines_eq(&rope, &[]);
+        rope.splice(0..0, vec![vec!['x']]);
+        assert_lines_eq(&rope, &[vec!['x']]);
+    }
+
+    #[test]
+    fn test_offsets() {
+        let lines: Vec<Vec<char>> = (0..2000).map(line).collect();
+        let strings: Vec<String> = lines.iter().map(|line| line.iter().collect()).collect();
+        let mut rope = TextRope::from_lines(strings.iter().map(String::as_str));
+        let mut offset = 0;
+        for (row, line) in lines.iter().enumerate() {
+            for col in 0..=line.len() {
+                assert_eq!(rope.offset_to_text_pos(offset + col), TextPos { row, col });
+                assert_eq!(rope.text_pos_to_offset(TextPos { row, col }), offset + col);
+            }
+            offset += line.len() + 1;
+        }
+        assert_eq!(rope.offset_to_text_pos(offset + 10), TextPos { row: 1999, col: 0 });
+        assert_eq!(rope.text_pos_to_offset(TextPos { row: 5000, col: 0 }), rope.char_count());
+
+        rope.modify_line(1500, |line| line.extend(['!', '!']));
+        assert_eq!(&rope[1500], "line 1500 é!!");
+        assert_eq!(rope.line_len(1500), 13);
+        assert_eq!(rope.char_count(), offset - 1 + 2);
+        assert_eq!(rope.offset_to_text_pos(offset + 1), TextPos { row: 1999, col: 11 });
+        assert_eq!(rope.iter_from(1999).count(), 1);
+    }
+}
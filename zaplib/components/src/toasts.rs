@@ -0,0 +1,156 @@
+//! Short notifications that go away by themselves.
+
+use crate::*;
+use zaplib::*;
+
+const TOAST_HEIGHT: f32 = 32.;
+const TOAST_PADDING_X: f32 = 12.;
+const TOAST_SPACING: f32 = 8.;
+/// Distance from the bottom right corner of the window.
+const MARGIN: f32 = 16.;
+
+const COLOR_TOAST: Vec4 = vec4(0.25, 0.25, 0.25, 1.);
+const COLOR_TEXT: Vec4 = vec4(0.9, 0.9, 0.9, 1.);
+
+struct Toast {
+    component_id: ComponentId,
+    message: String,
+    timer: Timer,
+    background: Background,
+}
+
+/// A stack of notifications in the bottom right corner of the window, on top of everything else (see
+/// [`View::with_is_overlay`]). Every toast goes away after [`Toasts::duration`] seconds, or when it's clicked.
+///
+/// Typically you have one of these for the whole application, which you handle before and draw after everything
+/// else.
+pub struct Toasts {
+    view: View,
+    /// Oldest first; the newest one is drawn at the bottom.
+    toasts: Vec<Toast>,
+    /// How long every toast is shown, in seconds.
+    pub duration: f64,
+}
+
+impl Default for Toasts {
+    fn default() -> Self {
+        Self { view: View::default().with_is_overlay(true), toasts: vec![], duration: 4. }
+    }
+}
+
+impl Toasts {
+    #[must_use]
+    pub fn with_duration(self, duration: f64) -> Self {
+        Self { duration, ..self }
+    }
+
+    /// Show a new toast with `message`.
+    pub fn show(&mut self, cx: &mut Cx, message: &str) {
+        let timer = cx.start_timer(self.duration, false);
+        self.toasts.push(Toast {
+            component_id: Default::default(),
+            message: message.to_string(),
+            timer,
+            background: Background::default().with_radius(4.),
+        });
+        cx.request_draw();
+    }
+
+    /// The messages of the toasts that are currently shown, oldest first.
+    pub fn messages(&self) -> Vec<&str> {
+        self.toasts.iter().map(|toast| toast.message.as_str()).collect()
+    }
+
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if let Event::Timer(te) = event {
+            if let Some(index) = self.toasts.iter_mut().position(|toast| toast.timer.is_timer(te)) {
+                self.toasts.remove(index);
+                cx.request_draw();
+            }
+            return;
+        }
+
+        for index in 0..self.toasts.len() {
+            let toast = &mut self.toasts[index];
+            let rect = toast.background.area().get_rect_for_first_instance(cx);
+            if let Event::PointerDown(_) = event.hits_pointer(cx, toast.component_id, rect) {
+                cx.keep_key_focus();
+                cx.stop_timer(&mut toast.timer);
+                self.toasts.remove(index);
+                cx.request_draw();
+                return;
+            }
+        }
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx) {
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        cx.begin_absolute_box();
+        self.view.begin_view(cx, LayoutSize::FILL);
+        let props = TextInsProps { color: COLOR_TEXT, position_anchoring: TEXT_ANCHOR_CENTER_V, ..TextInsProps::DEFAULT };
+        let bottom_right = cx.get_draw_pos() + vec2(cx.get_width_left(), cx.get_height_left()) - vec2(MARGIN, MARGIN);
+        let mut y = bottom_right.y;
+        for toast in self.toasts.iter_mut().rev() {
+            let width = TextIns::measure_str_width(cx, &toast.message, &props) + 2. * TOAST_PADDING_X;
+            y -= TOAST_HEIGHT;
+            let rect = Rect { pos: vec2(bottom_right.x - width, y), size: vec2(width, TOAST_HEIGHT) };
+            toast.background.draw(cx, rect, COLOR_TOAST);
+            TextIns::draw_str(cx, &toast.message, rect.pos + vec2(TOAST_PADDING_X, TOAST_HEIGHT / 2.), &props);
+            y -= TOAST_SPACING;
+        }
+        self.view.end_view(cx);
+        cx.end_absolute_box();
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use zaplib::*;
+
+    use crate::*;
+
+    struct ToastsTestApp {
+        toasts: Toasts,
+    }
+
+    impl MainApp for ToastsTestApp {
+        fn new(_cx: &mut Cx) -> Self {
+            Self { toasts: Toasts::default() }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            self.toasts.handle(cx, event);
+            if let Event::KeyDown(ke) = event {
+                match ke.key_code {
+                    KeyCode::KeyS => self.toasts.show(cx, "Saved"),
+                    KeyCode::KeyC => self.toasts.show(cx, "Copied to clipboard"),
+                    _ => (),
+                }
+            }
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            self.toasts.draw(cx);
+        }
+    }
+
+    #[test]
+    fn test_toasts() {
+        let mut test = AppTest::<ToastsTestApp>::new_in_window(vec2(300., 200.));
+
+        test.key_press(KeyCode::KeyS);
+        test.advance_time(1.);
+        test.key_press(KeyCode::KeyC);
+        assert_eq!(test.app.toasts.messages(), vec!["Saved", "Copied to clipboard"]);
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/toasts.png"));
+
+        // Toasts go away by themselves, or when clicked.
+        test.advance_time(3.5);
+        assert_eq!(test.app.toasts.messages(), vec!["Copied to clipboard"]);
+        test.click(vec2(200., 170.));
+        assert!(test.app.toasts.messages().is_empty());
+    }
+}
@@ -0,0 +1,489 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::textrope::*;
+use crate::tokentype::*;
+
+/// Start a new segment once the current one spans this many lines.
+const SEGMENT_LINES: usize = 64;
+/// When retokenizing after an edit, don't go back to the old segments while the last new one is shorter than this,
+/// so that segments don't keep getting smaller.
+const MIN_SEGMENT_LINES: usize = SEGMENT_LINES / 4;
+
+/// A range of text that was replaced, in char offsets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TextEdit {
+    start: usize,
+    /// End of the replaced range in the old text.
+    old_end: usize,
+    /// End of the replacement in the new text.
+    new_end: usize,
+}
+
+impl TextEdit {
+    /// Combine with an `edit` that happened after this one, returning a single edit that covers both.
+    fn merge(self, edit: TextEdit) -> TextEdit {
+        let start = self.start.min(edit.start);
+        let (old_end, new_end) = if edit.old_end > self.new_end {
+            (self.old_end + edit.old_end - self.new_end, edit.new_end)
+        } else {
+            (self.old_end, self.new_end + edit.new_end - edit.old_end)
+        };
+        TextEdit { start, old_end, new_end }
+    }
+}
+
+/// Where [`TokenChunks::update`] can restart tokenizing, with everything before it unchanged.
+#[derive(Clone)]
+struct TokenCheckpoint {
+    /// Position of the tokenizer, relative to the start of its segment.
+    position: TokenizerPosition,
+    /// The tokens before the segment that the tokenizer gets to see; see [`TokenChunk::lookback`].
+    lookback: Vec<TokenChunk>,
+    tokenizer: Arc<dyn Any + Send + Sync>,
+}
+
+#[derive(Clone, Default)]
+struct TokenSegment {
+    /// Only set for segments made by [`TokenChunks::update`].
+    checkpoint: Option<TokenCheckpoint>,
+    /// The tokens, with [`TokenChunk::offset`] relative to the start of the segment, and [`TokenChunk::pair_token`]
+    /// an index into `tokens`. Parentheses that aren't matched within the segment point to themselves.
+    tokens: Vec<TokenChunk>,
+    char_count: usize,
+    line_count: usize,
+    /// Indices of [`TokenType::ParenOpen`] tokens that aren't closed within the segment.
+    open_parens: Vec<usize>,
+    /// Indices of [`TokenType::ParenClose`] tokens that aren't opened within the segment. These always come before
+    /// `open_parens`.
+    close_parens: Vec<usize>,
+}
+
+impl TokenSegment {
+    fn push(&mut self, token_type: TokenType, len: usize, next: char) {
+        let index = self.tokens.len();
+        let mut pair_token = index;
+        if token_type == TokenType::ParenOpen {
+            self.open_parens.push(index);
+        } else if token_type == TokenType::ParenClose {
+            if let Some(other) = self.open_parens.pop() {
+                self.tokens[other].pair_token = index;
+                pair_token = other;
+            } else {
+                self.close_parens.push(index);
+            }
+        }
+        self.tokens.push(TokenChunk { token_type, offset: self.char_count, pair_token, len, next });
+        self.char_count += len;
+    }
+}
+
+/// Where a segment starts.
+#[derive(Clone, Copy, Default)]
+struct SegmentStart {
+    index: usize,
+    token_index: usize,
+    offset: usize,
+    row: usize,
+}
+
+impl SegmentStart {
+    fn next(self, segment: &TokenSegment) -> Self {
+        Self {
+            index: self.index + 1,
+            token_index: self.token_index + segment.tokens.len(),
+            offset: self.offset + segment.char_count,
+            row: self.row + segment.line_count,
+        }
+    }
+}
+
+/// The tokens of a [`crate::TextBuffer`].
+///
+/// Like [`TextRope`], this stores tokens in segments of a few dozen lines, with offsets relative to the start of their
+/// segment. Every segment remembers the tokenizer state at its start, so after an edit only the segments around the
+/// edit get tokenized again, and the ones after it are kept as they are. Parentheses are paired within a segment
+/// while tokenizing it, and ones that are left over are paired across segments when accessing them.
+///
+/// Tokens are accessed by index like a `Vec<TokenChunk>`, e.g. `token_chunks.get(index)`, `token_chunks.len()`, and
+/// `token_chunks.iter()`, but get returned by value since their offsets and pair tokens are computed on the fly.
+#[derive(Clone, Default)]
+pub struct TokenChunks {
+    segments: Vec<TokenSegment>,
+    len: usize,
+    /// The range of text that changed since the last [`TokenChunks::update`].
+    edit: Option<TextEdit>,
+}
+
+impl TokenChunks {
+    /// Number of tokens.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.len = 0;
+        self.edit = None;
+    }
+
+    pub fn get(&self, index: usize) -> Option<TokenChunk> {
+        let mut start = SegmentStart::default();
+        for segment in &self.segments {
+            if index < start.token_index + segment.tokens.len() {
+                return Some(self.token_chunk(start, index - start.token_index));
+            }
+            start = start.next(segment);
+        }
+        None
+    }
+
+    pub fn last(&self) -> Option<TokenChunk> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    pub fn iter(&self) -> TokenChunksIter<'_> {
+        self.iter_from(0)
+    }
+
+    /// Iterate over the tokens starting at `index`, without walking all the tokens before it.
+    pub fn iter_from(&self, index: usize) -> TokenChunksIter<'_> {
+        let mut start = SegmentStart::default();
+        for segment in &self.segments {
+            if index < start.token_index + segment.tokens.len() {
+                break;
+            }
+            start = start.next(segment);
+        }
+        TokenChunksIter { token_chunks: self, start, token_index: index.saturating_sub(start.token_index) }
+    }
+
+    /// Index of the token that contains the char at `offset`, if any.
+    pub fn find_offset(&self, offset: usize) -> Option<usize> {
+        let mut start = SegmentStart::default();
+        for segment in &self.segments {
+            if offset < start.offset + segment.char_count {
+                let segment_offset = offset - start.offset;
+                let index = segment.tokens.partition_point(|token| token.offset + token.len <= segment_offset);
+                return Some(start.token_index + index);
+            }
+            start = start.next(segment);
+        }
+        None
+    }
+
+    /// Append a token, pairing it up if it's a parenthesis. Returns whether it's a [`TokenType::ParenClose`] that
+    /// doesn't match anything.
+    ///
+    /// This is for tokenizing the whole text yourself, instead of using [`crate::TextBuffer::update_token_chunks`].
+    /// Tokens added this way all go into one segment, so see [`TokenChunks::lookback`] for what to pass to
+    /// [`Tokenizer::next_token`].
+    pub fn push(&mut self, token_type: TokenType, len: usize, next: char) -> bool {
+        if self.segments.is_empty() {
+            self.segments.push(TokenSegment::default());
+        }
+        let segment = self.segments.last_mut().unwrap();
+        let close_parens = segment.close_parens.len();
+        segment.push(token_type, len, next);
+        if token_type == TokenType::Newline {
+            segment.line_count += 1;
+        }
+        self.len += 1;
+        segment.close_parens.len() > close_parens
+    }
+
+    /// The tokens before the next one that gets [`TokenChunks::push`]ed, for [`Tokenizer::next_token`].
+    pub fn lookback(&self) -> &[TokenChunk] {
+        self.segments.last().map_or(&[], |segment| &segment.tokens)
+    }
+
+    /// Returns whether there are any unmatched parentheses.
+    pub fn has_invalid_pair(&self) -> bool {
+        let mut depth = 0;
+        for segment in &self.segments {
+            if segment.close_parens.len() > depth {
+                return true;
+            }
+            depth = depth - segment.close_parens.len() + segment.open_parens.len();
+        }
+        depth > 0
+    }
+
+    /// The token at `token_index` of the segment at `start`, with absolute offset and pair token.
+    fn token_chunk(&self, start: SegmentStart, token_index: usize) -> TokenChunk {
+        let segment = &self.segments[start.index];
+        let token_chunk = &segment.tokens[token_index];
+        let pair_token = if token_chunk.pair_token != token_index {
+            start.token_index + token_chunk.pair_token
+        } else if let Ok(index) = segment.open_parens.binary_search(&token_index) {
+            self.find_close_paren(start, segment.open_parens.len() - index)
+        } else if let Ok(index) = segment.close_parens.binary_search(&token_index) {
+            self.find_open_paren(start, index + 1)
+        } else {
+            start.token_index + token_index
+        };
+        TokenChunk { offset: start.offset + token_chunk.offset, pair_token, ..token_chunk.clone() }
+    }
+
+    /// Index of the `depth`th unmatched [`TokenType::ParenClose`] after the segment at `start`, or of the token at
+    /// the end of the `depth`th unmatched [`TokenType::ParenOpen`] of that segment if it isn't closed.
+    fn find_close_paren(&self, start: SegmentStart, mut depth: usize) -> usize {
+        let own =
+            start.token_index + self.segments[start.index].open_parens[self.segments[start.index].open_parens.len() - depth];
+        let mut start = start.next(&self.segments[start.index]);
+        for segment in &self.segments[start.index..] {
+            if let Some(index) = segment.close_parens.get(depth - 1) {
+                return start.token_index + index;
+            }
+            depth = depth - segment.close_parens.len() + segment.open_parens.len();
+            start = start.next(segment);
+        }
+        own
+    }
+
+    /// Index of the unmatched [`TokenType::ParenOpen`] before the segment at `start` that matches its `depth`th
+    /// unmatched [`TokenType::ParenClose`], or of that close paren itself if it isn't opened.
+    fn find_open_paren(&self, start: SegmentStart, mut depth: usize) -> usize {
+        let own = start.token_index + self.segments[start.index].close_parens[depth - 1];
+        let mut token_index = start.token_index;
+        for segment in self.segments[..start.index].iter().rev() {
+            token_index -= segment.tokens.len();
+            if segment.open_parens.len() >= depth {
+                return token_index + segment.open_parens[segment.open_parens.len() - depth];
+            }
+            depth = depth - segment.open_parens.len() + segment.close_parens.len();
+        }
+        own
+    }
+
+    /// Record that `len` chars at `start` got replaced by `new_len` chars, for [`TokenChunks::update`].
+    pub(crate) fn record_edit(&mut self, start: usize, len: usize, new_len: usize) {
+        let edit = TextEdit { start, old_end: start + len, new_end: start + new_len };
+        self.edit = Some(match self.edit {
+            Some(token_edit) => token_edit.merge(edit),
+            None => edit,
+        });
+    }
+
+    /// Make the next [`TokenChunks::update`] tokenize everything, for when the text changed in ways that weren't
+    /// recorded with [`TokenChunks::record_edit`].
+    pub(crate) fn reset_checkpoints(&mut self) {
+        for segment in &mut self.segments {
+            segment.checkpoint = None;
+        }
+        self.edit = None;
+    }
+
+    /// Bring the tokens up to date with `lines` after the recorded edits, using `tokenizer` in its initial state.
+    pub(crate) fn update<T: Tokenizer>(&mut self, lines: &TextRope, tokenizer: &T) {
+        let restart = self.edit.take().and_then(|edit| {
+            // The token before a segment ended based on the char at its start, so that one has to be unchanged.
+            let mut restart = None;
+            let mut start = SegmentStart::default();
+            for segment in &self.segments {
+                if start.index > 0 && start.offset >= edit.start {
+                    break;
+                }
+                restart = Some(start);
+                start = start.next(segment);
+            }
+            let restart = restart?;
+            self.segments[restart.index].checkpoint.as_ref()?.tokenizer.downcast_ref::<T>()?;
+            Some((edit, restart))
+        });
+
+        if let Some((edit, restart)) = restart {
+            self.retokenize::<T>(lines, edit, restart);
+        } else {
+            self.clear();
+            if !lines.is_empty() {
+                let checkpoint = TokenCheckpoint {
+                    position: TokenizerState::new(lines).position(),
+                    lookback: Vec::new(),
+                    tokenizer: Arc::new(tokenizer.clone()),
+                };
+                self.segments.push(TokenSegment { checkpoint: Some(checkpoint), ..TokenSegment::default() });
+                let edit = TextEdit { start: 0, old_end: 0, new_end: usize::MAX };
+                self.retokenize::<T>(lines, edit, SegmentStart::default());
+            }
+        }
+    }
+
+    /// Tokenize from the segment at `restart` until past `edit`, and then keep the old segments from the first one
+    /// where the tokenizer is in the same state again.
+    fn retokenize<T: Tokenizer>(&mut self, lines: &TextRope, edit: TextEdit, restart: SegmentStart) {
+        let checkpoint = self.segments[restart.index].checkpoint.clone().unwrap();
+        let mut tokenizer = checkpoint.tokenizer.downcast_ref::<T>().unwrap().clone();
+        // The first segment can start right at the edit, so its lookahead chars might have changed.
+        let mut state = if restart.index == 0 {
+            TokenizerState::new(lines)
+        } else {
+            TokenizerState::from_position(lines, &checkpoint.position.shifted(restart.offset as isize, restart.row as isize))
+        };
+        let char_delta = edit.new_end as isize - edit.old_end as isize;
+        let shift = |value: usize| (value as isize + char_delta) as usize;
+
+        let mut new_segments = Vec::new();
+        let mut segment = TokenSegment { checkpoint: Some(checkpoint.clone()), ..TokenSegment::default() };
+        let (mut segment_offset, mut segment_row) = (restart.offset, restart.row);
+        let mut lookback = checkpoint.lookback;
+        let mut old = restart.next(&self.segments[restart.index]);
+        let mut chunk = Vec::new();
+        let end = loop {
+            chunk.clear();
+            let token_type = tokenizer.next_token(&mut state, &mut chunk, &lookback);
+            segment.push(token_type, chunk.len(), state.next);
+            lookback.push(segment.tokens.last().unwrap().clone());
+            segment.line_count = state.line_counter - segment_row;
+            if token_type == TokenType::Eof {
+                break self.segments.len();
+            }
+
+            let offset = segment_offset + segment.char_count;
+            if offset > edit.new_end && segment.line_count >= MIN_SEGMENT_LINES {
+                while old.index < self.segments.len() && shift(old.offset) < offset {
+                    old = old.next(&self.segments[old.index]);
+                }
+                if let Some(TokenSegment { checkpoint: Some(old_checkpoint), .. }) = self.segments.get(old.index) {
+                    if old.offset >= edit.old_end
+                        && shift(old.offset) == offset
+                        && old_checkpoint.position == state.position().shifted(-(offset as isize), -(state.line_counter as isize))
+                        && old_checkpoint.tokenizer.downcast_ref::<T>() == Some(&tokenizer)
+                        && TokenChunk::lookback(&lookback)
+                            .iter()
+                            .map(|token| token.token_type)
+                            .eq(old_checkpoint.lookback.iter().map(|token| token.token_type))
+                    {
+                        // Back in sync, so keep the old segments from here.
+                        break old.index;
+                    }
+                }
+            }
+
+            if segment.line_count >= SEGMENT_LINES {
+                lookback = TokenChunk::lookback(&lookback);
+                let checkpoint = TokenCheckpoint {
+                    position: state.position().shifted(-(offset as isize), -(state.line_counter as isize)),
+                    lookback: lookback.clone(),
+                    tokenizer: Arc::new(tokenizer.clone()),
+                };
+                new_segments.push(std::mem::replace(
+                    &mut segment,
+                    TokenSegment { checkpoint: Some(checkpoint), ..TokenSegment::default() },
+                ));
+                segment_offset = offset;
+                segment_row = state.line_counter;
+            }
+        };
+        new_segments.push(segment);
+
+        let removed: usize = self.segments[restart.index..end].iter().map(|segment| segment.tokens.len()).sum();
+        let added: usize = new_segments.iter().map(|segment| segment.tokens.len()).sum();
+        self.len = self.len - removed + added;
+        self.segments.splice(restart.index..end, new_segments);
+    }
+}
+
+/// Iterator over the tokens of [`TokenChunks`].
+pub struct TokenChunksIter<'a> {
+    token_chunks: &'a TokenChunks,
+    start: SegmentStart,
+    /// Index within the segment at `start`.
+    token_index: usize,
+}
+
+impl<'a> Iterator for TokenChunksIter<'a> {
+    type Item = TokenChunk;
+
+    fn next(&mut self) -> Option<TokenChunk> {
+        loop {
+            let segment = self.token_chunks.segments.get(self.start.index)?;
+            if self.token_index < segment.tokens.len() {
+                self.token_index += 1;
+                return Some(self.token_chunks.token_chunk(self.start, self.token_index - 1));
+            }
+            self.start = self.start.next(segment);
+            self.token_index = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::textbuffer::TextPos;
+
+    #[derive(Clone, Default, PartialEq)]
+    struct WordTokenizer {}
+
+    impl Tokenizer for WordTokenizer {
+        fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, _: &[TokenChunk]) -> TokenType {
+            let token_type = match state.next {
+                '\0' => TokenType::Eof,
+                '\n' => TokenType::Newline,
+                ' ' => TokenType::Whitespace,
+                '(' => TokenType::ParenOpen,
+                ')' => TokenType::ParenClose,
+                _ => TokenType::Identifier,
+            };
+            chunk.push(state.next);
+            state.advance();
+            while token_type == TokenType::Identifier && !" ()\n\0".contains(state.next) {
+                chunk.push(state.next);
+                state.advance();
+            }
+            token_type
+        }
+    }
+
+    fn tokens(token_chunks: &TokenChunks) -> Vec<(TokenType, usize, usize, usize)> {
+        token_chunks.iter().map(|chunk| (chunk.token_type, chunk.offset, chunk.len, chunk.pair_token)).collect()
+    }
+
+    #[test]
+    fn test_edit_keeps_later_segments() {
+        let lines: Vec<String> = (0..2000).map(|row| format!("line ({})", row)).collect();
+        let mut lines = TextRope::from_lines(lines.iter().map(String::as_str));
+        let mut token_chunks = TokenChunks::default();
+        token_chunks.update(&lines, &WordTokenizer::default());
+        assert!(token_chunks.segments.len() > 20);
+        let segment_tokens = |token_chunks: &TokenChunks| -> Vec<*const TokenChunk> {
+            token_chunks.segments.iter().map(|segment| segment.tokens.as_ptr()).collect()
+        };
+        let old_segment_tokens = segment_tokens(&token_chunks);
+
+        // Open a parenthesis and type a bit further down. Only the segments around that get tokenized again.
+        let offset = lines.text_pos_to_offset(TextPos { row: 100, col: 5 });
+        lines.modify_line(100, |line| line.insert(5, '('));
+        token_chunks.record_edit(offset, 0, 1);
+        let offset = lines.text_pos_to_offset(TextPos { row: 120, col: 0 });
+        lines.modify_line(120, |line| line.splice(0..0, "abc ".chars()).count());
+        token_chunks.record_edit(offset, 0, 4);
+        token_chunks.update(&lines, &WordTokenizer::default());
+        assert!(token_chunks.has_invalid_pair());
+        let new_segment_tokens = segment_tokens(&token_chunks);
+        assert_eq!(new_segment_tokens.len(), old_segment_tokens.len());
+        let changed = (0..new_segment_tokens.len()).filter(|&i| new_segment_tokens[i] != old_segment_tokens[i]).count();
+        assert!(changed <= 2, "{} segments changed", changed);
+
+        // Close it on the last line, so it gets paired up across all segments.
+        let last_row = lines.len() - 1;
+        let offset = lines.text_pos_to_offset(TextPos { row: last_row, col: 0 });
+        lines.modify_line(last_row, |line| line.insert(0, ')'));
+        token_chunks.record_edit(offset, 0, 1);
+        token_chunks.update(&lines, &WordTokenizer::default());
+
+        let mut expected = TokenChunks::default();
+        expected.update(&lines, &WordTokenizer::default());
+        assert_eq!(tokens(&token_chunks), tokens(&expected));
+        let open = token_chunks.find_offset(lines.text_pos_to_offset(TextPos { row: 100, col: 5 })).unwrap();
+        let close = token_chunks.find_offset(lines.text_pos_to_offset(TextPos { row: last_row, col: 0 })).unwrap();
+        assert_eq!(token_chunks.get(open).unwrap().pair_token, close);
+        assert_eq!(token_chunks.get(close).unwrap().pair_token, open);
+        assert!(!token_chunks.has_invalid_pair());
+    }
+}
@@ -1,19 +1,66 @@
 // the 'rust' tokenizer
 
+use crate::textrope::*;
+
+/// A tokenizer that can be used with [`crate::TextBuffer::update_token_chunks`].
+///
+/// Tokenizers get cloned at regular intervals to be able to restart tokenizing after an edit, and compared to find
+/// out when tokenizing after an edit is back in the same state as before it, so keep them small.
+pub trait Tokenizer: Clone + PartialEq + Send + Sync + 'static {
+    /// Read the next token from `state`, pushing the chars that it consists of onto `chunk`, and return its type.
+    /// Returns [`TokenType::Eof`] at the end of the text.
+    ///
+    /// `token_chunks` contains the last few tokens before this one: at least the last two, and the last one that isn't
+    /// [`TokenType::should_ignore`]d, so that [`TokenChunk::scan_last_token`] works. Their offsets and pair tokens are
+    /// not meaningful, since tokens get stored relative to where tokenizing can restart; see [`TokenChunk::lookback`].
+    fn next_token<'a>(&mut self, state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, token_chunks: &[TokenChunk])
+        -> TokenType;
+}
+
 pub struct TokenizerState<'a> {
     pub prev: char,
     pub cur: char,
     pub next: char,
-    pub lines: &'a [Vec<char>],
+    pub lines: &'a TextRope,
     pub line_start: usize,
     pub line_counter: usize,
     pub eof: bool,
     pub offset: usize,
-    iter: std::slice::Iter<'a, char>,
+    /// The chars of the current line, and how many of them were read.
+    line: Vec<char>,
+    col: usize,
+    next_lines: TextRopeIter<'a>,
+}
+
+/// A snapshot of the position of a [`TokenizerState`], for resuming tokenization later on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct TokenizerPosition {
+    prev: char,
+    cur: char,
+    next: char,
+    line_start: usize,
+    pub(crate) line_counter: usize,
+    /// Number of chars of the current line that were read.
+    line_chars_read: usize,
+    eof: bool,
+    offset: usize,
+}
+
+impl TokenizerPosition {
+    /// The same position after inserting `char_delta` chars and `line_delta` lines before it. Wraps around, so that
+    /// positions can be made relative to a later point and back.
+    pub(crate) fn shifted(&self, char_delta: isize, line_delta: isize) -> Self {
+        Self {
+            line_start: self.line_start.wrapping_add(char_delta as usize),
+            line_counter: self.line_counter.wrapping_add(line_delta as usize),
+            offset: self.offset.wrapping_add(char_delta as usize),
+            ..*self
+        }
+    }
 }
 
 impl<'a> TokenizerState<'a> {
-    pub fn new(lines: &'a [Vec<char>]) -> Self {
+    pub fn new(lines: &'a TextRope) -> Self {
         let mut ret = Self {
             lines,
             line_start: 0,
@@ -23,15 +70,47 @@ impl<'a> TokenizerState<'a> {
             prev: '\0',
             cur: '\0',
             next: '\0',
-            iter: lines[0].iter(),
+            line: lines.line_chars(0),
+            col: 0,
+            next_lines: lines.iter_from(1),
         };
         ret.advance_with_cur();
         ret
     }
 
+    pub(crate) fn from_position(lines: &'a TextRope, position: &TokenizerPosition) -> Self {
+        Self {
+            lines,
+            line_start: position.line_start,
+            line_counter: position.line_counter,
+            offset: position.offset,
+            eof: position.eof,
+            prev: position.prev,
+            cur: position.cur,
+            next: position.next,
+            line: lines.line_chars(position.line_counter),
+            col: position.line_chars_read,
+            next_lines: lines.iter_from(position.line_counter + 1),
+        }
+    }
+
+    pub(crate) fn position(&self) -> TokenizerPosition {
+        TokenizerPosition {
+            prev: self.prev,
+            cur: self.cur,
+            next: self.next,
+            line_start: self.line_start,
+            line_counter: self.line_counter,
+            line_chars_read: self.col,
+            eof: self.eof,
+            offset: self.offset,
+        }
+    }
+
     pub fn advance(&mut self) {
-        if let Some(next) = self.iter.next() {
-            self.next = *next;
+        if let Some(&next) = self.line.get(self.col) {
+            self.col += 1;
+            self.next = next;
             self.offset += 1;
         } else {
             self.next_line();
@@ -43,7 +122,9 @@ impl<'a> TokenizerState<'a> {
             self.line_counter += 1;
             self.line_start = self.offset;
             self.offset += 1;
-            self.iter = self.lines[self.line_counter].iter();
+            self.line.clear();
+            self.line.extend(self.next_lines.next().unwrap().chars());
+            self.col = 0;
             self.next = '\n'
         } else {
             self.offset += 1;
@@ -94,6 +175,11 @@ impl<'a> TokenizerState<'a> {
         }
         true
     }
+
+    /// The chars after [`TokenizerState::next`] up to the end of the line, for looking further ahead.
+    pub fn peek_line(&self) -> &[char] {
+        &self.line[self.col..]
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -119,32 +205,17 @@ impl TokenChunk {
         TokenType::Unexpected
     }
 
-    pub fn push_with_pairing(
-        token_chunks: &mut Vec<TokenChunk>,
-        pair_stack: &mut Vec<usize>,
-        next: char,
-        offset: usize,
-        offset2: usize,
-        token_type: TokenType,
-    ) -> bool {
-        let mut invalid_pair = false;
-        let pair_token = if token_type == TokenType::ParenOpen {
-            pair_stack.push(token_chunks.len());
-            token_chunks.len()
-        } else if token_type == TokenType::ParenClose {
-            if !pair_stack.is_empty() {
-                let other = pair_stack.pop().unwrap();
-                token_chunks[other].pair_token = token_chunks.len();
-                other
-            } else {
-                invalid_pair = true;
-                token_chunks.len()
-            }
-        } else {
-            token_chunks.len()
-        };
-        token_chunks.push(TokenChunk { offset, pair_token, len: offset2 - offset, next, token_type });
-        invalid_pair
+    /// The last few of `token_chunks` that a [`Tokenizer`] needs to see to continue after them: the last two, and
+    /// the last one that isn't [`TokenType::should_ignore`]d if that is further back.
+    pub fn lookback(token_chunks: &[TokenChunk]) -> Vec<TokenChunk> {
+        let start = token_chunks.len().saturating_sub(2);
+        let mut lookback = Vec::with_capacity(3);
+        match token_chunks.iter().rposition(|token_chunk| !token_chunk.token_type.should_ignore()) {
+            Some(index) if index < start => lookback.push(token_chunks[index].clone()),
+            _ => {}
+        }
+        lookback.extend_from_slice(&token_chunks[start..]);
+        lookback
     }
 }
 
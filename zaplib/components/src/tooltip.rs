@@ -0,0 +1,144 @@
+//! Text that shows up when hovering over something for a little while.
+
+use crate::*;
+use zaplib::*;
+
+const TOOLTIP_HEIGHT: f32 = 24.;
+const TOOLTIP_PADDING_X: f32 = 8.;
+/// Distance between the pointer and the tooltip.
+const POINTER_OFFSET: f32 = 16.;
+
+const COLOR_TOOLTIP: Vec4 = vec4(0.3, 0.3, 0.3, 1.);
+const COLOR_TEXT: Vec4 = vec4(0.9, 0.9, 0.9, 1.);
+
+/// A tooltip for any [`Area`], e.g. the [`Area`] of a [`Button`] or of a [`Background`] that some component uses.
+/// When hovering over the [`Area`] for [`Tooltip::delay`] seconds, it shows some text below the pointer, on top of
+/// everything else (see [`Popover::begin_draw_anchored`]). It goes away when the pointer leaves the [`Area`], or when
+/// clicking or scrolling.
+///
+/// This doesn't mark any events as handled, so [`Tooltip::handle`] can be called before or after the event handler
+/// of the component that the [`Area`] belongs to.
+pub struct Tooltip {
+    popover: Popover,
+    /// How long to hover before showing the tooltip, in seconds.
+    pub delay: f64,
+    timer: Timer,
+    /// Where the pointer is hovering over the [`Area`], in window coordinates.
+    hover_pos: Option<Vec2>,
+    is_visible: bool,
+}
+
+impl Default for Tooltip {
+    fn default() -> Self {
+        Self { popover: Popover::default(), delay: 0.5, timer: Timer::empty(), hover_pos: None, is_visible: false }
+    }
+}
+
+impl Tooltip {
+    #[must_use]
+    pub fn with_delay(self, delay: f64) -> Self {
+        Self { delay, ..self }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.is_visible
+    }
+
+    fn hide(&mut self, cx: &mut Cx) {
+        cx.stop_timer(&mut self.timer);
+        self.hover_pos = None;
+        if self.is_visible {
+            self.is_visible = false;
+            cx.request_draw();
+        }
+    }
+
+    /// Show the tooltip after hovering over `area` for a while.
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event, area: Area) {
+        match event {
+            Event::PointerHover(pe) => {
+                let is_over = pe.hover_state != HoverState::Out
+                    && area.get_rect_for_first_instance(cx).map_or(false, |rect| rect.contains(pe.abs));
+                if !is_over {
+                    self.hide(cx);
+                } else if self.hover_pos.is_none() {
+                    self.hover_pos = Some(pe.abs);
+                    self.timer = cx.start_timer(self.delay, false);
+                } else if !self.is_visible {
+                    // Show the tooltip where the pointer ends up, but keep it in place once it's visible.
+                    self.hover_pos = Some(pe.abs);
+                }
+            }
+            Event::PointerDown(_) | Event::PointerScroll(_) | Event::KeyDown(_) => self.hide(cx),
+            Event::Timer(te) => {
+                if self.timer.is_timer(te) {
+                    self.timer = Timer::empty();
+                    self.is_visible = true;
+                    cx.request_draw();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx, text: &str) {
+        if let (true, Some(hover_pos)) = (self.is_visible, self.hover_pos) {
+            let props = TextInsProps { color: COLOR_TEXT, position_anchoring: TEXT_ANCHOR_CENTER_V, ..TextInsProps::DEFAULT };
+            let size = vec2(TextIns::measure_str_width(cx, text, &props) + 2. * TOOLTIP_PADDING_X, TOOLTIP_HEIGHT);
+            let anchor = Rect { pos: hover_pos, size: vec2(0., POINTER_OFFSET) };
+            let rect = self.popover.begin_draw_anchored(cx, anchor, size, COLOR_TOOLTIP);
+            TextIns::draw_str(cx, text, rect.pos + vec2(TOOLTIP_PADDING_X, TOOLTIP_HEIGHT / 2.), &props);
+            self.popover.end_draw_anchored(cx);
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use zaplib::*;
+
+    use crate::*;
+
+    struct TooltipTestApp {
+        button: Button,
+        tooltip: Tooltip,
+    }
+
+    impl MainApp for TooltipTestApp {
+        fn new(_cx: &mut Cx) -> Self {
+            Self { button: Button::default(), tooltip: Tooltip::default() }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            self.button.handle(cx, event);
+            self.tooltip.handle(cx, event, self.button.area());
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            cx.begin_padding_box(Padding::all(10.));
+            self.button.draw(cx, "Save");
+            cx.end_padding_box();
+            self.tooltip.draw(cx, "Save the file (Ctrl+S)");
+        }
+    }
+
+    #[test]
+    fn test_tooltip() {
+        let mut test = AppTest::<TooltipTestApp>::new_in_window(vec2(300., 200.));
+
+        let rect = test.app.button.area().get_rect_for_first_instance(&test.cx).unwrap();
+        test.pointer_move(rect.pos + rect.size * 0.5);
+        test.advance_time(0.3);
+        assert!(!test.app.tooltip.is_visible());
+        test.advance_time(0.3);
+        assert!(test.app.tooltip.is_visible());
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/tooltip.png"));
+
+        // Leaving the button hides it, and coming back starts the delay all over.
+        test.pointer_move(vec2(250., 150.));
+        assert!(!test.app.tooltip.is_visible());
+        test.pointer_move(rect.pos + rect.size * 0.5);
+        test.advance_time(0.3);
+        assert!(!test.app.tooltip.is_visible());
+    }
+}
@@ -0,0 +1,815 @@
+//! A generic tree, see [`TreeView`].
+
+use std::collections::HashMap;
+
+use crate::*;
+use zaplib::*;
+
+/// The position of a node in [`TreeView::roots`]: the index of the root, followed by the index of the child in
+/// every level below that.
+pub type TreeViewPath = Vec<usize>;
+
+pub enum TreeChildren<T> {
+    /// The node can't be expanded.
+    Leaf,
+    /// The node can be expanded, but its children are loaded on demand, see [`TreeViewEvent::LoadChildren`].
+    NotLoaded,
+    Loaded(Vec<TreeNode<T>>),
+}
+
+pub struct TreeNode<T> {
+    pub label: String,
+    /// Arbitrary data that belongs to this node, e.g. a file path.
+    pub data: T,
+    pub children: TreeChildren<T>,
+    expanded: bool,
+    /// Animates between collapsed (0) and expanded (1).
+    animator: Animator,
+    /// The value of `animator` as of the last draw.
+    openness: f32,
+}
+
+impl<T> TreeNode<T> {
+    pub fn new(label: &str, data: T) -> Self {
+        Self {
+            label: label.to_string(),
+            data,
+            children: TreeChildren::Leaf,
+            expanded: false,
+            animator: Animator::default(),
+            openness: 0.,
+        }
+    }
+
+    #[must_use]
+    pub fn with_children(self, children: Vec<TreeNode<T>>) -> Self {
+        Self { children: TreeChildren::Loaded(children), ..self }
+    }
+
+    /// Makes the node expandable, without loading its children yet.
+    #[must_use]
+    pub fn with_lazy_children(self) -> Self {
+        Self { children: TreeChildren::NotLoaded, ..self }
+    }
+
+    #[must_use]
+    pub fn with_expanded(self, expanded: bool) -> Self {
+        Self { expanded, openness: if expanded { 1. } else { 0. }, ..self }
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+
+    fn loaded_children(&self) -> &[TreeNode<T>] {
+        match &self.children {
+            TreeChildren::Loaded(children) => children,
+            TreeChildren::Leaf | TreeChildren::NotLoaded => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeViewEvent {
+    None,
+    /// A node with [`TreeChildren::NotLoaded`] was expanded. Provide its children with [`TreeView::set_children`].
+    LoadChildren {
+        path: TreeViewPath,
+    },
+    /// See [`TreeView::selected_paths`].
+    SelectionChanged,
+    /// A node was double clicked, or Enter was pressed while it was selected.
+    Activate {
+        path: TreeViewPath,
+    },
+    /// Nodes were moved by dragging and dropping them. `from` are the paths before the move, and `to` the paths
+    /// after the move, in the same order.
+    Moved {
+        from: Vec<TreeViewPath>,
+        to: Vec<TreeViewPath>,
+    },
+}
+
+/// A visible node, in the order in which they are drawn.
+struct TreeViewRow {
+    path: TreeViewPath,
+    depth: usize,
+    /// The rect as of the last draw, not taking scrolling into account.
+    rect: Rect,
+}
+
+/// Where dragged nodes would be dropped, relative to a row index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TreeViewDrop {
+    Before(usize),
+    After(usize),
+    Inside(usize),
+}
+
+struct TreeViewDrag {
+    dragging: bool,
+    drop: Option<TreeViewDrop>,
+    /// Pressing on a node that is already selected keeps the selection, so that it can be dragged. If it doesn't
+    /// get dragged, only that node gets selected when releasing.
+    select_on_release: Option<usize>,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct TreeViewArrowIns {
+    base: QuadIns,
+    color: Vec4,
+    openness: f32,
+}
+
+static ARROW_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            instance color: vec4;
+            instance openness: float;
+
+            fn pixel() -> vec4 {
+                // Rotate from pointing right when collapsed to pointing down when expanded.
+                let angle = openness * 1.5708;
+                let p = pos * 10. - vec2(5., 5.);
+                let r = vec2(p.x * cos(angle) + p.y * sin(angle), p.y * cos(angle) - p.x * sin(angle)) + vec2(5., 5.);
+                let df = Df::viewport(r);
+                df.triangle(vec2(3., 2.), vec2(8., 5.), vec2(3., 8.));
+                df.fill(color);
+                return df.result;
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+const ANIM_COLLAPSED: Anim =
+    Anim { duration: 0.01, chain: false, tracks: &[Track::Float { key_frames: &[(1.0, 0.0)], ease: Ease::DEFAULT }] };
+const ANIM_EXPANDED: Anim =
+    Anim { duration: 0.01, chain: false, tracks: &[Track::Float { key_frames: &[(1.0, 1.0)], ease: Ease::DEFAULT }] };
+const ANIM_COLLAPSE: Anim = Anim { duration: 0.15, ..ANIM_COLLAPSED };
+const ANIM_EXPAND: Anim = Anim { duration: 0.15, ..ANIM_EXPANDED };
+
+const INDENT: f32 = 12.;
+const ARROW_SIZE: f32 = 10.;
+/// Type-ahead search starts over when no key is typed for this long, in seconds.
+const TYPE_AHEAD_TIMEOUT: f64 = 1.;
+
+const COLOR_BG_EVEN: Vec4 = vec4(0.16, 0.16, 0.16, 1.0);
+const COLOR_BG_ODD: Vec4 = vec4(0.15, 0.15, 0.15, 1.0);
+const COLOR_BG_HOVER: Vec4 = vec4(0.22, 0.22, 0.22, 1.0);
+const COLOR_BG_SELECTED: Vec4 = vec4(0.07, 0.27, 0.43, 1.0);
+const COLOR_DROP: Vec4 = vec4(0.4, 0.6, 1., 1.);
+const COLOR_ARROW: Vec4 = vec4(0.6, 0.6, 0.6, 1.);
+const COLOR_LABEL: Vec4 = vec4(0.85, 0.85, 0.85, 1.);
+
+/// Shows a tree of [`TreeNode`]s, of which the children can be expanded and collapsed.
+///
+/// Selection works like in a [`List`] with [`List::multi_select`]: clicking selects a node, and holding shift or
+/// control extends the selection. The keyboard can be used to navigate, as well as typing the start of a label.
+/// Selected nodes can be dragged elsewhere in the tree.
+pub struct TreeView<T> {
+    pub roots: Vec<TreeNode<T>>,
+    pub row_height: f32,
+    component_id: ComponentId,
+    view: ScrollView,
+    /// Keeps track of the selection, with an item per row.
+    list: List,
+    rows: Vec<TreeViewRow>,
+    /// Paths to select when the rows are updated, instead of the current selection.
+    pending_selection: Option<Vec<TreeViewPath>>,
+    /// The node that keyboard navigation starts from.
+    cursor: Option<TreeViewPath>,
+    hover: Option<usize>,
+    drag: Option<TreeViewDrag>,
+    type_ahead: String,
+    type_ahead_time: f64,
+}
+
+impl<T> Default for TreeView<T> {
+    fn default() -> Self {
+        Self {
+            roots: vec![],
+            row_height: 20.,
+            component_id: Default::default(),
+            view: ScrollView::default().with_scroll_v(ScrollBarConfig::default().with_smoothing(0.15)),
+            list: List::default().with_multi_select(true),
+            rows: vec![],
+            pending_selection: None,
+            cursor: None,
+            hover: None,
+            drag: None,
+            type_ahead: String::new(),
+            type_ahead_time: 0.,
+        }
+    }
+}
+
+impl<T> TreeView<T> {
+    #[must_use]
+    pub fn with_roots(self, roots: Vec<TreeNode<T>>) -> Self {
+        Self { roots, ..self }
+    }
+
+    pub fn node(&self, path: &[usize]) -> Option<&TreeNode<T>> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.roots.get(*first)?;
+        for &index in rest {
+            node = node.loaded_children().get(index)?;
+        }
+        Some(node)
+    }
+
+    pub fn node_mut(&mut self, path: &[usize]) -> Option<&mut TreeNode<T>> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.roots.get_mut(*first)?;
+        for &index in rest {
+            node = match &mut node.children {
+                TreeChildren::Loaded(children) => children.get_mut(index)?,
+                TreeChildren::Leaf | TreeChildren::NotLoaded => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// Sets the children of a node, typically in response to [`TreeViewEvent::LoadChildren`].
+    pub fn set_children(&mut self, cx: &mut Cx, path: &[usize], children: Vec<TreeNode<T>>) {
+        if let Some(node) = self.node_mut(path) {
+            node.children = TreeChildren::Loaded(children);
+            self.update_rows();
+            cx.request_draw();
+        }
+    }
+
+    /// Expands or collapses a node. Returns [`TreeViewEvent::LoadChildren`] if the children need to be loaded.
+    pub fn set_expanded(&mut self, cx: &mut Cx, path: &[usize], expanded: bool) -> TreeViewEvent {
+        let node = match self.node_mut(path) {
+            Some(node) if !matches!(node.children, TreeChildren::Leaf) => node,
+            _ => return TreeViewEvent::None,
+        };
+        if node.expanded == expanded {
+            return TreeViewEvent::None;
+        }
+        node.expanded = expanded;
+        node.animator.play_anim(cx, if expanded { ANIM_EXPAND } else { ANIM_COLLAPSE });
+        let load = expanded && matches!(node.children, TreeChildren::NotLoaded);
+        self.update_rows();
+        cx.request_draw();
+        if load {
+            TreeViewEvent::LoadChildren { path: path.to_vec() }
+        } else {
+            TreeViewEvent::None
+        }
+    }
+
+    /// The paths of the selected nodes, in the order in which they were selected.
+    pub fn selected_paths(&self) -> Vec<TreeViewPath> {
+        self.list.selection.iter().filter_map(|&index| self.rows.get(index)).map(|row| row.path.clone()).collect()
+    }
+
+    pub fn select_paths(&mut self, cx: &mut Cx, paths: Vec<TreeViewPath>) {
+        self.cursor = paths.last().cloned();
+        self.pending_selection = Some(paths);
+        self.update_rows();
+        cx.request_draw();
+    }
+
+    /// Flattens the nodes that are visible into rows, keeping the same nodes selected.
+    fn update_rows(&mut self) {
+        let selection = match self.pending_selection.take() {
+            Some(selection) => selection,
+            None => self.selected_paths(),
+        };
+
+        fn add_rows<T>(rows: &mut Vec<TreeViewRow>, nodes: &[TreeNode<T>], path: &mut TreeViewPath) {
+            for (index, node) in nodes.iter().enumerate() {
+                path.push(index);
+                rows.push(TreeViewRow { path: path.clone(), depth: path.len() - 1, rect: Rect::default() });
+                // Keep showing the children while they are collapsing.
+                if node.expanded || node.openness > 0. {
+                    add_rows(rows, node.loaded_children(), path);
+                }
+                path.pop();
+            }
+        }
+        let mut rows = vec![];
+        add_rows(&mut rows, &self.roots, &mut vec![]);
+
+        // Keep the rects of the last draw around for hit testing, until the next draw.
+        let mut rects: HashMap<&TreeViewPath, Rect> = self.rows.iter().map(|row| (&row.path, row.rect)).collect();
+        for row in &mut rows {
+            if let Some(rect) = rects.remove(&row.path) {
+                row.rect = rect;
+            }
+        }
+        let indices: HashMap<&TreeViewPath, usize> = rows.iter().enumerate().map(|(index, row)| (&row.path, index)).collect();
+        let selection: Vec<usize> = selection.iter().filter_map(|path| indices.get(path).copied()).collect();
+
+        self.list.set_list_len(rows.len());
+        for item in &mut self.list.list_items {
+            item.is_selected = false;
+        }
+        for &index in &selection {
+            self.list.list_items[index].is_selected = true;
+        }
+        self.list.selection = selection;
+        self.list.start_item = 0;
+        self.list.end_item = rows.len();
+        if self.hover.map_or(false, |hover| hover >= rows.len()) {
+            self.hover = None;
+        }
+        self.rows = rows;
+    }
+
+    fn row_index(&self, path: &[usize]) -> Option<usize> {
+        self.rows.iter().position(|row| row.path == path)
+    }
+
+    /// The row at a position that doesn't take scrolling into account.
+    fn row_at(&self, pos: Vec2) -> Option<usize> {
+        self.rows.iter().position(|row| row.rect.contains(pos))
+    }
+
+    fn arrow_rect(&self, row: &TreeViewRow) -> Rect {
+        let size = ARROW_SIZE * row.rect.size.y / self.row_height;
+        Rect {
+            pos: vec2(row.rect.pos.x + row.depth as f32 * INDENT + 2., row.rect.pos.y + 0.5 * (row.rect.size.y - size)),
+            size: vec2(size, size),
+        }
+    }
+
+    fn drop_at(&self, pos: Vec2) -> Option<TreeViewDrop> {
+        let index = match self.row_at(pos) {
+            Some(index) => index,
+            None => {
+                let last = self.rows.len().checked_sub(1)?;
+                return if pos.y > self.rows[last].rect.pos.y { Some(TreeViewDrop::After(last)) } else { None };
+            }
+        };
+        let rect = self.rows[index].rect;
+        let can_drop_inside = matches!(self.node(&self.rows[index].path)?.children, TreeChildren::Loaded(_));
+        let drop = if can_drop_inside && pos.y > rect.pos.y + 0.25 * rect.size.y && pos.y < rect.pos.y + 0.75 * rect.size.y {
+            TreeViewDrop::Inside(index)
+        } else if pos.y < rect.pos.y + 0.5 * rect.size.y {
+            TreeViewDrop::Before(index)
+        } else {
+            TreeViewDrop::After(index)
+        };
+
+        // Nodes can't be dropped inside of themselves.
+        let target_parent = self.drop_parent_and_index(drop).0;
+        if self.selected_paths().iter().any(|path| target_parent.starts_with(path)) {
+            return None;
+        }
+        Some(drop)
+    }
+
+    /// The path of the node to drop into, and the index among its children, before moving any nodes.
+    fn drop_parent_and_index(&self, drop: TreeViewDrop) -> (TreeViewPath, usize) {
+        match drop {
+            TreeViewDrop::Before(index) | TreeViewDrop::After(index) => {
+                let path = &self.rows[index].path;
+                let (last, parent) = path.split_last().unwrap();
+                (parent.to_vec(), if matches!(drop, TreeViewDrop::Before(_)) { *last } else { last + 1 })
+            }
+            TreeViewDrop::Inside(index) => {
+                let path = self.rows[index].path.clone();
+                let len = self.node(&path).map_or(0, |node| node.loaded_children().len());
+                (path, len)
+            }
+        }
+    }
+
+    fn children_mut(&mut self, parent: &[usize]) -> Option<&mut Vec<TreeNode<T>>> {
+        if parent.is_empty() {
+            return Some(&mut self.roots);
+        }
+        match &mut self.node_mut(parent)?.children {
+            TreeChildren::Loaded(children) => Some(children),
+            TreeChildren::Leaf | TreeChildren::NotLoaded => None,
+        }
+    }
+
+    /// Moves the selected nodes to where they were dropped.
+    fn move_selection(&mut self, cx: &mut Cx, drop: TreeViewDrop) -> TreeViewEvent {
+        let (mut parent, mut index) = self.drop_parent_and_index(drop);
+        if let TreeViewDrop::Inside(row_index) = drop {
+            let path = self.rows[row_index].path.clone();
+            self.set_expanded(cx, &path, true);
+        }
+
+        // Only move the top-most selected nodes, since their children move along with them.
+        let mut from = self.selected_paths();
+        from.sort();
+        from.dedup();
+        let from: Vec<TreeViewPath> =
+            from.iter().filter(|path| !from.iter().any(|other| other != *path && path.starts_with(other))).cloned().collect();
+        if from.is_empty() {
+            return TreeViewEvent::None;
+        }
+
+        // Remove the nodes back to front, so that the paths of the nodes that still need to be removed stay the same.
+        let mut nodes = vec![];
+        for path in from.iter().rev() {
+            let (&last, path_parent) = path.split_last().unwrap();
+            if let Some(children) = self.children_mut(path_parent) {
+                nodes.push(children.remove(last));
+            }
+            // Removing a node shifts its later siblings, which can include the drop target or one of its ancestors.
+            let depth = path_parent.len();
+            if parent.len() > depth && parent[..depth] == *path_parent && parent[depth] > last {
+                parent[depth] -= 1;
+            } else if parent == path_parent && index > last {
+                index -= 1;
+            }
+        }
+        nodes.reverse();
+
+        let count = nodes.len();
+        if let Some(children) = self.children_mut(&parent) {
+            let index = index.min(children.len());
+            children.splice(index..index, nodes);
+        }
+        let to: Vec<TreeViewPath> = (index..index + count)
+            .map(|index| {
+                let mut path = parent.clone();
+                path.push(index);
+                path
+            })
+            .collect();
+        self.select_paths(cx, to.clone());
+        TreeViewEvent::Moved { from, to }
+    }
+
+    fn scroll_row_into_view(&mut self, cx: &mut Cx, index: usize) {
+        // Rows that haven't been drawn yet don't have a rect.
+        if let Some(row) = self.rows.get(index).filter(|row| row.rect.size.y > 0.) {
+            let rect = Rect { pos: row.rect.pos, size: vec2(row.rect.size.x, self.row_height) };
+            self.view.scroll_into_view_abs(cx, rect);
+        }
+    }
+
+    /// Selects the next row of which the label starts with the typed text.
+    fn type_ahead_select(&self, input: &str) -> Option<usize> {
+        let start = self.cursor.as_ref().and_then(|cursor| self.row_index(cursor)).unwrap_or(0);
+        // When continuing to type, the row that is already selected can still match.
+        let offset = if self.type_ahead.chars().count() > input.chars().count() { 0 } else { 1 };
+        let search = self.type_ahead.to_lowercase();
+        (0..self.rows.len())
+            .map(|i| (start + offset + i) % self.rows.len())
+            .find(|&index| self.node(&self.rows[index].path).map_or(false, |node| node.label.to_lowercase().starts_with(&search)))
+    }
+
+    fn handle_key_down(&mut self, cx: &mut Cx, ke: &KeyEvent) -> (ListSelect, TreeViewEvent) {
+        if self.rows.is_empty() {
+            return (ListSelect::None, TreeViewEvent::None);
+        }
+        let cursor = self.cursor.as_ref().and_then(|cursor| self.row_index(cursor));
+        let last = self.rows.len() - 1;
+        let select = |index: usize| if ke.modifiers.shift { ListSelect::Range(index) } else { ListSelect::Single(index) };
+        match ke.key_code {
+            KeyCode::ArrowUp => (select(cursor.map_or(0, |cursor| cursor.saturating_sub(1))), TreeViewEvent::None),
+            KeyCode::ArrowDown => (select(cursor.map_or(0, |cursor| (cursor + 1).min(last))), TreeViewEvent::None),
+            KeyCode::Home => (select(0), TreeViewEvent::None),
+            KeyCode::End => (select(last), TreeViewEvent::None),
+            KeyCode::KeyA if ke.modifiers.control || ke.modifiers.logo => (ListSelect::All, TreeViewEvent::None),
+            KeyCode::ArrowRight | KeyCode::ArrowLeft => {
+                let cursor = match cursor {
+                    Some(cursor) => cursor,
+                    None => return (ListSelect::Single(0), TreeViewEvent::None),
+                };
+                let path = self.rows[cursor].path.clone();
+                let node = self.node(&path).unwrap();
+                let expandable = !matches!(node.children, TreeChildren::Leaf);
+                if ke.key_code == KeyCode::ArrowRight {
+                    if expandable && !node.expanded {
+                        (ListSelect::None, self.set_expanded(cx, &path, true))
+                    } else if expandable && !node.loaded_children().is_empty() {
+                        (ListSelect::Single(cursor + 1), TreeViewEvent::None)
+                    } else {
+                        (ListSelect::None, TreeViewEvent::None)
+                    }
+                } else if expandable && node.expanded {
+                    (ListSelect::None, self.set_expanded(cx, &path, false))
+                } else if path.len() > 1 {
+                    (self.row_index(&path[..path.len() - 1]).map_or(ListSelect::None, ListSelect::Single), TreeViewEvent::None)
+                } else {
+                    (ListSelect::None, TreeViewEvent::None)
+                }
+            }
+            KeyCode::Return => match cursor {
+                Some(cursor) => (ListSelect::None, TreeViewEvent::Activate { path: self.rows[cursor].path.clone() }),
+                None => (ListSelect::None, TreeViewEvent::None),
+            },
+            _ => (ListSelect::None, TreeViewEvent::None),
+        }
+    }
+
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> TreeViewEvent {
+        if let Event::NextFrame = event {
+            let paths: Vec<TreeViewPath> = self.rows.iter().map(|row| row.path.clone()).collect();
+            for path in paths {
+                if let Some(node) = self.node_mut(&path) {
+                    if node.animator.handle(cx, event) {
+                        cx.request_draw();
+                    }
+                }
+            }
+        }
+        self.view.handle(cx, event);
+
+        let rect = self.view.get_rect(cx);
+        let scroll = self.view.get_scroll_pos(cx);
+        let mut select = ListSelect::None;
+        let mut dblclick = false;
+        let mut ret = TreeViewEvent::None;
+        match event.hits_pointer(cx, self.component_id, Some(rect)) {
+            Event::PointerDown(pe) => {
+                cx.set_key_focus(Some(self.component_id));
+                if let Some(index) = self.row_at(pe.abs + scroll) {
+                    let path = self.rows[index].path.clone();
+                    let expandable = !matches!(self.node(&path).unwrap().children, TreeChildren::Leaf);
+                    if expandable && self.arrow_rect(&self.rows[index]).contains(pe.abs + scroll) {
+                        let expanded = self.node(&path).unwrap().expanded;
+                        return self.set_expanded(cx, &path, !expanded);
+                    }
+                    let mut select_on_release = None;
+                    select = if pe.modifiers.logo || pe.modifiers.control {
+                        ListSelect::Toggle(index)
+                    } else if pe.modifiers.shift {
+                        ListSelect::Range(index)
+                    } else if self.list.list_items[index].is_selected && pe.tap_count == 1 {
+                        select_on_release = Some(index);
+                        ListSelect::None
+                    } else {
+                        dblclick = pe.tap_count > 1;
+                        ListSelect::Single(index)
+                    };
+                    self.drag = Some(TreeViewDrag { dragging: false, drop: None, select_on_release });
+                }
+            }
+            Event::PointerMove(pe) => {
+                let drop = self.drop_at(pe.abs + scroll);
+                if let Some(drag) = &mut self.drag {
+                    if drag.dragging || pe.move_distance() > 5. {
+                        drag.dragging = true;
+                        drag.drop = drop;
+                        cx.set_down_mouse_cursor(MouseCursor::Hand);
+                        cx.request_draw();
+                    }
+                }
+            }
+            Event::PointerUp(_) => match self.drag.take() {
+                Some(TreeViewDrag { dragging: true, drop, .. }) => {
+                    cx.request_draw();
+                    if let Some(drop) = drop {
+                        return self.move_selection(cx, drop);
+                    }
+                }
+                Some(TreeViewDrag { dragging: false, select_on_release: Some(index), .. }) => {
+                    select = ListSelect::Single(index);
+                }
+                _ => (),
+            },
+            Event::PointerHover(pe) => {
+                let hover = match pe.hover_state {
+                    HoverState::Out => None,
+                    _ => self.row_at(pe.abs + scroll),
+                };
+                if hover != self.hover {
+                    self.hover = hover;
+                    cx.request_draw();
+                }
+            }
+            _ => (),
+        }
+
+        match event.hits_keyboard(cx, self.component_id) {
+            Event::KeyDown(ke) => {
+                let (key_select, key_ret) = self.handle_key_down(cx, &ke);
+                select = key_select;
+                ret = key_ret;
+            }
+            Event::TextInput(te) => {
+                if cx.last_event_time - self.type_ahead_time > TYPE_AHEAD_TIMEOUT {
+                    self.type_ahead.clear();
+                }
+                self.type_ahead_time = cx.last_event_time;
+                self.type_ahead.push_str(&te.input);
+                if let Some(index) = self.type_ahead_select(&te.input) {
+                    select = ListSelect::Single(index);
+                }
+            }
+            _ => (),
+        }
+
+        let cursor = match select {
+            ListSelect::Single(index) | ListSelect::Range(index) | ListSelect::Toggle(index) => Some(index),
+            ListSelect::All | ListSelect::None => None,
+        };
+        match self.list.handle_list_logic(cx, event, select, dblclick, |_, _, _, _| ()) {
+            ListEvent::SelectDouble(index) => {
+                ret = TreeViewEvent::Activate { path: self.rows[index].path.clone() };
+            }
+            ListEvent::SelectSingle(_) | ListEvent::SelectMultiple => {
+                ret = TreeViewEvent::SelectionChanged;
+            }
+            ListEvent::None => (),
+        }
+        if let Some(cursor) = cursor {
+            self.cursor = Some(self.rows[cursor].path.clone());
+            self.scroll_row_into_view(cx, cursor);
+            cx.request_draw();
+        }
+        ret
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx) {
+        self.view.begin_view(cx, LayoutSize::FILL);
+
+        // Update the animations first, since they determine which rows are visible.
+        let paths: Vec<TreeViewPath> = self.rows.iter().map(|row| row.path.clone()).collect();
+        for path in paths {
+            if let Some(node) = self.node_mut(&path) {
+                node.animator.draw(cx, if node.expanded { ANIM_EXPANDED } else { ANIM_COLLAPSED });
+                node.openness = node.animator.get_float(0);
+            }
+        }
+        self.update_rows();
+
+        // The height of every row is scaled by how far all of its ancestors are expanded.
+        let mut scales: Vec<f32> = vec![];
+        let mut arrows = vec![];
+        let mut labels = vec![];
+        let drop = self.drag.as_ref().and_then(|drag| drag.drop);
+        for index in 0..self.rows.len() {
+            let depth = self.rows[index].depth;
+            scales.truncate(depth);
+            let scale = scales.last().copied().unwrap_or(1.);
+            let node = self.node(&self.rows[index].path).unwrap();
+            scales.push(scale * node.openness);
+
+            let rect = cx.add_box(LayoutSize::new(Width::Fill, Height::Fix(self.row_height * scale)));
+            self.rows[index].rect = rect;
+            if scale < 0.01 {
+                continue;
+            }
+
+            let node = self.node(&self.rows[index].path).unwrap();
+            let color = if self.list.list_items[index].is_selected {
+                COLOR_BG_SELECTED
+            } else if self.hover == Some(index) {
+                COLOR_BG_HOVER
+            } else if index % 2 == 0 {
+                COLOR_BG_EVEN
+            } else {
+                COLOR_BG_ODD
+            };
+            Background::default().draw(cx, rect, color);
+            if drop == Some(TreeViewDrop::Inside(index)) {
+                Background::default().draw(cx, rect, vec4(COLOR_DROP.x, COLOR_DROP.y, COLOR_DROP.z, 0.3));
+            }
+
+            let arrow_rect = self.arrow_rect(&self.rows[index]);
+            if !matches!(node.children, TreeChildren::Leaf) {
+                arrows.push(TreeViewArrowIns {
+                    base: QuadIns::from_rect(arrow_rect),
+                    color: COLOR_ARROW,
+                    openness: node.openness,
+                });
+            }
+            let label_x = arrow_rect.pos.x + arrow_rect.size.x + 4.;
+            labels.push((index, vec2(label_x, rect.pos.y + 0.5 * rect.size.y), rect.pos.x + rect.size.x - label_x, scale));
+        }
+
+        // Show where dragged nodes would be dropped, as a line between rows.
+        let drop_line_y = match drop {
+            Some(TreeViewDrop::Before(index)) => Some(self.rows[index].rect.pos.y),
+            Some(TreeViewDrop::After(index)) => Some(self.rows[index].rect.pos.y + self.rows[index].rect.size.y),
+            _ => None,
+        };
+        if let Some(y) = drop_line_y {
+            let rect = self.view.get_rect(cx);
+            Background::default().draw(cx, Rect { pos: vec2(rect.pos.x, y - 1.), size: vec2(rect.size.x, 2.) }, COLOR_DROP);
+        }
+
+        cx.add_instances(&ARROW_SHADER, &arrows);
+        for (index, pos, width, scale) in labels {
+            let props = TextInsProps {
+                text_style: TEXT_STYLE_NORMAL,
+                color: COLOR_LABEL,
+                font_scale: scale,
+                position_anchoring: TEXT_ANCHOR_CENTER_V,
+                wrapping: Wrapping::Ellipsis(width),
+                ..TextInsProps::DEFAULT
+            };
+            let label = &self.node(&self.rows[index].path).unwrap().label;
+            TextIns::draw_str(cx, label, pos, &props);
+        }
+
+        ScrollShadow::draw_shadow_top(cx, 0.25);
+        self.view.end_view(cx);
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use zaplib::*;
+
+    use crate::*;
+
+    struct TreeViewTestApp {
+        tree: TreeView<()>,
+        events: Vec<TreeViewEvent>,
+    }
+
+    impl MainApp for TreeViewTestApp {
+        fn new(_cx: &mut Cx) -> Self {
+            let tree = TreeView::default().with_roots(vec![
+                TreeNode::new("src", ())
+                    .with_children(vec![
+                        TreeNode::new("main.rs", ()),
+                        TreeNode::new("lib.rs", ()),
+                        TreeNode::new("utils", ()).with_lazy_children(),
+                    ])
+                    .with_expanded(true),
+                TreeNode::new("docs", ()).with_children(vec![TreeNode::new("readme.md", ())]),
+                TreeNode::new("Cargo.toml", ()),
+            ]);
+            Self { tree, events: vec![] }
+        }
+
+        fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+            let event = self.tree.handle(cx, event);
+            if let TreeViewEvent::LoadChildren { path } = &event {
+                let children = vec![TreeNode::new("mod.rs", ()), TreeNode::new("io.rs", ())];
+                self.tree.set_children(cx, path, children);
+            }
+            if event != TreeViewEvent::None {
+                self.events.push(event);
+            }
+        }
+
+        fn draw(&mut self, cx: &mut Cx) {
+            self.tree.draw(cx);
+        }
+    }
+
+    #[test]
+    fn test_tree_view() {
+        let mut test = AppTest::<TreeViewTestApp>::new_in_window(vec2(200., 200.));
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/tree_view_initial.png"));
+
+        // Clicking on the arrow of "utils" loads its children.
+        test.click(vec2(19., 70.));
+        test.advance_time(1.0);
+        assert_eq!(test.app.events, vec![TreeViewEvent::LoadChildren { path: vec![0, 2] }]);
+        assert_eq!(test.app.tree.node(&[0, 2, 1]).unwrap().label, "io.rs");
+
+        // Select "main.rs" and extend the selection with the keyboard.
+        test.click(vec2(100., 30.));
+        test.set_modifiers(KeyModifiers { shift: true, ..KeyModifiers::default() });
+        test.key_press(KeyCode::ArrowDown);
+        test.key_press(KeyCode::ArrowDown);
+        test.set_modifiers(KeyModifiers::default());
+        test.next_frame();
+        assert_eq!(test.app.tree.selected_paths(), vec![vec![0, 0], vec![0, 1], vec![0, 2]]);
+        test.assert_golden_image(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/tree_view_expanded.png"));
+
+        // Typing selects the next node that starts with the typed text.
+        test.text_input("c");
+        assert_eq!(test.app.tree.selected_paths(), vec![vec![2]]);
+        test.advance_time(2.0);
+        test.text_input("d");
+        test.text_input("o");
+        assert_eq!(test.app.tree.selected_paths(), vec![vec![1]]);
+
+        // The arrow keys expand nodes and move into them.
+        test.key_press(KeyCode::ArrowRight);
+        test.advance_time(1.0);
+        test.key_press(KeyCode::ArrowRight);
+        assert_eq!(test.app.tree.selected_paths(), vec![vec![1, 0]]);
+        test.key_press(KeyCode::Return);
+        assert_eq!(test.app.events.last(), Some(&TreeViewEvent::Activate { path: vec![1, 0] }));
+    }
+
+    #[test]
+    fn test_tree_view_drag_and_drop() {
+        let mut test = AppTest::<TreeViewTestApp>::new_in_window(vec2(200., 200.));
+
+        // Drag "Cargo.toml" into "docs".
+        test.pointer_down(vec2(100., 110.));
+        test.pointer_move(vec2(100., 100.));
+        test.pointer_move(vec2(100., 90.));
+        test.pointer_up();
+        assert_eq!(test.app.events.last(), Some(&TreeViewEvent::Moved { from: vec![vec![2]], to: vec![vec![1, 1]] }));
+        assert_eq!(test.app.tree.roots.len(), 2);
+        assert_eq!(test.app.tree.node(&[1, 1]).unwrap().label, "Cargo.toml");
+        assert!(test.app.tree.node(&[1]).unwrap().is_expanded());
+        assert_eq!(test.app.tree.selected_paths(), vec![vec![1, 1]]);
+    }
+}
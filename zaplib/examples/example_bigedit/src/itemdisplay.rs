@@ -70,7 +70,7 @@ impl ItemDisplay {
         if text_buffer.needs_token_chunks() && !text_buffer.lines.is_empty() {
             let mut state = TokenizerState::new(&text_buffer.lines);
             let mut tokenizer = MprsTokenizer::default();
-            let mut pair_stack = Vec::new();
+            let mut chunk = Vec::new();
             let mut line_count = 0;
             let mut token_count = 0;
             let mut backtick_toggle = false;
@@ -78,12 +78,9 @@ impl ItemDisplay {
             let mut first_block_code_line = false;
             let mut message_type = TokenType::Warning;
             loop {
-                let offset = text_buffer.flat_text.len();
-                let mut token_type = tokenizer.next_token(&mut state, &mut text_buffer.flat_text, &text_buffer.token_chunks);
-                let mut val = String::new();
-                for i in offset..text_buffer.flat_text.len() {
-                    val.push(text_buffer.flat_text[i]);
-                }
+                chunk.clear();
+                let mut token_type = tokenizer.next_token(&mut state, &mut chunk, text_buffer.token_chunks.lookback());
+                let val: String = chunk.iter().collect();
                 if token_type == TokenType::Operator && val == "`" {
                     backtick_toggle = !backtick_toggle;
                 }
@@ -128,14 +125,7 @@ impl ItemDisplay {
                 }
                 //println!("{:?} {}", token_type, val);
 
-                TokenChunk::push_with_pairing(
-                    &mut text_buffer.token_chunks,
-                    &mut pair_stack,
-                    state.next,
-                    offset,
-                    text_buffer.flat_text.len(),
-                    token_type,
-                );
+                text_buffer.token_chunks.push(token_type, chunk.len(), state.next);
 
                 token_count += 1;
                 if token_type == TokenType::Newline {
@@ -177,8 +167,8 @@ impl ItemDisplay {
                 let text_buffer = &mut self.text_buffer;
 
                 self.text_disp.begin_text_editor(cx, text_buffer, None);
-                for (index, token_chunk) in text_buffer.token_chunks.iter_mut().enumerate() {
-                    self.text_disp.draw_chunk(cx, index, &text_buffer.flat_text, token_chunk, &text_buffer.markers);
+                for token in text_buffer.iter_token_chunks() {
+                    self.text_disp.draw_chunk(cx, &token, &text_buffer.markers);
                 }
                 self.text_disp.end_text_editor(cx, text_buffer);
             }
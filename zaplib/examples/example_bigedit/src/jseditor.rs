@@ -20,8 +20,8 @@ impl JSEditor {
         JSTokenizer::update_token_chunks(mtb, search_index);
 
         self.text_editor.begin_text_editor(cx, &mut mtb.text_buffer, None);
-        for (index, token_chunk) in mtb.text_buffer.token_chunks.iter_mut().enumerate() {
-            self.text_editor.draw_chunk(cx, index, &mtb.text_buffer.flat_text, token_chunk, &mtb.text_buffer.markers);
+        for token in mtb.text_buffer.iter_token_chunks() {
+            self.text_editor.draw_chunk(cx, &token, &mtb.text_buffer.markers);
         }
 
         self.text_editor.end_text_editor(cx, &mut mtb.text_buffer);
@@ -40,18 +40,11 @@ impl JSTokenizer {
         if text_buffer.needs_token_chunks() && !text_buffer.lines.is_empty() {
             let mut state = TokenizerState::new(&text_buffer.lines);
             let mut tokenizer = JSTokenizer::default();
-            let mut pair_stack = Vec::new();
+            let mut chunk = Vec::new();
             loop {
-                let offset = text_buffer.flat_text.len();
-                let token_type = tokenizer.next_token(&mut state, &mut text_buffer.flat_text, &text_buffer.token_chunks);
-                TokenChunk::push_with_pairing(
-                    &mut text_buffer.token_chunks,
-                    &mut pair_stack,
-                    state.next,
-                    offset,
-                    text_buffer.flat_text.len(),
-                    token_type,
-                );
+                chunk.clear();
+                let token_type = tokenizer.next_token(&mut state, &mut chunk, text_buffer.token_chunks.lookback());
+                text_buffer.token_chunks.push(token_type, chunk.len(), state.next);
                 if token_type == TokenType::Eof {
                     break;
                 }
@@ -63,7 +56,7 @@ impl JSTokenizer {
         &mut self,
         state: &mut TokenizerState<'a>,
         chunk: &mut Vec<char>,
-        token_chunks: &Vec<TokenChunk>,
+        token_chunks: &[TokenChunk],
     ) -> TokenType {
         let start = chunk.len();
         if self.comment_depth > 0 {
@@ -437,7 +430,7 @@ impl JSTokenizer {
         }
     }
 
-    fn parse_js_keyword<'a>(state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, _token_chunks: &Vec<TokenChunk>) -> TokenType {
+    fn parse_js_keyword<'a>(state: &mut TokenizerState<'a>, chunk: &mut Vec<char>, _token_chunks: &[TokenChunk]) -> TokenType {
         match state.cur {
             'b' => {
                 if state.keyword(chunk, "reak") {
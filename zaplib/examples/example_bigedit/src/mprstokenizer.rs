@@ -13,7 +13,7 @@ impl MprsTokenizer {
         &mut self,
         state: &mut TokenizerState<'a>,
         chunk: &mut Vec<char>,
-        token_chunks: &Vec<TokenChunk>,
+        token_chunks: &[TokenChunk],
     ) -> TokenType {
         let start = chunk.len();
         //chunk.truncate(0);
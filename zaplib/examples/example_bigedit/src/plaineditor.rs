@@ -19,8 +19,8 @@ impl PlainEditor {
     pub fn draw(&mut self, cx: &mut Cx, mtb: &mut MakepadTextBuffer, search_index: Option<&mut SearchIndex>) {
         PlainTokenizer::update_token_chunks(&mut mtb.text_buffer, search_index);
         self.text_editor.begin_text_editor(cx, &mtb.text_buffer, None);
-        for (index, token_chunk) in mtb.text_buffer.token_chunks.iter_mut().enumerate() {
-            self.text_editor.draw_chunk(cx, index, &mtb.text_buffer.flat_text, token_chunk, &mtb.text_buffer.markers);
+        for token in mtb.text_buffer.iter_token_chunks() {
+            self.text_editor.draw_chunk(cx, &token, &mtb.text_buffer.markers);
         }
 
         self.text_editor.end_text_editor(cx, &mtb.text_buffer);
@@ -35,18 +35,11 @@ impl PlainTokenizer {
         if text_buffer.needs_token_chunks() && !text_buffer.lines.is_empty() {
             let mut state = TokenizerState::new(&text_buffer.lines);
             let mut tokenizer = PlainTokenizer::default();
-            let mut pair_stack = Vec::new();
+            let mut chunk = Vec::new();
             loop {
-                let offset = text_buffer.flat_text.len();
-                let token_type = tokenizer.next_token(&mut state, &mut text_buffer.flat_text, &text_buffer.token_chunks);
-                TokenChunk::push_with_pairing(
-                    &mut text_buffer.token_chunks,
-                    &mut pair_stack,
-                    state.next,
-                    offset,
-                    text_buffer.flat_text.len(),
-                    token_type,
-                );
+                chunk.clear();
+                let token_type = tokenizer.next_token(&mut state, &mut chunk, text_buffer.token_chunks.lookback());
+                text_buffer.token_chunks.push(token_type, chunk.len(), state.next);
                 if token_type == TokenType::Eof {
                     break;
                 }
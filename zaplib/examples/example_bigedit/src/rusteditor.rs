@@ -63,8 +63,8 @@ impl RustEditor {
         self.splitter.begin_draw(cx);
         Self::update_token_chunks(mtb, search_index);
         self.text_editor.begin_text_editor(cx, &mtb.text_buffer, None);
-        for (index, token_chunk) in mtb.text_buffer.token_chunks.iter_mut().enumerate() {
-            self.text_editor.draw_chunk(cx, index, &mtb.text_buffer.flat_text, token_chunk, &mtb.text_buffer.markers);
+        for token in mtb.text_buffer.iter_token_chunks() {
+            self.text_editor.draw_chunk(cx, &token, &mtb.text_buffer.markers);
         }
         self.text_editor.end_text_editor(cx, &mtb.text_buffer);
 
@@ -85,20 +85,11 @@ impl RustEditor {
         if mtb.text_buffer.needs_token_chunks() && !mtb.text_buffer.lines.is_empty() {
             let mut state = TokenizerState::new(&mtb.text_buffer.lines);
             let mut tokenizer = MprsTokenizer::default();
-            let mut pair_stack = Vec::new();
+            let mut chunk = Vec::new();
             loop {
-                let offset = mtb.text_buffer.flat_text.len();
-                let token_type = tokenizer.next_token(&mut state, &mut mtb.text_buffer.flat_text, &mtb.text_buffer.token_chunks);
-                if TokenChunk::push_with_pairing(
-                    &mut mtb.text_buffer.token_chunks,
-                    &mut pair_stack,
-                    state.next,
-                    offset,
-                    mtb.text_buffer.flat_text.len(),
-                    token_type,
-                ) {
-                    mtb.text_buffer.was_invalid_pair = true;
-                }
+                chunk.clear();
+                let token_type = tokenizer.next_token(&mut state, &mut chunk, mtb.text_buffer.token_chunks.lookback());
+                mtb.text_buffer.token_chunks.push(token_type, chunk.len(), state.next);
 
                 if token_type == TokenType::Eof {
                     break;
@@ -107,9 +98,7 @@ impl RustEditor {
                     search_index.new_rust_token(mtb);
                 }
             }
-            if !pair_stack.is_empty() {
-                mtb.text_buffer.was_invalid_pair = true;
-            }
+            mtb.text_buffer.was_invalid_pair = mtb.text_buffer.token_chunks.has_invalid_pair();
         }
     }
 }
@@ -16,14 +16,16 @@ impl SearchIndex {
         if mtb.text_buffer.token_chunks.len() <= 1 {
             return;
         }
-        let chunk_id = mtb.text_buffer.token_chunks.len() - 2;
+        let token_chunks = &mtb.text_buffer.token_chunks;
+        let chunk_id = token_chunks.len() - 2;
+        let token_chunk = token_chunks.get(chunk_id).unwrap();
         // lets figure out if its a decl, an impl or a use
-        match mtb.text_buffer.token_chunks[chunk_id].token_type {
+        match token_chunk.token_type {
             TokenType::Identifier | TokenType::Call | TokenType::Macro | TokenType::TypeName => {
                 let prev_tt = {
                     let mut i = if chunk_id > 0 { chunk_id - 1 } else { 0 };
                     loop {
-                        let tt = mtb.text_buffer.token_chunks[i].token_type;
+                        let tt = token_chunks.get(i).unwrap().token_type;
                         if i == 0 || !tt.should_ignore() {
                             break tt;
                         }
@@ -33,22 +35,21 @@ impl SearchIndex {
                 let (next_tt, next_char) = {
                     let mut i = chunk_id + 1;
                     loop {
-                        if i >= mtb.text_buffer.token_chunks.len() {
-                            break (TokenType::Unexpected, '\0');
-                        }
-                        let tt = mtb.text_buffer.token_chunks[i].token_type;
-                        if !tt.should_ignore() {
-                            break (tt, mtb.text_buffer.flat_text[mtb.text_buffer.token_chunks[i].offset]);
+                        let next = match token_chunks.get(i) {
+                            Some(next) => next,
+                            None => break (TokenType::Unexpected, '\0'),
+                        };
+                        if !next.token_type.should_ignore() {
+                            break (next.token_type, mtb.text_buffer.get_char(next.offset));
                         }
                         i += 1;
                     }
                 };
-                let offset = mtb.text_buffer.token_chunks[chunk_id].offset;
-                let len = mtb.text_buffer.token_chunks[chunk_id].len;
-                let chars = &mtb.text_buffer.flat_text[offset..(offset + len)];
+                let pos = mtb.text_buffer.offset_to_text_pos(token_chunk.offset);
+                let chars: Vec<char> = mtb.text_buffer.lines[pos.row].chars().skip(pos.col).take(token_chunk.len).collect();
                 let mut_id = (mtb.text_buffer.mutation_id & 0xffff) as u16;
 
-                let prio = match mtb.text_buffer.token_chunks[chunk_id].token_type {
+                let prio = match token_chunk.token_type {
                     TokenType::Identifier => match prev_tt {
                         TokenType::Keyword => 1,
                         _ => 5,
@@ -76,7 +77,7 @@ impl SearchIndex {
                     _ => 4,
                 };
 
-                self.identifiers.write(chars, mtb.text_buffer_id, mut_id, prio, chunk_id as u32);
+                self.identifiers.write(&chars, mtb.text_buffer_id, mut_id, prio, chunk_id as u32);
             }
             _ => (),
         }
@@ -285,7 +286,7 @@ impl TextIndex {
                         },
                     });
                     // lets output a result cursor int he textbuffer
-                    let tok = &tb.token_chunks[*token as usize];
+                    let tok = tb.token_chunks.get(*token as usize).unwrap();
                     tb.markers.search_cursors.push(TextCursor { head: tok.offset + tok.len, tail: tok.offset, max: 0 });
                 } else {
                     cleanup.push((*text_buffer_id, *token));
@@ -95,7 +95,7 @@ impl SearchResults {
         if !self.results.is_empty() {
             let result = &self.results[0];
             let text_buffer = &mut makepad_storage.text_buffers[result.text_buffer_id.as_index()].text_buffer;
-            let tok = &text_buffer.token_chunks[result.token as usize];
+            let tok = text_buffer.token_chunks.get(result.token as usize).unwrap();
             Some((result.text_buffer_id, (tok.offset + tok.len, tok.offset)))
         } else {
             None
@@ -196,7 +196,7 @@ impl SearchResults {
                 if let Event::PointerDown(_) = event {
                     self.search_input.text_editor.set_key_focus(cx);
                 }
-                let tok = &text_buffer.token_chunks[result.token as usize];
+                let tok = text_buffer.token_chunks.get(result.token as usize).unwrap();
                 return SearchResultEvent::DisplayFile {
                     text_buffer_id: result.text_buffer_id,
                     cursor: (tok.offset + tok.len, tok.offset),
@@ -206,7 +206,7 @@ impl SearchResults {
                 // we need to get a filepath
                 let result = &self.results[select_index];
                 let text_buffer = &mut makepad_storage.text_buffers[result.text_buffer_id.as_index()].text_buffer;
-                let tok = &text_buffer.token_chunks[result.token as usize];
+                let tok = text_buffer.token_chunks.get(result.token as usize).unwrap();
                 return SearchResultEvent::OpenFile {
                     text_buffer_id: result.text_buffer_id,
                     cursor: (tok.offset + tok.len, tok.offset),
@@ -326,7 +326,7 @@ impl SearchResultDraw {
         let (first_tok, delta) = text_buffer.scan_token_chunks_prev_line(token as usize, window_up);
         let last_tok = text_buffer.scan_token_chunks_next_line(token as usize, window_down);
 
-        let tok = &text_buffer.token_chunks[token as usize];
+        let tok = text_buffer.token_chunks.get(token as usize).unwrap();
         let pos = text_buffer.offset_to_text_pos(tok.offset);
 
         let split = path.split('/').collect::<Vec<&str>>();
@@ -345,14 +345,13 @@ impl SearchResultDraw {
         self.text_editor.init_draw_state(cx, text_buffer);
 
         let mut first_ws = !selected;
-        for index in first_tok..last_tok {
-            let token_chunk = &text_buffer.token_chunks[index];
-            if first_ws && token_chunk.token_type == TokenType::Whitespace {
+        for token in text_buffer.iter_token_chunks_from(first_tok).take(last_tok - first_tok) {
+            if first_ws && token.token_chunk.token_type == TokenType::Whitespace {
                 continue;
             } else {
                 first_ws = false;
             }
-            self.text_editor.draw_chunk(cx, index, &text_buffer.flat_text, token_chunk, &text_buffer.markers);
+            self.text_editor.draw_chunk(cx, &token, &text_buffer.markers);
         }
 
         self.text_editor.draw_search_markers(cx);
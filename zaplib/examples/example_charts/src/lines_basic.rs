@@ -16,6 +16,8 @@ pub(crate) struct LinesBasic {
     pub(crate) tooltip: ChartTooltipConfig,
     pub(crate) pan_enabled: bool,
     pub(crate) zoom_enabled: bool,
+    pub(crate) chart_type: ChartType,
+    pub(crate) stacked: bool,
 }
 
 impl Default for LinesBasic {
@@ -33,6 +35,8 @@ impl Default for LinesBasic {
             tooltip: Default::default(),
             pan_enabled: false,
             zoom_enabled: false,
+            chart_type: ChartType::Line,
+            stacked: false,
         };
 
         // Add two initial datasets
@@ -60,6 +64,14 @@ impl LinesBasic {
         Self { zoom_enabled: true, pan_enabled: true, ..Self::default() }
     }
 
+    pub(crate) fn with_chart_type(chart_type: ChartType) -> Self {
+        Self { chart_type, ..Self::default() }
+    }
+
+    pub(crate) fn with_stacked_chart_type(chart_type: ChartType) -> Self {
+        Self { chart_type, stacked: true, ..Self::default() }
+    }
+
     fn get_random_data(count: usize) -> Vec<f32> {
         if count == 0 {
             vec![]
@@ -141,6 +153,12 @@ impl LinesBasic {
                 point_radius: 4.,
                 border_color: colors[i % colors.len()],
                 border_width: 2.,
+                background_color: colors[i % colors.len()] * vec4(1., 1., 1., 0.5),
+                background_colors: if matches!(self.chart_type, ChartType::Pie | ChartType::Doughnut) {
+                    colors.clone()
+                } else {
+                    vec![]
+                },
                 ..ChartDataset::default()
             })
             .collect();
@@ -155,7 +173,8 @@ impl LinesBasic {
 
         let config = ChartConfig {
             labels,
-            chart_type: ChartType::Line,
+            chart_type: self.chart_type,
+            stacked: self.stacked,
             datasets,
             style: self.style.clone(),
             tooltip: self.tooltip.clone(),
@@ -6,6 +6,10 @@ use chart_list::*;
 
 mod lines_basic;
 use lines_basic::*;
+mod plugins;
+use plugins::*;
+mod scales_multi_axis;
+use scales_multi_axis::*;
 mod tooltip_custom;
 use tooltip_custom::*;
 
@@ -40,6 +44,14 @@ impl ChartsExampleApp {
                 "Interaction - Zoom",
                 "Interaction - Pan",
                 "Interaction - Zoom & Pan",
+                "Bar",
+                "Bar - Stacked",
+                "Scatter",
+                "Area - Stacked",
+                "Pie",
+                "Doughnut",
+                "Scales - Time & Log",
+                "Plugins - Legend, Annotations & Crosshair",
             ]),
             chart: Box::new(LinesBasic::default()),
         }
@@ -61,6 +73,14 @@ impl ChartsExampleApp {
                 "Interaction - Zoom" => self.chart = Box::new(LinesBasic::with_zoom()),
                 "Interaction - Pan" => self.chart = Box::new(LinesBasic::with_pan()),
                 "Interaction - Zoom & Pan" => self.chart = Box::new(LinesBasic::with_zoom_and_pan()),
+                "Bar" => self.chart = Box::new(LinesBasic::with_chart_type(ChartType::Bar)),
+                "Bar - Stacked" => self.chart = Box::new(LinesBasic::with_stacked_chart_type(ChartType::Bar)),
+                "Scatter" => self.chart = Box::new(LinesBasic::with_chart_type(ChartType::Scatter)),
+                "Area - Stacked" => self.chart = Box::new(LinesBasic::with_stacked_chart_type(ChartType::Area)),
+                "Pie" => self.chart = Box::new(LinesBasic::with_chart_type(ChartType::Pie)),
+                "Doughnut" => self.chart = Box::new(LinesBasic::with_chart_type(ChartType::Doughnut)),
+                "Scales - Time & Log" => self.chart = Box::new(ScalesMultiAxisExample::default()),
+                "Plugins - Legend, Annotations & Crosshair" => self.chart = Box::new(PluginsExample::default()),
                 _ => (),
             }
             cx.request_draw();
@@ -0,0 +1,89 @@
+use std::sync::{Arc, RwLock};
+
+use zaplib::*;
+
+use crate::*;
+
+/// Two charts with a legend each, annotations, and a crosshair that is shared between them.
+pub(crate) struct PluginsExample {
+    charts: [Chart; 2],
+    data: [Vec<Vec<f32>>; 2],
+}
+
+impl Default for PluginsExample {
+    fn default() -> Self {
+        let crosshair = Arc::new(RwLock::new(ChartCrosshair::default()));
+        let annotations = Arc::new(RwLock::new(ChartAnnotations {
+            annotations: vec![
+                ChartAnnotation {
+                    kind: ChartAnnotationKind::VerticalRange { min_x: 2., max_x: 4. },
+                    label: "Maintenance".to_string(),
+                    color: vec4(0., 0., 1., 0.1),
+                    ..ChartAnnotation::default()
+                },
+                ChartAnnotation {
+                    kind: ChartAnnotationKind::HorizontalLine { y: 50. },
+                    label: "Limit".to_string(),
+                    ..ChartAnnotation::default()
+                },
+            ],
+        }));
+
+        let chart = |with_annotations: bool| {
+            let mut chart = Chart::default();
+            if with_annotations {
+                chart.plugins.push(annotations.clone());
+            }
+            chart.plugins.push(Arc::new(RwLock::new(ChartLegend::default())));
+            chart.plugins.push(crosshair.clone());
+            chart
+        };
+        let data = |offset: usize| {
+            (0..3).map(|i| (0..8).map(|x| (((x + offset) * (i + 3) * 37) % 140) as f32 - 40.).collect()).collect()
+        };
+
+        Self { charts: [chart(true), chart(false)], data: [data(0), data(5)] }
+    }
+}
+
+impl ChartExample for PluginsExample {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> ChartEvent {
+        let mut ret = ChartEvent::None;
+        for chart in &mut self.charts {
+            if let event @ (ChartEvent::PointerHover { .. } | ChartEvent::PointerDown { .. }) = chart.handle(cx, event) {
+                ret = event;
+            }
+        }
+        ret
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        let colors = [COLOR_RED, COLOR_GREEN, COLOR_BLUE];
+        let height = 0.5 * cx.get_height_left();
+        for (chart, data) in self.charts.iter_mut().zip(&self.data) {
+            cx.begin_row(Width::Fill, Height::Fix(height));
+            cx.begin_padding_box(Padding::top(20.));
+
+            let config = ChartConfig {
+                datasets: data
+                    .iter()
+                    .enumerate()
+                    .map(|(i, data)| ChartDataset {
+                        label: format!("Sensor {}", i),
+                        data: ChartData::from_values(data),
+                        border_color: colors[i],
+                        point_background_color: colors[i],
+                        point_radius: 4.,
+                        ..ChartDataset::default()
+                    })
+                    .collect(),
+                style: CHART_STYLE_LIGHT,
+                ..ChartConfig::default()
+            };
+            chart.draw(cx, &config);
+
+            cx.end_padding_box();
+            cx.end_row();
+        }
+    }
+}
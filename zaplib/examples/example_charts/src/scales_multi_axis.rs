@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use zaplib::*;
+
+use crate::*;
+
+const MS_PER_HOUR: f32 = 60. * 60. * 1000.;
+
+/// Hourly measurements over a week, with a time x axis, a linear axis on the left and a logarithmic axis on the right.
+pub(crate) struct ScalesMultiAxisExample {
+    chart: Chart,
+    temperature: Vec<Vec2>,
+    requests: Vec<Vec2>,
+}
+
+impl Default for ScalesMultiAxisExample {
+    fn default() -> Self {
+        let hours = 0..7 * 24;
+        Self {
+            chart: Chart::default(),
+            temperature: hours
+                .clone()
+                .map(|hour| vec2(hour as f32 * MS_PER_HOUR, 15. + 8. * (hour as f32 * std::f32::consts::PI / 12.).sin()))
+                .collect(),
+            // Grows by a factor of 10 every other day.
+            requests: hours.map(|hour| vec2(hour as f32 * MS_PER_HOUR, 10_f32.powf(1. + hour as f32 / 48.))).collect(),
+        }
+    }
+}
+
+impl ChartExample for ScalesMultiAxisExample {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> ChartEvent {
+        self.chart.handle(cx, event)
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        cx.begin_padding_box(Padding::top(20.));
+
+        let mut scales = HashMap::new();
+        scales.insert(
+            "x".to_string(),
+            // 2024-01-01 00:00:00 UTC
+            ChartScale { scale_type: ChartScaleType::Time, time_origin: 1_704_067_200_000., ..ChartScale::default() },
+        );
+        scales.insert(
+            "requests".to_string(),
+            ChartScale { scale_type: ChartScaleType::Logarithmic, position: ChartAxisPosition::Right, ..ChartScale::default() },
+        );
+
+        let config = ChartConfig {
+            datasets: vec![
+                ChartDataset {
+                    label: "Temperature".to_string(),
+                    data: ChartData::from_pairs(&self.temperature),
+                    border_color: COLOR_RED,
+                    point_background_color: COLOR_RED,
+                    point_radius: 0.,
+                    ..ChartDataset::default()
+                },
+                ChartDataset {
+                    label: "Requests".to_string(),
+                    data: ChartData::from_pairs(&self.requests),
+                    border_color: COLOR_BLUE,
+                    point_background_color: COLOR_BLUE,
+                    point_radius: 0.,
+                    y_axis_id: "requests".to_string(),
+                    ..ChartDataset::default()
+                },
+            ],
+            scales,
+            style: CHART_STYLE_LIGHT,
+            ..ChartConfig::default()
+        };
+        self.chart.draw(cx, &config);
+
+        cx.end_padding_box();
+    }
+}
@@ -308,8 +308,8 @@ impl LogoApp {
             {
                 Self::update_token_chunks(&mut self.text_buffer);
                 self.text_editor.begin_text_editor(cx, &self.text_buffer, None);
-                for (index, token_chunk) in self.text_buffer.token_chunks.iter_mut().enumerate() {
-                    self.text_editor.draw_chunk(cx, index, &self.text_buffer.flat_text, token_chunk, &self.text_buffer.markers);
+                for token in self.text_buffer.iter_token_chunks() {
+                    self.text_editor.draw_chunk(cx, &token, &self.text_buffer.markers);
                 }
                 self.text_editor.end_text_editor(cx, &self.text_buffer);
             }
@@ -325,28 +325,17 @@ impl LogoApp {
         if text_buffer.needs_token_chunks() && !text_buffer.lines.is_empty() {
             let mut state = TokenizerState::new(&text_buffer.lines);
             let mut tokenizer = MprsTokenizer::default();
-            let mut pair_stack = Vec::new();
+            let mut chunk = Vec::new();
             loop {
-                let offset = text_buffer.flat_text.len();
-                let token_type = tokenizer.next_token(&mut state, &mut text_buffer.flat_text, &text_buffer.token_chunks);
-                if TokenChunk::push_with_pairing(
-                    &mut text_buffer.token_chunks,
-                    &mut pair_stack,
-                    state.next,
-                    offset,
-                    text_buffer.flat_text.len(),
-                    token_type,
-                ) {
-                    text_buffer.was_invalid_pair = true;
-                }
+                chunk.clear();
+                let token_type = tokenizer.next_token(&mut state, &mut chunk, text_buffer.token_chunks.lookback());
+                text_buffer.token_chunks.push(token_type, chunk.len(), state.next);
 
                 if token_type == TokenType::Eof {
                     break;
                 }
             }
-            if !pair_stack.is_empty() {
-                text_buffer.was_invalid_pair = true;
-            }
+            text_buffer.was_invalid_pair = text_buffer.token_chunks.has_invalid_pair();
         }
     }
 }
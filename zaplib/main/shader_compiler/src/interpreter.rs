@@ -0,0 +1,1095 @@
+//! Runs a type-checked [`ShaderAst`] directly on the CPU, instead of generating code for a GPU.
+//!
+//! This is used by the headless (software) rendering backend, so that the exact same shaders
+//! (written using `code_fragment!`) can be rendered in tests or on machines without a GPU. It is
+//! of course much slower than running on real hardware, so it's only meant for small framebuffers.
+//!
+//! All scalars, vectors, and matrices are represented as a flat list of up to 16 floats (with
+//! bools as `0.0`/`1.0`, and ints as whole floats). We rely on the types that the type checker
+//! stored on every [`Expr`] to know how to interpret them. Matrices are column-major, just like
+//! in GLSL.
+
+use crate::env::VarKind;
+use crate::ident::{Ident, IdentPath};
+use crate::lit::Lit;
+use crate::math::{Mat4, Vec2, Vec4};
+use crate::shaderast::*;
+use crate::swizzle::Swizzle;
+use crate::ty::{Ty, TyLit};
+use crate::val::Val;
+use std::collections::HashMap;
+
+/// The inputs to a [`CpuShader`] that are shared between all vertices and pixels of a draw call.
+pub struct CpuShaderInputs<'a> {
+    /// Uniforms in the `pass` uniform block, laid out the same way as for the OpenGL backend.
+    pub pass_uniforms: &'a [f32],
+    /// Uniforms in the `view` uniform block.
+    pub view_uniforms: &'a [f32],
+    /// Uniforms in the `draw` uniform block.
+    pub draw_uniforms: &'a [f32],
+    /// Uniforms without a block, as set by the user.
+    pub user_uniforms: &'a [f32],
+    /// Samples the texture with the given index (in order of texture declarations) at the given
+    /// position. Should return a premultiplied RGBA colour.
+    pub sample_texture: &'a dyn Fn(usize, Vec2) -> Vec4,
+}
+
+/// A shader that can be run on the CPU; see [`crate::interpreter`].
+#[derive(Clone, Debug)]
+pub struct CpuShader {
+    shader_ast: ShaderAst,
+    /// Types of geometry, instance, and varying declarations, which are stored as "globals".
+    global_tys: Vec<Ty>,
+    global_indices: HashMap<Ident, usize>,
+    /// (global index, offset into the geometry slice).
+    geometries: Vec<(usize, usize)>,
+    /// (global index, offset into the instance slice).
+    instances: Vec<(usize, usize)>,
+    /// (global index, offset into the varyings slice). Contains everything that has to be passed
+    /// from the vertex shader to the pixel shader, so geometries and instances too.
+    varyings: Vec<(usize, usize)>,
+    varyings_len: usize,
+    uniforms: HashMap<Ident, UniformSlot>,
+    textures: HashMap<Ident, usize>,
+    consts: HashMap<Ident, Value>,
+    fns: HashMap<IdentPath, usize>,
+    /// Index of every field, keyed by (struct, field).
+    struct_fields: HashMap<(Ident, Ident), usize>,
+    /// Decl indices of the `vertex` and `pixel` functions.
+    entry_points: [Option<usize>; 2],
+    uses_derivatives: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum UniformBlock {
+    Pass,
+    View,
+    Draw,
+    User,
+}
+
+#[derive(Clone, Debug)]
+struct UniformSlot {
+    block: Option<UniformBlock>,
+    offset: usize,
+    ty: Ty,
+}
+
+impl CpuShader {
+    /// Prepare a [`ShaderAst`] for running on the CPU. The AST must already have been
+    /// type-checked, which is the case for anything that comes out of
+    /// [`crate::generate_shader_ast::generate_shader_ast`].
+    pub fn new(shader_ast: ShaderAst) -> Self {
+        let mut global_tys = Vec::new();
+        let mut global_indices = HashMap::new();
+        let mut geometries = Vec::new();
+        let mut instances = Vec::new();
+        let mut varying_globals = Vec::new();
+        let mut geometry_offset = 0;
+        let mut instance_offset = 0;
+        let mut block_offsets = [0; 4];
+        let mut uniforms = HashMap::new();
+        let mut textures = HashMap::new();
+        let mut fns = HashMap::new();
+        let mut struct_fields = HashMap::new();
+
+        let mut add_global = |ident: Ident, ty: Ty| {
+            global_indices.insert(ident, global_tys.len());
+            global_tys.push(ty);
+            global_tys.len() - 1
+        };
+
+        for (decl_index, decl) in shader_ast.decls.iter().enumerate() {
+            match decl {
+                Decl::Geometry(decl) => {
+                    let ty = decl.ty_expr.ty.borrow().clone().unwrap();
+                    let size = ty.size();
+                    let index = add_global(decl.ident, ty);
+                    geometries.push((index, geometry_offset));
+                    geometry_offset += size;
+                    if decl.is_used_in_fragment_shader.get().unwrap_or(false) {
+                        varying_globals.push(index);
+                    }
+                }
+                Decl::Instance(decl) => {
+                    let ty = decl.ty_expr.ty.borrow().clone().unwrap();
+                    let size = ty.size();
+                    let index = add_global(decl.ident, ty);
+                    instances.push((index, instance_offset));
+                    instance_offset += size;
+                    if decl.is_used_in_fragment_shader.get().unwrap_or(false) {
+                        varying_globals.push(index);
+                    }
+                }
+                Decl::Varying(decl) => {
+                    let ty = decl.ty_expr.ty.borrow().clone().unwrap();
+                    varying_globals.push(add_global(decl.ident, ty));
+                }
+                Decl::Uniform(decl) => {
+                    let ty = decl.ty_expr.ty.borrow().clone().unwrap();
+                    let block = match decl.block_ident {
+                        None => Some(UniformBlock::User),
+                        Some(block_ident) => block_ident.with(|name| match name {
+                            "pass" => Some(UniformBlock::Pass),
+                            "view" => Some(UniformBlock::View),
+                            "draw" => Some(UniformBlock::Draw),
+                            _ => None,
+                        }),
+                    };
+                    let mut offset = 0;
+                    if let Some(block) = block {
+                        // Same alignment rule as `set_uniform_buffer` in the OpenGL backend.
+                        let block_offset = &mut block_offsets[block as usize];
+                        let size = ty.size();
+                        if (*block_offset & 3) != 0 && (*block_offset & 3) + size > 4 {
+                            *block_offset += 4 - (*block_offset & 3);
+                        }
+                        offset = *block_offset;
+                        *block_offset += size;
+                    }
+                    uniforms.insert(decl.ident, UniformSlot { block, offset, ty });
+                }
+                Decl::Texture(decl) => {
+                    let index = textures.len();
+                    textures.insert(decl.ident, index);
+                }
+                Decl::Fn(decl) => {
+                    fns.insert(decl.ident_path, decl_index);
+                }
+                Decl::Struct(decl) => {
+                    for (field_index, field) in decl.fields.iter().enumerate() {
+                        struct_fields.insert((decl.ident, field.ident), field_index);
+                    }
+                }
+                Decl::Const(_) => {}
+            }
+        }
+
+        let mut varyings = Vec::new();
+        let mut varyings_len = 0;
+        for index in varying_globals {
+            varyings.push((index, varyings_len));
+            varyings_len += global_tys[index].size();
+        }
+
+        let entry_points = ["vertex", "pixel"].map(|name| fns.get(&IdentPath::from_str(name)).copied());
+        let uses_derivatives = shader_ast.decls.iter().any(|decl| match decl {
+            Decl::Fn(decl) => block_uses_derivatives(&decl.block),
+            _ => false,
+        });
+
+        let mut shader = CpuShader {
+            shader_ast,
+            global_tys,
+            global_indices,
+            geometries,
+            instances,
+            varyings,
+            varyings_len,
+            uniforms,
+            textures,
+            consts: HashMap::new(),
+            fns,
+            struct_fields,
+            entry_points,
+            uses_derivatives,
+        };
+
+        let mut consts = HashMap::new();
+        {
+            let sample_texture = |_, _| Vec4::default();
+            let inputs = CpuShaderInputs {
+                pass_uniforms: &[],
+                view_uniforms: &[],
+                draw_uniforms: &[],
+                user_uniforms: &[],
+                sample_texture: &sample_texture,
+            };
+            let mut interp = Interp::new(&shader, &inputs, Derivatives::Ignore);
+            for decl in &shader.shader_ast.decls {
+                if let Decl::Const(decl) = decl {
+                    consts.insert(decl.ident, interp.eval(&decl.expr));
+                }
+            }
+        }
+        shader.consts = consts;
+        shader
+    }
+
+    /// The number of floats that the vertex shader writes to the varyings slice, and that the
+    /// pixel shader reads from it.
+    pub fn varyings_len(&self) -> usize {
+        self.varyings_len
+    }
+
+    /// Whether the shader uses `dFdx` or `dFdy`. If not, there is no need to compute the
+    /// varyings of neighbouring pixels when calling [`CpuShader::run_pixel`].
+    pub fn uses_derivatives(&self) -> bool {
+        self.uses_derivatives
+    }
+
+    /// Runs the vertex shader for a single vertex, writing the varyings to `varyings_out` (which
+    /// should have a length of at least [`CpuShader::varyings_len`]). Returns the position in clip
+    /// space.
+    pub fn run_vertex(&self, inputs: &CpuShaderInputs, geometry: &[f32], instance: &[f32], varyings_out: &mut [f32]) -> Vec4 {
+        let mut interp = Interp::new(self, inputs, Derivatives::Ignore);
+        for &(index, offset) in &self.geometries {
+            interp.globals[index] = Value::Num(Num::read(geometry, offset, self.global_tys[index].size()));
+        }
+        for &(index, offset) in &self.instances {
+            interp.globals[index] = Value::Num(Num::read(instance, offset, self.global_tys[index].size()));
+        }
+        let position = interp.call_entry_point(0);
+        for &(index, offset) in &self.varyings {
+            let num = interp.globals[index].num();
+            varyings_out[offset..offset + num.len].copy_from_slice(num.as_slice());
+        }
+        position.num().to_vec4()
+    }
+
+    /// Runs the pixel shader for a single pixel, given the interpolated varyings. Returns the
+    /// (premultiplied) colour.
+    ///
+    /// If the shader [uses derivatives][CpuShader::uses_derivatives], you can pass in the varyings
+    /// of the pixels one to the right and one below, which are used to compute `dFdx` and `dFdy`
+    /// (similar to how a GPU runs pixels in 2x2 quads). Otherwise derivatives are always zero.
+    pub fn run_pixel(&self, inputs: &CpuShaderInputs, varyings: &[f32], neighbours: Option<(&[f32], &[f32])>) -> Vec4 {
+        let derivatives = match neighbours {
+            Some((varyings_right, varyings_below)) if self.uses_derivatives => {
+                let record = || Derivatives::Record { dfdx: Vec::new(), dfdy: Vec::new() };
+                let (_, right) = self.run_pixel_lane(inputs, varyings_right, record());
+                let (_, below) = self.run_pixel_lane(inputs, varyings_below, record());
+                match (right, below) {
+                    (Derivatives::Record { dfdx, .. }, Derivatives::Record { dfdy, .. }) => {
+                        Derivatives::Replay { dfdx, dfdy, dfdx_index: 0, dfdy_index: 0 }
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            _ => Derivatives::Ignore,
+        };
+        self.run_pixel_lane(inputs, varyings, derivatives).0
+    }
+
+    fn run_pixel_lane(&self, inputs: &CpuShaderInputs, varyings: &[f32], derivatives: Derivatives) -> (Vec4, Derivatives) {
+        let mut interp = Interp::new(self, inputs, derivatives);
+        for &(index, offset) in &self.varyings {
+            interp.globals[index] = Value::Num(Num::read(varyings, offset, self.global_tys[index].size()));
+        }
+        let color = interp.call_entry_point(1).num().to_vec4();
+        (color, interp.derivatives)
+    }
+}
+
+fn block_uses_derivatives(block: &Block) -> bool {
+    block.stmts.iter().any(|stmt| match stmt {
+        Stmt::Break { .. } | Stmt::Continue { .. } => false,
+        Stmt::For { from_expr, to_expr, step_expr, block, .. } => {
+            expr_uses_derivatives(from_expr)
+                || expr_uses_derivatives(to_expr)
+                || step_expr.as_ref().map_or(false, expr_uses_derivatives)
+                || block_uses_derivatives(block)
+        }
+        Stmt::If { expr, block_if_true, block_if_false, .. } => {
+            expr_uses_derivatives(expr)
+                || block_uses_derivatives(block_if_true)
+                || block_if_false.as_ref().map_or(false, |block| block_uses_derivatives(block))
+        }
+        Stmt::Let { expr, .. } | Stmt::Return { expr, .. } => expr.as_ref().map_or(false, expr_uses_derivatives),
+        Stmt::Expr { expr, .. } => expr_uses_derivatives(expr),
+    })
+}
+
+fn expr_uses_derivatives(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Cond { expr, expr_if_true, expr_if_false, .. } => {
+            expr_uses_derivatives(expr) || expr_uses_derivatives(expr_if_true) || expr_uses_derivatives(expr_if_false)
+        }
+        ExprKind::Bin { left_expr, right_expr, .. } => expr_uses_derivatives(left_expr) || expr_uses_derivatives(right_expr),
+        ExprKind::Un { expr, .. } | ExprKind::Field { expr, .. } => expr_uses_derivatives(expr),
+        ExprKind::Index { expr, index_expr, .. } => expr_uses_derivatives(expr) || expr_uses_derivatives(index_expr),
+        ExprKind::Call { ident_path, arg_exprs, .. } => {
+            let is_derivative =
+                ident_path.get_single().map_or(false, |ident| ident.with(|name| name == "dFdx" || name == "dFdy"));
+            is_derivative || arg_exprs.iter().any(expr_uses_derivatives)
+        }
+        ExprKind::MethodCall { arg_exprs, .. } | ExprKind::ConsCall { arg_exprs, .. } => {
+            arg_exprs.iter().any(expr_uses_derivatives)
+        }
+        ExprKind::Var { .. } | ExprKind::Lit { .. } => false,
+    }
+}
+
+/// A scalar, vector, or matrix; see [`crate::interpreter`].
+#[derive(Clone, Copy, Debug)]
+struct Num {
+    len: usize,
+    v: [f32; 16],
+}
+
+impl Num {
+    fn zero(len: usize) -> Self {
+        Num { len, v: [0.0; 16] }
+    }
+
+    fn scalar(value: f32) -> Self {
+        let mut num = Num::zero(1);
+        num.v[0] = value;
+        num
+    }
+
+    fn from_bool(value: bool) -> Self {
+        Num::scalar(if value { 1.0 } else { 0.0 })
+    }
+
+    fn from_slice(slice: &[f32]) -> Self {
+        let mut num = Num::zero(slice.len());
+        num.v[..slice.len()].copy_from_slice(slice);
+        num
+    }
+
+    /// Reads a value from a slice, or returns zeroes if the slice is too short.
+    fn read(slice: &[f32], offset: usize, len: usize) -> Self {
+        match slice.get(offset..offset + len) {
+            Some(slice) => Num::from_slice(slice),
+            None => Num::zero(len),
+        }
+    }
+
+    fn as_slice(&self) -> &[f32] {
+        &self.v[..self.len]
+    }
+
+    /// Get a component, broadcasting scalars.
+    fn get(&self, index: usize) -> f32 {
+        if self.len == 1 {
+            self.v[0]
+        } else {
+            self.v[index]
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        self.v[0] != 0.0
+    }
+
+    fn map(self, f: impl Fn(f32) -> f32) -> Self {
+        let mut num = self;
+        for value in &mut num.v[..num.len] {
+            *value = f(*value);
+        }
+        num
+    }
+
+    fn zip(a: Self, b: Self, f: impl Fn(f32, f32) -> f32) -> Self {
+        let mut num = Num::zero(a.len.max(b.len));
+        for i in 0..num.len {
+            num.v[i] = f(a.get(i), b.get(i));
+        }
+        num
+    }
+
+    fn zip3(a: Self, b: Self, c: Self, f: impl Fn(f32, f32, f32) -> f32) -> Self {
+        let mut num = Num::zero(a.len.max(b.len).max(c.len));
+        for i in 0..num.len {
+            num.v[i] = f(a.get(i), b.get(i), c.get(i));
+        }
+        num
+    }
+
+    fn dot(a: Self, b: Self) -> f32 {
+        (0..a.len.max(b.len)).map(|i| a.get(i) * b.get(i)).sum()
+    }
+
+    fn to_vec4(self) -> Vec4 {
+        Vec4 { x: self.v[0], y: self.v[1], z: self.v[2], w: self.v[3] }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Num(Num),
+    /// Structs and arrays.
+    Composite(Vec<Value>),
+    /// Index into [`CpuShaderInputs::sample_texture`].
+    Texture(usize),
+}
+
+impl Value {
+    fn num(&self) -> Num {
+        match self {
+            Value::Num(num) => *num,
+            _ => panic!("Expected a scalar, vector, or matrix value"),
+        }
+    }
+}
+
+impl From<&Val> for Value {
+    fn from(val: &Val) -> Self {
+        Value::Num(match *val {
+            Val::Bool(value) => Num::from_bool(value),
+            Val::Int(value) => Num::scalar(value as f32),
+            Val::Float(value) => Num::scalar(value),
+            Val::Vec4(value) => Num::from_slice(&[value.x, value.y, value.z, value.w]),
+        })
+    }
+}
+
+fn matrix_dim(ty: &Ty) -> Option<usize> {
+    match ty {
+        Ty::Mat2 => Some(2),
+        Ty::Mat3 => Some(3),
+        Ty::Mat4 => Some(4),
+        _ => None,
+    }
+}
+
+/// Convert floats to the representation of the target type, e.g. when constructing an `ivec2`
+/// from a `vec2`.
+fn convert_num(num: Num, ty: &Ty) -> Num {
+    match ty {
+        Ty::Int | Ty::Ivec2 | Ty::Ivec3 | Ty::Ivec4 => num.map(f32::trunc),
+        Ty::Bool | Ty::Bvec2 | Ty::Bvec3 | Ty::Bvec4 => num.map(|value| if value != 0.0 { 1.0 } else { 0.0 }),
+        _ => num,
+    }
+}
+
+/// Derivatives are computed by running the neighbouring pixels first ([`Derivatives::Record`]),
+/// keeping track of every argument passed to `dFdx` and `dFdy`, and then running the actual pixel
+/// ([`Derivatives::Replay`]), subtracting its own arguments from the recorded ones in order.
+enum Derivatives {
+    Ignore,
+    Record { dfdx: Vec<Num>, dfdy: Vec<Num> },
+    Replay { dfdx: Vec<Num>, dfdy: Vec<Num>, dfdx_index: usize, dfdy_index: usize },
+}
+
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+struct Interp<'a> {
+    shader: &'a CpuShader,
+    inputs: &'a CpuShaderInputs<'a>,
+    globals: Vec<Value>,
+    locals: Vec<(Ident, Value)>,
+    /// Start of the locals of the current function call.
+    frame_start: usize,
+    derivatives: Derivatives,
+}
+
+impl<'a> Interp<'a> {
+    fn new(shader: &'a CpuShader, inputs: &'a CpuShaderInputs<'a>, derivatives: Derivatives) -> Self {
+        let globals = shader.global_tys.iter().map(|ty| Value::Num(Num::zero(ty.size()))).collect();
+        Interp { shader, inputs, globals, locals: Vec::new(), frame_start: 0, derivatives }
+    }
+
+    /// Call `vertex` (0) or `pixel` (1).
+    fn call_entry_point(&mut self, entry_point: usize) -> Value {
+        let shader = self.shader;
+        let decl_index = shader.entry_points[entry_point].expect("Shader is missing an entry point");
+        match &shader.shader_ast.decls[decl_index] {
+            Decl::Fn(decl) => self.call_fn(decl, &[]),
+            _ => unreachable!(),
+        }
+    }
+
+    fn zero_value(&self, ty: &Ty) -> Value {
+        match ty {
+            Ty::Struct { ident } => {
+                let struct_decl = self.shader.shader_ast.find_struct_decl(*ident).unwrap();
+                Value::Composite(
+                    struct_decl.fields.iter().map(|field| self.zero_value(field.ty_expr.ty.borrow().as_ref().unwrap())).collect(),
+                )
+            }
+            Ty::Array { elem_ty, len } => Value::Composite(vec![self.zero_value(elem_ty); *len]),
+            Ty::Texture2D => Value::Texture(0),
+            _ => Value::Num(Num::zero(ty.size())),
+        }
+    }
+
+    fn call_fn(&mut self, decl: &'a FnDecl, arg_exprs: &'a [Expr]) -> Value {
+        let args: Vec<Value> = arg_exprs.iter().map(|arg_expr| self.eval(arg_expr)).collect();
+        let frame_start = self.locals.len();
+        for (param, arg) in decl.params.iter().zip(args) {
+            self.locals.push((param.ident, arg));
+        }
+        let prev_frame_start = std::mem::replace(&mut self.frame_start, frame_start);
+        let result = match self.exec_block(&decl.block) {
+            Flow::Return(value) => value,
+            _ => Value::Num(Num::zero(0)),
+        };
+        let outs: Vec<(usize, Value)> = decl
+            .params
+            .iter()
+            .enumerate()
+            .filter(|(_, param)| param.is_inout)
+            .map(|(index, _)| (index, self.locals[frame_start + index].1.clone()))
+            .collect();
+        self.locals.truncate(frame_start);
+        self.frame_start = prev_frame_start;
+        for (index, value) in outs {
+            self.store(&arg_exprs[index], value);
+        }
+        result
+    }
+
+    fn exec_block(&mut self, block: &'a Block) -> Flow {
+        let locals_len = self.locals.len();
+        let mut flow = Flow::Normal;
+        for stmt in &block.stmts {
+            flow = self.exec_stmt(stmt);
+            if !matches!(flow, Flow::Normal) {
+                break;
+            }
+        }
+        self.locals.truncate(locals_len);
+        flow
+    }
+
+    fn exec_stmt(&mut self, stmt: &'a Stmt) -> Flow {
+        match stmt {
+            Stmt::Break { .. } => Flow::Break,
+            Stmt::Continue { .. } => Flow::Continue,
+            Stmt::For { ident, from_expr, to_expr, step_expr, block, .. } => {
+                let const_int = |expr: &Expr| expr.const_val.borrow().as_ref().unwrap().as_ref().unwrap().to_int().unwrap();
+                let from = const_int(from_expr);
+                let to = const_int(to_expr);
+                let step = step_expr.as_ref().map_or(if from <= to { 1 } else { -1 }, const_int);
+                // Same loop bounds as the generated GLSL.
+                let mut i = if from <= to { from } else { from - 1 };
+                let local_index = self.locals.len();
+                self.locals.push((*ident, Value::Num(Num::scalar(i as f32))));
+                let mut flow = Flow::Normal;
+                while (from <= to && i < to) || (from > to && i >= to) {
+                    self.locals[local_index].1 = Value::Num(Num::scalar(i as f32));
+                    match self.exec_block(block) {
+                        Flow::Break => break,
+                        Flow::Return(value) => {
+                            flow = Flow::Return(value);
+                            break;
+                        }
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                    i += step;
+                }
+                self.locals.truncate(local_index);
+                flow
+            }
+            Stmt::If { expr, block_if_true, block_if_false, .. } => {
+                if self.eval(expr).num().is_true() {
+                    self.exec_block(block_if_true)
+                } else if let Some(block_if_false) = block_if_false {
+                    self.exec_block(block_if_false)
+                } else {
+                    Flow::Normal
+                }
+            }
+            Stmt::Let { ty, ident, expr, .. } => {
+                let value = match expr {
+                    Some(expr) => self.eval(expr),
+                    None => self.zero_value(ty.borrow().as_ref().unwrap()),
+                };
+                self.locals.push((*ident, value));
+                Flow::Normal
+            }
+            Stmt::Return { expr, .. } => Flow::Return(expr.as_ref().map_or(Value::Num(Num::zero(0)), |expr| self.eval(expr))),
+            Stmt::Expr { expr, .. } => {
+                self.eval(expr);
+                Flow::Normal
+            }
+        }
+    }
+
+    fn find_local(&mut self, ident: Ident) -> &mut Value {
+        let frame_start = self.frame_start;
+        &mut self.locals[frame_start..].iter_mut().rev().find(|(local_ident, _)| *local_ident == ident).unwrap().1
+    }
+
+    fn eval(&mut self, expr: &'a Expr) -> Value {
+        if let Some(Some(val)) = expr.const_val.borrow().as_ref() {
+            return Value::from(val);
+        }
+        match &expr.kind {
+            ExprKind::Cond { expr, expr_if_true, expr_if_false, .. } => {
+                if self.eval(expr).num().is_true() {
+                    self.eval(expr_if_true)
+                } else {
+                    self.eval(expr_if_false)
+                }
+            }
+            ExprKind::Bin { op, left_expr, right_expr, .. } => self.eval_bin(*op, left_expr, right_expr),
+            ExprKind::Un { op, expr, .. } => {
+                let num = self.eval(expr).num();
+                Value::Num(match op {
+                    UnOp::Not => num.map(|value| if value != 0.0 { 0.0 } else { 1.0 }),
+                    UnOp::Neg => num.map(|value| -value),
+                })
+            }
+            ExprKind::MethodCall { ident, arg_exprs, .. } => {
+                let struct_ident = match arg_exprs[0].ty.borrow().as_ref().unwrap() {
+                    Ty::Struct { ident } => *ident,
+                    _ => panic!(),
+                };
+                self.eval_call(IdentPath::from_two(struct_ident, *ident), arg_exprs, expr)
+            }
+            ExprKind::Field { expr: base_expr, field_ident, .. } => {
+                if let Some(value) = self.place(expr) {
+                    return value.clone();
+                }
+                let base = self.eval(base_expr);
+                let base_ty = base_expr.ty.borrow();
+                match base_ty.as_ref().unwrap() {
+                    Ty::Struct { ident } => match base {
+                        Value::Composite(mut fields) => fields.swap_remove(self.struct_field_index(*ident, *field_ident)),
+                        _ => unreachable!(),
+                    },
+                    _ => {
+                        let base = base.num();
+                        let swizzle = Swizzle::parse(*field_ident).unwrap();
+                        let mut num = Num::zero(swizzle.len());
+                        for (i, &index) in swizzle.iter().enumerate() {
+                            num.v[i] = base.v[index];
+                        }
+                        Value::Num(num)
+                    }
+                }
+            }
+            ExprKind::Index { expr: base_expr, index_expr, .. } => {
+                if let Some(value) = self.place(expr) {
+                    return value.clone();
+                }
+                let base = self.eval(base_expr);
+                let index = self.eval(index_expr).num().v[0] as usize;
+                let base_ty = base_expr.ty.borrow();
+                match (base, base_ty.as_ref().unwrap()) {
+                    (Value::Composite(mut elems), _) => elems.swap_remove(index),
+                    (Value::Num(num), ty) => match matrix_dim(ty) {
+                        Some(dim) => Value::Num(Num::from_slice(&num.v[index * dim..(index + 1) * dim])),
+                        None => Value::Num(Num::scalar(num.v[index])),
+                    },
+                    (Value::Texture(_), _) => panic!(),
+                }
+            }
+            ExprKind::Call { ident_path, arg_exprs, .. } => self.eval_call(*ident_path, arg_exprs, expr),
+            ExprKind::ConsCall { ty_lit, arg_exprs, .. } => self.eval_cons_call(*ty_lit, arg_exprs),
+            ExprKind::Var { kind, ident_path, .. } => {
+                let ident = ident_path.get_single().unwrap();
+                match kind.get().unwrap() {
+                    VarKind::Local => self.find_local(ident).clone(),
+                    VarKind::Geometry | VarKind::Instance | VarKind::Varying => {
+                        self.globals[self.shader.global_indices[&ident]].clone()
+                    }
+                    VarKind::Const => self.shader.consts[&ident].clone(),
+                    VarKind::Texture => Value::Texture(self.shader.textures[&ident]),
+                    VarKind::Uniform => {
+                        let slot = &self.shader.uniforms[&ident];
+                        let uniforms = match slot.block {
+                            Some(UniformBlock::Pass) => self.inputs.pass_uniforms,
+                            Some(UniformBlock::View) => self.inputs.view_uniforms,
+                            Some(UniformBlock::Draw) => self.inputs.draw_uniforms,
+                            Some(UniformBlock::User) => self.inputs.user_uniforms,
+                            None => &[],
+                        };
+                        Value::Num(Num::read(uniforms, slot.offset, slot.ty.size()))
+                    }
+                }
+            }
+            ExprKind::Lit { lit, .. } => Value::Num(match *lit {
+                Lit::Bool(value) => Num::from_bool(value),
+                Lit::Int(value) => Num::scalar(value as f32),
+                Lit::Float(value) => Num::scalar(value),
+                Lit::Vec4(value) => Num::from_slice(&[value.x, value.y, value.z, value.w]),
+            }),
+        }
+    }
+
+    fn struct_field_index(&self, struct_ident: Ident, field_ident: Ident) -> usize {
+        self.shader.struct_fields[&(struct_ident, field_ident)]
+    }
+
+    /// If `expr` is a (chain of) struct field or array element access on a local or global
+    /// variable, get a reference to it directly. This avoids copying entire structs (such as `Df`)
+    /// for every field access, which would otherwise dominate the running time.
+    fn place(&mut self, expr: &'a Expr) -> Option<&mut Value> {
+        let mut path = Vec::new();
+        let mut root = expr;
+        loop {
+            match &root.kind {
+                ExprKind::Field { expr: base_expr, field_ident, .. } => match base_expr.ty.borrow().as_ref().unwrap() {
+                    Ty::Struct { ident } => path.push(self.struct_field_index(*ident, *field_ident)),
+                    _ => return None,
+                },
+                ExprKind::Index { expr: base_expr, index_expr, .. } => match base_expr.ty.borrow().as_ref().unwrap() {
+                    Ty::Array { .. } => path.push(self.eval(index_expr).num().v[0] as usize),
+                    _ => return None,
+                },
+                ExprKind::Var { .. } => break,
+                _ => return None,
+            }
+            root = match &root.kind {
+                ExprKind::Field { expr, .. } | ExprKind::Index { expr, .. } => expr,
+                _ => unreachable!(),
+            };
+        }
+        let mut value = match &root.kind {
+            ExprKind::Var { kind, ident_path, .. } => {
+                let ident = ident_path.get_single().unwrap();
+                match kind.get().unwrap() {
+                    VarKind::Local => self.find_local(ident),
+                    VarKind::Geometry | VarKind::Instance | VarKind::Varying => {
+                        &mut self.globals[self.shader.global_indices[&ident]]
+                    }
+                    _ => return None,
+                }
+            }
+            _ => unreachable!(),
+        };
+        for &index in path.iter().rev() {
+            value = match value {
+                Value::Composite(elems) => &mut elems[index],
+                _ => unreachable!(),
+            };
+        }
+        Some(value)
+    }
+
+    /// Write a value to an lvalue expression (for assignments and `inout` parameters).
+    fn store(&mut self, expr: &'a Expr, value: Value) {
+        if let Some(place) = self.place(expr) {
+            *place = value;
+            return;
+        }
+        match &expr.kind {
+            ExprKind::Var { kind, ident_path, .. } => {
+                let ident = ident_path.get_single().unwrap();
+                match kind.get().unwrap() {
+                    VarKind::Local => *self.find_local(ident) = value,
+                    VarKind::Geometry | VarKind::Instance | VarKind::Varying => {
+                        let index = self.shader.global_indices[&ident];
+                        self.globals[index] = value;
+                    }
+                    _ => panic!("Cannot assign to {}", ident),
+                }
+            }
+            ExprKind::Field { expr: base_expr, field_ident, .. } => {
+                let mut base = self.eval(base_expr);
+                let struct_ident = match base_expr.ty.borrow().as_ref().unwrap() {
+                    Ty::Struct { ident } => Some(*ident),
+                    _ => None,
+                };
+                match (&mut base, struct_ident) {
+                    (Value::Composite(fields), Some(struct_ident)) => {
+                        fields[self.struct_field_index(struct_ident, *field_ident)] = value;
+                    }
+                    (Value::Num(num), None) => {
+                        let value = value.num();
+                        for (i, &index) in Swizzle::parse(*field_ident).unwrap().iter().enumerate() {
+                            num.v[index] = value.get(i);
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+                self.store(base_expr, base);
+            }
+            ExprKind::Index { expr: base_expr, index_expr, .. } => {
+                let mut base = self.eval(base_expr);
+                let index = self.eval(index_expr).num().v[0] as usize;
+                let dim = matrix_dim(base_expr.ty.borrow().as_ref().unwrap());
+                match (&mut base, dim) {
+                    (Value::Composite(elems), _) => elems[index] = value,
+                    (Value::Num(num), Some(dim)) => {
+                        num.v[index * dim..(index + 1) * dim].copy_from_slice(value.num().as_slice());
+                    }
+                    (Value::Num(num), None) => num.v[index] = value.num().v[0],
+                    (Value::Texture(_), _) => panic!(),
+                }
+                self.store(base_expr, base);
+            }
+            _ => panic!("Expression is not assignable"),
+        }
+    }
+
+    fn eval_bin(&mut self, op: BinOp, left_expr: &'a Expr, right_expr: &'a Expr) -> Value {
+        let arith_op = match op {
+            BinOp::Assign => {
+                let value = self.eval(right_expr);
+                self.store(left_expr, value.clone());
+                return value;
+            }
+            BinOp::Or => {
+                let result = self.eval(left_expr).num().is_true() || self.eval(right_expr).num().is_true();
+                return Value::Num(Num::from_bool(result));
+            }
+            BinOp::And => {
+                let result = self.eval(left_expr).num().is_true() && self.eval(right_expr).num().is_true();
+                return Value::Num(Num::from_bool(result));
+            }
+            BinOp::AddAssign => BinOp::Add,
+            BinOp::SubAssign => BinOp::Sub,
+            BinOp::MulAssign => BinOp::Mul,
+            BinOp::DivAssign => BinOp::Div,
+            _ => op,
+        };
+        let left = self.eval(left_expr).num();
+        let right = self.eval(right_expr).num();
+        let left_ty = left_expr.ty.borrow();
+        let right_ty = right_expr.ty.borrow();
+        let (left_ty, right_ty) = (left_ty.as_ref().unwrap(), right_ty.as_ref().unwrap());
+        let is_int = matches!(left_ty, Ty::Int | Ty::Ivec2 | Ty::Ivec3 | Ty::Ivec4);
+        let result = match arith_op {
+            BinOp::Eq => Num::from_bool(left.as_slice() == right.as_slice()),
+            BinOp::Ne => Num::from_bool(left.as_slice() != right.as_slice()),
+            BinOp::Lt => Num::from_bool(left.v[0] < right.v[0]),
+            BinOp::Le => Num::from_bool(left.v[0] <= right.v[0]),
+            BinOp::Gt => Num::from_bool(left.v[0] > right.v[0]),
+            BinOp::Ge => Num::from_bool(left.v[0] >= right.v[0]),
+            BinOp::Add => Num::zip(left, right, |a, b| a + b),
+            BinOp::Sub => Num::zip(left, right, |a, b| a - b),
+            BinOp::Div if is_int => Num::zip(left, right, |a, b| if b == 0.0 { 0.0 } else { (a / b).trunc() }),
+            BinOp::Div => Num::zip(left, right, |a, b| a / b),
+            BinOp::Mul => match (matrix_dim(left_ty), matrix_dim(right_ty)) {
+                (Some(dim), Some(_)) => {
+                    let mut num = Num::zero(dim * dim);
+                    for c in 0..dim {
+                        for r in 0..dim {
+                            num.v[c * dim + r] = (0..dim).map(|k| left.v[k * dim + r] * right.v[c * dim + k]).sum();
+                        }
+                    }
+                    num
+                }
+                (Some(dim), None) if right_ty.is_vector() => {
+                    let mut num = Num::zero(dim);
+                    for r in 0..dim {
+                        num.v[r] = (0..dim).map(|c| left.v[c * dim + r] * right.v[c]).sum();
+                    }
+                    num
+                }
+                (None, Some(dim)) if left_ty.is_vector() => {
+                    let mut num = Num::zero(dim);
+                    for c in 0..dim {
+                        num.v[c] = (0..dim).map(|r| left.v[r] * right.v[c * dim + r]).sum();
+                    }
+                    num
+                }
+                _ => Num::zip(left, right, |a, b| a * b),
+            },
+            _ => unreachable!(),
+        };
+        let result = Value::Num(result);
+        if !matches!(op, BinOp::AddAssign | BinOp::SubAssign | BinOp::MulAssign | BinOp::DivAssign) {
+            return result;
+        }
+        self.store(left_expr, result.clone());
+        result
+    }
+
+    fn eval_cons_call(&mut self, ty_lit: TyLit, arg_exprs: &'a [Expr]) -> Value {
+        let ty = ty_lit.to_ty();
+        let size = ty.size();
+        let mut num = Num::zero(size);
+        if let [arg_expr] = arg_exprs {
+            let arg = self.eval(arg_expr).num();
+            let arg_ty = arg_expr.ty.borrow();
+            let arg_ty = arg_ty.as_ref().unwrap();
+            if arg_ty.is_scalar() {
+                match matrix_dim(&ty) {
+                    // A matrix from a scalar is a diagonal matrix.
+                    Some(dim) => (0..dim).for_each(|i| num.v[i * dim + i] = arg.v[0]),
+                    None => (0..size).for_each(|i| num.v[i] = arg.v[0]),
+                }
+                return Value::Num(convert_num(num, &ty));
+            }
+            if let (Some(dim), Some(arg_dim)) = (matrix_dim(&ty), matrix_dim(arg_ty)) {
+                // A matrix from a matrix of a different size takes the upper-left part, and
+                // fills the rest with the identity matrix.
+                for c in 0..dim {
+                    for r in 0..dim {
+                        num.v[c * dim + r] = if c < arg_dim && r < arg_dim {
+                            arg.v[c * arg_dim + r]
+                        } else if c == r {
+                            1.0
+                        } else {
+                            0.0
+                        };
+                    }
+                }
+                return Value::Num(num);
+            }
+        }
+        let mut i = 0;
+        for arg_expr in arg_exprs {
+            for &value in self.eval(arg_expr).num().as_slice() {
+                if i < size {
+                    num.v[i] = value;
+                    i += 1;
+                }
+            }
+        }
+        Value::Num(convert_num(num, &ty))
+    }
+
+    fn eval_call(&mut self, ident_path: IdentPath, arg_exprs: &'a [Expr], expr: &'a Expr) -> Value {
+        let shader = self.shader;
+        if let Some(&decl_index) = shader.fns.get(&ident_path) {
+            match &shader.shader_ast.decls[decl_index] {
+                Decl::Fn(decl) => return self.call_fn(decl, arg_exprs),
+                _ => unreachable!(),
+            }
+        }
+        let args: Vec<Value> = arg_exprs.iter().map(|arg_expr| self.eval(arg_expr)).collect();
+        let ident = ident_path.get_single().unwrap();
+        let arg_ty = arg_exprs.first().map(|arg_expr| arg_expr.ty.borrow().clone().unwrap());
+        let result = ident.with(|name| self.eval_builtin(name, &args, arg_ty.as_ref()));
+        let result = match result {
+            Some(result) => result,
+            None => panic!("Builtin `{}` is not supported by the CPU shader interpreter", ident),
+        };
+        Value::Num(convert_num(result, expr.ty.borrow().as_ref().unwrap()))
+    }
+
+    fn derivative(&mut self, is_x: bool, value: Num) -> Num {
+        match &mut self.derivatives {
+            Derivatives::Ignore => Num::zero(value.len),
+            Derivatives::Record { dfdx, dfdy } => {
+                let recorded = if is_x { dfdx } else { dfdy };
+                recorded.push(value);
+                Num::zero(value.len)
+            }
+            Derivatives::Replay { dfdx, dfdy, dfdx_index, dfdy_index } => {
+                let (recorded, index) = if is_x { (dfdx, dfdx_index) } else { (dfdy, dfdy_index) };
+                let result = match recorded.get(*index) {
+                    Some(neighbour) if neighbour.len == value.len => Num::zip(*neighbour, value, |a, b| a - b),
+                    _ => Num::zero(value.len),
+                };
+                *index += 1;
+                result
+            }
+        }
+    }
+
+    fn eval_builtin(&mut self, name: &str, args: &[Value], arg_ty: Option<&Ty>) -> Option<Num> {
+        let arg = |index: usize| args[index].num();
+        let bool_op = |f: fn(f32, f32) -> bool| Num::zip(arg(0), arg(1), |a, b| if f(a, b) { 1.0 } else { 0.0 });
+        Some(match name {
+            "abs" => arg(0).map(f32::abs),
+            "acos" => arg(0).map(f32::acos),
+            "asin" => arg(0).map(f32::asin),
+            "atan" if args.len() == 2 => Num::zip(arg(0), arg(1), f32::atan2),
+            "atan" => arg(0).map(f32::atan),
+            "ceil" => arg(0).map(f32::ceil),
+            "cos" => arg(0).map(f32::cos),
+            "degrees" => arg(0).map(f32::to_degrees),
+            "exp" => arg(0).map(f32::exp),
+            "exp2" => arg(0).map(f32::exp2),
+            "floor" => arg(0).map(f32::floor),
+            "fract" => arg(0).map(|x| x - x.floor()),
+            "inversesqrt" => arg(0).map(|x| 1.0 / x.sqrt()),
+            "log" => arg(0).map(f32::ln),
+            "log2" => arg(0).map(f32::log2),
+            "radians" => arg(0).map(f32::to_radians),
+            "sign" => arg(0).map(|x| {
+                if x > 0.0 {
+                    1.0
+                } else if x < 0.0 {
+                    -1.0
+                } else {
+                    0.0
+                }
+            }),
+            "sin" => arg(0).map(f32::sin),
+            "sqrt" => arg(0).map(f32::sqrt),
+            "tan" => arg(0).map(f32::tan),
+            "clamp" => Num::zip3(arg(0), arg(1), arg(2), |x, min, max| x.max(min).min(max)),
+            "max" => Num::zip(arg(0), arg(1), f32::max),
+            "min" => Num::zip(arg(0), arg(1), f32::min),
+            "mod" => Num::zip(arg(0), arg(1), |x, y| x - y * (x / y).floor()),
+            "pow" => Num::zip(arg(0), arg(1), f32::powf),
+            "step" => Num::zip(arg(0), arg(1), |edge, x| if x < edge { 0.0 } else { 1.0 }),
+            "mix" => Num::zip3(arg(0), arg(1), arg(2), |a, b, t| a + (b - a) * t),
+            "smoothstep" => Num::zip3(arg(0), arg(1), arg(2), |edge0, edge1, x| {
+                let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            }),
+            "length" => Num::scalar(Num::dot(arg(0), arg(0)).sqrt()),
+            "distance" => {
+                let diff = Num::zip(arg(0), arg(1), |a, b| a - b);
+                Num::scalar(Num::dot(diff, diff).sqrt())
+            }
+            "dot" => Num::scalar(Num::dot(arg(0), arg(1))),
+            "normalize" => {
+                let length = Num::dot(arg(0), arg(0)).sqrt();
+                arg(0).map(|x| x / length)
+            }
+            "cross" => {
+                let (a, b) = (arg(0), arg(1));
+                Num::from_slice(&[
+                    a.v[1] * b.v[2] - a.v[2] * b.v[1],
+                    a.v[2] * b.v[0] - a.v[0] * b.v[2],
+                    a.v[0] * b.v[1] - a.v[1] * b.v[0],
+                ])
+            }
+            "reflect" => {
+                let (i, n) = (arg(0), arg(1));
+                let dot = Num::dot(n, i);
+                Num::zip(i, n, |i, n| i - 2.0 * dot * n)
+            }
+            "refract" => {
+                let (i, n, eta) = (arg(0), arg(1), arg(2).v[0]);
+                let dot = Num::dot(n, i);
+                let k = 1.0 - eta * eta * (1.0 - dot * dot);
+                if k < 0.0 {
+                    Num::zero(i.len)
+                } else {
+                    Num::zip(i, n, |i, n| eta * i - (eta * dot + k.sqrt()) * n)
+                }
+            }
+            "faceforward" => {
+                let n = arg(0);
+                if Num::dot(arg(2), arg(1)) < 0.0 {
+                    n
+                } else {
+                    n.map(|x| -x)
+                }
+            }
+            "all" => Num::from_bool(arg(0).as_slice().iter().all(|&x| x != 0.0)),
+            "any" => Num::from_bool(arg(0).as_slice().iter().any(|&x| x != 0.0)),
+            "not" => arg(0).map(|x| if x != 0.0 { 0.0 } else { 1.0 }),
+            "equal" => bool_op(|a, b| a == b),
+            "notEqual" => bool_op(|a, b| a != b),
+            "lessThan" => bool_op(|a, b| a < b),
+            "lessThanEqual" => bool_op(|a, b| a <= b),
+            "greaterThan" => bool_op(|a, b| a > b),
+            "greaterThanEqual" => bool_op(|a, b| a >= b),
+            "matrixCompMult" => Num::zip(arg(0), arg(1), |a, b| a * b),
+            "inverse" => {
+                let mut mat = Mat4::default();
+                mat.v.copy_from_slice(arg(0).as_slice());
+                Num::from_slice(&mat.invert().v)
+            }
+            "transpose" => {
+                let (mat, dim) = (arg(0), matrix_dim(arg_ty?)?);
+                let mut num = Num::zero(dim * dim);
+                for c in 0..dim {
+                    for r in 0..dim {
+                        num.v[r * dim + c] = mat.v[c * dim + r];
+                    }
+                }
+                num
+            }
+            "dFdx" => self.derivative(true, arg(0)),
+            "dFdy" => self.derivative(false, arg(0)),
+            "sample2d" => match &args[0] {
+                Value::Texture(index) => {
+                    let pos = arg(1);
+                    let color = (self.inputs.sample_texture)(*index, Vec2 { x: pos.v[0], y: pos.v[1] });
+                    Num::from_slice(&[color.x, color.y, color.z, color.w])
+                }
+                _ => return None,
+            },
+            _ => return None,
+        })
+    }
+}
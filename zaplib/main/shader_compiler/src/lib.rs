@@ -22,6 +22,7 @@ pub mod generate_hlsl;
 pub mod generate_metal;
 pub mod generate_shader_ast;
 mod ident;
+pub mod interpreter;
 mod lex;
 mod lhs_check;
 mod lit;
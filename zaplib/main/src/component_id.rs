@@ -12,7 +12,7 @@ use crate::*;
 /// It's 32-bit, so you can use it in instance data and then read it out again,
 /// either directly or when by using [`Area::get_slice`] or [`Area::get_first`].
 #[repr(C)]
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ComponentId(u32);
 
 /// The next number to use for [`ComponentId`].
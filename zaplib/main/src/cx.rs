@@ -8,7 +8,7 @@ use cef_browser::MaybeCefBrowser;
 use debug_log::DebugLog;
 use std::{
     any::{Any, TypeId},
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, HashSet},
     fmt::Write,
     sync::{Arc, RwLock},
 };
@@ -108,6 +108,14 @@ pub struct Cx {
     /// on the entire [`CxView`], for better performance?
     pub(crate) layout_box_align_list: Vec<Area>,
 
+    /// What every [`Cx::begin_flex_box`] walked during the previous draw, by the [`ComponentId`] that was passed
+    /// in, so we can size their flex items. See [`CxFlexMeasure`].
+    pub(crate) flex_box_measures: HashMap<ComponentId, CxFlexMeasure>,
+
+    /// What every [`Cx::begin_grid`] measured during the previous draw, by the [`ComponentId`] that was passed in,
+    /// so we can size their [`GridTrack::Auto`] tracks. See [`CxGridMeasure`].
+    pub(crate) grid_measures: HashMap<ComponentId, CxGridMeasure>,
+
     /// The system-default `dpi_factor`. See also [`PassUniforms::dpi_factor`].
     ///
     /// More commonly known as the "device pixel ratio". TODO(JP): Rename?
@@ -125,6 +133,10 @@ pub struct Cx {
 
     /// The last [`Timer::timer_id`] that was issued.
     pub(crate) last_timer_id: u64,
+    /// The last [`HttpRequestId`] that was issued.
+    pub(crate) last_http_request_id: u64,
+    /// Requests made with [`CxDesktopVsWasmCommon::fetch`] that are not done or cancelled yet.
+    pub(crate) http_requests_in_flight: HashSet<HttpRequestId>,
     /// The last [`Signal::signal_id`] that was issued.
     pub(crate) last_signal_id: usize,
 
@@ -183,6 +195,9 @@ pub struct Cx {
     /// Settings per command; see [`CommandId`] and [`CxCommandSetting`].
     pub(crate) command_settings: HashMap<CommandId, CxCommandSetting>,
 
+    /// Commands sent using [`Cx::send_command`] that still need to be turned into [`Event::Command`]s.
+    pub(crate) commands: Vec<CommandId>,
+
     /// When set to true, will trigger a panic on the next redraw. Can be useful
     /// for debugging unwanted redraws. Can be set to true by pressing the "print
     /// screen" button on the keyboard.
@@ -262,9 +277,6 @@ impl Default for CxDebugDrawTree {
 }
 
 /// Settings for "commands"; see [`CommandId`].
-///
-/// Only supported on OSX for now.
-#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
 #[derive(Clone, Copy, Default)]
 pub(crate) struct CxCommandSetting {
     pub(crate) shift: bool,
@@ -319,12 +331,16 @@ impl Cx {
             view_stack: Vec::with_capacity(50),
             layout_boxes: Vec::with_capacity(100),
             layout_box_align_list: Vec::with_capacity(100),
+            flex_box_measures: HashMap::new(),
+            grid_measures: HashMap::new(),
             shader_group_instance_offsets: Vec::with_capacity(10),
 
             last_event_time: 0.0,
 
             redraw_id: 1,
             last_timer_id: 1,
+            last_http_request_id: 0,
+            http_requests_in_flight: HashSet::new(),
             last_signal_id: 1,
 
             next_key_focus: None,
@@ -339,6 +355,7 @@ impl Cx {
             shader_ast_generator: ShaderAstGenerator::new(),
 
             command_settings: HashMap::new(),
+            commands: Vec::new(),
 
             requested_next_frame: false,
             requested_draw: false,
@@ -368,7 +385,7 @@ impl Cx {
         }
     }
 
-    #[cfg(test)]
+    /// Create a [`Cx`] for use in tests, with fonts already loaded. See also [`AppTest`], which uses this.
     pub fn new_test() -> Self {
         let mut cx = Self::new(TypeId::of::<()>());
         cx.load_fonts();
@@ -376,6 +393,7 @@ impl Cx {
     }
 
     pub(crate) fn process_pre_event(&mut self, event: &mut Event) {
+        self.process_http_response_event(event);
         match event {
             Event::PointerHover(pe) => {
                 self.pointers[pe.digit].over_last = None;
@@ -418,6 +436,9 @@ impl Cx {
                             log!("Set draw_tree to {:?}", self.debug_flags.draw_tree);
                             self.request_draw();
                         }
+                        KeyCode::Key4 => {
+                            log!("{:?}", self.glyph_cache_stats());
+                        }
                         _ => {}
                     }
                 }
@@ -597,6 +618,12 @@ impl Cx {
         self.next_key_focus = Some(self.prev_key_focus);
     }
 
+    /// The [`ComponentId`] that currently has keyboard focus, e.g. to give it back later using
+    /// [`Cx::set_key_focus`].
+    pub fn get_key_focus(&self) -> Option<ComponentId> {
+        self.key_focus
+    }
+
     /// Check if a [`ComponentId`] currently has keyboard focus.
     pub fn has_key_focus(&self, component_id: Option<ComponentId>) -> bool {
         self.key_focus == component_id
@@ -739,12 +766,17 @@ impl Cx {
 
     pub(crate) fn call_signals(&mut self) {
         let mut counter = 0;
-        while !self.signals.is_empty() {
+        while !self.signals.is_empty() || !self.commands.is_empty() {
             counter += 1;
-            let mut signals = HashMap::new();
-            std::mem::swap(&mut self.signals, &mut signals);
+            for command in std::mem::take(&mut self.commands) {
+                self.call_event_handler(&mut Event::Command(command));
+            }
+            if !self.signals.is_empty() {
+                let mut signals = HashMap::new();
+                std::mem::swap(&mut self.signals, &mut signals);
 
-            self.call_event_handler(&mut Event::Signal(SignalEvent { signals }));
+                self.call_event_handler(&mut Event::Signal(SignalEvent { signals }));
+            }
 
             if counter > 100 {
                 println!("Signal feedback loop detected");
@@ -773,7 +805,7 @@ impl Cx {
         if self.debug_flags.draw_tree == CxDebugDrawTree::DrawTree || self.debug_flags.draw_tree == CxDebugDrawTree::Instances {
             let mut s = String::new();
             let dump_instances = self.debug_flags.draw_tree == CxDebugDrawTree::Instances;
-            self.debug_draw_tree_recur(dump_instances, &mut s, view_id, 0);
+            self.debug_draw_tree_recur(dump_instances, true, &mut s, view_id, 0);
             crate::log!("{}", &s);
         }
         if self.debug_flags.draw_tree == CxDebugDrawTree::Geometries {
@@ -792,7 +824,16 @@ impl Cx {
         }
     }
 
-    fn debug_draw_tree_recur(&mut self, dump_instances: bool, s: &mut String, view_id: usize, depth: usize) {
+    /// Write the draw tree of `view_id` and its subviews to `s`. If `shader_locations` is false, only the file names
+    /// of shaders are printed instead of the full location of their code, which changes too often for golden files.
+    pub(crate) fn debug_draw_tree_recur(
+        &mut self,
+        dump_instances: bool,
+        shader_locations: bool,
+        s: &mut String,
+        view_id: usize,
+        depth: usize,
+    ) {
         if view_id >= self.views.len() {
             writeln!(s, "---------- Drawlist still empty ---------").unwrap();
             return;
@@ -815,19 +856,25 @@ impl Cx {
         for draw_call_id in 0..draw_calls_len {
             let sub_view_id = self.views[view_id].draw_calls[draw_call_id].sub_view_id;
             if sub_view_id != 0 {
-                self.debug_draw_tree_recur(dump_instances, s, sub_view_id, depth + 1);
+                self.debug_draw_tree_recur(dump_instances, shader_locations, s, sub_view_id, depth + 1);
             } else {
                 let cxview = &mut self.views[view_id];
                 let draw_call = &mut cxview.draw_calls[draw_call_id];
                 let sh = &self.shaders[draw_call.shader_id];
                 let slots = sh.mapping.instance_props.total_slots;
                 let instances = draw_call.instances.len() / slots;
+                let name = if shader_locations {
+                    sh.name.as_str()
+                } else {
+                    // Strip the path, line and column from "path/to/file.rs:line:column".
+                    sh.name.split(&['/', '\\', ':'][..]).rev().nth(2).unwrap_or_default()
+                };
                 writeln!(
                     s,
                     "{}call {}: {}({}) *:{} scroll: {} draw_local_scroll: {}",
                     indent,
                     draw_call_id,
-                    sh.name,
+                    name,
                     draw_call.shader_id,
                     instances,
                     vec2(draw_call.draw_uniforms.draw_scroll_x, draw_call.draw_uniforms.draw_scroll_y),
@@ -945,9 +992,19 @@ pub trait CxDesktopVsWasmCommon {
     /// Write `data` to `path`.
     fn file_write(&mut self, path: &str, data: &[u8]);
 
-    /// Send data over a Websocket.
+    /// Send data over a Websocket, as a binary message. Connects on the first call for a given `url`, and on the
+    /// next call after the connection closed. Received messages and errors (including the connection closing) come
+    /// in as [`Event::WebSocketMessage`].
     fn websocket_send(&mut self, url: &str, data: &[u8]);
 
+    /// Make an HTTP request, on a background thread (native) or using the browser's `fetch` (WebAssembly). The response
+    /// comes in as one or more [`Event::HttpResponse`]s with the returned [`HttpRequestId`]; see [`HttpResponse`].
+    fn fetch(&mut self, request: HttpRequest) -> HttpRequestId;
+
+    /// Cancel a request made with [`CxDesktopVsWasmCommon::fetch`]. No more [`Event::HttpResponse`]s will come in
+    /// for it, even if they were already on their way.
+    fn cancel_fetch(&mut self, request_id: HttpRequestId);
+
     /// Make an HTTP request. When done, you get a [`SignalEvent`] corresponding to the provided
     /// [`Signal`] with [`Cx::STATUS_HTTP_SEND_OK`] or [`Cx::STATUS_HTTP_SEND_FAIL`] as the status.
     fn http_send(
@@ -991,6 +1048,9 @@ pub trait CxPlatformCommon {
     fn update_menu(&mut self, menu: &Menu);
     /// Copy the given text to the clipboard, if possible.
     fn copy_text_to_clipboard(&mut self, text: &str);
+    /// Make the given text available as the primary selection, which gets pasted with a middle click. Typically
+    /// called when selecting text with the pointer. Only does something on Linux (X11).
+    fn set_primary_selection(&mut self, text: &str);
     /// Send zaplib Event for processing from any thread
     fn send_event_from_any_thread(event: Event);
 }
@@ -1,13 +1,22 @@
 //! Common code between all native desktop platforms. The counterpart is `cx_wasm32`.
 
+use crate::desktop_http::fetch_on_thread;
+use crate::desktop_websocket::DesktopWebSocket;
 use crate::*;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-#[derive(Clone, Default)]
+#[derive(Default)]
 pub(crate) struct CxDesktop {
     pub(crate) repaint_via_scroll_event: bool,
+    /// Connections made by [`CxDesktopVsWasmCommon::websocket_send`], by URL.
+    pub(crate) websockets: HashMap<String, DesktopWebSocket>,
+    /// Set to stop the threads of requests made by [`CxDesktopVsWasmCommon::fetch`], by request.
+    pub(crate) http_cancel_flags: HashMap<HttpRequestId, Arc<AtomicBool>>,
 }
 
 impl CxDesktopVsWasmCommon for Cx {
@@ -30,7 +39,32 @@ impl CxDesktopVsWasmCommon for Cx {
     }
 
     /// See [`CxDesktopVsWasmCommon::websocket_send`] for documentation.
-    fn websocket_send(&mut self, _url: &str, _data: &[u8]) {}
+    fn websocket_send(&mut self, url: &str, data: &[u8]) {
+        let websocket = self.platform.desktop.websockets.entry(url.to_string()).or_insert_with(|| {
+            let url = url.to_string();
+            DesktopWebSocket::new(&url.clone(), move |result| {
+                Cx::send_event_from_any_thread(Event::WebSocketMessage(WebSocketMessageEvent { url: url.clone(), result }));
+            })
+        });
+        websocket.send(data);
+    }
+
+    /// See [`CxDesktopVsWasmCommon::fetch`] for documentation.
+    fn fetch(&mut self, request: HttpRequest) -> HttpRequestId {
+        let request_id = self.new_http_request_id();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.platform.desktop.http_cancel_flags.insert(request_id, Arc::clone(&cancelled));
+        fetch_on_thread(request_id, request, cancelled);
+        request_id
+    }
+
+    /// See [`CxDesktopVsWasmCommon::cancel_fetch`] for documentation.
+    fn cancel_fetch(&mut self, request_id: HttpRequestId) {
+        self.http_requests_in_flight.remove(&request_id);
+        if let Some(cancelled) = self.platform.desktop.http_cancel_flags.remove(&request_id) {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
 
     /// See [`CxDesktopVsWasmCommon::http_send`] for documentation.
     fn http_send(
@@ -0,0 +1,854 @@
+//! Headless platform backend, which renders the draw tree into framebuffers in memory using
+//! [`zaplib_shader_compiler::interpreter`], instead of using a GPU.
+//!
+//! It doesn't need an X11 display or OpenGL context, so it can be used on CI machines. The event
+//! loop doesn't run by itself; instead you drive it by sending [`Event`]s and advancing time
+//! manually, after which you can read back the rendered pixels using
+//! [`Cx::headless_window_framebuffer`].
+//!
+//! This tries to render the same things as `cx_opengl`, but since every pixel runs through a shader
+//! interpreter it is very slow, so only use it with small windows. To keep that down, passes only get
+//! rasterized when you actually read back the pixels of a window, unless their previous contents matter.
+
+use crate::*;
+use std::collections::HashMap;
+use std::sync::{Mutex, Once};
+use zaplib_shader_compiler::interpreter::{CpuShader, CpuShaderInputs};
+
+/// Events sent using [`Cx::send_event_from_any_thread`] or [`Cx::post_signal`] (potentially from
+/// other threads) while running headless.
+///
+/// TODO(JP): This is shared between all headless [`Cx`] instances in a process, so when running
+/// multiple of them at the same time they might receive each other's events.
+static mut GLOBAL_HEADLESS_EVENTS: *mut Mutex<Vec<Event>> = std::ptr::null_mut();
+static GLOBAL_HEADLESS_EVENTS_INIT: Once = Once::new();
+
+/// The pixels of a window or texture that was rendered by the headless backend.
+#[derive(Clone, Debug, Default)]
+pub struct HeadlessFramebuffer {
+    pub width: usize,
+    pub height: usize,
+    /// Premultiplied RGBA pixels, row by row starting at the top left, with the red channel in the
+    /// lowest byte (so the same layout as [`TextureHandle::get_image_mut`]).
+    pub pixels: Vec<u32>,
+}
+
+impl HeadlessFramebuffer {
+    fn new(width: usize, height: usize) -> Self {
+        Self { width, height, pixels: vec![0; width * height] }
+    }
+
+    /// Get a single pixel as a (premultiplied) colour.
+    pub fn get_pixel(&self, x: usize, y: usize) -> Vec4 {
+        unpack_color(self.pixels[y * self.width + x])
+    }
+
+    fn clear(&mut self, color: Vec4) {
+        let color = pack_color(color);
+        self.pixels.iter_mut().for_each(|pixel| *pixel = color);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum HeadlessTarget {
+    Window(usize),
+    Texture(u32),
+}
+
+#[derive(Clone)]
+struct HeadlessTimer {
+    timer_id: u64,
+    interval: f64,
+    repeats: bool,
+    fire_at: f64,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct CxHeadless {
+    /// The simulated time in seconds, which only changes in [`Cx::headless_advance_time`].
+    time: f64,
+    dpi_factor: f32,
+    /// Indexed by shader id.
+    shaders: Vec<Option<CpuShader>>,
+    framebuffers: HashMap<HeadlessTarget, HeadlessFramebuffer>,
+    /// Passes that were painted but not rasterized yet, in the order to rasterize them in; see
+    /// [`Cx::headless_window_framebuffer`].
+    passes_to_rasterize: Vec<usize>,
+    /// Indexed by pass id; always the same size as the pass' colour framebuffer.
+    depth_buffers: HashMap<usize, Vec<f32>>,
+    timers: Vec<HeadlessTimer>,
+}
+
+/// What we're currently rendering into; temporarily taken out of [`CxHeadless`] so that we can still
+/// sample from other framebuffers while rendering.
+struct HeadlessRenderTarget<'a> {
+    color: &'a mut HeadlessFramebuffer,
+    depth: &'a mut [f32],
+}
+
+impl Cx {
+    /// Start running headless: send [`Event::Construct`] to `event_handler`, and draw and paint the
+    /// first frame. Use this instead of [`Cx::event_loop`].
+    ///
+    /// The `event_handler` should be the same function as you'd pass to [`Cx::event_loop`], and needs to
+    /// be passed again when calling the other `headless_*` methods.
+    pub fn headless_start<F>(&mut self, event_handler: &mut F)
+    where
+        F: FnMut(&mut Cx, &mut Event),
+    {
+        self.platform_type = PlatformType::Linux { custom_window_chrome: false };
+        self.platform.headless = Some(Box::new(CxHeadless { dpi_factor: 1.0, ..CxHeadless::default() }));
+
+        GLOBAL_HEADLESS_EVENTS_INIT.call_once(|| unsafe {
+            GLOBAL_HEADLESS_EVENTS = Box::into_raw(Box::new(Mutex::new(Vec::new())));
+        });
+
+        self.load_fonts();
+
+        self.with_headless_event_handler(event_handler, |cx| {
+            cx.call_event_handler(&mut Event::Construct);
+            cx.request_draw();
+            cx.headless_paint();
+        });
+    }
+
+    /// Send an [`Event`] to a headless [`Cx`], and then draw and paint if necessary.
+    pub fn headless_send_event<F>(&mut self, event_handler: &mut F, mut event: Event)
+    where
+        F: FnMut(&mut Cx, &mut Event),
+    {
+        self.with_headless_event_handler(event_handler, |cx| {
+            cx.headless_process_event(&mut event);
+            cx.headless_paint();
+        });
+    }
+
+    /// Advance the simulated time of a headless [`Cx`], firing [`Event::Timer`]s for every timer that
+    /// expired in the meantime, and then an [`Event::NextFrame`] if one was requested. Then draw and
+    /// paint if necessary.
+    pub fn headless_advance_time<F>(&mut self, event_handler: &mut F, seconds: f64)
+    where
+        F: FnMut(&mut Cx, &mut Event),
+    {
+        self.with_headless_event_handler(event_handler, |cx| {
+            let end_time = cx.headless_mut().time + seconds;
+
+            cx.headless_update_timers();
+            loop {
+                let headless = cx.headless_mut();
+                let next_timer = (0..headless.timers.len())
+                    .filter(|&index| headless.timers[index].fire_at <= end_time)
+                    .min_by(|&a, &b| headless.timers[a].fire_at.partial_cmp(&headless.timers[b].fire_at).unwrap());
+                let timer_id = match next_timer {
+                    Some(index) => {
+                        let timer = &mut headless.timers[index];
+                        let timer_id = timer.timer_id;
+                        headless.time = headless.time.max(timer.fire_at);
+                        if timer.repeats && timer.interval > 0.0 {
+                            timer.fire_at += timer.interval;
+                        } else {
+                            headless.timers.remove(index);
+                        }
+                        timer_id
+                    }
+                    None => break,
+                };
+                cx.headless_process_event(&mut Event::Timer(TimerEvent { timer_id }));
+                // Timers might have been started or stopped by the event handler.
+                cx.headless_update_timers();
+            }
+            cx.headless_mut().time = end_time;
+
+            if cx.requested_next_frame {
+                cx.last_event_time = end_time;
+                cx.call_next_frame_event();
+            }
+
+            cx.headless_paint();
+        });
+    }
+
+    /// Get the pixels of a window that were painted by the headless backend, if the window exists.
+    ///
+    /// Anything that was painted since the last call gets rasterized here, so don't call this more often
+    /// than you need to.
+    pub fn headless_window_framebuffer(&mut self, window_id: usize) -> Option<&HeadlessFramebuffer> {
+        let mut headless = self.platform.headless.take()?;
+        self.headless_rasterize_passes(&mut headless, true);
+        self.platform.headless = Some(headless);
+        self.platform.headless.as_ref()?.framebuffers.get(&HeadlessTarget::Window(window_id))
+    }
+
+    /// Queue an [`Event`] from any thread, to be processed the next time a headless [`Cx`] paints. Gives the
+    /// event back when we're not running headless.
+    pub(crate) fn headless_send_event_from_any_thread(event: Event) -> Result<(), Event> {
+        unsafe {
+            if GLOBAL_HEADLESS_EVENTS.is_null() {
+                return Err(event);
+            }
+            if let Ok(mut events_locked) = (*GLOBAL_HEADLESS_EVENTS).lock() {
+                events_locked.push(event);
+            }
+        }
+        Ok(())
+    }
+
+    fn headless_mut(&mut self) -> &mut CxHeadless {
+        self.platform.headless.as_mut().expect("Call `Cx::headless_start` first")
+    }
+
+    fn with_headless_event_handler<F>(&mut self, event_handler: &mut F, f: impl FnOnce(&mut Cx))
+    where
+        F: FnMut(&mut Cx, &mut Event),
+    {
+        self.event_handler = Some(event_handler as *const dyn FnMut(&mut Cx, &mut Event) as *mut dyn FnMut(&mut Cx, &mut Event));
+        f(self);
+        self.event_handler = None;
+    }
+
+    fn headless_process_event(&mut self, event: &mut Event) {
+        self.last_event_time = self.headless_mut().time;
+        self.process_pre_event(event);
+        match &event {
+            Event::WindowGeomChange(re) => {
+                self.windows[re.window_id].window_geom = re.new_geom.clone();
+                if re.old_geom.inner_size != re.new_geom.inner_size {
+                    self.request_draw();
+                }
+                self.call_event_handler(event);
+            }
+            Event::WindowClosed(wc) => {
+                self.windows[wc.window_id].window_state = CxWindowState::Closed;
+                self.windows_free.push(wc.window_id);
+                self.headless_mut().framebuffers.remove(&HeadlessTarget::Window(wc.window_id));
+                self.call_event_handler(event);
+            }
+            Event::Signal { .. } => {
+                self.call_event_handler(event);
+                self.call_signals();
+            }
+            Event::None => {}
+            _ => {
+                self.call_event_handler(event);
+            }
+        }
+        self.process_post_event(event);
+    }
+
+    fn headless_update_timers(&mut self) {
+        let time = self.headless_mut().time;
+        while let Some((timer_id, interval, repeats)) = self.platform.start_timer.pop() {
+            let fire_at = time + interval;
+            self.headless_mut().timers.push(HeadlessTimer { timer_id, interval, repeats, fire_at });
+        }
+        while let Some(timer_id) = self.platform.stop_timer.pop() {
+            self.headless_mut().timers.retain(|timer| timer.timer_id != timer_id);
+        }
+    }
+
+    /// Keep drawing and painting until nothing is dirty anymore (or we give up).
+    fn headless_paint(&mut self) {
+        for _ in 0..10 {
+            let events = unsafe { std::mem::take(&mut *(*GLOBAL_HEADLESS_EVENTS).lock().unwrap()) };
+            for mut event in events {
+                self.headless_process_event(&mut event);
+            }
+
+            self.call_signals();
+            if self.requested_draw {
+                self.call_draw_event();
+            }
+            self.call_signals();
+
+            let dpi_factor = self.headless_mut().dpi_factor;
+            let mut created_window = false;
+            for window in &mut self.windows {
+                window.window_state = match &window.window_state {
+                    CxWindowState::Create { inner_size, position, .. } => {
+                        window.window_geom = WindowGeom {
+                            dpi_factor,
+                            inner_size: *inner_size,
+                            outer_size: *inner_size,
+                            position: position.unwrap_or_default(),
+                            ..WindowGeom::default()
+                        };
+                        created_window = true;
+                        CxWindowState::Created
+                    }
+                    CxWindowState::Close => CxWindowState::Closed,
+                    CxWindowState::Created => CxWindowState::Created,
+                    CxWindowState::Closed => CxWindowState::Closed,
+                };
+                window.window_command = CxWindowCmd::None;
+            }
+
+            self.headless_update_timers();
+
+            let mut passes_todo = Vec::new();
+            let mut windows_need_repaint = 0;
+            self.compute_passes_to_repaint(&mut passes_todo, &mut windows_need_repaint);
+            if !passes_todo.is_empty() {
+                // Take the headless state out of `self`, so we can borrow both.
+                let mut headless = self.platform.headless.take().unwrap();
+                self.headless_compile_shaders(&mut headless);
+                for pass_id in passes_todo {
+                    match self.passes[pass_id].dep_of.clone() {
+                        CxPassDepOf::Window(window_id) => {
+                            if matches!(self.windows[window_id].window_state, CxWindowState::Created) {
+                                self.headless_draw_pass_to_window(&mut headless, pass_id, window_id);
+                            }
+                        }
+                        CxPassDepOf::Pass(parent_pass_id) => {
+                            let dpi_factor = self.get_delegated_dpi_factor(parent_pass_id);
+                            self.headless_draw_pass_to_texture(&mut headless, pass_id, dpi_factor);
+                        }
+                        CxPassDepOf::None => {
+                            self.headless_draw_pass_to_texture(&mut headless, pass_id, 1.0);
+                        }
+                    }
+                }
+                self.platform.headless = Some(headless);
+            }
+
+            if created_window {
+                // Same as `first_draw` for OpenGL windows.
+                self.request_draw();
+            }
+
+            if !self.requested_draw && !self.passes.iter().any(|pass| pass.paint_dirty) && self.signals.is_empty() {
+                break;
+            }
+        }
+    }
+
+    fn headless_compile_shaders(&mut self, headless: &mut CxHeadless) {
+        for shader_id in self.shader_recompile_ids.drain(..) {
+            let shader_ast = self.shaders[shader_id].shader_ast.take().unwrap();
+            if headless.shaders.len() <= shader_id {
+                headless.shaders.resize(shader_id + 1, None);
+            }
+            headless.shaders[shader_id] = Some(CpuShader::new(shader_ast));
+        }
+    }
+
+    /// Updates the draw calls, but leaves the pixels to [`Cx::headless_rasterize_passes`].
+    fn headless_draw_pass_to_window(&mut self, headless: &mut CxHeadless, pass_id: usize, window_id: usize) {
+        let dpi_factor = self.windows[window_id].window_geom.dpi_factor;
+        let pass_size = self.passes[pass_id].pass_size;
+        self.passes[pass_id].set_matrix(Vec2::default(), pass_size);
+        self.passes[pass_id].set_dpi_factor(dpi_factor);
+        self.passes[pass_id].paint_dirty = false;
+
+        self.headless_render_pass(headless, pass_id, None);
+        headless.passes_to_rasterize.retain(|&id| id != pass_id);
+        headless.passes_to_rasterize.push(pass_id);
+    }
+
+    /// Rasterize the passes that were painted since the last time, in order. With `include_windows` set to
+    /// false, this only rasterizes passes that render into textures, and leaves windows for later.
+    fn headless_rasterize_passes(&mut self, headless: &mut CxHeadless, include_windows: bool) {
+        for pass_id in std::mem::take(&mut headless.passes_to_rasterize) {
+            match self.passes[pass_id].dep_of {
+                CxPassDepOf::Window(_) if !include_windows => headless.passes_to_rasterize.push(pass_id),
+                CxPassDepOf::Window(window_id) => {
+                    if matches!(self.windows[window_id].window_state, CxWindowState::Created) {
+                        self.headless_rasterize_window(headless, pass_id, window_id);
+                    }
+                }
+                _ => self.headless_rasterize_texture(headless, pass_id),
+            }
+        }
+    }
+
+    fn headless_rasterize_window(&mut self, headless: &mut CxHeadless, pass_id: usize, window_id: usize) {
+        let window_geom = &self.windows[window_id].window_geom;
+        let width = (window_geom.inner_size.x * window_geom.dpi_factor) as usize;
+        let height = (window_geom.inner_size.y * window_geom.dpi_factor) as usize;
+
+        let clear_color = match self.passes[pass_id].color_textures.first() {
+            Some(color_texture) => match color_texture.clear_color {
+                ClearColor::InitWith(color) | ClearColor::ClearWith(color) => color,
+            },
+            None => Vec4::default(),
+        };
+        let clear_depth = match self.passes[pass_id].clear_depth {
+            ClearDepth::InitWith(depth) | ClearDepth::ClearWith(depth) => depth as f32,
+        };
+
+        let target = HeadlessTarget::Window(window_id);
+        let mut color = match headless.framebuffers.remove(&target) {
+            Some(color) if color.width == width && color.height == height => color,
+            _ => HeadlessFramebuffer::new(width, height),
+        };
+        color.clear(clear_color);
+        let mut depth = vec![clear_depth; width * height];
+
+        self.headless_render_pass(headless, pass_id, Some(&mut HeadlessRenderTarget { color: &mut color, depth: &mut depth }));
+
+        headless.framebuffers.insert(target, color);
+    }
+
+    /// Updates the draw calls, and rasterizes right away if the pass builds on its previous contents (so
+    /// when it doesn't clear all its textures). Otherwise leaves that to [`Cx::headless_rasterize_passes`].
+    fn headless_draw_pass_to_texture(&mut self, headless: &mut CxHeadless, pass_id: usize, inherit_dpi_factor: f32) {
+        let pass_size = self.passes[pass_id].pass_size;
+        self.passes[pass_id].set_matrix(Vec2::default(), pass_size);
+        self.passes[pass_id].paint_dirty = false;
+
+        let dpi_factor = self.passes[pass_id].override_dpi_factor.unwrap_or(inherit_dpi_factor);
+        self.passes[pass_id].set_dpi_factor(dpi_factor);
+
+        let cxpass = &self.passes[pass_id];
+        let clears_everything =
+            cxpass.color_textures.iter().all(|color_texture| matches!(color_texture.clear_color, ClearColor::ClearWith(_)))
+                && !matches!((cxpass.depth_texture, &cxpass.clear_depth), (Some(_), ClearDepth::InitWith(_)));
+        if clears_everything {
+            self.headless_render_pass(headless, pass_id, None);
+            headless.passes_to_rasterize.retain(|&id| id != pass_id);
+            headless.passes_to_rasterize.push(pass_id);
+        } else {
+            // This might sample from textures that haven't been rasterized yet.
+            self.headless_rasterize_passes(headless, false);
+            self.headless_rasterize_texture(headless, pass_id);
+        }
+    }
+
+    fn headless_rasterize_texture(&mut self, headless: &mut CxHeadless, pass_id: usize) {
+        let pass_size = self.passes[pass_id].pass_size;
+        let dpi_factor = self.passes[pass_id].pass_uniforms.dpi_factor;
+
+        // Like OpenGL, we only render into the first colour texture, but do allocate and clear the others.
+        let mut color_target = None;
+        for index in 0..self.passes[pass_id].color_textures.len() {
+            let color_texture = &self.passes[pass_id].color_textures[index];
+            let (texture_id, clear_color) = (color_texture.texture_id, color_texture.clear_color.clone());
+            let cxtexture = &mut self.textures[texture_id as usize];
+            let width = cxtexture.desc.width.unwrap_or((pass_size.x * dpi_factor) as usize);
+            let height = cxtexture.desc.height.unwrap_or((pass_size.y * dpi_factor) as usize);
+
+            let target = HeadlessTarget::Texture(texture_id);
+            let reallocated = cxtexture.platform.width != width as u64
+                || cxtexture.platform.height != height as u64
+                || cxtexture.platform.alloc_desc != cxtexture.desc
+                || !headless.framebuffers.contains_key(&target);
+            if reallocated {
+                cxtexture.platform.alloc_desc = cxtexture.desc.clone();
+                cxtexture.platform.width = width as u64;
+                cxtexture.platform.height = height as u64;
+                headless.framebuffers.insert(target, HeadlessFramebuffer::new(width, height));
+            }
+            match clear_color {
+                ClearColor::InitWith(color) if reallocated => headless.framebuffers.get_mut(&target).unwrap().clear(color),
+                ClearColor::InitWith(_) => {}
+                ClearColor::ClearWith(color) => headless.framebuffers.get_mut(&target).unwrap().clear(color),
+            }
+            if index == 0 {
+                color_target = Some(target);
+            }
+        }
+        let target = match color_target {
+            Some(target) => target,
+            None => return,
+        };
+        let mut color = headless.framebuffers.remove(&target).unwrap();
+
+        // Passes without a depth texture still get a depth buffer, just like the "bugfix" depth buffer in
+        // the OpenGL backend.
+        let pixels = color.width * color.height;
+        let (clear_depth, always_clear) = match (self.passes[pass_id].depth_texture, &self.passes[pass_id].clear_depth) {
+            (Some(_), ClearDepth::InitWith(depth)) => (*depth as f32, false),
+            (Some(_), ClearDepth::ClearWith(depth)) => (*depth as f32, true),
+            (None, _) => (1.0, true),
+        };
+        let mut depth = match headless.depth_buffers.remove(&pass_id) {
+            Some(depth) if depth.len() == pixels && !always_clear => depth,
+            _ => vec![clear_depth; pixels],
+        };
+
+        self.headless_render_pass(headless, pass_id, Some(&mut HeadlessRenderTarget { color: &mut color, depth: &mut depth }));
+
+        headless.depth_buffers.insert(pass_id, depth);
+        headless.framebuffers.insert(target, color);
+    }
+
+    /// Update the draw calls of a pass, and render them into `target` if there is one.
+    fn headless_render_pass(&mut self, headless: &CxHeadless, pass_id: usize, mut target: Option<&mut HeadlessRenderTarget>) {
+        let view_id = self.passes[pass_id].main_view_id.unwrap();
+        let mut zbias = 0.0;
+        let zbias_step = self.passes[pass_id].zbias_step;
+        self.headless_render_view(
+            headless,
+            &mut target,
+            pass_id,
+            view_id,
+            Vec2::default(),
+            (Vec2 { x: -50000., y: -50000. }, Vec2 { x: 50000., y: 50000. }),
+            &mut zbias,
+            zbias_step,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn headless_render_view(
+        &mut self,
+        headless: &CxHeadless,
+        target: &mut Option<&mut HeadlessRenderTarget>,
+        pass_id: usize,
+        view_id: usize,
+        scroll: Vec2,
+        clip: (Vec2, Vec2),
+        zbias: &mut f32,
+        zbias_step: f32,
+    ) {
+        let draw_calls_len = self.views[view_id].draw_calls_len;
+        self.views[view_id].parent_scroll = scroll;
+        let local_scroll = self.views[view_id].snapped_scroll;
+        let clip = self.views[view_id].intersect_clip(clip);
+        for draw_call_id in 0..draw_calls_len {
+            let sub_view_id = self.views[view_id].draw_calls[draw_call_id].sub_view_id;
+            if sub_view_id != 0 {
+                self.headless_render_view(
+                    headless,
+                    target,
+                    pass_id,
+                    sub_view_id,
+                    Vec2 { x: local_scroll.x + scroll.x, y: local_scroll.y + scroll.y },
+                    clip,
+                    zbias,
+                    zbias_step,
+                );
+                continue;
+            }
+
+            let gpu_geometry_id = GpuGeometry::get_id(self, view_id, draw_call_id);
+
+            let draw_call = &mut self.views[view_id].draw_calls[draw_call_id];
+            draw_call.instance_dirty = false;
+            draw_call.uniforms_dirty = false;
+            draw_call.set_zbias(*zbias);
+            draw_call.set_local_scroll(scroll, local_scroll);
+            draw_call.set_clip(clip);
+            *zbias += zbias_step;
+
+            let target = match target {
+                Some(target) => &mut **target,
+                None => continue,
+            };
+            let draw_call = &self.views[view_id].draw_calls[draw_call_id];
+            let shader = match headless.shaders.get(draw_call.shader_id) {
+                Some(Some(shader)) => shader,
+                _ => continue,
+            };
+            let mapping = &self.shaders[draw_call.shader_id].mapping;
+            let instance_slots = mapping.instance_props.total_slots;
+            let geometry_slots = mapping.geometry_props.total_slots;
+            if instance_slots == 0 || geometry_slots == 0 {
+                continue;
+            }
+
+            let textures = &self.textures;
+            let sample_texture = |index: usize, pos: Vec2| -> Vec4 {
+                let texture_id = match draw_call.textures_2d.get(index) {
+                    Some(texture_id) => *texture_id,
+                    None => return Vec4::default(),
+                };
+                if let Some(framebuffer) = headless.framebuffers.get(&HeadlessTarget::Texture(texture_id)) {
+                    // Render targets are stored top-down, which `sample2d` already accounts for.
+                    return sample_bilinear(framebuffer.width, framebuffer.height, &framebuffer.pixels, pos.x, pos.y);
+                }
+                let cxtexture = &textures[texture_id as usize];
+                match (cxtexture.desc.width, cxtexture.desc.height) {
+                    (Some(width), Some(height)) if cxtexture.image_u32.len() >= width * height => {
+                        // Uploaded images have their first row at the bottom, like in OpenGL.
+                        sample_bilinear(width, height, &cxtexture.image_u32, pos.x, 1.0 - pos.y)
+                    }
+                    _ => Vec4::default(),
+                }
+            };
+            let inputs = CpuShaderInputs {
+                pass_uniforms: &self.passes[pass_id].pass_uniforms.as_slice()[..],
+                view_uniforms: &self.views[view_id].view_uniforms.as_slice()[..],
+                draw_uniforms: &draw_call.draw_uniforms.as_slice()[..],
+                user_uniforms: &draw_call.user_uniforms,
+                sample_texture: &sample_texture,
+            };
+
+            let geometry = &self.gpu_geometries[gpu_geometry_id].geometry;
+            let vertices = geometry.vertices_f32_slice();
+            let indices = geometry.indices_u32_slice();
+            let vertex_count = vertices.len() / geometry_slots;
+            let varyings_len = shader.varyings_len();
+            let mut positions = vec![Vec4::default(); vertex_count];
+            let mut varyings = vec![0.0; vertex_count * varyings_len];
+            for instance in draw_call.instances.chunks_exact(instance_slots) {
+                for vertex_index in 0..vertex_count {
+                    positions[vertex_index] = shader.run_vertex(
+                        &inputs,
+                        &vertices[vertex_index * geometry_slots..(vertex_index + 1) * geometry_slots],
+                        instance,
+                        &mut varyings[vertex_index * varyings_len..(vertex_index + 1) * varyings_len],
+                    );
+                }
+                for triangle in indices.chunks_exact(3) {
+                    let vertex = |i: usize| {
+                        let index = triangle[i] as usize;
+                        (positions[index], &varyings[index * varyings_len..(index + 1) * varyings_len])
+                    };
+                    rasterize_triangle(target, shader, &inputs, [vertex(0), vertex(1), vertex(2)]);
+                }
+            }
+        }
+        self.debug_draw_tree(view_id);
+    }
+}
+
+/// Rasterize a single triangle, using the same rules as OpenGL as much as possible: pixel centers
+/// are sampled, varyings are interpolated with perspective correction, and we use the
+/// [`Cx::set_default_depth_and_blend_mode`] depth test and (premultiplied alpha) blending.
+fn rasterize_triangle(
+    target: &mut HeadlessRenderTarget,
+    shader: &CpuShader,
+    inputs: &CpuShaderInputs,
+    vertices: [(Vec4, &[f32]); 3],
+) {
+    if vertices.iter().any(|(position, _)| position.w <= 0.0) {
+        return;
+    }
+    let (width, height) = (target.color.width, target.color.height);
+    // Window coordinates, with y pointing down.
+    let mut screen = vertices.map(|(position, _)| {
+        let inv_w = 1.0 / position.w;
+        Vec4 {
+            x: (position.x * inv_w + 1.0) * 0.5 * width as f32,
+            y: (1.0 - position.y * inv_w) * 0.5 * height as f32,
+            z: (position.z * inv_w + 1.0) * 0.5,
+            w: inv_w,
+        }
+    });
+    let mut varyings = vertices.map(|(_, varyings)| varyings);
+
+    let edge = |a: Vec4, b: Vec4, x: f32, y: f32| (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x);
+    let mut area = edge(screen[0], screen[1], screen[2].x, screen[2].y);
+    if area == 0.0 || !area.is_finite() {
+        return;
+    }
+    if area < 0.0 {
+        screen.swap(1, 2);
+        varyings.swap(1, 2);
+        area = -area;
+    }
+    // Every edge is shared with the neighbouring triangle in the opposite direction, so this makes
+    // sure that pixels exactly on an edge are only drawn once.
+    let owns_edge = |a: Vec4, b: Vec4| b.y - a.y > 0.0 || (b.y == a.y && b.x - a.x < 0.0);
+    let edges = [(screen[1], screen[2]), (screen[2], screen[0]), (screen[0], screen[1])];
+
+    let min_x = screen.iter().map(|v| v.x).fold(f32::INFINITY, f32::min).floor().max(0.0) as usize;
+    let max_x = screen.iter().map(|v| v.x).fold(f32::NEG_INFINITY, f32::max).ceil().min(width as f32) as usize;
+    let min_y = screen.iter().map(|v| v.y).fold(f32::INFINITY, f32::min).floor().max(0.0) as usize;
+    let max_y = screen.iter().map(|v| v.y).fold(f32::NEG_INFINITY, f32::max).ceil().min(height as f32) as usize;
+
+    let varyings_len = shader.varyings_len();
+    let mut pixel_varyings = vec![0.0; varyings_len];
+    let mut right_varyings = vec![0.0; varyings_len];
+    let mut below_varyings = vec![0.0; varyings_len];
+    let interpolate = |out: &mut [f32], x: f32, y: f32| {
+        let weights = [0, 1, 2].map(|i| edge(edges[i].0, edges[i].1, x, y) / area * screen[i].w);
+        let sum: f32 = weights.iter().sum();
+        for (index, value) in out.iter_mut().enumerate() {
+            *value = (0..3).map(|i| weights[i] * varyings[i][index]).sum::<f32>() / sum;
+        }
+    };
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let mut inside = true;
+            let mut barycentric = [0.0; 3];
+            for i in 0..3 {
+                let (a, b) = edges[i];
+                let value = edge(a, b, px, py);
+                if value < 0.0 || (value == 0.0 && !owns_edge(a, b)) {
+                    inside = false;
+                    break;
+                }
+                barycentric[i] = value / area;
+            }
+            if !inside {
+                continue;
+            }
+
+            let z = (0..3).map(|i| barycentric[i] * screen[i].z).sum::<f32>();
+            let pixel_index = y * width + x;
+            if !(0.0..=1.0).contains(&z) || z > target.depth[pixel_index] {
+                continue;
+            }
+
+            interpolate(&mut pixel_varyings, px, py);
+            let color = if shader.uses_derivatives() {
+                interpolate(&mut right_varyings, px + 1.0, py);
+                interpolate(&mut below_varyings, px, py + 1.0);
+                shader.run_pixel(inputs, &pixel_varyings, Some((&right_varyings, &below_varyings)))
+            } else {
+                shader.run_pixel(inputs, &pixel_varyings, None)
+            };
+
+            let src = Vec4 {
+                x: color.x.clamp(0.0, 1.0),
+                y: color.y.clamp(0.0, 1.0),
+                z: color.z.clamp(0.0, 1.0),
+                w: color.w.clamp(0.0, 1.0),
+            };
+            let dst = unpack_color(target.color.pixels[pixel_index]);
+            let inv_alpha = 1.0 - src.w;
+            target.color.pixels[pixel_index] = pack_color(Vec4 {
+                x: src.x + dst.x * inv_alpha,
+                y: src.y + dst.y * inv_alpha,
+                z: src.z + dst.z * inv_alpha,
+                w: src.w + dst.w * inv_alpha,
+            });
+            target.depth[pixel_index] = z;
+        }
+    }
+}
+
+fn unpack_color(color: u32) -> Vec4 {
+    Vec4 {
+        x: (color & 0xff) as f32 / 255.0,
+        y: ((color >> 8) & 0xff) as f32 / 255.0,
+        z: ((color >> 16) & 0xff) as f32 / 255.0,
+        w: ((color >> 24) & 0xff) as f32 / 255.0,
+    }
+}
+
+fn pack_color(color: Vec4) -> u32 {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u32;
+    channel(color.x) | (channel(color.y) << 8) | (channel(color.z) << 16) | (channel(color.w) << 24)
+}
+
+/// Bilinear sampling with wrapping, like `GL_LINEAR` and `GL_REPEAT`. `v` goes down the rows of `pixels`.
+fn sample_bilinear(width: usize, height: usize, pixels: &[u32], u: f32, v: f32) -> Vec4 {
+    if width == 0 || height == 0 {
+        return Vec4::default();
+    }
+    let x = u * width as f32 - 0.5;
+    let y = v * height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let fetch = |x: f32, y: f32| {
+        let x = (x as i64).rem_euclid(width as i64) as usize;
+        let y = (y as i64).rem_euclid(height as i64) as usize;
+        unpack_color(pixels[y * width + x])
+    };
+    let top = Vec4::from_lerp(fetch(x0, y0), fetch(x0 + 1.0, y0), fx);
+    let bottom = Vec4::from_lerp(fetch(x0, y0 + 1.0), fetch(x0 + 1.0, y0 + 1.0), fx);
+    Vec4::from_lerp(top, bottom, fy)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    static SHADER: Shader = Shader {
+        build_geom: Some(QuadIns::build_geom),
+        code_to_concatenate: &[
+            Cx::STD_SHADER,
+            QuadIns::SHADER,
+            code_fragment!(
+                r#"
+                fn pixel() -> vec4 {
+                    return vec4(0., 1., 0., 1.);
+                }
+                "#
+            ),
+        ],
+        ..Shader::DEFAULT
+    };
+
+    #[derive(Default)]
+    struct App {
+        window: Window,
+        pass: Pass,
+        view: View,
+    }
+
+    impl App {
+        fn draw(&mut self, cx: &mut Cx) {
+            self.window.create_inner_size = Some(vec2(40., 30.));
+            self.window.begin_window(cx);
+            self.pass.begin_pass(cx, vec4(0., 0., 1., 1.));
+            self.view.begin_view(cx, LayoutSize::FILL);
+            cx.add_instances(&SHADER, &[QuadIns::from_rect(Rect { pos: vec2(10., 10.), size: vec2(10., 5.) })]);
+            self.view.end_view(cx);
+            self.pass.end_pass(cx);
+            self.window.end_window(cx);
+        }
+    }
+
+    #[test]
+    fn test_headless_draw_quad() {
+        let mut cx = Cx::new_test();
+        let mut app = App::default();
+        let mut event_handler = |cx: &mut Cx, event: &mut Event| {
+            if let Event::System(SystemEvent::Draw) = event {
+                app.draw(cx);
+            }
+        };
+        cx.headless_start(&mut event_handler);
+
+        let framebuffer = cx.headless_window_framebuffer(0).unwrap();
+        assert_eq!((framebuffer.width, framebuffer.height), (40, 30));
+        assert_eq!(framebuffer.get_pixel(10, 10), vec4(0., 1., 0., 1.));
+        assert_eq!(framebuffer.get_pixel(19, 14), vec4(0., 1., 0., 1.));
+        assert_eq!(framebuffer.get_pixel(20, 14), vec4(0., 0., 1., 1.));
+        assert_eq!(framebuffer.get_pixel(5, 5), vec4(0., 0., 1., 1.));
+    }
+
+    #[test]
+    fn test_headless_overlay_after_main_view() {
+        let mut cx = Cx::new_test();
+        let mut app = App::default();
+        let mut overlay = View::default().with_is_overlay(true);
+        let mut event_handler = |cx: &mut Cx, event: &mut Event| {
+            if let Event::System(SystemEvent::Draw) = event {
+                app.window.create_inner_size = Some(vec2(40., 30.));
+                app.window.begin_window(cx);
+                app.pass.begin_pass(cx, vec4(0., 0., 1., 1.));
+                app.view.begin_view(cx, LayoutSize::FILL);
+                app.view.end_view(cx);
+                // Overlays can still be drawn after the main view has ended.
+                overlay.begin_view(cx, LayoutSize::FILL);
+                cx.add_instances(&SHADER, &[QuadIns::from_rect(Rect { pos: vec2(10., 10.), size: vec2(10., 5.) })]);
+                overlay.end_view(cx);
+                app.pass.end_pass(cx);
+                app.window.end_window(cx);
+            }
+        };
+        cx.headless_start(&mut event_handler);
+
+        let framebuffer = cx.headless_window_framebuffer(0).unwrap();
+        assert_eq!(framebuffer.get_pixel(10, 10), vec4(0., 1., 0., 1.));
+        assert_eq!(framebuffer.get_pixel(5, 5), vec4(0., 0., 1., 1.));
+    }
+
+    #[test]
+    fn test_headless_send_event_from_any_thread() {
+        let mut cx = Cx::new_test();
+        let mut timer_ids = vec![];
+        let mut event_handler = |_cx: &mut Cx, event: &mut Event| {
+            if let Event::Timer(TimerEvent { timer_id }) = event {
+                timer_ids.push(*timer_id);
+            }
+        };
+        cx.headless_start(&mut event_handler);
+
+        std::thread::spawn(|| {
+            for timer_id in 1..=3 {
+                Cx::send_event_from_any_thread(Event::Timer(TimerEvent { timer_id }));
+            }
+        })
+        .join()
+        .unwrap();
+        cx.headless_advance_time(&mut event_handler, 0.);
+        assert_eq!(timer_ids, vec![1, 2, 3]);
+    }
+}
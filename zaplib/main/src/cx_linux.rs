@@ -2,6 +2,7 @@
 
 use crate::cx_xlib::*;
 use crate::*;
+use std::collections::{BTreeSet, HashMap};
 
 impl Cx {
     pub fn event_loop<F>(&mut self, mut event_handler: F)
@@ -247,12 +248,6 @@ impl Cx {
         })
     }
 
-    /// TODO(JP): Generalize [`Cx::post_signal`] into this.
-    #[cfg(feature = "cef")]
-    pub(crate) fn send_event_from_any_thread(_event: Event) {
-        todo!();
-    }
-
     #[cfg(feature = "cef")]
     pub(crate) fn cef_schedule_message_pump_work(_delay_ms: i64) {
         todo!();
@@ -285,7 +280,13 @@ impl CxPlatformCommon for Cx {
 
     /// See [`CxPlatformCommon::post_signal`] for documentation.
     fn post_signal(signal: Signal, status: StatusId) {
-        XlibApp::post_signal(signal, status);
+        if signal.signal_id != 0 {
+            let mut signals = HashMap::new();
+            let mut new_set = BTreeSet::new();
+            new_set.insert(status);
+            signals.insert(signal, new_set);
+            Cx::send_event_from_any_thread(Event::Signal(SignalEvent { signals }));
+        }
     }
 
     /// See [`CxPlatformCommon::update_menu`] for documentation.
@@ -296,15 +297,25 @@ impl CxPlatformCommon for Cx {
         XlibApp::copy_text_to_clipboard(text);
     }
 
-    fn send_event_from_any_thread(_event: Event) {
-        todo!();
+    /// See [`CxPlatformCommon::set_primary_selection`] for documentation.
+    fn set_primary_selection(&mut self, text: &str) {
+        XlibApp::set_primary_selection(text);
+    }
+
+    /// See [`CxPlatformCommon::send_event_from_any_thread`] for documentation.
+    fn send_event_from_any_thread(event: Event) {
+        if let Err(event) = Cx::headless_send_event_from_any_thread(event) {
+            XlibApp::send_event_from_any_thread(event);
+        }
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Default)]
 pub(crate) struct CxPlatform {
     pub(crate) set_ime_position: Option<Vec2>,
     pub(crate) start_timer: Vec<(u64, f64, bool)>,
     pub(crate) stop_timer: Vec<u64>,
     pub(crate) desktop: CxDesktop,
+    /// Only set when running headless; see [`Cx::headless_start`].
+    pub(crate) headless: Option<Box<CxHeadless>>,
 }
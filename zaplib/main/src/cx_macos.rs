@@ -386,13 +386,16 @@ impl CxPlatformCommon for Cx {
         CocoaApp::copy_text_to_clipboard(text);
     }
 
+    /// See [`CxPlatformCommon::set_primary_selection`] for documentation.
+    fn set_primary_selection(&mut self, _text: &str) {}
+
     /// See [`CxPlatformCommon::send_event_from_any_thread`] for documentation.
     fn send_event_from_any_thread(event: Event) {
         CocoaApp::send_event_from_any_thread(event);
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Default)]
 pub(crate) struct CxPlatform {
     pub(crate) bytes_written: usize,
     pub(crate) draw_calls_done: usize,
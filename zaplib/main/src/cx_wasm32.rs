@@ -39,6 +39,11 @@ const MSG_TYPE_DRAG_ENTER: u32 = 27;
 const MSG_TYPE_DRAG_LEAVE: u32 = 28;
 const MSG_TYPE_DRAG_OVER: u32 = 29;
 const MSG_TYPE_CALL_RUST: u32 = 30;
+const MSG_TYPE_HTTP_RESPONSE: u32 = 31;
+const MSG_TYPE_HTTP_RESPONSE_HEAD: u32 = 32;
+const MSG_TYPE_HTTP_RESPONSE_CHUNK: u32 = 33;
+const MSG_TYPE_HTTP_RESPONSE_END: u32 = 34;
+const MSG_TYPE_HTTP_RESPONSE_ERROR: u32 = 35;
 
 impl Cx {
     /// Initialize global error handlers.
@@ -410,6 +415,38 @@ impl Cx {
                         callback_id,
                     }))));
                 }
+                MSG_TYPE_HTTP_RESPONSE => {
+                    let request_id = HttpRequestId(zerde_parser.parse_f64() as u64);
+                    let status = zerde_parser.parse_u32() as u16;
+                    let headers = parse_http_headers(&mut zerde_parser);
+                    let body = zerde_parser.parse_vec_ptr();
+                    let response = HttpResponse::Complete { status, headers, body };
+                    self.wasm_event_handler(Event::HttpResponse(HttpResponseEvent { request_id, response }));
+                }
+                MSG_TYPE_HTTP_RESPONSE_HEAD => {
+                    let request_id = HttpRequestId(zerde_parser.parse_f64() as u64);
+                    let status = zerde_parser.parse_u32() as u16;
+                    let headers = parse_http_headers(&mut zerde_parser);
+                    let response = HttpResponse::Head { status, headers };
+                    self.wasm_event_handler(Event::HttpResponse(HttpResponseEvent { request_id, response }));
+                }
+                MSG_TYPE_HTTP_RESPONSE_CHUNK => {
+                    let request_id = HttpRequestId(zerde_parser.parse_f64() as u64);
+                    let response = HttpResponse::Chunk(zerde_parser.parse_vec_ptr());
+                    self.wasm_event_handler(Event::HttpResponse(HttpResponseEvent { request_id, response }));
+                }
+                MSG_TYPE_HTTP_RESPONSE_END => {
+                    let request_id = HttpRequestId(zerde_parser.parse_f64() as u64);
+                    self.wasm_event_handler(Event::HttpResponse(HttpResponseEvent { request_id, response: HttpResponse::End }));
+                }
+                MSG_TYPE_HTTP_RESPONSE_ERROR => {
+                    let request_id = HttpRequestId(zerde_parser.parse_f64() as u64);
+                    let is_timeout = zerde_parser.parse_u32() == 1;
+                    let message = zerde_parser.parse_string();
+                    let error = if is_timeout { HttpError::Timeout } else { HttpError::Network(message) };
+                    let response = HttpResponse::Error(error);
+                    self.wasm_event_handler(Event::HttpResponse(HttpResponseEvent { request_id, response }));
+                }
                 _ => {
                     panic!("Message unknown {}", msg_type);
                 }
@@ -583,6 +620,20 @@ impl CxDesktopVsWasmCommon for Cx {
         self.platform.zerde_eventloop_msgs.websocket_send(url, data);
     }
 
+    /// See [`CxDesktopVsWasmCommon::fetch`] for documentation.
+    fn fetch(&mut self, request: HttpRequest) -> HttpRequestId {
+        let request_id = self.new_http_request_id();
+        self.platform.zerde_eventloop_msgs.fetch(request_id, &request);
+        request_id
+    }
+
+    /// See [`CxDesktopVsWasmCommon::cancel_fetch`] for documentation.
+    fn cancel_fetch(&mut self, request_id: HttpRequestId) {
+        if self.http_requests_in_flight.remove(&request_id) {
+            self.platform.zerde_eventloop_msgs.cancel_fetch(request_id);
+        }
+    }
+
     /// See [`CxDesktopVsWasmCommon::call_js`] for documentation.
     fn call_js(&mut self, name: &str, params: Vec<ZapParam>) {
         self.platform.zerde_eventloop_msgs.call_js(name, params);
@@ -641,6 +692,9 @@ impl CxPlatformCommon for Cx {
         self.platform.zerde_eventloop_msgs.text_copy_response(text);
     }
 
+    /// See [`CxPlatformCommon::set_primary_selection`] for documentation.
+    fn set_primary_selection(&mut self, _text: &str) {}
+
     fn send_event_from_any_thread(event: Event) {
         let event_ptr = Box::into_raw(Box::new(event));
         unsafe {
@@ -816,6 +870,31 @@ impl ZerdeEventloopMsgs {
 
         self.builder.build_zap_params(params);
     }
+
+    pub(crate) fn fetch(&mut self, request_id: HttpRequestId, request: &HttpRequest) {
+        self.builder.send_u32(19);
+        self.builder.send_f64(request_id.0 as f64);
+        self.builder.send_string(&request.method);
+        self.builder.send_string(&request.url);
+        self.builder.send_u32(request.headers.len() as u32);
+        for (name, value) in &request.headers {
+            self.builder.send_string(name);
+            self.builder.send_string(value);
+        }
+        self.builder.send_u8slice(&request.body);
+        self.builder.send_f64(request.timeout.unwrap_or(-1.));
+        self.builder.send_u32(if request.stream { 1 } else { 0 });
+    }
+
+    pub(crate) fn cancel_fetch(&mut self, request_id: HttpRequestId) {
+        self.builder.send_u32(20);
+        self.builder.send_f64(request_id.0 as f64);
+    }
+}
+
+fn parse_http_headers(zerde_parser: &mut ZerdeParser) -> Vec<(String, String)> {
+    let len = zerde_parser.parse_u32();
+    (0..len).map(|_| (zerde_parser.parse_string(), zerde_parser.parse_string())).collect()
 }
 
 // for use with sending wasm vec data
@@ -25,6 +25,9 @@ use winapi::um::{dwmapi, libloaderapi, winbase, winuser};
 
 static mut GLOBAL_WIN32_APP: *mut Win32App = 0 as *mut _;
 
+/// Message that [`Win32App::send_event_from_any_thread`] posts to wake up the event loop.
+const WM_QUEUED_EVENTS: UINT = winuser::WM_USER + 1;
+
 pub(crate) struct Win32App {
     pub(crate) time_start: u64,
     pub(crate) event_callback: Option<*mut dyn FnMut(&mut Win32App, &mut Vec<Event>) -> bool>,
@@ -35,6 +38,8 @@ pub(crate) struct Win32App {
     pub(crate) timers: Vec<Win32Timer>,
     pub(crate) free_timers: Vec<usize>,
     pub(crate) race_signals: Mutex<Vec<(usize, isize)>>,
+    /// Events sent using [`Win32App::send_event_from_any_thread`], in the order in which they were sent.
+    pub(crate) queued_events: Mutex<Vec<Event>>,
     pub(crate) loop_block: bool,
     pub(crate) dpi_functions: DpiFunctions,
     pub(crate) current_cursor: MouseCursor,
@@ -90,6 +95,7 @@ impl Win32App {
             class_name_wstr,
             time_start: precise_time_ns(),
             race_signals: Mutex::new(Vec::new()),
+            queued_events: Mutex::new(Vec::new()),
             event_callback: None,
             event_recur_block: false,
             event_loop_running: true,
@@ -277,6 +283,23 @@ impl Win32App {
         }
     }
 
+    /// Queue an [`Event`] from any thread, and wake up the event loop so it gets processed. Events are
+    /// delivered in the order in which they were sent.
+    pub(crate) fn send_event_from_any_thread(event: Event) {
+        unsafe {
+            if let Some(win32_app) = GLOBAL_WIN32_APP.as_ref() {
+                if let Ok(mut queued_events) = win32_app.queued_events.lock() {
+                    queued_events.push(event);
+                    // Without windows there's nothing to post to yet; the first window that gets created
+                    // picks up the queue.
+                    if !win32_app.all_windows.is_empty() {
+                        winuser::PostMessageW(win32_app.all_windows[0], WM_QUEUED_EVENTS, 0, 0);
+                    }
+                }
+            }
+        }
+    }
+
     pub(crate) fn terminate_event_loop(&mut self) {
         unsafe {
             if !self.all_windows.is_empty() {
@@ -428,6 +451,11 @@ impl Win32Window {
                 }
                 sigs.truncate(0);
             }
+            if let Ok(queued_events) = (*self.win32_app).queued_events.lock() {
+                if !queued_events.is_empty() {
+                    winuser::PostMessageW(hwnd, WM_QUEUED_EVENTS, 0, 0);
+                }
+            }
         }
     }
 
@@ -679,6 +707,18 @@ impl Win32Window {
                 signals.insert(Signal { signal_id: wparam as usize }, set);
                 window.do_callback(&mut vec![Event::Signal(SignalEvent { signals })]);
             }
+            WM_QUEUED_EVENTS => {
+                // Several messages may have been posted for one batch of events, in which case the
+                // later ones find the queue empty.
+                let mut queued_events = if let Ok(mut queued_events) = (*window.win32_app).queued_events.lock() {
+                    mem::take(&mut *queued_events)
+                } else {
+                    Vec::new()
+                };
+                if !queued_events.is_empty() {
+                    window.do_callback(&mut queued_events);
+                }
+            }
             winuser::WM_CLOSE => {
                 // close requested
                 let mut events = vec![Event::WindowCloseRequested(WindowCloseRequestedEvent {
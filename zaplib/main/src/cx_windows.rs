@@ -270,10 +270,9 @@ impl Cx {
         })
     }
 
-    /// TODO(JP): Generalize [`Cx::post_signal`] into this.
     #[cfg(feature = "cef")]
-    pub(crate) fn send_event_from_any_thread(_event: Event) {
-        todo!();
+    pub(crate) fn send_event_from_any_thread(event: Event) {
+        Win32App::send_event_from_any_thread(event);
     }
 
     #[cfg(feature = "cef")]
@@ -319,8 +318,14 @@ impl CxPlatformCommon for Cx {
         Win32App::copy_text_to_clipboard(text);
     }
 
-    fn send_event_from_any_thread(_event: Event) {
-        todo!();
+    /// See [`CxPlatformCommon::set_primary_selection`] for documentation.
+    fn set_primary_selection(&mut self, _text: &str) {}
+
+    /// See [`CxPlatformCommon::send_event_from_any_thread`] for documentation.
+    fn send_event_from_any_thread(event: Event) {
+        if let Err(event) = Cx::headless_send_event_from_any_thread(event) {
+            Win32App::send_event_from_any_thread(event);
+        }
     }
 }
 
@@ -4,7 +4,7 @@
 
 use crate::*;
 use libc::timeval;
-use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::collections::{HashMap, VecDeque};
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::slice;
@@ -28,9 +28,11 @@ static mut GLOBAL_XLIB_APP: *mut XlibApp = 0 as *mut _;
 pub(crate) struct XlibApp {
     pub(crate) display: *mut X11_sys::Display,
     pub(crate) xim: X11_sys::XIM,
-    pub(crate) clipboard: String,
     pub(crate) display_fd: c_int,
-    pub(crate) signal_fd: c_int,
+    /// Read end of a pipe that [`XlibApp::send_event_from_any_thread`] writes to, so it can wake up the
+    /// `select` in [`XlibApp::event_loop`].
+    pub(crate) wakeup_read_fd: c_int,
+    pub(crate) wakeup_write_fd: c_int,
     pub(crate) window_map: HashMap<c_ulong, *mut XlibWindow>,
     pub(crate) time_start: u64,
     pub(crate) last_scroll_time: f64,
@@ -41,11 +43,13 @@ pub(crate) struct XlibApp {
     pub(crate) event_loop_running: bool,
     pub(crate) timers: VecDeque<XlibTimer>,
     pub(crate) free_timers: Vec<usize>,
-    pub(crate) signals: Mutex<Vec<Event>>,
+    /// Events sent using [`XlibApp::send_event_from_any_thread`], in the order in which they were sent.
+    pub(crate) queued_events: Mutex<Vec<Event>>,
     pub(crate) loop_block: bool,
     pub(crate) current_cursor: MouseCursor,
+    /// The X server time of the last key or button event, which we use when taking ownership of a selection.
+    pub(crate) last_event_time: X11_sys::Time,
 
-    pub(crate) atom_clipboard: X11_sys::Atom,
     pub(crate) atom_net_wm_moveresize: X11_sys::Atom,
     pub(crate) atom_wm_delete_window: X11_sys::Atom,
     pub(crate) atom_wm_protocols: X11_sys::Atom,
@@ -53,14 +57,9 @@ pub(crate) struct XlibApp {
     pub(crate) atom_net_wm_state: X11_sys::Atom,
     pub(crate) atom_new_wm_state_maximized_horz: X11_sys::Atom,
     pub(crate) atom_new_wm_state_maximized_vert: X11_sys::Atom,
-    pub(crate) atom_targets: X11_sys::Atom,
-    pub(crate) atom_utf8_string: X11_sys::Atom,
-    pub(crate) atom_text: X11_sys::Atom,
-    pub(crate) atom_multiple: X11_sys::Atom,
-    pub(crate) atom_text_plain: X11_sys::Atom,
-    pub(crate) atom_atom: X11_sys::Atom,
 
     pub(crate) dnd: Dnd,
+    pub(crate) selections: Selections,
 }
 
 #[derive(Clone)]
@@ -113,9 +112,11 @@ impl XlibApp {
             let display = X11_sys::XOpenDisplay(ptr::null());
             let display_fd = X11_sys::XConnectionNumber(display);
             let xim = X11_sys::XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
-            let signal_fd = 0i32; //libc::pipe();
+            let mut wakeup_fds = [0 as c_int; 2];
+            if libc::pipe2(wakeup_fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) != 0 {
+                panic!("Failed to create wakeup pipe");
+            }
             XlibApp {
-                atom_clipboard: X11_sys::XInternAtom(display, CString::new("CLIPBOARD").unwrap().as_ptr(), 0),
                 atom_net_wm_moveresize: X11_sys::XInternAtom(display, CString::new("_NET_WM_MOVERESIZE").unwrap().as_ptr(), 0),
                 atom_wm_delete_window: X11_sys::XInternAtom(display, CString::new("WM_DELETE_WINDOW").unwrap().as_ptr(), 0),
                 atom_wm_protocols: X11_sys::XInternAtom(display, CString::new("WM_PROTOCOLS").unwrap().as_ptr(), 0),
@@ -131,22 +132,16 @@ impl XlibApp {
                     CString::new("_NET_WM_STATE_MAXIMIZED_VERT").unwrap().as_ptr(),
                     0,
                 ),
-                atom_targets: X11_sys::XInternAtom(display, CString::new("TARGETS").unwrap().as_ptr(), 0),
-                atom_utf8_string: X11_sys::XInternAtom(display, CString::new("UTF8_STRING").unwrap().as_ptr(), 1),
-                atom_atom: X11_sys::XInternAtom(display, CString::new("ATOM").unwrap().as_ptr(), 0),
-                atom_text: X11_sys::XInternAtom(display, CString::new("TEXT").unwrap().as_ptr(), 0),
-                atom_text_plain: X11_sys::XInternAtom(display, CString::new("text/plain").unwrap().as_ptr(), 0),
-                atom_multiple: X11_sys::XInternAtom(display, CString::new("MULTIPLE").unwrap().as_ptr(), 0),
                 xim,
                 display,
                 display_fd,
-                signal_fd,
-                clipboard: String::new(),
+                wakeup_read_fd: wakeup_fds[0],
+                wakeup_write_fd: wakeup_fds[1],
                 last_scroll_time: 0.0,
                 last_click_time: 0.0,
                 last_click_pos: (0, 0),
                 window_map: HashMap::new(),
-                signals: Mutex::new(Vec::new()),
+                queued_events: Mutex::new(Vec::new()),
                 time_start: precise_time_ns(),
                 event_callback: None,
                 event_recur_block: false,
@@ -155,7 +150,9 @@ impl XlibApp {
                 timers: VecDeque::new(),
                 free_timers: Vec::new(),
                 current_cursor: MouseCursor::Default,
+                last_event_time: X11_sys::CurrentTime as X11_sys::Time,
                 dnd: Dnd::new(display),
+                selections: Selections::new(display),
             }
         }
     }
@@ -191,6 +188,7 @@ impl XlibApp {
                     let mut fds = mem::MaybeUninit::uninit();
                     libc::FD_ZERO(fds.as_mut_ptr());
                     libc::FD_SET(self.display_fd, fds.as_mut_ptr());
+                    libc::FD_SET(self.wakeup_read_fd, fds.as_mut_ptr());
                     // If there are any timers, we set the timeout for select to the `delta_timeout`
                     // of the first timer that should be fired. Otherwise, we set the timeout to
                     // None, so that select will block indefinitely.
@@ -205,7 +203,7 @@ impl XlibApp {
                         }
                     });
                     let _nfds = libc::select(
-                        self.display_fd + 1,
+                        self.display_fd.max(self.wakeup_read_fd) + 1,
                         fds.as_mut_ptr(),
                         ptr::null_mut(),
                         ptr::null_mut(),
@@ -248,109 +246,22 @@ impl XlibApp {
                             let selection = event.xselection;
                             if selection.property == self.dnd.atoms.selection {
                                 self.dnd.handle_selection_event(&selection);
-                            } else {
-                                // first get the size of the thing
-                                let mut actual_type = mem::MaybeUninit::uninit();
-                                let mut actual_format = mem::MaybeUninit::uninit();
-                                let mut n_items = mem::MaybeUninit::uninit();
-                                let mut bytes_to_read = mem::MaybeUninit::uninit();
-                                let mut ret = mem::MaybeUninit::uninit();
-                                X11_sys::XGetWindowProperty(
-                                    self.display,
-                                    selection.requestor,
-                                    selection.property,
-                                    0,
-                                    0,
-                                    0,
-                                    X11_sys::AnyPropertyType as c_ulong,
-                                    actual_type.as_mut_ptr(),
-                                    actual_format.as_mut_ptr(),
-                                    n_items.as_mut_ptr(),
-                                    bytes_to_read.as_mut_ptr(),
-                                    ret.as_mut_ptr(),
-                                );
-                                //let actual_type = actual_type.assume_init();
-                                //let actual_format = actual_format.assume_init();
-                                //let n_items = n_items.assume_init();
-                                let bytes_to_read = bytes_to_read.assume_init();
-                                //let mut ret = ret.assume_init();
-                                let mut bytes_after = mem::MaybeUninit::uninit();
-                                X11_sys::XGetWindowProperty(
-                                    self.display,
-                                    selection.requestor,
-                                    selection.property,
-                                    0,
-                                    bytes_to_read as c_long,
-                                    0,
-                                    X11_sys::AnyPropertyType as c_ulong,
-                                    actual_type.as_mut_ptr(),
-                                    actual_format.as_mut_ptr(),
-                                    n_items.as_mut_ptr(),
-                                    bytes_after.as_mut_ptr(),
-                                    ret.as_mut_ptr(),
-                                );
-                                let ret = ret.assume_init();
-                                //let bytes_after = bytes_after.assume_init();
-                                if !ret.is_null() && bytes_to_read > 0 {
-                                    let utf8_slice =
-                                        std::slice::from_raw_parts::<u8>(ret as *const _ as *const u8, bytes_to_read as usize);
-                                    if let Ok(utf8_string) = String::from_utf8(utf8_slice.to_vec()) {
-                                        self.do_callback(&mut vec![Event::TextInput(TextInputEvent {
-                                            input: utf8_string,
-                                            was_paste: true,
-                                            replace_last: false,
-                                        })]);
-                                    }
-                                    X11_sys::XFree(ret as *mut _ as *mut c_void);
+                            } else if selection.requestor == self.selections.window {
+                                if let Some(text) = self.selections.handle_selection_notify(&selection) {
+                                    self.do_paste(text);
                                 }
                             }
                         }
                         X11_sys::SelectionRequest => {
-                            let request = event.xselectionrequest;
-                            let mut response = X11_sys::XSelectionEvent {
-                                type_: X11_sys::SelectionNotify as i32,
-                                serial: 0,
-                                send_event: 0,
-                                display: self.display,
-                                requestor: request.requestor,
-                                selection: request.selection,
-                                target: request.target,
-                                time: request.time,
-                                property: request.property,
-                            };
-                            if request.target == self.atom_targets {
-                                let mut targets = [self.atom_utf8_string];
-                                X11_sys::XChangeProperty(
-                                    self.display,
-                                    request.requestor,
-                                    request.property,
-                                    4,
-                                    32,
-                                    X11_sys::PropModeReplace as i32,
-                                    targets.as_mut() as *mut _ as *mut c_uchar,
-                                    targets.len() as i32,
-                                );
-                            } else if request.target == self.atom_utf8_string {
-                                X11_sys::XChangeProperty(
-                                    self.display,
-                                    request.requestor,
-                                    request.property,
-                                    self.atom_utf8_string,
-                                    8,
-                                    X11_sys::PropModeReplace as i32,
-                                    self.clipboard.as_ptr() as *const _ as *const c_uchar,
-                                    self.clipboard.len() as i32,
-                                );
-                            } else {
-                                response.property = 0;
+                            self.selections.handle_selection_request(&event.xselectionrequest);
+                        }
+                        X11_sys::SelectionClear => {
+                            self.selections.handle_selection_clear(&event.xselectionclear);
+                        }
+                        X11_sys::PropertyNotify => {
+                            if let Some(text) = self.selections.handle_property_notify(&event.xproperty) {
+                                self.do_paste(text);
                             }
-                            X11_sys::XSendEvent(
-                                self.display,
-                                request.requestor,
-                                1,
-                                0,
-                                &mut response as *mut _ as *mut X11_sys::XEvent,
-                            );
                         }
                         X11_sys::DestroyNotify => {
                             // our window got destroyed
@@ -489,6 +400,7 @@ impl XlibApp {
                             // mouse down
                             let button = event.xbutton;
                             let time_now = self.time_now();
+                            self.last_event_time = button.time;
                             if let Some(window_ptr) = self.window_map.get(&button.window) {
                                 let window = &mut (**window_ptr);
                                 X11_sys::XSetInputFocus(
@@ -579,18 +491,27 @@ impl XlibApp {
                                     }
                                 }
                             }
+                            if button.button == 2 {
+                                // middle click pastes the primary selection, after the pointer down moved the cursor
+                                let primary = self.selections.atoms.primary;
+                                if let Some(text) = self.selections.request(primary, button.time) {
+                                    self.do_paste(text);
+                                }
+                            }
                             self.last_click_time = time_now;
                             self.last_click_pos = (button.x_root, button.y_root);
                         }
                         X11_sys::ButtonRelease => {
                             // mouse up
                             let button = event.xbutton;
+                            self.last_event_time = button.time;
                             if let Some(window_ptr) = self.window_map.get(&button.window) {
                                 let window = &mut (**window_ptr);
                                 window.send_pointer_up(button.button as usize, self.xkeystate_to_modifiers(button.state))
                             }
                         }
                         X11_sys::KeyPress => {
+                            self.last_event_time = event.xkey.time;
                             if let Some(window_ptr) = self.window_map.get(&event.xkey.window) {
                                 let window = &mut (**window_ptr);
                                 let block_text = if event.xkey.keycode != 0 {
@@ -602,23 +523,10 @@ impl XlibApp {
                                             KeyCode::KeyV => {
                                                 // paste
                                                 // request the pasteable text from the other side
-                                                X11_sys::XConvertSelection(
-                                                    self.display,
-                                                    self.atom_clipboard,
-                                                    self.atom_utf8_string,
-                                                    self.atom_clipboard,
-                                                    window.window.unwrap(),
-                                                    event.xkey.time,
-                                                );
-                                                /*
-                                                self.do_callback(&mut vec![
-                                                    Event::TextInput(TextInputEvent {
-                                                        input: String::new(),
-                                                        was_paste: true,
-                                                        replace_last: false
-                                                    })
-                                                ]);
-                                                */
+                                                let clipboard = self.selections.atoms.clipboard;
+                                                if let Some(text) = self.selections.request(clipboard, event.xkey.time) {
+                                                    self.do_paste(text);
+                                                }
                                             }
                                             KeyCode::KeyX | KeyCode::KeyC => {
                                                 let mut events = vec![Event::TextCopy];
@@ -706,16 +614,16 @@ impl XlibApp {
                         _ => {}
                     }
                 }
-                // process all signals in the queue
-                let mut proc_signals = if let Ok(mut signals) = self.signals.lock() {
-                    let sigs = signals.clone();
-                    signals.truncate(0);
-                    sigs
+                // process all events sent from other threads, after emptying the wakeup pipe so that
+                // any event sent after taking the queue will wake up the next `select`
+                self.drain_wakeup_pipe();
+                let mut queued_events = if let Ok(mut queued_events) = self.queued_events.lock() {
+                    mem::take(&mut *queued_events)
                 } else {
                     Vec::new()
                 };
-                if !proc_signals.is_empty() {
-                    self.do_callback(&mut proc_signals);
+                if !queued_events.is_empty() {
+                    self.do_callback(&mut queued_events);
                 }
 
                 self.do_callback(&mut vec![Event::System(SystemEvent::Paint)]);
@@ -803,21 +711,26 @@ impl XlibApp {
         }
     }
 
-    pub(crate) fn post_signal(signal: Signal, status: StatusId) {
+    /// Queue an [`Event`] from any thread, and wake up the event loop so it gets processed. Events are
+    /// delivered in the order in which they were sent.
+    pub(crate) fn send_event_from_any_thread(event: Event) {
         unsafe {
-            if let Ok(mut signals_locked) = (*GLOBAL_XLIB_APP).signals.lock() {
-                let mut signals = HashMap::new();
-                let mut set = BTreeSet::new();
-                set.insert(status);
-                signals.insert(signal, set);
-                signals_locked.push(Event::Signal(SignalEvent { signals }));
-                //let mut f = unsafe { File::from_raw_fd((*GLOBAL_XLIB_APP).display_fd) };
-                //let _ = write!(&mut f, "\0");
-                // !TODO unblock the select!
+            if let Some(app) = GLOBAL_XLIB_APP.as_ref() {
+                if let Ok(mut queued_events) = app.queued_events.lock() {
+                    queued_events.push(event);
+                    // If the pipe is full the event loop has plenty of wakeups pending already, so we
+                    // can ignore errors here.
+                    libc::write(app.wakeup_write_fd, [0u8].as_ptr() as *const c_void, 1);
+                }
             }
         }
     }
 
+    fn drain_wakeup_pipe(&self) {
+        let mut buf = [0u8; 64];
+        unsafe { while libc::read(self.wakeup_read_fd, buf.as_mut_ptr() as *mut c_void, buf.len()) > 0 {} }
+    }
+
     pub(crate) fn terminate_event_loop(&mut self) {
         // maybe need to do more here
         self.event_loop_running = false;
@@ -1039,19 +952,26 @@ impl XlibApp {
         }
     }
 
-    pub(crate) fn copy_text_to_clipboard(_text: &str) {
-        // store the text on the clipboard
-        // TODO(JP): do this in a way that is not window-specific;
-        // see e.g. https://stackoverflow.com/a/41970363
-        // let app = *GLOBAL_XLIB_APP;
-        // app.clipboard = text.clone();
-        // X11_sys::XSetSelectionOwner(
-        //     app.display,
-        //     app.atom_clipboard,
-        //     window.window.unwrap(),
-        //     event.xkey.time,
-        // );
-        // X11_sys::XFlush(app.display);
+    pub(crate) fn copy_text_to_clipboard(text: &str) {
+        unsafe {
+            if let Some(app) = GLOBAL_XLIB_APP.as_mut() {
+                let clipboard = app.selections.atoms.clipboard;
+                app.selections.set_owner(clipboard, text, app.last_event_time);
+            }
+        }
+    }
+
+    pub(crate) fn set_primary_selection(text: &str) {
+        unsafe {
+            if let Some(app) = GLOBAL_XLIB_APP.as_mut() {
+                let primary = app.selections.atoms.primary;
+                app.selections.set_owner(primary, text, app.last_event_time);
+            }
+        }
+    }
+
+    fn do_paste(&mut self, text: String) {
+        self.do_callback(&mut vec![Event::TextInput(TextInputEvent { input: text, was_paste: true, replace_last: false })]);
     }
 }
 
@@ -1919,3 +1839,368 @@ impl DndAtoms {
         }
     }
 }
+
+/// Implements the X11 selections that are used for copy and paste, see
+/// <https://www.x.org/releases/X11R7.6/doc/xorg-docs/specs/ICCCM/icccm.html#use_of_selection_atoms>.
+///
+/// We own the CLIPBOARD selection when copying text, and the PRIMARY selection when selecting text, using a hidden
+/// window, so that it keeps working regardless of which of our windows is open. Pasting is done by asking the owner
+/// which targets (formats) it supports, and then converting the selection to the best text format. Large payloads
+/// are transferred in chunks in both directions, using the INCR mechanism.
+pub(crate) struct Selections {
+    atoms: SelectionAtoms,
+    display: *mut X11_sys::Display,
+    /// Hidden window that owns our selections, and that receives pasted data.
+    window: X11_sys::Window,
+    /// The text that we offer as the CLIPBOARD selection, if we own it.
+    clipboard: String,
+    /// The text that we offer as the PRIMARY selection, if we own it.
+    primary: String,
+    /// Payloads bigger than this (in bytes) are sent using INCR.
+    max_chunk_size: usize,
+    /// The paste that we're currently waiting for.
+    paste: Option<PasteRequest>,
+    /// Payloads that we're currently sending using INCR.
+    incr_transfers: Vec<IncrTransfer>,
+}
+
+struct PasteRequest {
+    selection: X11_sys::Atom,
+    /// Set once the owner has started an INCR transfer, after which we receive the data in chunks.
+    incr_data: Option<Vec<u8>>,
+}
+
+struct IncrTransfer {
+    requestor: X11_sys::Window,
+    property: X11_sys::Atom,
+    type_: X11_sys::Atom,
+    data: Vec<u8>,
+    /// How much of [`IncrTransfer::data`] has been sent.
+    offset: usize,
+}
+
+impl Selections {
+    unsafe fn new(display: *mut X11_sys::Display) -> Selections {
+        let window = X11_sys::XCreateSimpleWindow(display, X11_sys::XDefaultRootWindow(display), 0, 0, 1, 1, 0, 0, 0);
+        // We need to know when properties change on our window, for receiving INCR transfers.
+        X11_sys::XSelectInput(display, window, X11_sys::PropertyChangeMask as c_long);
+        // `XMaxRequestSize` is in units of 4 bytes; leave some room for the rest of the request.
+        let max_chunk_size = (X11_sys::XMaxRequestSize(display) as usize * 4).saturating_sub(100).max(1024);
+        Selections {
+            atoms: SelectionAtoms::new(display),
+            display,
+            window,
+            clipboard: String::new(),
+            primary: String::new(),
+            max_chunk_size,
+            paste: None,
+            incr_transfers: vec![],
+        }
+    }
+
+    /// Start offering `text` as the given `selection` (CLIPBOARD or PRIMARY).
+    unsafe fn set_owner(&mut self, selection: X11_sys::Atom, text: &str, time: X11_sys::Time) {
+        if selection == self.atoms.clipboard {
+            self.clipboard = text.to_string();
+        } else {
+            self.primary = text.to_string();
+        }
+        X11_sys::XSetSelectionOwner(self.display, selection, self.window, time);
+        X11_sys::XFlush(self.display);
+    }
+
+    /// Request the text of the given `selection`. If we own it ourselves, the text is returned right away.
+    /// Otherwise the text is returned later on by [`Selections::handle_selection_notify`] or
+    /// [`Selections::handle_property_notify`].
+    unsafe fn request(&mut self, selection: X11_sys::Atom, time: X11_sys::Time) -> Option<String> {
+        if X11_sys::XGetSelectionOwner(self.display, selection) == self.window {
+            return Some(if selection == self.atoms.clipboard { self.clipboard.clone() } else { self.primary.clone() });
+        }
+        self.paste = Some(PasteRequest { selection, incr_data: None });
+        self.convert_selection(selection, self.atoms.targets, time);
+        None
+    }
+
+    unsafe fn convert_selection(&self, selection: X11_sys::Atom, target: X11_sys::Atom, time: X11_sys::Time) {
+        X11_sys::XConvertSelection(self.display, selection, target, self.atoms.property, self.window, time);
+        X11_sys::XFlush(self.display);
+    }
+
+    /// Handles a XSelectionEvent, which the owner of a selection sends in response to
+    /// [`Selections::convert_selection`].
+    unsafe fn handle_selection_notify(&mut self, event: &X11_sys::XSelectionEvent) -> Option<String> {
+        if self.paste.as_ref()?.selection != event.selection {
+            return None;
+        }
+        if event.property == X11_sys::None as X11_sys::Atom {
+            if event.target == self.atoms.targets {
+                // The owner doesn't support TARGETS, so just try the most common text format.
+                self.convert_selection(event.selection, self.atoms.utf8_string, event.time);
+            } else {
+                self.paste = None;
+            }
+            return None;
+        }
+
+        let (type_, data) = self.take_property();
+        if event.target == self.atoms.targets {
+            let targets: Vec<X11_sys::Atom> = data
+                .chunks_exact(mem::size_of::<X11_sys::Atom>())
+                .map(|chunk| X11_sys::Atom::from_ne_bytes(chunk.try_into().unwrap()))
+                .collect();
+            let target =
+                self.atoms.text_targets().into_iter().find(|target| targets.contains(target)).unwrap_or(self.atoms.utf8_string);
+            self.convert_selection(event.selection, target, event.time);
+            None
+        } else if type_ == self.atoms.incr {
+            // Deleting the property (in `take_property`) tells the owner to start sending chunks.
+            self.paste.as_mut().unwrap().incr_data = Some(vec![]);
+            None
+        } else {
+            self.paste = None;
+            Some(self.atoms.decode_text(type_, &data))
+        }
+    }
+
+    /// Handles a XPropertyEvent, which we use for INCR transfers in both directions.
+    unsafe fn handle_property_notify(&mut self, event: &X11_sys::XPropertyEvent) -> Option<String> {
+        if event.state == X11_sys::PropertyDelete as c_int {
+            // The requestor has read the previous chunk, so send the next one. An empty chunk ends the transfer.
+            let index = self
+                .incr_transfers
+                .iter()
+                .position(|transfer| transfer.requestor == event.window && transfer.property == event.atom)?;
+            let transfer = &mut self.incr_transfers[index];
+            let end = (transfer.offset + self.max_chunk_size).min(transfer.data.len());
+            X11_sys::XChangeProperty(
+                self.display,
+                transfer.requestor,
+                transfer.property,
+                transfer.type_,
+                8,
+                X11_sys::PropModeReplace as c_int,
+                transfer.data[transfer.offset..end].as_ptr(),
+                (end - transfer.offset) as c_int,
+            );
+            if transfer.offset == end {
+                self.incr_transfers.remove(index);
+            } else {
+                transfer.offset = end;
+            }
+            X11_sys::XFlush(self.display);
+        } else if event.state == X11_sys::PropertyNewValue as c_int
+            && event.window == self.window
+            && event.atom == self.atoms.property
+            && self.paste.as_ref().map_or(false, |paste| paste.incr_data.is_some())
+        {
+            // The owner has sent the next chunk; an empty chunk means that we have everything.
+            let (type_, chunk) = self.take_property();
+            if chunk.is_empty() {
+                let data = self.paste.take().unwrap().incr_data.unwrap();
+                return Some(self.atoms.decode_text(type_, &data));
+            }
+            self.paste.as_mut().unwrap().incr_data.as_mut().unwrap().extend_from_slice(&chunk);
+        }
+        None
+    }
+
+    /// Handles a XSelectionRequestEvent, which another application sends when it wants to paste a selection that
+    /// we own.
+    unsafe fn handle_selection_request(&mut self, request: &X11_sys::XSelectionRequestEvent) {
+        // Obsolete clients don't set a property, in which case we should use the target as the property.
+        let property = if request.property == X11_sys::None as X11_sys::Atom { request.target } else { request.property };
+        let text = if request.selection == self.atoms.clipboard {
+            Some(&self.clipboard)
+        } else if request.selection == self.atoms.primary {
+            Some(&self.primary)
+        } else {
+            None
+        };
+
+        let success = match text {
+            Some(_) if request.target == self.atoms.targets => {
+                let mut targets = vec![self.atoms.targets];
+                targets.extend(self.atoms.text_targets());
+                X11_sys::XChangeProperty(
+                    self.display,
+                    request.requestor,
+                    property,
+                    4, // XA_ATOM
+                    32,
+                    X11_sys::PropModeReplace as c_int,
+                    targets.as_ptr() as *const c_uchar,
+                    targets.len() as c_int,
+                );
+                true
+            }
+            Some(text) if self.atoms.text_targets().contains(&request.target) => {
+                let (type_, data) = self.atoms.encode_text(request.target, text);
+                if data.len() > self.max_chunk_size {
+                    // Tell the requestor how big the payload is, and send it in chunks whenever it deletes the
+                    // property, which we need to get notified about.
+                    X11_sys::XSelectInput(self.display, request.requestor, X11_sys::PropertyChangeMask as c_long);
+                    let size = data.len() as c_ulong;
+                    X11_sys::XChangeProperty(
+                        self.display,
+                        request.requestor,
+                        property,
+                        self.atoms.incr,
+                        32,
+                        X11_sys::PropModeReplace as c_int,
+                        &size as *const c_ulong as *const c_uchar,
+                        1,
+                    );
+                    self.incr_transfers
+                        .retain(|transfer| transfer.requestor != request.requestor || transfer.property != property);
+                    self.incr_transfers.push(IncrTransfer { requestor: request.requestor, property, type_, data, offset: 0 });
+                } else {
+                    X11_sys::XChangeProperty(
+                        self.display,
+                        request.requestor,
+                        property,
+                        type_,
+                        8,
+                        X11_sys::PropModeReplace as c_int,
+                        data.as_ptr(),
+                        data.len() as c_int,
+                    );
+                }
+                true
+            }
+            _ => false,
+        };
+
+        let mut response = X11_sys::XSelectionEvent {
+            type_: X11_sys::SelectionNotify as c_int,
+            serial: 0,
+            send_event: 0,
+            display: self.display,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            time: request.time,
+            property: if success { property } else { X11_sys::None as X11_sys::Atom },
+        };
+        X11_sys::XSendEvent(
+            self.display,
+            request.requestor,
+            X11_sys::False as c_int,
+            X11_sys::NoEventMask as c_long,
+            &mut response as *mut X11_sys::XSelectionEvent as *mut X11_sys::XEvent,
+        );
+        X11_sys::XFlush(self.display);
+    }
+
+    /// Handles a XSelectionClearEvent, which we get when another application takes ownership of a selection.
+    unsafe fn handle_selection_clear(&mut self, event: &X11_sys::XSelectionClearEvent) {
+        if event.selection == self.atoms.clipboard {
+            self.clipboard.clear();
+        } else if event.selection == self.atoms.primary {
+            self.primary.clear();
+        }
+    }
+
+    /// Reads and deletes [`SelectionAtoms::property`] from our window, returning its type and contents. Note that
+    /// Xlib returns items of format 32 as [`c_long`]s.
+    unsafe fn take_property(&self) -> (X11_sys::Atom, Vec<u8>) {
+        let mut data = Vec::new();
+        let mut offset = 0;
+        let length = 1 << 16;
+        let mut actual_type = 0;
+        let mut actual_format = 0;
+        let mut nitems = 0;
+        let mut bytes_after = 0;
+        let mut prop = ptr::null_mut();
+        loop {
+            X11_sys::XGetWindowProperty(
+                self.display,
+                self.window,
+                self.atoms.property,
+                offset,
+                length,
+                X11_sys::False as c_int,
+                X11_sys::AnyPropertyType as X11_sys::Atom,
+                &mut actual_type,
+                &mut actual_format,
+                &mut nitems,
+                &mut bytes_after,
+                &mut prop,
+            );
+            if prop.is_null() {
+                break;
+            }
+            let item_size = match actual_format {
+                16 => mem::size_of::<std::os::raw::c_short>(),
+                32 => mem::size_of::<c_long>(),
+                _ => 1,
+            };
+            data.extend_from_slice(slice::from_raw_parts(prop as *const u8, nitems as usize * item_size));
+            X11_sys::XFree(prop as *mut c_void);
+            if bytes_after == 0 {
+                break;
+            }
+            // `offset` and `length` are in units of 4 bytes.
+            offset += length;
+        }
+        X11_sys::XDeleteProperty(self.display, self.window, self.atoms.property);
+        X11_sys::XFlush(self.display);
+        (actual_type, data)
+    }
+}
+
+struct SelectionAtoms {
+    clipboard: X11_sys::Atom,
+    incr: X11_sys::Atom,
+    primary: X11_sys::Atom,
+    /// The property on [`Selections::window`] that we ask selection owners to put their data in.
+    property: X11_sys::Atom,
+    string: X11_sys::Atom,
+    targets: X11_sys::Atom,
+    text: X11_sys::Atom,
+    text_plain: X11_sys::Atom,
+    text_plain_utf8: X11_sys::Atom,
+    utf8_string: X11_sys::Atom,
+}
+
+impl SelectionAtoms {
+    unsafe fn new(display: *mut X11_sys::Display) -> SelectionAtoms {
+        SelectionAtoms {
+            clipboard: X11_sys::XInternAtom(display, CString::new("CLIPBOARD").unwrap().as_ptr(), 0),
+            incr: X11_sys::XInternAtom(display, CString::new("INCR").unwrap().as_ptr(), 0),
+            primary: X11_sys::XInternAtom(display, CString::new("PRIMARY").unwrap().as_ptr(), 0),
+            property: X11_sys::XInternAtom(display, CString::new("ZAPLIB_SELECTION").unwrap().as_ptr(), 0),
+            string: X11_sys::XInternAtom(display, CString::new("STRING").unwrap().as_ptr(), 0),
+            targets: X11_sys::XInternAtom(display, CString::new("TARGETS").unwrap().as_ptr(), 0),
+            text: X11_sys::XInternAtom(display, CString::new("TEXT").unwrap().as_ptr(), 0),
+            text_plain: X11_sys::XInternAtom(display, CString::new("text/plain").unwrap().as_ptr(), 0),
+            text_plain_utf8: X11_sys::XInternAtom(display, CString::new("text/plain;charset=utf-8").unwrap().as_ptr(), 0),
+            utf8_string: X11_sys::XInternAtom(display, CString::new("UTF8_STRING").unwrap().as_ptr(), 0),
+        }
+    }
+
+    /// The text formats that we support, from most to least preferred.
+    fn text_targets(&self) -> [X11_sys::Atom; 5] {
+        [self.utf8_string, self.text_plain_utf8, self.text_plain, self.string, self.text]
+    }
+
+    /// Returns the type and contents for sending `text` as the given `target`. STRING is Latin-1; for everything
+    /// else we use UTF-8.
+    fn encode_text(&self, target: X11_sys::Atom, text: &str) -> (X11_sys::Atom, Vec<u8>) {
+        if target == self.string {
+            (target, text.chars().map(|c| if (c as u32) < 256 { c as u8 } else { b'?' }).collect())
+        } else if target == self.text {
+            (self.utf8_string, text.as_bytes().to_vec())
+        } else {
+            (target, text.as_bytes().to_vec())
+        }
+    }
+
+    /// The inverse of [`SelectionAtoms::encode_text`].
+    fn decode_text(&self, type_: X11_sys::Atom, data: &[u8]) -> String {
+        if type_ == self.string {
+            data.iter().map(|&b| b as char).collect()
+        } else {
+            String::from_utf8_lossy(data).into_owned()
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Native implementation of [`CxDesktopVsWasmCommon::fetch`], which runs every request on its own thread using
+//! `ureq`. The counterpart is the browser's `fetch`, which we use in `cx_wasm32`.
+
+use crate::*;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Run `request` on a new thread, sending [`Event::HttpResponse`]s until it's done or `cancelled` gets set.
+pub(crate) fn fetch_on_thread(request_id: HttpRequestId, request: HttpRequest, cancelled: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        run_fetch(&request, &cancelled, |response| {
+            if !cancelled.load(Ordering::Relaxed) {
+                Cx::send_event_from_any_thread(Event::HttpResponse(HttpResponseEvent { request_id, response }));
+            }
+        });
+    });
+}
+
+fn run_fetch(request: &HttpRequest, cancelled: &AtomicBool, send: impl Fn(HttpResponse)) {
+    // `ureq` doesn't tell us clearly when a timeout happened, so we check ourselves when we get an error.
+    let deadline = request.timeout.map(|timeout| Instant::now() + Duration::from_secs_f64(timeout));
+    let error = |message: String| {
+        if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+            HttpResponse::Error(HttpError::Timeout)
+        } else {
+            HttpResponse::Error(HttpError::Network(message))
+        }
+    };
+
+    let mut ureq_request = ureq::request(&request.method.to_ascii_uppercase(), &request.url);
+    for (name, value) in &request.headers {
+        ureq_request = ureq_request.set(name, value);
+    }
+    if let Some(timeout) = request.timeout {
+        ureq_request = ureq_request.timeout(Duration::from_secs_f64(timeout));
+    }
+    let response = match ureq_request.send_bytes(&request.body) {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(err) => return send(error(err.to_string())),
+    };
+
+    let status = response.status();
+    // Lowercase the names, like the browser does.
+    let headers = response
+        .headers_names()
+        .into_iter()
+        .filter_map(|name| Some((name.to_ascii_lowercase(), response.header(&name)?.to_string())))
+        .collect();
+    let mut reader = response.into_reader();
+    if request.stream {
+        send(HttpResponse::Head { status, headers });
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        while !cancelled.load(Ordering::Relaxed) {
+            match reader.read(&mut buffer) {
+                Ok(0) => return send(HttpResponse::End),
+                Ok(len) => send(HttpResponse::Chunk(buffer[..len].to_vec())),
+                Err(err) => return send(error(err.to_string())),
+            }
+        }
+    } else {
+        let mut body = vec![];
+        match reader.read_to_end(&mut body) {
+            Ok(_) => send(HttpResponse::Complete { status, headers, body }),
+            Err(err) => send(error(err.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    /// Serve a single request with `response`, and collect the [`HttpResponse`]s we get for `request` to it.
+    fn fetch(request: HttpRequest, response: &'static [u8]) -> Vec<HttpResponse> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/test", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buffer = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let len = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..len]);
+            }
+            stream.write_all(response).unwrap();
+            // Keep the connection open for a bit, for the timeout test.
+            std::thread::sleep(Duration::from_secs(1));
+        });
+
+        let responses = RefCell::new(vec![]);
+        run_fetch(&HttpRequest { url, ..request }, &AtomicBool::new(false), |response| responses.borrow_mut().push(response));
+        responses.into_inner()
+    }
+
+    #[test]
+    fn test_fetch() {
+        let responses =
+            fetch(HttpRequest::new("get", ""), b"HTTP/1.1 404 Not Found\r\nContent-Length: 5\r\nX-Test: yes\r\n\r\nhello");
+        assert_eq!(responses.len(), 1);
+        if let HttpResponse::Complete { status, headers, body } = &responses[0] {
+            assert_eq!(*status, 404);
+            assert!(headers.contains(&("x-test".to_string(), "yes".to_string())));
+            assert_eq!(body, b"hello");
+        } else {
+            panic!("Unexpected response: {:?}", responses[0]);
+        }
+
+        let responses = fetch(HttpRequest::new("GET", "").with_stream(), b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+        assert!(matches!(responses.first(), Some(HttpResponse::Head { status: 200, .. })));
+        assert_eq!(responses.last(), Some(&HttpResponse::End));
+        let body: Vec<u8> = responses
+            .iter()
+            .flat_map(|response| if let HttpResponse::Chunk(chunk) = response { chunk.clone() } else { vec![] })
+            .collect();
+        assert_eq!(body, b"hello");
+    }
+
+    #[test]
+    fn test_fetch_timeout() {
+        // Promise a longer body than we send, so reading the body times out.
+        let responses =
+            fetch(HttpRequest::new("GET", "").with_timeout(0.1), b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello");
+        assert_eq!(responses, vec![HttpResponse::Error(HttpError::Timeout)]);
+    }
+}
@@ -0,0 +1,595 @@
+//! Native WebSocket client, used for [`CxDesktopVsWasmCommon::websocket_send`] on desktop platforms. The
+//! counterpart is the browser's `WebSocket`, which we use in `cx_wasm32`.
+//!
+//! Every URL gets its own background thread, which connects when the first message is sent. When the connection
+//! drops, we only reconnect once the next message is sent, just like the browser would need a new `WebSocket`.
+//! Connecting is retried with exponential backoff for as long as there are messages to send, and that backoff
+//! carries over to the next connection if it drops right away, except when the server closed the connection
+//! normally (code 1000). The thread closes the connection and stops when the [`DesktopWebSocket`] is dropped.
+//! Frames are read on a second thread, so we can keep sending while waiting for messages.
+//!
+//! TODO(JP): Only `ws://` URLs are supported for now, since we don't have a TLS implementation that we can use
+//! directly (`ureq` hides its own).
+
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::io::{Cursor, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_HANDSHAKE_RESPONSE_LEN: usize = 16 * 1024;
+/// Frames and messages that are bigger than this close the connection with [`CLOSE_MESSAGE_TOO_BIG`], so a
+/// misbehaving server can't make us allocate arbitrary amounts of memory.
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+
+const OPCODE_CONTINUATION: u8 = 0;
+const OPCODE_TEXT: u8 = 1;
+const OPCODE_BINARY: u8 = 2;
+const OPCODE_CLOSE: u8 = 8;
+const OPCODE_PING: u8 = 9;
+const OPCODE_PONG: u8 = 10;
+
+/// See <https://www.rfc-editor.org/rfc/rfc6455#section-7.4.1>.
+const CLOSE_NORMAL: u16 = 1000;
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+/// Reserved to signal that a close frame didn't contain a code, so it must never be sent.
+const CLOSE_NO_STATUS: u16 = 1005;
+const CLOSE_MESSAGE_TOO_BIG: u16 = 1009;
+
+type OnMessage = Arc<dyn Fn(Result<Vec<u8>, String>) + Send + Sync>;
+
+/// Messages for the thread that owns a connection, either from the app or from the thread that reads frames.
+enum WebSocketCommand {
+    Send(Vec<u8>),
+    /// The [`DesktopWebSocket`] was dropped.
+    Stop,
+    /// Reply to a ping from the server.
+    Pong {
+        generation: u64,
+        data: Vec<u8>,
+    },
+    /// The server closed the connection (in which case we echo `close_code`), or reading failed.
+    Disconnected {
+        generation: u64,
+        close_code: Option<u16>,
+        error: String,
+    },
+}
+
+/// Handle to the background thread of a single WebSocket URL.
+pub(crate) struct DesktopWebSocket {
+    sender: mpsc::Sender<WebSocketCommand>,
+}
+
+impl DesktopWebSocket {
+    /// Spawn the background thread for `url`. `on_message` gets called (from other threads) with every message
+    /// that we receive, and with an error whenever connecting fails or the connection drops.
+    pub(crate) fn new(url: &str, on_message: impl Fn(Result<Vec<u8>, String>) + Send + Sync + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let url = url.to_string();
+        let thread_sender = sender.clone();
+        std::thread::spawn(move || run_websocket(&url, receiver, thread_sender, Arc::new(on_message)));
+        Self { sender }
+    }
+
+    /// Queue a binary message. Messages are sent in order once connected; a message that fails to send is
+    /// dropped (and reported through `on_message`).
+    pub(crate) fn send(&self, data: &[u8]) {
+        // The thread holds on to a sender itself, so it only stops receiving once we send `Stop`.
+        self.sender.send(WebSocketCommand::Send(data.to_vec())).unwrap();
+    }
+}
+
+impl Drop for DesktopWebSocket {
+    fn drop(&mut self) {
+        let _ = self.sender.send(WebSocketCommand::Stop);
+    }
+}
+
+/// Why [`run_connection`] returned.
+enum ConnectionEnd {
+    Stopped,
+    /// The server closed the connection with [`CLOSE_NORMAL`].
+    Closed(String),
+    Lost(String),
+}
+
+fn run_websocket(
+    url: &str,
+    receiver: mpsc::Receiver<WebSocketCommand>,
+    sender: mpsc::Sender<WebSocketCommand>,
+    on_message: OnMessage,
+) {
+    let (address, host, path) = match parse_url(url) {
+        Ok(parts) => parts,
+        Err(error) => {
+            for command in receiver.iter() {
+                match command {
+                    WebSocketCommand::Send(_) => on_message(Err(error.clone())),
+                    WebSocketCommand::Stop => return,
+                    WebSocketCommand::Pong { .. } | WebSocketCommand::Disconnected { .. } => {}
+                }
+            }
+            return;
+        }
+    };
+
+    // Messages to send once we're connected.
+    let mut queue = VecDeque::new();
+    let mut backoff = INITIAL_BACKOFF;
+    // When to connect next, if the last connection attempt failed or the connection dropped.
+    let mut retry_at = None;
+    let mut generation = 0;
+    loop {
+        // Only connect when there is something to send.
+        while queue.is_empty() {
+            match receiver.recv().unwrap() {
+                WebSocketCommand::Send(data) => queue.push_back(data),
+                WebSocketCommand::Stop => return,
+                // Left over from a previous connection.
+                WebSocketCommand::Pong { .. } | WebSocketCommand::Disconnected { .. } => {}
+            }
+        }
+        if let Some(retry_at) = retry_at.take() {
+            if !wait_until(retry_at, &receiver, &mut queue) {
+                return;
+            }
+        }
+
+        generation += 1;
+        match connect(&address, &host, &path) {
+            Ok((stream, leftover)) => {
+                match run_connection(stream, leftover, generation, &mut queue, &receiver, &sender, &on_message) {
+                    ConnectionEnd::Stopped => return,
+                    ConnectionEnd::Closed(error) => {
+                        on_message(Err(error));
+                        backoff = INITIAL_BACKOFF;
+                    }
+                    ConnectionEnd::Lost(error) => {
+                        on_message(Err(error));
+                        retry_at = Some(Instant::now() + backoff);
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                    }
+                }
+            }
+            Err(error) => {
+                on_message(Err(error));
+                retry_at = Some(Instant::now() + backoff);
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+/// Wait until `deadline`, queueing any messages to send in the meantime. Returns `false` if we got stopped.
+fn wait_until(deadline: Instant, receiver: &mpsc::Receiver<WebSocketCommand>, queue: &mut VecDeque<Vec<u8>>) -> bool {
+    loop {
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(WebSocketCommand::Send(data)) => queue.push_back(data),
+            Ok(WebSocketCommand::Stop) => return false,
+            Ok(WebSocketCommand::Pong { .. } | WebSocketCommand::Disconnected { .. }) => {}
+            Err(mpsc::RecvTimeoutError::Timeout) => return true,
+            Err(mpsc::RecvTimeoutError::Disconnected) => unreachable!("We hold on to a sender ourselves"),
+        }
+    }
+}
+
+/// Write queued messages until the connection drops or we get stopped, and return why.
+fn run_connection(
+    mut stream: TcpStream,
+    leftover: Vec<u8>,
+    generation: u64,
+    queue: &mut VecDeque<Vec<u8>>,
+    receiver: &mpsc::Receiver<WebSocketCommand>,
+    sender: &mpsc::Sender<WebSocketCommand>,
+    on_message: &OnMessage,
+) -> ConnectionEnd {
+    let read_stream = match stream.try_clone() {
+        Ok(read_stream) => read_stream,
+        Err(err) => return ConnectionEnd::Lost(format!("WebSocket connection failed: {}", err)),
+    };
+    let reader_sender = sender.clone();
+    let reader_on_message = Arc::clone(on_message);
+    std::thread::spawn(move || {
+        read_messages(Cursor::new(leftover).chain(read_stream), generation, &reader_sender, &reader_on_message)
+    });
+
+    loop {
+        let command = match queue.pop_front() {
+            Some(data) => WebSocketCommand::Send(data),
+            None => receiver.recv().unwrap(),
+        };
+        match command {
+            WebSocketCommand::Send(data) => {
+                if let Err(err) = write_frame(&mut stream, OPCODE_BINARY, &data, Some(rand::random())) {
+                    let _ = stream.shutdown(Shutdown::Both);
+                    return ConnectionEnd::Lost(format!("WebSocket connection lost: {}", err));
+                }
+            }
+            WebSocketCommand::Stop => {
+                let _ = write_frame(&mut stream, OPCODE_CLOSE, &CLOSE_NORMAL.to_be_bytes(), Some(rand::random()));
+                let _ = stream.shutdown(Shutdown::Both);
+                return ConnectionEnd::Stopped;
+            }
+            WebSocketCommand::Pong { generation: pong_generation, data } if pong_generation == generation => {
+                let _ = write_frame(&mut stream, OPCODE_PONG, &data, Some(rand::random()));
+            }
+            WebSocketCommand::Disconnected { generation: disconnected_generation, close_code, error }
+                if disconnected_generation == generation =>
+            {
+                if let Some(close_code) = close_code {
+                    let payload = if close_code == CLOSE_NO_STATUS { vec![] } else { close_code.to_be_bytes().to_vec() };
+                    let _ = write_frame(&mut stream, OPCODE_CLOSE, &payload, Some(rand::random()));
+                }
+                let _ = stream.shutdown(Shutdown::Both);
+                if close_code == Some(CLOSE_NORMAL) {
+                    return ConnectionEnd::Closed(error);
+                }
+                return ConnectionEnd::Lost(error);
+            }
+            // Left over from a previous connection.
+            WebSocketCommand::Pong { .. } | WebSocketCommand::Disconnected { .. } => {}
+        }
+    }
+}
+
+/// Read frames until the connection drops, passing complete text and binary messages to `on_message`.
+fn read_messages(mut reader: impl Read, generation: u64, sender: &mpsc::Sender<WebSocketCommand>, on_message: &OnMessage) {
+    let mut message = vec![];
+    let (close_code, error) = loop {
+        let frame = match read_frame(&mut reader) {
+            Ok(frame) => frame,
+            // Only `read_frame` itself returns `InvalidData`, for frames that are too big.
+            Err(err) if err.kind() == ErrorKind::InvalidData => break (Some(CLOSE_MESSAGE_TOO_BIG), err.to_string()),
+            Err(err) => break (None, format!("WebSocket connection lost: {}", err)),
+        };
+        match frame.opcode {
+            OPCODE_TEXT | OPCODE_BINARY | OPCODE_CONTINUATION => {
+                if message.len() + frame.payload.len() > MAX_MESSAGE_LEN {
+                    break (Some(CLOSE_MESSAGE_TOO_BIG), "WebSocket message is too big".to_string());
+                }
+                message.extend_from_slice(&frame.payload);
+                if frame.fin {
+                    on_message(Ok(std::mem::take(&mut message)));
+                }
+            }
+            OPCODE_PING => {
+                let _ = sender.send(WebSocketCommand::Pong { generation, data: frame.payload });
+            }
+            OPCODE_PONG => {}
+            OPCODE_CLOSE => {
+                // We don't validate the close frame: a 1-byte payload is treated as if there was no code, and
+                // reserved or unknown codes are echoed back as is.
+                if frame.payload.len() < 2 {
+                    break (Some(CLOSE_NO_STATUS), "WebSocket closed without a code".to_string());
+                }
+                let code = u16::from_be_bytes([frame.payload[0], frame.payload[1]]);
+                let reason = String::from_utf8_lossy(&frame.payload[2..]);
+                if reason.is_empty() {
+                    break (Some(code), format!("WebSocket closed with code {}", code));
+                }
+                break (Some(code), format!("WebSocket closed with code {}: {}", code, reason));
+            }
+            opcode => break (Some(CLOSE_PROTOCOL_ERROR), format!("Unsupported WebSocket opcode {}", opcode)),
+        }
+    };
+    let _ = sender.send(WebSocketCommand::Disconnected { generation, close_code, error });
+}
+
+/// Split a `ws://host[:port]/path` URL into an address to connect to, a `Host` header, and a path.
+fn parse_url(url: &str) -> Result<(String, String, String), String> {
+    let rest = url.strip_prefix("ws://").ok_or_else(|| format!("Only ws:// WebSocket URLs are supported on desktop: {}", url))?;
+    let (host, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    if host.is_empty() {
+        return Err(format!("Missing host in WebSocket URL: {}", url));
+    }
+    let has_port = host.rsplit_once(':').map_or(false, |(_, port)| port.parse::<u16>().is_ok());
+    let address = if has_port { host.to_string() } else { format!("{}:80", host) };
+    Ok((address, host.to_string(), path.to_string()))
+}
+
+/// Connect and do the opening handshake. Returns the stream, and whatever we read past the handshake response.
+fn connect(address: &str, host: &str, path: &str) -> Result<(TcpStream, Vec<u8>), String> {
+    let mut stream = TcpStream::connect(address).map_err(|err| format!("WebSocket connection to {} failed: {}", address, err))?;
+    let _ = stream.set_nodelay(true);
+
+    let key = base64_encode(&rand::random::<[u8; 16]>());
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\n\
+         Sec-WebSocket-Version: 13\r\n\r\n",
+        path, host, key
+    );
+    stream.write_all(request.as_bytes()).map_err(|err| format!("WebSocket handshake failed: {}", err))?;
+
+    let (head, leftover) = read_http_head(&mut stream)?;
+    let status_line = head.lines().next().unwrap_or_default();
+    if status_line.split(' ').nth(1) != Some("101") {
+        return Err(format!("WebSocket handshake failed: {}", status_line));
+    }
+    if find_header(&head, "sec-websocket-accept") != Some(&websocket_accept_key(&key)) {
+        return Err("WebSocket handshake failed: invalid Sec-WebSocket-Accept".to_string());
+    }
+    Ok((stream, leftover))
+}
+
+/// Read the status line and headers of an HTTP message, returning them as a string, and any bytes we read
+/// past them.
+fn read_http_head(stream: &mut impl Read) -> Result<(String, Vec<u8>), String> {
+    let mut buffer = vec![];
+    let mut chunk = [0u8; 1024];
+    loop {
+        if let Some(index) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            let leftover = buffer.split_off(index + 4);
+            return Ok((String::from_utf8_lossy(&buffer).into_owned(), leftover));
+        }
+        if buffer.len() > MAX_HANDSHAKE_RESPONSE_LEN {
+            return Err("WebSocket handshake failed: response too long".to_string());
+        }
+        match stream.read(&mut chunk) {
+            Ok(0) => return Err("WebSocket handshake failed: connection closed".to_string()),
+            Ok(len) => buffer.extend_from_slice(&chunk[..len]),
+            Err(err) => return Err(format!("WebSocket handshake failed: {}", err)),
+        }
+    }
+}
+
+fn find_header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .find(|(header_name, _)| header_name.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+fn read_frame(reader: &mut impl Read) -> std::io::Result<Frame> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head)?;
+    let len = match head[1] & 127 {
+        126 => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as usize
+        }
+        127 => {
+            let mut len = [0u8; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len).try_into().unwrap_or(usize::MAX)
+        }
+        len => len as usize,
+    };
+    if len > MAX_MESSAGE_LEN {
+        return Err(std::io::Error::new(ErrorKind::InvalidData, format!("WebSocket frame of {} bytes is too big", len)));
+    }
+    let mut mask = None;
+    if head[1] & 128 != 0 {
+        let mut mask_bytes = [0u8; 4];
+        reader.read_exact(&mut mask_bytes)?;
+        mask = Some(mask_bytes);
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    if let Some(mask) = mask {
+        apply_mask(&mut payload, mask);
+    }
+    Ok(Frame { fin: head[0] & 128 != 0, opcode: head[0] & 15, payload })
+}
+
+/// Write a single (unfragmented) frame. Frames sent by a client always have to be masked.
+fn write_frame(writer: &mut impl Write, opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) -> std::io::Result<()> {
+    let mask_bit = if mask.is_some() { 128 } else { 0 };
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(128 | opcode);
+    if payload.len() < 126 {
+        frame.push(mask_bit | payload.len() as u8);
+    } else if payload.len() < 65536 {
+        frame.push(mask_bit | 126);
+        frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    } else {
+        frame.push(mask_bit | 127);
+        frame.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+    }
+    if let Some(mask) = mask {
+        frame.extend_from_slice(&mask);
+    }
+    let payload_start = frame.len();
+    frame.extend_from_slice(payload);
+    if let Some(mask) = mask {
+        apply_mask(&mut frame[payload_start..], mask);
+    }
+    writer.write_all(&frame)
+}
+
+fn apply_mask(data: &mut [u8], mask: [u8; 4]) {
+    for (index, byte) in data.iter_mut().enumerate() {
+        *byte ^= mask[index % 4];
+    }
+}
+
+/// The value that the server has to return in `Sec-WebSocket-Accept` for a given `Sec-WebSocket-Key`.
+fn websocket_accept_key(key: &str) -> String {
+    base64_encode(&sha1(format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key).as_bytes()))
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (index, word) in block.chunks(4).enumerate() {
+            words[index] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for index in 16..80 {
+            words[index] = (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (index, word) in words.iter().enumerate() {
+            let (f, k) = match index {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, new_value) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(new_value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (bytes, value) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bits = u32::from_be_bytes([0, chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)]);
+        for index in 0..4 {
+            if index <= chunk.len() {
+                out.push(TABLE[(bits >> (18 - 6 * index) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_websocket_accept_key() {
+        // Example from <https://www.rfc-editor.org/rfc/rfc6455#section-1.3>.
+        assert_eq!(websocket_accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+        assert_eq!(base64_encode(b"a"), "YQ==");
+    }
+
+    #[test]
+    fn test_websocket_frame_too_big() {
+        let mut head = vec![OPCODE_BINARY | 128, 127];
+        head.extend_from_slice(&(MAX_MESSAGE_LEN as u64 + 1).to_be_bytes());
+        let err = read_frame(&mut Cursor::new(head)).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    /// Accept a connection and do the server side of the opening handshake.
+    fn accept(listener: &TcpListener) -> (TcpStream, impl Read) {
+        let (mut stream, _) = listener.accept().unwrap();
+        let (head, leftover) = read_http_head(&mut stream).unwrap();
+        assert!(head.starts_with("GET /echo HTTP/1.1"));
+        let accept = websocket_accept_key(find_header(&head, "sec-websocket-key").unwrap());
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\r\n",
+            accept
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+        let reader = Cursor::new(leftover).chain(stream.try_clone().unwrap());
+        (stream, reader)
+    }
+
+    #[test]
+    fn test_websocket_messages_and_close() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/echo", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, mut reader) = accept(&listener);
+            let frame = read_frame(&mut reader).unwrap();
+            assert_eq!(frame.opcode, OPCODE_BINARY);
+            write_frame(&mut stream, OPCODE_TEXT, &frame.payload, None).unwrap();
+            // A binary message in two fragments, a ping, and then a close frame with code 1001 ("going away").
+            stream.write_all(&[OPCODE_BINARY, 3, b'a', b'b', b'c', 128, 2, b'd', b'e']).unwrap();
+            write_frame(&mut stream, OPCODE_PING, b"ping", None).unwrap();
+            write_frame(&mut stream, OPCODE_CLOSE, &1001u16.to_be_bytes(), None).unwrap();
+
+            let frame = read_frame(&mut reader).unwrap();
+            assert_eq!((frame.opcode, frame.payload), (OPCODE_PONG, b"ping".to_vec()));
+            let frame = read_frame(&mut reader).unwrap();
+            assert_eq!((frame.opcode, frame.payload), (OPCODE_CLOSE, 1001u16.to_be_bytes().to_vec()));
+        });
+
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let websocket = DesktopWebSocket::new(&url, move |result| sender.lock().unwrap().send(result).unwrap());
+        websocket.send(b"hello");
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), Ok(b"hello".to_vec()));
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), Ok(b"abcde".to_vec()));
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), Err("WebSocket closed with code 1001".to_string()));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_websocket_reconnect_and_drop() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/echo", listener.local_addr().unwrap());
+        let (checked_sender, checked_receiver) = mpsc::channel();
+        let server = std::thread::spawn(move || {
+            let (mut stream, mut reader) = accept(&listener);
+            assert_eq!(read_frame(&mut reader).unwrap().payload, b"first");
+            write_frame(&mut stream, OPCODE_CLOSE, &CLOSE_NORMAL.to_be_bytes(), None).unwrap();
+            assert_eq!(read_frame(&mut reader).unwrap().opcode, OPCODE_CLOSE);
+
+            // Nothing reconnects until the next message is sent.
+            listener.set_nonblocking(true).unwrap();
+            std::thread::sleep(Duration::from_millis(500));
+            assert_eq!(listener.accept().err().unwrap().kind(), ErrorKind::WouldBlock);
+            listener.set_nonblocking(false).unwrap();
+            checked_sender.send(()).unwrap();
+
+            let (_stream, mut reader) = accept(&listener);
+            assert_eq!(read_frame(&mut reader).unwrap().payload, b"second");
+            // Dropping the `DesktopWebSocket` closes the connection.
+            let frame = read_frame(&mut reader).unwrap();
+            assert_eq!((frame.opcode, frame.payload), (OPCODE_CLOSE, CLOSE_NORMAL.to_be_bytes().to_vec()));
+        });
+
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let websocket = DesktopWebSocket::new(&url, move |result| {
+            // The reading thread may report the connection being shut down after the test is done.
+            let _ = sender.lock().unwrap().send(result);
+        });
+        websocket.send(b"first");
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), Err("WebSocket closed with code 1000".to_string()));
+        checked_receiver.recv_timeout(timeout).unwrap();
+        websocket.send(b"second");
+        drop(websocket);
+        server.join().unwrap();
+    }
+}
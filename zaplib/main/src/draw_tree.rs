@@ -41,7 +41,8 @@ pub struct View {
     /// The most recent [`Cx::redraw_id`] that this [`View`] was drawn with.
     pub(crate) redraw_id: u64,
     /// Whether this [`View`] is an overlay/popup, which means all [`DrawCall`]s underneath it
-    /// will get rendered last.
+    /// will get rendered last, on top of all other [`View`]s in the [`Pass`]. Overlays are
+    /// rendered in the order in which [`View::begin_view`] was called on them.
     pub(crate) is_overlay: bool,
 
     debugger: Debugger,
@@ -98,6 +99,9 @@ impl View {
             cxpass.main_view_id = Some(view_id);
             // we should take the window geometry and abs position as our box layout
             (Layout { absolute: true, abs_size: Some(cxpass.pass_size), ..layout }, true)
+        } else if self.is_overlay && cx.layout_boxes.is_empty() {
+            // an overlay after the main view has ended, which also covers the whole window
+            (Layout { absolute: true, abs_size: Some(cx.passes[pass_id].pass_size), ..layout }, false)
         } else {
             (layout, false)
         };
@@ -117,28 +121,12 @@ impl View {
 
         // push ourselves up the parent draw_stack
         if view_id != parent_view_id {
-            // we need a new draw
-            let parent_cxview = &mut cx.views[parent_view_id];
-
-            let id = parent_cxview.draw_calls_len;
-            parent_cxview.draw_calls_len += 1;
-
-            // see if we need to add a new one
-            if parent_cxview.draw_calls_len > parent_cxview.draw_calls.len() {
-                parent_cxview.draw_calls.push({
-                    DrawCall {
-                        view_id: parent_view_id,
-                        draw_call_id: parent_cxview.draw_calls.len(),
-                        redraw_id: cx.redraw_id,
-                        sub_view_id: view_id,
-                        ..Default::default()
-                    }
-                })
+            if self.is_overlay {
+                // Overlays get added to the main view when the pass ends, so that they end up on top of everything else,
+                // even when they're drawn after the main view has ended.
+                cxpass.overlay_view_ids.push(view_id);
             } else {
-                // or reuse a sub list node
-                let draw = &mut parent_cxview.draw_calls[id];
-                draw.sub_view_id = view_id;
-                draw.redraw_id = cx.redraw_id;
+                cx.add_sub_view_draw_call(parent_view_id, view_id);
             }
         }
 
@@ -252,6 +240,33 @@ impl View {
 }
 
 impl Cx {
+    /// Add a [`DrawCall`] to the parent [`CxView`] that draws the [`CxView`] with `view_id`, reusing an existing
+    /// [`DrawCall`] if possible.
+    pub(crate) fn add_sub_view_draw_call(&mut self, parent_view_id: usize, view_id: usize) {
+        let parent_cxview = &mut self.views[parent_view_id];
+
+        let id = parent_cxview.draw_calls_len;
+        parent_cxview.draw_calls_len += 1;
+
+        // see if we need to add a new one
+        if parent_cxview.draw_calls_len > parent_cxview.draw_calls.len() {
+            parent_cxview.draw_calls.push({
+                DrawCall {
+                    view_id: parent_view_id,
+                    draw_call_id: parent_cxview.draw_calls.len(),
+                    redraw_id: self.redraw_id,
+                    sub_view_id: view_id,
+                    ..Default::default()
+                }
+            })
+        } else {
+            // or reuse a sub list node
+            let draw = &mut parent_cxview.draw_calls[id];
+            draw.sub_view_id = view_id;
+            draw.redraw_id = self.redraw_id;
+        }
+    }
+
     /// Returns an existing [`DrawCall`] based on the given [`Shader`], or
     /// creates a new one if none can be found in the current [`CxView`].
     ///
@@ -273,6 +273,13 @@ pub struct WebSocketMessageEvent {
     pub result: Result<Vec<u8>, String>,
 }
 
+/// See [`Event::HttpResponse`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpResponseEvent {
+    pub request_id: HttpRequestId,
+    pub response: HttpResponse,
+}
+
 /// A file that was supplied by a user, as opposed to by the application itself (like font resources
 /// and such).
 #[derive(Clone, Debug)]
@@ -365,6 +372,7 @@ pub enum Event {
     Timer(TimerEvent),
     /// A signal was fired using [`Cx::send_signal`].
     Signal(SignalEvent),
+    /// A [`Menu`] item was picked, or [`Cx::send_command`] was called.
     Command(CommandId),
     /// Keyboard focus changed between components.
     KeyFocus(KeyFocusEvent),
@@ -380,6 +388,8 @@ pub enum Event {
     TextCopy,
     /// A websocket message was received.
     WebSocketMessage(WebSocketMessageEvent),
+    /// (Part of) a response to [`CxDesktopVsWasmCommon::fetch`] came in.
+    HttpResponse(HttpResponseEvent),
     /// Intended for platforms that can register a file type to an application.
     /// Fires:
     /// - when application starts with a file
@@ -5,6 +5,7 @@
 //! * <https://github.com/Zaplib/zaplib/issues/174>
 //! * <https://github.com/Zaplib/zaplib/issues/175>
 
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::RwLock;
 use std::sync::RwLockReadGuard;
 
@@ -13,6 +14,7 @@ use zaplib_vector::geometry::Trapezoid;
 use zaplib_vector::geometry::{AffineTransformation, Transform, Vector};
 use zaplib_vector::internal_iter::*;
 use zaplib_vector::path::PathIterator;
+use zaplib_vector::shaping::{shape, ShapedGlyph};
 use zaplib_vector::trapezoidator::Trapezoidator;
 
 /// The default [Ubuntu font](https://design.ubuntu.com/font/).
@@ -33,6 +35,7 @@ pub const TEXT_STYLE_NORMAL: TextStyle = TextStyle {
     line_spacing: 1.4,
     top_drop: 1.2,
     height_factor: 1.3,
+    fallback_fonts: [None; MAX_FALLBACK_FONTS],
 };
 
 /// A monospace [`TextStyle`].
@@ -45,7 +48,15 @@ pub const TEXT_STYLE_MONO: TextStyle = TextStyle {
     ..TEXT_STYLE_NORMAL
 };
 
+/// Number of built-in fonts; fonts loaded at runtime get ids after these.
+const BUILTIN_FONTS_COUNT: usize = 2;
+
+/// Maximum number of fonts in [`TextStyle::fallback_fonts`].
+pub const MAX_FALLBACK_FONTS: usize = 4;
+
 /// A pointer to a [`CxFont`] (indexed in [`CxFontsData::fonts`] using [`Font::font_id`]),
+///
+/// Get one for your own font data using [`Cx::load_font`].
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Font {
     pub font_id: usize,
@@ -65,6 +76,28 @@ pub struct TextStyle {
     pub line_spacing: f32,
     pub top_drop: f32,
     pub height_factor: f32,
+    /// Fonts to look in (in order) for characters that are missing from [`TextStyle::font`],
+    /// e.g. CJK characters or symbols. Set these using [`TextStyle::with_fallback_fonts`].
+    pub fallback_fonts: [Option<Font>; MAX_FALLBACK_FONTS],
+}
+
+impl TextStyle {
+    /// Returns a copy of this [`TextStyle`] with [`TextStyle::fallback_fonts`] set to `fonts`.
+    ///
+    /// Panics if more than [`MAX_FALLBACK_FONTS`] are given.
+    pub fn with_fallback_fonts(self, fonts: &[Font]) -> Self {
+        assert!(fonts.len() <= MAX_FALLBACK_FONTS, "At most {} fallback fonts are supported", MAX_FALLBACK_FONTS);
+        let mut fallback_fonts = [None; MAX_FALLBACK_FONTS];
+        for (fallback_font, font) in fallback_fonts.iter_mut().zip(fonts) {
+            *fallback_font = Some(*font);
+        }
+        Self { fallback_fonts, ..self }
+    }
+
+    /// The primary font followed by the fallback fonts, in lookup order.
+    pub(crate) fn font_chain(&self) -> impl Iterator<Item = Font> + '_ {
+        std::iter::once(self.font).chain(self.fallback_fonts.iter().flatten().copied())
+    }
 }
 
 impl Default for TextStyle {
@@ -77,21 +110,58 @@ impl Default for TextStyle {
             line_spacing: 1.4,
             top_drop: 1.1,
             height_factor: 1.3,
+            fallback_fonts: [None; MAX_FALLBACK_FONTS],
         }
     }
 }
 
 impl Cx {
+    /// Load the built-in fonts, if that hasn't happened yet.
+    ///
+    /// This can be called multiple times, since fonts loaded using [`Cx::load_font`] (e.g. in the app's
+    /// `new` function) might be loaded before the platform calls this.
     pub(crate) fn load_fonts(&mut self) {
+        let mut write_fonts_data = self.fonts_data.write().unwrap();
+        if !write_fonts_data.fonts.is_empty() {
+            return;
+        }
         #[cfg(not(feature = "disable-fonts"))]
         {
-            let mut write_fonts_data = self.fonts_data.write().unwrap();
             write_fonts_data.fonts = Iterator::map(FONTS_BYTES.iter(), |bytes| {
                 let font = zaplib_vector::ttf_parser::parse_ttf(bytes).expect("Error loading font");
                 CxFont { font_loaded: Some(font), atlas_pages: vec![] }
             })
             .collect();
         }
+        // Keep the ids of the built-in fonts reserved, so that fonts loaded at runtime don't take their place.
+        write_fonts_data.fonts.resize(BUILTIN_FONTS_COUNT, CxFont::default());
+    }
+
+    /// Load a TrueType or OpenType (`.ttf` or `.otf`) font from `bytes`, returning a [`Font`] that you can use in a
+    /// [`TextStyle`], either as [`TextStyle::font`] or in [`TextStyle::fallback_fonts`].
+    ///
+    /// Returns an error if the font data could not be parsed.
+    pub fn load_font(&mut self, bytes: &[u8]) -> std::io::Result<Font> {
+        let font = zaplib_vector::ttf_parser::parse_ttf(bytes)
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "Error parsing font"))?;
+        self.load_fonts();
+        let mut write_fonts_data = self.fonts_data.write().unwrap();
+        write_fonts_data.fonts.push(CxFont { font_loaded: Some(font), atlas_pages: vec![] });
+        Ok(Font { font_id: write_fonts_data.fonts.len() - 1 })
+    }
+
+    /// Load a font from a [`UniversalFile`]. See [`Cx::load_font`].
+    pub fn load_font_from_file(&mut self, file: &mut UniversalFile) -> std::io::Result<Font> {
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(file, &mut bytes)?;
+        self.load_font(&bytes)
+    }
+
+    /// Load a font from an absolute URL. See [`Cx::load_font`].
+    ///
+    /// Like [`UniversalFile::open_url`], this blocks until the entire font is loaded.
+    pub fn load_font_from_url(&mut self, url: &str) -> std::io::Result<Font> {
+        self.load_font_from_file(&mut UniversalFile::open_url(url)?)
     }
 
     pub fn reset_font_atlas_and_redraw(&mut self) {
@@ -104,14 +174,17 @@ impl Cx {
                 font.atlas_pages.truncate(0);
             }
 
-            write_fonts.fonts_atlas.alloc_xpos = 0.;
-            write_fonts.fonts_atlas.alloc_ypos = 0.;
-            write_fonts.fonts_atlas.alloc_hmax = 0.;
-            write_fonts.fonts_atlas.clear_buffer = true;
+            write_fonts.fonts_atlas.reset();
         }
 
         self.request_draw();
     }
+
+    /// Statistics about the glyph cache (the font atlas texture), for debugging. These are also logged
+    /// when pressing ctrl+option+cmd+4.
+    pub fn glyph_cache_stats(&self) -> GlyphCacheStats {
+        self.fonts_data.read().unwrap().fonts_atlas.stats()
+    }
 }
 
 static SHADER: Shader = Shader {
@@ -125,7 +198,7 @@ static SHADER: Shader = Shader {
             // trapezoid
             instance a_xs: vec2;
             instance a_ys: vec4;
-            // index
+            // index, or 4 to clear the trapezoid (used for evicted parts of the atlas)
             instance chan: float;
 
             varying v_p0: vec2;
@@ -188,6 +261,9 @@ static SHADER: Shader = Shader {
             fn pixel() -> vec4 {
                 let p_min = v_pixel.xy - 0.5;
                 let p_max = v_pixel.xy + 0.5;
+                if chan > 3.5 {
+                    return vec4(0., 0., 0., 1.);
+                }
                 let t_area = compute_clamped_trapezoid_area(p_min, p_max);
                 if chan < 0.5 {
                     return vec4(t_area, 0., 0., 0.);
@@ -294,9 +370,9 @@ impl TrapezoidText {
                 let atlas_page = &cxfont.atlas_pages[todo.atlas_page_id];
                 let glyph = &font.glyphs[todo.glyph_id];
 
-                if todo.glyph_id == font.char_code_to_glyph_index_map[10]
-                    || todo.glyph_id == font.char_code_to_glyph_index_map[9]
-                    || todo.glyph_id == font.char_code_to_glyph_index_map[13]
+                if [10, 9, 13]
+                    .iter()
+                    .any(|&char_code| todo.glyph_id == font.char_code_to_glyph_index_map.get(char_code).copied().unwrap_or(0))
                 {
                     return;
                 }
@@ -395,7 +471,16 @@ impl CxAfterDraw {
             let mut atlas_todo = Vec::new();
             std::mem::swap(&mut cx.fonts_data.write().unwrap().fonts_atlas.atlas_todo, &mut atlas_todo);
 
+            // Clear evicted regions first, so we can draw new glyphs in them. The quad that the shader draws is
+            // 1 pixel larger on each side than the trapezoid.
+            let mut clear_todo = Vec::new();
+            std::mem::swap(&mut cx.fonts_data.write().unwrap().fonts_atlas.clear_todo, &mut clear_todo);
             let mut instances = vec![];
+            for rect in clear_todo {
+                let (x1, y1) = (rect.pos.x + 1.0, rect.pos.y + 1.0);
+                let (x2, y2) = (rect.pos.x + rect.size.x - 1.0, rect.pos.y + rect.size.y - 1.0);
+                instances.push((Trapezoid::new([x1, x2], [y1, y1, y2, y2]), 4.0));
+            }
             for todo in atlas_todo {
                 self.trapezoid_text.draw_todo(cx, todo, &mut instances);
             }
@@ -405,6 +490,11 @@ impl CxAfterDraw {
             self.atlas_view.end_view(cx);
             self.atlas_pass.end_pass(cx);
         }
+
+        let redraw_requested = cx.fonts_data.write().unwrap().fonts_atlas.end_draw();
+        if redraw_requested {
+            cx.request_draw();
+        }
         //println!("TOTALT TIME {}", Cx::profile_time_ns() - start);
     }
 }
@@ -430,6 +520,8 @@ pub(crate) struct CxFontAtlasGlyph {
     pub(crate) ty1: f32,
     pub(crate) tx2: f32,
     pub(crate) ty2: f32,
+    /// Index in [`CxFontsAtlas::shelves`].
+    shelf_id: usize,
 }
 
 /// TODO(JP): subpixel_x_fract and subpixel_y_fract work in confusing (maybe even wrong) ways.
@@ -444,15 +536,71 @@ pub(crate) struct CxFontsAtlasTodo {
     pub(crate) subpixel_id: usize,
 }
 
+/// Identifies a glyph in [`CxFontAtlasPage::atlas_glyphs`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct CxFontsAtlasGlyphId {
+    font_id: usize,
+    atlas_page_id: usize,
+    glyph_id: usize,
+    subpixel_id: usize,
+}
+
+/// A row of glyphs in the atlas. When the atlas is full, all glyphs in the least recently used shelf are
+/// evicted, so its space can be reused.
+#[derive(Debug, Default)]
+struct CxFontsAtlasShelf {
+    ypos: f32,
+    height: f32,
+    /// Where the next glyph goes.
+    xpos: f32,
+    /// The last [`CxFontsAtlas::generation`] in which any of the glyphs in this shelf was drawn. This is atomic
+    /// so that it can be updated while only holding a read lock on [`CxFontsData`].
+    last_used: AtomicU64,
+    glyphs: Vec<CxFontsAtlasGlyphId>,
+}
+
+/// Statistics about the glyph cache. See [`Cx::glyph_cache_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GlyphCacheStats {
+    /// The number of glyphs that are currently in the atlas.
+    pub cached_glyphs: usize,
+    /// How often a glyph was drawn that was already in the atlas.
+    pub hits: u64,
+    /// How often a glyph had to be added to the atlas.
+    pub misses: u64,
+    /// The number of glyphs that were evicted from the atlas to make room for other glyphs.
+    pub evictions: u64,
+    /// How often the glyphs of a single draw didn't fit in the atlas, in which case glyphs that were still in
+    /// use had to be evicted, and another draw was requested (only once, if the next draw overflows as well).
+    pub overflows: u64,
+    /// The fraction of the atlas height that is taken up by shelves (rows of glyphs).
+    pub occupancy: f32,
+}
+
 /// An "atlas" for font glyphs, which is like a cached version of glyphs.
+///
+/// Glyphs are packed into shelves (rows). Every glyph that is drawn marks its shelf as used in the current
+/// draw, and when the atlas is full, the least recently used shelf that wasn't used in the current draw gets
+/// evicted. This is safe because everything gets redrawn in every draw, so glyphs that haven't been used in
+/// the current draw yet aren't referenced by any [`TextIns`] anymore once the draw is done.
 #[derive(Debug, Default)]
 pub(crate) struct CxFontsAtlas {
     texture_handle: Option<TextureHandle>,
     texture_size: Vec2,
     clear_buffer: bool,
-    alloc_xpos: f32,
-    alloc_ypos: f32,
-    alloc_hmax: f32,
+    /// Ordered from top to bottom.
+    shelves: Vec<CxFontsAtlasShelf>,
+    /// Incremented at the end of every draw in [`CxFontsAtlas::end_draw`].
+    generation: u64,
+    /// Regions of evicted shelves, which have to be cleared before drawing new glyphs in them.
+    clear_todo: Vec<Rect>,
+    /// Set when we evicted glyphs that were used in the current draw.
+    overflowed: bool,
+    /// Whether the previous draw overflowed, in which case we don't request yet another draw.
+    overflowed_previous_draw: bool,
+    /// Kept separately from [`CxFontsAtlas::stats`], since it's updated while only holding a read lock.
+    hits: AtomicU64,
+    stats: GlyphCacheStats,
     pub(crate) atlas_todo: Vec<CxFontsAtlasTodo>,
 }
 
@@ -498,29 +646,138 @@ pub fn get_font_atlas_page_id(
 }
 
 impl CxFontsAtlas {
-    pub fn alloc_atlas_glyph(&mut self, w: f32, h: f32) -> CxFontAtlasGlyph {
-        if w + self.alloc_xpos >= self.texture_size.x {
-            self.alloc_xpos = 0.0;
-            self.alloc_ypos += self.alloc_hmax + 1.0;
-            self.alloc_hmax = 0.0;
+    /// Mark `glyph` as used in the current draw, so that it won't be evicted.
+    pub(crate) fn mark_used(&self, glyph: &CxFontAtlasGlyph) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        self.shelves[glyph.shelf_id].last_used.store(self.generation, Ordering::Relaxed);
+    }
+
+    /// Remove all glyphs. Make sure to also clear the [`CxFont::atlas_pages`] that refer to them.
+    fn reset(&mut self) {
+        self.shelves.clear();
+        self.clear_todo.clear();
+        self.clear_buffer = true;
+    }
+
+    /// Called at the end of every draw. Returns whether another draw is needed, because glyphs had to be
+    /// evicted that were still in use.
+    ///
+    /// We only draw again once per overflow: if the glyphs of a single draw don't fit in the atlas at all, the
+    /// next draw overflows as well, and we'd keep drawing forever.
+    fn end_draw(&mut self) -> bool {
+        self.generation += 1;
+        let overflowed = std::mem::take(&mut self.overflowed);
+        let redraw = overflowed && !self.overflowed_previous_draw;
+        self.overflowed_previous_draw = overflowed;
+        redraw
+    }
+
+    fn stats(&self) -> GlyphCacheStats {
+        let used_height = self.shelves.last().map_or(0.0, |shelf| shelf.ypos + shelf.height + 1.0);
+        GlyphCacheStats {
+            cached_glyphs: self.shelves.iter().fold(0, |count, shelf| count + shelf.glyphs.len()),
+            hits: self.hits.load(Ordering::Relaxed),
+            occupancy: if self.texture_size.y > 0.0 { (used_height / self.texture_size.y).min(1.0) } else { 0.0 },
+            ..self.stats
         }
-        if h + self.alloc_ypos >= self.texture_size.y {
-            println!("FONT ATLAS FULL, TODO FIX THIS");
+    }
+
+    /// Find a shelf that has room for a glyph of `w` by `h` pixels, evicting a shelf if necessary. Returns
+    /// the shelf id, and the glyphs that were evicted.
+    fn alloc_shelf(&mut self, w: f32, h: f32) -> (usize, Vec<CxFontsAtlasGlyphId>) {
+        let texture_size = self.texture_size;
+        let fits = |shelf: &CxFontsAtlasShelf| w + shelf.xpos < texture_size.x;
+
+        // The shortest shelf that's high enough.
+        if let Some((shelf_id, _)) = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| shelf.height >= h && fits(shelf))
+            .min_by(|(_, a), (_, b)| a.height.partial_cmp(&b.height).unwrap())
+        {
+            return (shelf_id, vec![]);
         }
-        if h > self.alloc_hmax {
-            self.alloc_hmax = h;
+
+        // The bottom shelf can grow, as long as it stays within the texture.
+        if let Some(shelf) = self.shelves.last_mut() {
+            if fits(shelf) && h + shelf.ypos < texture_size.y {
+                shelf.height = h;
+                return (self.shelves.len() - 1, vec![]);
+            }
         }
 
-        let tx1 = self.alloc_xpos / self.texture_size.x;
-        let ty1 = self.alloc_ypos / self.texture_size.y;
+        // Add a new shelf at the bottom.
+        let ypos = self.shelves.last().map_or(0.0, |shelf| shelf.ypos + shelf.height + 1.0);
+        if h + ypos < texture_size.y {
+            self.shelves.push(CxFontsAtlasShelf {
+                ypos,
+                height: h,
+                last_used: AtomicU64::new(self.generation),
+                ..CxFontsAtlasShelf::default()
+            });
+            return (self.shelves.len() - 1, vec![]);
+        }
 
-        self.alloc_xpos += w + 1.0;
+        // Evict the least recently used shelf that is high enough, preferring ones that weren't used in the
+        // current draw.
+        let lru_shelf_id = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| shelf.height >= h)
+            .min_by_key(|(_, shelf)| shelf.last_used.load(Ordering::Relaxed))
+            .map(|(shelf_id, _)| shelf_id);
+        match lru_shelf_id {
+            Some(shelf_id) => {
+                if self.shelves[shelf_id].last_used.load(Ordering::Relaxed) == self.generation {
+                    self.overflow();
+                }
+                (shelf_id, self.evict_shelf(shelf_id))
+            }
+            None => {
+                // The glyph is higher than any shelf, so start over.
+                self.overflow();
+                let mut evicted = Vec::new();
+                for shelf_id in 0..self.shelves.len() {
+                    evicted.extend(self.evict_shelf(shelf_id));
+                }
+                self.shelves.clear();
+                self.shelves.push(CxFontsAtlasShelf {
+                    height: h,
+                    last_used: AtomicU64::new(self.generation),
+                    ..CxFontsAtlasShelf::default()
+                });
+                (0, evicted)
+            }
+        }
+    }
 
-        if h > self.alloc_hmax {
-            self.alloc_hmax = h;
+    fn overflow(&mut self) {
+        if self.stats.overflows == 0 {
+            log!("Font atlas overflow: the glyphs in a single draw don't fit in the atlas");
         }
+        self.stats.overflows += 1;
+        self.overflowed = true;
+    }
 
-        CxFontAtlasGlyph { tx1, ty1, tx2: tx1 + (w / self.texture_size.x), ty2: ty1 + (h / self.texture_size.y) }
+    fn evict_shelf(&mut self, shelf_id: usize) -> Vec<CxFontsAtlasGlyphId> {
+        let shelf = &mut self.shelves[shelf_id];
+        shelf.xpos = 0.0;
+        shelf.last_used.store(self.generation, Ordering::Relaxed);
+        self.clear_todo.push(Rect { pos: vec2(0.0, shelf.ypos), size: vec2(self.texture_size.x, shelf.height + 1.0) });
+        let evicted = std::mem::take(&mut shelf.glyphs);
+        self.stats.evictions += evicted.len() as u64;
+        // Glyphs that were evicted before they were even drawn into the atlas.
+        self.atlas_todo.retain(|todo| {
+            !evicted.contains(&CxFontsAtlasGlyphId {
+                font_id: todo.font_id,
+                atlas_page_id: todo.atlas_page_id,
+                glyph_id: todo.glyph_id,
+                subpixel_id: todo.subpixel_id,
+            })
+        });
+        evicted
     }
 }
 
@@ -535,6 +792,79 @@ pub struct CxFontsData {
 }
 
 impl CxFontsData {
+    /// Find the first font in `text_style`'s [`TextStyle::font_chain`] that has a glyph for `c`, and return
+    /// its font_id and glyph_id. If none of the fonts have a glyph for `c`, returns the "missing glyph" of
+    /// the primary font.
+    pub(crate) fn resolve_glyph(&self, text_style: &TextStyle, c: char) -> (usize, usize) {
+        let primary_font_id = text_style.font.font_id;
+        // Control characters such as newlines are always taken from the primary font.
+        if c.is_control() {
+            let font = self.fonts[primary_font_id].font_loaded.as_ref().unwrap();
+            return (primary_font_id, font.char_code_to_glyph_index_map.get(c as usize).copied().unwrap_or(0));
+        }
+        for font in text_style.font_chain() {
+            if let Some(glyph_id) = self.fonts[font.font_id].font_loaded.as_ref().and_then(|font| font.glyph_index(c)) {
+                return (font.font_id, glyph_id);
+            }
+        }
+        (primary_font_id, 0)
+    }
+
+    /// Shape `chars` with the fonts of `text_style`. Characters are assigned to fonts using
+    /// [`CxFontsData::resolve_glyph`], and each run of characters with the same font is shaped separately.
+    ///
+    /// Returns the font_id of every glyph along with the glyph itself; clusters are indices into `chars`.
+    pub(crate) fn shape(&self, text_style: &TextStyle, chars: &[char]) -> Vec<(usize, ShapedGlyph)> {
+        let mut shaped_glyphs = Vec::with_capacity(chars.len());
+        let mut run_start = 0;
+        while run_start < chars.len() {
+            let font_id = self.resolve_glyph(text_style, chars[run_start]).0;
+            let run_end = (run_start + 1..chars.len())
+                .find(|&index| self.resolve_glyph(text_style, chars[index]).0 != font_id)
+                .unwrap_or(chars.len());
+            let font = self.fonts[font_id].font_loaded.as_ref().unwrap();
+            for shaped_glyph in shape(font, &chars[run_start..run_end]) {
+                shaped_glyphs.push((font_id, ShapedGlyph { cluster: run_start + shaped_glyph.cluster, ..shaped_glyph }));
+            }
+            run_start = run_end;
+        }
+        shaped_glyphs
+    }
+
+    /// The scale factor from font units to logical pixels, for a font at a given `font_size`.
+    pub(crate) fn font_size_logical(&self, font_id: usize, font_size: f32) -> f32 {
+        font_size * 96.0 / (72.0 * self.fonts[font_id].font_loaded.as_ref().unwrap().units_per_em)
+    }
+
+    /// Allocate space in the atlas for a glyph of `w` by `h` pixels, and queue `todo` to draw it there.
+    pub(crate) fn alloc_atlas_glyph(&mut self, w: f32, h: f32, todo: CxFontsAtlasTodo) -> CxFontAtlasGlyph {
+        let (shelf_id, evicted) = self.fonts_atlas.alloc_shelf(w, h);
+        for id in evicted {
+            self.fonts[id.font_id].atlas_pages[id.atlas_page_id].atlas_glyphs[id.glyph_id][id.subpixel_id] = None;
+        }
+
+        let atlas = &mut self.fonts_atlas;
+        let texture_size = atlas.texture_size;
+        let shelf = &mut atlas.shelves[shelf_id];
+        let tx1 = shelf.xpos / texture_size.x;
+        let ty1 = shelf.ypos / texture_size.y;
+        shelf.xpos += w + 1.0;
+        shelf.last_used.store(atlas.generation, Ordering::Relaxed);
+        let id = CxFontsAtlasGlyphId {
+            font_id: todo.font_id,
+            atlas_page_id: todo.atlas_page_id,
+            glyph_id: todo.glyph_id,
+            subpixel_id: todo.subpixel_id,
+        };
+        shelf.glyphs.push(id);
+        atlas.stats.misses += 1;
+        atlas.atlas_todo.push(todo);
+
+        let glyph = CxFontAtlasGlyph { tx1, ty1, tx2: tx1 + (w / texture_size.x), ty2: ty1 + (h / texture_size.y), shelf_id };
+        self.fonts[id.font_id].atlas_pages[id.atlas_page_id].atlas_glyphs[id.glyph_id][id.subpixel_id] = Some(glyph);
+        glyph
+    }
+
     pub fn get_fonts_atlas_texture_handle(&self) -> TextureHandle {
         self.fonts_atlas.texture_handle.unwrap()
     }
@@ -543,3 +873,56 @@ impl CxFontsData {
         CxFontsData::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_atlas_eviction() {
+        let cx = Cx::new_test();
+        let font_id = TEXT_STYLE_NORMAL.font.font_id;
+        let atlas_page_id = get_font_atlas_page_id(&cx.fonts_data, font_id, 1.0, 10.0).0;
+        let mut fonts_data = cx.fonts_data.write().unwrap();
+        // Room for 3 shelves of 3 glyphs each.
+        fonts_data.fonts_atlas.texture_size = vec2(64., 64.);
+        let alloc = |fonts_data: &mut CxFontsData, glyph_id| {
+            fonts_data.alloc_atlas_glyph(
+                20.,
+                20.,
+                CxFontsAtlasTodo { font_id, atlas_page_id, glyph_id, subpixel_id: 0, ..CxFontsAtlasTodo::default() },
+            )
+        };
+        let glyphs: Vec<CxFontAtlasGlyph> = (0..9).map(|glyph_id| alloc(&mut fonts_data, glyph_id)).collect();
+        assert_eq!(fonts_data.fonts_atlas.stats().cached_glyphs, 9);
+        assert_eq!(fonts_data.fonts_atlas.stats().evictions, 0);
+        assert!(!fonts_data.fonts_atlas.end_draw());
+
+        // In the next draw, only the first shelf is unused, so it gets evicted.
+        for glyph in &glyphs[3..] {
+            fonts_data.fonts_atlas.mark_used(glyph);
+        }
+        let glyph = alloc(&mut fonts_data, 9);
+        assert_eq!(glyph.ty1, 0.);
+        assert!(fonts_data.fonts[font_id].atlas_pages[atlas_page_id].atlas_glyphs[0][0].is_none());
+        assert!(fonts_data.fonts[font_id].atlas_pages[atlas_page_id].atlas_glyphs[9][0].is_some());
+        assert_eq!(fonts_data.fonts_atlas.clear_todo.len(), 1);
+        let stats = fonts_data.fonts_atlas.stats();
+        assert_eq!((stats.cached_glyphs, stats.hits, stats.misses, stats.evictions, stats.overflows), (7, 6, 10, 3, 0));
+
+        // Once everything is used in the current draw, we have to evict glyphs that are in use, and draw again.
+        alloc(&mut fonts_data, 10);
+        alloc(&mut fonts_data, 11);
+        alloc(&mut fonts_data, 12);
+        assert_eq!(fonts_data.fonts_atlas.stats().overflows, 1);
+        assert!(fonts_data.fonts_atlas.end_draw());
+
+        // If the glyphs of a single draw never fit, the next draw overflows too, but we shouldn't keep drawing.
+        for glyph_id in 0..10 {
+            alloc(&mut fonts_data, glyph_id);
+        }
+        assert_eq!(fonts_data.fonts_atlas.stats().overflows, 2);
+        assert!(!fonts_data.fonts_atlas.end_draw());
+        assert!(!fonts_data.fonts_atlas.end_draw());
+    }
+}
@@ -0,0 +1,191 @@
+//! Finding grapheme cluster boundaries: what users perceive as single characters, like "e" followed by a
+//! combining accent, or emoji joined with zero width joiners. Text cursors should move and delete by these.
+//!
+//! This follows the extended grapheme cluster rules from Unicode Text Segmentation (UAX #29), with the
+//! character properties approximated using ranges of the most common scripts and emoji.
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GraphemeClass {
+    CR,
+    LF,
+    Control,
+    Extend,
+    ZWJ,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    L,
+    V,
+    T,
+    LV,
+    LVT,
+    ExtendedPictographic,
+    Other,
+}
+
+impl GraphemeClass {
+    fn of(c: char) -> GraphemeClass {
+        match c as u32 {
+            0x0D => GraphemeClass::CR,
+            0x0A => GraphemeClass::LF,
+            0x200D => GraphemeClass::ZWJ,
+            0x00..=0x1F | 0x7F..=0x9F | 0xAD | 0x061C | 0x180E | 0x200B | 0x200E | 0x200F | 0x2028..=0x202E => {
+                GraphemeClass::Control
+            }
+            0x2060..=0x206F | 0xFEFF | 0xFFF0..=0xFFFB => GraphemeClass::Control,
+            // Combining marks, zero width non-joiner, variation selectors, emoji modifiers and tags.
+            0x0300..=0x036F | 0x0483..=0x0489 | 0x0591..=0x05BD | 0x05BF | 0x05C1 | 0x05C2 | 0x05C4 | 0x05C5 | 0x05C7 => {
+                GraphemeClass::Extend
+            }
+            0x0610..=0x061A | 0x064B..=0x065F | 0x0670 | 0x06D6..=0x06DC | 0x06DF..=0x06E4 | 0x06E7 | 0x06E8 => {
+                GraphemeClass::Extend
+            }
+            0x06EA..=0x06ED | 0x0711 | 0x0730..=0x074A | 0x07A6..=0x07B0 | 0x07EB..=0x07F3 | 0x08D3..=0x08E1 => {
+                GraphemeClass::Extend
+            }
+            0x08E3..=0x0902 | 0x093A | 0x093C | 0x0941..=0x0948 | 0x094D | 0x0951..=0x0957 | 0x0962 | 0x0963 => {
+                GraphemeClass::Extend
+            }
+            0x0981 | 0x09BC | 0x09BE | 0x09C1..=0x09C4 | 0x09CD | 0x09D7 | 0x09E2 | 0x09E3 => GraphemeClass::Extend,
+            0x0E31 | 0x0E34..=0x0E3A | 0x0E47..=0x0E4E | 0x0EB1 | 0x0EB4..=0x0EBC | 0x0EC8..=0x0ECD => GraphemeClass::Extend,
+            0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x200C | 0x20D0..=0x20FF | 0x302A..=0x302F | 0x3099 | 0x309A => {
+                GraphemeClass::Extend
+            }
+            0xFE00..=0xFE0F | 0xFE20..=0xFE2F | 0xFF9E | 0xFF9F | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F => GraphemeClass::Extend,
+            0xE0100..=0xE01EF => GraphemeClass::Extend,
+            0x0903 | 0x093B | 0x093E..=0x0940 | 0x0949..=0x094C | 0x094E | 0x094F | 0x0982 | 0x0983 | 0x09BF | 0x09C0 => {
+                GraphemeClass::SpacingMark
+            }
+            0x09C7 | 0x09C8 | 0x09CB | 0x09CC | 0x0E33 | 0x0EB3 => GraphemeClass::SpacingMark,
+            0x0600..=0x0605 | 0x06DD | 0x070F | 0x0890 | 0x0891 | 0x08E2 | 0x110BD | 0x110CD => GraphemeClass::Prepend,
+            0x1F1E6..=0x1F1FF => GraphemeClass::RegionalIndicator,
+            0x1100..=0x115F | 0xA960..=0xA97C => GraphemeClass::L,
+            0x1160..=0x11A7 | 0xD7B0..=0xD7C6 => GraphemeClass::V,
+            0x11A8..=0x11FF | 0xD7CB..=0xD7FB => GraphemeClass::T,
+            0xAC00..=0xD7A3 if (c as u32 - 0xAC00) % 28 == 0 => GraphemeClass::LV,
+            0xAC00..=0xD7A3 => GraphemeClass::LVT,
+            0xA9 | 0xAE | 0x203C | 0x2049 | 0x2122 | 0x2139 | 0x2194..=0x2199 | 0x21A9 | 0x21AA | 0x231A | 0x231B | 0x2328 => {
+                GraphemeClass::ExtendedPictographic
+            }
+            0x2388 | 0x23CF | 0x23E9..=0x23F3 | 0x23F8..=0x23FA | 0x24C2 | 0x25AA | 0x25AB | 0x25B6 | 0x25C0 => {
+                GraphemeClass::ExtendedPictographic
+            }
+            0x25FB..=0x25FE | 0x2600..=0x27BF | 0x2934 | 0x2935 | 0x2B05..=0x2B07 | 0x2B1B | 0x2B1C | 0x2B50 | 0x2B55 => {
+                GraphemeClass::ExtendedPictographic
+            }
+            0x3030 | 0x303D | 0x3297 | 0x3299 | 0x1F000..=0x1F0FF | 0x1F10D..=0x1F10F | 0x1F12F | 0x1F16C..=0x1F171 => {
+                GraphemeClass::ExtendedPictographic
+            }
+            0x1F17E | 0x1F17F | 0x1F18E | 0x1F191..=0x1F19A | 0x1F1AD..=0x1F1E5 | 0x1F201..=0x1F20F | 0x1F21A | 0x1F22F => {
+                GraphemeClass::ExtendedPictographic
+            }
+            0x1F232..=0x1F23A | 0x1F23C..=0x1F23F | 0x1F249..=0x1F3FA | 0x1F400..=0x1F53D | 0x1F546..=0x1F64F => {
+                GraphemeClass::ExtendedPictographic
+            }
+            0x1F680..=0x1F6FF | 0x1F774..=0x1F77F | 0x1F7D5..=0x1F7FF | 0x1F80C..=0x1F80F | 0x1F848..=0x1F84F => {
+                GraphemeClass::ExtendedPictographic
+            }
+            0x1F85A..=0x1F85F | 0x1F888..=0x1F88F | 0x1F8AE..=0x1F8FF | 0x1F90C..=0x1F93A | 0x1F93C..=0x1F945 => {
+                GraphemeClass::ExtendedPictographic
+            }
+            0x1F947..=0x1FAFF | 0x1FC00..=0x1FFFD => GraphemeClass::ExtendedPictographic,
+            _ => GraphemeClass::Other,
+        }
+    }
+}
+
+/// Returns whether there is a grapheme cluster boundary before `chars[index]`. The start and end of `chars` are
+/// always boundaries.
+pub fn is_boundary(chars: &[char], index: usize) -> bool {
+    if index == 0 || index >= chars.len() {
+        return true;
+    }
+    match (GraphemeClass::of(chars[index - 1]), GraphemeClass::of(chars[index])) {
+        // GB3
+        (GraphemeClass::CR, GraphemeClass::LF) => false,
+        // GB4, GB5
+        (GraphemeClass::CR | GraphemeClass::LF | GraphemeClass::Control, _)
+        | (_, GraphemeClass::CR | GraphemeClass::LF | GraphemeClass::Control) => true,
+        // GB6, GB7, GB8: Hangul syllables.
+        (GraphemeClass::L, GraphemeClass::L | GraphemeClass::V | GraphemeClass::LV | GraphemeClass::LVT)
+        | (GraphemeClass::LV | GraphemeClass::V, GraphemeClass::V | GraphemeClass::T)
+        | (GraphemeClass::LVT | GraphemeClass::T, GraphemeClass::T) => false,
+        // GB9, GB9a, GB9b
+        (_, GraphemeClass::Extend | GraphemeClass::ZWJ | GraphemeClass::SpacingMark) | (GraphemeClass::Prepend, _) => false,
+        // GB11: emoji ZWJ sequences.
+        (GraphemeClass::ZWJ, GraphemeClass::ExtendedPictographic) => {
+            let mut prev_index = index - 1;
+            while prev_index > 0 && GraphemeClass::of(chars[prev_index - 1]) == GraphemeClass::Extend {
+                prev_index -= 1;
+            }
+            !(prev_index > 0 && GraphemeClass::of(chars[prev_index - 1]) == GraphemeClass::ExtendedPictographic)
+        }
+        // GB12, GB13: flags are pairs of regional indicators.
+        (GraphemeClass::RegionalIndicator, GraphemeClass::RegionalIndicator) => {
+            let regional_indicator_count =
+                chars[..index].iter().rev().take_while(|&&c| GraphemeClass::of(c) == GraphemeClass::RegionalIndicator).count();
+            regional_indicator_count % 2 == 0
+        }
+        // GB999
+        _ => true,
+    }
+}
+
+/// Returns the index of the first grapheme cluster boundary after `index`, or `chars.len()` if there is none.
+pub fn next_boundary(chars: &[char], index: usize) -> usize {
+    let mut index = index + 1;
+    while index < chars.len() && !is_boundary(chars, index) {
+        index += 1;
+    }
+    index.min(chars.len())
+}
+
+/// Returns the index of the last grapheme cluster boundary before `index`, or 0 if there is none.
+pub fn prev_boundary(chars: &[char], index: usize) -> usize {
+    let mut index = index.min(chars.len()).saturating_sub(1);
+    while index > 0 && !is_boundary(chars, index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boundaries(text: &str) -> Vec<usize> {
+        let chars: Vec<char> = text.chars().collect();
+        (0..=chars.len()).filter(|&index| is_boundary(&chars, index)).collect()
+    }
+
+    #[test]
+    fn test_boundaries() {
+        assert_eq!(boundaries("ab"), vec![0, 1, 2]);
+        assert_eq!(boundaries("\r\n"), vec![0, 2]);
+        // Combining accent.
+        assert_eq!(boundaries("e\u{0301}x"), vec![0, 2, 3]);
+        // Family emoji: man, ZWJ, woman, ZWJ, girl.
+        assert_eq!(boundaries("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}"), vec![0, 5]);
+        // Thumbs up with a skin tone modifier, followed by a ZWJ that doesn't join anything.
+        assert_eq!(boundaries("\u{1F44D}\u{1F3FD}\u{200D}a"), vec![0, 3, 4]);
+        // Two flags.
+        assert_eq!(boundaries("\u{1F1F3}\u{1F1F1}\u{1F1E9}\u{1F1EA}"), vec![0, 2, 4]);
+        // Hangul jamo.
+        assert_eq!(boundaries("\u{1100}\u{1161}\u{11A8}\u{1100}"), vec![0, 3, 4]);
+    }
+
+    #[test]
+    fn test_next_prev_boundary() {
+        let chars: Vec<char> = "ae\u{0301}\u{0302}b".chars().collect();
+        assert_eq!(next_boundary(&chars, 0), 1);
+        assert_eq!(next_boundary(&chars, 1), 4);
+        assert_eq!(next_boundary(&chars, 2), 4);
+        assert_eq!(next_boundary(&chars, 4), 5);
+        assert_eq!(next_boundary(&chars, 5), 5);
+        assert_eq!(prev_boundary(&chars, 5), 4);
+        assert_eq!(prev_boundary(&chars, 4), 1);
+        assert_eq!(prev_boundary(&chars, 1), 0);
+        assert_eq!(prev_boundary(&chars, 0), 0);
+    }
+}
@@ -0,0 +1,145 @@
+//! Types for making HTTP requests using [`CxDesktopVsWasmCommon::fetch`].
+
+use crate::*;
+
+/// An HTTP request, to be passed into [`CxDesktopVsWasmCommon::fetch`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpRequest {
+    /// E.g. `"GET"` or `"POST"`.
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Give up with [`HttpError::Timeout`] if the request (including reading the body) takes longer than this
+    /// many seconds.
+    pub timeout: Option<f64>,
+    /// Deliver the response as [`HttpResponse::Head`], a number of [`HttpResponse::Chunk`]s, and then
+    /// [`HttpResponse::End`], instead of a single [`HttpResponse::Complete`].
+    pub stream: bool,
+}
+
+impl HttpRequest {
+    pub fn new(method: &str, url: &str) -> Self {
+        Self { method: method.to_string(), url: url.to_string(), headers: vec![], body: vec![], timeout: None, stream: false }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: f64) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_stream(mut self) -> Self {
+        self.stream = true;
+        self
+    }
+}
+
+/// Identifies a request made using [`CxDesktopVsWasmCommon::fetch`], so you can match up
+/// [`HttpResponseEvent`]s with it, or cancel it using [`CxDesktopVsWasmCommon::cancel_fetch`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HttpRequestId(pub(crate) u64);
+
+/// What an [`HttpResponseEvent`] contains. [`HttpResponse::Complete`], [`HttpResponse::End`], and
+/// [`HttpResponse::Error`] are the last event for a request.
+///
+/// Statuses like 404 or 500 are not errors; you get those as a regular response.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HttpResponse {
+    /// The whole response, if [`HttpRequest::stream`] was not set.
+    Complete {
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    },
+    /// The status and headers, if [`HttpRequest::stream`] was set.
+    Head {
+        status: u16,
+        headers: Vec<(String, String)>,
+    },
+    /// The next part of the body, if [`HttpRequest::stream`] was set.
+    Chunk(Vec<u8>),
+    /// The body is done, if [`HttpRequest::stream`] was set.
+    End,
+    Error(HttpError),
+}
+
+impl HttpResponse {
+    pub(crate) fn is_last(&self) -> bool {
+        matches!(self, HttpResponse::Complete { .. } | HttpResponse::End | HttpResponse::Error(_))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HttpError {
+    /// [`HttpRequest::timeout`] expired.
+    Timeout,
+    /// Couldn't connect, the connection dropped, or the request was blocked (e.g. by CORS in the browser).
+    Network(String),
+}
+
+impl Cx {
+    /// Issue a new [`HttpRequestId`], and remember that it's in flight, so we know which
+    /// [`Event::HttpResponse`]s to let through.
+    pub(crate) fn new_http_request_id(&mut self) -> HttpRequestId {
+        self.last_http_request_id += 1;
+        let request_id = HttpRequestId(self.last_http_request_id);
+        self.http_requests_in_flight.insert(request_id);
+        request_id
+    }
+
+    /// Drop events for requests that were cancelled, and forget about requests that are done.
+    pub(crate) fn process_http_response_event(&mut self, event: &mut Event) {
+        if let Event::HttpResponse(HttpResponseEvent { request_id, response }) = event {
+            let in_flight = if response.is_last() {
+                #[cfg(not(target_arch = "wasm32"))]
+                self.platform.desktop.http_cancel_flags.remove(request_id);
+                self.http_requests_in_flight.remove(request_id)
+            } else {
+                self.http_requests_in_flight.contains(request_id)
+            };
+            if !in_flight {
+                *event = Event::None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_cancelled_http_responses_are_dropped() {
+        let mut cx = Cx::new_test();
+        let mut request_ids = vec![];
+        let mut event_handler = |_cx: &mut Cx, event: &mut Event| {
+            if let Event::HttpResponse(HttpResponseEvent { request_id, .. }) = event {
+                request_ids.push(*request_id);
+            }
+        };
+        cx.headless_start(&mut event_handler);
+
+        let cancelled_id = cx.new_http_request_id();
+        let request_id = cx.new_http_request_id();
+        cx.cancel_fetch(cancelled_id);
+        for request_id in [cancelled_id, request_id, request_id] {
+            cx.headless_send_event(
+                &mut event_handler,
+                Event::HttpResponse(HttpResponseEvent { request_id, response: HttpResponse::End }),
+            );
+        }
+        // The second `End` is dropped too, since the request was already done.
+        assert_eq!(request_ids, vec![request_id]);
+    }
+}
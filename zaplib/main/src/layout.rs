@@ -128,6 +128,13 @@ pub enum Width {
     Compute,
     /// Fill up as much of the available space as possible up to provided width
     FillUntil(f32),
+    /// Inside of a [`Cx::begin_flex_box`] that walks to the right: take a share of the width that is left over
+    /// after all other items (and gaps) have been laid out, proportional to this weight. Elsewhere this behaves
+    /// like [`Width::Fill`].
+    Flex(f32),
+    /// Same as [`Width::Flex`], but never smaller than `min` or bigger than `max`. Space that an item can't take
+    /// because of this gets distributed among the other flex items.
+    FlexMinMax { weight: f32, min: f32, max: f32 },
 }
 impl Width {
     /// TODO(JP): Replace these with Width::default() when
@@ -153,6 +160,10 @@ pub enum Height {
     Compute,
     /// See [`Width::FillUntil`],
     FillUntil(f32),
+    /// See [`Width::Flex`]; applies inside of a [`Cx::begin_flex_box`] that walks down.
+    Flex(f32),
+    /// See [`Width::FlexMinMax`].
+    FlexMinMax { weight: f32, min: f32, max: f32 },
 }
 impl Height {
     /// TODO(JP): [`Height::Fill`] might be a bad default, because if you use
@@ -170,6 +181,131 @@ impl Default for Height {
     }
 }
 
+/// How a [`Cx::begin_flex_box`] distributes space that is left over along its [`Direction`]. There is only ever
+/// space left over if the box has no [`Width::Flex`]/[`Height::Flex`] items (or if they all hit their maximum),
+/// and if its size in that direction is not computed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Justify {
+    /// Keep all items at the start of the box.
+    Start,
+    /// Move all items to the end of the box.
+    End,
+    /// Move all items to the middle of the box.
+    Center,
+    /// Put the first item at the start and the last item at the end, with equal space between items.
+    SpaceBetween,
+    /// Put equal space on both sides of every item, so the space at the edges is half of the space between items.
+    SpaceAround,
+    /// Put equal space between items and at the edges.
+    SpaceEvenly,
+}
+impl Justify {
+    /// TODO(JP): Replace these with Justify::default() when
+    /// <https://github.com/rust-lang/rust/issues/67792> gets done
+    pub const DEFAULT: Justify = Justify::Start;
+}
+impl Default for Justify {
+    fn default() -> Self {
+        Justify::DEFAULT
+    }
+}
+
+/// How a [`Cx::begin_flex_box`] positions items perpendicular to its [`Direction`], e.g. vertically within a row.
+/// If the box's size in that direction is computed, items are aligned within the biggest item.
+///
+/// Also used to align the contents of a [`GridCell`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlignItems {
+    Start,
+    Center,
+    End,
+}
+impl AlignItems {
+    /// TODO(JP): Replace these with AlignItems::default() when
+    /// <https://github.com/rust-lang/rust/issues/67792> gets done
+    pub const DEFAULT: AlignItems = AlignItems::Start;
+}
+impl Default for AlignItems {
+    fn default() -> Self {
+        AlignItems::DEFAULT
+    }
+}
+
+/// Configures a [`Cx::begin_flex_box`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlexLayout {
+    /// The direction in which items are laid out. Flex boxes never wrap.
+    pub direction: Direction,
+    /// Space between consecutive items.
+    pub gap: f32,
+    /// See [`Justify`].
+    pub justify: Justify,
+    /// See [`AlignItems`].
+    pub align: AlignItems,
+}
+impl FlexLayout {
+    /// TODO(JP): Replace these with FlexLayout::default() when
+    /// <https://github.com/rust-lang/rust/issues/67792> gets done
+    pub const DEFAULT: FlexLayout =
+        FlexLayout { direction: Direction::DEFAULT, gap: 0., justify: Justify::DEFAULT, align: AlignItems::DEFAULT };
+}
+impl Default for FlexLayout {
+    fn default() -> Self {
+        FlexLayout::DEFAULT
+    }
+}
+
+/// The size of a column or row in a [`Cx::begin_grid`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GridTrack {
+    /// Use a fixed size.
+    Fix(f32),
+    /// Take a share of the space that is left after all [`GridTrack::Fix`] and [`GridTrack::Auto`] tracks,
+    /// proportional to this number. Like `fr` in CSS Grid.
+    Fraction(f32),
+    /// Use the size of the biggest cell in this track. Cells that span multiple tracks are not taken into account.
+    Auto,
+}
+
+/// Where to put a cell within a [`Cx::begin_grid`]; see [`Cx::begin_grid_cell`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GridCell {
+    pub column: usize,
+    pub row: usize,
+    /// The number of columns that this cell covers, starting at [`GridCell::column`].
+    pub column_span: usize,
+    /// The number of rows that this cell covers, starting at [`GridCell::row`].
+    pub row_span: usize,
+    /// How to position the contents of the cell horizontally, if they are smaller than the cell.
+    pub align_x: AlignItems,
+    /// How to position the contents of the cell vertically, if they are smaller than the cell.
+    pub align_y: AlignItems,
+}
+impl GridCell {
+    /// TODO(JP): Replace these with GridCell::default() when
+    /// <https://github.com/rust-lang/rust/issues/67792> gets done
+    pub const DEFAULT: GridCell = GridCell::new(0, 0);
+
+    pub const fn new(column: usize, row: usize) -> Self {
+        Self { column, row, column_span: 1, row_span: 1, align_x: AlignItems::DEFAULT, align_y: AlignItems::DEFAULT }
+    }
+
+    #[must_use]
+    pub fn with_span(self, column_span: usize, row_span: usize) -> Self {
+        Self { column_span, row_span, ..self }
+    }
+
+    #[must_use]
+    pub fn with_align(self, align_x: AlignItems, align_y: AlignItems) -> Self {
+        Self { align_x, align_y, ..self }
+    }
+}
+impl Default for GridCell {
+    fn default() -> Self {
+        GridCell::DEFAULT
+    }
+}
+
 /// Defines how elements on [`Cx::layout_box_align_list`] should be moved horizontally
 pub(crate) struct AlignX(pub f32);
 
@@ -53,6 +53,70 @@ impl Cx {
         self.end_typed_box(CxBoxType::Column)
     }
 
+    /// Starts a box that lays out its items in a single row or column, like a simplified
+    /// [CSS Flexbox](https://en.wikipedia.org/wiki/CSS_Flexible_Box_Layout). Items can take a share of the space that
+    /// is left over by using [`Width::Flex`] (or [`Height::Flex`] when walking down), and there can be a
+    /// [`FlexLayout::gap`] between items. Items are moved afterwards according to [`FlexLayout::justify`] and
+    /// [`FlexLayout::align`].
+    ///
+    /// Every [`Cx::begin_row`], [`Cx::add_box`], etc. directly inside the flex box counts as a single item.
+    ///
+    /// Since we only make a single pass, flex items are sized based on what the box contained during the previous
+    /// draw, which is remembered using `component_id`. If that changes (e.g. when adding items, or when the text in
+    /// a button changes), we automatically draw again. This means a flex box should get the same `component_id`
+    /// during every draw (typically a field of your component), and what is drawn inside of non-flex items should
+    /// not depend on the size of flex items.
+    pub fn begin_flex_box(&mut self, component_id: ComponentId, width: Width, height: Height, flex_layout: FlexLayout) {
+        self.begin_typed_box(
+            CxBoxType::FlexBox,
+            Layout { direction: flex_layout.direction, layout_size: LayoutSize { width, height }, ..Layout::default() },
+        );
+        self.begin_flex_items(component_id, flex_layout);
+    }
+
+    /// Ends the current box that was opened by [`Cx::begin_flex_box`].
+    /// Returns a [`Rect`] representing the overall area of that box
+    pub fn end_flex_box(&mut self) -> Rect {
+        self.assert_last_box_type_matches(CxBoxType::FlexBox);
+        self.end_flex_items();
+        self.end_typed_box(CxBoxType::FlexBox)
+    }
+
+    /// Starts a box that lays out cells in `columns` and `rows`, like a simplified
+    /// [CSS Grid](https://en.wikipedia.org/wiki/CSS_grid_layout). Inside of it, draw every cell between
+    /// [`Cx::begin_grid_cell`] and [`Cx::end_grid_cell`]. Anything else is drawn at the top left of the grid.
+    ///
+    /// The grid uses the space that is left in the current box for its [`GridTrack::Fraction`] tracks. Since we only
+    /// make a single pass, [`GridTrack::Auto`] tracks are sized based on what their cells contained during the
+    /// previous draw, which is remembered using `component_id`, and we automatically draw again if that changes. This
+    /// means a grid should get the same `component_id` during every draw (typically a field of your component).
+    pub fn begin_grid(&mut self, component_id: ComponentId, columns: &[GridTrack], rows: &[GridTrack]) {
+        self.begin_typed_box(
+            CxBoxType::Grid,
+            Layout { layout_size: LayoutSize { width: Width::Compute, height: Height::Compute }, ..Layout::default() },
+        );
+        self.begin_grid_tracks(component_id, columns, rows);
+    }
+
+    /// Ends the current box that was opened by [`Cx::begin_grid`].
+    /// Returns a [`Rect`] representing the overall area of the grid
+    pub fn end_grid(&mut self) -> Rect {
+        self.assert_last_box_type_matches(CxBoxType::Grid);
+        self.end_grid_tracks();
+        self.end_typed_box(CxBoxType::Grid)
+    }
+
+    /// Starts a box for a single cell of the current [`Cx::begin_grid`]. Its contents are aligned within the cell
+    /// using [`GridCell::align_x`] and [`GridCell::align_y`].
+    pub fn begin_grid_cell(&mut self, cell: GridCell) {
+        self.begin_grid_cell_box(cell);
+    }
+
+    /// Ends the current box that was opened by [`Cx::begin_grid_cell`].
+    pub fn end_grid_cell(&mut self) {
+        self.end_grid_cell_box();
+    }
+
     /// Starts alignment element that fills all remaining space by y axis and centers content by it
     pub fn begin_center_y_align(&mut self) {
         let parent = self.layout_boxes.last().unwrap();
@@ -71,6 +135,8 @@ impl Cx {
             box_type: CxBoxType::CenterYAlign,
             available_width: parent.get_width_left(),
             available_height: parent.get_height_left(),
+            flex: None,
+            grid: None,
         };
         self.layout_boxes.push(layout_box);
     }
@@ -107,6 +173,8 @@ impl Cx {
             box_type: CxBoxType::CenterXYAlign,
             available_width: parent.get_width_left(),
             available_height: parent.get_height_left(),
+            flex: None,
+            grid: None,
         };
         self.layout_boxes.push(layout_box);
     }
@@ -142,6 +210,8 @@ impl Cx {
             box_type: CxBoxType::BottomBox,
             available_width: parent.get_width_left(),
             available_height: parent.get_height_left(),
+            flex: None,
+            grid: None,
         };
         self.layout_boxes.push(layout_box);
     }
@@ -183,6 +253,8 @@ impl Cx {
             box_type: CxBoxType::CenterXAlign,
             available_width: parent.get_width_left(),
             available_height: parent.get_height_left(),
+            flex: None,
+            grid: None,
         };
         self.layout_boxes.push(layout_box);
     }
@@ -218,6 +290,8 @@ impl Cx {
             box_type: CxBoxType::RightBox,
             available_width: parent.get_width_left(),
             available_height: parent.get_height_left(),
+            flex: None,
+            grid: None,
         };
         self.layout_boxes.push(layout_box);
     }
@@ -16,6 +16,9 @@ pub enum CxBoxType {
     Column,
     AbsoluteBox,
     WrappingBox,
+    FlexBox,
+    Grid,
+    GridCell,
     View,
 }
 
@@ -95,6 +98,211 @@ pub(crate) struct CxLayoutBox {
     /// but available_height is defined until the bounds of parent/
     /// This is capped at 0 if the content already overflows the bounds.
     pub(crate) available_height: f32,
+
+    /// Only set for boxes started with [`Cx::begin_flex_box`].
+    pub(crate) flex: Option<CxFlexBox>,
+
+    /// Only set for boxes started with [`Cx::begin_grid`].
+    pub(crate) grid: Option<CxGrid>,
+}
+
+/// The weight and constraints of a [`Width::Flex`] or [`Height::Flex`] item.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct CxFlexSpec {
+    weight: f32,
+    min: f32,
+    max: f32,
+}
+
+impl Width {
+    fn flex_spec(&self) -> Option<CxFlexSpec> {
+        match *self {
+            Width::Flex(weight) => Some(CxFlexSpec { weight, min: 0., max: f32::INFINITY }),
+            Width::FlexMinMax { weight, min, max } => Some(CxFlexSpec { weight, min, max }),
+            _ => None,
+        }
+    }
+}
+
+impl Height {
+    fn flex_spec(&self) -> Option<CxFlexSpec> {
+        match *self {
+            Height::Flex(weight) => Some(CxFlexSpec { weight, min: 0., max: f32::INFINITY }),
+            Height::FlexMinMax { weight, min, max } => Some(CxFlexSpec { weight, min, max }),
+            _ => None,
+        }
+    }
+}
+
+/// Something that walked a [`CxFlexBox`].
+#[derive(Clone, Debug)]
+pub(crate) struct CxFlexItem {
+    flex_spec: Option<CxFlexSpec>,
+    rect: Rect,
+    /// The range within [`Cx::layout_box_align_list`] of everything that was drawn for this item.
+    align_list_start_index: usize,
+    align_list_end_index: usize,
+}
+
+/// What a [`CxFlexBox`] walked during the previous draw, which we need to know upfront to size its flex items,
+/// since we only make a single pass. See [`Cx::flex_box_measures`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct CxFlexMeasure {
+    flex_specs: Vec<CxFlexSpec>,
+    item_count: usize,
+    /// The size of all items that are not flex items, along the [`Direction`] of the box.
+    fixed_size: f32,
+}
+
+impl CxFlexMeasure {
+    /// Distribute the space that is left along the [`Direction`] of the box among the flex items. Like in CSS
+    /// Flexbox, items that would get bigger than their max or smaller than their min get frozen at that size, after
+    /// which the space is distributed again among the other items.
+    fn resolve_flex_sizes(&self, size: f32, gap: f32) -> Vec<f32> {
+        let gaps = gap * (self.item_count.max(1) - 1) as f32;
+        let space = (size - self.fixed_size - gaps).max(0.);
+        let mut sizes = vec![0.; self.flex_specs.len()];
+        let mut targets = vec![0.; self.flex_specs.len()];
+        let mut frozen = vec![false; self.flex_specs.len()];
+        loop {
+            let frozen_size: f32 = (0..sizes.len()).filter(|&i| frozen[i]).map(|i| sizes[i]).sum();
+            let weight: f32 = (0..sizes.len()).filter(|&i| !frozen[i]).map(|i| self.flex_specs[i].weight).sum();
+            let mut violation = 0.;
+            for i in (0..sizes.len()).filter(|&i| !frozen[i]) {
+                let spec = &self.flex_specs[i];
+                targets[i] = if weight > 0. { (space - frozen_size).max(0.) * spec.weight / weight } else { 0. };
+                sizes[i] = targets[i].min(spec.max).max(spec.min);
+                violation += sizes[i] - targets[i];
+            }
+            if f32::abs(violation) < 0.01 {
+                return sizes;
+            }
+            for i in 0..sizes.len() {
+                if (violation > 0. && sizes[i] > targets[i]) || (violation < 0. && sizes[i] < targets[i]) {
+                    frozen[i] = true;
+                }
+            }
+        }
+    }
+}
+
+/// The state of a box started with [`Cx::begin_flex_box`].
+#[derive(Clone, Debug)]
+pub(crate) struct CxFlexBox {
+    flex_layout: FlexLayout,
+    /// Key into [`Cx::flex_box_measures`].
+    component_id: ComponentId,
+    /// The sizes of the flex items along the [`Direction`] of the box, based on [`CxFlexMeasure`] from the
+    /// previous draw.
+    flex_sizes: Vec<f32>,
+    items: Vec<CxFlexItem>,
+}
+
+impl CxFlexSpec {
+    fn clamp(&self, size: f32) -> f32 {
+        size.min(self.max).max(self.min)
+    }
+}
+
+impl CxFlexBox {
+    /// Returns the size for the next flex item, if it is going to be sized by this box.
+    fn next_flex_size(&self, direction: Direction, spec: CxFlexSpec) -> Option<f32> {
+        if self.flex_layout.direction != direction {
+            return None;
+        }
+        let index = self.items.iter().filter(|item| item.flex_spec.is_some()).count();
+        // When we haven't seen this item before, use its minimum size until we redraw.
+        Some(self.flex_sizes.get(index).copied().unwrap_or(spec.min))
+    }
+
+    fn main_size(&self, size: Vec2) -> f32 {
+        match self.flex_layout.direction {
+            Direction::Right => size.x,
+            Direction::Down => size.y,
+        }
+    }
+
+    fn cross_size(&self, size: Vec2) -> f32 {
+        match self.flex_layout.direction {
+            Direction::Right => size.y,
+            Direction::Down => size.x,
+        }
+    }
+}
+
+/// The auto-sized tracks of a [`CxGrid`] during the previous draw. See [`Cx::grid_measures`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct CxGridMeasure {
+    /// The size of the biggest cell in every [`GridTrack::Auto`] column, and 0 for other columns.
+    columns: Vec<f32>,
+    /// Same as [`CxGridMeasure::columns`] but for rows.
+    rows: Vec<f32>,
+}
+
+/// Compute the offsets of the edges of all `tracks`, starting at 0. Returns one more offset than there are tracks.
+fn resolve_grid_tracks(tracks: &[GridTrack], auto_sizes: &[f32], available: f32) -> Vec<f32> {
+    let auto_size = |i: usize| auto_sizes.get(i).copied().unwrap_or(0.);
+    let mut used = 0.;
+    let mut fractions = 0.;
+    for (i, track) in tracks.iter().enumerate() {
+        match track {
+            GridTrack::Fix(size) => used += size,
+            GridTrack::Fraction(fraction) => fractions += fraction,
+            GridTrack::Auto => used += auto_size(i),
+        }
+    }
+    let space = (available - used).max(0.);
+
+    let mut offsets = Vec::with_capacity(tracks.len() + 1);
+    let mut offset = 0.;
+    offsets.push(offset);
+    for (i, track) in tracks.iter().enumerate() {
+        offset += match track {
+            GridTrack::Fix(size) => *size,
+            GridTrack::Fraction(fraction) if fractions > 0. => space * fraction / fractions,
+            GridTrack::Fraction(_) => 0.,
+            GridTrack::Auto => auto_size(i),
+        };
+        offsets.push(offset);
+    }
+    offsets
+}
+
+/// The state of a box started with [`Cx::begin_grid`].
+#[derive(Clone, Debug)]
+pub(crate) struct CxGrid {
+    columns: Vec<GridTrack>,
+    rows: Vec<GridTrack>,
+    /// Key into [`Cx::grid_measures`].
+    component_id: ComponentId,
+    /// See [`resolve_grid_tracks`].
+    column_offsets: Vec<f32>,
+    /// See [`resolve_grid_tracks`].
+    row_offsets: Vec<f32>,
+    /// What we measured during this draw so far.
+    measure: CxGridMeasure,
+    /// The cell that we're currently drawing, if any.
+    current_cell: Option<GridCell>,
+}
+
+impl CxGrid {
+    fn cell_rect(&self, cell: &GridCell) -> Rect {
+        let (column_end, row_end) = (cell.column + cell.column_span, cell.row + cell.row_span);
+        assert!(
+            cell.column_span > 0 && cell.row_span > 0 && column_end <= self.columns.len() && row_end <= self.rows.len(),
+            "Grid cell {:?} does not fit in a grid of {}x{}",
+            cell,
+            self.columns.len(),
+            self.rows.len()
+        );
+        Rect {
+            pos: vec2(self.column_offsets[cell.column], self.row_offsets[cell.row]),
+            size: vec2(
+                self.column_offsets[column_end] - self.column_offsets[cell.column],
+                self.row_offsets[row_end] - self.row_offsets[cell.row],
+            ),
+        }
+    }
 }
 
 impl CxLayoutBox {
@@ -174,6 +382,8 @@ impl Cx {
             box_type,
             available_height,
             available_width,
+            flex: None,
+            grid: None,
         };
 
         self.layout_boxes.push(layout_box);
@@ -260,6 +470,7 @@ impl Cx {
         // We can probably express this better in type system, but this is good enough for now.
         let w = self.eval_walking_width(&layout_size.width);
         let h = self.eval_walking_height(&layout_size.height);
+        let align_list_len = self.layout_box_align_list.len();
 
         let ret = if let Some(layout_box) = self.layout_boxes.last_mut() {
             let old_pos = match layout_box.layout.direction {
@@ -303,7 +514,30 @@ impl Cx {
             let new_bound = old_pos + vec2(w, h);
             layout_box.bound_right_bottom = layout_box.bound_right_bottom.max(&new_bound);
 
-            Rect { pos: old_pos, size: vec2(w, h) }
+            let rect = Rect { pos: old_pos, size: vec2(w, h) };
+            if let Some(flex) = &mut layout_box.flex {
+                match flex.flex_layout.direction {
+                    Direction::Right => layout_box.pos.x += flex.flex_layout.gap,
+                    Direction::Down => layout_box.pos.y += flex.flex_layout.gap,
+                }
+
+                let item_size = old_box.map_or(layout_size, |old_box| old_box.layout.layout_size);
+                let flex_spec = match flex.flex_layout.direction {
+                    Direction::Right => item_size.width.flex_spec(),
+                    Direction::Down => item_size.height.flex_spec(),
+                };
+                // Boxes have drawn everything by the time they walk, whereas whatever walks using `Cx::add_box`
+                // typically gets drawn right after, so then the item owns everything up to the next item.
+                let (align_list_start_index, align_list_end_index) = match old_box {
+                    Some(old_box) => (old_box.align_list_x_start_index, align_list_len),
+                    None => (align_list_len, usize::MAX),
+                };
+                if let Some(last_item) = flex.items.last_mut() {
+                    last_item.align_list_end_index = last_item.align_list_end_index.min(align_list_start_index);
+                }
+                flex.items.push(CxFlexItem { flex_spec, rect, align_list_start_index, align_list_end_index });
+            }
+            rect
         } else {
             Rect { pos: vec2(0.0, 0.0), size: vec2(w, h) }
         };
@@ -339,8 +573,13 @@ impl Cx {
     /// TODO(JP): Should we move some of this stuff to [`Area`], where we already seem to do a bunch
     /// of rectangle and position calculations?
     fn move_by_x(&mut self, dx: f32, align_start: usize) {
+        self.move_range_by_x(dx, align_start, self.layout_box_align_list.len());
+    }
+
+    /// Same as [`Cx::move_by_x`], but only for the items in [`Cx::layout_box_align_list`] up to `align_end`.
+    fn move_range_by_x(&mut self, dx: f32, align_start: usize, align_end: usize) {
         let dx = (dx * self.current_dpi_factor).floor() / self.current_dpi_factor;
-        for i in align_start..self.layout_box_align_list.len() {
+        for i in align_start..align_end {
             let align_item = &self.layout_box_align_list[i];
             match align_item {
                 Area::InstanceRange(inst) => {
@@ -382,8 +621,13 @@ impl Cx {
     /// TODO(JP): Should we move some of this stuff to [`Area`], where we already seem to do a bunch
     /// of rectangle and position calculations?
     fn move_by_y(&mut self, dy: f32, align_start: usize) {
+        self.move_range_by_y(dy, align_start, self.layout_box_align_list.len());
+    }
+
+    /// Same as [`Cx::move_by_y`], but only for the items in [`Cx::layout_box_align_list`] up to `align_end`.
+    fn move_range_by_y(&mut self, dy: f32, align_start: usize, align_end: usize) {
         let dy = (dy * self.current_dpi_factor).floor() / self.current_dpi_factor;
-        for i in align_start..self.layout_box_align_list.len() {
+        for i in align_start..align_end {
             let align_item = &self.layout_box_align_list[i];
             match align_item {
                 Area::InstanceRange(inst) => {
@@ -459,6 +703,7 @@ impl Cx {
             Width::Fix(v) => v.max(0.),
             Width::Fill => self.get_width_left(),
             Width::FillUntil(v) => self.get_width_left().min(*v),
+            Width::Flex(_) | Width::FlexMinMax { .. } => self.eval_flex_size(width.flex_spec().unwrap(), Direction::Right),
         }
     }
 
@@ -468,6 +713,7 @@ impl Cx {
             Width::Fix(v) => max_zero_keep_nan(*v),
             Width::Fill => max_zero_keep_nan(abs_size),
             Width::FillUntil(v) => min_keep_nan(*v, abs_size),
+            Width::Flex(_) | Width::FlexMinMax { .. } => width.flex_spec().unwrap().clamp(max_zero_keep_nan(abs_size)),
         }
     }
 
@@ -477,6 +723,7 @@ impl Cx {
             Width::Fix(v) => v.max(0.),
             Width::Fill => self.get_width_left(),
             Width::FillUntil(v) => self.get_width_left().min(*v),
+            Width::Flex(_) | Width::FlexMinMax { .. } => self.eval_flex_size(width.flex_spec().unwrap(), Direction::Right),
         }
     }
 
@@ -491,6 +738,7 @@ impl Cx {
             Width::Fix(v) => *v,
             Width::FillUntil(v) => parent.get_width_left().min(*v),
             Width::Compute | Width::Fill => parent.get_width_left(),
+            Width::Flex(_) | Width::FlexMinMax { .. } => self.eval_width(width),
         }
     }
 
@@ -500,6 +748,7 @@ impl Cx {
             Height::Fix(v) => v.max(0.),
             Height::Fill => self.get_height_left(),
             Height::FillUntil(v) => self.get_height_left().min(*v),
+            Height::Flex(_) | Height::FlexMinMax { .. } => self.eval_flex_size(height.flex_spec().unwrap(), Direction::Down),
         }
     }
 
@@ -509,6 +758,7 @@ impl Cx {
             Height::Fix(v) => v.max(0.),
             Height::Fill => max_zero_keep_nan(abs_size),
             Height::FillUntil(v) => min_keep_nan(*v, abs_size),
+            Height::Flex(_) | Height::FlexMinMax { .. } => height.flex_spec().unwrap().clamp(max_zero_keep_nan(abs_size)),
         }
     }
 
@@ -518,6 +768,7 @@ impl Cx {
             Height::Fix(v) => v.max(0.),
             Height::Fill => self.get_height_left(),
             Height::FillUntil(v) => self.get_height_left().min(*v),
+            Height::Flex(_) | Height::FlexMinMax { .. } => self.eval_flex_size(height.flex_spec().unwrap(), Direction::Down),
         }
     }
 
@@ -531,6 +782,207 @@ impl Cx {
             Height::Fix(v) => *v,
             Height::FillUntil(v) => parent.get_height_left().min(*v),
             Height::Compute | Height::Fill => parent.get_height_left(),
+            Height::Flex(_) | Height::FlexMinMax { .. } => self.eval_height(height),
+        }
+    }
+
+    /// The size of a flex item in the current box, in the given [`Direction`]. If the current box is not a
+    /// [`CxFlexBox`] going in that direction, we fill up the space that is left, within the min/max constraints.
+    fn eval_flex_size(&self, spec: CxFlexSpec, direction: Direction) -> f32 {
+        let layout_box = self.layout_boxes.last().unwrap();
+        if let Some(size) = layout_box.flex.as_ref().and_then(|flex| flex.next_flex_size(direction, spec)) {
+            return size;
+        }
+        match direction {
+            Direction::Right => spec.clamp(layout_box.get_width_left()),
+            Direction::Down => spec.clamp(layout_box.get_height_left()),
+        }
+    }
+
+    /// Turn the current box into a [`CxFlexBox`], sizing its flex items based on what it walked during the previous
+    /// draw.
+    pub(crate) fn begin_flex_items(&mut self, component_id: ComponentId, flex_layout: FlexLayout) {
+        let layout_box = self.layout_boxes.last_mut().unwrap();
+        let main_available = match flex_layout.direction {
+            Direction::Right => layout_box.available_width - layout_box.layout.padding.l,
+            Direction::Down => layout_box.available_height - layout_box.layout.padding.t,
+        };
+        let flex_sizes =
+            self.flex_box_measures.entry(component_id).or_default().resolve_flex_sizes(main_available, flex_layout.gap);
+        layout_box.flex = Some(CxFlexBox { flex_layout, component_id, flex_sizes, items: vec![] });
+    }
+
+    /// Turn the current box into a [`CxGrid`], sizing its [`GridTrack::Auto`] tracks based on the previous draw.
+    pub(crate) fn begin_grid_tracks(&mut self, component_id: ComponentId, columns: &[GridTrack], rows: &[GridTrack]) {
+        let old_measure = self.grid_measures.entry(component_id).or_default();
+        let layout_box = self.layout_boxes.last_mut().unwrap();
+        let column_offsets = resolve_grid_tracks(columns, &old_measure.columns, layout_box.available_width);
+        let row_offsets = resolve_grid_tracks(rows, &old_measure.rows, layout_box.available_height);
+        layout_box.grid = Some(CxGrid {
+            columns: columns.to_vec(),
+            rows: rows.to_vec(),
+            component_id,
+            column_offsets,
+            row_offsets,
+            measure: CxGridMeasure { columns: vec![0.; columns.len()], rows: vec![0.; rows.len()] },
+            current_cell: None,
+        });
+    }
+
+    /// Make the current [`CxGrid`] walk all of its tracks, and remember the size of its [`GridTrack::Auto`] tracks
+    /// in [`Cx::grid_measures`]. If that changed since the previous draw, we request another draw.
+    pub(crate) fn end_grid_tracks(&mut self) {
+        let layout_box = self.layout_boxes.last_mut().unwrap();
+        let grid = layout_box.grid.take().unwrap();
+        assert!(grid.current_cell.is_none(), "Forgot to call end_grid_cell");
+        let size = vec2(*grid.column_offsets.last().unwrap(), *grid.row_offsets.last().unwrap());
+        layout_box.bound_right_bottom = layout_box.origin + size;
+
+        let old_measure = self.grid_measures.get_mut(&grid.component_id).unwrap();
+        let changed = |old: &[f32], new: &[f32]| old.len() != new.len() || old.iter().zip(new).any(|(a, b)| (a - b).abs() > 0.01);
+        if changed(&old_measure.columns, &grid.measure.columns) || changed(&old_measure.rows, &grid.measure.rows) {
+            *old_measure = grid.measure;
+            self.request_draw();
+        }
+    }
+
+    /// Start a box for a [`GridCell`] in the current [`CxGrid`]. Cells that only cover [`GridTrack::Auto`] tracks
+    /// get a computed size, so we can measure their contents.
+    pub(crate) fn begin_grid_cell_box(&mut self, cell: GridCell) {
+        let layout_box = self.layout_boxes.last_mut().unwrap();
+        let grid = layout_box.grid.as_mut().expect("begin_grid_cell must be called directly inside of begin_grid");
+        assert!(grid.current_cell.is_none(), "Grid cells can't be nested; start a new grid instead");
+        let rect = grid.cell_rect(&cell);
+        let width = if grid.columns[cell.column..cell.column + cell.column_span].iter().all(|track| *track == GridTrack::Auto) {
+            Width::Compute
+        } else {
+            Width::Fix(rect.size.x)
+        };
+        let height = if grid.rows[cell.row..cell.row + cell.row_span].iter().all(|track| *track == GridTrack::Auto) {
+            Height::Compute
+        } else {
+            Height::Fix(rect.size.y)
+        };
+        grid.current_cell = Some(cell);
+        layout_box.pos = layout_box.origin + rect.pos;
+
+        self.begin_typed_box(CxBoxType::GridCell, Layout { layout_size: LayoutSize { width, height }, ..Layout::default() });
+    }
+
+    /// End the box started in [`Cx::begin_grid_cell_box`], measuring its contents and aligning them within the cell.
+    /// This doesn't walk the [`CxGrid`], since cells are placed explicitly.
+    pub(crate) fn end_grid_cell_box(&mut self) {
+        self.assert_last_box_type_matches(CxBoxType::GridCell);
+        let cell_box = self.layout_boxes.pop().unwrap();
+        let content_size = if cell_box.bound_right_bottom.x == std::f32::NEG_INFINITY {
+            Vec2::default()
+        } else {
+            (cell_box.bound_right_bottom - cell_box.origin).max(&Vec2::default())
+        };
+
+        let layout_box = self.layout_boxes.last_mut().unwrap();
+        layout_box.pos = layout_box.origin;
+        let grid = layout_box.grid.as_mut().unwrap();
+        let cell = grid.current_cell.take().unwrap();
+        if cell.column_span == 1 && grid.columns[cell.column] == GridTrack::Auto {
+            grid.measure.columns[cell.column] = grid.measure.columns[cell.column].max(content_size.x);
+        }
+        if cell.row_span == 1 && grid.rows[cell.row] == GridTrack::Auto {
+            grid.measure.rows[cell.row] = grid.measure.rows[cell.row].max(content_size.y);
+        }
+
+        let cell_size = grid.cell_rect(&cell).size;
+        let align_factor = |align: AlignItems| match align {
+            AlignItems::Start => 0.,
+            AlignItems::Center => 0.5,
+            AlignItems::End => 1.,
+        };
+        let dx = align_factor(cell.align_x) * (cell_size.x - content_size.x);
+        let dy = align_factor(cell.align_y) * (cell_size.y - content_size.y);
+        if dx > 0. {
+            self.do_align_x(dx, cell_box.align_list_x_start_index);
+        }
+        if dy > 0. {
+            self.do_align_y(dy, cell_box.align_list_y_start_index);
+        }
+    }
+
+    /// Move the items of the current [`CxFlexBox`] according to its [`FlexLayout::justify`] and
+    /// [`FlexLayout::align`], and remember what it walked in [`Cx::flex_box_measures`]. If that changed since the
+    /// previous draw, then the flex items were sized incorrectly, so we request another draw.
+    pub(crate) fn end_flex_items(&mut self) {
+        let layout_box = self.layout_boxes.last_mut().unwrap();
+        let flex = layout_box.flex.take().unwrap();
+        let align_list_len = self.layout_box_align_list.len();
+        let item_count = flex.items.len();
+
+        let measure = CxFlexMeasure {
+            flex_specs: flex.items.iter().filter_map(|item| item.flex_spec).collect(),
+            item_count,
+            fixed_size: flex
+                .items
+                .iter()
+                .filter(|item| item.flex_spec.is_none())
+                .map(|item| flex.main_size(item.rect.size))
+                .sum(),
+        };
+        let old_measure = self.flex_box_measures.get_mut(&flex.component_id).unwrap();
+        if old_measure.flex_specs != measure.flex_specs
+            || old_measure.item_count != measure.item_count
+            || f32::abs(old_measure.fixed_size - measure.fixed_size) > 0.01
+        {
+            *old_measure = measure;
+            self.request_draw();
+        }
+
+        let layout_box = self.layout_boxes.last().unwrap();
+        let (main_size, cross_size) = match flex.flex_layout.direction {
+            Direction::Right => (layout_box.width, layout_box.height),
+            Direction::Down => (layout_box.height, layout_box.width),
+        };
+        let (main_available, cross_available) = match flex.flex_layout.direction {
+            Direction::Right => (layout_box.available_width, layout_box.available_height),
+            Direction::Down => (layout_box.available_height, layout_box.available_width),
+        };
+
+        let used: f32 = flex.items.iter().map(|item| flex.main_size(item.rect.size)).sum::<f32>()
+            + flex.flex_layout.gap * (item_count.max(1) - 1) as f32;
+        let free = if main_size.is_nan() { 0. } else { (main_available - used).max(0.) };
+        let cross_size = if cross_size.is_nan() {
+            flex.items.iter().map(|item| flex.cross_size(item.rect.size)).fold(0., f32::max)
+        } else {
+            cross_available
+        };
+
+        for (index, item) in flex.items.iter().enumerate() {
+            let index = index as f32;
+            let count = item_count as f32;
+            let main_offset = match flex.flex_layout.justify {
+                Justify::Start => 0.,
+                Justify::End => free,
+                Justify::Center => free / 2.,
+                Justify::SpaceBetween if item_count > 1 => free * index / (count - 1.),
+                Justify::SpaceBetween => 0.,
+                Justify::SpaceAround => free * (index + 0.5) / count,
+                Justify::SpaceEvenly => free * (index + 1.) / (count + 1.),
+            };
+            let cross_offset = match flex.flex_layout.align {
+                AlignItems::Start => 0.,
+                AlignItems::Center => (cross_size - flex.cross_size(item.rect.size)) / 2.,
+                AlignItems::End => cross_size - flex.cross_size(item.rect.size),
+            }
+            .max(0.);
+            let (dx, dy) = match flex.flex_layout.direction {
+                Direction::Right => (main_offset, cross_offset),
+                Direction::Down => (cross_offset, main_offset),
+            };
+            let align_list_end_index = item.align_list_end_index.min(align_list_len);
+            if dx > 0. {
+                self.move_range_by_x(dx, item.align_list_start_index, align_list_end_index);
+            }
+            if dy > 0. {
+                self.move_range_by_y(dy, item.align_list_start_index, align_list_end_index);
+            }
         }
     }
 
@@ -559,3 +1011,145 @@ pub(crate) fn min_keep_nan(a: f32, b: f32) -> f32 {
         f32::min(a, b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[cfg(target_os = "linux")]
+    static SHADER: Shader = Shader {
+        build_geom: Some(QuadIns::build_geom),
+        code_to_concatenate: &[
+            Cx::STD_SHADER,
+            QuadIns::SHADER,
+            code_fragment!(
+                r#"
+                fn pixel() -> vec4 {
+                    return vec4(0., 1., 0., 1.);
+                }
+                "#
+            ),
+        ],
+        ..Shader::DEFAULT
+    };
+
+    #[cfg(target_os = "linux")]
+    const GREEN: Vec4 = vec4(0., 1., 0., 1.);
+    #[cfg(target_os = "linux")]
+    const BLUE: Vec4 = vec4(0., 0., 1., 1.);
+
+    /// Draws a window of `size`, calling `draw_contents` to draw inside of its main view.
+    #[cfg(target_os = "linux")]
+    fn draw_window(size: Vec2, mut draw_contents: impl FnMut(&mut Cx)) -> HeadlessFramebuffer {
+        let mut cx = Cx::new_test();
+        let mut window = Window { create_inner_size: Some(size), ..Window::default() };
+        let mut pass = Pass::default();
+        let mut view = View::default();
+        let mut event_handler = |cx: &mut Cx, event: &mut Event| {
+            if let Event::System(SystemEvent::Draw) = event {
+                window.begin_window(cx);
+                pass.begin_pass(cx, BLUE);
+                view.begin_view(cx, LayoutSize::FILL);
+                draw_contents(cx);
+                view.end_view(cx);
+                pass.end_pass(cx);
+                window.end_window(cx);
+            }
+        };
+        cx.headless_start(&mut event_handler);
+        cx.headless_window_framebuffer(0).unwrap().clone()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn draw_quad(cx: &mut Cx, layout_size: LayoutSize) {
+        let rect = cx.add_box(layout_size);
+        cx.add_instances(&SHADER, &[QuadIns::from_rect(rect)]);
+    }
+
+    /// Draws `items` in a flex box that fills a window of `size`, with a quad for each item.
+    #[cfg(target_os = "linux")]
+    fn draw_flex_box(size: Vec2, flex_layout: FlexLayout, items: &[LayoutSize]) -> HeadlessFramebuffer {
+        let component_id = ComponentId::default();
+        draw_window(size, |cx| {
+            cx.begin_flex_box(component_id, Width::Fill, Height::Fill, flex_layout);
+            for item in items {
+                draw_quad(cx, *item);
+            }
+            cx.end_flex_box();
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_flex_box_weights_and_gap() {
+        let framebuffer = draw_flex_box(
+            vec2(100., 10.),
+            FlexLayout { gap: 10., ..FlexLayout::default() },
+            &[
+                LayoutSize::new(Width::Fix(20.), Height::Fill),
+                LayoutSize::new(Width::Flex(1.), Height::Fill),
+                LayoutSize::new(Width::Flex(2.), Height::Fill),
+            ],
+        );
+        let row: Vec<Vec4> = [0, 19, 20, 29, 30, 49, 50, 59, 60, 99].iter().map(|&x| framebuffer.get_pixel(x, 5)).collect();
+        assert_eq!(row, vec![GREEN, GREEN, BLUE, BLUE, GREEN, GREEN, BLUE, BLUE, GREEN, GREEN]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_flex_box_min_max() {
+        let framebuffer = draw_flex_box(
+            vec2(100., 10.),
+            FlexLayout { gap: 10., ..FlexLayout::default() },
+            &[
+                LayoutSize::new(Width::FlexMinMax { weight: 1., min: 0., max: 20. }, Height::Fill),
+                LayoutSize::new(Width::Flex(1.), Height::Fill),
+            ],
+        );
+        // The first item is capped at 20, and the second one gets the rest.
+        let row: Vec<Vec4> = [0, 19, 20, 29, 30, 99].iter().map(|&x| framebuffer.get_pixel(x, 5)).collect();
+        assert_eq!(row, vec![GREEN, GREEN, BLUE, BLUE, GREEN, GREEN]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_flex_box_justify_and_align() {
+        let item = LayoutSize::new(Width::Fix(10.), Height::Fix(10.));
+        let framebuffer = draw_flex_box(
+            vec2(50., 30.),
+            FlexLayout { justify: Justify::SpaceBetween, align: AlignItems::Center, ..FlexLayout::default() },
+            &[item, item, item],
+        );
+        let row: Vec<Vec4> = [0, 9, 10, 19, 20, 29, 30, 39, 40, 49].iter().map(|&x| framebuffer.get_pixel(x, 15)).collect();
+        assert_eq!(row, vec![GREEN, GREEN, BLUE, BLUE, GREEN, GREEN, BLUE, BLUE, GREEN, GREEN]);
+        let column: Vec<Vec4> = [9, 10, 19, 20].iter().map(|&y| framebuffer.get_pixel(5, y)).collect();
+        assert_eq!(column, vec![BLUE, GREEN, GREEN, BLUE]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_grid() {
+        let component_id = ComponentId::default();
+        let framebuffer = draw_window(vec2(60., 30.), |cx| {
+            cx.begin_grid(component_id, &[GridTrack::Auto, GridTrack::Fraction(1.)], &[GridTrack::Fix(10.), GridTrack::Fix(10.)]);
+            cx.begin_grid_cell(GridCell::new(0, 0));
+            draw_quad(cx, LayoutSize::new(Width::Fix(15.), Height::Fix(10.)));
+            cx.end_grid_cell();
+            cx.begin_grid_cell(GridCell::new(1, 0).with_align(AlignItems::End, AlignItems::Start));
+            draw_quad(cx, LayoutSize::new(Width::Fix(10.), Height::Fix(10.)));
+            cx.end_grid_cell();
+            cx.begin_grid_cell(GridCell::new(0, 1).with_span(2, 1).with_align(AlignItems::Center, AlignItems::End));
+            draw_quad(cx, LayoutSize::new(Width::Fix(10.), Height::Fix(4.)));
+            cx.end_grid_cell();
+            cx.end_grid();
+        });
+        // The auto column is as wide as its cell, and the fraction column takes the rest.
+        let row: Vec<Vec4> = [0, 14, 15, 49, 50, 59].iter().map(|&x| framebuffer.get_pixel(x, 5)).collect();
+        assert_eq!(row, vec![GREEN, GREEN, BLUE, BLUE, GREEN, GREEN]);
+        // The cell that spans both columns is aligned to the bottom center.
+        let row: Vec<Vec4> = [24, 25, 34, 35].iter().map(|&x| framebuffer.get_pixel(x, 17)).collect();
+        assert_eq!(row, vec![BLUE, GREEN, GREEN, BLUE]);
+        let column: Vec<Vec4> = [15, 16, 19, 20].iter().map(|&y| framebuffer.get_pixel(30, y)).collect();
+        assert_eq!(column, vec![BLUE, GREEN, GREEN, BLUE]);
+    }
+}
@@ -20,6 +20,8 @@
 #[macro_use]
 mod macros;
 
+#[cfg(target_os = "linux")]
+mod cx_headless;
 #[cfg(any(target_os = "linux"))]
 mod cx_linux;
 #[cfg(target_os = "linux")]
@@ -27,6 +29,8 @@ mod cx_opengl;
 #[cfg(target_os = "linux")]
 mod cx_xlib;
 #[cfg(target_os = "linux")]
+pub use cx_headless::*;
+#[cfg(target_os = "linux")]
 pub(crate) use cx_linux::*;
 #[cfg(target_os = "linux")]
 pub(crate) use cx_opengl::*;
@@ -64,6 +68,10 @@ mod cx_webgl;
 mod cx_desktop;
 #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
 pub(crate) use cx_desktop::*;
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+mod desktop_http;
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+mod desktop_websocket;
 
 #[cfg(target_arch = "wasm32")]
 pub use cx_wasm32::*;
@@ -94,7 +102,9 @@ mod draw_tree;
 mod events;
 mod fonts;
 mod geometry;
+pub mod grapheme;
 mod hash;
+mod http;
 mod layout;
 mod layout_api;
 mod layout_internal;
@@ -116,6 +126,7 @@ mod image_ins;
 mod menu;
 mod quad_ins;
 mod std_shader;
+mod test_harness;
 mod text_ins;
 
 use cast::*;
@@ -131,6 +142,7 @@ pub use image_ins::*;
 pub use param::*;
 pub use quad_ins::*;
 pub use std_shader::*;
+pub use test_harness::*;
 pub use text_ins::*;
 pub use texture::*;
 pub use window::*;
@@ -145,6 +157,7 @@ pub use draw_tree::*;
 pub use fonts::*;
 pub use geometry::*;
 pub use hash::*;
+pub use http::*;
 pub use layout::*;
 pub use layout_api::*;
 pub use layout_internal::*;
@@ -4,9 +4,23 @@
 ///
 /// Your `$app` should implement the `draw` and `handle`
 /// functions. Refer to the examples to get started.
+///
+/// This also implements [`MainApp`] for `$app`, so you can test it using [`AppTest`].
 #[macro_export]
 macro_rules! main_app {
     ( $ app: ident) => {
+        impl MainApp for $app {
+            fn new(cx: &mut Cx) -> Self {
+                $app::new(cx)
+            }
+            fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+                $app::handle(self, cx, event);
+            }
+            fn draw(&mut self, cx: &mut Cx) {
+                $app::draw(self, cx);
+            }
+        }
+
         #[cfg(not(target_arch = "wasm32"))]
         fn main() {
             //TODO do this with a macro to generate both entrypoints for App and Cx
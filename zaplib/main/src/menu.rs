@@ -27,6 +27,13 @@ impl Cx {
         Cx::COMMAND_ZOOM_IN.set_key(self, KeyCode::Equals);
         Cx::COMMAND_MINIMIZE.set_key(self, KeyCode::KeyM);
     }
+
+    /// Triggers an [`Event::Command`], just like picking an item from a native [`Menu`] does.
+    ///
+    /// This is useful for menus that are drawn inside of the window, on platforms that don't have native menus.
+    pub fn send_command(&mut self, command: CommandId) {
+        self.commands.push(command);
+    }
 }
 
 /// An alias over LocationHash so we have a semantic type
@@ -35,6 +42,18 @@ impl Cx {
 pub type CommandId = LocationHash;
 
 impl CommandId {
+    /// Whether this command can currently be used. Like native menu items, commands are disabled until they get
+    /// enabled using [`CommandId::set_enabled`].
+    pub fn is_enabled(&self, cx: &Cx) -> bool {
+        cx.command_settings.get(self).map_or(false, |s| s.enabled)
+    }
+
+    /// The key that triggers this command together with the platform's command key (e.g. Cmd on Mac, Ctrl
+    /// elsewhere), and whether it also needs Shift. See [`CommandId::set_key`] and [`CommandId::set_key_shift`].
+    pub fn get_key(&self, cx: &Cx) -> Option<(KeyCode, bool)> {
+        cx.command_settings.get(self).filter(|s| s.key_code != KeyCode::Unknown).map(|s| (s.key_code, s.shift))
+    }
+
     pub fn set_enabled(&self, cx: &mut Cx, enabled: bool) {
         let mut s = if let Some(s) = cx.command_settings.get(self) { *s } else { CxCommandSetting::default() };
         s.enabled = enabled;
@@ -74,6 +74,7 @@ impl Pass {
 
         let cxpass = &mut cx.passes[pass_id];
         cxpass.main_view_id = None;
+        cxpass.overlay_view_ids.clear();
         cxpass.color_textures.truncate(0);
         cx.pass_stack.push(pass_id);
     }
@@ -121,13 +122,26 @@ impl Pass {
     }
 
     pub fn end_pass(&mut self, cx: &mut Cx) {
-        cx.pass_stack.pop();
+        let pass_id = cx.pass_stack.pop().expect("Not inside a Pass::begin_pass currently");
+        if let Some(main_view_id) = cx.passes[pass_id].main_view_id {
+            for overlay_view_id in std::mem::take(&mut cx.passes[pass_id].overlay_view_ids) {
+                cx.add_sub_view_draw_call(main_view_id, overlay_view_id);
+            }
+        }
         if !cx.pass_stack.is_empty() {
             cx.current_dpi_factor = cx.get_delegated_dpi_factor(*cx.pass_stack.last().unwrap());
         }
     }
 }
 
+impl Cx {
+    /// The size of the [`Pass`] that is currently being drawn. For the main [`Pass`] of a window this is the
+    /// size of the window, which is useful for keeping popups inside of it.
+    pub fn get_pass_size(&self) -> Vec2 {
+        self.pass_stack.last().map_or(Vec2::default(), |pass_id| self.passes[*pass_id].pass_size)
+    }
+}
+
 /// The color to either initialize a [`Texture`] with (when rendering it for the very first time),
 /// or to clear it with on every paint.
 #[derive(Clone)]
@@ -166,7 +180,7 @@ pub(crate) struct PassUniforms {
     /// The inverse rotation matrix for a camera. Useful for working with billboards.
     inv_camera_rot: [f32; 16],
     /// More commonly known as the "device pixel ratio". TODO(JP): Rename?
-    dpi_factor: f32,
+    pub(crate) dpi_factor: f32,
     /// Some amount by which to thicken lines, that depends on the "device pixel ratio"
     /// ([`PassUniforms`]).
     ///
@@ -198,6 +212,9 @@ pub(crate) struct CxPass {
     pub(crate) clear_depth: ClearDepth,
     pub(crate) override_dpi_factor: Option<f32>,
     pub(crate) main_view_id: Option<usize>,
+    /// The overlay [`View`]s (see [`View::with_is_overlay`]) that were drawn in this pass, in order. These get
+    /// added to the main [`View`] when the pass ends, so that they are drawn on top of everything else.
+    pub(crate) overlay_view_ids: Vec<usize>,
     pub(crate) dep_of: CxPassDepOf,
     pub(crate) paint_dirty: bool,
     pub(crate) pass_size: Vec2,
@@ -218,6 +235,7 @@ impl Default for CxPass {
             override_dpi_factor: None,
             clear_depth: ClearDepth::ClearWith(1.0),
             main_view_id: None,
+            overlay_view_ids: Vec::new(),
             dep_of: CxPassDepOf::None,
             paint_dirty: false,
             pass_size: Vec2::default(),
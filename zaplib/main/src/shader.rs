@@ -294,6 +294,9 @@ pub(crate) struct CxShader {
     pub(crate) platform: Option<CxPlatformShader>,
     pub(crate) mapping: CxShaderMapping,
     pub(crate) shader_ast: Option<ShaderAst>,
+    /// The static [`Shader`] that this was compiled from, so we can check if [`Shader::shader_id`]
+    /// actually refers to this [`Cx`], in case there are multiple in one process (e.g. in tests).
+    pub(crate) static_shader: Option<&'static Shader>,
 }
 
 impl Cx {
@@ -302,7 +305,11 @@ impl Cx {
     /// For more information on what [`LocationHash`] is used for here, see [`Shader`].
     pub(crate) fn get_shader_id(&mut self, shader: &'static Shader) -> usize {
         let shader_id = shader.shader_id.load(Ordering::Relaxed);
-        if shader_id != Shader::UNCOMPILED_SHADER_ID {
+        let is_this_shader = |cx_shader: &CxShader| cx_shader.static_shader.map_or(false, |s| std::ptr::eq(s, shader));
+        if shader_id != Shader::UNCOMPILED_SHADER_ID && self.shaders.get(shader_id).map_or(false, is_this_shader) {
+            shader_id
+        } else if let Some(shader_id) = self.shaders.iter().position(is_this_shader) {
+            // Compiled in this `Cx`, but `shader.shader_id` was overwritten by another `Cx`.
             shader_id
         } else {
             // Use the last code fragment as the shader name.
@@ -319,6 +326,7 @@ impl Cx {
                         mapping: CxShaderMapping::from_shader_ast(shader_ast.clone()),
                         platform: None,
                         shader_ast: Some(shader_ast),
+                        static_shader: Some(shader),
                     });
                     self.shader_recompile_ids.push(shader_id);
 
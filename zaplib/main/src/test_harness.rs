@@ -0,0 +1,500 @@
+//! Harness for regression testing apps and components.
+//!
+//! [`AppTest`] runs an app on the headless backend (see [`Cx::headless_start`]), lets you feed it a
+//! scripted sequence of [`Event`]s, and then compares snapshots of the draw tree or of the rendered
+//! pixels against "golden" files that are checked into the repo.
+//!
+//! To (re)generate golden files, run the tests with the `ZAPLIB_UPDATE_GOLDEN=1` environment variable,
+//! and then carefully review the changes before committing them.
+
+use crate::*;
+use std::path::Path;
+
+/// The methods that [`main_app!`] calls on an app. [`main_app!`] implements this trait for you, so you
+/// only need to implement it yourself for apps that exist only in tests.
+pub trait MainApp {
+    fn new(cx: &mut Cx) -> Self;
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event);
+    fn draw(&mut self, cx: &mut Cx);
+}
+
+/// Environment variable that makes [`assert_golden_text`] and [`assert_golden_image`] overwrite golden
+/// files instead of comparing against them.
+const UPDATE_GOLDEN_ENV_VAR: &str = "ZAPLIB_UPDATE_GOLDEN";
+
+/// Runs a [`MainApp`] headless, so you can send it events and compare its output against golden files.
+///
+/// All pointer events are sent to the first window, using a single mouse pointer.
+///
+/// Example:
+/// ```ignore
+/// let mut test = AppTest::<MyApp>::new();
+/// test.pointer_down(vec2(10., 10.));
+/// test.pointer_move(vec2(50., 10.));
+/// test.pointer_up();
+/// test.advance_time(1.0);
+/// test.assert_golden_draw_tree(concat!(env!("CARGO_MANIFEST_DIR"), "/golden/my_app.txt"));
+/// ```
+#[cfg(target_os = "linux")]
+pub struct AppTest<A: MainApp> {
+    pub cx: Cx,
+    pub app: A,
+    cx_after_draw: CxAfterDraw,
+    /// Mirrors the simulated time of the headless backend, for timestamping events.
+    time: f64,
+    pointer_pos: Vec2,
+    pointer_start_pos: Vec2,
+    pointer_is_down: bool,
+    modifiers: KeyModifiers,
+    /// Set when using [`AppTest::new_in_window`].
+    test_window: Option<TestWindow>,
+}
+
+/// The window that [`AppTest::new_in_window`] draws the app into.
+#[cfg(target_os = "linux")]
+struct TestWindow {
+    window: Window,
+    pass: Pass,
+    view: View,
+}
+
+#[cfg(target_os = "linux")]
+impl<A: MainApp> AppTest<A> {
+    /// Create the app, send [`Event::Construct`], and draw and paint the first frame.
+    pub fn new() -> Self {
+        Self::new_with_test_window(None)
+    }
+
+    /// Same as [`AppTest::new`], but for apps that draw only some contents, not a whole window. Every
+    /// [`MainApp::draw`] gets wrapped in a window of `size`, with a black [`Pass`] and a [`View`] that fills it.
+    ///
+    /// This is useful for testing components:
+    /// ```ignore
+    /// struct ButtonTestApp {
+    ///     button: Button,
+    /// }
+    ///
+    /// impl MainApp for ButtonTestApp {
+    ///     fn new(_cx: &mut Cx) -> Self {
+    ///         Self { button: Button::default() }
+    ///     }
+    ///
+    ///     fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+    ///         self.button.handle(cx, event);
+    ///     }
+    ///
+    ///     fn draw(&mut self, cx: &mut Cx) {
+    ///         self.button.draw(cx, "Hello");
+    ///     }
+    /// }
+    ///
+    /// let mut test = AppTest::<ButtonTestApp>::new_in_window(vec2(100., 50.));
+    /// ```
+    pub fn new_in_window(size: Vec2) -> Self {
+        Self::new_with_test_window(Some(TestWindow {
+            window: Window { create_inner_size: Some(size), ..Window::default() },
+            pass: Pass::default(),
+            view: View::default(),
+        }))
+    }
+
+    fn new_with_test_window(test_window: Option<TestWindow>) -> Self {
+        let mut cx = Cx::new_test();
+        let app = A::new(&mut cx);
+        let cx_after_draw = CxAfterDraw::new(&mut cx);
+        cx.set_finished_app_new();
+        let mut test = Self {
+            cx,
+            app,
+            cx_after_draw,
+            time: 0.0,
+            pointer_pos: Vec2::default(),
+            pointer_start_pos: Vec2::default(),
+            pointer_is_down: false,
+            modifiers: KeyModifiers::default(),
+            test_window,
+        };
+        let Self { cx, app, cx_after_draw, test_window, .. } = &mut test;
+        cx.headless_start(&mut |cx: &mut Cx, event: &mut Event| Self::call_app(app, cx_after_draw, test_window, cx, event));
+        test
+    }
+
+    /// Same as the event handler that [`main_app!`] generates.
+    fn call_app(
+        app: &mut A,
+        cx_after_draw: &mut CxAfterDraw,
+        test_window: &mut Option<TestWindow>,
+        cx: &mut Cx,
+        event: &mut Event,
+    ) {
+        match event {
+            Event::System(SystemEvent::Draw) => {
+                match test_window {
+                    Some(TestWindow { window, pass, view }) => {
+                        window.begin_window(cx);
+                        pass.begin_pass(cx, COLOR_BLACK);
+                        view.begin_view(cx, LayoutSize::FILL);
+                        app.draw(cx);
+                        view.end_view(cx);
+                        pass.end_pass(cx);
+                        window.end_window(cx);
+                    }
+                    None => app.draw(cx),
+                }
+                cx_after_draw.after_draw(cx);
+            }
+            Event::System(_) => {}
+            _ => app.handle(cx, event),
+        }
+    }
+
+    /// Send an arbitrary [`Event`], and then draw and paint if necessary.
+    pub fn send_event(&mut self, event: Event) {
+        let Self { cx, app, cx_after_draw, test_window, .. } = self;
+        cx.headless_send_event(
+            &mut |cx: &mut Cx, event: &mut Event| Self::call_app(app, cx_after_draw, test_window, cx, event),
+            event,
+        );
+    }
+
+    /// Advance the simulated time, firing any [`Event::Timer`]s and [`Event::NextFrame`] along the way.
+    pub fn advance_time(&mut self, seconds: f64) {
+        self.time += seconds;
+        let Self { cx, app, cx_after_draw, test_window, .. } = self;
+        cx.headless_advance_time(
+            &mut |cx: &mut Cx, event: &mut Event| Self::call_app(app, cx_after_draw, test_window, cx, event),
+            seconds,
+        );
+    }
+
+    /// Advance the simulated time by a single frame at 60fps.
+    pub fn next_frame(&mut self) {
+        self.advance_time(1.0 / 60.0);
+    }
+
+    /// Set the [`KeyModifiers`] used for all subsequent pointer and keyboard events.
+    pub fn set_modifiers(&mut self, modifiers: KeyModifiers) {
+        self.modifiers = modifiers;
+    }
+
+    /// Move the mouse to `pos`, sending [`Event::PointerMove`] if the mouse is down, and always
+    /// [`Event::PointerHover`] (same as the native backends).
+    pub fn pointer_move(&mut self, pos: Vec2) {
+        self.pointer_pos = pos;
+        if self.pointer_is_down {
+            self.send_event(Event::PointerMove(PointerMoveEvent {
+                window_id: 0,
+                abs: pos,
+                abs_start: self.pointer_start_pos,
+                rel: pos,
+                rel_start: self.pointer_start_pos,
+                rect: Rect::default(),
+                is_over: false,
+                digit: 0,
+                input_type: PointerInputType::Mouse,
+                modifiers: self.modifiers.clone(),
+                time: self.time,
+            }));
+        }
+        self.send_event(Event::PointerHover(PointerHoverEvent {
+            window_id: 0,
+            digit: 0,
+            abs: pos,
+            rel: pos,
+            rect: Rect::default(),
+            any_down: self.pointer_is_down,
+            handled: false,
+            hover_state: HoverState::Over,
+            modifiers: self.modifiers.clone(),
+            time: self.time,
+        }));
+    }
+
+    /// Move the mouse to `pos` and press the left mouse button.
+    pub fn pointer_down(&mut self, pos: Vec2) {
+        if pos != self.pointer_pos {
+            self.pointer_move(pos);
+        }
+        self.pointer_is_down = true;
+        self.pointer_start_pos = pos;
+        self.send_event(Event::PointerDown(PointerDownEvent {
+            window_id: 0,
+            abs: pos,
+            rel: pos,
+            rect: Rect::default(),
+            digit: 0,
+            button: MouseButton::Left,
+            tap_count: 0,
+            handled: false,
+            input_type: PointerInputType::Mouse,
+            modifiers: self.modifiers.clone(),
+            time: self.time,
+        }));
+    }
+
+    /// Release the left mouse button at the current mouse position.
+    pub fn pointer_up(&mut self) {
+        self.pointer_is_down = false;
+        self.send_event(Event::PointerUp(PointerUpEvent {
+            window_id: 0,
+            abs: self.pointer_pos,
+            abs_start: self.pointer_start_pos,
+            rel: self.pointer_pos,
+            rel_start: self.pointer_start_pos,
+            rect: Rect::default(),
+            digit: 0,
+            button: MouseButton::Left,
+            is_over: false,
+            input_type: PointerInputType::Mouse,
+            modifiers: self.modifiers.clone(),
+            time: self.time,
+        }));
+    }
+
+    /// Press and release the left mouse button at `pos`.
+    pub fn click(&mut self, pos: Vec2) {
+        self.pointer_down(pos);
+        self.pointer_up();
+    }
+
+    /// Send [`Event::KeyDown`] followed by [`Event::KeyUp`].
+    pub fn key_press(&mut self, key_code: KeyCode) {
+        let key_event = KeyEvent { key_code, is_repeat: false, modifiers: self.modifiers.clone(), time: self.time };
+        self.send_event(Event::KeyDown(key_event.clone()));
+        self.send_event(Event::KeyUp(key_event));
+    }
+
+    /// Send [`Event::TextInput`], as if `input` was typed.
+    pub fn text_input(&mut self, input: &str) {
+        self.send_event(Event::TextInput(TextInputEvent { input: input.to_string(), replace_last: false, was_paste: false }));
+    }
+
+    /// The pixels of the first window, as last painted.
+    pub fn framebuffer(&mut self) -> &HeadlessFramebuffer {
+        self.cx.headless_window_framebuffer(0).expect("App didn't create a window")
+    }
+
+    /// Text dump of the draw trees of all passes that end up in a window. See also [`Cx::debug_flags_mut`].
+    pub fn draw_tree(&mut self) -> String {
+        let mut s = String::new();
+        for pass_id in 0..self.cx.passes.len() {
+            if matches!(self.cx.passes[pass_id].dep_of, CxPassDepOf::None) {
+                continue;
+            }
+            if let Some(view_id) = self.cx.passes[pass_id].main_view_id {
+                self.cx.debug_draw_tree_recur(false, false, &mut s, view_id, 0);
+            }
+        }
+        s
+    }
+
+    /// Compare [`AppTest::draw_tree`] against a golden file; see [`assert_golden_text`].
+    pub fn assert_golden_draw_tree(&mut self, path: impl AsRef<Path>) {
+        let draw_tree = self.draw_tree();
+        assert_golden_text(path, &draw_tree);
+    }
+
+    /// Compare [`AppTest::framebuffer`] against a golden file; see [`assert_golden_image`].
+    pub fn assert_golden_image(&mut self, path: impl AsRef<Path>) {
+        assert_golden_image(path, self.framebuffer());
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<A: MainApp> Default for AppTest<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn should_update_golden() -> bool {
+    std::env::var_os(UPDATE_GOLDEN_ENV_VAR).map_or(false, |value| !value.is_empty() && value != "0")
+}
+
+fn write_golden(path: &Path, contents: &[u8]) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).unwrap_or_else(|err| panic!("Could not create {}: {}", parent.display(), err));
+    }
+    std::fs::write(path, contents).unwrap_or_else(|err| panic!("Could not write {}: {}", path.display(), err));
+}
+
+/// Compare `actual` against the contents of the file at `path`, or overwrite the file if the
+/// `ZAPLIB_UPDATE_GOLDEN` environment variable is set.
+///
+/// On a mismatch, `actual` is written next to the golden file with an extra `.actual` extension, so
+/// you can diff the two.
+pub fn assert_golden_text(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    if should_update_golden() {
+        write_golden(path, actual.as_bytes());
+        return;
+    }
+    let expected = std::fs::read_to_string(path).unwrap_or_else(|err| {
+        panic!("Could not read {}: {}; run with {}=1 to create it", path.display(), err, UPDATE_GOLDEN_ENV_VAR)
+    });
+    if expected != actual {
+        let actual_path = path.with_extension(format!("{}.actual", path.extension().unwrap_or_default().to_string_lossy()));
+        std::fs::write(&actual_path, actual).unwrap();
+        let first_difference = expected.lines().zip(actual.lines()).position(|(a, b)| a != b).unwrap_or_else(|| {
+            // One is a prefix of the other.
+            expected.lines().count().min(actual.lines().count())
+        });
+        panic!(
+            "{} doesn't match, starting at line {}; see {} (run with {}=1 to update)",
+            path.display(),
+            first_difference + 1,
+            actual_path.display(),
+            UPDATE_GOLDEN_ENV_VAR
+        );
+    }
+}
+
+/// Compare `framebuffer` against a PNG image at `path`, or overwrite the file if the `ZAPLIB_UPDATE_GOLDEN`
+/// environment variable is set. The alpha channel is ignored.
+///
+/// On a mismatch, the actual image is written next to the golden file with an extra `.actual` extension.
+#[cfg(target_os = "linux")]
+pub fn assert_golden_image(path: impl AsRef<Path>, framebuffer: &HeadlessFramebuffer) {
+    let path = path.as_ref();
+    let actual_rgb = framebuffer_rgb(framebuffer);
+    if should_update_golden() {
+        write_golden(path, &encode_png(framebuffer.width, framebuffer.height, &actual_rgb));
+        return;
+    }
+    let expected = std::fs::read(path).unwrap_or_else(|err| {
+        panic!("Could not read {}: {}; run with {}=1 to create it", path.display(), err, UPDATE_GOLDEN_ENV_VAR)
+    });
+    let (width, height, expected_rgb) =
+        decode_png(&expected).unwrap_or_else(|| panic!("{} is not an 8-bit RGB PNG file", path.display()));
+    if (width, height, &expected_rgb) != (framebuffer.width, framebuffer.height, &actual_rgb) {
+        let actual_path = path.with_extension(format!("{}.actual", path.extension().unwrap_or_default().to_string_lossy()));
+        std::fs::write(&actual_path, encode_png(framebuffer.width, framebuffer.height, &actual_rgb)).unwrap();
+        let differing_pixels = if (width, height) == (framebuffer.width, framebuffer.height) {
+            expected_rgb.chunks(3).zip(actual_rgb.chunks(3)).filter(|(a, b)| a != b).count().to_string()
+        } else {
+            "all".to_string()
+        };
+        panic!(
+            "{} doesn't match ({} pixels differ); see {} (run with {}=1 to update)",
+            path.display(),
+            differing_pixels,
+            actual_path.display(),
+            UPDATE_GOLDEN_ENV_VAR
+        );
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn framebuffer_rgb(framebuffer: &HeadlessFramebuffer) -> Vec<u8> {
+    framebuffer.pixels.iter().flat_map(|pixel| <[u8; 3]>::try_from(&pixel.to_le_bytes()[0..3]).unwrap()).collect()
+}
+
+#[cfg(target_os = "linux")]
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Encode as an 8-bit RGB PNG file. Every row uses the "Up" filter, which works well for screenshots of UIs.
+#[cfg(target_os = "linux")]
+fn encode_png(width: usize, height: usize, rgb: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    let stride = width * 3;
+    let mut filtered = Vec::with_capacity((stride + 1) * height);
+    for y in 0..height {
+        filtered.push(2);
+        for x in 0..stride {
+            let above = if y > 0 { rgb[(y - 1) * stride + x] } else { 0 };
+            filtered.push(rgb[y * stride + x].wrapping_sub(above));
+        }
+    }
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(&filtered).unwrap();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // Bit depth, colour type (RGB), compression, filter method, interlacing.
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    for (chunk_type, data) in [(b"IHDR", header), (b"IDAT", encoder.finish().unwrap()), (b"IEND", vec![])] {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        png.extend_from_slice(chunk_type);
+        png.extend_from_slice(&data);
+        let mut crc = flate2::Crc::new();
+        crc.update(chunk_type);
+        crc.update(&data);
+        png.extend_from_slice(&crc.sum().to_be_bytes());
+    }
+    png
+}
+
+/// Decode an 8-bit RGB PNG file (like the ones that [`encode_png`] writes) into its width, height, and pixels.
+#[cfg(target_os = "linux")]
+fn decode_png(png: &[u8]) -> Option<(usize, usize, Vec<u8>)> {
+    use std::io::Read;
+
+    let mut rest = png.strip_prefix(&PNG_SIGNATURE[..])?;
+    let mut header = None;
+    let mut compressed = Vec::new();
+    while rest.len() >= 12 {
+        let len = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+        let data = rest.get(8..8 + len)?;
+        match &rest[4..8] {
+            b"IHDR" => header = Some(data),
+            b"IDAT" => compressed.extend_from_slice(data),
+            _ => {}
+        }
+        rest = rest.get(12 + len..)?;
+    }
+    let header = header.filter(|header| header.len() == 13 && header[8..] == [8, 2, 0, 0, 0])?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+
+    let mut filtered = Vec::new();
+    flate2::read::ZlibDecoder::new(&compressed[..]).read_to_end(&mut filtered).ok()?;
+    let stride = width * 3;
+    if filtered.len() != (stride + 1) * height {
+        return None;
+    }
+    let mut rgb = vec![0u8; stride * height];
+    for y in 0..height {
+        let filter = filtered[y * (stride + 1)];
+        for x in 0..stride {
+            let left = if x >= 3 { rgb[y * stride + x - 3] } else { 0 };
+            let above = if y > 0 { rgb[(y - 1) * stride + x] } else { 0 };
+            let above_left = if x >= 3 && y > 0 { rgb[(y - 1) * stride + x - 3] } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => above,
+                3 => ((left as u16 + above as u16) / 2) as u8,
+                4 => {
+                    let estimate = left as i16 + above as i16 - above_left as i16;
+                    let (a, b, c) =
+                        ((estimate - left as i16).abs(), (estimate - above as i16).abs(), (estimate - above_left as i16).abs());
+                    if a <= b && a <= c {
+                        left
+                    } else if b <= c {
+                        above
+                    } else {
+                        above_left
+                    }
+                }
+                _ => return None,
+            };
+            rgb[y * stride + x] = filtered[y * (stride + 1) + 1 + x].wrapping_add(predicted);
+        }
+    }
+    Some((width, height, rgb))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_round_trip() {
+        let rgb: Vec<u8> = (0..5 * 3 * 3).map(|i| (i * 37 % 256) as u8).collect();
+        assert_eq!(decode_png(&encode_png(5, 3, &rgb)), Some((5, 3, rgb)));
+    }
+}
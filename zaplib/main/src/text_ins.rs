@@ -8,6 +8,8 @@
 use std::{borrow::Borrow, sync::RwLock};
 
 use crate::*;
+use zaplib_vector::bidi;
+use zaplib_vector::shaping::ShapedGlyph;
 
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
@@ -237,7 +239,21 @@ struct TextChunk {
     newline: bool,
 }
 
+/// A position of a character for [`TextIns::closest_offset`].
+struct Caret {
+    base: Vec2,
+    width: f32,
+    font_size: f32,
+    char_offset: usize,
+}
+
 impl TextIns {
+    /// Generate glyphs for `chars`, starting at `pos`.
+    ///
+    /// Right-to-left text is reordered (see [`zaplib_vector::bidi`]), so the returned glyphs are in visual order,
+    /// and `char_callback` gets called with visual positions. Reordering only happens within `chars` though, so
+    /// pass whole lines rather than individual words or tokens (like [`TextIns::draw_walk`] does).
+    ///
     /// TODO(JP): It's hard to get text to render crisply; see
     /// * <https://github.com/Zaplib/zaplib/issues/169>
     /// * <https://github.com/Zaplib/zaplib/issues/174>
@@ -252,47 +268,112 @@ impl TextIns {
         pos: Vec2,
         char_offset: usize,
         chars: impl IntoIterator<Item = impl Borrow<char>>,
+        char_callback: F,
+    ) -> Vec<TextIns>
+    where
+        F: FnMut(char, usize, f32, f32) -> f32,
+    {
+        let chars: Vec<char> = chars.into_iter().map(|c| *c.borrow()).collect();
+        let levels = if bidi::has_rtl(&chars) { Some(bidi::levels(&chars)) } else { None };
+        Self::generate_2d_glyphs_with_levels(
+            text_style,
+            fonts_data,
+            dpi_factor,
+            font_scale,
+            draw_depth,
+            color,
+            pos,
+            char_offset,
+            &chars,
+            levels.as_deref(),
+            char_callback,
+        )
+    }
+
+    /// Like [`TextIns::generate_2d_glyphs`], but with bidi levels for `chars` that were computed for the whole
+    /// paragraph, e.g. when `chars` is one line of wrapped text. `None` means that everything is left-to-right.
+    #[allow(clippy::too_many_arguments)]
+    fn generate_2d_glyphs_with_levels<F>(
+        text_style: &TextStyle,
+        fonts_data: &RwLock<CxFontsData>,
+        dpi_factor: f32,
+        font_scale: f32,
+        draw_depth: f32,
+        color: Vec4,
+        pos: Vec2,
+        char_offset: usize,
+        chars: &[char],
+        levels: Option<&[u8]>,
         mut char_callback: F,
     ) -> Vec<TextIns>
     where
         F: FnMut(char, usize, f32, f32) -> f32,
     {
-        let chars = chars.into_iter();
-        let mut ret = Vec::with_capacity(chars.size_hint().0);
+        let mut ret = Vec::with_capacity(chars.len());
+
+        let mut read_lock = fonts_data.read().unwrap();
+
+        // Shape in logical order (with mirrored brackets in right-to-left runs), and then lay out the glyphs in
+        // visual order. Skip all of that for the common case of text without any right-to-left characters.
+        let (shaped_glyphs, visual_order) = if let Some(levels) = levels {
+            let mirrored_chars: Vec<char> =
+                chars.iter().zip(levels).map(|(&c, level)| if level % 2 == 1 { bidi::mirror(c) } else { c }).collect();
+            let shaped_glyphs = read_lock.shape(text_style, &mirrored_chars);
+            let glyph_levels: Vec<u8> = shaped_glyphs.iter().map(|(_, shaped_glyph)| levels[shaped_glyph.cluster]).collect();
+            (shaped_glyphs, Some(bidi::visual_order(&glyph_levels)))
+        } else {
+            (read_lock.shape(text_style, chars), None)
+        };
 
-        let font_id = text_style.font.font_id;
+        // Atlas pages per font in the `text_style.font_chain()`, which we create lazily since fallback fonts
+        // are typically only used for a few characters.
+        let mut atlas_page_ids: Vec<(usize, usize)> = Vec::new();
 
-        let (atlas_page_id, mut read_lock) = get_font_atlas_page_id(fonts_data, font_id, dpi_factor, text_style.font_size);
+        let mut x = pos.x;
 
-        let font_size_logical =
-            text_style.font_size * 96.0 / (72.0 * read_lock.fonts[font_id].font_loaded.as_ref().unwrap().units_per_em);
-        let font_size_pixels = font_size_logical * dpi_factor;
+        for visual_index in 0..shaped_glyphs.len() {
+            let index = visual_order.as_ref().map_or(visual_index, |visual_order| visual_order[visual_index]);
+            let (font_id, shaped_glyph) = shaped_glyphs[index];
+            let glyph_id = shaped_glyph.glyph_id;
 
-        let mut x = pos.x;
-        let mut char_offset = char_offset;
+            let atlas_page_id = if let Some((_, atlas_page_id)) = atlas_page_ids.iter().find(|(id, _)| *id == font_id) {
+                *atlas_page_id
+            } else {
+                drop(read_lock);
+                let (atlas_page_id, new_read_lock) =
+                    get_font_atlas_page_id(fonts_data, font_id, dpi_factor, text_style.font_size);
+                read_lock = new_read_lock;
+                atlas_page_ids.push((font_id, atlas_page_id));
+                atlas_page_id
+            };
+
+            let font_size_logical = read_lock.font_size_logical(font_id, text_style.font_size);
+            let font_size_pixels = font_size_logical * dpi_factor;
 
-        for wc in chars {
-            let unicode = *wc.borrow() as usize;
+            let advance = shaped_glyph.x_advance * font_size_logical * font_scale;
+            let glyph_x = x + shaped_glyph.x_offset * font_size_logical * font_scale;
 
             // Scope the `cxfont` borrow to these variables.
-            let (glyph_id, advance, w, h, min_pos_x, subpixel_x_fract, subpixel_y_fract, scaled_min_pos_x, scaled_min_pos_y) = {
+            let (w, h, min_pos_x, subpixel_x_fract, subpixel_y_fract, scaled_min_pos_x, scaled_min_pos_y) = {
                 let cxfont = read_lock.fonts[font_id].font_loaded.as_ref().unwrap();
-                let glyph_id = cxfont.char_code_to_glyph_index_map[unicode];
                 if glyph_id >= cxfont.glyphs.len() {
-                    println!("GLYPHID OUT OF BOUNDS {} {} len is {}", unicode, glyph_id, cxfont.glyphs.len());
+                    println!(
+                        "GLYPHID OUT OF BOUNDS {} {} len is {}",
+                        chars[shaped_glyph.cluster] as usize,
+                        glyph_id,
+                        cxfont.glyphs.len()
+                    );
                     continue;
                 }
 
                 let glyph = &cxfont.glyphs[glyph_id];
 
-                let advance = glyph.horizontal_metrics.advance_width * font_size_logical * font_scale;
-
                 // snap width/height to pixel granularity
                 let w = ((glyph.bounds.p_max.x - glyph.bounds.p_min.x) * font_size_pixels).ceil() + 1.0;
                 let h = ((glyph.bounds.p_max.y - glyph.bounds.p_min.y) * font_size_pixels).ceil() + 1.0;
 
                 // this one needs pixel snapping
-                let min_pos_x = x + font_size_logical * glyph.bounds.p_min.x;
+                let min_pos_x = glyph_x + font_size_logical * glyph.bounds.p_min.x;
                 let min_pos_y = pos.y - font_size_logical * glyph.bounds.p_min.y + text_style.font_size * text_style.top_drop;
 
                 // compute subpixel shift
@@ -303,12 +384,12 @@ impl TextIns {
                 let subpixel_y_fract = min_pos_y - (min_pos_y * dpi_factor).floor() / dpi_factor;
 
                 // scale and snap it
-                let scaled_min_pos_x = x + font_size_logical * font_scale * glyph.bounds.p_min.x - subpixel_x_fract;
+                let scaled_min_pos_x = glyph_x + font_size_logical * font_scale * glyph.bounds.p_min.x - subpixel_x_fract;
                 let scaled_min_pos_y = pos.y - font_size_logical * font_scale * glyph.bounds.p_min.y
                     + text_style.font_size * font_scale * text_style.top_drop
                     - subpixel_y_fract;
 
-                (glyph_id, advance, w, h, min_pos_x, subpixel_x_fract, subpixel_y_fract, scaled_min_pos_x, scaled_min_pos_y)
+                (w, h, min_pos_x, subpixel_x_fract, subpixel_y_fract, scaled_min_pos_x, scaled_min_pos_y)
             };
 
             // only use a subpixel id for small fonts
@@ -321,28 +402,18 @@ impl TextIns {
             };
 
             let tc = if let Some(tc) = read_lock.fonts[font_id].atlas_pages[atlas_page_id].atlas_glyphs[glyph_id][subpixel_id] {
+                read_lock.fonts_atlas.mark_used(&tc);
                 tc
             } else {
                 // Drop `read_lock` to do some writes, and then reacquire it.
                 drop(read_lock);
-                {
-                    let mut write_fonts_data = fonts_data.write().unwrap();
-
-                    write_fonts_data.fonts_atlas.atlas_todo.push(CxFontsAtlasTodo {
-                        subpixel_x_fract,
-                        subpixel_y_fract,
-                        font_id,
-                        atlas_page_id,
-                        glyph_id,
-                        subpixel_id,
-                    });
-
-                    let new_glyph = write_fonts_data.fonts_atlas.alloc_atlas_glyph(w, h);
-                    write_fonts_data.fonts[font_id].atlas_pages[atlas_page_id].atlas_glyphs[glyph_id][subpixel_id] =
-                        Some(new_glyph);
-                }
+                let tc = fonts_data.write().unwrap().alloc_atlas_glyph(
+                    w,
+                    h,
+                    CxFontsAtlasTodo { subpixel_x_fract, subpixel_y_fract, font_id, atlas_page_id, glyph_id, subpixel_id },
+                );
                 read_lock = fonts_data.read().unwrap();
-                read_lock.fonts[font_id].atlas_pages[atlas_page_id].atlas_glyphs[glyph_id][subpixel_id].unwrap()
+                tc
             };
 
             ret.push(TextIns {
@@ -354,19 +425,59 @@ impl TextIns {
                 char_depth: draw_depth + 0.00001 * min_pos_x,
                 base: vec2(x, pos.y),
                 font_size: text_style.font_size,
-                char_offset: char_offset as f32,
+                char_offset: (char_offset + shaped_glyph.cluster) as f32,
 
                 // give the callback a chance to do things
-                marker: char_callback(*wc.borrow(), char_offset, x, advance),
+                marker: Self::call_char_callback(chars, &shaped_glyphs, index, char_offset, x, advance, &mut char_callback),
             });
 
             x += advance;
-            char_offset += 1;
         }
 
         ret
     }
 
+    /// Whether `chars` contain right-to-left text, which [`TextIns::generate_2d_glyphs`] reorders.
+    pub fn has_rtl(chars: &[char]) -> bool {
+        bidi::has_rtl(chars)
+    }
+
+    /// Calls `char_callback` for every character in the cluster of `shaped_glyphs[index]`, dividing the advance
+    /// of the glyph among them, and returns the result for the first character. If the glyph is not the first
+    /// glyph of its cluster, the callback is not called and 0 is returned.
+    fn call_char_callback<F>(
+        chars: &[char],
+        shaped_glyphs: &[(usize, ShapedGlyph)],
+        index: usize,
+        char_offset: usize,
+        x: f32,
+        advance: f32,
+        char_callback: &mut F,
+    ) -> f32
+    where
+        F: FnMut(char, usize, f32, f32) -> f32,
+    {
+        let cluster = shaped_glyphs[index].1.cluster;
+        if index > 0 && shaped_glyphs[index - 1].1.cluster == cluster {
+            return 0.0;
+        }
+        let cluster_end = shaped_glyphs[index + 1..]
+            .iter()
+            .map(|(_, shaped_glyph)| shaped_glyph.cluster)
+            .find(|&next_cluster| next_cluster > cluster)
+            .unwrap_or(chars.len());
+        let char_advance = advance / (cluster_end - cluster) as f32;
+        let mut marker = 0.0;
+        for (i, char_index) in (cluster..cluster_end).enumerate() {
+            let char_marker =
+                char_callback(chars[char_index], char_offset + char_index, x + char_advance * i as f32, char_advance);
+            if i == 0 {
+                marker = char_marker;
+            }
+        }
+        marker
+    }
+
     pub fn set_color(cx: &mut Cx, area: Area, color: Vec4) {
         let glyphs = area.get_slice_mut::<TextIns>(cx);
         for glyph in glyphs {
@@ -422,41 +533,44 @@ impl TextIns {
         area
     }
 
+    /// Measures the width of `text` when drawn using [`TextIns::draw_str`] (without [`Wrapping::Ellipsis`]).
+    pub fn measure_str_width(cx: &Cx, text: &str, props: &TextInsProps) -> f32 {
+        Self::measure_width(cx, &text.chars().collect::<Vec<char>>(), props)
+    }
+
     /// Measures the width of the text, not including newlines.
     fn measure_width(cx: &Cx, chars: &[char], props: &TextInsProps) -> f32 {
-        let text_style = &props.text_style;
-        let font_id = text_style.font.font_id;
-        let read_fonts = &cx.fonts_data.read().unwrap().fonts;
-        let font_size_logical =
-            text_style.font_size * 96.0 / (72.0 * read_fonts[font_id].font_loaded.as_ref().unwrap().units_per_em);
+        Self::measure_width_with_fonts_data(&cx.fonts_data.read().unwrap(), chars, props)
+    }
 
-        let mut width = 0.0;
-        for &c in chars {
-            if c < '\u{10000}' && c != '\n' {
-                let slot = read_fonts[font_id].font_loaded.as_ref().unwrap().char_code_to_glyph_index_map[c as usize];
-                let glyph = &read_fonts[font_id].font_loaded.as_ref().unwrap().glyphs[slot];
-                let glyph_width = glyph.horizontal_metrics.advance_width * font_size_logical * props.font_scale;
-                width += glyph_width;
-            }
-        }
-        width
+    fn measure_width_with_fonts_data(fonts_data: &CxFontsData, chars: &[char], props: &TextInsProps) -> f32 {
+        fonts_data
+            .shape(&props.text_style, chars)
+            .iter()
+            .filter(|(_, shaped_glyph)| chars[shaped_glyph.cluster] != '\n')
+            .map(|(font_id, shaped_glyph)| {
+                shaped_glyph.x_advance * fonts_data.font_size_logical(*font_id, props.text_style.font_size) * props.font_scale
+            })
+            .sum()
+    }
+
+    /// The advance width of a single character, taking [`TextStyle::fallback_fonts`] into account, but without
+    /// any shaping.
+    fn glyph_width(fonts_data: &CxFontsData, c: char, props: &TextInsProps) -> f32 {
+        let (font_id, glyph_id) = fonts_data.resolve_glyph(&props.text_style, c);
+        let glyph = &fonts_data.fonts[font_id].font_loaded.as_ref().unwrap().glyphs[glyph_id];
+        glyph.horizontal_metrics.advance_width
+            * fonts_data.font_size_logical(font_id, props.text_style.font_size)
+            * props.font_scale
     }
 
     /// Adds an ellipsis to the end of the text if it is too long, or nothing at all
     /// if even the ellipsis is too long.
     fn truncate_to_ellipsis(cx: &Cx, text: &str, props: &TextInsProps, max_width: f32) -> TextChunk {
-        let text_style = &props.text_style;
-        let font_id = text_style.font.font_id;
-        let read_fonts = &cx.fonts_data.read().unwrap().fonts;
-        let font_size_logical =
-            text_style.font_size * 96.0 / (72.0 * read_fonts[font_id].font_loaded.as_ref().unwrap().units_per_em);
+        let read_fonts_data = cx.fonts_data.read().unwrap();
 
         // Actual ellipsis char "…" is not supported in our current fonts.
-        let ellipsis_slot = read_fonts[font_id].font_loaded.as_ref().unwrap().char_code_to_glyph_index_map['.' as usize];
-        let ellipsis_width = 3.0
-            * read_fonts[font_id].font_loaded.as_ref().unwrap().glyphs[ellipsis_slot].horizontal_metrics.advance_width
-            * font_size_logical
-            * props.font_scale;
+        let ellipsis_width = 3.0 * Self::glyph_width(&read_fonts_data, '.', props);
 
         let mut iter = text.chars().peekable();
         let mut width = 0.0;
@@ -465,27 +579,25 @@ impl TextIns {
         while let Some(c) = iter.next() {
             let last = iter.peek().is_none();
 
-            if c < '\u{10000}' {
-                let slot = read_fonts[font_id].font_loaded.as_ref().unwrap().char_code_to_glyph_index_map[c as usize];
-                let glyph = &read_fonts[font_id].font_loaded.as_ref().unwrap().glyphs[slot];
-                let glyph_width = glyph.horizontal_metrics.advance_width * font_size_logical * props.font_scale;
-                // Put in an ellipsis if we'd otherwise overflow, but DON'T put an ellipsis if we're at the end
-                // already and the current glyph is less wide than the ellipsis itself.
-                if width + glyph_width >= max_width - ellipsis_width && !(last && glyph_width <= ellipsis_width) {
-                    // If there's no room for the ellipsis, return, but don't actually print it.
-                    if width + ellipsis_width <= max_width {
-                        // Actual ellipsis char "…" is not supported in our current fonts.
-                        chars.push('.');
-                        chars.push('.');
-                        chars.push('.');
-                        width += ellipsis_width;
-                    }
-                    return TextChunk { chars, width, newline: false };
+            let glyph_width = Self::glyph_width(&read_fonts_data, c, props);
+            // Put in an ellipsis if we'd otherwise overflow, but DON'T put an ellipsis if we're at the end
+            // already and the current glyph is less wide than the ellipsis itself.
+            if width + glyph_width >= max_width - ellipsis_width && !(last && glyph_width <= ellipsis_width) {
+                // If there's no room for the ellipsis, return, but don't actually print it.
+                if width + ellipsis_width <= max_width {
+                    // Actual ellipsis char "…" is not supported in our current fonts.
+                    chars.push('.');
+                    chars.push('.');
+                    chars.push('.');
                 }
-                chars.push(c);
-                width += glyph_width;
+                // Measure again, since shaping (e.g. kerning) can make this slightly different from `width`.
+                let width = Self::measure_width_with_fonts_data(&read_fonts_data, &chars, props);
+                return TextChunk { chars, width, newline: false };
             }
+            chars.push(c);
+            width += glyph_width;
         }
+        let width = Self::measure_width_with_fonts_data(&read_fonts_data, &chars, props);
         TextChunk { chars, width, newline: false }
     }
 
@@ -556,6 +668,8 @@ impl TextIns {
     ///
     /// Calls `cx.add_box` for each "chunk" of text (depending on [`TextInsProps::wrapping`]).
     ///
+    /// Bidi levels are computed per paragraph, and right-to-left text is reordered per (wrapped) line.
+    ///
     /// TODO(JP): This doesn't seem to work well with [`Direction::Down`] (or other directions for
     /// that matter). Not a high priority but might good to be aware of.
     ///
@@ -566,39 +680,71 @@ impl TextIns {
         let line_spacing = text_style.line_spacing;
         let height_factor = text_style.height_factor;
 
-        let mut glyphs: Vec<TextIns> = Vec::with_capacity(text.len());
-
         cx.begin_row(Width::Compute, Height::Compute);
         cx.begin_padding_box(props.padding);
         cx.begin_wrapping_box();
 
+        // Lay out all chunks first, since we only know which chunks end up on the same line afterwards.
+        let mut chunks = Vec::new();
         for chunk in Self::apply_wrapping(cx, text, props) {
             let height = font_size * height_factor * props.font_scale;
             let rect = cx.add_box(LayoutSize { width: Width::Fix(chunk.width), height: Height::Fix(height) });
+            if chunk.newline {
+                cx.draw_new_line_min_height(font_size * line_spacing * props.font_scale);
+            }
+            chunks.push((chunk, rect));
+        }
 
-            if !rect.pos.x.is_nan() && !rect.pos.y.is_nan() {
-                glyphs.extend(Self::generate_2d_glyphs(
+        cx.end_wrapping_box();
+        cx.end_padding_box();
+        cx.end_row();
+
+        let chars: Vec<char> = chunks.iter().flat_map(|(chunk, _)| chunk.chars.iter().copied()).collect();
+        let levels = if bidi::has_rtl(&chars) { Some(bidi::levels(&chars)) } else { None };
+
+        let mut glyphs: Vec<TextIns> = Vec::with_capacity(chars.len());
+        let mut chunk_start = 0;
+        let mut line: Option<(Vec2, std::ops::Range<usize>)> = None;
+        let flush_line = |line: &mut Option<(Vec2, std::ops::Range<usize>)>, glyphs: &mut Vec<TextIns>| {
+            if let Some((pos, range)) = line.take() {
+                glyphs.extend(Self::generate_2d_glyphs_with_levels(
                     &props.text_style,
                     &cx.fonts_data,
                     cx.current_dpi_factor,
                     props.font_scale,
                     props.draw_depth,
                     props.color,
-                    rect.pos,
+                    pos,
                     0,
-                    chunk.chars,
+                    &chars[range.clone()],
+                    levels.as_ref().map(|levels| &levels[range]),
                     |_, _, _, _| 0.0,
                 ));
             }
-
+        };
+        for (chunk, rect) in &chunks {
+            let range = chunk_start..chunk_start + chunk.chars.len();
+            chunk_start = range.end;
+            if rect.pos.x.is_nan() || rect.pos.y.is_nan() {
+                flush_line(&mut line, &mut glyphs);
+                continue;
+            }
+            match &mut line {
+                // Without right-to-left text we can draw every chunk separately, and there's no need to join them.
+                #[allow(clippy::float_cmp)]
+                Some((pos, line_range)) if levels.is_some() && pos.y == rect.pos.y && line_range.end == range.start => {
+                    line_range.end = range.end;
+                }
+                _ => {
+                    flush_line(&mut line, &mut glyphs);
+                    line = Some((rect.pos, range));
+                }
+            }
             if chunk.newline {
-                cx.draw_new_line_min_height(font_size * line_spacing * props.font_scale);
+                flush_line(&mut line, &mut glyphs);
             }
         }
-
-        cx.end_wrapping_box();
-        cx.end_padding_box();
-        cx.end_row();
+        flush_line(&mut line, &mut glyphs);
 
         Self::draw_glyphs(
             cx,
@@ -623,30 +769,59 @@ impl TextIns {
         let spos = Vec2 { x: pos.x + scroll_pos.x, y: pos.y + scroll_pos.y };
 
         let glyphs = area.get_slice::<TextIns>(cx);
+        let carets = Self::carets(glyphs);
         let mut i = 0;
-        let len = glyphs.len();
+        let len = carets.len();
         while i < len {
-            let glyph = &glyphs[i];
-            if glyph.base.y + glyph.font_size * line_spacing > spos.y {
+            let caret = &carets[i];
+            if caret.base.y + caret.font_size * line_spacing > spos.y {
                 // Find a matching character within this line.
                 while i < len {
-                    let glyph = &glyphs[i];
-                    let width = glyph.rect_size.x;
-                    if glyph.base.x > spos.x + width * 0.5 || glyph.base.y > spos.y {
-                        let prev_glyph = &glyphs[if i == 0 { 0 } else { i - 1 }];
-                        let prev_width = prev_glyph.rect_size.x;
-                        if i < len - 1 && prev_glyph.base.x > spos.x + prev_width {
+                    let caret = &carets[i];
+                    let width = caret.width;
+                    if caret.base.x > spos.x + width * 0.5 || caret.base.y > spos.y {
+                        let prev_caret = &carets[if i == 0 { 0 } else { i - 1 }];
+                        let prev_width = prev_caret.width;
+                        if i < len - 1 && prev_caret.base.x > spos.x + prev_width {
                             // fix newline jump-back
-                            return Some(glyph.char_offset as usize);
+                            return Some(caret.char_offset);
                         }
-                        return Some(prev_glyph.char_offset as usize);
+                        return Some(prev_caret.char_offset);
                     }
                     i += 1;
                 }
             }
             i += 1;
         }
-        Some(glyphs[len - 1].char_offset as usize)
+        Some(carets[len - 1].char_offset)
+    }
+
+    /// Get a [`Caret`] for every character in `glyphs`, by splitting up glyphs that represent multiple
+    /// characters (e.g. ligatures) into equal parts.
+    fn carets(glyphs: &[TextIns]) -> Vec<Caret> {
+        let mut carets = Vec::with_capacity(glyphs.len());
+        for (index, glyph) in glyphs.iter().enumerate() {
+            // Only split if the next glyph directly follows this one, and not e.g. when characters were skipped
+            // because they were out of view.
+            let char_count = match glyphs.get(index + 1) {
+                Some(next_glyph)
+                    if next_glyph.base.y == glyph.base.y
+                        && next_glyph.char_offset > glyph.char_offset
+                        && next_glyph.base.x <= glyph.base.x + glyph.rect_size.x =>
+                {
+                    (next_glyph.char_offset - glyph.char_offset) as usize
+                }
+                _ => 1,
+            };
+            let width = glyph.rect_size.x / char_count as f32;
+            carets.extend((0..char_count).map(|i| Caret {
+                base: vec2(glyph.base.x + width * i as f32, glyph.base.y),
+                width,
+                font_size: glyph.font_size,
+                char_offset: glyph.char_offset as usize + i,
+            }));
+        }
+        carets
     }
 
     pub fn get_monospace_base(cx: &Cx, text_style: &TextStyle) -> Vec2 {
@@ -679,6 +854,111 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_fallback_fonts() {
+        let cx = Cx::new_test();
+        // The arrow is in Liberation Mono but not in Ubuntu.
+        let arrow_width =
+            TextIns::measure_width(&cx, &['→'], &TextInsProps { text_style: TEXT_STYLE_MONO, ..TextInsProps::default() });
+        assert!(arrow_width > 0.0);
+
+        let text_style = TEXT_STYLE_NORMAL.with_fallback_fonts(&[TEXT_STYLE_MONO.font]);
+        assert_eq!(cx.fonts_data.read().unwrap().resolve_glyph(&TEXT_STYLE_NORMAL, '→'), (TEXT_STYLE_NORMAL.font.font_id, 0));
+        assert_eq!(cx.fonts_data.read().unwrap().resolve_glyph(&text_style, '→').0, TEXT_STYLE_MONO.font.font_id);
+        assert_eq!(cx.fonts_data.read().unwrap().resolve_glyph(&text_style, 'a').0, TEXT_STYLE_NORMAL.font.font_id);
+        assert_eq!(TextIns::measure_width(&cx, &['→'], &TextInsProps { text_style, ..TextInsProps::default() }), arrow_width);
+    }
+
+    #[test]
+    fn test_load_font() {
+        let mut cx = Cx::new_test();
+        let font = cx.load_font(include_bytes!("../resources/LiberationMono-Regular.ttf")).unwrap();
+        assert!(font.font_id >= 2);
+        let text_style = TextStyle { font, ..TEXT_STYLE_MONO };
+        assert_eq!(
+            TextIns::measure_width(&cx, &['a', 'b'], &TextInsProps { text_style, ..TextInsProps::default() }),
+            TextIns::measure_width(&cx, &['a', 'b'], &TextInsProps { text_style: TEXT_STYLE_MONO, ..TextInsProps::default() })
+        );
+        assert!(cx.load_font(b"not a font").is_err());
+    }
+
+    #[test]
+    fn test_shaping() {
+        let cx = Cx::new_test();
+        let props = TextInsProps { text_style: TEXT_STYLE_NORMAL, ..TextInsProps::default() };
+        let unshaped_width = {
+            let fonts_data = cx.fonts_data.read().unwrap();
+            TextIns::glyph_width(&fonts_data, 'A', &props) + TextIns::glyph_width(&fonts_data, 'V', &props)
+        };
+        assert!(TextIns::measure_width(&cx, &['A', 'V'], &props) < unshaped_width);
+
+        let glyphs = TextIns::generate_2d_glyphs(
+            &props.text_style,
+            &cx.fonts_data,
+            1.0,
+            1.0,
+            0.0,
+            COLOR_WHITE,
+            vec2(0., 0.),
+            10,
+            "fit".chars(),
+            |_, _, _, _| 0.0,
+        );
+        // "fi" is a ligature.
+        assert_eq!(glyphs.iter().map(|glyph| glyph.char_offset).collect::<Vec<_>>(), vec![10., 12.]);
+        assert_eq!(TextIns::carets(&glyphs).iter().map(|caret| caret.char_offset).collect::<Vec<_>>(), vec![10, 11, 12]);
+    }
+
+    #[test]
+    fn test_bidi_reordering() {
+        let cx = Cx::new_test();
+        // "a", then Hebrew "אב" with a bracket, then "b".
+        let glyphs = TextIns::generate_2d_glyphs(
+            &TEXT_STYLE_NORMAL,
+            &cx.fonts_data,
+            1.0,
+            1.0,
+            0.0,
+            COLOR_WHITE,
+            vec2(0., 0.),
+            0,
+            "a \u{05D0}(\u{05D1} b".chars(),
+            |_, _, _, _| 0.0,
+        );
+        assert_eq!(glyphs.iter().map(|glyph| glyph.char_offset).collect::<Vec<_>>(), vec![0., 1., 4., 3., 2., 5., 6.]);
+        assert!(glyphs.windows(2).all(|pair| pair[0].base.x < pair[1].base.x));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_bidi_reordering_across_words() {
+        let mut cx = Cx::new_test();
+        let mut cx_after_draw = CxAfterDraw::new(&mut cx);
+        let mut window = Window { create_inner_size: Some(vec2(200., 50.)), ..Window::default() };
+        let mut pass = Pass::default();
+        let mut view = View::default();
+        let mut char_offsets = Vec::new();
+        let mut event_handler = |cx: &mut Cx, event: &mut Event| {
+            if let Event::System(SystemEvent::Draw) = event {
+                window.begin_window(cx);
+                pass.begin_pass(cx, COLOR_BLACK);
+                view.begin_view(cx, LayoutSize::FILL);
+                // Two Hebrew words, which are separate chunks but get reordered as one line.
+                let props = TextInsProps { text_style: TEXT_STYLE_NORMAL, wrapping: Wrapping::Word, ..TextInsProps::default() };
+                let area = TextIns::draw_walk(cx, "\u{05D0}\u{05D1} \u{05D2}\u{05D3}", &props);
+                let glyphs = area.get_slice::<TextIns>(cx);
+                char_offsets = glyphs.iter().map(|glyph| glyph.char_offset).collect();
+                assert!(glyphs.windows(2).all(|pair| pair[0].base.x < pair[1].base.x));
+                view.end_view(cx);
+                pass.end_pass(cx);
+                window.end_window(cx);
+                cx_after_draw.after_draw(cx);
+            }
+        };
+        cx.headless_start(&mut event_handler);
+        assert_eq!(char_offsets, vec![4., 3., 2., 1., 0.]);
+    }
+
     #[test]
     fn test_apply_wrapping() {
         let cx = Cx::new_test();
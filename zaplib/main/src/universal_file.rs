@@ -5,6 +5,20 @@ extern "C" {
     /// Synchronously read data from a URL, returning a new buffer. Return value is 0 or 1 depending
     /// on whether the data was successfully read.
     fn readUrlSync(url_ptr: usize, url_len: usize, buf_ptr_out: *mut u32, buf_len_out: *mut u32) -> u32;
+    /// Synchronously read the bytes from `start` up to `end` from a URL using an HTTP Range Request, returning
+    /// a new buffer. Returns 0 on error, 1 if the server ignored the range and sent the entire file, 2 if the
+    /// server sent just the range (or nothing, for an empty file) and `size_out` got set to the size of the
+    /// entire file, and 3 if the server sent just the range but we couldn't find out the size (e.g. because
+    /// `Content-Range` isn't exposed through CORS).
+    fn readUrlRangeSync(
+        url_ptr: usize,
+        url_len: usize,
+        start: u64,
+        end: u64,
+        buf_ptr_out: *mut u32,
+        buf_len_out: *mut u32,
+        size_out: *mut u64,
+    ) -> u32;
 }
 
 enum UniversalFileInner {
@@ -21,6 +35,95 @@ enum UniversalFileInner {
     /// An actual file handle in JavaScript, e.g. from dragging in a file.
     #[cfg(any(doc, target_arch = "wasm32"))]
     WasmFile { id: usize, size: u64, pos: u64 },
+
+    /// A URL that we read in blocks using HTTP Range Requests. The cache is shared between clones.
+    RangeRequests { cache: std::sync::Arc<std::sync::Mutex<RangeCache>>, size: u64, pos: u64 },
+}
+
+/// Options for [`UniversalFile::open_url_with_range_requests`].
+#[derive(Clone, Copy, Debug)]
+pub struct RangeRequestOptions {
+    /// How many bytes to request at a time.
+    pub block_size: u64,
+    /// How many blocks to keep in memory. When we need a new block, the least recently used one is evicted.
+    pub max_cached_blocks: usize,
+}
+
+impl Default for RangeRequestOptions {
+    fn default() -> Self {
+        Self { block_size: 1024 * 1024, max_cached_blocks: 100 }
+    }
+}
+
+/// What we get back when requesting a range of bytes from a URL.
+enum RangeResponse {
+    /// The server honored the Range header. `size` is the size of the entire file, if the server told us.
+    Partial { data: Vec<u8>, size: Option<u64> },
+    /// The server ignored the Range header, and sent the entire file.
+    Full(Vec<u8>),
+}
+
+/// Blocks of [`RangeRequestOptions::block_size`] bytes that we fetched for
+/// [`UniversalFileInner::RangeRequests`], evicting the least recently used block when we have too many.
+///
+/// Similar to webviz's `CachedFilelike`, but a lot simpler, since we can just block while fetching.
+struct RangeCache {
+    url: String,
+    size: u64,
+    options: RangeRequestOptions,
+    /// Maps block indices to their data and the value of `clock` when they were last used.
+    blocks: std::collections::HashMap<u64, (Vec<u8>, u64)>,
+    /// Gets incremented on every read, so we know which block was used least recently.
+    clock: u64,
+}
+
+impl RangeCache {
+    /// The number of bytes that the block at `index` should have.
+    fn block_len(&self, index: u64) -> u64 {
+        let start = index * self.options.block_size;
+        std::cmp::min(start + self.options.block_size, self.size) - start
+    }
+
+    fn insert_block(&mut self, index: u64, data: Vec<u8>) -> std::io::Result<()> {
+        if data.len() as u64 != self.block_len(index) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("Got {} bytes instead of {} for block {} of {}", data.len(), self.block_len(index), index, self.url),
+            ));
+        }
+        if self.blocks.len() >= self.options.max_cached_blocks {
+            if let Some(&lru_index) = self.blocks.iter().min_by_key(|(_, (_, last_used))| *last_used).map(|(index, _)| index) {
+                self.blocks.remove(&lru_index);
+            }
+        }
+        self.blocks.insert(index, (data, self.clock));
+        Ok(())
+    }
+
+    /// Read from the block that contains `pos`, fetching it if necessary.
+    fn read_at(&mut self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        if pos >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        self.clock += 1;
+        let index = pos / self.options.block_size;
+        if !self.blocks.contains_key(&index) {
+            let start = index * self.options.block_size;
+            let end = start + self.block_len(index);
+            let data = match fetch_url_range(&self.url, start, end)? {
+                RangeResponse::Partial { data, size: _ } => data,
+                // The server stopped honoring Range headers, so just pick out what we need.
+                RangeResponse::Full(data) => data.get(start as usize..end as usize).unwrap_or_default().to_vec(),
+            };
+            self.insert_block(index, data)?;
+        }
+        let (block, last_used) = self.blocks.get_mut(&index).unwrap();
+        *last_used = self.clock;
+        let offset = (pos - index * self.options.block_size) as usize;
+        let len = std::cmp::min(buf.len(), block.len() - offset);
+        buf[..len].copy_from_slice(&block[offset..offset + len]);
+        Ok(len)
+    }
 }
 
 /// A file handle that abstracts over the different ways we have to deal with different kinds of
@@ -30,18 +133,17 @@ enum UniversalFileInner {
 /// * For the WebAssembly target, opening a file will read the entire file in memory synchronously,
 ///   over HTTP(S). This is quite different from the native behavior. This is required if we never
 ///   want [`std::io::Seek::seek`] to fail.
-///   TODO(JP): We should consider different behaviors here -- potentially configurable by the user --
-///   such as completely disallowing seeking, or seeking into a buffer with a certain size, or even using
-///   HTTP Range Requests when available to fetch the data starting at a seek point (when not buffered
-///   already). This can somewhat mirror [`std::fs::OpenOptions`]. We can take some inspiration from
-///   <https://github.com/cruise-automation/webviz/blob/4dcd47d/packages/webviz-core/src/util/CachedFilelike.js>
-///   and <https://github.com/cruise-automation/webviz/blob/4dcd47d/packages/webviz-core/src/dataProviders/BrowserHttpReader.js>.
-///   Even just an option to defer loading until the first read would be useful, so you can open a file
-///   on the main thread and pass it to another thread for processing (without having to create multiple
-///   functions for processing e.g. handles from [`crate::AppOpenFilesEvent`] differently).
+///   See [`UniversalFile::open_url_with_range_requests`] for an alternative for big files.
+///   TODO(JP): We should consider more behaviors here, such as completely disallowing seeking. This can
+///   somewhat mirror [`std::fs::OpenOptions`]. Even just an option to defer loading until the first read
+///   would be useful, so you can open a file on the main thread and pass it to another thread for processing
+///   (without having to create multiple functions for processing e.g. handles from
+///   [`crate::AppOpenFilesEvent`] differently).
 /// * [`UniversalFile::open_url`] exists, which is not available in the regular [`std::fs::File`] API. This matches
 ///   the behavior of the WebAssembly URL loading described above, but works on both WebAssembly and native
 ///   targets.
+/// * [`UniversalFile::open_url_with_range_requests`] instead fetches blocks on demand using HTTP Range Requests,
+///   which lets you seek around in files that are too big to load in memory.
 /// * You can use [`std::clone::Clone::clone`] to get a truly new handle, e.g. with its own [`std::io::Seek`]
 ///   state. Also note that it's not a `try_clone` -- it will always succeed. This means that if you
 ///   clone a handle to a file that doesn't exist anymore, then you'll get that error on the next
@@ -121,6 +223,40 @@ impl UniversalFile {
         }
     }
 
+    /// Open an absolute URL, and only fetch the parts that are actually read, in blocks of
+    /// [`RangeRequestOptions::block_size`] bytes, using HTTP Range Requests. Blocks are kept in a cache that is
+    /// shared between clones of the returned handle. A read waits until the blocks it needs are loaded, so you typically
+    /// want to use this in a thread.
+    ///
+    /// If the server doesn't support Range Requests (or doesn't tell us the size of the file), this falls back to
+    /// loading the entire file, like [`UniversalFile::open_url`].
+    pub fn open_url_with_range_requests(url: &str, options: RangeRequestOptions) -> std::io::Result<Self> {
+        if !is_absolute_url(url) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("'url' is not an absolute URL, use 'open' instead: {}", url),
+            ));
+        }
+        assert!(options.block_size > 0, "block_size must be greater than 0");
+
+        match fetch_url_range(url, 0, options.block_size)? {
+            RangeResponse::Partial { data, size: Some(size) } => {
+                let mut cache =
+                    RangeCache { url: url.to_string(), size, options, blocks: std::collections::HashMap::new(), clock: 0 };
+                if size > 0 {
+                    cache.insert_block(0, data)?;
+                }
+                Ok(Self(UniversalFileInner::RangeRequests {
+                    cache: std::sync::Arc::new(std::sync::Mutex::new(cache)),
+                    size,
+                    pos: 0,
+                }))
+            }
+            RangeResponse::Partial { data: _, size: None } => Self::open_url(url),
+            RangeResponse::Full(data) => Ok(Self(UniversalFileInner::FullyLoaded { data: std::sync::Arc::new(data), pos: 0 })),
+        }
+    }
+
     /// Create a new [`UniversalFile`] from a JS file handle.
     #[cfg(target_arch = "wasm32")]
     pub(crate) fn from_wasm_file(id: usize, size: u64) -> Self {
@@ -162,6 +298,59 @@ impl UniversalFile {
     }
 }
 
+/// Synchronously request the bytes from `start` up to `end` from a URL on Wasm targets.
+#[cfg(target_arch = "wasm32")]
+fn fetch_url_range(url: &str, start: u64, end: u64) -> std::io::Result<RangeResponse> {
+    let chars = url.chars().collect::<Vec<char>>();
+    unsafe {
+        let mut buf_ptr_out: u32 = 0;
+        let mut buf_len_out: u32 = 0;
+        let mut size_out: u64 = 0;
+        let result = readUrlRangeSync(
+            chars.as_ptr() as usize,
+            chars.len() as usize,
+            start,
+            end,
+            &mut buf_ptr_out,
+            &mut buf_len_out,
+            &mut size_out,
+        );
+        if result == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("Error while loading {}; check the browser console for details", url),
+            ));
+        }
+        let data = Vec::<u8>::from_raw_parts(buf_ptr_out as *mut u8, buf_len_out as usize, buf_len_out as usize);
+        Ok(match result {
+            1 => RangeResponse::Full(data),
+            2 => RangeResponse::Partial { data, size: Some(size_out) },
+            _ => RangeResponse::Partial { data, size: None },
+        })
+    }
+}
+
+/// Synchronously request the bytes from `start` up to `end` from a URL on native targets.
+#[cfg(not(target_arch = "wasm32"))]
+fn fetch_url_range(url: &str, start: u64, end: u64) -> std::io::Result<RangeResponse> {
+    let response = match ureq::get(url).set("range", &format!("bytes={}-{}", start, end - 1)).call() {
+        Ok(response) => response,
+        // No range can be satisfied for an empty file, which servers signal with "Content-Range: bytes */0".
+        Err(ureq::Error::Status(416, response)) if response.header("content-range") == Some("bytes */0") => {
+            return Ok(RangeResponse::Partial { data: vec![], size: Some(0) });
+        }
+        Err(err) => {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("Error while loading {}: {}", url, err)));
+        }
+    };
+    let partial = response.status() == 206;
+    // E.g. "bytes 0-1023/146515"; the size can also be "*" if the server doesn't know it.
+    let size = response.header("content-range").and_then(|range| range.rsplit('/').next()?.parse().ok());
+    let mut data = vec![];
+    std::io::Read::read_to_end(&mut response.into_reader(), &mut data)?;
+    Ok(if partial { RangeResponse::Partial { data, size } } else { RangeResponse::Full(data) })
+}
+
 /// Convenience function to load a local file path into a [`String`].
 ///
 /// Might be faster than manually using [`std::io::Read::read_to_string`] if we can preallocate
@@ -199,6 +388,9 @@ impl Clone for UniversalFile {
             UniversalFileInner::WasmFile { id, size, pos: _ } => {
                 Self(UniversalFileInner::WasmFile { id: *id, size: *size, pos: 0 })
             }
+            UniversalFileInner::RangeRequests { cache, size, pos: _ } => {
+                Self(UniversalFileInner::RangeRequests { cache: std::sync::Arc::clone(cache), size: *size, pos: 0 })
+            }
         }
     }
 }
@@ -222,6 +414,11 @@ impl std::io::Read for UniversalFile {
                 *pos += bytes_read;
                 Ok(bytes_read as usize)
             },
+            UniversalFileInner::RangeRequests { cache, size: _, pos } => {
+                let bytes_read = cache.lock().unwrap().read_at(*pos, buf)?;
+                *pos += bytes_read as u64;
+                Ok(bytes_read)
+            }
         }
     }
 }
@@ -254,6 +451,7 @@ impl std::io::Seek for UniversalFile {
             UniversalFileInner::LocalFile { path, file } => get_local_file(path, file)?.seek(style),
             #[cfg(target_arch = "wasm32")]
             UniversalFileInner::WasmFile { id: _, size, pos } => update_pos(pos, *size, style),
+            UniversalFileInner::RangeRequests { cache: _, size, pos } => update_pos(pos, *size, style),
         }
     }
 }
@@ -265,3 +463,118 @@ impl std::fmt::Debug for UniversalFile {
         write!(f, "<UniversalFile>")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serve `data` on a loopback server, optionally honoring Range headers. Returns the URL and the number of
+    /// requests that have been made so far.
+    fn serve(data: Vec<u8>, support_ranges: bool) -> (String, std::sync::Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/file", listener.local_addr().unwrap());
+        let request_count = std::sync::Arc::new(AtomicUsize::new(0));
+        let thread_request_count = std::sync::Arc::clone(&request_count);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = vec![];
+                let mut buffer = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let len = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..len]);
+                }
+                thread_request_count.fetch_add(1, Ordering::SeqCst);
+
+                let request = String::from_utf8(request).unwrap().to_ascii_lowercase();
+                let range = request.lines().find_map(|line| line.strip_prefix("range: bytes=")).filter(|_| support_ranges);
+                let (status, content_range, body) = if range.is_some() && data.is_empty() {
+                    ("416 Range Not Satisfiable", "Content-Range: bytes */0\r\n".to_string(), &data[..])
+                } else if let Some(range) = range {
+                    let (start, end) = range.split_once('-').unwrap();
+                    let (start, end): (usize, usize) = (start.parse().unwrap(), end.parse().unwrap());
+                    let end = std::cmp::min(end + 1, data.len());
+                    (
+                        "206 Partial Content",
+                        format!("Content-Range: bytes {}-{}/{}\r\n", start, end - 1, data.len()),
+                        &data[start..end],
+                    )
+                } else {
+                    ("200 OK", String::new(), &data[..])
+                };
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+                    status,
+                    body.len(),
+                    content_range
+                );
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+        (url, request_count)
+    }
+
+    fn read_at(file: &mut UniversalFile, pos: SeekFrom, len: usize) -> Vec<u8> {
+        file.seek(pos).unwrap();
+        let mut buffer = vec![0u8; len];
+        file.read_exact(&mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_open_url_with_range_requests() {
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let (url, request_count) = serve(data.clone(), true);
+        let mut file =
+            UniversalFile::open_url_with_range_requests(&url, RangeRequestOptions { block_size: 1000, max_cached_blocks: 2 })
+                .unwrap();
+        assert_eq!(request_count.load(Ordering::SeqCst), 1);
+
+        assert_eq!(read_at(&mut file, SeekFrom::End(-10), 10), &data[9990..]);
+        assert_eq!(request_count.load(Ordering::SeqCst), 2);
+        // Spans blocks 0 and 1, which evicts block 9.
+        assert_eq!(read_at(&mut file, SeekFrom::Start(950), 100), &data[950..1050]);
+        assert_eq!(request_count.load(Ordering::SeqCst), 3);
+        assert_eq!(read_at(&mut file, SeekFrom::Start(0), 10), &data[..10]);
+        assert_eq!(request_count.load(Ordering::SeqCst), 3);
+        assert_eq!(read_at(&mut file, SeekFrom::End(-10), 10), &data[9990..]);
+        assert_eq!(request_count.load(Ordering::SeqCst), 4);
+
+        // Clones share the cache.
+        let mut clone = file.clone();
+        assert_eq!(read_at(&mut clone, SeekFrom::Start(9000), 1000), &data[9000..]);
+        let mut rest = vec![];
+        clone.read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(request_count.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_open_url_with_range_requests_fallback() {
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let (url, request_count) = serve(data.clone(), false);
+        let mut file =
+            UniversalFile::open_url_with_range_requests(&url, RangeRequestOptions { block_size: 1000, max_cached_blocks: 2 })
+                .unwrap();
+        let mut contents = vec![];
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, data);
+        assert_eq!(request_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_open_url_with_range_requests_empty() {
+        let (url, request_count) = serve(vec![], true);
+        let mut file =
+            UniversalFile::open_url_with_range_requests(&url, RangeRequestOptions { block_size: 1000, max_cached_blocks: 2 })
+                .unwrap();
+        let mut contents = vec![];
+        file.read_to_end(&mut contents).unwrap();
+        assert!(contents.is_empty());
+        assert_eq!(request_count.load(Ordering::SeqCst), 1);
+    }
+}
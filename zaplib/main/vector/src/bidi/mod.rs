@@ -0,0 +1,329 @@
+//! Bidirectional text: computing embedding levels and the visual order of characters, following the Unicode
+//! Bidirectional Algorithm (UAX #9).
+//!
+//! This supports a subset of the full algorithm:
+//! * The paragraph direction is determined from the first strong character (rules P2 and P3).
+//! * The weak, neutral and implicit rules (W1-W7, N1-N2, I1-I2) and whitespace resetting (L1).
+//! * Reordering of levels (L2) and mirroring of brackets (L4).
+//!
+//! Explicit embeddings, overrides and isolates (like U+202B RIGHT-TO-LEFT EMBEDDING) are ignored, as is
+//! bracket pair matching (N0). Bidi classes are approximated using ranges of the most common scripts.
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BidiClass {
+    /// Left-to-right.
+    L,
+    /// Right-to-left.
+    R,
+    /// Arabic letter.
+    AL,
+    /// European number.
+    EN,
+    /// European separator.
+    ES,
+    /// European terminator.
+    ET,
+    /// Arabic number.
+    AN,
+    /// Common separator.
+    CS,
+    /// Nonspacing mark.
+    NSM,
+    /// Boundary neutral.
+    BN,
+    /// Paragraph separator.
+    B,
+    /// Segment separator.
+    S,
+    /// Whitespace.
+    WS,
+    /// Other neutral.
+    ON,
+}
+
+impl BidiClass {
+    fn of(c: char) -> BidiClass {
+        match c as u32 {
+            0x09 | 0x0B | 0x1F => BidiClass::S,
+            0x0A | 0x0D | 0x1C..=0x1E | 0x85 | 0x2029 => BidiClass::B,
+            0x0C | 0x20 | 0x1680 | 0x2000..=0x200A | 0x2028 | 0x205F | 0x3000 => BidiClass::WS,
+            0x00..=0x08 | 0x0E..=0x1B | 0x7F..=0x84 | 0x86..=0x9F | 0xAD | 0x200B..=0x200D | 0x202A..=0x202E => BidiClass::BN,
+            0x2060..=0x206F | 0xFEFF => BidiClass::BN,
+            0x200E => BidiClass::L,
+            0x200F => BidiClass::R,
+            0x061C => BidiClass::AL,
+            0x30..=0x39 | 0xB2 | 0xB3 | 0xB9 | 0x06F0..=0x06F9 | 0x2070..=0x2079 | 0x2080..=0x2089 => BidiClass::EN,
+            0xFF10..=0xFF19 => BidiClass::EN,
+            0x2B | 0x2D | 0x207A | 0x207B | 0x208A | 0x208B | 0x2212 | 0xFB29 | 0xFE62 | 0xFE63 | 0xFF0B | 0xFF0D => {
+                BidiClass::ES
+            }
+            0x23..=0x25 | 0xA2..=0xA5 | 0xB0 | 0xB1 | 0x066A | 0x2030..=0x2034 | 0x20A0..=0x20CF => BidiClass::ET,
+            0x2C | 0x2E | 0x2F | 0x3A | 0xA0 | 0x060C | 0x202F | 0x2044 | 0xFE50 | 0xFE52 | 0xFE55 | 0xFF0C | 0xFF0E => {
+                BidiClass::CS
+            }
+            0xFF0F | 0xFF1A => BidiClass::CS,
+            0x0600..=0x0605 | 0x0660..=0x0669 | 0x066B | 0x066C | 0x06DD | 0x0890 | 0x0891 | 0x08E2 => BidiClass::AN,
+            0x0300..=0x036F | 0x0483..=0x0489 | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF => BidiClass::NSM,
+            0xFE00..=0xFE0F | 0xFE20..=0xFE2F | 0xE0100..=0xE01EF => BidiClass::NSM,
+            // Hebrew points and accents, and Arabic tashkil.
+            0x0591..=0x05BD | 0x05BF | 0x05C1 | 0x05C2 | 0x05C4 | 0x05C5 | 0x05C7 => BidiClass::NSM,
+            0x0610..=0x061A | 0x064B..=0x065F | 0x0670 | 0x06D6..=0x06DC | 0x06DF..=0x06E4 | 0x06E7 | 0x06E8 => BidiClass::NSM,
+            0x06EA..=0x06ED | 0x0711 | 0x0730..=0x074A | 0x07A6..=0x07B0 | 0x07EB..=0x07F3 | 0x08D3..=0x08E1 => BidiClass::NSM,
+            0x08E3..=0x08FF => BidiClass::NSM,
+            // Arabic, Syriac, Thaana, and the Arabic presentation forms (also in the supplementary planes, which we
+            // check before the right-to-left ranges that contain them).
+            0x0600..=0x07BF | 0x0860..=0x08FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFE => BidiClass::AL,
+            0x10D00..=0x10D3F | 0x10F30..=0x10F6F | 0x1EC70..=0x1ECBF | 0x1ED00..=0x1ED4F | 0x1EE00..=0x1EEFF => BidiClass::AL,
+            // Hebrew, NKo, Samaritan, Mandaic, the Hebrew presentation forms, and historic right-to-left scripts.
+            0x0590..=0x05FF | 0x07C0..=0x085F | 0xFB1D..=0xFB4F => BidiClass::R,
+            0x10800..=0x10CFF | 0x10E80..=0x10FFF | 0x1E800..=0x1EDFF => BidiClass::R,
+            _ if c.is_alphanumeric() => BidiClass::L,
+            _ => BidiClass::ON,
+        }
+    }
+
+    fn is_strong(self) -> bool {
+        matches!(self, BidiClass::L | BidiClass::R | BidiClass::AL)
+    }
+
+    fn is_neutral(self) -> bool {
+        matches!(self, BidiClass::B | BidiClass::S | BidiClass::WS | BidiClass::ON)
+    }
+
+    fn is_rtl(self) -> bool {
+        matches!(self, BidiClass::R | BidiClass::AL | BidiClass::AN)
+    }
+}
+
+/// Returns whether any of `chars` is a right-to-left character (or an Arabic number).
+///
+/// If this returns `false`, then [`levels`] will return all zeros, so callers can skip reordering.
+pub fn has_rtl(chars: &[char]) -> bool {
+    chars.iter().any(|&c| BidiClass::of(c).is_rtl())
+}
+
+/// Compute the embedding level of each of `chars`. Even levels are left-to-right, odd levels right-to-left.
+///
+/// Paragraph separators (like `'\n'`) start a new paragraph, which gets its own direction.
+pub fn levels(chars: &[char]) -> Vec<u8> {
+    let mut levels = Vec::with_capacity(chars.len());
+    let mut paragraph_start = 0;
+    for (index, &c) in chars.iter().enumerate() {
+        if BidiClass::of(c) == BidiClass::B {
+            paragraph_levels(&chars[paragraph_start..=index], &mut levels);
+            paragraph_start = index + 1;
+        }
+    }
+    if paragraph_start < chars.len() {
+        paragraph_levels(&chars[paragraph_start..], &mut levels);
+    }
+    levels
+}
+
+fn paragraph_levels(chars: &[char], levels: &mut Vec<u8>) {
+    let original_classes: Vec<BidiClass> = chars.iter().map(|&c| BidiClass::of(c)).collect();
+
+    // P2, P3: the paragraph level follows the first strong character.
+    let paragraph_level = match original_classes.iter().find(|class| class.is_strong()) {
+        Some(BidiClass::R | BidiClass::AL) => 1,
+        _ => 0,
+    };
+    let embedding_direction = if paragraph_level == 1 { BidiClass::R } else { BidiClass::L };
+
+    let mut classes = original_classes.clone();
+
+    // W1: nonspacing marks (and boundary neutrals, which we don't remove) take the class of the previous character.
+    let mut prev_class = embedding_direction;
+    for class in &mut classes {
+        if matches!(*class, BidiClass::NSM | BidiClass::BN) {
+            *class = prev_class;
+        } else {
+            prev_class = *class;
+        }
+    }
+
+    // W2: European numbers after Arabic letters become Arabic numbers. W3: Arabic letters become R.
+    let mut last_strong = embedding_direction;
+    for class in &mut classes {
+        match *class {
+            BidiClass::EN if last_strong == BidiClass::AL => *class = BidiClass::AN,
+            BidiClass::L | BidiClass::R => last_strong = *class,
+            BidiClass::AL => {
+                last_strong = BidiClass::AL;
+                *class = BidiClass::R;
+            }
+            _ => {}
+        }
+    }
+
+    // W4: a single separator between two numbers of the same type joins them.
+    for index in 1..classes.len().saturating_sub(1) {
+        let (prev, next) = (classes[index - 1], classes[index + 1]);
+        match classes[index] {
+            BidiClass::ES if prev == BidiClass::EN && next == BidiClass::EN => classes[index] = BidiClass::EN,
+            BidiClass::CS if prev == next && matches!(prev, BidiClass::EN | BidiClass::AN) => classes[index] = prev,
+            _ => {}
+        }
+    }
+
+    // W5: terminators adjacent to European numbers become European numbers.
+    let mut index = 0;
+    while index < classes.len() {
+        if classes[index] == BidiClass::ET {
+            let start = index;
+            while index < classes.len() && classes[index] == BidiClass::ET {
+                index += 1;
+            }
+            let touches_number =
+                (start > 0 && classes[start - 1] == BidiClass::EN) || (index < classes.len() && classes[index] == BidiClass::EN);
+            if touches_number {
+                classes[start..index].fill(BidiClass::EN);
+            }
+        } else {
+            index += 1;
+        }
+    }
+
+    // W6: remaining separators and terminators become neutral. W7: European numbers after L become L.
+    let mut last_strong = embedding_direction;
+    for class in &mut classes {
+        match *class {
+            BidiClass::ES | BidiClass::ET | BidiClass::CS => *class = BidiClass::ON,
+            BidiClass::EN if last_strong == BidiClass::L => *class = BidiClass::L,
+            BidiClass::L | BidiClass::R => last_strong = *class,
+            _ => {}
+        }
+    }
+
+    // N1, N2: neutrals between characters of the same direction take that direction (numbers count as R),
+    // otherwise they take the embedding direction.
+    let direction = |class: BidiClass| match class {
+        BidiClass::L => BidiClass::L,
+        _ => BidiClass::R,
+    };
+    let mut index = 0;
+    while index < classes.len() {
+        if classes[index].is_neutral() {
+            let start = index;
+            while index < classes.len() && classes[index].is_neutral() {
+                index += 1;
+            }
+            let before = if start > 0 { direction(classes[start - 1]) } else { embedding_direction };
+            let after = if index < classes.len() { direction(classes[index]) } else { embedding_direction };
+            classes[start..index].fill(if before == after { before } else { embedding_direction });
+        } else {
+            index += 1;
+        }
+    }
+
+    // I1, I2: resolve implicit levels.
+    let first_index = levels.len();
+    levels.extend(classes.iter().map(|&class| match (paragraph_level, class) {
+        (0, BidiClass::R) => 1,
+        (0, BidiClass::AN | BidiClass::EN) => 2,
+        (0, _) => 0,
+        (_, BidiClass::L | BidiClass::AN | BidiClass::EN) => 2,
+        _ => 1,
+    }));
+
+    // L1: separators, and whitespace before them or at the end of the line, are reset to the paragraph level.
+    let levels = &mut levels[first_index..];
+    let mut reset_whitespace = true;
+    for index in (0..original_classes.len()).rev() {
+        match original_classes[index] {
+            BidiClass::B | BidiClass::S => {
+                levels[index] = paragraph_level;
+                reset_whitespace = true;
+            }
+            BidiClass::WS | BidiClass::BN if reset_whitespace => levels[index] = paragraph_level,
+            BidiClass::WS | BidiClass::BN => {}
+            _ => reset_whitespace = false,
+        }
+    }
+}
+
+/// Returns the indices of `levels` in visual (left-to-right) order, by reversing every run of characters at or
+/// above each odd level (rule L2).
+pub fn visual_order(levels: &[u8]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..levels.len()).collect();
+    let max_level = levels.iter().copied().max().unwrap_or(0);
+    let min_odd_level = levels.iter().copied().filter(|level| level % 2 == 1).min().unwrap_or(max_level + 1);
+    for level in (min_odd_level..=max_level).rev() {
+        let mut index = 0;
+        while index < order.len() {
+            if levels[order[index]] >= level {
+                let start = index;
+                while index < order.len() && levels[order[index]] >= level {
+                    index += 1;
+                }
+                order[start..index].reverse();
+            } else {
+                index += 1;
+            }
+        }
+    }
+    order
+}
+
+/// Returns the mirrored version of `c`, for characters with the `Bidi_Mirrored` property that are displayed
+/// right-to-left (rule L4). Other characters are returned as is.
+pub fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '<' => '>',
+        '>' => '<',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        '⁅' => '⁆',
+        '⁆' => '⁅',
+        '≤' => '≥',
+        '≥' => '≤',
+        '「' => '」',
+        '」' => '「',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(levels(&chars("abc")), vec![0, 0, 0]);
+        // Hebrew in a left-to-right paragraph, with trailing whitespace.
+        assert_eq!(levels(&chars("a \u{05D0}\u{05D1} ")), vec![0, 0, 1, 1, 0]);
+        // Latin and numbers in a right-to-left paragraph.
+        assert_eq!(levels(&chars("\u{05D0} ab 12")), vec![1, 1, 2, 2, 2, 2, 2]);
+        assert_eq!(levels(&chars("\u{05D0} 12 ab")), vec![1, 1, 2, 2, 1, 2, 2]);
+        // Numbers after Arabic letters are Arabic numbers, and the separator joins them.
+        assert_eq!(levels(&chars("\u{0628} 1.5")), vec![1, 1, 2, 2, 2]);
+        // Each paragraph gets its own direction.
+        assert_eq!(levels(&chars("\u{05D0}\na")), vec![1, 1, 0]);
+    }
+
+    #[test]
+    fn test_visual_order() {
+        assert_eq!(visual_order(&[0, 0, 0]), vec![0, 1, 2]);
+        assert_eq!(visual_order(&[0, 1, 1, 0]), vec![0, 2, 1, 3]);
+        assert_eq!(visual_order(&[1, 1, 2, 2, 1, 2, 2]), vec![5, 6, 4, 2, 3, 1, 0]);
+    }
+
+    #[test]
+    fn test_has_rtl() {
+        assert!(!has_rtl(&chars("hello (world) 123")));
+        assert!(has_rtl(&chars("hello \u{05E9}\u{05DC}\u{05D5}\u{05DD}")));
+    }
+}
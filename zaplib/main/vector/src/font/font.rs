@@ -1,3 +1,4 @@
+use crate::font::layout::{GlyphPositioning, GlyphSubstitution, KerningPairs};
 use crate::font::Glyph;
 use crate::geometry::Rectangle;
 
@@ -9,6 +10,52 @@ pub struct VectorFont {
     pub(crate) descender: f32,
     pub(crate) line_gap: f32,
     pub(crate) bounds: Rectangle,
+    /// Glyph indices for the Basic Multilingual Plane, indexed by char code. Missing glyphs are 0.
     pub char_code_to_glyph_index_map: Vec<usize>,
+    /// Glyph indices for char codes outside of the Basic Multilingual Plane, sorted by char code.
+    pub(crate) supplementary_char_code_groups: Vec<CharCodeGroup>,
     pub glyphs: Vec<Glyph>,
+    pub(crate) glyph_substitution: Option<GlyphSubstitution>,
+    pub(crate) glyph_positioning: Option<GlyphPositioning>,
+    /// Kerning from the legacy `kern` table, which we only use if there is no `kern` feature in `GPOS`.
+    pub(crate) kerning_pairs: Option<KerningPairs>,
+}
+
+/// A range of consecutive char codes that map to consecutive glyph indices.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct CharCodeGroup {
+    pub(crate) start_char_code: u32,
+    pub(crate) end_char_code: u32,
+    pub(crate) start_glyph_index: usize,
+}
+
+impl VectorFont {
+    /// Get the glyph index for a character, or `None` if this font doesn't have a glyph for it.
+    pub fn glyph_index(&self, c: char) -> Option<usize> {
+        let char_code = c as u32;
+        let glyph_index = if char_code < 0x10000 {
+            self.char_code_to_glyph_index_map.get(char_code as usize).copied().unwrap_or(0)
+        } else {
+            match self.supplementary_char_code_groups.binary_search_by(|group| {
+                if group.end_char_code < char_code {
+                    std::cmp::Ordering::Less
+                } else if group.start_char_code > char_code {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            }) {
+                Ok(index) => {
+                    let group = &self.supplementary_char_code_groups[index];
+                    group.start_glyph_index + (char_code - group.start_char_code) as usize
+                }
+                Err(_) => 0,
+            }
+        };
+        if glyph_index == 0 || glyph_index >= self.glyphs.len() {
+            None
+        } else {
+            Some(glyph_index)
+        }
+    }
 }
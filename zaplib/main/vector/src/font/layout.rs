@@ -0,0 +1,165 @@
+//! OpenType layout tables (`GSUB`, `GPOS` and the legacy `kern` table), as far as we use them for
+//! [`crate::shaping`].
+//!
+//! Glyph ids are stored as [`u16`] here, since that is what OpenType uses.
+
+/// A 4-byte OpenType tag, like `b"liga"` or `b"arab"`.
+pub type Tag = [u8; 4];
+
+/// Maps glyph ids to a "coverage index", which is used to index into the data of a lookup subtable.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Coverage {
+    /// Ranges of `(start_glyph_id, end_glyph_id, start_coverage_index)`, sorted by glyph id.
+    pub(crate) ranges: Vec<(u16, u16, u16)>,
+}
+
+impl Coverage {
+    pub(crate) fn index(&self, glyph_id: u16) -> Option<usize> {
+        let index = self.ranges.partition_point(|&(_, end_glyph_id, _)| end_glyph_id < glyph_id);
+        match self.ranges.get(index) {
+            Some(&(start_glyph_id, _, start_coverage_index)) if start_glyph_id <= glyph_id => {
+                Some((start_coverage_index + (glyph_id - start_glyph_id)) as usize)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Maps glyph ids to classes. Glyphs that are not in any range have class 0.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ClassDef {
+    /// Ranges of `(start_glyph_id, end_glyph_id, class)`, sorted by glyph id.
+    pub(crate) ranges: Vec<(u16, u16, u16)>,
+}
+
+impl ClassDef {
+    pub(crate) fn class(&self, glyph_id: u16) -> u16 {
+        let index = self.ranges.partition_point(|&(_, end_glyph_id, _)| end_glyph_id < glyph_id);
+        match self.ranges.get(index) {
+            Some(&(start_glyph_id, _, class)) if start_glyph_id <= glyph_id => class,
+            _ => 0,
+        }
+    }
+}
+
+/// The scripts and features of a `GSUB` or `GPOS` table.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct LayoutFeatures {
+    /// Script tags with the feature indices of their default language system.
+    pub(crate) scripts: Vec<(Tag, Vec<u16>)>,
+    /// Feature tags with their lookup indices, indexed by feature index.
+    pub(crate) features: Vec<(Tag, Vec<u16>)>,
+}
+
+impl LayoutFeatures {
+    /// Get the lookup indices for a feature in the first of `script_tags` that the font supports,
+    /// falling back to the `DFLT` script.
+    pub(crate) fn lookup_indices<'a>(&'a self, script_tags: &[&Tag], feature_tag: &'a Tag) -> impl Iterator<Item = u16> + 'a {
+        let feature_indices = script_tags
+            .iter()
+            .copied()
+            .chain(std::iter::once(b"DFLT"))
+            .find_map(|script_tag| self.scripts.iter().find(|(tag, _)| tag == script_tag))
+            .map(|(_, feature_indices)| feature_indices.as_slice())
+            .unwrap_or(&[]);
+        feature_indices
+            .iter()
+            .filter_map(move |&feature_index| self.features.get(feature_index as usize))
+            .filter(move |(tag, _)| tag == feature_tag)
+            .flat_map(|(_, lookup_indices)| lookup_indices.iter().copied())
+    }
+}
+
+/// The glyph substitution (`GSUB`) table.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct GlyphSubstitution {
+    pub(crate) features: LayoutFeatures,
+    /// Indexed by lookup index. Each lookup contains subtables that are tried in order.
+    pub(crate) lookups: Vec<Vec<SubstitutionSubtable>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum SubstitutionSubtable {
+    /// Replace a glyph with `glyph_id + delta`.
+    SingleDelta { coverage: Coverage, delta: i16 },
+    /// Replace a glyph with `substitutes[coverage_index]`.
+    SingleList { coverage: Coverage, substitutes: Vec<u16> },
+    /// Replace a glyph with a sequence of glyphs, `sequences[coverage_index]`.
+    Multiple { coverage: Coverage, sequences: Vec<Vec<u16>> },
+    /// Replace a sequence of glyphs with a single glyph. The first glyph of the sequence is in the coverage,
+    /// and `ligature_sets[coverage_index]` contains the candidate ligatures, in order of preference.
+    Ligature { coverage: Coverage, ligature_sets: Vec<Vec<Ligature>> },
+    /// Apply other lookups to a sequence of glyphs that are each in the respective `input` coverage, and that
+    /// are preceded and followed by glyphs in the `backtrack` (in reverse order) and `lookahead` coverages.
+    /// `lookups` contains `(sequence_index, lookup_index)` pairs. Both context (type 5) and chained context
+    /// (type 6) substitutions end up here, but only coverage-based ones (format 3).
+    ChainContext { backtrack: Vec<Coverage>, input: Vec<Coverage>, lookahead: Vec<Coverage>, lookups: Vec<(u16, u16)> },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Ligature {
+    pub(crate) glyph_id: u16,
+    /// The glyphs following the first glyph.
+    pub(crate) components: Vec<u16>,
+}
+
+/// The glyph positioning (`GPOS`) table.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct GlyphPositioning {
+    pub(crate) features: LayoutFeatures,
+    /// Indexed by lookup index. Each lookup contains subtables that are tried in order.
+    pub(crate) lookups: Vec<Vec<PositioningSubtable>>,
+}
+
+/// Horizontal adjustments from an OpenType `ValueRecord`, in font units.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct ValueAdjustment {
+    pub(crate) x_placement: i16,
+    pub(crate) x_advance: i16,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum PositioningSubtable {
+    /// Adjustments for specific pairs of glyphs. `pair_sets[coverage_index]` contains
+    /// `(second_glyph_id, first_adjustment, second_adjustment)`, sorted by `second_glyph_id`.
+    PairGlyphs { coverage: Coverage, pair_sets: Vec<Vec<(u16, ValueAdjustment, ValueAdjustment)>> },
+    /// Adjustments for pairs of glyph classes, indexed by `class1 * class2_count + class2`.
+    PairClasses {
+        coverage: Coverage,
+        class_def1: ClassDef,
+        class_def2: ClassDef,
+        class2_count: usize,
+        adjustments: Vec<(ValueAdjustment, ValueAdjustment)>,
+    },
+}
+
+impl PositioningSubtable {
+    /// Get the adjustments for a pair of glyphs, if this subtable has any.
+    pub(crate) fn pair_adjustment(&self, first: u16, second: u16) -> Option<(ValueAdjustment, ValueAdjustment)> {
+        match self {
+            PositioningSubtable::PairGlyphs { coverage, pair_sets } => {
+                let pair_set = pair_sets.get(coverage.index(first)?)?;
+                let index = pair_set.binary_search_by_key(&second, |&(glyph_id, _, _)| glyph_id).ok()?;
+                Some((pair_set[index].1, pair_set[index].2))
+            }
+            PositioningSubtable::PairClasses { coverage, class_def1, class_def2, class2_count, adjustments } => {
+                coverage.index(first)?;
+                let index = class_def1.class(first) as usize * class2_count + class_def2.class(second) as usize;
+                adjustments.get(index).copied()
+            }
+        }
+    }
+}
+
+/// Pairs from the legacy `kern` table, as `((left_glyph_id << 16) | right_glyph_id, value)`, sorted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct KerningPairs {
+    pub(crate) pairs: Vec<(u32, i16)>,
+}
+
+impl KerningPairs {
+    pub(crate) fn get(&self, left: u16, right: u16) -> Option<i16> {
+        let key = (left as u32) << 16 | right as u32;
+        self.pairs.binary_search_by_key(&key, |&(key, _)| key).ok().map(|index| self.pairs[index].1)
+    }
+}
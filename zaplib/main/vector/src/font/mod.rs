@@ -1,6 +1,7 @@
 // Clippy TODO
 #![warn(clippy::module_inception)]
 
+pub(crate) mod layout;
 pub mod outline;
 
 mod font;
@@ -8,6 +9,7 @@ mod glyph;
 mod horizontal_metrics;
 mod outline_point;
 
+pub(crate) use self::font::CharCodeGroup;
 pub use self::font::VectorFont;
 pub use self::glyph::Glyph;
 pub use self::horizontal_metrics::HorizontalMetrics;
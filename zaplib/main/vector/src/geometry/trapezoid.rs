@@ -5,3 +5,11 @@ pub struct Trapezoid {
     pub(crate) xs: [f32; 2],
     pub(crate) ys: [f32; 4],
 }
+
+impl Trapezoid {
+    /// Creates a new trapezoid with vertical sides at `xs`. `ys` are the y-coordinates of the bottom left,
+    /// bottom right, top left, and top right corners, in that order.
+    pub fn new(xs: [f32; 2], ys: [f32; 4]) -> Trapezoid {
+        Trapezoid { xs, ys }
+    }
+}
@@ -2,9 +2,11 @@
 //!
 //! For internal Zaplib use, unless you know what you're doing.
 
+pub mod bidi;
 pub mod font;
 pub mod geometry;
 pub mod internal_iter;
 pub mod path;
+pub mod shaping;
 pub mod trapezoidator;
 pub mod ttf_parser;
@@ -0,0 +1,384 @@
+//! Devanagari shaping, following the OpenType Indic shaping model (like HarfBuzz and Uniscribe do):
+//! 1. The text is split into syllables, which are shaped one by one.
+//! 2. In a consonant syllable we find the base consonant, and mark the other consonants as reph, pre-base or
+//!    post-base, which decides which of the `rphf`, `half`, `blwf` and `pstf` features apply to them. The pre-base
+//!    "i" vowel sign is moved in front of the consonants.
+//! 3. The basic shaping features are applied one by one, in the standard order.
+//! 4. The reph is moved after the base consonant, and the "i" vowel sign after any pre-base consonant that still
+//!    has a visible virama.
+//! 5. The presentation features are applied.
+//!
+//! Syllables in which glyphs got reordered get a single cluster, so the cursor can't end up inside of them.
+
+use super::{apply_features, GlyphInfo, MASK_BLWF, MASK_HALF, MASK_PSTF, MASK_RPHF};
+use crate::font::layout::{GlyphSubstitution, Tag};
+use crate::font::VectorFont;
+
+const SCRIPT_TAGS: &[&Tag] = &[b"dev2", b"deva"];
+
+/// Applied one at a time, before the final reordering.
+const BASIC_FEATURES: &[(&Tag, u8)] = &[
+    (b"locl", 0),
+    (b"ccmp", 0),
+    (b"nukt", 0),
+    (b"akhn", 0),
+    (b"rphf", MASK_RPHF),
+    (b"rkrf", 0),
+    (b"blwf", MASK_BLWF),
+    (b"half", MASK_HALF),
+    (b"pstf", MASK_PSTF),
+    (b"vatu", 0),
+    (b"cjct", 0),
+];
+
+/// Applied together, after the final reordering.
+const PRESENTATION_FEATURES: &[(&Tag, u8)] =
+    &[(b"pres", 0), (b"abvs", 0), (b"blws", 0), (b"psts", 0), (b"haln", 0), (b"calt", 0), (b"clig", 0)];
+
+const VIRAMA: char = '\u{094D}';
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Category {
+    Consonant,
+    Ra,
+    Nukta,
+    Virama,
+    /// Zero width joiner and non-joiner.
+    Joiner,
+    IndependentVowel,
+    VowelSign,
+    /// The "i" vowel sign, which is written in front of its consonants.
+    PreBaseVowelSign,
+    /// Candrabindu, anusvara, visarga and the like.
+    Modifier,
+    Other,
+}
+
+fn category(c: char) -> Category {
+    match c as u32 {
+        0x0930 => Category::Ra,
+        0x0915..=0x0939 | 0x0958..=0x095F | 0x0978..=0x097F => Category::Consonant,
+        0x093C => Category::Nukta,
+        0x094D => Category::Virama,
+        0x200C | 0x200D => Category::Joiner,
+        0x0904..=0x0914 | 0x0960..=0x0961 | 0x0972..=0x0977 => Category::IndependentVowel,
+        0x093F => Category::PreBaseVowelSign,
+        0x093A..=0x093B | 0x093E | 0x0940..=0x094C | 0x094E..=0x094F | 0x0955..=0x0957 | 0x0962..=0x0963 => Category::VowelSign,
+        0x0900..=0x0903 | 0x0951..=0x0954 => Category::Modifier,
+        _ => Category::Other,
+    }
+}
+
+fn is_consonant(category: Category) -> bool {
+    matches!(category, Category::Consonant | Category::Ra)
+}
+
+/// The role of a glyph in a consonant syllable, used for the final reordering.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum IndicPosition {
+    /// Not in a consonant syllable.
+    None,
+    /// The initial Ra + virama that turns into a reph.
+    Reph,
+    PreBaseVowelSign,
+    /// Consonants (with their viramas) before the base consonant, which can get half forms.
+    PreBase,
+    Base,
+    /// Consonants (with their viramas) after the base consonant, which can get below-base forms.
+    PostBase,
+    /// Vowel signs and modifiers.
+    Tail,
+}
+
+impl Default for IndicPosition {
+    fn default() -> Self {
+        IndicPosition::None
+    }
+}
+
+/// Find the end of the syllable starting at `start`, and whether it's a consonant syllable.
+///
+/// Consonant syllables look like `(C N? H J?)* C N? (V | N | M)* H?`, where the consonants and viramas can
+/// also end the syllable without a final consonant (as in "क्").
+fn syllable_end(categories: &[Category], start: usize) -> (usize, bool) {
+    let len = categories.len();
+    let skip = |index: &mut usize, category: Category| {
+        while *index < len && categories[*index] == category {
+            *index += 1;
+        }
+    };
+    let mut end = start + 1;
+    let is_consonant_syllable = is_consonant(categories[start]);
+    match categories[start] {
+        Category::Consonant | Category::Ra => {
+            skip(&mut end, Category::Nukta);
+            while end < len && categories[end] == Category::Virama {
+                end += 1;
+                skip(&mut end, Category::Joiner);
+                if end < len && is_consonant(categories[end]) {
+                    end += 1;
+                    skip(&mut end, Category::Nukta);
+                } else {
+                    return (end, true);
+                }
+            }
+        }
+        Category::IndependentVowel => {
+            skip(&mut end, Category::Nukta);
+            skip(&mut end, Category::Joiner);
+        }
+        _ => return (end, false),
+    }
+    while end < len && matches!(categories[end], Category::VowelSign | Category::PreBaseVowelSign | Category::Nukta) {
+        end += 1;
+    }
+    if end < len && categories[end] == Category::Virama {
+        end += 1;
+    }
+    skip(&mut end, Category::Modifier);
+    (end, is_consonant_syllable)
+}
+
+/// Shape Devanagari `chars`, given `glyphs` that still map 1:1 to `chars`.
+pub(super) fn shape_syllables(font: &VectorFont, chars: &[char], glyphs: Vec<GlyphInfo>) -> Vec<GlyphInfo> {
+    let glyph_substitution = font.glyph_substitution.as_ref();
+    let virama_glyph_id = font.glyph_index(VIRAMA).map(|glyph_id| glyph_id as u16);
+    let categories: Vec<Category> = chars.iter().map(|&c| category(c)).collect();
+
+    let mut out = Vec::with_capacity(glyphs.len());
+    let mut start = 0;
+    while start < chars.len() {
+        let (end, is_consonant_syllable) = syllable_end(&categories, start);
+        let mut syllable = glyphs[start..end].to_vec();
+        let mut reordered = false;
+        if is_consonant_syllable {
+            reordered |= initial_reordering(glyph_substitution, &categories[start..end], &mut syllable);
+        }
+        if let Some(glyph_substitution) = glyph_substitution {
+            for feature in BASIC_FEATURES {
+                apply_features(glyph_substitution, SCRIPT_TAGS, &[*feature], &mut syllable);
+            }
+        }
+        if is_consonant_syllable {
+            reordered |= final_reordering(virama_glyph_id, &mut syllable);
+        }
+        if let Some(glyph_substitution) = glyph_substitution {
+            apply_features(glyph_substitution, SCRIPT_TAGS, PRESENTATION_FEATURES, &mut syllable);
+        }
+        if reordered {
+            for glyph in &mut syllable {
+                glyph.cluster = glyphs[start].cluster;
+            }
+        }
+        out.extend(syllable);
+        start = end;
+    }
+    out
+}
+
+/// Whether `feature` substitutes anything in `glyph_ids`.
+fn would_substitute(glyph_substitution: Option<&GlyphSubstitution>, feature: &Tag, glyph_ids: &[u16]) -> bool {
+    let glyph_substitution = match glyph_substitution {
+        Some(glyph_substitution) => glyph_substitution,
+        None => return false,
+    };
+    let mut glyphs: Vec<GlyphInfo> = glyph_ids.iter().map(|&glyph_id| GlyphInfo { glyph_id, ..GlyphInfo::default() }).collect();
+    apply_features(glyph_substitution, SCRIPT_TAGS, &[(feature, 0)], &mut glyphs);
+    glyphs.len() != glyph_ids.len() || glyphs.iter().zip(glyph_ids).any(|(glyph, &glyph_id)| glyph.glyph_id != glyph_id)
+}
+
+/// Set the [`IndicPosition`] and feature masks of the glyphs in a consonant syllable, and move the pre-base
+/// vowel sign in front of the consonants. Returns whether the syllable needs to be a single cluster.
+fn initial_reordering(
+    glyph_substitution: Option<&GlyphSubstitution>,
+    categories: &[Category],
+    syllable: &mut Vec<GlyphInfo>,
+) -> bool {
+    let len = categories.len();
+    let consonants_end = categories
+        .iter()
+        .position(|category| matches!(category, Category::VowelSign | Category::PreBaseVowelSign | Category::Modifier))
+        .unwrap_or(len);
+
+    // An initial Ra + virama becomes a reph if there's a consonant after it, and the font supports it.
+    let has_reph = len > 2
+        && categories[0] == Category::Ra
+        && categories[1] == Category::Virama
+        && is_consonant(categories[2])
+        && would_substitute(glyph_substitution, b"rphf", &[syllable[0].glyph_id, syllable[1].glyph_id]);
+    let consonants_start = if has_reph { 2 } else { 0 };
+
+    // The base consonant is the last consonant, unless it has a below-base form (like the Ra in "क्र"), in which
+    // case we keep looking backwards.
+    let mut base = consonants_start;
+    for index in (consonants_start..consonants_end).rev() {
+        if !is_consonant(categories[index]) {
+            continue;
+        }
+        base = index;
+        let has_below_base_form = index > consonants_start && categories[index - 1] == Category::Virama && {
+            let (virama, consonant) = (syllable[index - 1].glyph_id, syllable[index].glyph_id);
+            would_substitute(glyph_substitution, b"blwf", &[virama, consonant])
+                || would_substitute(glyph_substitution, b"blwf", &[consonant, virama])
+        };
+        if !has_below_base_form {
+            break;
+        }
+    }
+
+    for (index, glyph) in syllable.iter_mut().enumerate() {
+        let (position, mask) = if index < consonants_start {
+            (IndicPosition::Reph, MASK_RPHF)
+        } else if index < base {
+            (IndicPosition::PreBase, MASK_HALF | MASK_BLWF)
+        } else if index == base || (index == base + 1 && categories[index] == Category::Nukta) {
+            (IndicPosition::Base, 0)
+        } else if index < consonants_end {
+            (IndicPosition::PostBase, MASK_BLWF | MASK_PSTF)
+        } else if categories[index] == Category::PreBaseVowelSign {
+            (IndicPosition::PreBaseVowelSign, 0)
+        } else {
+            (IndicPosition::Tail, 0)
+        };
+        glyph.position = position;
+        glyph.mask = mask;
+    }
+
+    let mut reordered = has_reph;
+    if let Some(index) = syllable.iter().position(|glyph| glyph.position == IndicPosition::PreBaseVowelSign) {
+        let vowel_sign = syllable.remove(index);
+        syllable.insert(consonants_start, vowel_sign);
+        reordered = true;
+    }
+    reordered
+}
+
+/// Move the reph and the pre-base vowel sign to where they are drawn, now that the basic features have formed
+/// ligatures. Returns whether anything moved.
+fn final_reordering(virama_glyph_id: Option<u16>, syllable: &mut Vec<GlyphInfo>) -> bool {
+    let mut reordered = false;
+
+    // If `rphf` turned Ra + virama into a single glyph, it goes after the base consonant and its below-base forms.
+    // Conjuncts keep the position of their first glyph, so pre-base glyphs can also end up after the base.
+    if syllable.iter().filter(|glyph| glyph.position == IndicPosition::Reph).count() == 1 {
+        let index = syllable.iter().position(|glyph| glyph.position == IndicPosition::Reph).unwrap();
+        let reph = syllable.remove(index);
+        let target = syllable
+            .iter()
+            .rposition(|glyph| matches!(glyph.position, IndicPosition::PreBase | IndicPosition::Base | IndicPosition::PostBase))
+            .map_or(index, |target| target + 1);
+        syllable.insert(target, reph);
+        reordered = true;
+    }
+
+    // A pre-base consonant that didn't get a half form is drawn with a visible virama, and the vowel sign goes
+    // after it, in front of the part that is drawn as one.
+    if let Some(index) = syllable.iter().position(|glyph| glyph.position == IndicPosition::PreBaseVowelSign) {
+        let virama = syllable
+            .iter()
+            .rposition(|glyph| glyph.position == IndicPosition::PreBase && Some(glyph.glyph_id) == virama_glyph_id);
+        if let Some(virama) = virama.filter(|&virama| virama > index) {
+            let vowel_sign = syllable.remove(index);
+            syllable.insert(virama, vowel_sign);
+            reordered = true;
+        }
+    }
+    reordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::layout::{Coverage, LayoutFeatures, Ligature, SubstitutionSubtable};
+    use crate::font::{Glyph, HorizontalMetrics, Outline};
+    use crate::geometry::Rectangle;
+    use crate::shaping::shape;
+
+    const KA: u16 = 1;
+    const RA: u16 = 2;
+    const SSA: u16 = 3;
+    const TA: u16 = 4;
+    const VIRAMA_GLYPH: u16 = 5;
+    const I: u16 = 6;
+    const KSSA: u16 = 7;
+    const REPH: u16 = 8;
+    const HALF_KA: u16 = 9;
+    const RAKAR: u16 = 10;
+
+    fn ligature(first: u16, components: &[u16], glyph_id: u16) -> SubstitutionSubtable {
+        SubstitutionSubtable::Ligature {
+            coverage: Coverage { ranges: vec![(first, first, 0)] },
+            ligature_sets: vec![vec![Ligature { glyph_id, components: components.to_vec() }]],
+        }
+    }
+
+    /// A font with just enough glyphs and `GSUB` features to test Devanagari shaping.
+    fn font() -> VectorFont {
+        let mut char_code_to_glyph_index_map = vec![0; 0x0980];
+        for (c, glyph_id) in [('क', KA), ('र', RA), ('ष', SSA), ('त', TA), ('\u{094D}', VIRAMA_GLYPH), ('ि', I)] {
+            char_code_to_glyph_index_map[c as usize] = glyph_id as usize;
+        }
+        let glyph = Glyph {
+            horizontal_metrics: HorizontalMetrics { advance_width: 500.0, left_side_bearing: 0.0 },
+            bounds: Rectangle::default(),
+            outline: Outline::default(),
+        };
+        let features: Vec<(Tag, Vec<u16>)> =
+            vec![(*b"akhn", vec![0]), (*b"rphf", vec![1]), (*b"half", vec![2]), (*b"blwf", vec![3])];
+        VectorFont {
+            units_per_em: 1000.0,
+            ascender: 800.0,
+            descender: -200.0,
+            line_gap: 0.0,
+            bounds: Rectangle::default(),
+            char_code_to_glyph_index_map,
+            supplementary_char_code_groups: Vec::new(),
+            glyphs: vec![glyph; 11],
+            glyph_substitution: Some(GlyphSubstitution {
+                features: LayoutFeatures { scripts: vec![(*b"dev2", (0..features.len() as u16).collect())], features },
+                lookups: vec![
+                    vec![ligature(KA, &[VIRAMA_GLYPH, SSA], KSSA)],
+                    vec![ligature(RA, &[VIRAMA_GLYPH], REPH)],
+                    vec![ligature(KA, &[VIRAMA_GLYPH], HALF_KA)],
+                    vec![ligature(VIRAMA_GLYPH, &[RA], RAKAR)],
+                ],
+            }),
+            glyph_positioning: None,
+            kerning_pairs: None,
+        }
+    }
+
+    fn glyph_ids(font: &VectorFont, text: &str) -> Vec<usize> {
+        shape(font, &text.chars().collect::<Vec<_>>()).iter().map(|glyph| glyph.glyph_id).collect()
+    }
+
+    #[test]
+    fn test_conjunct() {
+        let font = font();
+        assert_eq!(glyph_ids(&font, "क्ष"), vec![KSSA as usize]);
+        // A half form, and a below-base form (which makes Ka the base consonant).
+        assert_eq!(glyph_ids(&font, "क्त"), vec![HALF_KA as usize, TA as usize]);
+        assert_eq!(glyph_ids(&font, "क्र"), vec![KA as usize, RAKAR as usize]);
+    }
+
+    #[test]
+    fn test_reph() {
+        let font = font();
+        let shaped = shape(&font, &"र्क".chars().collect::<Vec<_>>());
+        assert_eq!(shaped.iter().map(|glyph| glyph.glyph_id).collect::<Vec<_>>(), vec![KA as usize, REPH as usize]);
+        assert_eq!(shaped.iter().map(|glyph| glyph.cluster).collect::<Vec<_>>(), vec![0, 0]);
+        // Without a following consonant there is no reph.
+        assert_eq!(glyph_ids(&font, "र्"), vec![RA as usize, VIRAMA_GLYPH as usize]);
+    }
+
+    #[test]
+    fn test_pre_base_vowel_sign() {
+        let font = font();
+        assert_eq!(glyph_ids(&font, "कि"), vec![I as usize, KA as usize]);
+        assert_eq!(glyph_ids(&font, "क्षि"), vec![I as usize, KSSA as usize]);
+        assert_eq!(glyph_ids(&font, "र्कि"), vec![I as usize, KA as usize, REPH as usize]);
+        // "कि" followed by "त", as two syllables.
+        let shaped = shape(&font, &"कित".chars().collect::<Vec<_>>());
+        assert_eq!(shaped.iter().map(|glyph| glyph.cluster).collect::<Vec<_>>(), vec![0, 0, 2]);
+    }
+}
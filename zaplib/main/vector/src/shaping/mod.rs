@@ -0,0 +1,444 @@
+//! Text shaping: turning characters into positioned glyphs, using the OpenType layout tables of a font.
+//!
+//! This supports a subset of what a full shaping engine like HarfBuzz does:
+//! * Single, multiple and ligature substitutions from `GSUB` (e.g. ligatures like "fi", and Arabic
+//!   contextual forms), and coverage-based (chained) context substitutions.
+//! * Pair adjustments from `GPOS`, or from the legacy `kern` table if `GPOS` has no kerning.
+//! * Arabic joining.
+//! * Devanagari syllables: reph, half forms, below-base forms and conjuncts; see [`indic`].
+//!
+//! Class- and glyph-based context substitutions, mark positioning, and lookup flags are not supported.
+//! Glyphs are always returned in logical order, so right-to-left text still needs to be reordered.
+
+mod indic;
+
+use crate::font::layout::{Coverage, GlyphSubstitution, SubstitutionSubtable, Tag};
+use crate::font::VectorFont;
+use indic::IndicPosition;
+
+/// A glyph produced by [`shape`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapedGlyph {
+    pub glyph_id: usize,
+    /// Index of the first character (in the input of [`shape`]) that this glyph was produced from.
+    ///
+    /// Multiple characters can be combined into one glyph (e.g. ligatures), in which case the next glyph will
+    /// have a cluster that is more than 1 higher. One character can also result in multiple glyphs, which
+    /// then all have the same cluster.
+    pub cluster: usize,
+    /// Horizontal advance in font units, including kerning.
+    pub x_advance: f32,
+    /// Horizontal offset in font units, to apply when drawing the glyph.
+    pub x_offset: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Script {
+    Default,
+    Arabic,
+    Devanagari,
+}
+
+impl Script {
+    /// Returns `None` for characters that are shared between scripts, like spaces, digits and punctuation.
+    fn of(c: char) -> Option<Script> {
+        match c as u32 {
+            0x0600..=0x06FF | 0x0750..=0x077F | 0x08A0..=0x08FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Some(Script::Arabic),
+            0x0900..=0x097F | 0xA8E0..=0xA8FF => Some(Script::Devanagari),
+            _ if c.is_whitespace() || c.is_ascii_punctuation() || c.is_ascii_digit() || c.is_control() => None,
+            _ => Some(Script::Default),
+        }
+    }
+
+    fn tags(self) -> &'static [&'static Tag] {
+        match self {
+            Script::Default => &[b"latn"],
+            Script::Arabic => &[b"arab"],
+            Script::Devanagari => &[b"dev2", b"deva"],
+        }
+    }
+
+    /// The `GSUB` features that we apply, with an optional mask for features that only apply to some glyphs.
+    fn substitution_features(self) -> &'static [(&'static Tag, u8)] {
+        match self {
+            Script::Default => &[(b"ccmp", 0), (b"locl", 0), (b"rlig", 0), (b"liga", 0), (b"clig", 0)],
+            Script::Arabic => &[
+                (b"ccmp", 0),
+                (b"locl", 0),
+                (b"isol", MASK_ISOL),
+                (b"fina", MASK_FINA),
+                (b"medi", MASK_MEDI),
+                (b"init", MASK_INIT),
+                (b"rlig", 0),
+                (b"liga", 0),
+                (b"clig", 0),
+            ],
+            // Devanagari features are applied per syllable, in stages; see `indic`.
+            Script::Devanagari => &[],
+        }
+    }
+
+    /// The `GPOS` features that we apply.
+    fn positioning_features(self) -> &'static [&'static Tag] {
+        match self {
+            Script::Devanagari => &[b"kern", b"dist"],
+            _ => &[b"kern"],
+        }
+    }
+}
+
+const MASK_ISOL: u8 = 1;
+const MASK_FINA: u8 = 2;
+const MASK_MEDI: u8 = 4;
+const MASK_INIT: u8 = 8;
+const MASK_RPHF: u8 = 16;
+const MASK_HALF: u8 = 32;
+const MASK_BLWF: u8 = 64;
+const MASK_PSTF: u8 = 128;
+
+/// How deep context substitutions can nest, to protect against fonts with cyclic lookups.
+const MAX_NESTING_DEPTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct GlyphInfo {
+    glyph_id: u16,
+    cluster: usize,
+    /// Which masked features (like [`MASK_INIT`]) apply to this glyph.
+    mask: u8,
+    /// The role of the glyph in its syllable, for scripts that are shaped per syllable.
+    position: IndicPosition,
+}
+
+/// Shape `chars` using `font`, returning glyphs in logical order.
+///
+/// Characters that are missing from `font` are mapped to glyph 0 (the "missing glyph").
+pub fn shape(font: &VectorFont, chars: &[char]) -> Vec<ShapedGlyph> {
+    let mut shaped_glyphs = Vec::with_capacity(chars.len());
+    // Split `chars` into runs of the same script, attaching shared characters to the preceding run.
+    let mut run_start = 0;
+    let mut run_script = None;
+    for (index, &c) in chars.iter().enumerate() {
+        if let Some(script) = Script::of(c) {
+            match run_script {
+                Some(current_script) if current_script != script => {
+                    shape_run(font, current_script, chars, run_start..index, &mut shaped_glyphs);
+                    run_start = index;
+                    run_script = Some(script);
+                }
+                None => run_script = Some(script),
+                _ => {}
+            }
+        }
+    }
+    shape_run(font, run_script.unwrap_or(Script::Default), chars, run_start..chars.len(), &mut shaped_glyphs);
+    shaped_glyphs
+}
+
+fn shape_run(font: &VectorFont, script: Script, chars: &[char], range: std::ops::Range<usize>, out: &mut Vec<ShapedGlyph>) {
+    let run_chars = &chars[range.clone()];
+    let mut glyphs: Vec<GlyphInfo> = run_chars
+        .iter()
+        .enumerate()
+        .map(|(index, &c)| GlyphInfo {
+            glyph_id: font.glyph_index(c).unwrap_or(0) as u16,
+            cluster: range.start + index,
+            ..GlyphInfo::default()
+        })
+        .collect();
+
+    if script == Script::Devanagari {
+        glyphs = indic::shape_syllables(font, run_chars, glyphs);
+    } else {
+        if script == Script::Arabic {
+            set_arabic_joining_masks(run_chars, &mut glyphs);
+        }
+        if let Some(glyph_substitution) = &font.glyph_substitution {
+            apply_features(glyph_substitution, script.tags(), script.substitution_features(), &mut glyphs);
+        }
+    }
+
+    let first_index = out.len();
+    out.extend(glyphs.iter().map(|glyph| ShapedGlyph {
+        glyph_id: glyph.glyph_id as usize,
+        cluster: glyph.cluster,
+        x_advance: font.glyphs.get(glyph.glyph_id as usize).map_or(0.0, |glyph| glyph.horizontal_metrics.advance_width),
+        x_offset: 0.0,
+    }));
+    apply_positioning(font, script, &glyphs, &mut out[first_index..]);
+}
+
+/// Apply the lookups of `features`, with an optional mask per feature for features that only apply to some glyphs.
+fn apply_features(
+    glyph_substitution: &GlyphSubstitution,
+    script_tags: &[&Tag],
+    features: &[(&Tag, u8)],
+    glyphs: &mut Vec<GlyphInfo>,
+) {
+    // Lookups are applied in lookup list order, regardless of the order of the features.
+    let mut lookups: Vec<(u16, u8)> = Vec::new();
+    for &(feature_tag, mask) in features {
+        for lookup_index in glyph_substitution.features.lookup_indices(script_tags, feature_tag) {
+            match lookups.iter_mut().find(|(index, _)| *index == lookup_index) {
+                // A mask of 0 means that the lookup applies to all glyphs.
+                Some((_, existing_mask)) => {
+                    *existing_mask = if *existing_mask == 0 || mask == 0 { 0 } else { *existing_mask | mask }
+                }
+                None => lookups.push((lookup_index, mask)),
+            }
+        }
+    }
+    lookups.sort_unstable_by_key(|&(lookup_index, _)| lookup_index);
+
+    for (lookup_index, mask) in lookups {
+        let subtables = match glyph_substitution.lookups.get(lookup_index as usize) {
+            Some(subtables) => subtables,
+            None => continue,
+        };
+        let mut index = 0;
+        while index < glyphs.len() {
+            let mut step = 1;
+            if mask == 0 || glyphs[index].mask & mask != 0 {
+                if let Some(output_len) = apply_lookup(glyph_substitution, subtables, glyphs, index, 0) {
+                    step = output_len.max(1);
+                }
+            }
+            index += step;
+        }
+    }
+}
+
+/// Apply the first subtable of a lookup that applies at `index`, returning the number of resulting glyphs.
+fn apply_lookup(
+    glyph_substitution: &GlyphSubstitution,
+    subtables: &[SubstitutionSubtable],
+    glyphs: &mut Vec<GlyphInfo>,
+    index: usize,
+    depth: usize,
+) -> Option<usize> {
+    subtables.iter().find_map(|subtable| apply_substitution(glyph_substitution, subtable, glyphs, index, depth))
+}
+
+/// Apply a substitution at `index`, returning the number of resulting glyphs if it applied.
+fn apply_substitution(
+    glyph_substitution: &GlyphSubstitution,
+    subtable: &SubstitutionSubtable,
+    glyphs: &mut Vec<GlyphInfo>,
+    index: usize,
+    depth: usize,
+) -> Option<usize> {
+    let glyph = glyphs[index];
+    match subtable {
+        SubstitutionSubtable::SingleDelta { coverage, delta } => {
+            coverage.index(glyph.glyph_id)?;
+            glyphs[index].glyph_id = glyph.glyph_id.wrapping_add(*delta as u16);
+            Some(1)
+        }
+        SubstitutionSubtable::SingleList { coverage, substitutes } => {
+            glyphs[index].glyph_id = *substitutes.get(coverage.index(glyph.glyph_id)?)?;
+            Some(1)
+        }
+        SubstitutionSubtable::Multiple { coverage, sequences } => {
+            let sequence = sequences.get(coverage.index(glyph.glyph_id)?)?;
+            glyphs.splice(index..index + 1, sequence.iter().map(|&glyph_id| GlyphInfo { glyph_id, ..glyph }));
+            Some(sequence.len())
+        }
+        SubstitutionSubtable::Ligature { coverage, ligature_sets } => {
+            let ligature_set = ligature_sets.get(coverage.index(glyph.glyph_id)?)?;
+            let following = &glyphs[index + 1..];
+            let ligature = ligature_set.iter().find(|ligature| {
+                ligature.components.len() <= following.len()
+                    && ligature.components.iter().zip(following).all(|(&component, glyph)| component == glyph.glyph_id)
+            })?;
+            glyphs[index].glyph_id = ligature.glyph_id;
+            glyphs.drain(index + 1..index + 1 + ligature.components.len());
+            Some(1)
+        }
+        SubstitutionSubtable::ChainContext { backtrack, input, lookahead, lookups } => {
+            let matches = |coverages: &[Coverage], glyphs: &mut dyn Iterator<Item = &GlyphInfo>| {
+                coverages.iter().all(|coverage| glyphs.next().map_or(false, |glyph| coverage.index(glyph.glyph_id).is_some()))
+            };
+            if input.is_empty()
+                || !matches(backtrack, &mut glyphs[..index].iter().rev())
+                || !matches(input, &mut glyphs[index..].iter())
+                || !matches(lookahead, &mut glyphs[index + input.len()..].iter())
+            {
+                return None;
+            }
+            let mut input_len = input.len();
+            if depth < MAX_NESTING_DEPTH {
+                for &(sequence_index, lookup_index) in lookups {
+                    let position = index + sequence_index as usize;
+                    let subtables = match glyph_substitution.lookups.get(lookup_index as usize) {
+                        Some(subtables) if position < index + input_len => subtables,
+                        _ => continue,
+                    };
+                    let len_before = glyphs.len();
+                    if apply_lookup(glyph_substitution, subtables, glyphs, position, depth + 1).is_some() {
+                        input_len = (input_len + glyphs.len()).saturating_sub(len_before);
+                    }
+                }
+            }
+            Some(input_len)
+        }
+    }
+}
+
+fn apply_positioning(font: &VectorFont, script: Script, glyphs: &[GlyphInfo], shaped_glyphs: &mut [ShapedGlyph]) {
+    let lookups: Vec<_> = match &font.glyph_positioning {
+        Some(glyph_positioning) => {
+            let mut lookup_indices: Vec<u16> = script
+                .positioning_features()
+                .iter()
+                .flat_map(|&feature_tag| glyph_positioning.features.lookup_indices(script.tags(), feature_tag))
+                .collect();
+            lookup_indices.sort_unstable();
+            lookup_indices.dedup();
+            lookup_indices.iter().filter_map(|&lookup_index| glyph_positioning.lookups.get(lookup_index as usize)).collect()
+        }
+        None => Vec::new(),
+    };
+
+    for index in 1..glyphs.len() {
+        let (first, second) = (glyphs[index - 1].glyph_id, glyphs[index].glyph_id);
+        if lookups.is_empty() {
+            if let Some(value) = font.kerning_pairs.as_ref().and_then(|kerning_pairs| kerning_pairs.get(first, second)) {
+                shaped_glyphs[index - 1].x_advance += value as f32;
+            }
+            continue;
+        }
+        for subtables in &lookups {
+            if let Some((first_adjustment, second_adjustment)) =
+                subtables.iter().find_map(|subtable| subtable.pair_adjustment(first, second))
+            {
+                shaped_glyphs[index - 1].x_advance += first_adjustment.x_advance as f32;
+                shaped_glyphs[index - 1].x_offset += first_adjustment.x_placement as f32;
+                shaped_glyphs[index].x_advance += second_adjustment.x_advance as f32;
+                shaped_glyphs[index].x_offset += second_adjustment.x_placement as f32;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum JoiningType {
+    NonJoining,
+    RightJoining,
+    DualJoining,
+    JoinCausing,
+    Transparent,
+}
+
+/// Simplified version of the Unicode `ArabicShaping.txt` data, for the main Arabic blocks.
+fn arabic_joining_type(c: char) -> JoiningType {
+    match c as u32 {
+        0x0610..=0x061A | 0x064B..=0x065F | 0x0670 | 0x06D6..=0x06DC | 0x06DF..=0x06E4 | 0x06E7..=0x06E8 | 0x06EA..=0x06ED => {
+            JoiningType::Transparent
+        }
+        0x0640 | 0x200D => JoiningType::JoinCausing,
+        0x0622..=0x0625
+        | 0x0627
+        | 0x0629
+        | 0x062F..=0x0632
+        | 0x0648
+        | 0x0671..=0x0673
+        | 0x0675..=0x0677
+        | 0x0688..=0x0699
+        | 0x06C0
+        | 0x06C3..=0x06CB
+        | 0x06CD
+        | 0x06CF
+        | 0x06D2..=0x06D3
+        | 0x06D5
+        | 0x06EE..=0x06EF
+        | 0x0759..=0x075B
+        | 0x076B..=0x076C
+        | 0x0771
+        | 0x0773..=0x0774
+        | 0x0778..=0x0779 => JoiningType::RightJoining,
+        0x0620
+        | 0x0626
+        | 0x0628
+        | 0x062A..=0x062E
+        | 0x0633..=0x063F
+        | 0x0641..=0x0647
+        | 0x0649..=0x064A
+        | 0x066E..=0x066F
+        | 0x0678..=0x0687
+        | 0x069A..=0x06BF
+        | 0x06C1..=0x06C2
+        | 0x06CC
+        | 0x06CE
+        | 0x06D0..=0x06D1
+        | 0x06FA..=0x06FC
+        | 0x06FF
+        | 0x0750..=0x077F => JoiningType::DualJoining,
+        _ => JoiningType::NonJoining,
+    }
+}
+
+/// Set [`MASK_ISOL`], [`MASK_FINA`], [`MASK_MEDI`] or [`MASK_INIT`] based on how characters join with their
+/// neighbours. Assumes that `glyphs` still maps 1:1 to `chars`.
+fn set_arabic_joining_masks(chars: &[char], glyphs: &mut [GlyphInfo]) {
+    let joining_types: Vec<JoiningType> = chars.iter().map(|&c| arabic_joining_type(c)).collect();
+    let neighbour = |indices: &mut dyn Iterator<Item = usize>| {
+        indices.map(|index| joining_types[index]).find(|&joining_type| joining_type != JoiningType::Transparent)
+    };
+    for index in 0..chars.len() {
+        let joining_type = joining_types[index];
+        if joining_type != JoiningType::RightJoining && joining_type != JoiningType::DualJoining {
+            continue;
+        }
+        let joins_previous =
+            matches!(neighbour(&mut (0..index).rev()), Some(JoiningType::DualJoining) | Some(JoiningType::JoinCausing));
+        let joins_next = joining_type == JoiningType::DualJoining
+            && matches!(
+                neighbour(&mut (index + 1..chars.len())),
+                Some(JoiningType::RightJoining) | Some(JoiningType::DualJoining) | Some(JoiningType::JoinCausing)
+            );
+        glyphs[index].mask = match (joins_previous, joins_next) {
+            (true, true) => MASK_MEDI,
+            (true, false) => MASK_FINA,
+            (false, true) => MASK_INIT,
+            (false, false) => MASK_ISOL,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ttf_parser::parse_ttf;
+
+    fn ubuntu() -> VectorFont {
+        parse_ttf(include_bytes!("../../../resources/Ubuntu-R.ttf")).unwrap()
+    }
+
+    #[test]
+    fn test_kerning() {
+        let font = ubuntu();
+        let shaped = shape(&font, &['A', 'V']);
+        assert_eq!(shaped.len(), 2);
+        let advance = font.glyphs[shaped[0].glyph_id].horizontal_metrics.advance_width;
+        assert!(shaped[0].x_advance < advance);
+        assert_eq!(shape(&font, &['A', ' ', 'V'])[0].x_advance, advance);
+    }
+
+    #[test]
+    fn test_ligature() {
+        let font = ubuntu();
+        let shaped = shape(&font, &['a', 'f', 'i', 'b']);
+        assert_eq!(shaped.iter().map(|glyph| glyph.cluster).collect::<Vec<_>>(), vec![0, 1, 3]);
+        assert_ne!(shaped[1].glyph_id, font.glyph_index('f').unwrap());
+    }
+
+    #[test]
+    fn test_arabic_joining() {
+        let chars = ['\u{0628}', '\u{064E}', '\u{0628}', '\u{0627}', '\u{0628}'];
+        let mut glyphs: Vec<GlyphInfo> = (0..chars.len()).map(|cluster| GlyphInfo { cluster, ..GlyphInfo::default() }).collect();
+        set_arabic_joining_masks(&chars, &mut glyphs);
+        assert_eq!(
+            glyphs.iter().map(|glyph| glyph.mask).collect::<Vec<_>>(),
+            vec![MASK_INIT, 0, MASK_MEDI, MASK_FINA, MASK_ISOL]
+        );
+    }
+}
@@ -0,0 +1,752 @@
+//! Parsing of the `CFF ` and `CFF2` tables, which contain the outlines of OpenType fonts with PostScript outlines
+//! (sfnt version `OTTO`).
+//!
+//! Glyphs are described by Type 2 charstrings, which draw cubic Bezier curves. [`Outline`] only stores quadratic
+//! b-splines, so every cubic is approximated by one or more quadratic segments.
+//!
+//! Hints are skipped, and `CFF2` variations are ignored (we always produce the default instance).
+
+use super::{Error, Reader, Result};
+use crate::font::{Outline, OutlinePoint};
+use crate::geometry::Point;
+
+/// The maximum distance, in font units, between a cubic curve and its quadratic approximation.
+const CUBIC_TOLERANCE: f32 = 0.25;
+/// CFF2 allows up to 513 operands; CFF only 48.
+const MAX_STACK_SIZE: usize = 513;
+const MAX_SUBR_DEPTH: usize = 10;
+
+/// Parse the outlines of all glyphs in a `CFF ` table, indexed by glyph index.
+pub(super) fn parse_cff(bytes: &[u8]) -> Result<Vec<Outline>> {
+    let mut reader = reader_at(bytes, 2)?;
+    let header_size = reader.read_u8()? as usize;
+    let mut reader = reader_at(bytes, header_size)?;
+    read_index(&mut reader, false)?; // Name INDEX
+    let top_dict = parse_dict(read_index(&mut reader, false)?.first().ok_or(Error)?)?;
+    read_index(&mut reader, false)?; // String INDEX
+    let global_subrs = read_index(&mut reader, false)?;
+    if matches!(dict_operand(&top_dict, 1206, 0), Some(charstring_type) if charstring_type != 2.) {
+        return Err(Error);
+    }
+    let char_strings = read_index(&mut reader_at(bytes, dict_offset(&top_dict, 17)?.ok_or(Error)?)?, false)?;
+    let font_dicts = if let Some(fd_array_offset) = dict_offset(&top_dict, 1236)? {
+        // CID-keyed fonts have a separate Private DICT for each Font DICT.
+        read_index(&mut reader_at(bytes, fd_array_offset)?, false)?
+            .into_iter()
+            .map(|font_dict| parse_local_subrs(bytes, &parse_dict(font_dict)?, false))
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        vec![parse_local_subrs(bytes, &top_dict, false)?]
+    };
+    let fd_select = dict_offset(&top_dict, 1237)?.map(|offset| bytes.get(offset..).ok_or(Error)).transpose()?;
+    parse_char_strings(&char_strings, &global_subrs, &font_dicts, fd_select, &[], false)
+}
+
+/// Parse the outlines of all glyphs in a `CFF2` table, indexed by glyph index.
+pub(super) fn parse_cff2(bytes: &[u8]) -> Result<Vec<Outline>> {
+    let mut reader = reader_at(bytes, 2)?;
+    let header_size = reader.read_u8()? as usize;
+    let top_dict_length = reader.read_u16()? as usize;
+    let top_dict = parse_dict(bytes.get(header_size..header_size + top_dict_length).ok_or(Error)?)?;
+    let global_subrs = read_index(&mut reader_at(bytes, header_size + top_dict_length)?, true)?;
+    let char_strings = read_index(&mut reader_at(bytes, dict_offset(&top_dict, 17)?.ok_or(Error)?)?, true)?;
+    let font_dicts = read_index(&mut reader_at(bytes, dict_offset(&top_dict, 1236)?.ok_or(Error)?)?, true)?
+        .into_iter()
+        .map(|font_dict| parse_local_subrs(bytes, &parse_dict(font_dict)?, true))
+        .collect::<Result<Vec<_>>>()?;
+    let fd_select = dict_offset(&top_dict, 1237)?.map(|offset| bytes.get(offset..).ok_or(Error)).transpose()?;
+    let region_counts = match dict_offset(&top_dict, 24)? {
+        Some(offset) => parse_region_counts(bytes.get(offset + 2..).ok_or(Error)?)?,
+        None => Vec::new(),
+    };
+    parse_char_strings(&char_strings, &global_subrs, &font_dicts, fd_select, &region_counts, true)
+}
+
+fn parse_char_strings(
+    char_strings: &[&[u8]],
+    global_subrs: &[&[u8]],
+    font_dicts: &[Vec<&[u8]>],
+    fd_select: Option<&[u8]>,
+    region_counts: &[usize],
+    is_cff2: bool,
+) -> Result<Vec<Outline>> {
+    let fd_indices = match fd_select {
+        Some(bytes) => parse_fd_select(bytes, char_strings.len())?,
+        None => vec![0; char_strings.len()],
+    };
+    let mut outlines = Vec::with_capacity(char_strings.len());
+    for (&char_string, &fd_index) in char_strings.iter().zip(fd_indices.iter()) {
+        let local_subrs = font_dicts.get(fd_index as usize).ok_or(Error)?;
+        let mut interpreter = CharStringInterpreter::new(global_subrs, local_subrs, region_counts, is_cff2);
+        interpreter.execute(char_string, 0)?;
+        outlines.push(interpreter.finish());
+    }
+    Ok(outlines)
+}
+
+/// Get the local subroutines from the Private DICT that a Top DICT or Font DICT points to.
+fn parse_local_subrs<'a>(bytes: &'a [u8], dict: &[(u16, Vec<f64>)], is_cff2: bool) -> Result<Vec<&'a [u8]>> {
+    let (size, offset) = match (dict_operand(dict, 18, 0), dict_operand(dict, 18, 1)) {
+        (Some(size), Some(offset)) if size >= 0. && offset >= 0. => (size as usize, offset as usize),
+        _ => return Ok(Vec::new()),
+    };
+    let private_dict = parse_dict(bytes.get(offset..offset + size).ok_or(Error)?)?;
+    match dict_operand(&private_dict, 19, 0) {
+        // The offset to the local subroutines is relative to the start of the Private DICT.
+        Some(subrs_offset) if subrs_offset >= 0. => read_index(&mut reader_at(bytes, offset + subrs_offset as usize)?, is_cff2),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Map each glyph index to a Font DICT index.
+fn parse_fd_select(bytes: &[u8], glyph_count: usize) -> Result<Vec<u16>> {
+    let mut reader = Reader::new(bytes);
+    match reader.read_u8()? {
+        0 => (0..glyph_count).map(|_| Ok(reader.read_u8()? as u16)).collect(),
+        format @ (3 | 4) => {
+            let read_glyph_index = |reader: &mut Reader| -> Result<usize> {
+                Ok(if format == 3 { reader.read_u16()? as usize } else { reader.read_u32()? as usize })
+            };
+            let range_count = read_glyph_index(&mut reader)?;
+            let mut fd_indices = vec![0; glyph_count];
+            let mut first = read_glyph_index(&mut reader)?;
+            for _ in 0..range_count {
+                let fd_index = if format == 3 { reader.read_u8()? as u16 } else { reader.read_u16()? };
+                let next = read_glyph_index(&mut reader)?;
+                for item in fd_indices.iter_mut().take(next).skip(first) {
+                    *item = fd_index;
+                }
+                first = next;
+            }
+            Ok(fd_indices)
+        }
+        _ => Err(Error),
+    }
+}
+
+/// Get the number of variation regions for each `vsindex` from an `ItemVariationStore`, which we need to know how
+/// many operands a `blend` operator consumes.
+fn parse_region_counts(bytes: &[u8]) -> Result<Vec<usize>> {
+    let mut reader = reader_at(bytes, 6)?;
+    let data_count = reader.read_u16()? as usize;
+    (0..data_count)
+        .map(|_| {
+            let mut reader = reader_at(bytes, reader.read_u32()? as usize + 4)?;
+            Ok(reader.read_u16()? as usize)
+        })
+        .collect()
+}
+
+fn reader_at(bytes: &[u8], offset: usize) -> Result<Reader<'_>> {
+    Ok(Reader::new(bytes.get(offset..).ok_or(Error)?))
+}
+
+fn read_bytes<'a>(reader: &mut Reader<'a>, count: usize) -> Result<&'a [u8]> {
+    let bytes = reader.bytes.get(..count).ok_or(Error)?;
+    reader.skip(count)?;
+    Ok(bytes)
+}
+
+/// Read an INDEX, and return its items as subslices. CFF2 uses a 32-bit count, while CFF uses a 16-bit one.
+fn read_index<'a>(reader: &mut Reader<'a>, is_cff2: bool) -> Result<Vec<&'a [u8]>> {
+    let count = if is_cff2 { reader.read_u32()? as usize } else { reader.read_u16()? as usize };
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let offset_size = reader.read_u8()? as usize;
+    if !(1..=4).contains(&offset_size) {
+        return Err(Error);
+    }
+    let offsets = read_bytes(reader, (count + 1) * offset_size)?
+        .chunks(offset_size)
+        .map(|bytes| bytes.iter().fold(0, |offset, &byte| offset << 8 | byte as usize))
+        .collect::<Vec<_>>();
+    // Offsets are relative to the byte before the data.
+    let data = read_bytes(reader, offsets[count].checked_sub(1).ok_or(Error)?)?;
+    offsets
+        .windows(2)
+        .map(|offsets| match (offsets[0].checked_sub(1), offsets[1].checked_sub(1)) {
+            (Some(start), Some(end)) => data.get(start..end).ok_or(Error),
+            _ => Err(Error),
+        })
+        .collect()
+}
+
+/// Parse a DICT into `(operator, operands)` pairs. Two-byte operators are represented as `1200 + second_byte`.
+fn parse_dict(bytes: &[u8]) -> Result<Vec<(u16, Vec<f64>)>> {
+    let mut reader = Reader::new(bytes);
+    let mut entries = Vec::new();
+    let mut operands = Vec::new();
+    while !reader.bytes.is_empty() {
+        let b0 = reader.read_u8()?;
+        match b0 {
+            0..=11 | 13..=24 => entries.push((b0 as u16, std::mem::take(&mut operands))),
+            12 => entries.push((1200 + reader.read_u8()? as u16, std::mem::take(&mut operands))),
+            28 => operands.push(reader.read_i16()? as f64),
+            29 => operands.push(reader.read_u32()? as i32 as f64),
+            30 => operands.push(read_real(&mut reader)?),
+            32..=246 => operands.push(b0 as f64 - 139.),
+            247..=250 => operands.push((b0 as f64 - 247.) * 256. + reader.read_u8()? as f64 + 108.),
+            251..=254 => operands.push(-(b0 as f64 - 251.) * 256. - reader.read_u8()? as f64 - 108.),
+            _ => return Err(Error),
+        }
+    }
+    Ok(entries)
+}
+
+/// Read a real number operand, which is encoded as a sequence of nibbles.
+fn read_real(reader: &mut Reader) -> Result<f64> {
+    let mut string = String::new();
+    loop {
+        let byte = reader.read_u8()?;
+        for nibble in [byte >> 4, byte & 0xf] {
+            match nibble {
+                0..=9 => string.push((b'0' + nibble) as char),
+                0xa => string.push('.'),
+                0xb => string.push('e'),
+                0xc => string.push_str("e-"),
+                0xe => string.push('-'),
+                0xf => return string.parse().map_err(|_| Error),
+                _ => return Err(Error),
+            }
+        }
+    }
+}
+
+fn dict_operand(dict: &[(u16, Vec<f64>)], operator: u16, index: usize) -> Option<f64> {
+    dict.iter().find(|(op, _)| *op == operator).and_then(|(_, operands)| operands.get(index).copied())
+}
+
+fn dict_offset(dict: &[(u16, Vec<f64>)], operator: u16) -> Result<Option<usize>> {
+    match dict_operand(dict, operator, 0) {
+        Some(offset) if offset < 0. => Err(Error),
+        offset => Ok(offset.map(|offset| offset as usize)),
+    }
+}
+
+/// Subroutine numbers are biased, so that more of them fit in the short operand encodings.
+fn subr_bias(count: usize) -> i32 {
+    if count < 1240 {
+        107
+    } else if count < 33900 {
+        1131
+    } else {
+        32768
+    }
+}
+
+/// Executes a Type 2 charstring, and builds an [`Outline`] from the path it describes.
+struct CharStringInterpreter<'a> {
+    global_subrs: &'a [&'a [u8]],
+    local_subrs: &'a [&'a [u8]],
+    region_counts: &'a [usize],
+    is_cff2: bool,
+    stack: Vec<f32>,
+    stem_count: usize,
+    /// Whether we've seen the first stack-clearing operator, which might have the advance width as an extra operand.
+    width_parsed: bool,
+    vsindex: usize,
+    current_point: Point,
+    contour: Vec<OutlinePoint>,
+    outline: Outline,
+}
+
+impl<'a> CharStringInterpreter<'a> {
+    fn new(
+        global_subrs: &'a [&'a [u8]],
+        local_subrs: &'a [&'a [u8]],
+        region_counts: &'a [usize],
+        is_cff2: bool,
+    ) -> CharStringInterpreter<'a> {
+        CharStringInterpreter {
+            global_subrs,
+            local_subrs,
+            region_counts,
+            is_cff2,
+            stack: Vec::new(),
+            stem_count: 0,
+            width_parsed: false,
+            vsindex: 0,
+            current_point: Point::origin(),
+            contour: Vec::new(),
+            outline: Outline::default(),
+        }
+    }
+
+    fn finish(mut self) -> Outline {
+        self.close_contour();
+        self.outline
+    }
+
+    /// Returns `true` if `endchar` was executed, in which case the caller should stop as well.
+    fn execute(&mut self, bytes: &[u8], depth: usize) -> Result<bool> {
+        if depth > MAX_SUBR_DEPTH {
+            return Err(Error);
+        }
+        let mut reader = Reader::new(bytes);
+        while !reader.bytes.is_empty() {
+            if self.stack.len() > MAX_STACK_SIZE {
+                return Err(Error);
+            }
+            let b0 = reader.read_u8()?;
+            match b0 {
+                // hstem, vstem, hstemhm, vstemhm
+                1 | 3 | 18 | 23 => {
+                    self.parse_width(self.stack.len() % 2 == 1);
+                    self.stem_count += self.stack.len() / 2;
+                    self.stack.clear();
+                }
+                // vmoveto
+                4 => {
+                    self.parse_width(self.stack.len() > 1);
+                    let dy = self.arg(0)?;
+                    self.move_to(0., dy);
+                }
+                // rlineto
+                5 => {
+                    for index in (0..self.stack.len() / 2 * 2).step_by(2) {
+                        self.line_to(self.stack[index], self.stack[index + 1]);
+                    }
+                }
+                // hlineto, vlineto
+                6 | 7 => {
+                    for index in 0..self.stack.len() {
+                        if (index % 2 == 0) == (b0 == 6) {
+                            self.line_to(self.stack[index], 0.);
+                        } else {
+                            self.line_to(0., self.stack[index]);
+                        }
+                    }
+                }
+                // rrcurveto
+                8 => {
+                    for index in (0..self.stack.len() / 6 * 6).step_by(6) {
+                        self.curve_to_args(index)?;
+                    }
+                }
+                // callsubr
+                10 => {
+                    if self.call_subr(self.local_subrs, depth)? {
+                        return Ok(true);
+                    }
+                    continue;
+                }
+                // return
+                11 => return Ok(false),
+                12 => {
+                    let b1 = reader.read_u8()?;
+                    self.execute_flex(b1)?;
+                }
+                // endchar
+                14 => {
+                    self.parse_width(self.stack.len() == 1 || self.stack.len() == 5);
+                    self.close_contour();
+                    return Ok(true);
+                }
+                // vsindex
+                15 => {
+                    self.vsindex = self.stack.pop().ok_or(Error)? as usize;
+                    continue;
+                }
+                // blend
+                16 => {
+                    self.blend()?;
+                    continue;
+                }
+                // hintmask, cntrmask
+                19 | 20 => {
+                    // Operands before a hintmask are an implied vstem.
+                    self.parse_width(self.stack.len() % 2 == 1);
+                    self.stem_count += self.stack.len() / 2;
+                    reader.skip((self.stem_count + 7) / 8)?;
+                }
+                // rmoveto
+                21 => {
+                    self.parse_width(self.stack.len() > 2);
+                    let (dx, dy) = (self.arg(0)?, self.arg(1)?);
+                    self.move_to(dx, dy);
+                }
+                // hmoveto
+                22 => {
+                    self.parse_width(self.stack.len() > 1);
+                    let dx = self.arg(0)?;
+                    self.move_to(dx, 0.);
+                }
+                // rcurveline
+                24 => {
+                    let curve_count = self.stack.len().saturating_sub(2) / 6;
+                    for index in (0..curve_count * 6).step_by(6) {
+                        self.curve_to_args(index)?;
+                    }
+                    self.line_to(self.arg(curve_count * 6)?, self.arg(curve_count * 6 + 1)?);
+                }
+                // rlinecurve
+                25 => {
+                    let line_count = self.stack.len().saturating_sub(6) / 2;
+                    for index in (0..line_count * 2).step_by(2) {
+                        self.line_to(self.stack[index], self.stack[index + 1]);
+                    }
+                    self.curve_to_args(line_count * 2)?;
+                }
+                // vvcurveto, hhcurveto
+                26 | 27 => {
+                    let mut index = self.stack.len() % 2;
+                    let mut first = if index == 1 { self.stack[0] } else { 0. };
+                    while index + 4 <= self.stack.len() {
+                        let s = &self.stack[index..index + 4];
+                        if b0 == 26 {
+                            self.curve_to(first, s[0], s[1], s[2], 0., s[3]);
+                        } else {
+                            self.curve_to(s[0], first, s[1], s[2], s[3], 0.);
+                        }
+                        first = 0.;
+                        index += 4;
+                    }
+                }
+                // callgsubr
+                29 => {
+                    if self.call_subr(self.global_subrs, depth)? {
+                        return Ok(true);
+                    }
+                    continue;
+                }
+                // vhcurveto, hvcurveto
+                30 | 31 => {
+                    let mut horizontal = b0 == 31;
+                    let mut index = 0;
+                    while index + 4 <= self.stack.len() {
+                        let s = &self.stack[index..index + 4];
+                        // The last curve can have an extra operand for its final coordinate.
+                        let last = if self.stack.len() - index == 5 { self.stack[index + 4] } else { 0. };
+                        if horizontal {
+                            self.curve_to(s[0], 0., s[1], s[2], last, s[3]);
+                        } else {
+                            self.curve_to(0., s[0], s[1], s[2], s[3], last);
+                        }
+                        horizontal = !horizontal;
+                        index += 4;
+                    }
+                }
+                28 => self.stack.push(reader.read_i16()? as f32),
+                32..=246 => self.stack.push(b0 as f32 - 139.),
+                247..=250 => self.stack.push((b0 as f32 - 247.) * 256. + reader.read_u8()? as f32 + 108.),
+                251..=254 => self.stack.push(-(b0 as f32 - 251.) * 256. - reader.read_u8()? as f32 - 108.),
+                255 => self.stack.push(reader.read_u32()? as i32 as f32 / 65536.),
+                _ => return Err(Error),
+            }
+            // Operators clear the stack, except for the ones that `continue` above.
+            if b0 < 28 || b0 == 30 || b0 == 31 {
+                self.stack.clear();
+            }
+        }
+        Ok(false)
+    }
+
+    /// The two-byte flex operators. Arithmetic and storage operators are not supported, since they are deprecated
+    /// and not used in practice.
+    fn execute_flex(&mut self, b1: u8) -> Result<()> {
+        let s = &self.stack;
+        match b1 {
+            // hflex
+            34 if s.len() >= 7 => {
+                let s = [s[0], s[1], s[2], s[3], s[4], s[5], s[6]];
+                self.curve_to(s[0], 0., s[1], s[2], s[3], 0.);
+                self.curve_to(s[4], 0., s[5], -s[2], s[6], 0.);
+            }
+            // flex
+            35 if s.len() >= 12 => {
+                self.curve_to_args(0)?;
+                self.curve_to_args(6)?;
+            }
+            // hflex1
+            36 if s.len() >= 9 => {
+                let s = [s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7], s[8]];
+                self.curve_to(s[0], s[1], s[2], s[3], s[4], 0.);
+                self.curve_to(s[5], 0., s[6], s[7], s[8], -(s[1] + s[3] + s[7]));
+            }
+            // flex1
+            37 if s.len() >= 11 => {
+                let s: Vec<f32> = s[..11].to_vec();
+                let dx = s[0] + s[2] + s[4] + s[6] + s[8];
+                let dy = s[1] + s[3] + s[5] + s[7] + s[9];
+                // The last point ends either horizontally or vertically level with the start point.
+                let (dx6, dy6) = if dx.abs() > dy.abs() { (s[10], -dy) } else { (-dx, s[10]) };
+                self.curve_to(s[0], s[1], s[2], s[3], s[4], s[5]);
+                self.curve_to(s[6], s[7], s[8], s[9], dx6, dy6);
+            }
+            _ => return Err(Error),
+        }
+        Ok(())
+    }
+
+    fn call_subr(&mut self, subrs: &[&[u8]], depth: usize) -> Result<bool> {
+        let index = self.stack.pop().ok_or(Error)? as i32 + subr_bias(subrs.len());
+        let subr = usize::try_from(index).ok().and_then(|index| subrs.get(index)).ok_or(Error)?;
+        self.execute(subr, depth + 1)
+    }
+
+    /// Replace the operands of a `blend` by their default values, since we don't support variations.
+    fn blend(&mut self) -> Result<()> {
+        let count = self.stack.pop().ok_or(Error)? as usize;
+        let region_count = self.region_counts.get(self.vsindex).copied().unwrap_or(0);
+        let start = self.stack.len().checked_sub(count * (region_count + 1)).ok_or(Error)?;
+        self.stack.truncate(start + count);
+        Ok(())
+    }
+
+    fn parse_width(&mut self, has_width: bool) {
+        if !self.is_cff2 && !self.width_parsed && has_width {
+            self.stack.remove(0);
+        }
+        self.width_parsed = true;
+    }
+
+    fn arg(&self, index: usize) -> Result<f32> {
+        self.stack.get(index).copied().ok_or(Error)
+    }
+
+    fn move_to(&mut self, dx: f32, dy: f32) {
+        self.close_contour();
+        self.current_point = Point::new(self.current_point.x + dx, self.current_point.y + dy);
+        self.contour.push(OutlinePoint { is_on_curve: true, point: self.current_point });
+    }
+
+    fn line_to(&mut self, dx: f32, dy: f32) {
+        self.begin_contour_if_needed();
+        self.current_point = Point::new(self.current_point.x + dx, self.current_point.y + dy);
+        self.contour.push(OutlinePoint { is_on_curve: true, point: self.current_point });
+    }
+
+    fn curve_to_args(&mut self, index: usize) -> Result<()> {
+        let s = self.stack.get(index..index + 6).ok_or(Error)?;
+        let s = [s[0], s[1], s[2], s[3], s[4], s[5]];
+        self.curve_to(s[0], s[1], s[2], s[3], s[4], s[5]);
+        Ok(())
+    }
+
+    /// Append a cubic Bezier curve given by relative coordinates, approximated by quadratic segments.
+    fn curve_to(&mut self, dx1: f32, dy1: f32, dx2: f32, dy2: f32, dx3: f32, dy3: f32) {
+        self.begin_contour_if_needed();
+        let p0 = self.current_point;
+        let p1 = Point::new(p0.x + dx1, p0.y + dy1);
+        let p2 = Point::new(p1.x + dx2, p1.y + dy2);
+        let p3 = Point::new(p2.x + dx3, p2.y + dy3);
+        // Approximating a cubic by a single quadratic has an error of at most `sqrt(3) / 36 * |p3 - 3 * p2 + 3 * p1 - p0|`,
+        // and splitting the cubic into `n` pieces divides that by `n^3`.
+        let error = 3f32.sqrt() / 36. * (p3.x - 3. * p2.x + 3. * p1.x - p0.x).hypot(p3.y - 3. * p2.y + 3. * p1.y - p0.y);
+        let count = ((error / CUBIC_TOLERANCE).cbrt().ceil() as usize).clamp(1, 16);
+        let evaluate = |t: f32| {
+            let u = 1. - t;
+            let (a, b, c, d) = (u * u * u, 3. * u * u * t, 3. * u * t * t, t * t * t);
+            Point::new(a * p0.x + b * p1.x + c * p2.x + d * p3.x, a * p0.y + b * p1.y + c * p2.y + d * p3.y)
+        };
+        let derivative = |t: f32| {
+            let u = 1. - t;
+            let (a, b, c) = (3. * u * u, 6. * u * t, 3. * t * t);
+            (a * (p1.x - p0.x) + b * (p2.x - p1.x) + c * (p3.x - p2.x), a * (p1.y - p0.y) + b * (p2.y - p1.y) + c * (p3.y - p2.y))
+        };
+        let mut start = p0;
+        for index in 0..count {
+            let (t0, t1) = (index as f32 / count as f32, (index + 1) as f32 / count as f32);
+            let end = if index + 1 == count { p3 } else { evaluate(t1) };
+            // The control points of the piece of the cubic between `t0` and `t1`.
+            let (d0, d1) = (derivative(t0), derivative(t1));
+            let scale = (t1 - t0) / 3.;
+            let c1 = Point::new(start.x + d0.0 * scale, start.y + d0.1 * scale);
+            let c2 = Point::new(end.x - d1.0 * scale, end.y - d1.1 * scale);
+            // The quadratic control point that best matches the cubic's midpoint.
+            let control = Point::new((3. * (c1.x + c2.x) - start.x - end.x) / 4., (3. * (c1.y + c2.y) - start.y - end.y) / 4.);
+            self.contour.push(OutlinePoint { is_on_curve: false, point: control });
+            self.contour.push(OutlinePoint { is_on_curve: true, point: end });
+            start = end;
+        }
+        self.current_point = p3;
+    }
+
+    /// Charstrings are allowed to draw without a preceding moveto, in which case the contour starts at the origin.
+    fn begin_contour_if_needed(&mut self) {
+        if self.contour.is_empty() {
+            self.contour.push(OutlinePoint { is_on_curve: true, point: self.current_point });
+        }
+    }
+
+    fn close_contour(&mut self) {
+        // Contours are closed implicitly, so drop the last point if it coincides with the first.
+        if self.contour.len() > 1 && self.contour.last() == self.contour.first() {
+            self.contour.pop();
+        }
+        if self.contour.len() > 1 {
+            let mut contour = self.outline.begin_contour();
+            for point in self.contour.drain(..) {
+                contour.push(point);
+            }
+            contour.end();
+        }
+        self.contour.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal_iter::InternalIterator;
+    use crate::path::PathCommand;
+
+    /// Encode integers as charstring operands, followed by an operator.
+    fn op(operands: &[i32], operator: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for &operand in operands {
+            match operand {
+                -107..=107 => bytes.push((operand + 139) as u8),
+                _ => {
+                    bytes.push(28);
+                    bytes.extend_from_slice(&(operand as i16).to_be_bytes());
+                }
+            }
+        }
+        bytes.extend_from_slice(operator);
+        bytes
+    }
+
+    fn index(items: &[&[u8]]) -> Vec<u8> {
+        let mut bytes = (items.len() as u16).to_be_bytes().to_vec();
+        if !items.is_empty() {
+            bytes.push(2);
+            let mut offset = 1u16;
+            bytes.extend_from_slice(&offset.to_be_bytes());
+            for item in items {
+                offset += item.len() as u16;
+                bytes.extend_from_slice(&offset.to_be_bytes());
+            }
+            for item in items {
+                bytes.extend_from_slice(item);
+            }
+        }
+        bytes
+    }
+
+    /// CFF2 INDEXes are the same as CFF ones, except for a 32-bit count.
+    fn cff2_index(items: &[&[u8]]) -> Vec<u8> {
+        [&[0, 0][..], &index(items)].concat()
+    }
+
+    fn commands(outline: &Outline) -> Vec<PathCommand> {
+        let mut commands = Vec::new();
+        outline.commands().for_each(&mut |command| {
+            commands.push(command);
+            true
+        });
+        commands
+    }
+
+    #[test]
+    fn test_parse_cff() {
+        // A square drawn with a local subroutine, preceded by an advance width and a hint, and a glyph with a curve.
+        let subr = [op(&[100, 0, 0, 100, -100, 0], &[5]), vec![11]].concat();
+        let square = [op(&[500, 0, 10], &[1]), op(&[10, 10], &[21]), op(&[-107], &[10]), op(&[], &[14])].concat();
+        let curve = [op(&[0, 0], &[21]), op(&[0, 100, 100, 0, 100, -100], &[8]), op(&[], &[14])].concat();
+        let char_strings = index(&[&square, &curve]);
+        let private_dict = op(&[2], &[19]);
+        let subrs = index(&[&subr]);
+        // The Top DICT uses 16-bit operands, so that we can compute offsets before knowing its contents.
+        let top_dict_size = 3 + 1 + 3 + 3 + 1;
+        let header = [1, 0, 4, 2];
+        let name_index = index(&[&b"Test"[..]]);
+        let string_index = index(&[]);
+        let global_subrs = index(&[]);
+        let top_dict_index_size = 2 + 1 + 4 + top_dict_size;
+        let char_strings_offset = header.len() + name_index.len() + top_dict_index_size + string_index.len() + global_subrs.len();
+        let private_dict_offset = char_strings_offset + char_strings.len();
+        let int16 = |value: usize| [&[28][..], &(value as i16).to_be_bytes()].concat();
+        let top_dict =
+            [int16(char_strings_offset), vec![17], int16(private_dict.len()), int16(private_dict_offset), vec![18]].concat();
+        assert_eq!(top_dict.len(), top_dict_size);
+        let bytes =
+            [&header[..], &name_index, &index(&[&top_dict]), &string_index, &global_subrs, &char_strings, &private_dict, &subrs]
+                .concat();
+
+        let outlines = parse_cff(&bytes).unwrap();
+        assert_eq!(outlines.len(), 2);
+        assert_eq!(
+            commands(&outlines[0]),
+            vec![
+                PathCommand::MoveTo(Point::new(10., 10.)),
+                PathCommand::LineTo(Point::new(110., 10.)),
+                PathCommand::LineTo(Point::new(110., 110.)),
+                PathCommand::LineTo(Point::new(10., 110.)),
+                PathCommand::LineTo(Point::new(10., 10.)),
+                PathCommand::Close,
+            ]
+        );
+
+        // The cubic is approximated by several quadratics, whose midpoints stay close to it. Its top is at `y = 75`.
+        let points = outlines[1].points();
+        assert!(points.len() > 3);
+        assert_eq!(points.last().unwrap().point, Point::new(200., 0.));
+        let mut top = 0f32;
+        for points in points.windows(3).step_by(2) {
+            top = top.max(0.25 * points[0].point.y + 0.5 * points[1].point.y + 0.25 * points[2].point.y);
+        }
+        assert!((top - 75.).abs() < 0.5, "{}", top);
+    }
+
+    #[test]
+    fn test_parse_cff2() {
+        // CFF2 charstrings have no advance width and no `endchar`. `blend` operands are followed by a delta for each
+        // variation region, which depends on `vsindex`; we only keep the default values.
+        let square = [op(&[10, 10], &[21]), op(&[100, 0, 50, 7, 2], &[16]), op(&[], &[5]), op(&[0, 100, -100, 0], &[5])].concat();
+        let corner =
+            [op(&[1], &[15]), op(&[0, 0], &[21]), op(&[100, 30, 40, 1], &[16]), op(&[], &[6]), op(&[100], &[7])].concat();
+        let char_strings = cff2_index(&[&square, &corner]);
+        let font_dicts = cff2_index(&[&[]]);
+        // An `ItemVariationStore` with one region for `vsindex` 0, and two for `vsindex` 1, preceded by its length.
+        let variation_data = [&[0, 0, 0, 0, 0, 1, 0, 0][..], &[0, 0, 0, 0, 0, 2, 0, 0, 0, 1]].concat();
+        let variation_store_header = [&[0, 1][..], &[0, 0, 0, 0], &[0, 2], &16u32.to_be_bytes(), &24u32.to_be_bytes()].concat();
+        let variation_store = [variation_store_header, variation_data].concat();
+        let variation_store = [&(variation_store.len() as u16).to_be_bytes()[..], &variation_store].concat();
+
+        // The Top DICT uses 16-bit operands, so that we can compute offsets before knowing its contents.
+        let top_dict_size = 3 + 1 + 3 + 2 + 3 + 1;
+        let global_subrs = cff2_index(&[]);
+        let char_strings_offset = 5 + top_dict_size + global_subrs.len();
+        let font_dicts_offset = char_strings_offset + char_strings.len();
+        let variation_store_offset = font_dicts_offset + font_dicts.len();
+        let int16 = |value: usize| [&[28][..], &(value as i16).to_be_bytes()].concat();
+        let top_dict = [
+            int16(char_strings_offset),
+            vec![17],
+            int16(font_dicts_offset),
+            vec![12, 36],
+            int16(variation_store_offset),
+            vec![24],
+        ]
+        .concat();
+        assert_eq!(top_dict.len(), top_dict_size);
+        let header = [&[2, 0, 5][..], &(top_dict_size as u16).to_be_bytes()].concat();
+        let bytes = [header, top_dict, global_subrs, char_strings, font_dicts, variation_store].concat();
+
+        let outlines = parse_cff2(&bytes).unwrap();
+        assert_eq!(outlines.len(), 2);
+        assert_eq!(
+            commands(&outlines[0]),
+            vec![
+                PathCommand::MoveTo(Point::new(10., 10.)),
+                PathCommand::LineTo(Point::new(110., 10.)),
+                PathCommand::LineTo(Point::new(110., 110.)),
+                PathCommand::LineTo(Point::new(10., 110.)),
+                PathCommand::LineTo(Point::new(10., 10.)),
+                PathCommand::Close,
+            ]
+        );
+        assert_eq!(
+            commands(&outlines[1]),
+            vec![
+                PathCommand::MoveTo(Point::new(0., 0.)),
+                PathCommand::LineTo(Point::new(100., 0.)),
+                PathCommand::LineTo(Point::new(100., 100.)),
+                PathCommand::LineTo(Point::new(0., 0.)),
+                PathCommand::Close,
+            ]
+        );
+    }
+}
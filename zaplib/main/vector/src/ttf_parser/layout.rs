@@ -0,0 +1,344 @@
+//! Parsing of the OpenType layout tables; see [`crate::font::layout`].
+//!
+//! Lookup types and subtable formats that we don't support are skipped (resulting in empty lookups), so that
+//! lookup indices keep lining up with the lookup list.
+
+use super::{Error, Reader, Result};
+use crate::font::layout::*;
+
+fn reader_at(bytes: &[u8], offset: usize) -> Result<Reader<'_>> {
+    Ok(Reader::new(bytes.get(offset..).ok_or(Error)?))
+}
+
+fn read_tag(reader: &mut Reader) -> Result<Tag> {
+    Ok(reader.read_u32()?.to_be_bytes())
+}
+
+fn read_u16_array(reader: &mut Reader, count: usize) -> Result<Vec<u16>> {
+    (0..count).map(|_| reader.read_u16()).collect()
+}
+
+/// Read an array of 16-bit offsets, and return them as subslices of `bytes`.
+fn read_offset_array<'a>(reader: &mut Reader, bytes: &'a [u8], count: usize) -> Result<Vec<&'a [u8]>> {
+    (0..count).map(|_| bytes.get(reader.read_u16()? as usize..).ok_or(Error)).collect()
+}
+
+fn parse_coverage(bytes: &[u8]) -> Result<Coverage> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    let count = reader.read_u16()? as usize;
+    let mut ranges = match format {
+        1 => (0..count)
+            .map(|index| {
+                let glyph_id = reader.read_u16()?;
+                Ok((glyph_id, glyph_id, index as u16))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        2 => (0..count).map(|_| Ok((reader.read_u16()?, reader.read_u16()?, reader.read_u16()?))).collect::<Result<Vec<_>>>()?,
+        _ => return Err(Error),
+    };
+    ranges.sort_unstable_by_key(|&(start_glyph_id, _, _)| start_glyph_id);
+    Ok(Coverage { ranges })
+}
+
+fn parse_class_def(bytes: &[u8]) -> Result<ClassDef> {
+    let mut reader = Reader::new(bytes);
+    let mut ranges = match reader.read_u16()? {
+        1 => {
+            let start_glyph_id = reader.read_u16()?;
+            let count = reader.read_u16()?;
+            (0..count)
+                .map(|index| {
+                    let glyph_id = start_glyph_id.checked_add(index).ok_or(Error)?;
+                    Ok((glyph_id, glyph_id, reader.read_u16()?))
+                })
+                .collect::<Result<Vec<_>>>()?
+        }
+        2 => {
+            let count = reader.read_u16()? as usize;
+            (0..count).map(|_| Ok((reader.read_u16()?, reader.read_u16()?, reader.read_u16()?))).collect::<Result<Vec<_>>>()?
+        }
+        _ => return Err(Error),
+    };
+    ranges.sort_unstable_by_key(|&(start_glyph_id, _, _)| start_glyph_id);
+    Ok(ClassDef { ranges })
+}
+
+/// Parse the script list, feature list, and the lookup subtables (as raw bytes along with their lookup type,
+/// with extension subtables already resolved).
+/// Subtables of every lookup, as `(lookup_type, subtable_bytes)`.
+type RawLookups<'a> = Vec<Vec<(u16, &'a [u8])>>;
+
+fn parse_layout_table(bytes: &[u8], extension_lookup_type: u16) -> Result<(LayoutFeatures, RawLookups<'_>)> {
+    let mut reader = Reader::new(bytes);
+    reader.skip(4)?;
+    let script_list_bytes = bytes.get(reader.read_u16()? as usize..).ok_or(Error)?;
+    let feature_list_bytes = bytes.get(reader.read_u16()? as usize..).ok_or(Error)?;
+    let lookup_list_bytes = bytes.get(reader.read_u16()? as usize..).ok_or(Error)?;
+
+    let mut reader = Reader::new(script_list_bytes);
+    let script_count = reader.read_u16()? as usize;
+    let mut scripts = Vec::with_capacity(script_count);
+    for _ in 0..script_count {
+        let script_tag = read_tag(&mut reader)?;
+        let script_offset = reader.read_u16()? as usize;
+        let script_bytes = script_list_bytes.get(script_offset..).ok_or(Error)?;
+        let default_lang_sys_offset = Reader::new(script_bytes).read_u16()? as usize;
+        let mut feature_indices = Vec::new();
+        if default_lang_sys_offset != 0 {
+            let mut reader = reader_at(script_bytes, default_lang_sys_offset)?;
+            reader.skip(2)?;
+            let required_feature_index = reader.read_u16()?;
+            if required_feature_index != 0xFFFF {
+                feature_indices.push(required_feature_index);
+            }
+            let feature_index_count = reader.read_u16()? as usize;
+            feature_indices.extend(read_u16_array(&mut reader, feature_index_count)?);
+        }
+        scripts.push((script_tag, feature_indices));
+    }
+
+    let mut reader = Reader::new(feature_list_bytes);
+    let feature_count = reader.read_u16()? as usize;
+    let mut features = Vec::with_capacity(feature_count);
+    for _ in 0..feature_count {
+        let feature_tag = read_tag(&mut reader)?;
+        let mut feature_reader = reader_at(feature_list_bytes, reader.read_u16()? as usize)?;
+        feature_reader.skip(2)?;
+        let lookup_index_count = feature_reader.read_u16()? as usize;
+        features.push((feature_tag, read_u16_array(&mut feature_reader, lookup_index_count)?));
+    }
+
+    let mut reader = Reader::new(lookup_list_bytes);
+    let lookup_count = reader.read_u16()? as usize;
+    let mut lookups = Vec::with_capacity(lookup_count);
+    for lookup_bytes in read_offset_array(&mut reader, lookup_list_bytes, lookup_count)? {
+        let mut reader = Reader::new(lookup_bytes);
+        let lookup_type = reader.read_u16()?;
+        reader.skip(2)?;
+        let subtable_count = reader.read_u16()? as usize;
+        let mut subtables = Vec::with_capacity(subtable_count);
+        for subtable_bytes in read_offset_array(&mut reader, lookup_bytes, subtable_count)? {
+            if lookup_type == extension_lookup_type {
+                let mut reader = Reader::new(subtable_bytes);
+                if reader.read_u16()? != 1 {
+                    continue;
+                }
+                let lookup_type = reader.read_u16()?;
+                let offset = reader.read_u32()? as usize;
+                subtables.push((lookup_type, subtable_bytes.get(offset..).ok_or(Error)?));
+            } else {
+                subtables.push((lookup_type, subtable_bytes));
+            }
+        }
+        lookups.push(subtables);
+    }
+
+    Ok((LayoutFeatures { scripts, features }, lookups))
+}
+
+pub(crate) fn parse_gsub(bytes: &[u8]) -> Result<GlyphSubstitution> {
+    let (features, lookups) = parse_layout_table(bytes, 7)?;
+    let lookups = lookups
+        .into_iter()
+        .map(|subtables| {
+            Ok(subtables
+                .into_iter()
+                .map(|(lookup_type, bytes)| parse_substitution_subtable(lookup_type, bytes))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .flatten()
+                .collect())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(GlyphSubstitution { features, lookups })
+}
+
+fn read_coverage_array(reader: &mut Reader, bytes: &[u8], count: usize) -> Result<Vec<Coverage>> {
+    read_offset_array(reader, bytes, count)?.into_iter().map(parse_coverage).collect()
+}
+
+/// Read `SequenceLookupRecord`s as `(sequence_index, lookup_index)` pairs.
+fn read_sequence_lookups(reader: &mut Reader, count: usize) -> Result<Vec<(u16, u16)>> {
+    (0..count).map(|_| Ok((reader.read_u16()?, reader.read_u16()?))).collect()
+}
+
+fn parse_substitution_subtable(lookup_type: u16, bytes: &[u8]) -> Result<Option<SubstitutionSubtable>> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    match (lookup_type, format) {
+        (5, 3) => {
+            let glyph_count = reader.read_u16()? as usize;
+            let lookup_count = reader.read_u16()? as usize;
+            let input = read_coverage_array(&mut reader, bytes, glyph_count)?;
+            let lookups = read_sequence_lookups(&mut reader, lookup_count)?;
+            return Ok(Some(SubstitutionSubtable::ChainContext { backtrack: Vec::new(), input, lookahead: Vec::new(), lookups }));
+        }
+        (6, 3) => {
+            let backtrack_count = reader.read_u16()? as usize;
+            let backtrack = read_coverage_array(&mut reader, bytes, backtrack_count)?;
+            let input_count = reader.read_u16()? as usize;
+            let input = read_coverage_array(&mut reader, bytes, input_count)?;
+            let lookahead_count = reader.read_u16()? as usize;
+            let lookahead = read_coverage_array(&mut reader, bytes, lookahead_count)?;
+            let lookup_count = reader.read_u16()? as usize;
+            let lookups = read_sequence_lookups(&mut reader, lookup_count)?;
+            return Ok(Some(SubstitutionSubtable::ChainContext { backtrack, input, lookahead, lookups }));
+        }
+        _ => {}
+    }
+    if !matches!((lookup_type, format), (1, 1) | (1, 2) | (2, 1) | (4, 1)) {
+        return Ok(None);
+    }
+    let coverage = parse_coverage(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+    Ok(Some(match (lookup_type, format) {
+        (1, 1) => SubstitutionSubtable::SingleDelta { coverage, delta: reader.read_i16()? },
+        (1, 2) => {
+            let glyph_count = reader.read_u16()? as usize;
+            SubstitutionSubtable::SingleList { coverage, substitutes: read_u16_array(&mut reader, glyph_count)? }
+        }
+        (2, 1) => {
+            let sequence_count = reader.read_u16()? as usize;
+            let sequences = read_offset_array(&mut reader, bytes, sequence_count)?
+                .into_iter()
+                .map(|sequence_bytes| {
+                    let mut reader = Reader::new(sequence_bytes);
+                    let glyph_count = reader.read_u16()? as usize;
+                    read_u16_array(&mut reader, glyph_count)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            SubstitutionSubtable::Multiple { coverage, sequences }
+        }
+        (4, 1) => {
+            let ligature_set_count = reader.read_u16()? as usize;
+            let ligature_sets = read_offset_array(&mut reader, bytes, ligature_set_count)?
+                .into_iter()
+                .map(|ligature_set_bytes| {
+                    let mut reader = Reader::new(ligature_set_bytes);
+                    let ligature_count = reader.read_u16()? as usize;
+                    read_offset_array(&mut reader, ligature_set_bytes, ligature_count)?
+                        .into_iter()
+                        .map(|ligature_bytes| {
+                            let mut reader = Reader::new(ligature_bytes);
+                            let glyph_id = reader.read_u16()?;
+                            let component_count = reader.read_u16()? as usize;
+                            let components = read_u16_array(&mut reader, component_count.saturating_sub(1))?;
+                            Ok(Ligature { glyph_id, components })
+                        })
+                        .collect::<Result<Vec<_>>>()
+                })
+                .collect::<Result<Vec<_>>>()?;
+            SubstitutionSubtable::Ligature { coverage, ligature_sets }
+        }
+        _ => return Ok(None),
+    }))
+}
+
+pub(crate) fn parse_gpos(bytes: &[u8]) -> Result<GlyphPositioning> {
+    let (features, lookups) = parse_layout_table(bytes, 9)?;
+    let lookups = lookups
+        .into_iter()
+        .map(|subtables| {
+            Ok(subtables
+                .into_iter()
+                .map(|(lookup_type, bytes)| parse_positioning_subtable(lookup_type, bytes))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .flatten()
+                .collect())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(GlyphPositioning { features, lookups })
+}
+
+fn read_value_record(reader: &mut Reader, value_format: u16) -> Result<ValueAdjustment> {
+    let mut adjustment = ValueAdjustment::default();
+    for bit in 0..8 {
+        if value_format & (1 << bit) != 0 {
+            let value = reader.read_i16()?;
+            match bit {
+                0 => adjustment.x_placement = value,
+                2 => adjustment.x_advance = value,
+                _ => {}
+            }
+        }
+    }
+    Ok(adjustment)
+}
+
+fn parse_positioning_subtable(lookup_type: u16, bytes: &[u8]) -> Result<Option<PositioningSubtable>> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    if lookup_type != 2 || !matches!(format, 1 | 2) {
+        return Ok(None);
+    }
+    let coverage = parse_coverage(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+    let value_format1 = reader.read_u16()?;
+    let value_format2 = reader.read_u16()?;
+    Ok(Some(match format {
+        1 => {
+            let pair_set_count = reader.read_u16()? as usize;
+            let pair_sets = read_offset_array(&mut reader, bytes, pair_set_count)?
+                .into_iter()
+                .map(|pair_set_bytes| {
+                    let mut reader = Reader::new(pair_set_bytes);
+                    let pair_value_count = reader.read_u16()? as usize;
+                    let mut pair_set = (0..pair_value_count)
+                        .map(|_| {
+                            Ok((
+                                reader.read_u16()?,
+                                read_value_record(&mut reader, value_format1)?,
+                                read_value_record(&mut reader, value_format2)?,
+                            ))
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    pair_set.sort_unstable_by_key(|&(second_glyph_id, _, _)| second_glyph_id);
+                    Ok(pair_set)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            PositioningSubtable::PairGlyphs { coverage, pair_sets }
+        }
+        2 => {
+            let class_def1 = parse_class_def(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+            let class_def2 = parse_class_def(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+            let class1_count = reader.read_u16()? as usize;
+            let class2_count = reader.read_u16()? as usize;
+            let adjustments = (0..class1_count * class2_count)
+                .map(|_| Ok((read_value_record(&mut reader, value_format1)?, read_value_record(&mut reader, value_format2)?)))
+                .collect::<Result<Vec<_>>>()?;
+            PositioningSubtable::PairClasses { coverage, class_def1, class_def2, class2_count, adjustments }
+        }
+        _ => return Ok(None),
+    }))
+}
+
+/// Parse the horizontal format 0 subtables of the (Microsoft version of the) legacy `kern` table.
+pub(crate) fn parse_kern(bytes: &[u8]) -> Result<KerningPairs> {
+    let mut reader = Reader::new(bytes);
+    if reader.read_u16()? != 0 {
+        return Err(Error);
+    }
+    let subtable_count = reader.read_u16()? as usize;
+    let mut pairs = Vec::new();
+    let mut subtable_bytes = reader.bytes;
+    for _ in 0..subtable_count {
+        let mut reader = Reader::new(subtable_bytes);
+        reader.skip(2)?;
+        let length = reader.read_u16()? as usize;
+        let coverage = reader.read_u16()?;
+        let format = coverage >> 8;
+        // Only horizontal (bit 0), non-minimum (bit 1), non-cross-stream (bit 2) kerning.
+        if format == 0 && coverage & 0b111 == 0b001 {
+            let pair_count = reader.read_u16()? as usize;
+            reader.skip(6)?;
+            for _ in 0..pair_count {
+                let key = (reader.read_u16()? as u32) << 16 | reader.read_u16()? as u32;
+                pairs.push((key, reader.read_i16()?));
+            }
+        }
+        subtable_bytes = subtable_bytes.get(length..).ok_or(Error)?;
+    }
+    pairs.sort_unstable_by_key(|&(key, _)| key);
+    pairs.dedup_by_key(|&mut (key, _)| key);
+    Ok(KerningPairs { pairs })
+}
@@ -1,7 +1,10 @@
-use crate::font::{Glyph, HorizontalMetrics, Outline, OutlinePoint, VectorFont};
+use crate::font::{CharCodeGroup, Glyph, HorizontalMetrics, Outline, OutlinePoint, VectorFont};
 use crate::geometry::{AffineTransformation, LinearTransformation, Point, Rectangle, Transform, Vector};
 use std::{mem, result};
 
+mod cff;
+mod layout;
+
 #[derive(Clone, Debug)]
 pub(crate) struct GlyphsParser<'a> {
     glyphs: Vec<Option<Glyph>>,
@@ -84,17 +87,7 @@ impl<'a> GlyphsParser<'a> {
     }
 
     fn parse_horizontal_metrics(&self, index: usize) -> Result<HorizontalMetrics> {
-        let mut reader = Reader::new(self.hmtx_table_bytes);
-        if index < self.advance_width_count {
-            reader.skip(index * 4)?;
-            Ok(HorizontalMetrics { advance_width: reader.read_u16()? as f32, left_side_bearing: reader.read_i16()? as f32 })
-        } else {
-            reader.skip((self.advance_width_count - 1) * 4)?;
-            let advance_width = reader.read_u16()? as f32;
-            reader.skip(2)?;
-            reader.skip((index - self.advance_width_count) * 2)?;
-            Ok(HorizontalMetrics { advance_width, left_side_bearing: reader.read_i16()? as f32 })
-        }
+        parse_horizontal_metrics(self.hmtx_table_bytes, self.advance_width_count, index)
     }
 
     fn parse_simple_glyph(
@@ -445,44 +438,52 @@ pub type Result<T> = result::Result<T, Error>;
 pub struct Error;
 
 pub fn parse_ttf(bytes: &[u8]) -> Result<VectorFont> {
-    let mut reader = Reader::new(&bytes[0..12]);
+    let mut reader = Reader::new(bytes.get(0..12).ok_or(Error)?);
     let sfnt_version = reader.read_u32()?;
-    if ![0x00010000, u32::from_be_bytes(*b"true")].contains(&sfnt_version) {
+    if ![0x00010000, u32::from_be_bytes(*b"true"), u32::from_be_bytes(*b"OTTO")].contains(&sfnt_version) {
         return Err(Error);
     }
     let table_count = reader.read_u16()? as usize;
     reader.skip(6)?;
+    let mut cff_table_bytes = None;
+    let mut cff2_table_bytes = None;
     let mut cmap_table_bytes = None;
     let mut glyf_table_bytes = None;
+    let mut gpos_table_bytes = None;
+    let mut gsub_table_bytes = None;
     let mut head_table_bytes = None;
     let mut hhea_table_bytes = None;
     let mut hmtx_table_bytes = None;
+    let mut kern_table_bytes = None;
     let mut loca_table_bytes = None;
     let mut maxp_table_bytes = None;
     for index in 0..table_count {
-        let mut reader = Reader::new(&bytes[(12 + index * 16)..][..16]);
+        let mut reader = Reader::new(bytes.get((12 + index * 16)..(28 + index * 16)).ok_or(Error)?);
         let table_tag = reader.read_u32()?;
         reader.skip(4)?;
         let offset = reader.read_u32()? as usize;
         let length = reader.read_u32()? as usize;
-        let table_bytes = &bytes[offset..][..length];
+        let table_bytes = bytes.get(offset..offset + length).ok_or(Error)?;
         match &table_tag.to_be_bytes() {
+            b"CFF " => cff_table_bytes = Some(table_bytes),
+            b"CFF2" => cff2_table_bytes = Some(table_bytes),
             b"cmap" => cmap_table_bytes = Some(table_bytes),
             b"glyf" => glyf_table_bytes = Some(table_bytes),
+            b"GPOS" => gpos_table_bytes = Some(table_bytes),
+            b"GSUB" => gsub_table_bytes = Some(table_bytes),
             b"head" => head_table_bytes = Some(table_bytes),
             b"hhea" => hhea_table_bytes = Some(table_bytes),
             b"hmtx" => hmtx_table_bytes = Some(table_bytes),
+            b"kern" => kern_table_bytes = Some(table_bytes),
             b"loca" => loca_table_bytes = Some(table_bytes),
             b"maxp" => maxp_table_bytes = Some(table_bytes),
             _ => {}
         }
     }
     let cmap_table_bytes = cmap_table_bytes.ok_or(Error)?;
-    let glyf_table_bytes = glyf_table_bytes.ok_or(Error)?;
     let head_table_bytes = head_table_bytes.ok_or(Error)?;
     let hhea_table_bytes = hhea_table_bytes.ok_or(Error)?;
     let hmtx_table_bytes = hmtx_table_bytes.ok_or(Error)?;
-    let loca_table_bytes = loca_table_bytes.ok_or(Error)?;
     let maxp_table_bytes = maxp_table_bytes.ok_or(Error)?;
     let mut reader = Reader::new(hhea_table_bytes);
     reader.skip(4)?;
@@ -506,14 +507,9 @@ pub fn parse_ttf(bytes: &[u8]) -> Result<VectorFont> {
     reader.skip(6)?;
     let index_to_loc_format = IndexToLocFormat::from_i16(reader.read_i16()?).ok_or(Error)?;
     reader.skip(2)?;
-    Ok(VectorFont {
-        units_per_em,
-        ascender,
-        descender,
-        line_gap,
-        bounds,
-        char_code_to_glyph_index_map: parse_char_code_to_glyph_index_map(cmap_table_bytes)?,
-        glyphs: GlyphsParser::new(
+    let (char_code_to_glyph_index_map, supplementary_char_code_groups) = parse_char_code_to_glyph_index_map(cmap_table_bytes)?;
+    let glyphs = match (glyf_table_bytes, loca_table_bytes, cff_table_bytes, cff2_table_bytes) {
+        (Some(glyf_table_bytes), Some(loca_table_bytes), _, _) => GlyphsParser::new(
             glyph_count,
             advance_width_count,
             hmtx_table_bytes,
@@ -522,31 +518,123 @@ pub fn parse_ttf(bytes: &[u8]) -> Result<VectorFont> {
             glyf_table_bytes,
         )
         .parse_glyphs()?,
+        (_, _, Some(cff_table_bytes), _) => {
+            glyphs_from_outlines(cff::parse_cff(cff_table_bytes)?, hmtx_table_bytes, advance_width_count)?
+        }
+        (_, _, _, Some(cff2_table_bytes)) => {
+            glyphs_from_outlines(cff::parse_cff2(cff2_table_bytes)?, hmtx_table_bytes, advance_width_count)?
+        }
+        _ => return Err(Error),
+    };
+    Ok(VectorFont {
+        units_per_em,
+        ascender,
+        descender,
+        line_gap,
+        bounds,
+        char_code_to_glyph_index_map,
+        supplementary_char_code_groups,
+        glyphs,
+        // Layout tables are optional, and fonts are still usable if we can't parse them.
+        glyph_substitution: gsub_table_bytes.and_then(|bytes| layout::parse_gsub(bytes).ok()),
+        glyph_positioning: gpos_table_bytes.and_then(|bytes| layout::parse_gpos(bytes).ok()),
+        kerning_pairs: kern_table_bytes.and_then(|bytes| layout::parse_kern(bytes).ok()),
     })
 }
 
-fn parse_char_code_to_glyph_index_map(bytes: &[u8]) -> Result<Vec<usize>> {
+fn parse_horizontal_metrics(hmtx_table_bytes: &[u8], advance_width_count: usize, index: usize) -> Result<HorizontalMetrics> {
+    let mut reader = Reader::new(hmtx_table_bytes);
+    if index < advance_width_count {
+        reader.skip(index * 4)?;
+        Ok(HorizontalMetrics { advance_width: reader.read_u16()? as f32, left_side_bearing: reader.read_i16()? as f32 })
+    } else {
+        reader.skip(advance_width_count.checked_sub(1).ok_or(Error)? * 4)?;
+        let advance_width = reader.read_u16()? as f32;
+        reader.skip(2)?;
+        reader.skip((index - advance_width_count) * 2)?;
+        Ok(HorizontalMetrics { advance_width, left_side_bearing: reader.read_i16()? as f32 })
+    }
+}
+
+/// Build glyphs from `CFF ` or `CFF2` outlines, which don't come with bounds, unlike `glyf` outlines.
+fn glyphs_from_outlines(outlines: Vec<Outline>, hmtx_table_bytes: &[u8], advance_width_count: usize) -> Result<Vec<Glyph>> {
+    let mut glyphs = Vec::with_capacity(outlines.len());
+    for (index, outline) in outlines.into_iter().enumerate() {
+        let mut points = outline.points().iter().map(|point| point.point);
+        let bounds = match points.next() {
+            Some(first) => points.fold(Rectangle::new(first, first), |bounds, point| {
+                Rectangle::new(
+                    Point::new(bounds.p_min.x.min(point.x), bounds.p_min.y.min(point.y)),
+                    Point::new(bounds.p_max.x.max(point.x), bounds.p_max.y.max(point.y)),
+                )
+            }),
+            None => Rectangle::default(),
+        };
+        let horizontal_metrics = parse_horizontal_metrics(hmtx_table_bytes, advance_width_count, index)?;
+        glyphs.push(Glyph { horizontal_metrics, bounds, outline });
+    }
+    Ok(glyphs)
+}
+
+type CharCodeMaps = (Vec<usize>, Vec<CharCodeGroup>);
+
+fn parse_char_code_to_glyph_index_map(bytes: &[u8]) -> Result<CharCodeMaps> {
     let mut reader = Reader::new(bytes);
     reader.skip(2)?;
-    let mut subtable_bytes = None;
+    let mut format_4_subtable_bytes = None;
+    let mut format_12_subtable_bytes = None;
     let subtable_count = reader.read_u16()? as usize;
     for _ in 0..subtable_count {
         let platform_id = reader.read_u16()?;
         let encoding_id = reader.read_u16()?;
         let offset = reader.read_u32()? as usize;
         if let (0, _) | (3, 1) | (3, 10) = (platform_id, encoding_id) {
-            subtable_bytes = Some(&bytes[offset..]);
-            break;
+            let subtable_bytes = bytes.get(offset..).ok_or(Error)?;
+            match Reader::new(subtable_bytes).read_u16()? {
+                4 if format_4_subtable_bytes.is_none() => format_4_subtable_bytes = Some(subtable_bytes),
+                12 if format_12_subtable_bytes.is_none() => format_12_subtable_bytes = Some(subtable_bytes),
+                _ => {}
+            }
         }
     }
-    let subtable_bytes = subtable_bytes.ok_or(Error)?;
-    let mut reader = Reader::new(subtable_bytes);
-    let format = reader.read_u16()?;
-    let bytes = &subtable_bytes[2..];
-    match format {
-        4 => parse_char_code_to_glyph_index_map_format_4(bytes),
-        _ => Err(Error),
+    // Format 12 is a superset of format 4 that also covers char codes outside of the
+    // Basic Multilingual Plane (e.g. emoji), so prefer it when available.
+    if let Some(subtable_bytes) = format_12_subtable_bytes {
+        parse_char_code_to_glyph_index_map_format_12(&subtable_bytes[2..])
+    } else {
+        let subtable_bytes = format_4_subtable_bytes.ok_or(Error)?;
+        Ok((parse_char_code_to_glyph_index_map_format_4(&subtable_bytes[2..])?, Vec::new()))
+    }
+}
+
+fn parse_char_code_to_glyph_index_map_format_12(bytes: &[u8]) -> Result<CharCodeMaps> {
+    let mut reader = Reader::new(bytes);
+    reader.skip(10)?;
+    let group_count = reader.read_u32()? as usize;
+    let mut char_code_to_glyph_index_map = Vec::new();
+    let mut supplementary_char_code_groups = Vec::new();
+    for _ in 0..group_count {
+        let start_char_code = reader.read_u32()?;
+        let end_char_code = reader.read_u32()?;
+        let start_glyph_index = reader.read_u32()? as usize;
+        if start_char_code > end_char_code || end_char_code > 0x10FFFF {
+            return Err(Error);
+        }
+        for char_code in start_char_code..=end_char_code.min(0xFFFF) {
+            char_code_to_glyph_index_map.resize(char_code_to_glyph_index_map.len().max(char_code as usize + 1), 0);
+            char_code_to_glyph_index_map[char_code as usize] = start_glyph_index + (char_code - start_char_code) as usize;
+        }
+        if end_char_code >= 0x10000 {
+            let group_start_char_code = start_char_code.max(0x10000);
+            supplementary_char_code_groups.push(CharCodeGroup {
+                start_char_code: group_start_char_code,
+                end_char_code,
+                start_glyph_index: start_glyph_index + (group_start_char_code - start_char_code) as usize,
+            });
+        }
     }
+    supplementary_char_code_groups.sort_by_key(|group| group.start_char_code);
+    Ok((char_code_to_glyph_index_map, supplementary_char_code_groups))
 }
 
 fn parse_char_code_to_glyph_index_map_format_4(bytes: &[u8]) -> Result<Vec<usize>> {
@@ -572,7 +660,7 @@ fn parse_char_code_to_glyph_index_map_format_4(bytes: &[u8]) -> Result<Vec<usize
         let start_code = start_code_reader.read_u16()?;
         let id_delta = id_delta_reader.read_u16()? as usize;
         let id_range_offset = id_range_offset_reader.read_u16()? as usize;
-        for code in start_code..end_code {
+        for code in start_code..=end_code {
             let mut id = if id_range_offset == 0 {
                 code
             } else {
@@ -609,6 +697,9 @@ mod tests {
             for char_code in 0..font.char_code_to_glyph_index_map.len() {
                 assert!(font.char_code_to_glyph_index_map[char_code] <= font.glyphs.len());
             }
+            assert!(font.glyph_index('A').is_some());
+            assert!(font.glyph_index('~').is_some());
+            assert_eq!(font.glyph_index('\u{1F600}'), None);
         }
     }
 }
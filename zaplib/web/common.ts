@@ -531,6 +531,55 @@ export const getWasmEnv = ({
         return 0;
       }
     },
+    readUrlRangeSync: (
+      urlPtr,
+      urlLen,
+      start,
+      end,
+      bufPtrOut,
+      bufLenOut,
+      sizeOut
+    ) => {
+      if (!inWorker) {
+        // Main browser thread doesn't support synchronous+arraybuffer XMLHttpRequest.
+        throw new Error("Not yet implemented");
+      }
+
+      const url = parseString(urlPtr, urlLen);
+      const request = new XMLHttpRequest();
+      request.responseType = "arraybuffer";
+      request.open("GET", new URL(url, baseUri).href, false /* synchronous */);
+      request.setRequestHeader("Range", `bytes=${start}-${Number(end) - 1}`);
+      request.send(null);
+
+      // E.g. "bytes 0-1023/146515", or "bytes */0" for an empty file, which can't satisfy any range. Note that
+      // for cross-origin requests this header is only readable if the server lists it in
+      // `Access-Control-Expose-Headers`; otherwise we silently fall back to downloading the entire file.
+      const contentRange = request.getResponseHeader("Content-Range") || "";
+      const isEmptyFile =
+        request.status === 416 && contentRange === "bytes */0";
+      if (request.status !== 200 && request.status !== 206 && !isEmptyFile) {
+        return 0;
+      }
+      const data = isEmptyFile
+        ? new Uint8Array(0)
+        : new Uint8Array(request.response);
+      const exports = getExports();
+      const outputBufPtr = createWasmBuffer(memory, exports, data);
+      new Uint32Array(memory.buffer, bufPtrOut, 1)[0] = outputBufPtr;
+      new Uint32Array(memory.buffer, bufLenOut, 1)[0] = data.byteLength;
+      if (request.status === 200) {
+        return 1;
+      }
+
+      // The size can also be "*" if the server doesn't know it.
+      const sizeMatch = /\/(\d+)$/.exec(contentRange);
+      if (!sizeMatch) {
+        return 3;
+      }
+      new BigUint64Array(memory.buffer, sizeOut, 1)[0] = BigInt(sizeMatch[1]);
+      return 2;
+    },
     randomU64: () =>
       new BigUint64Array(
         globalThis.crypto.getRandomValues(new Uint32Array(2)).buffer
@@ -76,6 +76,7 @@ export class WasmApp {
   private timers: Timer[];
   private hasRequestedAnimationFrame: boolean;
   private websockets: Record<string, WebSocketWithSendStack | null>;
+  private fetchAbortControllers: Record<number, AbortController>;
   private fileHandles: FileHandle[];
   private zerdeEventloopEvents: ZerdeEventloopEvents;
   private appPtr: BigInt;
@@ -124,6 +125,7 @@ export class WasmApp {
     this.timers = [];
     this.hasRequestedAnimationFrame = false;
     this.websockets = {};
+    this.fetchAbortControllers = {};
     this.fileHandles = fileHandles;
 
     this.callRustAsyncNewCallbackId = 0;
@@ -658,6 +660,88 @@ export class WasmApp {
     }
   }
 
+  private fetch(
+    requestId: number,
+    method: string,
+    url: string,
+    headers: [string, string][],
+    body: Uint8Array,
+    timeout: number,
+    stream: boolean
+  ): void {
+    const abortController = new AbortController();
+    this.fetchAbortControllers[requestId] = abortController;
+    let timedOut = false;
+    const timeoutId =
+      timeout >= 0
+        ? setTimeout(() => {
+            timedOut = true;
+            abortController.abort();
+          }, timeout * 1000)
+        : undefined;
+    const finish = () => {
+      clearTimeout(timeoutId);
+      delete this.fetchAbortControllers[requestId];
+      this.doWasmIo();
+    };
+
+    fetch(url, {
+      method,
+      headers,
+      // GET and HEAD requests are not allowed to have a body at all.
+      body: body.byteLength > 0 ? body : undefined,
+      signal: abortController.signal,
+    })
+      .then(async (response) => {
+        const responseHeaders: [string, string][] = [];
+        response.headers.forEach((value, name) => {
+          responseHeaders.push([name, value]);
+        });
+        if (stream && response.body) {
+          this.zerdeEventloopEvents.httpResponseHead(
+            requestId,
+            response.status,
+            responseHeaders
+          );
+          this.doWasmIo();
+          const reader = response.body.getReader();
+          for (;;) {
+            const { done, value } = await reader.read();
+            if (done || !value) {
+              break;
+            }
+            this.zerdeEventloopEvents.httpResponseChunk(requestId, value);
+            this.doWasmIo();
+          }
+          this.zerdeEventloopEvents.httpResponseEnd(requestId);
+        } else {
+          const responseBody = await response.arrayBuffer();
+          this.zerdeEventloopEvents.httpResponse(
+            requestId,
+            response.status,
+            responseHeaders,
+            responseBody
+          );
+        }
+        finish();
+      })
+      .catch((error) => {
+        // Cancelled requests don't get any more events.
+        if (!abortController.signal.aborted || timedOut) {
+          this.zerdeEventloopEvents.httpResponseError(
+            requestId,
+            timedOut,
+            "" + error
+          );
+        }
+        finish();
+      });
+  }
+
+  private cancelFetch(requestId: number): void {
+    this.fetchAbortControllers[requestId]?.abort();
+  }
+
   private enableGlobalFileDropTarget(): void {
     rpc.send(WorkerEvent.EnableGlobalFileDropTarget);
     rpc.receive(WorkerEvent.DragEnter, () => {
@@ -951,6 +1035,28 @@ export class WasmApp {
         rpc.send(WorkerEvent.CallJs, { fnName, params });
       }
     },
+    // fetch
+    function fetch19(zelf) {
+      const requestId = zelf.zerdeParser.parseF64();
+      const method = zelf.zerdeParser.parseString();
+      const url = zelf.zerdeParser.parseString();
+      const numberOfHeaders = zelf.zerdeParser.parseU32();
+      const headers: [string, string][] = [];
+      for (let headerIndex = 0; headerIndex < numberOfHeaders; headerIndex++) {
+        headers.push([
+          zelf.zerdeParser.parseString(),
+          zelf.zerdeParser.parseString(),
+        ]);
+      }
+      const body = zelf.zerdeParser.parseU8Slice();
+      const timeout = zelf.zerdeParser.parseF64();
+      const stream = zelf.zerdeParser.parseU32() === 1;
+      zelf.fetch(requestId, method, url, headers, body, timeout, stream);
+    },
+    // cancel_fetch
+    function cancelFetch20(zelf) {
+      zelf.cancelFetch(zelf.zerdeParser.parseF64());
+    },
   ];
 }
 
@@ -65,6 +65,15 @@ export type WasmEnv = {
     bufPtrOut: number,
     bufLenOut: number
   ) => 1 | 0;
+  readUrlRangeSync: (
+    urlPtr: number,
+    urlLen: number,
+    start: BigInt,
+    end: BigInt,
+    bufPtrOut: number,
+    bufLenOut: number,
+    sizeOut: number
+  ) => 0 | 1 | 2 | 3;
   randomU64: () => BigInt;
   sendTaskWorkerMessage: (twMessagePtr: string) => void;
 };
@@ -38,6 +38,11 @@ const MSG_TYPE_DRAG_ENTER = 27;
 const MSG_TYPE_DRAG_LEAVE = 28;
 const MSG_TYPE_DRAG_OVER = 29;
 const MSG_TYPE_CALL_RUST = 30;
+const MSG_TYPE_HTTP_RESPONSE = 31;
+const MSG_TYPE_HTTP_RESPONSE_HEAD = 32;
+const MSG_TYPE_HTTP_RESPONSE_CHUNK = 33;
+const MSG_TYPE_HTTP_RESPONSE_END = 34;
+const MSG_TYPE_HTTP_RESPONSE_ERROR = 35;
 
 // A set of events. Each event starts with a u32 representing the event type, with 0 indicating the end. And
 // it is prefixed by a timestamp.
@@ -287,6 +292,62 @@ export class ZerdeEventloopEvents {
     this._zerdeBuilder.sendString(error);
   }
 
+  httpResponse(
+    requestId: number,
+    status: number,
+    headers: [string, string][],
+    body: ArrayBuffer
+  ): void {
+    const vecLen = body.byteLength;
+    const vecPtr = this.createWasmBuffer(new Uint8Array(body));
+    this._zerdeBuilder.sendU32(MSG_TYPE_HTTP_RESPONSE);
+    this._zerdeBuilder.sendF64(requestId);
+    this._zerdeBuilder.sendU32(status);
+    this.sendHttpHeaders(headers);
+    this._zerdeBuilder.sendU32(vecPtr);
+    this._zerdeBuilder.sendU32(vecLen);
+  }
+
+  httpResponseHead(
+    requestId: number,
+    status: number,
+    headers: [string, string][]
+  ): void {
+    this._zerdeBuilder.sendU32(MSG_TYPE_HTTP_RESPONSE_HEAD);
+    this._zerdeBuilder.sendF64(requestId);
+    this._zerdeBuilder.sendU32(status);
+    this.sendHttpHeaders(headers);
+  }
+
+  httpResponseChunk(requestId: number, chunk: Uint8Array): void {
+    const vecLen = chunk.byteLength;
+    const vecPtr = this.createWasmBuffer(chunk);
+    this._zerdeBuilder.sendU32(MSG_TYPE_HTTP_RESPONSE_CHUNK);
+    this._zerdeBuilder.sendF64(requestId);
+    this._zerdeBuilder.sendU32(vecPtr);
+    this._zerdeBuilder.sendU32(vecLen);
+  }
+
+  httpResponseEnd(requestId: number): void {
+    this._zerdeBuilder.sendU32(MSG_TYPE_HTTP_RESPONSE_END);
+    this._zerdeBuilder.sendF64(requestId);
+  }
+
+  httpResponseError(requestId: number, timedOut: boolean, error: string): void {
+    this._zerdeBuilder.sendU32(MSG_TYPE_HTTP_RESPONSE_ERROR);
+    this._zerdeBuilder.sendF64(requestId);
+    this._zerdeBuilder.sendU32(timedOut ? 1 : 0);
+    this._zerdeBuilder.sendString(error);
+  }
+
+  private sendHttpHeaders(headers: [string, string][]): void {
+    this._zerdeBuilder.sendU32(headers.length);
+    for (const [name, value] of headers) {
+      this._zerdeBuilder.sendString(name);
+      this._zerdeBuilder.sendString(value);
+    }
+  }
+
   appOpenFiles(fileHandles: FileHandle[]): void {
     this._zerdeBuilder.sendU32(MSG_TYPE_APP_OPEN_FILES);
     this._zerdeBuilder.sendU32(fileHandles.length);